@@ -16,3 +16,8 @@ end
 local x = 10
 local y = 20
 print("Math Check: " .. x .. " + " .. y .. " = " .. (x + y))
+
+-- Example: Persistent storage (survives restarts, namespaced per package)
+local launches = (lumina.storage.get("launch_count") or 0) + 1
+lumina.storage.set("launch_count", launches)
+print("Lumina has been started " .. launches .. " times")
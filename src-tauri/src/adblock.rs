@@ -0,0 +1,884 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Mutex, Arc, OnceLock, RwLock};
+use tauri::{AppHandle, Manager};
+use serde::Serialize;
+use ::adblock::engine::Engine;
+use arc_swap::ArcSwap;
+use base64::Engine as _;
+
+use crate::history_manager::HistoryManager;
+use crate::events::AppEvent;
+use crate::data::AppDataStore;
+use crate::{events, history_manager};
+use crate::refresh_tray_menu;
+use crate::error::LuminaError;
+use crate::layout::is_browser_window;
+
+/// Read-only once built; a list refresh swaps in a new engine so request checks never wait on a lock
+pub(crate) static ADBLOCK_ENGINE: OnceLock<ArcSwap<Engine>> = OnceLock::new();
+/// Requests blocked per tab label. Counted on the request thread and reported in batches by
+/// `start_stats_reporter`, so a busy page doesn't emit an event per blocked request.
+static BLOCKED_COUNTS: OnceLock<RwLock<HashMap<String, Arc<BlockedCounter>>>> = OnceLock::new();
+static BLOCKED_COUNTS_DIRTY: AtomicBool = AtomicBool::new(false);
+const STATS_REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+pub(crate) static ADBLOCK_ENABLED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
+/// Answer blocked images, scripts, styles and frames with harmless stand-ins instead of a 403
+static ADBLOCK_PLACEHOLDERS: AtomicBool = AtomicBool::new(true);
+static ADBLOCK_SITE_EXCEPTIONS: OnceLock<Mutex<std::collections::HashSet<String>>> = OnceLock::new();
+
+static HOST_BLOCKLIST: OnceLock<Mutex<std::collections::HashSet<String>>> = OnceLock::new();
+static ADBLOCK_LISTS: OnceLock<Mutex<Vec<AdblockListInfo>>> = OnceLock::new();
+// Blocked requests per tab label for the current page; cleared on top-level navigation
+static BLOCKED_REQUEST_LOG: OnceLock<Mutex<HashMap<String, Vec<BlockedRequestEntry>>>> = OnceLock::new();
+const BLOCKED_REQUEST_LOG_LIMIT: usize = 1000;
+
+fn adblock_site_exceptions() -> &'static Mutex<std::collections::HashSet<String>> {
+    ADBLOCK_SITE_EXCEPTIONS.get_or_init(|| Mutex::new(std::collections::HashSet::new()))
+}
+
+pub(crate) fn host_blocklist() -> &'static Mutex<std::collections::HashSet<String>> {
+    HOST_BLOCKLIST.get_or_init(|| Mutex::new(std::collections::HashSet::new()))
+}
+
+pub(crate) fn adblock_lists() -> &'static Mutex<Vec<AdblockListInfo>> {
+    ADBLOCK_LISTS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Makes `engine` the one requests are checked against, replacing any earlier one.
+pub(crate) fn install_adblock_engine(engine: Engine) {
+    let engine = Arc::new(engine);
+    ADBLOCK_ENGINE.get_or_init(|| ArcSwap::new(engine.clone())).store(engine);
+}
+
+pub(crate) fn blocked_request_log() -> &'static Mutex<HashMap<String, Vec<BlockedRequestEntry>>> {
+    BLOCKED_REQUEST_LOG.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[derive(Default)]
+struct BlockedCounter {
+    count: AtomicU32,
+    /// Last count sent to the frontend
+    reported: AtomicU32,
+}
+
+fn blocked_counts() -> &'static RwLock<HashMap<String, Arc<BlockedCounter>>> {
+    BLOCKED_COUNTS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Only a tab's first blocked request takes the write lock.
+fn count_blocked(label: &str) {
+    let counter = blocked_counts().read().unwrap().get(label).cloned();
+    let counter = counter.unwrap_or_else(|| blocked_counts().write().unwrap().entry(label.to_string()).or_default().clone());
+    counter.count.fetch_add(1, Ordering::Relaxed);
+    BLOCKED_COUNTS_DIRTY.store(true, Ordering::Release);
+}
+
+pub(crate) fn forget_blocked_count(label: &str) {
+    blocked_counts().write().unwrap().remove(label);
+}
+
+/// Sends the counts that changed since the last report as one event, every STATS_REPORT_INTERVAL.
+pub(crate) fn start_stats_reporter(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(STATS_REPORT_INTERVAL).await;
+            if !BLOCKED_COUNTS_DIRTY.swap(false, Ordering::Acquire) {
+                continue;
+            }
+            let changed: Vec<AdblockStatsPayload> = blocked_counts().read().unwrap().iter()
+                .filter_map(|(label, counter)| {
+                    let count = counter.count.load(Ordering::Relaxed);
+                    (counter.reported.swap(count, Ordering::Relaxed) != count).then(|| AdblockStatsPayload {
+                        label: label.clone(),
+                        blocked_count: count,
+                    })
+                })
+                .collect();
+            if !changed.is_empty() {
+                events::emit(&app, AppEvent::AdblockStatsUpdate(changed));
+            }
+        }
+    });
+}
+
+#[derive(Clone, serde::Serialize)]
+pub(crate) struct AdblockStatsPayload {
+    label: String,
+    blocked_count: u32,
+}
+
+enum AdblockVerdict {
+    Allow,
+    Block,
+    /// Answer with a neutered resource instead; holds the engine's `data:` URL
+    Redirect(String),
+}
+
+/// Runs a webview resource request through the adblocker and rewrites the response when it is blocked.
+pub(crate) fn handle_adblock_request(
+    request: &tauri::http::Request<Vec<u8>>,
+    response: &mut tauri::http::Response<std::borrow::Cow<'static, [u8]>>,
+    label: &str,
+) {
+    let referer = request.headers().get("referer").and_then(|h| h.to_str().ok());
+    let request_type = infer_request_type(request);
+    if request_type == "document" {
+        blocked_request_log().lock().unwrap().remove(label);
+    }
+    match check_adblock_url(&request.uri().to_string(), referer, request_type, label) {
+        AdblockVerdict::Allow => {}
+        AdblockVerdict::Block => {
+            *response = match placeholder(request_type).filter(|_| ADBLOCK_PLACEHOLDERS.load(Ordering::Relaxed)) {
+                Some((mime, body)) => tauri::http::Response::builder()
+                    .status(200)
+                    .header("Content-Type", mime)
+                    .header("Access-Control-Allow-Origin", "*")
+                    .body(std::borrow::Cow::Owned(body))
+                    .unwrap(),
+                None => tauri::http::Response::builder()
+                    .status(403)
+                    .body(std::borrow::Cow::Owned(Vec::new()))
+                    .unwrap(),
+            };
+        }
+        AdblockVerdict::Redirect(data_url) => {
+            let (mime, body) = decode_data_url(&data_url).unwrap_or_default();
+            *response = tauri::http::Response::builder()
+                .status(200)
+                .header("Content-Type", mime)
+                .header("Access-Control-Allow-Origin", "*")
+                .body(std::borrow::Cow::Owned(body))
+                .unwrap();
+        }
+    }
+}
+
+/// Stand-in for a blocked resource, so pages don't break around it. Requests that can fail
+/// gracefully (XHR, fonts, media) are left to fail.
+fn placeholder(request_type: &str) -> Option<(&'static str, Vec<u8>)> {
+    const TRANSPARENT_GIF: &str = "R0lGODlhAQABAIAAAAAAAP///yH5BAEAAAAALAAAAAABAAEAAAIBRAA7";
+    match request_type {
+        "image" => Some(("image/gif", base64::engine::general_purpose::STANDARD.decode(TRANSPARENT_GIF).unwrap_or_default())),
+        "script" => Some(("application/javascript", b"(function() {})();".to_vec())),
+        "stylesheet" => Some(("text/css", Vec::new())),
+        "subdocument" | "document" => Some(("text/html", b"<!DOCTYPE html><html><head></head><body></body></html>".to_vec())),
+        _ => None,
+    }
+}
+
+/// Whether the shield stops a top-level navigation to `url`; the tab shows the blocked page instead.
+pub(crate) fn blocks_navigation(url: &url::Url, label: &str) -> bool {
+    matches!(url.scheme(), "http" | "https")
+        && !matches!(check_adblock_url(url.as_str(), None, "document", label), AdblockVerdict::Allow)
+}
+
+pub(crate) fn blocked_page_url(url: &url::Url) -> String {
+    format!("lumina-app://localhost/blocked?url={}", urlencoding::encode(url.as_str()))
+}
+
+/// Splits a base64 `data:` URL into its MIME type and decoded bytes.
+fn decode_data_url(data_url: &str) -> Option<(String, Vec<u8>)> {
+    let (meta, data) = data_url.strip_prefix("data:")?.split_once(',')?;
+    let mime = meta.split(';').next().unwrap_or("text/plain").to_string();
+    let body = if meta.ends_with(";base64") {
+        base64::engine::general_purpose::STANDARD.decode(data).ok()?
+    } else {
+        data.as_bytes().to_vec()
+    };
+    Some((mime, body))
+}
+
+/// Tiny stand-ins for `$redirect=` filters, named after their uBlock Origin counterparts.
+pub(crate) fn neutered_resources() -> Vec<::adblock::resources::Resource> {
+    use ::adblock::resources::{MimeType, Resource, ResourceType};
+
+    let resource = |name: &str, aliases: &[&str], kind: MimeType, content: &[u8]| Resource {
+        name: name.to_string(),
+        aliases: aliases.iter().map(|a| a.to_string()).collect(),
+        kind: ResourceType::Mime(kind),
+        content: base64::engine::general_purpose::STANDARD.encode(content),
+        dependencies: Vec::new(),
+        permission: Default::default(),
+    };
+
+    const TRANSPARENT_GIF: &str = "R0lGODlhAQABAIAAAAAAAP///yH5BAEAAAAALAAAAAABAAEAAAIBRAA7";
+    let gif = base64::engine::general_purpose::STANDARD.decode(TRANSPARENT_GIF).unwrap_or_default();
+
+    vec![
+        resource("noop.js", &["noopjs"], MimeType::ApplicationJavascript, b"(function() {})();"),
+        resource("noop.css", &["noopcss"], MimeType::TextCss, b""),
+        resource("noop.txt", &["nooptext"], MimeType::TextPlain, b""),
+        resource("noop.json", &["noopjson"], MimeType::ApplicationJson, b"{}"),
+        resource("noop.html", &["noopframe"], MimeType::TextHtml, b"<!DOCTYPE html><html><head></head><body></body></html>"),
+        resource("1x1.gif", &["1x1-transparent.gif"], MimeType::ImageGif, &gif),
+        resource(
+            "google-analytics_analytics.js",
+            &["google-analytics.com/analytics.js", "googletagmanager_gtm.js", "googletagmanager.com/gtm.js"],
+            MimeType::ApplicationJavascript,
+            br#"(function() {
+                const noop = function() {};
+                const ga = function() {
+                    const last = arguments[arguments.length - 1];
+                    if (last && typeof last.hitCallback === 'function') { try { last.hitCallback(); } catch (e) {} }
+                };
+                ga.create = function() { return { get: noop, set: noop, send: noop }; };
+                ga.getAll = function() { return []; };
+                ga.getByName = function() { return null; };
+                ga.loaded = true;
+                window[window.GoogleAnalyticsObject || 'ga'] = ga;
+                const dl = window.dataLayer;
+                if (dl && typeof dl.push === 'function') {
+                    dl.push = function(o) { if (o && typeof o.eventCallback === 'function') { setTimeout(o.eventCallback, 1); } };
+                }
+            })();"#,
+        ),
+        resource(
+            "googlesyndication_adsbygoogle.js",
+            &["googlesyndication.com/adsbygoogle.js", "googlesyndication-adsbygoogle"],
+            MimeType::ApplicationJavascript,
+            br#"(function() {
+                window.adsbygoogle = { loaded: true, push: function() {} };
+            })();"#,
+        ),
+        resource(
+            "googletagservices_gpt.js",
+            &["googletagservices.com/gpt.js", "googletagservices-gpt"],
+            MimeType::ApplicationJavascript,
+            br#"(function() {
+                const noop = function() {};
+                const noopThis = function() { return this; };
+                const slot = { addService: noopThis, defineSizeMapping: noopThis, setTargeting: noopThis, getSlotElementId: function() { return ''; } };
+                const pubads = { addEventListener: noopThis, enableSingleRequest: noopThis, setTargeting: noopThis, collapseEmptyDivs: noopThis, disableInitialLoad: noop, refresh: noop, display: noop };
+                const cmd = (window.googletag && window.googletag.cmd) || [];
+                window.googletag = {
+                    apiReady: true,
+                    pubadsReady: true,
+                    cmd: { push: function(fn) { try { fn(); } catch (e) {} return 1; } },
+                    defineSlot: function() { return slot; },
+                    defineOutOfPageSlot: function() { return slot; },
+                    enableServices: noop,
+                    display: noop,
+                    destroySlots: noop,
+                    pubads: function() { return pubads; },
+                    sizeMapping: function() { return { addSize: noopThis, build: function() { return []; } }; },
+                };
+                cmd.forEach(function(fn) { try { fn(); } catch (e) {} });
+            })();"#,
+        ),
+    ]
+}
+
+/// Derives the adblock resource type ("script", "image", "xmlhttprequest", ...) for a webview request.
+/// Prefers Sec-Fetch-Dest, then the Accept header, then the URL's file extension.
+fn infer_request_type<T>(request: &tauri::http::Request<T>) -> &'static str {
+    let header = |name: &str| request.headers().get(name).and_then(|h| h.to_str().ok()).unwrap_or("");
+
+    match header("sec-fetch-dest") {
+        "document" => return "document",
+        "iframe" | "frame" => return "subdocument",
+        "script" | "worker" | "sharedworker" | "serviceworker" => return "script",
+        "style" => return "stylesheet",
+        "image" => return "image",
+        "font" => return "font",
+        "audio" | "video" | "track" => return "media",
+        "object" | "embed" => return "object",
+        "websocket" => return "websocket",
+        "report" => return "ping",
+        "empty" => return "xmlhttprequest",
+        _ => {}
+    }
+
+    if header("x-requested-with").eq_ignore_ascii_case("xmlhttprequest") {
+        return "xmlhttprequest";
+    }
+
+    let accept = header("accept");
+    if accept.starts_with("text/html") {
+        return "document";
+    } else if accept.starts_with("text/css") {
+        return "stylesheet";
+    } else if accept.starts_with("image/") {
+        return "image";
+    } else if accept.starts_with("application/json") {
+        return "xmlhttprequest";
+    }
+
+    let path = request.uri().path().to_lowercase();
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+    match extension {
+        "js" | "mjs" => "script",
+        "css" => "stylesheet",
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "ico" | "avif" | "bmp" => "image",
+        "woff" | "woff2" | "ttf" | "otf" | "eot" => "font",
+        "mp4" | "webm" | "mp3" | "ogg" | "wav" | "m3u8" | "ts" => "media",
+        "html" | "htm" => "document",
+        "json" => "xmlhttprequest",
+        _ => "other",
+    }
+}
+
+fn check_adblock_url(url: &str, referer: Option<&str>, request_type: &str, label: &str) -> AdblockVerdict {
+    // 0. Always Allow Internal Protocols
+    if url.starts_with("lumina:") || url.starts_with("lumina-app:") {
+        return AdblockVerdict::Allow;
+    }
+
+    // 0. Shield turned off globally or for the page's site
+    if !ADBLOCK_ENABLED.load(std::sync::atomic::Ordering::Relaxed) {
+        return AdblockVerdict::Allow;
+    }
+    let site_host = referer
+        .and_then(|r| url::Url::parse(r).ok())
+        .or_else(|| url::Url::parse(url).ok())
+        .and_then(|u| u.host_str().map(normalize_site_domain));
+    if let Some(host) = site_host {
+        if is_adblock_disabled_for_host(&host) {
+            return AdblockVerdict::Allow;
+        }
+    }
+
+    // 0. Force Block List (Overrides Friendly Policy) - Kills AdMatic & Google Ads on Friendly Sites
+    if url.contains("admatic.com.tr") || 
+       url.contains("doubleclick.net") || 
+       url.contains("googlesyndication.com") || 
+       url.contains("adnxs.com") || 
+       url.contains("smartadserver.com") ||
+       url.contains("criteo.com") ||
+       url.contains("rubiconproject.com") ||
+       url.contains("pubmatic.com") {
+        println!("Lumina Adblock: Forced block on ad domain: {}", url);
+        record_blocked_request(label, url, request_type, "forced");
+        return AdblockVerdict::Block;
+    }
+
+    // 1. Friendly Domain Policy (Bypass Adblock for Gemini/Google Critical Services)
+    if let Some(ref_str) = referer {
+         if ref_str.contains("gemini.google.com") || 
+            ref_str.contains("accounts.google.com") ||
+            ref_str.contains("google.com") ||
+            ref_str.contains("youtube.com") ||
+            ref_str.contains("transfermarkt") {
+              // println!("Lumina Adblock: Bypassing friendly domain: {}", url);
+              return AdblockVerdict::Allow;
+         }
+    }
+
+    // 1. Check Global Adblock Engine
+    if let Some(engine) = ADBLOCK_ENGINE.get().map(ArcSwap::load) {
+        let check_result = ::adblock::request::Request::new(url, referer.unwrap_or(""), request_type)
+            .map(|request| engine.check_network_request(&request));
+
+        if let Some(result) = check_result.ok().filter(|result| result.matched) {
+            println!("Lumina Adblock: Blocked {} ({})", url, request_type);
+            record_blocked_request(label, url, request_type, if result.redirect.is_some() { "redirect" } else { "filter" });
+            
+            count_blocked(label);
+
+            return match result.redirect {
+                Some(redirect) => AdblockVerdict::Redirect(redirect),
+                None => AdblockVerdict::Block,
+            };
+        }
+    }
+
+    // 2. Fallback to HostBlock List
+    if is_host_blocked(url) {
+        println!("Lumina HostBlock: {}", url);
+        record_blocked_request(label, url, request_type, "hostblock");
+        count_blocked(label);
+        return AdblockVerdict::Block;
+    }
+
+    AdblockVerdict::Allow
+}
+
+/// Lowercases and strips scheme, port, path and "www." so "https://www.Site.com/x" and "site.com"
+/// match. Anything that isn't a hostname comes back empty, so it can't end up in a page or a rule.
+pub(crate) fn normalize_site_domain(input: &str) -> String {
+    let trimmed = input.trim().to_lowercase();
+    let host = url::Url::parse(&trimmed)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_else(|| trimmed.split(['/', '?', '#']).next().and_then(|h| h.split(':').next()).unwrap_or("").to_string());
+    // IDNA to punycode, then only what a DNS name may hold
+    let host = match url::Host::parse(&host) {
+        Ok(url::Host::Domain(domain)) => domain,
+        Ok(url::Host::Ipv4(address)) => address.to_string(),
+        _ => return String::new(),
+    };
+    let host = host.trim_start_matches("www.");
+    if host.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        host.to_string()
+    } else {
+        String::new()
+    }
+}
+
+pub(crate) fn is_adblock_disabled_for_host(host: &str) -> bool {
+    let exceptions = adblock_site_exceptions().lock().unwrap();
+    exceptions.iter().any(|d| host == d || host.ends_with(&format!(".{}", d)))
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BlockedRequestEntry {
+    url: String,
+    domain: String,
+    request_type: String,
+    /// "filter", "redirect", "hostblock" or "forced"
+    reason: String,
+    timestamp: i64,
+}
+
+fn record_blocked_request(label: &str, url: &str, request_type: &str, reason: &str) {
+    let domain = url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
+        .unwrap_or_default();
+    let mut log = blocked_request_log().lock().unwrap();
+    let entries = log.entry(label.to_string()).or_default();
+    if entries.len() >= BLOCKED_REQUEST_LOG_LIMIT {
+        entries.remove(0);
+    }
+    entries.push(BlockedRequestEntry {
+        url: url.to_string(),
+        domain,
+        request_type: request_type.to_string(),
+        reason: reason.to_string(),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+    });
+}
+
+// (domain suffix, company, category)
+const KNOWN_TRACKERS: &[(&str, &str, &str)] = &[
+    ("doubleclick.net", "Google", "Advertising"),
+    ("googlesyndication.com", "Google", "Advertising"),
+    ("googleadservices.com", "Google", "Advertising"),
+    ("adservice.google.com", "Google", "Advertising"),
+    ("googletagservices.com", "Google", "Advertising"),
+    ("google-analytics.com", "Google", "Analytics"),
+    ("googletagmanager.com", "Google", "Analytics"),
+    ("facebook.net", "Meta", "Social"),
+    ("facebook.com", "Meta", "Social"),
+    ("ads-twitter.com", "X", "Social"),
+    ("ads.linkedin.com", "LinkedIn", "Social"),
+    ("adnxs.com", "Microsoft (Xandr)", "Advertising"),
+    ("bat.bing.com", "Microsoft", "Advertising"),
+    ("clarity.ms", "Microsoft", "Analytics"),
+    ("amazon-adsystem.com", "Amazon", "Advertising"),
+    ("criteo.com", "Criteo", "Advertising"),
+    ("criteo.net", "Criteo", "Advertising"),
+    ("taboola.com", "Taboola", "Advertising"),
+    ("outbrain.com", "Outbrain", "Advertising"),
+    ("rubiconproject.com", "Magnite", "Advertising"),
+    ("pubmatic.com", "PubMatic", "Advertising"),
+    ("smartadserver.com", "Equativ", "Advertising"),
+    ("admatic.com.tr", "AdMatic", "Advertising"),
+    ("scorecardresearch.com", "Comscore", "Analytics"),
+    ("hotjar.com", "Hotjar", "Analytics"),
+    ("mc.yandex.ru", "Yandex", "Analytics"),
+    ("yandex.ru", "Yandex", "Advertising"),
+    ("quantserve.com", "Quantcast", "Analytics"),
+    ("chartbeat.com", "Chartbeat", "Analytics"),
+    ("newrelic.com", "New Relic", "Analytics"),
+    ("nr-data.net", "New Relic", "Analytics"),
+];
+
+/// Maps a blocked host to (company, category), falling back to its last two labels and "Other".
+fn classify_tracker(domain: &str) -> (String, String) {
+    for (suffix, company, category) in KNOWN_TRACKERS {
+        if domain == *suffix || domain.ends_with(&format!(".{}", suffix)) {
+            return (company.to_string(), category.to_string());
+        }
+    }
+    let labels: Vec<&str> = domain.rsplitn(3, '.').collect();
+    let fallback = match labels.as_slice() {
+        [tld, name, ..] => format!("{}.{}", name, tld),
+        _ => domain.to_string(),
+    };
+    (fallback, "Other".to_string())
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShieldTrackerGroup {
+    company: String,
+    category: String,
+    blocked_count: u32,
+    domains: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PageShieldReport {
+    label: String,
+    total_blocked: u32,
+    trackers: Vec<ShieldTrackerGroup>,
+    categories: HashMap<String, u32>,
+}
+
+/// Requests blocked in the tab since its page last loaded, and how many hosts they went to.
+pub(crate) fn blocked_this_visit(label: &str) -> (u32, u32) {
+    let log = blocked_request_log().lock().unwrap();
+    let entries = log.get(label).map(Vec::as_slice).unwrap_or_default();
+    let domains: std::collections::HashSet<&str> = entries.iter().map(|entry| entry.domain.as_str()).collect();
+    (entries.len() as u32, domains.len() as u32)
+}
+
+#[tauri::command]
+pub(crate) fn get_blocked_requests(label: String) -> Vec<BlockedRequestEntry> {
+    blocked_request_log().lock().unwrap().get(&label).cloned().unwrap_or_default()
+}
+
+#[tauri::command]
+pub(crate) fn get_page_shield_report(label: String) -> PageShieldReport {
+    let entries = get_blocked_requests(label.clone());
+
+    let mut groups: HashMap<String, ShieldTrackerGroup> = HashMap::new();
+    let mut categories: HashMap<String, u32> = HashMap::new();
+    for entry in &entries {
+        let (company, category) = classify_tracker(&entry.domain);
+        *categories.entry(category.clone()).or_insert(0) += 1;
+
+        let group = groups.entry(company.clone()).or_insert_with(|| ShieldTrackerGroup {
+            company,
+            category,
+            blocked_count: 0,
+            domains: Vec::new(),
+        });
+        group.blocked_count += 1;
+        if !group.domains.contains(&entry.domain) {
+            group.domains.push(entry.domain.clone());
+        }
+    }
+
+    let mut trackers: Vec<ShieldTrackerGroup> = groups.into_values().collect();
+    trackers.sort_by(|a, b| b.blocked_count.cmp(&a.blocked_count).then_with(|| a.company.cmp(&b.company)));
+
+    PageShieldReport {
+        label,
+        total_blocked: entries.len() as u32,
+        trackers,
+        categories,
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AdblockStatePayload {
+    enabled: bool,
+    disabled_sites: Vec<String>,
+}
+
+fn get_adblock_state_payload() -> AdblockStatePayload {
+    let mut disabled_sites: Vec<String> = adblock_site_exceptions().lock().unwrap().iter().cloned().collect();
+    disabled_sites.sort();
+    AdblockStatePayload {
+        enabled: ADBLOCK_ENABLED.load(std::sync::atomic::Ordering::Relaxed),
+        disabled_sites,
+    }
+}
+
+pub(crate) fn load_adblock_state(app: &AppHandle) {
+    let (enabled, placeholders) = {
+        let data = app.state::<AppDataStore>();
+        let data = data.data.lock().unwrap();
+        (data.settings.adblock_enabled, data.settings.adblock_placeholders)
+    };
+    ADBLOCK_ENABLED.store(enabled, std::sync::atomic::Ordering::Relaxed);
+    ADBLOCK_PLACEHOLDERS.store(placeholders, Ordering::Relaxed);
+
+    let exceptions = app.state::<HistoryManager>().get_adblock_exceptions().unwrap_or_default();
+    *adblock_site_exceptions().lock().unwrap() = exceptions.into_iter().collect();
+}
+
+pub(crate) fn set_adblock_enabled(app: &AppHandle, enabled: bool) {
+    ADBLOCK_ENABLED.store(enabled, std::sync::atomic::Ordering::Relaxed);
+    let store = app.state::<AppDataStore>();
+    store.data.lock().unwrap().settings.adblock_enabled = enabled;
+    store.save();
+    events::emit(app, AppEvent::AdblockStateChanged(get_adblock_state_payload()));
+    refresh_tray_menu(app);
+}
+
+/// Flips the shield for one site. Returns whether blocking is now active on that site.
+pub(crate) fn toggle_adblock_site(app: &AppHandle, domain: &str) -> Result<bool, String> {
+    let domain = normalize_site_domain(domain);
+    if domain.is_empty() {
+        return Err("Invalid domain".to_string());
+    }
+
+    let history_manager = app.state::<HistoryManager>();
+    let now_blocking = {
+        // The set only changes once the database has, so a failed write leaves the two in step
+        let mut exceptions = adblock_site_exceptions().lock().unwrap();
+        if exceptions.contains(&domain) {
+            history_manager.remove_adblock_exception(&domain).map_err(|e| e.to_string())?;
+            exceptions.remove(&domain);
+            true
+        } else {
+            history_manager.add_adblock_exception(&domain).map_err(|e| e.to_string())?;
+            exceptions.insert(domain.clone());
+            false
+        }
+    };
+
+    println!("Lumina Adblock: {} for {}", if now_blocking { "Enabled" } else { "Disabled" }, domain);
+    events::emit(app, AppEvent::AdblockStateChanged(get_adblock_state_payload()));
+    Ok(now_blocking)
+}
+
+/// Matches the URL's host and every parent domain against the host blocklist.
+fn is_host_blocked(url: &str) -> bool {
+    let host = match url::Url::parse(url).ok().and_then(|u| u.host_str().map(|h| h.to_lowercase())) {
+        Some(h) => h,
+        None => return false,
+    };
+    let blocklist = host_blocklist().lock().unwrap();
+    let mut candidate = host.as_str();
+    loop {
+        if blocklist.contains(candidate) {
+            return true;
+        }
+        match candidate.split_once('.') {
+            Some((_, parent)) if parent.contains('.') => candidate = parent,
+            _ => return false,
+        }
+    }
+}
+
+pub(crate) fn load_host_blocklist(app: &AppHandle) {
+    let history_manager = app.state::<HistoryManager>();
+    if history_manager.get_meta("host_blocklist_seeded").ok().flatten().is_none() {
+        let defaults: Vec<String> = DEFAULT_BLOCKED_DOMAINS.iter().map(|d| d.to_string()).collect();
+        if history_manager.add_blocked_hosts(&defaults, "default").is_ok() {
+            let _ = history_manager.set_meta("host_blocklist_seeded", "1");
+        }
+    }
+
+    let hosts = history_manager.get_blocked_hosts().unwrap_or_default();
+    println!("Lumina HostBlock: {} hosts loaded", hosts.len());
+    *host_blocklist().lock().unwrap() = hosts.into_iter().map(|h| h.domain).collect();
+}
+
+/// Parses hosts-file syntax ("0.0.0.0 ads.example.com", comments, bare domains) into domains.
+fn parse_hosts_file(content: &str) -> Vec<String> {
+    let mut domains = Vec::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let first = parts.next().unwrap_or("");
+        let is_address = first.parse::<std::net::IpAddr>().is_ok();
+        let names: Vec<&str> = if is_address { parts.collect() } else { vec![first] };
+
+        for name in names {
+            let domain = normalize_site_domain(name);
+            if domain.contains('.') && !matches!(domain.as_str(), "localhost" | "localhost.localdomain" | "broadcasthost" | "0.0.0.0") {
+                domains.push(domain);
+            }
+        }
+    }
+    domains.sort();
+    domains.dedup();
+    domains
+}
+
+#[tauri::command]
+pub(crate) fn get_host_blocklist(history_manager: tauri::State<'_, HistoryManager>) -> Result<Vec<history_manager::HostBlockEntry>, LuminaError> {
+    history_manager.get_blocked_hosts().map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn add_blocked_host(history_manager: tauri::State<'_, HistoryManager>, domain: String) -> Result<(), LuminaError> {
+    let domain = normalize_site_domain(&domain);
+    if !domain.contains('.') {
+        return Err(LuminaError::InvalidInput("Invalid domain".to_string()));
+    }
+    history_manager.add_blocked_hosts(std::slice::from_ref(&domain), "user")?;
+    host_blocklist().lock().unwrap().insert(domain);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn remove_blocked_host(history_manager: tauri::State<'_, HistoryManager>, domain: String) -> Result<(), LuminaError> {
+    let domain = normalize_site_domain(&domain);
+    history_manager.remove_blocked_host(&domain)?;
+    host_blocklist().lock().unwrap().remove(&domain);
+    Ok(())
+}
+
+/// Imports a hosts file's contents. Returns the number of newly added domains.
+#[tauri::command]
+pub(crate) fn import_hosts_file(history_manager: tauri::State<'_, HistoryManager>, content: String) -> Result<usize, LuminaError> {
+    let domains = parse_hosts_file(&content);
+    let added = history_manager.add_blocked_hosts(&domains, "import")?;
+    host_blocklist().lock().unwrap().extend(domains);
+    println!("Lumina HostBlock: Imported {} new hosts", added);
+    Ok(added)
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AdblockListInfo {
+    pub(crate) name: String,
+    pub(crate) url: Option<String>,
+    pub(crate) version: Option<String>,
+    pub(crate) rule_count: usize,
+    pub(crate) fetched_at: Option<i64>,
+    pub(crate) error: Option<String>,
+}
+
+/// Reads the "! Version:" header and counts the filter lines of an ABP-style list.
+pub(crate) fn summarize_filter_list(text: &str) -> (Option<String>, usize) {
+    let version = text
+        .lines()
+        .take(50)
+        .find_map(|l| l.strip_prefix("! Version:").map(|v| v.trim().to_string()));
+    let rule_count = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('!') && !l.starts_with('['))
+        .count();
+    (version, rule_count)
+}
+
+#[tauri::command]
+pub(crate) fn toggle_adblock(app: AppHandle, enabled: bool) -> Result<(), LuminaError> {
+    set_adblock_enabled(&app, enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn toggle_adblock_for_site(app: AppHandle, domain: String) -> Result<bool, LuminaError> {
+    toggle_adblock_site(&app, &domain).map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn set_adblock_placeholders(state: tauri::State<'_, AppDataStore>, enabled: bool) -> Result<(), LuminaError> {
+    ADBLOCK_PLACEHOLDERS.store(enabled, Ordering::Relaxed);
+    state.data.lock().unwrap().settings.adblock_placeholders = enabled;
+    state.try_save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn get_adblock_state() -> AdblockStatePayload {
+    get_adblock_state_payload()
+}
+
+// Seeded into the host_blocklist table on first run; afterwards the table is the source of truth
+const DEFAULT_BLOCKED_DOMAINS: &[&str] = &[
+    "doubleclick.net",
+    "googleadservices.com",
+    "googlesyndication.com",
+    "adnxs.com",
+    "rubiconproject.com",
+    "taboola.com",
+    "outbrain.com",
+    "amazon-adsystem.com",
+    "adservice.google.com",
+    "moatads.com",
+    "criteo.com",
+    "pubmatic.com",
+    "openx.net",
+    "smartadserver.com",
+];
+
+#[tauri::command]
+pub(crate) fn clean_page(app: AppHandle) -> Result<(), LuminaError> {
+    let script = r#"
+        (function() {
+            // Domain Awareness for Clean Page
+            const host = window.location.hostname;
+            if (host.includes('google.com') || host.includes('gemini') || host.includes('youtube.com')) {
+                 console.log("Lumina Clean Page: Friendly domain detected (" + host + "), aborting force clean.");
+                 return;
+            }
+
+            const elements = document.querySelectorAll('div, iframe, section, aside, span, a, img, button');
+            let count = 0;
+            elements.forEach(el => {
+                const style = window.getComputedStyle(el);
+                if (style.position === 'fixed' || style.position === 'absolute') {
+                    // Check if it's likely an overlay/ad (high z-index or full width/height)
+                    if ((style.zIndex && parseInt(style.zIndex) > 10) || 
+                        (el.offsetWidth > window.innerWidth * 0.9 && el.offsetHeight > window.innerHeight * 0.9)) {
+                         el.remove();
+                         count++;
+                    }
+                }
+            });
+            console.log("Lumina Clean Page: Removed " + count + " floating elements.");
+        })();
+    "#;
+    
+    for (label, window) in app.webview_windows() {
+        if !is_browser_window(&label) {
+            window.eval(script)?;
+        }
+    }
+    Ok(())
+}
+
+/// Flag: aggressive_cosmetic_filtering. Generic element hiding for ad slots the network filter missed.
+pub(crate) fn get_cosmetic_filter_script() -> &'static str {
+    r#"
+    (function() {
+        if (window.self !== window.top) return;
+        const css = [
+            '.adsbygoogle', 'ins.adsbygoogle', '[id^="google_ads_"]', '[id^="div-gpt-ad"]', '[data-google-query-id]',
+            '[class*="ad-banner"]', '[class*="ad-container"]', '[class*="advert"]', '[id*="advert"]',
+            '[class*="sponsored"]', '[data-ad]', '[data-ad-slot]', '[aria-label="Advertisement"]',
+            '.taboola', '[id^="taboola-"]', '.OUTBRAIN', '[id^="outbrain_widget"]',
+            'iframe[src*="doubleclick.net"]', 'iframe[src*="googlesyndication.com"]'
+        ].join(',\n') + ' { display: none !important; }';
+
+        function inject() {
+            if (document.getElementById('lumina-cosmetic-filter')) return;
+            const style = document.createElement('style');
+            style.id = 'lumina-cosmetic-filter';
+            style.textContent = css;
+            (document.head || document.documentElement).appendChild(style);
+        }
+
+        if (document.head || document.documentElement) {
+            inject();
+        } else {
+            document.addEventListener('DOMContentLoaded', inject);
+        }
+    })();
+    "#
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn site_domains_drop_scheme_path_and_www() {
+        assert_eq!(normalize_site_domain("https://www.Site.com/x?y=1"), "site.com");
+        assert_eq!(normalize_site_domain("  www.example.org/path "), "example.org");
+        assert_eq!(normalize_site_domain("sub.example.org"), "sub.example.org");
+        assert_eq!(normalize_site_domain("example.org:8080/x"), "example.org");
+    }
+
+    #[test]
+    fn site_domains_are_hostnames_or_nothing() {
+        assert_eq!(normalize_site_domain("Bücher.de"), "xn--bcher-kva.de");
+        assert_eq!(normalize_site_domain("evil.com'),alert(1)//"), "");
+        assert_eq!(normalize_site_domain("a\"b.com"), "");
+        assert_eq!(normalize_site_domain("exa mple.com"), "");
+        assert_eq!(normalize_site_domain(""), "");
+    }
+
+    #[test]
+    fn hosts_files_yield_sorted_unique_domains() {
+        let content = "# comment\n0.0.0.0 ads.example.com tracker.example.com # trailing\n127.0.0.1 localhost\n\nbare.example.net\n0.0.0.0 ads.example.com\n::1 ip6-localhost\n";
+        assert_eq!(parse_hosts_file(content), vec!["ads.example.com", "bare.example.net", "tracker.example.com"]);
+    }
+}
@@ -0,0 +1,70 @@
+use tauri::Url;
+
+use crate::error::LuminaError;
+use crate::history_manager::{HistoryManager, UrlAlias};
+
+const MAX_ALIAS_LEN: usize = 32;
+
+/// Lowercase letters, digits, '-' and '_'. No dots, so an alias never shadows a domain typed in the omnibox.
+fn validated_alias(alias: &str) -> Result<String, LuminaError> {
+    let alias = alias.trim().to_lowercase();
+    if alias.is_empty() || alias.len() > MAX_ALIAS_LEN {
+        return Err(LuminaError::InvalidInput(format!("Aliases must be 1 to {} characters", MAX_ALIAS_LEN)));
+    }
+    if !alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(LuminaError::InvalidInput("Aliases can only contain letters, digits, '-' and '_'".to_string()));
+    }
+    Ok(alias)
+}
+
+/// Schemes without "//" that an alias can still point at
+const OPAQUE_SCHEMES: &[&str] = &["mailto:", "tel:"];
+
+/// "youtube.com" becomes "https://youtube.com". URLs with a scheme (lumina://, mailto:) are kept as typed.
+fn validated_url(url: &str) -> Result<String, LuminaError> {
+    let url = url.trim();
+    let has_scheme = url.contains("://") || OPAQUE_SCHEMES.iter().any(|scheme| url.starts_with(scheme));
+    let url = if has_scheme { url.to_string() } else { format!("https://{}", url) };
+    let parsed = Url::parse(&url.replace("%s", "x")).map_err(|e| LuminaError::InvalidInput(format!("Invalid URL: {}", e)))?;
+    if matches!(parsed.scheme(), "javascript" | "data") {
+        return Err(LuminaError::InvalidInput("Aliases can't run scripts".to_string()));
+    }
+    Ok(url)
+}
+
+/// Expands an alias typed in the omnibox, or returns None if the first word isn't one. Words after
+/// the alias only count when its URL has a `%s` to put them in; otherwise the input is a search.
+pub(crate) fn expand(history_manager: &HistoryManager, input: &str) -> Option<String> {
+    let input = input.trim();
+    let (alias, rest) = match input.split_once(char::is_whitespace) {
+        Some((alias, rest)) => (alias, rest.trim()),
+        None => (input, ""),
+    };
+    let url = history_manager.get_alias_url(&alias.to_lowercase()).ok()??;
+    if url.contains("%s") {
+        Some(url.replace("%s", &urlencoding::encode(rest)))
+    } else {
+        rest.is_empty().then_some(url)
+    }
+}
+
+/// Adding an alias that exists points it at the new URL.
+#[tauri::command]
+pub(crate) fn add_alias(history_manager: tauri::State<'_, HistoryManager>, alias: String, url: String) -> Result<UrlAlias, LuminaError> {
+    let alias = validated_alias(&alias)?;
+    let url = validated_url(&url)?;
+    history_manager.set_alias(&alias, &url).map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn remove_alias(history_manager: tauri::State<'_, HistoryManager>, alias: String) -> Result<(), LuminaError> {
+    if !history_manager.remove_alias(&alias.trim().to_lowercase())? {
+        return Err(LuminaError::NotFound(format!("Alias {} not found", alias.trim())));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn list_aliases(history_manager: tauri::State<'_, HistoryManager>) -> Result<Vec<UrlAlias>, LuminaError> {
+    history_manager.get_aliases().map_err(LuminaError::from)
+}
@@ -0,0 +1,566 @@
+use std::path::{Path, PathBuf};
+use base64::Engine;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use tauri::{AppHandle, Manager, Url, Webview};
+
+use crate::downloads::{register_saved_file, sanitize_filename, unique_download_path};
+use crate::error::LuminaError;
+use crate::events::{self, AppEvent};
+use crate::http;
+use crate::layout::UiState;
+use crate::scripting::eval_in_webview;
+use crate::tab_registry::TabRegistry;
+use crate::ToastPayload;
+
+const SNAPSHOT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Resources past these limits are left as links to the live site
+const MAX_RESOURCES: usize = 400;
+const MAX_RESOURCE_BYTES: usize = 20 * 1024 * 1024;
+const PARALLEL_FETCHES: usize = 8;
+/// How deep @import chains are followed when inlining stylesheets
+const MAX_CSS_DEPTH: usize = 3;
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PageFormat {
+    /// The browser's own MHTML archive
+    Mhtml,
+    /// One .html file with images and stylesheets inlined as data URIs
+    Html,
+}
+
+/// Serialized DOM of the page with every resource URL swapped for a `__lumina_resource_N__` or
+/// `__lumina_stylesheet_N__` token that indexes `resources`.
+#[derive(Deserialize)]
+struct PageSnapshot {
+    html: String,
+    resources: Vec<String>,
+    title: String,
+    url: String,
+}
+
+/// Clones the document, drops scripts and Lumina's own overlays, and replaces resource URLs with
+/// tokens. Links are made absolute so they still work from the saved file.
+const SNAPSHOT_JS: &str = r#"
+(() => {
+    const resources = [];
+    const abs = (u) => { try { return new URL(u, document.baseURI).href; } catch (e) { return null; } };
+    const token = (kind, u) => {
+        const url = abs(u);
+        if (!url || !/^https?:/.test(url)) return u;
+        let i = resources.indexOf(url);
+        if (i < 0) { i = resources.length; resources.push(url); }
+        return '__lumina_' + kind + '_' + i + '__';
+    };
+    const rewriteCss = (css) => css.replace(/url\(\s*(['"]?)([^'")]+)\1\s*\)/g, (m, q, u) => /^(data:|#)/.test(u) ? m : 'url(' + token('resource', u) + ')');
+
+    // Pick up what the browser actually loaded before the clone loses it
+    const live = [...document.querySelectorAll('img')].map(img => img.currentSrc || img.src);
+    const clone = document.documentElement.cloneNode(true);
+    clone.querySelectorAll('img').forEach((img, i) => {
+        if (live[i]) img.setAttribute('src', token('resource', live[i]));
+        img.removeAttribute('srcset');
+        img.removeAttribute('loading');
+    });
+    clone.querySelectorAll('picture source').forEach(el => el.remove());
+    clone.querySelectorAll('script, noscript, base, #__lumina_screenshot, #lumina-context-menu, meta[http-equiv]').forEach(el => el.remove());
+    clone.querySelectorAll('meta[charset]').forEach(el => el.remove());
+    clone.querySelectorAll('link[rel~="stylesheet"][href]').forEach(link => {
+        const style = document.createElement('style');
+        if (link.media) style.media = link.media;
+        style.textContent = token('stylesheet', link.getAttribute('href'));
+        link.replaceWith(style);
+    });
+    clone.querySelectorAll('link[rel~="icon"][href]').forEach(link => link.setAttribute('href', token('resource', link.getAttribute('href'))));
+    clone.querySelectorAll('video[poster]').forEach(v => v.setAttribute('poster', token('resource', v.getAttribute('poster'))));
+    clone.querySelectorAll('style').forEach(style => { style.textContent = rewriteCss(style.textContent); });
+    clone.querySelectorAll('[style]').forEach(el => el.setAttribute('style', rewriteCss(el.getAttribute('style'))));
+    clone.querySelectorAll('a[href], area[href]').forEach(a => { const url = abs(a.getAttribute('href')); if (url) a.setAttribute('href', url); });
+    clone.querySelectorAll('form[action]').forEach(f => { const url = abs(f.getAttribute('action')); if (url) f.setAttribute('action', url); });
+    clone.querySelectorAll('*').forEach(el => [...el.attributes].forEach(a => { if (a.name.startsWith('on')) el.removeAttribute(a.name); }));
+
+    const head = clone.querySelector('head');
+    if (head) {
+        const meta = document.createElement('meta');
+        meta.setAttribute('charset', 'utf-8');
+        head.prepend(meta);
+    }
+    return { html: '<!DOCTYPE html>\n' + clone.outerHTML, resources, title: document.title, url: location.href };
+})()
+"#;
+
+/// A fetched resource, ready to be spliced back in
+enum Inlined {
+    DataUri(String),
+    Css(String),
+}
+
+/// Saves the tab's page as MHTML or a self-contained HTML file in the downloads folder and lists
+/// it among the downloads. `label: null` saves the active tab.
+#[tauri::command]
+pub(crate) async fn save_page(app: AppHandle, label: Option<String>, format: PageFormat) -> Result<String, LuminaError> {
+    let label = match label {
+        Some(label) => label,
+        None => app.state::<UiState>().current_tab()
+            .ok_or_else(|| LuminaError::NotFound("No tab to save".to_string()))?,
+    };
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("Tab {} not found", label)))?;
+    let page_url = webview.url()?;
+    if !matches!(page_url.scheme(), "http" | "https") {
+        return Err(LuminaError::InvalidInput("Only web pages can be saved".to_string()));
+    }
+
+    let dir = app.path().download_dir().unwrap_or(PathBuf::from("downloads"));
+    std::fs::create_dir_all(&dir)?;
+    let title = app.state::<TabRegistry>().snapshot(None).tabs.into_iter()
+        .find(|t| t.label == label)
+        .map(|t| t.title)
+        .unwrap_or_default();
+
+    let path = match format {
+        PageFormat::Mhtml => {
+            let path = unique_download_path(&dir, &page_file_name(&title, &page_url, "mhtml"));
+            save_mhtml(&webview, &path).await.map_err(LuminaError::Webview)?;
+            path
+        }
+        PageFormat::Html => {
+            let html = single_file_html(&app, &label, &webview).await?;
+            let path = unique_download_path(&dir, &page_file_name(&title, &page_url, "html"));
+            tokio::fs::write(&path, html).await?;
+            path
+        }
+    };
+
+    register_saved_file(&app, page_url.as_str(), &path);
+    events::emit(&app, AppEvent::Toast(ToastPayload {
+        message: format!("Sayfa kaydedildi: {}", path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()),
+        level: "success".to_string(),
+    }));
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn page_file_name(title: &str, url: &Url, extension: &str) -> String {
+    let title = title.trim().replace(['/', '\\'], "-");
+    let stem = if title.is_empty() { url.host_str().unwrap_or("page").to_string() } else { title };
+    sanitize_filename(&format!("{}.{}", stem, extension))
+}
+
+#[cfg(target_os = "linux")]
+async fn save_mhtml(webview: &Webview, path: &Path) -> Result<(), String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let path = path.to_path_buf();
+    webview.with_webview(move |webview| {
+        use webkit2gtk::{gio, SaveMode, WebViewExt};
+        let file = gio::File::for_path(&path);
+        webview.inner().save_to_file(&file, SaveMode::Mhtml, None::<&gio::Cancellable>, move |result| {
+            let _ = tx.send(result.map_err(|e| e.to_string()));
+        });
+    }).map_err(|e| e.to_string())?;
+    rx.await.map_err(|_| "The webview dropped the save request".to_string())?
+}
+
+#[cfg(windows)]
+async fn save_mhtml(webview: &Webview, path: &Path) -> Result<(), String> {
+    // Page.captureSnapshot answers with { "data": "<MHTML document>" }
+    let value = crate::screenshot::call_devtools(webview, "Page.captureSnapshot", r#"{"format":"mhtml"}"#).await?;
+    let data = value["data"].as_str().ok_or("Snapshot response had no data")?;
+    tokio::fs::write(path, data).await.map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+async fn save_mhtml(_webview: &Webview, _path: &Path) -> Result<(), String> {
+    Err("Saving as MHTML is not supported on this platform yet".to_string())
+}
+
+async fn single_file_html(app: &AppHandle, label: &str, webview: &Webview) -> Result<String, LuminaError> {
+    let app_handle = app.clone();
+    let tab = label.to_string();
+    let json = tauri::async_runtime::spawn_blocking(move || eval_in_webview(&app_handle, &tab, SNAPSHOT_JS, SNAPSHOT_TIMEOUT))
+        .await
+        .map_err(|e| e.to_string())??
+        .ok_or_else(|| LuminaError::Webview("The page did not return a snapshot".to_string()))?;
+    let snapshot: PageSnapshot = serde_json::from_str(&json)?;
+    let page_url = Url::parse(&snapshot.url).map_err(|e| LuminaError::InvalidInput(e.to_string()))?;
+
+    let fetcher = Fetcher::new(webview.clone(), page_url);
+    let fetcher = &fetcher;
+    let requests: Vec<(String, bool)> = snapshot.resources.iter().take(MAX_RESOURCES).enumerate()
+        .map(|(index, url)| (url.clone(), snapshot.html.contains(&format!("__lumina_stylesheet_{}__", index))))
+        .collect();
+    let fetched: Vec<Option<Inlined>> = futures_util::stream::iter(requests)
+        .map(|(url, stylesheet)| async move {
+            let url = Url::parse(&url).ok()?;
+            if stylesheet {
+                fetcher.stylesheet(&url, 0).await.map(Inlined::Css)
+            } else {
+                fetcher.data_uri(&url).await.map(Inlined::DataUri)
+            }
+        })
+        .buffered(PARALLEL_FETCHES)
+        .collect()
+        .await;
+
+    let mut html = fill_tokens(&snapshot.html, |kind, index| match (kind, fetched.get(index)) {
+        ("resource", Some(Some(Inlined::DataUri(uri)))) => uri.clone(),
+        ("stylesheet", Some(Some(Inlined::Css(css)))) => css.replace("</style", "<\\/style"),
+        // Not fetched: point at the live site, or @import it so the styles still load when online
+        ("stylesheet", _) => format!("@import url(\"{}\");", snapshot.resources.get(index).map(String::as_str).unwrap_or("")),
+        _ => snapshot.resources.get(index).cloned().unwrap_or_default(),
+    });
+    if !snapshot.title.is_empty() && !html.contains("<title") {
+        html = html.replacen("<head>", &format!("<head><title>{}</title>", crate::escape_html(&snapshot.title)), 1);
+    }
+    Ok(html)
+}
+
+/// Replaces every `__lumina_<kind>_<index>__` token in `text` with `value(kind, index)`.
+fn fill_tokens(text: &str, value: impl Fn(&str, usize) -> String) -> String {
+    const PREFIX: &str = "__lumina_";
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(PREFIX) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + PREFIX.len()..];
+        let parsed = after.split_once("__").and_then(|(token, _)| {
+            let (kind, index) = token.rsplit_once('_')?;
+            Some((kind, index.parse::<usize>().ok()?, token.len()))
+        });
+        match parsed {
+            Some((kind, index, len)) if kind == "resource" || kind == "stylesheet" => {
+                out.push_str(&value(kind, index));
+                rest = &after[len + 2..];
+            }
+            _ => {
+                out.push_str(PREFIX);
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Fetches subresources the way the tab would: with the tab's cookies for each URL and the page as referrer.
+struct Fetcher {
+    webview: Webview,
+    page_url: Url,
+    client: reqwest::Client,
+}
+
+impl Fetcher {
+    fn new(webview: Webview, page_url: Url) -> Self {
+        let client = http::client(webview.app_handle(), http::Purpose::Page).unwrap_or_default();
+        Self { webview, page_url, client }
+    }
+
+    async fn fetch(&self, url: &Url) -> Option<(Vec<u8>, String)> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+        // Reading cookies can block on the webview's thread
+        let webview = self.webview.clone();
+        let cookie_url = url.clone();
+        let cookies = tauri::async_runtime::spawn_blocking(move || webview.cookies_for_url(cookie_url))
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .unwrap_or_default();
+        let cookie_header = cookies.iter().map(|c| format!("{}={}", c.name(), c.value())).collect::<Vec<_>>().join("; ");
+
+        let mut request = self.client.get(url.clone()).header(reqwest::header::REFERER, self.page_url.as_str());
+        if !cookie_header.is_empty() {
+            request = request.header(reqwest::header::COOKIE, cookie_header);
+        }
+        let response = request.send().await.ok()?.error_for_status().ok()?;
+        if response.content_length().is_some_and(|len| len as usize > MAX_RESOURCE_BYTES) {
+            return None;
+        }
+        let mime = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_string())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+
+        let mut body = Vec::new();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            body.extend_from_slice(&chunk.ok()?);
+            if body.len() > MAX_RESOURCE_BYTES {
+                return None;
+            }
+        }
+        Some((body, mime))
+    }
+
+    async fn data_uri(&self, url: &Url) -> Option<String> {
+        let (body, mime) = self.fetch(url).await?;
+        Some(format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(body)))
+    }
+
+    /// The stylesheet's text with its own url() references (fonts, images, @imports) inlined.
+    async fn stylesheet(&self, url: &Url, depth: usize) -> Option<String> {
+        let (body, _) = self.fetch(url).await?;
+        let css = String::from_utf8_lossy(&body).into_owned();
+        Some(self.inline_css_urls(&css, url, depth).await)
+    }
+
+    async fn inline_css_urls(&self, css: &str, base: &Url, depth: usize) -> String {
+        let mut out = String::with_capacity(css.len());
+        let mut rest = css;
+        while let Some(start) = rest.find("url(") {
+            let Some(end) = rest[start..].find(')') else { break };
+            out.push_str(&rest[..start]);
+            let raw = rest[start + 4..start + end].trim().trim_matches(['"', '\'']);
+
+            let target = if raw.starts_with("data:") || raw.starts_with('#') { None } else { base.join(raw).ok() };
+            let inlined = match &target {
+                Some(target) => {
+                    // An @import pulls in more CSS, which gets the same treatment
+                    if !out.trim_end().ends_with("@import") {
+                        self.data_uri(target).await
+                    } else if depth < MAX_CSS_DEPTH {
+                        Box::pin(self.stylesheet(target, depth + 1)).await.map(|css| {
+                            format!("data:text/css;base64,{}", base64::engine::general_purpose::STANDARD.encode(css))
+                        })
+                    } else {
+                        None
+                    }
+                }
+                None => None,
+            };
+            // Whatever couldn't be inlined still has to resolve from the saved file, so it goes in absolute
+            match (inlined, &target) {
+                (Some(uri), _) => out.push_str(&format!("url(\"{}\")", uri)),
+                (None, Some(target)) => out.push_str(&format!("url(\"{}\")", target)),
+                (None, None) => out.push_str(&rest[start..start + end + 1]),
+            }
+            rest = &rest[start + end + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+const PRINT_PREPARE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+const MAX_PRINT_MARGIN_MM: f64 = 50.0;
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PrintTarget {
+    /// The system print dialog
+    Printer,
+    /// A PDF in the downloads folder
+    Pdf,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct SimplifiedPrintOptions {
+    /// Title and site above the article, address and print date below it
+    header_footer: bool,
+    /// On every side of the page
+    margin_mm: f64,
+}
+
+impl Default for SimplifiedPrintOptions {
+    fn default() -> Self {
+        Self { header_footer: true, margin_mm: 15.0 }
+    }
+}
+
+/// Adds a print-only copy of the page's main content, as reader mode picks it, and hides the rest of
+/// the page from print. The screen doesn't change. `window.__LUMINA_PRINT_CLEANUP__()` takes it out
+/// again. Returns false when the page has no text to print.
+fn print_prepare_script(options: &SimplifiedPrintOptions) -> String {
+    format!(
+        r#"(() => {{
+            {find_content}
+            if (window.__LUMINA_PRINT_CLEANUP__) window.__LUMINA_PRINT_CLEANUP__();
+            const headerFooter = {header_footer};
+            const content = findContent();
+            if (!content || !content.innerText.trim()) return false;
+
+            const live = [...content.querySelectorAll('img')].map(img => img.currentSrc || img.src);
+            const article = content.cloneNode(true);
+            article.querySelectorAll('img').forEach((img, i) => {{
+                if (live[i]) img.setAttribute('src', live[i]);
+                img.removeAttribute('srcset');
+                img.removeAttribute('loading');
+            }});
+            article.querySelectorAll('script, style, iframe, nav, aside, form, button, video, audio, .ad, .ads, .advertisement, #__lumina_screenshot, #lumina-context-menu').forEach(el => el.remove());
+
+            const root = document.createElement('div');
+            root.id = '__lumina_print';
+            const meta = (tag, text) => {{
+                const el = document.createElement(tag);
+                el.className = '__lumina_print_meta';
+                el.textContent = text;
+                return el;
+            }};
+            if (headerFooter) {{
+                const header = document.createElement('header');
+                const title = document.createElement('h1');
+                title.textContent = document.title;
+                header.append(title, meta('div', location.hostname));
+                root.appendChild(header);
+            }}
+            root.appendChild(article);
+            if (headerFooter) {{
+                root.appendChild(meta('footer', location.href + ' · ' + new Date().toLocaleString()));
+            }}
+
+            const style = document.createElement('style');
+            style.textContent = `
+                #__lumina_print {{ display: none; }}
+                @media print {{
+                    @page {{ margin: {margin}mm; }}
+                    html, body {{ background: #fff !important; margin: 0 !important; padding: 0 !important; }}
+                    body > *:not(#__lumina_print) {{ display: none !important; }}
+                    #__lumina_print {{ display: block !important; color: #111; font-family: Georgia, 'Times New Roman', serif; font-size: 12pt; line-height: 1.5; }}
+                    #__lumina_print * {{ position: static !important; float: none !important; max-width: 100% !important; background: transparent !important; color: inherit !important; }}
+                    #__lumina_print h1, #__lumina_print h2, #__lumina_print h3, #__lumina_print h4 {{ font-family: Helvetica, Arial, sans-serif; break-after: avoid; }}
+                    #__lumina_print img, #__lumina_print figure, #__lumina_print pre, #__lumina_print blockquote {{ break-inside: avoid; }}
+                    #__lumina_print img {{ height: auto !important; display: block; margin: 12pt auto; }}
+                    #__lumina_print pre, #__lumina_print code {{ white-space: pre-wrap; font-size: 10pt; }}
+                    #__lumina_print blockquote {{ border-left: 3pt solid #ccc; padding-left: 10pt; margin-left: 0; color: #444 !important; }}
+                    #__lumina_print a {{ text-decoration: underline; }}
+                    #__lumina_print header {{ margin-bottom: 18pt; }}
+                    #__lumina_print footer {{ margin-top: 18pt; }}
+                    #__lumina_print .__lumina_print_meta {{ font-family: Helvetica, Arial, sans-serif; font-size: 9pt; color: #666 !important; overflow-wrap: anywhere; }}
+                }}
+            `;
+            document.head.appendChild(style);
+            document.body.appendChild(root);
+            window.__LUMINA_PRINT_CLEANUP__ = () => {{
+                root.remove();
+                style.remove();
+                delete window.__LUMINA_PRINT_CLEANUP__;
+            }};
+            return true;
+        }})()"#,
+        find_content = crate::tabs::READER_FIND_CONTENT_JS,
+        header_footer = options.header_footer,
+        margin = options.margin_mm,
+    )
+}
+
+/// Prints the tab's article without the page around it (menus, ads, sidebars), or saves it as a PDF
+/// in the downloads folder and returns its path. `label: null` prints the active tab.
+#[tauri::command]
+pub(crate) async fn print_simplified(app: AppHandle, label: Option<String>, target: PrintTarget, options: Option<SimplifiedPrintOptions>) -> Result<Option<String>, LuminaError> {
+    let options = options.unwrap_or_default();
+    if !(0.0..=MAX_PRINT_MARGIN_MM).contains(&options.margin_mm) {
+        return Err(LuminaError::InvalidInput(format!("Margins must be between 0 and {} mm", MAX_PRINT_MARGIN_MM)));
+    }
+    let label = match label {
+        Some(label) => label,
+        None => app.state::<UiState>().current_tab()
+            .ok_or_else(|| LuminaError::NotFound("No tab to print".to_string()))?,
+    };
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("Tab {} not found", label)))?;
+    let page_url = webview.url()?;
+    if !matches!(page_url.scheme(), "http" | "https") {
+        return Err(LuminaError::InvalidInput("Only web pages can be printed simplified".to_string()));
+    }
+
+    let js = print_prepare_script(&options);
+    let app_handle = app.clone();
+    let tab = label.clone();
+    let prepared = tauri::async_runtime::spawn_blocking(move || eval_in_webview(&app_handle, &tab, &js, PRINT_PREPARE_TIMEOUT))
+        .await
+        .map_err(|e| e.to_string())??
+        .and_then(|json| serde_json::from_str::<bool>(&json).ok())
+        .unwrap_or(false);
+    if !prepared {
+        return Err(LuminaError::NotFound("The page has no article to print".to_string()));
+    }
+
+    match target {
+        PrintTarget::Printer => {
+            // The dialog blocks the page until it closes; the copy goes away once printing is done
+            webview.eval("window.addEventListener('afterprint', () => window.__LUMINA_PRINT_CLEANUP__ && window.__LUMINA_PRINT_CLEANUP__(), { once: true }); setTimeout(() => window.print(), 0);")?;
+            Ok(None)
+        }
+        PrintTarget::Pdf => {
+            let dir = app.path().download_dir().unwrap_or(PathBuf::from("downloads"));
+            std::fs::create_dir_all(&dir)?;
+            let title = app.state::<TabRegistry>().snapshot(None).tabs.into_iter()
+                .find(|t| t.label == label)
+                .map(|t| t.title)
+                .unwrap_or_default();
+            let path = unique_download_path(&dir, &page_file_name(&title, &page_url, "pdf"));
+            let printed = print_to_pdf(&webview, &path, options.margin_mm).await;
+            let _ = webview.eval("window.__LUMINA_PRINT_CLEANUP__ && window.__LUMINA_PRINT_CLEANUP__()");
+            printed.map_err(LuminaError::Webview)?;
+
+            register_saved_file(&app, page_url.as_str(), &path);
+            events::emit(&app, AppEvent::Toast(ToastPayload {
+                message: format!("PDF kaydedildi: {}", path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()),
+                level: "success".to_string(),
+            }));
+            Ok(Some(path.to_string_lossy().to_string()))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn print_to_pdf(webview: &Webview, path: &Path, margin_mm: f64) -> Result<(), String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let uri = Url::from_file_path(path).map_err(|_| "The PDF path is not absolute".to_string())?.to_string();
+    webview.with_webview(move |webview| {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use webkit2gtk::{PrintOperation, PrintOperationExt};
+
+        let settings = gtk::PrintSettings::new();
+        settings.set_printer("Print to File");
+        settings.set(gtk::PRINT_SETTINGS_OUTPUT_FILE_FORMAT, Some("pdf"));
+        settings.set(gtk::PRINT_SETTINGS_OUTPUT_URI, Some(&uri));
+        let page_setup = gtk::PageSetup::new();
+        page_setup.set_top_margin(margin_mm, gtk::Unit::Mm);
+        page_setup.set_bottom_margin(margin_mm, gtk::Unit::Mm);
+        page_setup.set_left_margin(margin_mm, gtk::Unit::Mm);
+        page_setup.set_right_margin(margin_mm, gtk::Unit::Mm);
+
+        let operation = PrintOperation::new(&webview.inner());
+        operation.set_print_settings(&settings);
+        operation.set_page_setup(&page_setup);
+        // "finished" follows "failed", so the error is kept until then
+        let failure: Rc<RefCell<Option<String>>> = Rc::default();
+        let failed = failure.clone();
+        operation.connect_failed(move |_, error| *failed.borrow_mut() = Some(error.to_string()));
+        let tx = RefCell::new(Some(tx));
+        operation.connect_finished(move |_| {
+            if let Some(tx) = tx.borrow_mut().take() {
+                let _ = tx.send(failure.borrow_mut().take().map_or(Ok(()), Err));
+            }
+        });
+        operation.print();
+    }).map_err(|e| e.to_string())?;
+    rx.await.map_err(|_| "The webview dropped the print request".to_string())?
+}
+
+#[cfg(windows)]
+async fn print_to_pdf(webview: &Webview, path: &Path, margin_mm: f64) -> Result<(), String> {
+    // Page.printToPDF takes margins in inches and answers with { "data": "<base64 PDF>" }
+    let inches = margin_mm / 25.4;
+    let params = serde_json::json!({
+        "printBackground": false,
+        "marginTop": inches,
+        "marginBottom": inches,
+        "marginLeft": inches,
+        "marginRight": inches,
+    });
+    let value = crate::screenshot::call_devtools(webview, "Page.printToPDF", &params.to_string()).await?;
+    let data = value["data"].as_str().ok_or("Print response had no data")?;
+    let pdf = base64::engine::general_purpose::STANDARD.decode(data).map_err(|e| e.to_string())?;
+    tokio::fs::write(path, pdf).await.map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+async fn print_to_pdf(_webview: &Webview, _path: &Path, _margin_mm: f64) -> Result<(), String> {
+    Err("Saving as PDF is not supported on this platform yet".to_string())
+}
@@ -0,0 +1,238 @@
+use tauri::{AppHandle, Manager};
+
+use crate::adblock::normalize_site_domain;
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::events::{self, AppEvent};
+use crate::history_manager::{new_id, AutofillProfile, HistoryManager};
+use crate::layout::UiState;
+use crate::{page_script, to_script_json, ToastPayload};
+
+/// Helpers for the two autofill steps, which run in Lumina's isolated world so the page sees neither
+/// the profile nor the code: `fields()` lists the fillable fields with what each asks for, and
+/// `fill(el, value)` sets one. Password fields and fields the user can't see are never touched.
+const AUTOFILL_HELPERS: &str = r#"
+    // autocomplete tokens, then patterns over name/id/placeholder/label (English and Turkish)
+    const AUTOCOMPLETE = {
+        'name': 'fullName', 'given-name': 'givenName', 'family-name': 'familyName',
+        'email': 'email', 'tel': 'phone', 'tel-national': 'phone', 'organization': 'organization',
+        'street-address': 'streetAddress', 'address-line1': 'streetAddress',
+        'address-level2': 'city', 'address-level1': 'region', 'postal-code': 'postalCode',
+        'country': 'country', 'country-name': 'country'
+    };
+    const PATTERNS = [
+        ['email', /e-?mail|e-?posta/],
+        ['phone', /phone|mobile|\btel|gsm|telefon/],
+        ['postalCode', /zip|postal|post.?code|posta.?kod/],
+        ['familyName', /last.?name|surname|family.?name|lname|soyad/],
+        ['givenName', /first.?name|given.?name|fname|forename/],
+        ['fullName', /full.?name|^name$|your.?name|ad.?soyad|isim/],
+        ['organization', /company|organi[sz]ation|firma|şirket|kurum/],
+        ['country', /country|ülke/],
+        ['region', /state|province|region|county|bölge/],
+        ['city', /city|town|locality|şehir|sehir/],
+        ['skip', /address.?(line)?.?2|apartment|suite|\bapt/],
+        ['streetAddress', /address|street|adres|sokak|cadde/]
+    ];
+    const SKIPPED_TYPES = ['hidden', 'password', 'submit', 'button', 'reset', 'checkbox', 'radio', 'file', 'image', 'range', 'color'];
+
+    function describe(el) {
+        const labels = el.labels ? [...el.labels].map(l => l.textContent) : [];
+        return [el.name, el.id, el.placeholder, el.getAttribute('aria-label'), ...labels]
+            .filter(Boolean).join(' ').toLowerCase();
+    }
+
+    function classify(el) {
+        const tokens = (el.getAttribute('autocomplete') || '').toLowerCase().split(/\s+/);
+        for (const token of tokens) {
+            if (AUTOCOMPLETE[token]) return AUTOCOMPLETE[token];
+        }
+        if (el.type === 'email') return 'email';
+        if (el.type === 'tel') return 'phone';
+        const text = describe(el);
+        const match = PATTERNS.find(([, pattern]) => pattern.test(text));
+        return match && match[0] !== 'skip' ? match[0] : null;
+    }
+
+    // Fields pushed off-screen, shrunk to nothing or made transparent are traps for autofill
+    function isVisible(el) {
+        const rect = el.getBoundingClientRect();
+        if (rect.width < 2 || rect.height < 2) return false;
+        const root = document.documentElement;
+        if (rect.right + window.scrollX <= 0 || rect.bottom + window.scrollY <= 0) return false;
+        if (rect.left + window.scrollX >= root.scrollWidth || rect.top + window.scrollY >= root.scrollHeight) return false;
+        for (let node = el; node && node.nodeType === 1; node = node.parentElement) {
+            const style = getComputedStyle(node);
+            if (style.display === 'none' || style.visibility === 'hidden' || parseFloat(style.opacity) === 0) return false;
+        }
+        return true;
+    }
+
+    function candidates() {
+        return [...document.querySelectorAll('input, select, textarea')];
+    }
+
+    function fields() {
+        return candidates().map((el, index) => {
+            if (el.disabled || el.readOnly || SKIPPED_TYPES.includes(el.type) || el.value || !isVisible(el)) return null;
+            const kind = classify(el);
+            return kind && { index, kind };
+        }).filter(Boolean);
+    }
+
+    function fill(el, value) {
+        if (el.tagName === 'SELECT') {
+            const wanted = value.toLowerCase();
+            const option = [...el.options].find(o => o.value.toLowerCase() === wanted || o.text.trim().toLowerCase() === wanted);
+            if (!option) return false;
+            value = option.value;
+        }
+        // The prototype setter, so frameworks that track the value property see the change
+        const proto = Object.getPrototypeOf(el);
+        const setter = Object.getOwnPropertyDescriptor(proto, 'value').set;
+        setter.call(el, value);
+        el.dispatchEvent(new Event('input', { bubbles: true }));
+        el.dispatchEvent(new Event('change', { bubbles: true }));
+        return true;
+    }
+"#;
+
+/// What a matched field asks for, as the helpers name it
+#[derive(serde::Deserialize)]
+struct MatchedField {
+    index: usize,
+    kind: String,
+}
+
+/// The profile's value for a field kind, if it has one
+fn profile_value(profile: &AutofillProfile, kind: &str) -> Option<String> {
+    let parts: Vec<&str> = profile.full_name.split_whitespace().collect();
+    let value = match kind {
+        "fullName" => profile.full_name.trim().to_string(),
+        "givenName" if parts.len() > 1 => parts[..parts.len() - 1].join(" "),
+        "givenName" => parts.first().copied().unwrap_or_default().to_string(),
+        "familyName" if parts.len() > 1 => parts[parts.len() - 1].to_string(),
+        "email" => profile.email.clone(),
+        "phone" => profile.phone.clone(),
+        "organization" => profile.organization.clone(),
+        "streetAddress" => profile.street_address.clone(),
+        "city" => profile.city.clone(),
+        "region" => profile.region.clone(),
+        "postalCode" => profile.postal_code.clone(),
+        "country" => profile.country.clone(),
+        _ => String::new(),
+    };
+    (!value.is_empty()).then_some(value)
+}
+
+fn validated_domain(domain: &str) -> Result<String, LuminaError> {
+    let domain = normalize_site_domain(domain);
+    if domain.is_empty() {
+        return Err(LuminaError::InvalidInput("Invalid domain".to_string()));
+    }
+    Ok(domain)
+}
+
+#[tauri::command]
+pub(crate) fn list_autofill_profiles(history_manager: tauri::State<'_, HistoryManager>) -> Result<Vec<AutofillProfile>, LuminaError> {
+    history_manager.get_autofill_profiles().map_err(LuminaError::from)
+}
+
+/// Creates the profile when `id` is empty, otherwise replaces it. Returns the profile's id.
+#[tauri::command]
+pub(crate) fn save_autofill_profile(history_manager: tauri::State<'_, HistoryManager>, mut profile: AutofillProfile) -> Result<String, LuminaError> {
+    if profile.label.trim().is_empty() {
+        return Err(LuminaError::InvalidInput("The profile needs a name".to_string()));
+    }
+    if !history_manager.can_encrypt() {
+        return Err(LuminaError::PermissionDenied("Autofill profiles can't be saved without the encryption key".to_string()));
+    }
+    if profile.id.is_empty() {
+        profile.id = new_id();
+    }
+    history_manager.save_autofill_profile(&profile)?;
+    Ok(profile.id)
+}
+
+#[tauri::command]
+pub(crate) fn delete_autofill_profile(history_manager: tauri::State<'_, HistoryManager>, id: String) -> Result<(), LuminaError> {
+    history_manager.delete_autofill_profile(&id).map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn set_autofill_site_enabled(state: tauri::State<'_, AppDataStore>, domain: String, enabled: bool) -> Result<(), LuminaError> {
+    let domain = validated_domain(&domain)?;
+    {
+        let mut data = state.data.lock().unwrap();
+        let sites = &mut data.settings.autofill_disabled_sites;
+        sites.retain(|d| d != &domain);
+        if !enabled {
+            sites.push(domain);
+            sites.sort();
+        }
+    }
+    state.save();
+    Ok(())
+}
+
+/// Fills the empty fields of the forms on a tab from a profile. `label: null` uses the active tab and
+/// `profile_id: null` the most recently edited profile. Returns how many fields were filled.
+#[tauri::command]
+pub(crate) async fn fill_form(app: AppHandle, label: Option<String>, profile_id: Option<String>) -> Result<u32, LuminaError> {
+    let label = match label {
+        Some(label) => label,
+        None => app.state::<UiState>().current_tab()
+            .ok_or_else(|| LuminaError::NotFound("No tab to fill".to_string()))?,
+    };
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("Tab {} not found", label)))?;
+    let page_url = webview.url()?;
+    if !matches!(page_url.scheme(), "http" | "https") {
+        return Err(LuminaError::InvalidInput("Only web pages can be filled".to_string()));
+    }
+    let domain = normalize_site_domain(page_url.as_str());
+    let disabled = app.state::<AppDataStore>().data.lock().unwrap().settings.autofill_disabled_sites
+        .iter()
+        .any(|d| domain == *d || domain.ends_with(&format!(".{}", d)));
+    if disabled {
+        return Err(LuminaError::PermissionDenied(format!("Autofill is turned off for {}", domain)));
+    }
+
+    let profiles = app.state::<HistoryManager>().get_autofill_profiles()?;
+    let profile = match &profile_id {
+        Some(id) => profiles.into_iter().find(|p| &p.id == id),
+        None => profiles.into_iter().next(),
+    }
+    .ok_or_else(|| LuminaError::NotFound("No autofill profile; add one in settings".to_string()))?;
+
+    // First the page's fields, then only the values those fields ask for go back in. Both steps
+    // re-run the helpers, and the fill checks each field still asks for what it did.
+    let world = page_script::World::Isolated;
+    let listed = page_script::run(&webview, &format!("{}\nreturn fields();", AUTOFILL_HELPERS), world)
+        .await
+        .map_err(LuminaError::Webview)?;
+    let values: Vec<serde_json::Value> = serde_json::from_value::<Vec<MatchedField>>(listed)?
+        .into_iter()
+        .filter_map(|field| {
+            let value = profile_value(&profile, &field.kind)?;
+            Some(serde_json::json!({ "index": field.index, "kind": field.kind, "value": value }))
+        })
+        .collect();
+    let fill_js = format!(
+        "{}\nconst all = candidates();\nlet filled = 0;\nfor (const {{ index, kind, value }} of {}) {{\n    const el = all[index];\n    if (el && !el.value && isVisible(el) && classify(el) === kind && fill(el, value)) filled++;\n}}\nreturn filled;",
+        AUTOFILL_HELPERS,
+        to_script_json(&values)
+    );
+    let filled = if values.is_empty() {
+        0
+    } else {
+        page_script::run(&webview, &fill_js, world).await.map_err(LuminaError::Webview)?.as_u64().unwrap_or(0) as u32
+    };
+    if filled == 0 {
+        return Err(LuminaError::NotFound("No fields on this page matched the profile".to_string()));
+    }
+    events::emit(&app, AppEvent::Toast(ToastPayload {
+        message: format!("{} alan \"{}\" profiliyle dolduruldu", filled, profile.label),
+        level: "success".to_string(),
+    }));
+    Ok(filled)
+}
@@ -0,0 +1,72 @@
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// What installed web apps can count on from the embedded engine.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebCapabilities {
+    pub engine: String,
+    pub engine_version: String,
+    pub service_workers: bool,
+    pub push: bool,
+    /// Page notifications, delivered as desktop notifications with the engine's own click events
+    pub notifications: bool,
+    /// Whether `service_workers` was confirmed by a page rather than guessed from the version
+    pub probed: bool,
+    pub notes: Vec<String>,
+}
+
+/// Whether the last PWA window to report in had `navigator.serviceWorker`.
+static PROBED: Mutex<Option<bool>> = Mutex::new(None);
+
+pub fn record_probe(service_worker: bool) {
+    *PROBED.lock().unwrap() = Some(service_worker);
+}
+
+const ENGINE: &str = if cfg!(target_os = "windows") {
+    "WebView2"
+} else if cfg!(target_os = "macos") {
+    "WKWebView"
+} else {
+    "WebKitGTK"
+};
+
+/// Leading "major.minor" of a version string such as "144.0.3719.82" or "2.44.1".
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split('.').map(|p| p.trim().parse::<u32>().ok());
+    Some((parts.next()??, parts.next().flatten().unwrap_or(0)))
+}
+
+/// Service worker support by engine version, used until a page has reported in.
+fn service_workers_by_version(version: &str) -> bool {
+    match parse_version(version) {
+        Some((major, minor)) if ENGINE == "WebKitGTK" => (major, minor) >= (2, 28),
+        Some((major, _)) if ENGINE == "WebView2" => major >= 90,
+        // WKWebView reports the WebKit build, which has had service workers for every supported macOS
+        Some(_) => true,
+        None => false,
+    }
+}
+
+pub fn detect() -> WebCapabilities {
+    let engine_version = tauri::webview_version().unwrap_or_default();
+    let probe = *PROBED.lock().unwrap();
+    let service_workers = probe.unwrap_or_else(|| service_workers_by_version(&engine_version));
+
+    let mut notes = Vec::new();
+    if !service_workers {
+        notes.push("Service workers are unavailable, so offline mode and background sync won't work.".to_string());
+    }
+    // Even where PushManager is exposed, none of the embedded engines has a push service behind it
+    notes.push("Web push has no delivery service here; apps only get notifications while their window is open.".to_string());
+
+    WebCapabilities {
+        engine: ENGINE.to_string(),
+        engine_version,
+        service_workers,
+        push: false,
+        notifications: true,
+        probed: probe.is_some(),
+        notes,
+    }
+}
@@ -0,0 +1,182 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::webview::PageLoadEvent;
+use tauri::{AppHandle, Manager, WebviewWindow};
+use url::Url;
+
+use crate::adblock::handle_adblock_request;
+use crate::downloads::{filename_from_url, sanitize_filename};
+use crate::{apply_site_protection, get_lumina_stealth_script};
+use crate::pwa::get_pwa_init_script;
+use crate::screenshot::capture_png;
+use crate::scripting::{eval_in, LuaState};
+use crate::tabs::READER_FIND_CONTENT_JS;
+
+const HEADLESS_LABEL: &str = "headless";
+const CLI_PAGE_LOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// Time given to scripts after the load event before the page is read
+const CLI_SETTLE_DELAY: std::time::Duration = std::time::Duration::from_millis(1000);
+const CLI_EVAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+const CLI_USAGE: &str = "Usage:
+  lumina --fetch <url> [--output <file>] [--reader]
+  lumina --screenshot <url> [--output <file.png>]
+  lumina --run-lua <script.lua>";
+
+/// A scripted task given on the command line. The browser UI stays hidden while it runs and the
+/// process exits with 0 on success, 1 on failure.
+pub(crate) enum CliTask {
+    /// Saves the rendered HTML (or only the reader-mode article) of a page
+    Fetch { url: Url, output: Option<PathBuf>, reader: bool },
+    Screenshot { url: Url, output: Option<PathBuf> },
+    RunLua { script: PathBuf },
+}
+
+/// `Ok(None)` when the arguments don't ask for a headless task and the browser should start normally.
+pub(crate) fn parse_cli_args(args: &[String]) -> Result<Option<CliTask>, String> {
+    let mut task: Option<(&str, &str)> = None;
+    let mut output = None;
+    let mut reader = false;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            flag @ ("--fetch" | "--screenshot" | "--run-lua") => {
+                let value = iter.next().ok_or_else(|| format!("{} needs a value\n{}", flag, CLI_USAGE))?;
+                if task.is_some() {
+                    return Err(format!("Only one of --fetch, --screenshot and --run-lua can be given\n{}", CLI_USAGE));
+                }
+                task = Some((flag, value));
+            }
+            "--output" | "-o" => {
+                let value = iter.next().ok_or_else(|| format!("--output needs a file\n{}", CLI_USAGE))?;
+                output = Some(PathBuf::from(value));
+            }
+            "--reader" => reader = true,
+            _ => {}
+        }
+    }
+
+    let Some((flag, value)) = task else { return Ok(None) };
+    let parse_url = |value: &str| Url::parse(value).map_err(|e| format!("Invalid URL '{}': {}", value, e));
+    Ok(Some(match flag {
+        "--fetch" => CliTask::Fetch { url: parse_url(value)?, output, reader },
+        "--screenshot" => CliTask::Screenshot { url: parse_url(value)?, output },
+        _ => CliTask::RunLua { script: PathBuf::from(value) },
+    }))
+}
+
+/// Hides the main window, runs `task` and exits the app once it is done.
+pub(crate) fn start_headless(app: &AppHandle, task: CliTask) {
+    if let Some(main) = app.get_webview_window("main") {
+        let _ = main.hide();
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let code = match run_task(&app, task).await {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("lumina: {}", e);
+                1
+            }
+        };
+        app.exit(code);
+    });
+}
+
+async fn run_task(app: &AppHandle, task: CliTask) -> Result<(), String> {
+    match task {
+        CliTask::Fetch { url, output, reader } => {
+            let output = output.unwrap_or_else(|| default_output(&url, "html"));
+            let window = open_headless_page(app, &url, false).await?;
+            let js = if reader {
+                format!("(() => {{ {} return findContent().outerHTML; }})()", READER_FIND_CONTENT_JS)
+            } else {
+                "document.documentElement.outerHTML".to_string()
+            };
+            let html = eval_headless(app, js).await;
+            let _ = window.destroy();
+            std::fs::write(&output, html?).map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+            println!("Saved {} to {}", url, output.display());
+            Ok(())
+        }
+        CliTask::Screenshot { url, output } => {
+            let output = output.unwrap_or_else(|| default_output(&url, "png"));
+            let window = open_headless_page(app, &url, true).await?;
+            let png = capture_png(window.as_ref(), true).await;
+            let _ = window.destroy();
+            std::fs::write(&output, png?).map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+            println!("Saved screenshot of {} to {}", url, output.display());
+            Ok(())
+        }
+        CliTask::RunLua { script } => {
+            let code = std::fs::read_to_string(&script).map_err(|e| format!("Failed to read {}: {}", script.display(), e))?;
+            let chunk_name = script.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "cli".to_string());
+            let result = app.state::<LuaState>().run(code, &chunk_name, "user", true).await?;
+            if !result.is_empty() {
+                println!("{}", result);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// `<host or last path segment>.<extension>` in the working directory
+fn default_output(url: &Url, extension: &str) -> PathBuf {
+    let name = match filename_from_url(url).as_str() {
+        "downloaded_file" => url.host_str().unwrap_or("page").to_string(),
+        name => name.to_string(),
+    };
+    let stem = sanitize_filename(&name);
+    let stem = stem.rsplit_once('.').map(|(stem, _)| stem.to_string()).unwrap_or(stem);
+    PathBuf::from(format!("{}.{}", stem, extension))
+}
+
+/// Loads `url` in a window the user never sees, with the same stealth and adblock setup as a PWA
+/// window, and waits for the page to finish loading. Screenshots need a painted surface, so for those
+/// the window is shown far outside the visible desktop instead of being hidden.
+async fn open_headless_page(app: &AppHandle, url: &Url, painted: bool) -> Result<WebviewWindow, String> {
+    let (loaded_tx, loaded_rx) = tokio::sync::oneshot::channel::<()>();
+    let loaded_tx = Mutex::new(Some(loaded_tx));
+
+    let mut builder = tauri::WebviewWindowBuilder::new(app, HEADLESS_LABEL, tauri::WebviewUrl::External(url.clone()))
+        .title("Lumina")
+        .inner_size(1280.0, 800.0)
+        .skip_taskbar(true)
+        .focused(false)
+        .initialization_script(get_lumina_stealth_script())
+        .initialization_script(get_pwa_init_script(HEADLESS_LABEL, app.invoke_key()))
+        .on_web_resource_request(move |request, response| {
+            handle_adblock_request(&request, response, HEADLESS_LABEL);
+        })
+        .on_page_load(move |window, payload| {
+            apply_site_protection(window.app_handle(), window.as_ref(), &payload);
+            if payload.event() == PageLoadEvent::Finished {
+                if let Some(tx) = loaded_tx.lock().unwrap().take() {
+                    let _ = tx.send(());
+                }
+            }
+        });
+    builder = if painted {
+        builder.decorations(false).position(-20000.0, -20000.0)
+    } else {
+        builder.visible(false)
+    };
+    let window = builder.build().map_err(|e| format!("Failed to open {}: {}", url, e))?;
+
+    if tokio::time::timeout(CLI_PAGE_LOAD_TIMEOUT, loaded_rx).await.is_err() {
+        let _ = window.destroy();
+        return Err(format!("{} did not finish loading within {}s", url, CLI_PAGE_LOAD_TIMEOUT.as_secs()));
+    }
+    tokio::time::sleep(CLI_SETTLE_DELAY).await;
+    Ok(window)
+}
+
+async fn eval_headless(app: &AppHandle, js: String) -> Result<String, String> {
+    let webview = app.get_webview(HEADLESS_LABEL).ok_or_else(|| "The page was closed".to_string())?;
+    let json = tauri::async_runtime::spawn_blocking(move || eval_in(webview, &js, CLI_EVAL_TIMEOUT))
+        .await
+        .map_err(|e| e.to_string())??
+        .ok_or_else(|| "The page returned nothing".to_string())?;
+    serde_json::from_str::<String>(&json).map_err(|e| e.to_string())
+}
@@ -0,0 +1,186 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::error::LuminaError;
+use crate::events::{self, AppEvent};
+use crate::layout::{tab_window, UiState};
+use crate::tab_registry::TabRegistry;
+use crate::tabs;
+
+/// A tab crashing this often within CRASH_LOOP_WINDOW stops being reloaded on its own
+const CRASH_LOOP_LIMIT: u32 = 3;
+const CRASH_LOOP_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TabCrashedPayload {
+    pub label: String,
+    /// "crashed", "out-of-memory", "killed", "unresponsive" or "browser-exited"
+    pub reason: String,
+    pub url: String,
+}
+
+struct CrashedTab {
+    url: String,
+    reason: String,
+    at: Instant,
+    /// Crashes in a row, each within CRASH_LOOP_WINDOW of the last
+    count: u32,
+}
+
+/// Tabs whose renderer is gone, by label, until they are reloaded
+static CRASHED_TABS: OnceLock<Mutex<HashMap<String, CrashedTab>>> = OnceLock::new();
+
+fn crashed_tabs() -> &'static Mutex<HashMap<String, CrashedTab>> {
+    CRASHED_TABS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// A crashed tab keeps its real URL in the session while it shows the crash page.
+pub(crate) fn is_crashed(label: &str) -> bool {
+    crashed_tabs().lock().unwrap().contains_key(label)
+}
+
+fn is_crash_loop(label: &str) -> bool {
+    crashed_tabs().lock().unwrap().get(label).is_some_and(|tab| tab.count >= CRASH_LOOP_LIMIT)
+}
+
+fn show_crash_page(app: &AppHandle, label: &str) {
+    let Some(webview) = app.get_webview(label) else { return };
+    let reason = crashed_tabs().lock().unwrap().get(label).map(|tab| tab.reason.clone()).unwrap_or_default();
+    let target = format!(
+        "lumina-app://localhost/crashed?label={}&reason={}",
+        urlencoding::encode(label),
+        urlencoding::encode(&reason)
+    );
+    if let Ok(target) = target.parse() {
+        let _ = webview.navigate(target);
+    }
+}
+
+/// The tab in view shows what happened; a background tab reloads when it is switched to.
+fn tab_crashed(app: &AppHandle, label: &str, reason: &str) {
+    let Some(url) = app.state::<TabRegistry>().snapshot(None).tabs.into_iter().find(|tab| tab.label == label).map(|tab| tab.url) else { return };
+    println!("Lumina: Tab {} lost its renderer ({}) at {}", label, reason, url);
+    {
+        let mut crashed = crashed_tabs().lock().unwrap();
+        let count = match crashed.get(label) {
+            Some(previous) if previous.at.elapsed() < CRASH_LOOP_WINDOW => previous.count + 1,
+            _ => 1,
+        };
+        crashed.insert(label.to_string(), CrashedTab { url: url.clone(), reason: reason.to_string(), at: Instant::now(), count });
+    }
+    events::emit(app, AppEvent::TabCrashed(TabCrashedPayload {
+        label: label.to_string(),
+        reason: reason.to_string(),
+        url,
+    }));
+
+    let in_view = tab_window(app, label)
+        .is_some_and(|window| app.state::<UiState>().window(&window, |ui| ui.current_tab.as_deref() == Some(label)));
+    if in_view {
+        show_crash_page(app, label);
+    }
+}
+
+/// Brings a crashed background tab back as it is switched to, unless it keeps crashing.
+pub(crate) fn recover_on_switch(app: &AppHandle, label: &str) {
+    if !is_crashed(label) {
+        return;
+    }
+    if is_crash_loop(label) {
+        show_crash_page(app, label);
+    } else if let Err(e) = reload_crashed_tab(app.clone(), label.to_string()) {
+        eprintln!("Lumina: Tab {} could not be recovered: {}", label, e);
+    }
+}
+
+/// Loads a crashed tab's page again. When the whole browser process is gone the webview can't
+/// navigate any more, so the tab is rebuilt instead.
+#[tauri::command]
+pub(crate) fn reload_crashed_tab(app: AppHandle, label: String) -> Result<(), LuminaError> {
+    let tab = crashed_tabs().lock().unwrap().remove(&label)
+        .ok_or_else(|| LuminaError::NotFound(format!("Tab {} has not crashed", label)))?;
+    let url: url::Url = tab.url.parse()?;
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("Tab {} not found", label)))?;
+    if tab.reason == "browser-exited" || webview.navigate(url.clone()).is_err() {
+        tabs::rebuild_tab(&app, label, url);
+    }
+    Ok(())
+}
+
+pub(crate) fn forget(label: &str) {
+    crashed_tabs().lock().unwrap().remove(label);
+}
+
+/// Follows renderer and browser process failures through WebView2's ProcessFailed. GPU and
+/// utility processes are left out: WebView2 restarts those without the page noticing.
+#[cfg(windows)]
+pub(crate) fn watch_process_failures(app: &AppHandle, webview: &tauri::Webview) {
+    let app = app.clone();
+    let label = webview.label().to_string();
+    let _ = webview.with_webview(move |webview| {
+        use webview2_com::Microsoft::Web::WebView2::Win32::*;
+        use webview2_com::ProcessFailedEventHandler;
+        use windows_core::Interface;
+
+        let handler = ProcessFailedEventHandler::create(Box::new(move |_, args| {
+            let Some(args) = args else { return Ok(()) };
+            let mut kind = COREWEBVIEW2_PROCESS_FAILED_KIND::default();
+            unsafe { args.ProcessFailedKind(&mut kind)? };
+            let mut failure = COREWEBVIEW2_PROCESS_FAILED_REASON::default();
+            if let Ok(args) = args.cast::<ICoreWebView2ProcessFailedEventArgs2>() {
+                let _ = unsafe { args.Reason(&mut failure) };
+            }
+
+            let reason = if kind == COREWEBVIEW2_PROCESS_FAILED_KIND_RENDER_PROCESS_UNRESPONSIVE {
+                "unresponsive"
+            } else if kind != COREWEBVIEW2_PROCESS_FAILED_KIND_RENDER_PROCESS_EXITED && kind != COREWEBVIEW2_PROCESS_FAILED_KIND_BROWSER_PROCESS_EXITED {
+                return Ok(());
+            } else if failure == COREWEBVIEW2_PROCESS_FAILED_REASON_OUT_OF_MEMORY {
+                "out-of-memory"
+            } else if failure == COREWEBVIEW2_PROCESS_FAILED_REASON_TERMINATED {
+                "killed"
+            } else if kind == COREWEBVIEW2_PROCESS_FAILED_KIND_BROWSER_PROCESS_EXITED {
+                "browser-exited"
+            } else {
+                "crashed"
+            };
+            let (app, label) = (app.clone(), label.clone());
+            // Navigating from inside the handler would re-enter WebView2
+            tauri::async_runtime::spawn(async move { tab_crashed(&app, &label, reason) });
+            Ok(())
+        }));
+        unsafe {
+            if let Ok(core) = webview.controller().CoreWebView2() {
+                let mut token = 0i64;
+                let _ = core.add_ProcessFailed(&handler, &mut token);
+            }
+        }
+    });
+}
+
+/// Follows web process failures through WebKitGTK's web-process-terminated signal.
+#[cfg(target_os = "linux")]
+pub(crate) fn watch_process_failures(app: &AppHandle, webview: &tauri::Webview) {
+    let app = app.clone();
+    let label = webview.label().to_string();
+    let _ = webview.with_webview(move |webview| {
+        use webkit2gtk::{WebProcessTerminationReason, WebViewExt};
+
+        webview.inner().connect_web_process_terminated(move |_, reason| {
+            let reason = match reason {
+                WebProcessTerminationReason::ExceededMemoryLimit => "out-of-memory",
+                WebProcessTerminationReason::TerminatedByApi => "killed",
+                _ => "crashed",
+            };
+            let (app, label) = (app.clone(), label.clone());
+            tauri::async_runtime::spawn(async move { tab_crashed(&app, &label, reason) });
+        });
+    });
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub(crate) fn watch_process_failures(_app: &AppHandle, _webview: &tauri::Webview) {}
@@ -0,0 +1,212 @@
+use sha2::{Digest, Sha256};
+
+/// CRX3 signatures cover this, then the signed header data and the archive
+const SIGNED_DATA_PREFIX: &[u8] = b"CRX3 SignedData\x00";
+
+/// CrxFileHeader fields (Chromium's crx3.proto)
+const HEADER_SHA256_WITH_RSA: u64 = 2;
+const HEADER_SHA256_WITH_ECDSA: u64 = 3;
+const HEADER_SIGNED_DATA: u64 = 10000;
+
+/// A package whose signatures held.
+pub(crate) struct Crx<'a> {
+    /// Chrome's id for the extension, which comes from its developer's key
+    pub id: String,
+    /// The zip the package carries
+    pub archive: &'a [u8],
+}
+
+/// Chrome's extension id for a public key (or, for an unpacked extension without one, its path):
+/// the first 16 bytes of the SHA-256 digest, written with the letters a-p.
+pub(crate) fn extension_id(input: &[u8]) -> String {
+    id_from_hash(&Sha256::digest(input)[..16])
+}
+
+fn id_from_hash(hash: &[u8]) -> String {
+    hash.iter().flat_map(|b| [b >> 4, b & 0xf]).map(|n| (b'a' + n) as char).collect()
+}
+
+fn varint(bytes: &mut &[u8]) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or("Truncated CRX header")?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Malformed CRX header".to_string())
+}
+
+/// The length-delimited fields of a protobuf message, as (field number, bytes). Other fields are skipped.
+fn proto_fields(mut bytes: &[u8]) -> Result<Vec<(u64, &[u8])>, String> {
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let key = varint(&mut bytes)?;
+        let skip = match key & 7 {
+            0 => {
+                varint(&mut bytes)?;
+                0
+            }
+            1 => 8,
+            2 => {
+                let len = usize::try_from(varint(&mut bytes)?).map_err(|_| "Malformed CRX header")?;
+                fields.push((key >> 3, bytes.get(..len).ok_or("Truncated CRX header")?));
+                len
+            }
+            5 => 4,
+            _ => return Err("Malformed CRX header".to_string()),
+        };
+        bytes = bytes.get(skip..).ok_or("Truncated CRX header")?;
+    }
+    Ok(fields)
+}
+
+fn proto_field(bytes: &[u8], number: u64) -> Result<Option<&[u8]>, String> {
+    Ok(proto_fields(bytes)?.into_iter().find(|(field, _)| *field == number).map(|(_, value)| value))
+}
+
+/// Checks one AsymmetricKeyProof against the digest of the signed data; returns its public key.
+fn verify_proof(algorithm: u64, proof: &[u8], digest: Sha256) -> Result<&[u8], String> {
+    use p256::pkcs8::DecodePublicKey;
+    use rsa::signature::DigestVerifier;
+
+    let key = proto_field(proof, 1)?.ok_or("CRX proof has no key")?;
+    let signature = proto_field(proof, 2)?.ok_or("CRX proof has no signature")?;
+    let verified = if algorithm == HEADER_SHA256_WITH_RSA {
+        let key = rsa::RsaPublicKey::from_public_key_der(key).map_err(|_| "Malformed CRX key")?;
+        let signature = rsa::pkcs1v15::Signature::try_from(signature).map_err(|_| "Malformed CRX signature")?;
+        rsa::pkcs1v15::VerifyingKey::<Sha256>::new(key).verify_digest(digest, &signature).is_ok()
+    } else {
+        let key = p256::ecdsa::VerifyingKey::from_public_key_der(key).map_err(|_| "Malformed CRX key")?;
+        let signature = p256::ecdsa::Signature::from_der(signature).map_err(|_| "Malformed CRX signature")?;
+        key.verify_digest(digest, &signature).is_ok()
+    };
+    if !verified {
+        return Err("CRX signature does not match the package".to_string());
+    }
+    Ok(key)
+}
+
+/// Opens a .crx the way Chrome does: every signature in its header must hold, and one of them must
+/// be by the key the extension id comes from. CRX2 packages, signed with SHA-1, are refused.
+pub(crate) fn open(bytes: &[u8]) -> Result<Crx<'_>, String> {
+    let read_u32 = |offset: usize| -> Result<usize, String> {
+        bytes.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or_else(|| "Truncated CRX header".to_string())
+    };
+    if !bytes.starts_with(b"Cr24") {
+        return Err("Not a CRX file".to_string());
+    }
+    match read_u32(4)? {
+        3 => {}
+        2 => return Err("CRX2 packages are no longer supported; the extension has to be repacked".to_string()),
+        version => return Err(format!("Unsupported CRX version {}", version)),
+    }
+    let header_end = 12 + read_u32(8)?;
+    let header = bytes.get(12..header_end).ok_or("Truncated CRX file")?;
+    let archive = &bytes[header_end..];
+
+    let fields = proto_fields(header)?;
+    let signed_data = fields.iter().find(|(field, _)| *field == HEADER_SIGNED_DATA).map(|(_, value)| *value).ok_or("CRX has no signed header")?;
+    let crx_id = proto_field(signed_data, 1)?.filter(|id| id.len() == 16).ok_or("CRX has no id")?;
+
+    let mut digest = Sha256::new();
+    digest.update(SIGNED_DATA_PREFIX);
+    digest.update((signed_data.len() as u32).to_le_bytes());
+    digest.update(signed_data);
+    digest.update(archive);
+
+    let mut signed_by_developer = false;
+    for (algorithm, proof) in fields.iter().filter(|(field, _)| matches!(*field, HEADER_SHA256_WITH_RSA | HEADER_SHA256_WITH_ECDSA)) {
+        let key = verify_proof(*algorithm, proof, digest.clone())?;
+        signed_by_developer |= Sha256::digest(key)[..16] == *crx_id;
+    }
+    if !signed_by_developer {
+        return Err("CRX is not signed by the key its id comes from".to_string());
+    }
+    Ok(Crx { id: id_from_hash(crx_id), archive })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::signature::DigestSigner;
+    use p256::pkcs8::EncodePublicKey;
+
+    fn length_delimited(field: u64, value: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for mut n in [(field << 3) | 2, value.len() as u64] {
+            while n >= 0x80 {
+                out.push((n as u8) | 0x80);
+                n >>= 7;
+            }
+            out.push(n as u8);
+        }
+        out.extend_from_slice(value);
+        out
+    }
+
+    /// A CRX3 signed with a fixed P-256 key; `id_key` is the key the header's id is taken from.
+    fn signed_crx(archive: &[u8], id_key: Option<&[u8]>) -> (Vec<u8>, String) {
+        let signing = p256::ecdsa::SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let public = signing.verifying_key().to_public_key_der().unwrap();
+        let id_hash = Sha256::digest(id_key.unwrap_or(public.as_bytes()));
+        let signed_data = length_delimited(1, &id_hash[..16]);
+
+        let mut digest = Sha256::new();
+        digest.update(SIGNED_DATA_PREFIX);
+        digest.update((signed_data.len() as u32).to_le_bytes());
+        digest.update(&signed_data);
+        digest.update(archive);
+        let signature: p256::ecdsa::Signature = signing.sign_digest(digest);
+
+        let mut proof = length_delimited(1, public.as_bytes());
+        proof.extend(length_delimited(2, signature.to_der().as_bytes()));
+        let mut header = length_delimited(HEADER_SHA256_WITH_ECDSA, &proof);
+        header.extend(length_delimited(HEADER_SIGNED_DATA, &signed_data));
+
+        let mut crx = b"Cr24".to_vec();
+        crx.extend_from_slice(&3u32.to_le_bytes());
+        crx.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        crx.extend(header);
+        crx.extend_from_slice(archive);
+        (crx, id_from_hash(&id_hash[..16]))
+    }
+
+    #[test]
+    fn signed_packages_open_with_their_id() {
+        let (crx, id) = signed_crx(b"PK\x03\x04rest", None);
+        let opened = open(&crx).unwrap();
+        assert_eq!(opened.archive, b"PK\x03\x04rest");
+        assert_eq!(opened.id, id);
+        assert!(opened.id.len() == 32 && opened.id.chars().all(|c| ('a'..='p').contains(&c)));
+    }
+
+    #[test]
+    fn tampered_packages_are_refused() {
+        let (mut crx, _) = signed_crx(b"PK\x03\x04rest", None);
+        *crx.last_mut().unwrap() ^= 1;
+        assert!(open(&crx).is_err());
+    }
+
+    #[test]
+    fn packages_not_signed_by_their_id_key_are_refused() {
+        let (crx, _) = signed_crx(b"PK\x03\x04rest", Some(b"someone else"));
+        assert!(open(&crx).is_err());
+    }
+
+    #[test]
+    fn broken_and_old_crx_files_are_refused() {
+        assert!(open(b"PK\x03\x04rest").is_err());
+        assert!(open(b"Cr24\x03\x00").is_err());
+        assert!(open(b"Cr24\x02\x00\x00\x00\x00\x00\x00\x00").is_err());
+        assert!(open(b"Cr24\x04\x00\x00\x00\x00\x00\x00\x00").is_err());
+        let mut truncated = b"Cr24".to_vec();
+        truncated.extend_from_slice(&3u32.to_le_bytes());
+        truncated.extend_from_slice(&100u32.to_le_bytes());
+        assert!(open(&truncated).is_err());
+    }
+}
@@ -1,8 +1,31 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use rusqlite::{params, Connection};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
+use crate::history_manager::{canonicalize_url, new_id};
+
+/// Replaces `path` with `contents` without ever leaving a half-written file: the data goes to a
+/// temp file next to it, is fsynced, then renamed over the target.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    let result = (|| {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct HistoryItem {
     pub url: String,
@@ -12,29 +35,340 @@ pub struct HistoryItem {
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct FavoriteItem {
+    /// Stable id; the URL can change (redirect fixes, canonicalization) without losing the favorite
+    #[serde(default = "new_id")]
+    pub id: String,
     pub url: String,
     pub title: String,
+    /// Omnibox shortcut; `%s` in the URL is replaced with the text typed after it
+    #[serde(default)]
+    pub keyword: Option<String>,
+    /// Result of the last dead-link check
+    #[serde(default)]
+    pub health: Option<FavoriteHealth>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FavoriteHealth {
+    /// HTTP status of the final response, 0 if the host could not be reached
+    pub status: u16,
+    /// Where the URL ended up, if it redirected elsewhere
+    pub redirect: Option<String>,
+    pub checked_at: i64,
+}
+
+/// A `protocol_handlers` entry from a web app manifest; `url` contains `%s`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PwaProtocolHandler {
+    pub protocol: String,
+    pub url: String,
+}
+
+/// A `file_handlers` entry from a web app manifest.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PwaFileHandler {
+    pub action: String,
+    /// Lowercase, with the leading dot
+    pub extensions: Vec<String>,
+    pub mime_types: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledPwa {
+    /// Window label, also used for OS registrations
+    pub id: String,
+    pub start_url: String,
+    pub title: String,
+    #[serde(default)]
+    pub protocol_handlers: Vec<PwaProtocolHandler>,
+    #[serde(default)]
+    pub file_handlers: Vec<PwaFileHandler>,
+    /// The user agreed to register the handlers with the OS
+    #[serde(default)]
+    pub handlers_registered: bool,
+    /// Where the manifest was found, so it can be re-read for updates
+    #[serde(default)]
+    pub manifest_url: Option<String>,
+    #[serde(default)]
+    pub icon_url: Option<String>,
+    #[serde(default)]
+    pub icon_path: Option<String>,
+    #[serde(default)]
+    pub manifest_checked_at: i64,
+    /// Manifest `theme_color`/`background_color`, applied to the window frame
+    #[serde(default)]
+    pub theme_color: Option<String>,
+    #[serde(default)]
+    pub background_color: Option<String>,
+    /// Installed with its own cookie/storage directory instead of the browser profile
+    #[serde(default)]
+    pub isolated: bool,
+    /// May ask for notifications (the site's own permission still applies)
+    #[serde(default = "default_true")]
+    pub notifications: bool,
+    /// May register service workers
+    #[serde(default = "default_true")]
+    pub service_workers: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupBehavior {
+    #[default]
+    Homepage,
+    NewTab,
+    RestoreSession,
+    Urls,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SessionTab {
+    pub label: String,
+    pub url: String,
+}
+
+/// Experimental features toggled from lumina-app://flags
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Flags {
+    pub aggressive_cosmetic_filtering: bool,
+    pub tab_hibernation: bool,
+    pub fingerprint_protection: bool,
+    pub site_isolation: bool,
+}
+
+pub struct FlagInfo {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+pub const FLAG_INFO: &[FlagInfo] = &[
+    FlagInfo {
+        id: "aggressive_cosmetic_filtering",
+        name: "Aggressive Cosmetic Filtering",
+        description: "Hides common ad containers and sponsored blocks on every page, even when no network request was blocked. May hide legitimate content.",
+    },
+    FlagInfo {
+        id: "tab_hibernation",
+        name: "Tab Hibernation",
+        description: "Unloads tabs that have been in the background for a while to save memory. They reload when you switch back.",
+    },
+    FlagInfo {
+        id: "fingerprint_protection",
+        name: "Fingerprint Protection",
+        description: "Adds noise to canvas readback and reports generic hardware values to make cross-site fingerprinting harder. Applies to new tabs.",
+    },
+    FlagInfo {
+        id: "site_isolation",
+        name: "Site Isolation",
+        description: "Gives every site (e.g. all of *.google.com) its own cookies, storage and cache, so one site cannot read another's data. Each site group runs its own browser engine processes, which uses noticeably more memory, and you have to sign in separately per site. Tabs moving to another site reopen and lose their back history. Applies to new navigations; not supported on macOS.",
+    },
+];
+
+impl Flags {
+    pub fn get(&self, id: &str) -> Option<bool> {
+        match id {
+            "aggressive_cosmetic_filtering" => Some(self.aggressive_cosmetic_filtering),
+            "tab_hibernation" => Some(self.tab_hibernation),
+            "fingerprint_protection" => Some(self.fingerprint_protection),
+            "site_isolation" => Some(self.site_isolation),
+            _ => None,
+        }
+    }
+
+    /// Returns false if `id` is not a known flag.
+    pub fn set(&mut self, id: &str, enabled: bool) -> bool {
+        let flag = match id {
+            "aggressive_cosmetic_filtering" => &mut self.aggressive_cosmetic_filtering,
+            "tab_hibernation" => &mut self.tab_hibernation,
+            "fingerprint_protection" => &mut self.fingerprint_protection,
+            "site_isolation" => &mut self.site_isolation,
+            _ => return false,
+        };
+        *flag = enabled;
+        true
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct AppSettings {
     pub homepage: String,
+    #[serde(default = "default_true")]
+    pub show_home_button: bool,
     pub search_engine: String, // "google", "bing", "duckduckgo"
-    pub theme: String, // "dark", "light", "system"
+    pub theme: String, // "dark", "light", "system", "scheduled"
     pub accent_color: String, // Hex color e.g., "#3b82f6"
     pub vertical_tabs: bool,
     pub rounded_corners: bool,
+    #[serde(default = "default_true")]
+    pub adblock_enabled: bool,
+    #[serde(default)]
+    pub startup_behavior: StartupBehavior,
+    #[serde(default)]
+    pub startup_urls: Vec<String>,
+    #[serde(default = "default_new_tab_page")]
+    pub new_tab_page: String,
+    #[serde(default)]
+    pub flags: Flags,
+    #[serde(default = "default_true")]
+    pub download_notifications: bool,
+    #[serde(default = "default_true")]
+    pub scan_downloads: bool,
+    #[serde(default)]
+    pub auto_extract_archives: bool,
+    /// Block pop-ups not opened by a user gesture, unless the origin is on the allow list
+    #[serde(default = "default_true")]
+    pub block_popups: bool,
+    /// Skip known redirect/link-shim hops and navigate straight to their destination
+    #[serde(default = "default_true")]
+    pub unwrap_redirects: bool,
+    /// Blocked images, scripts, styles and frames get an empty stand-in instead of a failed request
+    #[serde(default = "default_true")]
+    pub adblock_placeholders: bool,
+    #[serde(default = "default_max_concurrent_downloads")]
+    pub max_concurrent_downloads: usize,
+    /// External scheme -> "allow", "deny" or "ask"
+    #[serde(default = "default_protocol_handlers")]
+    pub protocol_handlers: BTreeMap<String, String>,
+    /// Folder names under app_data/extensions that are installed but not loaded
+    #[serde(default)]
+    pub disabled_extensions: Vec<String>,
+    /// Site fixup ids that stay installed but aren't injected
+    #[serde(default)]
+    pub disabled_fixups: Vec<String>,
+    /// "HH:MM" local times the "scheduled" theme switches to light and to dark
+    #[serde(default = "default_theme_light_at")]
+    pub theme_light_at: String,
+    #[serde(default = "default_theme_dark_at")]
+    pub theme_dark_at: String,
+    /// Read-aloud speed as a multiple of the voice's normal rate
+    #[serde(default = "default_read_aloud_rate")]
+    pub read_aloud_rate: f32,
+    /// Voice id for read aloud; None uses the system default voice
+    #[serde(default)]
+    pub read_aloud_voice: Option<String>,
+    #[serde(default = "default_true")]
+    pub spellcheck_enabled: bool,
+    /// BCP 47 tags of the spelling dictionaries to use; empty follows the system languages
+    #[serde(default)]
+    pub spellcheck_languages: Vec<String>,
+    /// BCP 47 tags sent as Accept-Language and used for search results, most preferred first;
+    /// empty follows the system language
+    #[serde(default)]
+    pub accept_languages: Vec<String>,
+    /// HTTP proxy for the browser's own requests; None follows the environment
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    /// Domains where spell checking is off even when it's enabled globally
+    #[serde(default)]
+    pub spellcheck_disabled_sites: Vec<String>,
+    /// Domains where filling forms from an autofill profile is turned off
+    #[serde(default)]
+    pub autofill_disabled_sites: Vec<String>,
+    /// Sites never recorded in history. A plain domain covers its subdomains; `*` matches any
+    /// run of characters, e.g. "*.bank.com" or "health*"
+    #[serde(default)]
+    pub history_excluded_sites: Vec<String>,
+    /// Sites whose cookies and storage are deleted once their last tab closes
+    #[serde(default)]
+    pub forget_on_close_sites: Vec<String>,
+    /// Folders whose files may open in tabs as file:// pages
+    #[serde(default)]
+    pub local_file_folders: Vec<String>,
+    /// Off: no file:// pages and no folder view
+    #[serde(default = "default_true")]
+    pub local_file_access: bool,
+    /// Percent; sites with a zoom saved in zoom_levels keep theirs
+    #[serde(default = "default_zoom")]
+    pub default_zoom: i32,
+    /// How much of the page URL requests send as Referer: "full", "origin" or "none".
+    /// Sites in referrer_policies override it.
+    #[serde(default = "default_referrer_policy")]
+    pub referrer_policy: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_protocol_handlers() -> BTreeMap<String, String> {
+    ["magnet", "mailto"].iter().map(|s| (s.to_string(), "ask".to_string())).collect()
+}
+
+fn default_theme_light_at() -> String {
+    "07:00".to_string()
+}
+
+fn default_theme_dark_at() -> String {
+    "19:00".to_string()
+}
+
+fn default_read_aloud_rate() -> f32 {
+    1.0
+}
+
+fn default_max_concurrent_downloads() -> usize {
+    3
+}
+
+fn default_zoom() -> i32 {
+    100
+}
+
+fn default_referrer_policy() -> String {
+    "full".to_string()
+}
+
+fn default_new_tab_page() -> String {
+    "lumina-app://newtab".to_string()
 }
 
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
             homepage: "https://www.google.com".to_string(),
+            show_home_button: true,
             search_engine: "google".to_string(),
             theme: "dark".to_string(),
             accent_color: "#3b82f6".to_string(),
             vertical_tabs: false,
             rounded_corners: true,
+            adblock_enabled: true,
+            startup_behavior: StartupBehavior::default(),
+            startup_urls: Vec::new(),
+            new_tab_page: default_new_tab_page(),
+            flags: Flags::default(),
+            download_notifications: true,
+            scan_downloads: true,
+            auto_extract_archives: false,
+            block_popups: true,
+            unwrap_redirects: true,
+            adblock_placeholders: true,
+            max_concurrent_downloads: default_max_concurrent_downloads(),
+            protocol_handlers: default_protocol_handlers(),
+            disabled_extensions: Vec::new(),
+            disabled_fixups: Vec::new(),
+            theme_light_at: default_theme_light_at(),
+            theme_dark_at: default_theme_dark_at(),
+            read_aloud_rate: default_read_aloud_rate(),
+            read_aloud_voice: None,
+            spellcheck_enabled: true,
+            spellcheck_languages: Vec::new(),
+            spellcheck_disabled_sites: Vec::new(),
+            accept_languages: Vec::new(),
+            proxy_url: None,
+            autofill_disabled_sites: Vec::new(),
+            history_excluded_sites: Vec::new(),
+            forget_on_close_sites: Vec::new(),
+            local_file_folders: Vec::new(),
+            local_file_access: true,
+            default_zoom: default_zoom(),
+            referrer_policy: default_referrer_policy(),
         }
     }
 }
@@ -45,36 +379,311 @@ pub struct AppData {
     pub favorites: Vec<FavoriteItem>,
     #[serde(default)]
     pub settings: AppSettings,
+    /// Tabs open in the current run, kept up to date for "restore session"
+    #[serde(default)]
+    pub last_session: Vec<SessionTab>,
+    #[serde(default)]
+    pub installed_pwas: Vec<InstalledPwa>,
 }
 
 pub struct AppDataStore {
     pub data: Mutex<AppData>,
-    pub file_path: PathBuf,
+    /// Shared with HistoryManager; favorites, settings and session live in their own tables
+    db_path: PathBuf,
+    /// Kept open between saves; `None` until it could be opened
+    conn: Mutex<Option<Connection>>,
+    /// Tabs that were open when the previous run ended
+    pub previous_session: Vec<SessionTab>,
+}
+
+/// `*` matches any run of characters, including none; everything else matches itself.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else { return rest.is_empty() };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
 }
 
 impl AppDataStore {
     pub fn new(app_dir: PathBuf) -> Self {
-        let file_path = app_dir.join("browser_data.json");
-        let data = if file_path.exists() {
-            let content = fs::read_to_string(&file_path).unwrap_or_default();
-            serde_json::from_str(&content).unwrap_or_default()
-        } else {
-            AppData::default()
+        let db_path = app_dir.join("history.db");
+        let mut conn = match Self::open(&db_path) {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                eprintln!("Failed to open browser data: {}", e);
+                None
+            }
+        };
+        let mut data = match conn.as_ref().map(Self::load) {
+            Some(Ok(data)) => data,
+            Some(Err(e)) => {
+                eprintln!("Failed to load browser data: {}", e);
+                AppData::default()
+            }
+            None => AppData::default(),
         };
 
+        // One-time import of the old browser_data.json
+        let legacy_path = app_dir.join("browser_data.json");
+        if let Some(conn) = conn.as_mut().filter(|_| legacy_path.exists()) {
+            match Self::migrate_legacy_json(conn, &legacy_path) {
+                Ok(legacy) => data = legacy,
+                Err(e) => eprintln!("Failed to migrate {}: {}", legacy_path.display(), e),
+            }
+        }
+
+        if let Some(Err(e)) = conn.as_mut().map(|conn| Self::migrate_pwa_settings(conn, &mut data)) {
+            eprintln!("Failed to migrate app settings: {}", e);
+        }
+
+        if Self::migrate_favorite_ids(&mut data.favorites) {
+            if let Some(Err(e)) = conn.as_mut().map(|conn| Self::write(conn, &data)) {
+                eprintln!("Failed to save migrated favorites: {}", e);
+            }
+        }
+
+        let previous_session = std::mem::take(&mut data.last_session);
+
         Self {
             data: Mutex::new(data),
-            file_path,
+            db_path,
+            conn: Mutex::new(conn),
+            previous_session,
         }
     }
 
+    fn connect(db_path: &Path) -> rusqlite::Result<Connection> {
+        let conn = Connection::open(db_path)?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        Ok(conn)
+    }
+
+    fn open(db_path: &Path) -> rusqlite::Result<Connection> {
+        let conn = Self::connect(db_path)?;
+        Self::init(&conn)?;
+        Ok(conn)
+    }
+
+    fn init(conn: &Connection) -> rusqlite::Result<()> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS favorites (
+                url TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                keyword TEXT,
+                health TEXT,
+                position INTEGER NOT NULL,
+                id TEXT
+            );
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS app_meta (
+                key TEXT PRIMARY KEY,
+                value TEXT
+            );",
+        )?;
+        // Databases from before favorites had ids
+        let has_id = conn.prepare("SELECT 1 FROM pragma_table_info('favorites') WHERE name = 'id'")?.exists([])?;
+        if !has_id {
+            conn.execute("ALTER TABLE favorites ADD COLUMN id TEXT", [])?;
+        }
+        Ok(())
+    }
+
+    fn load(conn: &Connection) -> rusqlite::Result<AppData> {
+        let mut stmt = conn.prepare("SELECT url, title, keyword, health, id FROM favorites ORDER BY position")?;
+        let favorites = stmt
+            .query_map([], |row| {
+                let health: Option<String> = row.get(3)?;
+                Ok(FavoriteItem {
+                    id: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                    url: row.get(0)?,
+                    title: row.get(1)?,
+                    keyword: row.get(2)?,
+                    health: health.and_then(|h| serde_json::from_str(&h).ok()),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // Stored keys are laid over the defaults, so fields added later still deserialize
+        let mut settings = serde_json::to_value(AppSettings::default()).unwrap_or_default();
+        let mut stmt = conn.prepare("SELECT key, value FROM settings")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        for (key, value) in rows.flatten() {
+            if let (Some(map), Ok(value)) = (settings.as_object_mut(), serde_json::from_str(&value)) {
+                map.insert(key, value);
+            }
+        }
+        let settings = serde_json::from_value(settings).unwrap_or_default();
+
+        let meta = |key: &str| -> Option<String> {
+            conn.query_row("SELECT value FROM app_meta WHERE key = ?1", params![key], |row| row.get(0)).ok()
+        };
+        let history = meta("recent_history").and_then(|v| serde_json::from_str(&v).ok()).unwrap_or_default();
+        let last_session = meta("last_session").and_then(|v| serde_json::from_str(&v).ok()).unwrap_or_default();
+        let installed_pwas = meta("installed_pwas").and_then(|v| serde_json::from_str(&v).ok()).unwrap_or_default();
+
+        Ok(AppData { history, favorites, settings, last_session, installed_pwas })
+    }
+
+    /// Writes everything in one transaction, so a failed write leaves the previous state intact.
+    fn write(conn: &mut Connection, data: &AppData) -> Result<(), String> {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        Self::write_rows(&tx, data)?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Brings the tables in line with `data` row by row: favorites that are gone are deleted, and
+    /// only rows that differ from what is stored are written.
+    fn write_rows(tx: &rusqlite::Transaction, data: &AppData) -> Result<(), String> {
+        let urls: std::collections::HashSet<&str> = data.favorites.iter().map(|f| f.url.as_str()).collect();
+        let stored: Vec<String> = tx.prepare_cached("SELECT url FROM favorites")
+            .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+            .map_err(|e| e.to_string())?;
+        let mut delete = tx.prepare_cached("DELETE FROM favorites WHERE url = ?1").map_err(|e| e.to_string())?;
+        for url in stored.iter().filter(|url| !urls.contains(url.as_str())) {
+            delete.execute(params![url]).map_err(|e| e.to_string())?;
+        }
+
+        let mut upsert_favorite = tx.prepare_cached(
+            "INSERT INTO favorites (url, title, keyword, health, position, id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(url) DO UPDATE SET title = excluded.title, keyword = excluded.keyword, health = excluded.health,
+                 position = excluded.position, id = excluded.id
+             WHERE title IS NOT excluded.title OR keyword IS NOT excluded.keyword OR health IS NOT excluded.health
+                 OR position IS NOT excluded.position OR id IS NOT excluded.id",
+        )
+        .map_err(|e| e.to_string())?;
+        for (position, favorite) in data.favorites.iter().enumerate() {
+            let health = favorite.health.as_ref().and_then(|h| serde_json::to_string(h).ok());
+            upsert_favorite
+                .execute(params![favorite.url, favorite.title, favorite.keyword, health, position as i64, favorite.id])
+                .map_err(|e| e.to_string())?;
+        }
+
+        let mut upsert_setting = tx.prepare_cached(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value WHERE value IS NOT excluded.value",
+        )
+        .map_err(|e| e.to_string())?;
+        let settings = serde_json::to_value(&data.settings).map_err(|e| e.to_string())?;
+        for (key, value) in settings.as_object().into_iter().flatten() {
+            upsert_setting.execute(params![key, value.to_string()]).map_err(|e| e.to_string())?;
+        }
+
+        let mut upsert_meta = tx.prepare_cached(
+            "INSERT INTO app_meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value WHERE value IS NOT excluded.value",
+        )
+        .map_err(|e| e.to_string())?;
+        for (key, value) in [
+            ("recent_history", serde_json::to_string(&data.history)),
+            ("last_session", serde_json::to_string(&data.last_session)),
+            ("installed_pwas", serde_json::to_string(&data.installed_pwas)),
+        ] {
+            upsert_meta.execute(params![key, value.map_err(|e| e.to_string())?]).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// The notification and service worker switches used to cover every installed app. Whatever they
+    /// said moves onto each app, in the same transaction that drops them.
+    fn migrate_pwa_settings(conn: &mut Connection, data: &mut AppData) -> Result<(), String> {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let legacy: Vec<(String, String)> = tx.prepare("SELECT key, value FROM settings WHERE key IN ('pwa_notifications', 'pwa_service_workers')")
+            .and_then(|mut stmt| stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect())
+            .map_err(|e| e.to_string())?;
+        if legacy.is_empty() {
+            return Ok(());
+        }
+        for (key, _) in legacy.iter().filter(|(_, value)| value == "false") {
+            for pwa in &mut data.installed_pwas {
+                match key.as_str() {
+                    "pwa_notifications" => pwa.notifications = false,
+                    _ => pwa.service_workers = false,
+                }
+            }
+        }
+        Self::write_rows(&tx, data)?;
+        tx.execute("DELETE FROM settings WHERE key IN ('pwa_notifications', 'pwa_service_workers')", [])
+            .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Gives favorites without an id one and canonicalizes their URLs, dropping any that turn out to
+    /// duplicate an earlier favorite. Returns whether anything changed.
+    fn migrate_favorite_ids(favorites: &mut Vec<FavoriteItem>) -> bool {
+        let mut changed = false;
+        let mut seen = std::collections::HashSet::new();
+        favorites.retain_mut(|favorite| {
+            if favorite.id.is_empty() {
+                favorite.id = new_id();
+                changed = true;
+            }
+            let canonical = canonicalize_url(&favorite.url);
+            if canonical != favorite.url {
+                favorite.url = canonical;
+                changed = true;
+            }
+            let keep = seen.insert(favorite.url.clone());
+            changed |= !keep;
+            keep
+        });
+        changed
+    }
+
+    /// Imports browser_data.json into the database and removes it. The file is left alone if
+    /// it can't be parsed or written, so nothing is lost.
+    fn migrate_legacy_json(conn: &mut Connection, legacy_path: &Path) -> Result<AppData, String> {
+        let migrated: Option<String> = conn
+            .query_row("SELECT value FROM app_meta WHERE key = 'legacy_json_migrated'", [], |row| row.get(0))
+            .ok();
+        if migrated.is_some() {
+            // Imported before but the file couldn't be removed; the database is newer
+            fs::remove_file(legacy_path).map_err(|e| e.to_string())?;
+            return Self::load(conn).map_err(|e| e.to_string());
+        }
+
+        let content = fs::read_to_string(legacy_path).map_err(|e| e.to_string())?;
+        let data: AppData = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+        // Marked in the same transaction as the import, so the file is never imported twice
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        Self::write_rows(&tx, &data)?;
+        tx.execute("INSERT OR REPLACE INTO app_meta (key, value) VALUES ('legacy_json_migrated', '1')", [])
+            .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        fs::remove_file(legacy_path).map_err(|e| e.to_string())?;
+        println!("Migrated {} to SQLite", legacy_path.display());
+        Ok(data)
+    }
+
     pub fn save(&self) {
+        if let Err(e) = self.try_save() {
+            eprintln!("Failed to save browser data: {}", e);
+        }
+    }
+
+    /// Like `save`, for callers that pass the failure on instead of logging it.
+    pub fn try_save(&self) -> Result<(), String> {
         let data = self.data.lock().unwrap();
-        let content = serde_json::to_string_pretty(&*data).unwrap();
-        let _ = fs::write(&self.file_path, content);
+        let mut conn = self.conn.lock().unwrap();
+        if conn.is_none() {
+            *conn = Some(Self::open(&self.db_path).map_err(|e| e.to_string())?);
+        }
+        let conn = conn.as_mut().ok_or("Browser data is not open")?;
+        Self::write(conn, &data)
     }
 
     pub fn add_history(&self, url: String, title: String) {
+        let url = canonicalize_url(&url);
         let mut data = self.data.lock().unwrap();
         // Remove duplicate if exists (simple logic: move to top)
         if let Some(pos) = data.history.iter().position(|x| x.url == url) {
@@ -94,19 +703,110 @@ impl AppDataStore {
     }
 
     pub fn add_favorite(&self, url: String, title: String) {
+        let url = canonicalize_url(&url);
         let mut data = self.data.lock().unwrap();
         if !data.favorites.iter().any(|x| x.url == url) {
-            data.favorites.push(FavoriteItem { url, title });
+            data.favorites.push(FavoriteItem { id: new_id(), url, title, keyword: None, health: None });
         }
     }
 
-    pub fn remove_favorite(&self, url: String) {
+    pub fn is_favorite(&self, url: &str) -> bool {
+        let url = canonicalize_url(url);
+        self.data.lock().unwrap().favorites.iter().any(|f| f.url == url)
+    }
+
+    /// Returns the removed favorite and where it was, for undo.
+    pub fn remove_favorite(&self, url: String) -> Option<(usize, FavoriteItem)> {
+        let url = canonicalize_url(&url);
         let mut data = self.data.lock().unwrap();
-        if let Some(pos) = data.favorites.iter().position(|x| x.url == url) {
-            data.favorites.remove(pos);
+        let pos = data.favorites.iter().position(|x| x.url == url)?;
+        Some((pos, data.favorites.remove(pos)))
+    }
+
+    /// Puts removed favorites back at their old positions, skipping ones added again since.
+    pub fn restore_favorites(&self, items: Vec<(usize, FavoriteItem)>) {
+        let mut data = self.data.lock().unwrap();
+        for (pos, item) in items {
+            if !data.favorites.iter().any(|f| f.url == item.url) {
+                let pos = pos.min(data.favorites.len());
+                data.favorites.insert(pos, item);
+            }
         }
     }
     
+    pub fn set_favorite_health(&self, url: &str, health: FavoriteHealth) {
+        let mut data = self.data.lock().unwrap();
+        if let Some(favorite) = data.favorites.iter_mut().find(|f| f.url == url) {
+            favorite.health = Some(health);
+        }
+    }
+
+    /// Points a favorite at a new URL, e.g. where it now redirects. Fails if the new URL is already a favorite.
+    pub fn update_favorite_url(&self, url: &str, new_url: String) -> bool {
+        let (url, new_url) = (canonicalize_url(url), canonicalize_url(&new_url));
+        let mut data = self.data.lock().unwrap();
+        if data.favorites.iter().any(|f| f.url == new_url) {
+            return false;
+        }
+        match data.favorites.iter_mut().find(|f| f.url == url) {
+            Some(favorite) => {
+                favorite.url = new_url;
+                favorite.health = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets or clears the keyword of a favorite. A keyword belongs to one favorite at a time.
+    pub fn set_favorite_keyword(&self, url: &str, keyword: Option<String>) -> bool {
+        let keyword = keyword
+            .map(|k| k.trim().to_lowercase())
+            .filter(|k| !k.is_empty() && !k.contains(char::is_whitespace));
+        let url = canonicalize_url(url);
+        let mut data = self.data.lock().unwrap();
+        if !data.favorites.iter().any(|f| f.url == url) {
+            return false;
+        }
+        for favorite in data.favorites.iter_mut() {
+            if favorite.url == url {
+                favorite.keyword = keyword.clone();
+            } else if keyword.is_some() && favorite.keyword == keyword {
+                favorite.keyword = None;
+            }
+        }
+        true
+    }
+
+    /// Expands "gh rust" into the URL of the favorite with keyword "gh", filling `%s` with "rust".
+    pub fn expand_keyword(&self, input: &str) -> Option<String> {
+        let input = input.trim();
+        let (keyword, rest) = match input.split_once(char::is_whitespace) {
+            Some((keyword, rest)) => (keyword, rest.trim()),
+            None => (input, ""),
+        };
+        let keyword = keyword.to_lowercase();
+        let data = self.data.lock().unwrap();
+        let favorite = data.favorites.iter().find(|f| f.keyword.as_deref() == Some(keyword.as_str()))?;
+        Some(favorite.url.replace("%s", &urlencoding::encode(rest)))
+    }
+
+    /// Whether `url` is on a site history must not record. Patterns are tried on the host as it is
+    /// and without "www.", so `*.bank.com` covers www.bank.com and `bank.com` covers both.
+    pub fn is_history_excluded(&self, url: &str) -> bool {
+        let Some(host) = url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_lowercase)) else {
+            return false;
+        };
+        let hosts = [host.as_str(), host.trim_start_matches("www.")];
+        let data = self.data.lock().unwrap();
+        data.settings.history_excluded_sites.iter().any(|pattern| {
+            hosts.iter().any(|host| match pattern.contains('*') {
+                true => wildcard_match(pattern, host),
+                false => *host == pattern || host.ends_with(&format!(".{}", pattern)),
+            })
+        })
+    }
+
     pub fn update_settings(&self, homepage: String, search_engine: String, theme: String, accent_color: String, vertical_tabs: bool, rounded_corners: bool) {
         let mut data = self.data.lock().unwrap();
         data.settings.homepage = homepage;
@@ -116,4 +816,174 @@ impl AppDataStore {
         data.settings.vertical_tabs = vertical_tabs;
         data.settings.rounded_corners = rounded_corners;
     }
+
+    pub fn flags(&self) -> Flags {
+        self.data.lock().unwrap().settings.flags.clone()
+    }
+
+    pub fn set_flag(&self, id: &str, enabled: bool) -> bool {
+        self.data.lock().unwrap().settings.flags.set(id, enabled)
+    }
+
+    pub fn reset_flags(&self) {
+        self.data.lock().unwrap().settings.flags = Flags::default();
+    }
+
+    pub fn set_new_tab_page(&self, url: String) {
+        let mut data = self.data.lock().unwrap();
+        data.settings.new_tab_page = if url.trim().is_empty() { default_new_tab_page() } else { url.trim().to_string() };
+    }
+
+    pub fn update_startup(&self, behavior: StartupBehavior, urls: Vec<String>) {
+        let mut data = self.data.lock().unwrap();
+        data.settings.startup_behavior = behavior;
+        data.settings.startup_urls = urls;
+    }
+
+    /// Stored action for an external scheme; unknown schemes ask.
+    pub fn protocol_action(&self, scheme: &str) -> String {
+        let data = self.data.lock().unwrap();
+        data.settings.protocol_handlers.get(scheme).cloned().unwrap_or_else(|| "ask".to_string())
+    }
+
+    pub fn set_protocol_action(&self, scheme: String, action: String) {
+        let mut data = self.data.lock().unwrap();
+        data.settings.protocol_handlers.insert(scheme, action);
+    }
+
+    pub fn remove_protocol_action(&self, scheme: &str) {
+        let mut data = self.data.lock().unwrap();
+        data.settings.protocol_handlers.remove(scheme);
+    }
+
+    pub fn set_extension_enabled(&self, id: &str, enabled: bool) {
+        let mut data = self.data.lock().unwrap();
+        let disabled = &mut data.settings.disabled_extensions;
+        disabled.retain(|d| d != id);
+        if !enabled {
+            disabled.push(id.to_string());
+        }
+    }
+
+    pub fn is_extension_enabled(&self, id: &str) -> bool {
+        !self.data.lock().unwrap().settings.disabled_extensions.iter().any(|d| d == id)
+    }
+
+    pub fn set_fixup_enabled(&self, id: &str, enabled: bool) {
+        let mut data = self.data.lock().unwrap();
+        let disabled = &mut data.settings.disabled_fixups;
+        disabled.retain(|d| d != id);
+        if !enabled {
+            disabled.push(id.to_string());
+        }
+    }
+
+    pub fn is_fixup_enabled(&self, id: &str) -> bool {
+        !self.data.lock().unwrap().settings.disabled_fixups.iter().any(|d| d == id)
+    }
+
+    /// Records an installed app, replacing an earlier install with the same id.
+    pub fn upsert_installed_pwa(&self, pwa: InstalledPwa) {
+        let mut data = self.data.lock().unwrap();
+        data.installed_pwas.retain(|p| p.id != pwa.id);
+        data.installed_pwas.push(pwa);
+    }
+
+    pub fn installed_pwas(&self) -> Vec<InstalledPwa> {
+        self.data.lock().unwrap().installed_pwas.clone()
+    }
+
+    pub fn installed_pwa(&self, id: &str) -> Option<InstalledPwa> {
+        self.data.lock().unwrap().installed_pwas.iter().find(|p| p.id == id).cloned()
+    }
+
+    pub fn installed_pwa_by_start_url(&self, start_url: &str) -> Option<InstalledPwa> {
+        self.data.lock().unwrap().installed_pwas.iter().find(|p| p.start_url == start_url).cloned()
+    }
+
+    pub fn set_pwa_handlers_registered(&self, id: &str, registered: bool) {
+        if let Some(pwa) = self.data.lock().unwrap().installed_pwas.iter_mut().find(|p| p.id == id) {
+            pwa.handlers_registered = registered;
+        }
+    }
+
+    pub fn set_session(&self, tabs: Vec<SessionTab>) {
+        self.data.lock().unwrap().last_session = tabs;
+    }
+
+    /// URLs the first window should open with, according to `startup_behavior`.
+    pub fn startup_urls(&self) -> Vec<String> {
+        let data = self.data.lock().unwrap();
+        let settings = &data.settings;
+        let urls = match settings.startup_behavior {
+            StartupBehavior::Homepage => vec![settings.homepage.clone()],
+            StartupBehavior::NewTab => vec![settings.new_tab_page.clone()],
+            StartupBehavior::RestoreSession => self.previous_session.iter().map(|t| t.url.clone()).collect(),
+            StartupBehavior::Urls => settings.startup_urls.clone(),
+        };
+
+        if urls.is_empty() {
+            vec![settings.homepage.clone()]
+        } else {
+            urls
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_the_whole_text() {
+        assert!(wildcard_match("*.bank.com", "www.bank.com"));
+        assert!(!wildcard_match("*.bank.com", "bank.com"));
+        assert!(wildcard_match("mail.*.com", "mail.example.com"));
+        assert!(!wildcard_match("mail.*", "webmail.example.com"));
+        assert!(wildcard_match("*", "anything"));
+    }
+
+    fn favorite(url: &str) -> FavoriteItem {
+        FavoriteItem { id: new_id(), url: url.to_string(), title: url.to_string(), keyword: None, health: None }
+    }
+
+    #[test]
+    fn saves_update_favorites_in_place() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        AppDataStore::init(&conn).unwrap();
+        let mut data = AppData {
+            favorites: vec![favorite("https://a.test/"), favorite("https://b.test/"), favorite("https://c.test/")],
+            ..Default::default()
+        };
+        AppDataStore::write(&mut conn, &data).unwrap();
+
+        data.favorites.remove(1);
+        data.favorites.swap(0, 1);
+        data.favorites[0].title = "Renamed".to_string();
+        AppDataStore::write(&mut conn, &data).unwrap();
+
+        let loaded = AppDataStore::load(&conn).unwrap();
+        let urls: Vec<&str> = loaded.favorites.iter().map(|f| f.url.as_str()).collect();
+        assert_eq!(urls, ["https://c.test/", "https://a.test/"]);
+        assert_eq!(loaded.favorites[0].title, "Renamed");
+        assert_eq!(loaded.favorites[1].id, data.favorites[1].id);
+    }
+
+    #[test]
+    fn app_wide_pwa_switches_move_onto_each_app() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        AppDataStore::init(&conn).unwrap();
+        let pwa: InstalledPwa = serde_json::from_value(serde_json::json!({ "id": "app", "startUrl": "https://app.test/", "title": "App" })).unwrap();
+        let mut data = AppData { installed_pwas: vec![pwa], ..Default::default() };
+        AppDataStore::write(&mut conn, &data).unwrap();
+        conn.execute("INSERT INTO settings (key, value) VALUES ('pwa_notifications', 'false'), ('pwa_service_workers', 'true')", []).unwrap();
+
+        AppDataStore::migrate_pwa_settings(&mut conn, &mut data).unwrap();
+
+        let loaded = AppDataStore::load(&conn).unwrap();
+        assert!(!loaded.installed_pwas[0].notifications);
+        assert!(loaded.installed_pwas[0].service_workers);
+        let left: i64 = conn.query_row("SELECT COUNT(*) FROM settings WHERE key LIKE 'pwa_%'", [], |row| row.get(0)).unwrap();
+        assert_eq!(left, 0);
+    }
 }
@@ -0,0 +1,313 @@
+/// Turns a tab showing raw JSON or XML (by the document's content type, so API responses without a
+/// .json extension count too) into a collapsible, highlighted tree with search and "copy path".
+/// It works on the document the tab already loaded, so nothing is fetched again, and a Raw button
+/// brings the original text back. The viewer lives in a shadow root with a constructed stylesheet,
+/// which a response's `style-src` CSP doesn't block.
+pub(crate) const DATA_VIEWER_SCRIPT: &str = r##"
+    (function() {
+        if (window.self !== window.top) return;
+
+        const XHTML = 'http://www.w3.org/1999/xhtml';
+        const MAX_CHARS = 10 * 1024 * 1024;
+        const AUTO_EXPAND_DEPTH = 2;
+        const AUTO_EXPAND_CHILDREN = 100;
+        const SEARCH_NODE_LIMIT = 200000;
+
+        function viewerKind() {
+            const type = (document.contentType || '').toLowerCase();
+            if (type === 'application/json' || type === 'text/json' || type.endsWith('+json')) return 'json';
+            if (type === 'application/xml' || type === 'text/xml') return 'xml';
+            if (type.endsWith('+xml') && type !== 'application/xhtml+xml' && type !== 'image/svg+xml') return 'xml';
+            return null;
+        }
+        const kind = viewerKind();
+        if (!kind) return;
+
+        const CSS = `
+            :host { all: initial; display: block; font: 13px/1.5 ui-monospace, 'Cascadia Mono', Consolas, monospace;
+                    --bg: #ffffff; --fg: #1f2328; --dim: #6e7781; --bar: #f6f8fa; --border: #d0d7de; --hover: #eaeef2;
+                    --key: #0550ae; --string: #0a3069; --number: #953800; --literal: #8250df; --tag: #116329; --match: #fff8c5;
+                    background: var(--bg); color: var(--fg); min-height: 100vh; }
+            @media (prefers-color-scheme: dark) {
+                :host { --bg: #0d1117; --fg: #e6edf3; --dim: #8b949e; --bar: #161b22; --border: #30363d; --hover: #1f242c;
+                        --key: #79c0ff; --string: #a5d6ff; --number: #ffa657; --literal: #d2a8ff; --tag: #7ee787; --match: #5a4a00; }
+            }
+            .bar { position: sticky; top: 0; display: flex; gap: 8px; align-items: center; padding: 8px 12px;
+                   background: var(--bar); border-bottom: 1px solid var(--border); font-family: system-ui, sans-serif; z-index: 1; }
+            .bar button, .bar input { font: inherit; color: var(--fg); background: var(--bg); border: 1px solid var(--border); border-radius: 6px; padding: 4px 10px; }
+            .bar button { cursor: pointer; }
+            .bar button.active { border-color: var(--key); color: var(--key); }
+            .bar input { width: 220px; }
+            .path { flex: 1; color: var(--dim); font-family: ui-monospace, Consolas, monospace; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }
+            .tree { padding: 8px 12px; }
+            .row > .line { padding-left: 16px; position: relative; white-space: pre-wrap; word-break: break-word; cursor: default; border-radius: 3px; }
+            .row > .line:hover { background: var(--hover); }
+            .row.selected > .line { outline: 1px solid var(--key); }
+            .row.match > .line { background: var(--match); }
+            .children { margin-left: 18px; border-left: 1px dotted var(--border); }
+            .toggle { position: absolute; left: 0; width: 14px; text-align: center; color: var(--dim); cursor: pointer; user-select: none; }
+            .row.collapsed > .children, .row:not(.collapsed) > .line .summary { display: none; }
+            .summary { color: var(--dim); font-style: italic; margin: 0 4px; }
+            .key, .attr { color: var(--key); }
+            .string { color: var(--string); }
+            .number { color: var(--number); }
+            .boolean, .null { color: var(--literal); }
+            .tag { color: var(--tag); }
+            .punct, .index, .count { color: var(--dim); }
+            pre.raw { margin: 0; padding: 12px; white-space: pre-wrap; word-break: break-word; }
+        `;
+
+        // A node: `open`/`close` are [class, text] parts, `kids` builds the children once
+        function identifierKey(key) {
+            return /^[A-Za-z_$][\w$]*$/.test(key) ? '.' + key : '[' + JSON.stringify(key) + ']';
+        }
+
+        function jsonNode(key, value, path) {
+            const keyParts = key === null ? [] : [[typeof key === 'number' ? 'index' : 'key', typeof key === 'number' ? String(key) : JSON.stringify(key)], ['punct', ': ']];
+            if (value !== null && typeof value === 'object') {
+                const isArray = Array.isArray(value);
+                const entries = isArray ? value.map((v, i) => [i, v]) : Object.entries(value);
+                return {
+                    open: [...keyParts, ['punct', isArray ? '[' : '{']],
+                    close: [['punct', isArray ? ']' : '}']],
+                    summary: entries.length + (isArray ? ' items' : ' keys'),
+                    count: entries.length,
+                    path,
+                    text: key === null ? '' : String(key),
+                    build: () => entries.map(([k, v]) => jsonNode(k, v, path + (isArray ? '[' + k + ']' : identifierKey(k))))
+                };
+            }
+            const type = value === null ? 'null' : typeof value;
+            const shown = type === 'string' ? JSON.stringify(value) : String(value);
+            return { open: [...keyParts, [type, shown]], path, text: (key === null ? '' : key + ' ') + String(value) };
+        }
+
+        function xmlNode(el, path) {
+            const kids = [...el.childNodes].filter(n => n.nodeType === 1 || ((n.nodeType === 3 || n.nodeType === 4) && n.nodeValue.trim()));
+            const open = [['punct', '<'], ['tag', el.nodeName]];
+            for (const attr of el.attributes) {
+                open.push(['attr', ' ' + attr.name], ['punct', '="'], ['string', attr.value], ['punct', '"']);
+            }
+            const attrText = [...el.attributes].map(a => a.name + ' ' + a.value).join(' ');
+            const close = [['punct', '</'], ['tag', el.nodeName], ['punct', '>']];
+            if (kids.length === 0) {
+                return { open: [...open, ['punct', ' />']], path, text: el.nodeName + ' ' + attrText };
+            }
+            if (kids.length === 1 && kids[0].nodeType !== 1) {
+                const text = kids[0].nodeValue.trim();
+                return { open: [...open, ['punct', '>'], ['string', text], ...close], path, text: el.nodeName + ' ' + attrText + ' ' + text };
+            }
+            return {
+                open: [...open, ['punct', '>']],
+                close,
+                summary: kids.length + ' children',
+                count: kids.length,
+                path,
+                text: el.nodeName + ' ' + attrText,
+                build: () => {
+                    const seen = {};
+                    return kids.map(n => {
+                        if (n.nodeType !== 1) {
+                            const text = n.nodeValue.trim();
+                            return { open: [['string', text]], path: path + '/text()', text };
+                        }
+                        seen[n.nodeName] = (seen[n.nodeName] || 0) + 1;
+                        const total = kids.filter(k => k.nodeName === n.nodeName).length;
+                        return xmlNode(n, path + '/' + n.nodeName + (total > 1 ? '[' + seen[n.nodeName] + ']' : ''));
+                    });
+                }
+            };
+        }
+
+        function kidsOf(node) {
+            if (!node.build) return [];
+            if (!node.kids) {
+                node.kids = node.build();
+                for (const kid of node.kids) kid.parent = node;
+            }
+            return node.kids;
+        }
+
+        function start() {
+            let raw, root;
+            if (kind === 'json') {
+                raw = document.body ? document.body.innerText : '';
+                if (!raw || raw.length > MAX_CHARS) return;
+                try {
+                    root = jsonNode(null, JSON.parse(raw), '$');
+                } catch (e) {
+                    return;
+                }
+            } else {
+                // Chromium's own XML view keeps the source in this element
+                const source = document.getElementById('webkit-xml-viewer-source-xml');
+                const element = source ? source.firstElementChild : document.documentElement;
+                if (!element || element.namespaceURI === XHTML) return;
+                raw = new XMLSerializer().serializeToString(element);
+                if (raw.length > MAX_CHARS) return;
+                root = xmlNode(element, '/' + element.nodeName);
+            }
+
+            const host = document.createElementNS(XHTML, 'div');
+            const shadow = host.attachShadow({ mode: 'closed' });
+            const sheet = new CSSStyleSheet();
+            sheet.replaceSync(CSS);
+            shadow.adoptedStyleSheets = [sheet];
+
+            const html = (tag, className, text) => {
+                const el = document.createElementNS(XHTML, tag);
+                if (className) el.className = className;
+                if (text !== undefined) el.textContent = text;
+                return el;
+            };
+
+            const bar = html('div', 'bar');
+            const treeButton = html('button', 'active', 'Tree');
+            const rawButton = html('button', '', 'Raw');
+            const search = html('input');
+            search.placeholder = 'Search keys and values';
+            const pathLabel = html('span', 'path', root.path);
+            const copyButton = html('button', '', 'Copy path');
+            bar.append(treeButton, rawButton, search, pathLabel, copyButton);
+
+            const tree = html('div', 'tree');
+            const rawView = html('pre', 'raw');
+            rawView.textContent = kind === 'json' ? (() => { try { return JSON.stringify(JSON.parse(raw), null, 2); } catch (e) { return raw; } })() : raw;
+            rawView.style.display = 'none';
+            shadow.append(bar, tree, rawView);
+
+            let selected = null;
+            function select(node) {
+                if (selected && selected.el) selected.el.classList.remove('selected');
+                selected = node;
+                node.el.classList.add('selected');
+                pathLabel.textContent = node.path;
+            }
+
+            function renderChildren(node) {
+                if (node.rendered) return;
+                node.rendered = true;
+                for (const kid of kidsOf(node)) node.childrenEl.appendChild(render(kid, node.depth + 1));
+            }
+
+            function setExpanded(node, expanded) {
+                if (!node.build) return;
+                if (expanded) renderChildren(node);
+                node.el.classList.toggle('collapsed', !expanded);
+                node.toggleEl.textContent = expanded ? '▾' : '▸';
+            }
+
+            function render(node, depth) {
+                node.depth = depth;
+                const row = html('div', 'row');
+                const line = html('div', 'line');
+                node.el = row;
+                for (const [className, text] of node.open) line.appendChild(html('span', className, text));
+                if (node.build) {
+                    node.toggleEl = html('span', 'toggle');
+                    line.prepend(node.toggleEl);
+                    line.appendChild(html('span', 'summary', node.summary));
+                    node.toggleEl.addEventListener('click', e => {
+                        e.stopPropagation();
+                        setExpanded(node, row.classList.contains('collapsed'));
+                    });
+                }
+                line.addEventListener('click', () => select(node));
+                row.appendChild(line);
+                if (node.build) {
+                    node.childrenEl = html('div', 'children');
+                    row.appendChild(node.childrenEl);
+                    const closeLine = html('div', 'line');
+                    for (const [className, text] of node.close) closeLine.appendChild(html('span', className, text));
+                    node.childrenEl.after(closeLine);
+                    setExpanded(node, depth < AUTO_EXPAND_DEPTH && node.count <= AUTO_EXPAND_CHILDREN);
+                }
+                return row;
+            }
+
+            tree.appendChild(render(root, 0));
+
+            // Search walks the whole document, not just what is on screen, and opens the way to each hit
+            let matches = [];
+            let current = -1;
+            function reveal(node) {
+                const chain = [];
+                for (let n = node.parent; n; n = n.parent) chain.unshift(n);
+                for (const n of chain) setExpanded(n, true);
+            }
+            function runSearch() {
+                for (const node of matches) if (node.el) node.el.classList.remove('match');
+                matches = [];
+                current = -1;
+                const term = search.value.trim().toLowerCase();
+                if (!term) return;
+                const stack = [root];
+                let visited = 0;
+                while (stack.length && visited++ < SEARCH_NODE_LIMIT) {
+                    const node = stack.pop();
+                    if (node.text && node.text.toLowerCase().includes(term)) matches.push(node);
+                    const kids = kidsOf(node);
+                    for (let i = kids.length - 1; i >= 0; i--) stack.push(kids[i]);
+                }
+                for (const node of matches.slice(0, 500)) {
+                    reveal(node);
+                    node.el.classList.add('match');
+                }
+                next();
+            }
+            function next() {
+                const shown = matches.slice(0, 500);
+                if (!shown.length) return;
+                current = (current + 1) % shown.length;
+                select(shown[current]);
+                shown[current].el.scrollIntoView({ block: 'center' });
+            }
+            let searchTimer = null;
+            search.addEventListener('input', () => {
+                clearTimeout(searchTimer);
+                searchTimer = setTimeout(runSearch, 200);
+            });
+            search.addEventListener('keydown', e => {
+                if (e.key === 'Enter') next();
+            });
+
+            copyButton.addEventListener('click', () => {
+                const text = pathLabel.textContent;
+                const fallback = () => {
+                    const area = html('textarea');
+                    area.value = text;
+                    shadow.appendChild(area);
+                    area.select();
+                    document.execCommand('copy');
+                    area.remove();
+                };
+                if (navigator.clipboard) navigator.clipboard.writeText(text).catch(fallback);
+                else fallback();
+                copyButton.textContent = 'Copied';
+                setTimeout(() => copyButton.textContent = 'Copy path', 1200);
+            });
+
+            function showRaw(showing) {
+                tree.style.display = showing ? 'none' : '';
+                rawView.style.display = showing ? '' : 'none';
+                rawButton.classList.toggle('active', showing);
+                treeButton.classList.toggle('active', !showing);
+            }
+            treeButton.addEventListener('click', () => showRaw(false));
+            rawButton.addEventListener('click', () => showRaw(true));
+
+            if (kind === 'json') {
+                document.body.replaceChildren(host);
+                document.body.style.margin = '0';
+            } else {
+                document.replaceChild(host, document.documentElement);
+            }
+        }
+
+        if (document.readyState === 'loading') {
+            document.addEventListener('DOMContentLoaded', start, { once: true });
+        } else {
+            start();
+        }
+    })();
+"##;
@@ -0,0 +1,1018 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, Url};
+use futures_util::StreamExt;
+use tokio::io::{AsyncWriteExt, AsyncSeekExt};
+use serde::{Serialize, Deserialize};
+
+use crate::events::AppEvent;
+use crate::data::AppDataStore;
+use crate::{data, events, http, network, undo};
+use crate::{notifications, refresh_tray_menu, show_downloads};
+use crate::error::LuminaError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadItem {
+    pub url: String,
+    pub file_name: String,
+    pub total_size: u64,
+    pub downloaded_size: u64,
+    pub path: String,
+    pub status: String, // "scheduled", "queued", "downloading", "paused", "quarantined", "held", "completed", "failed", "blocked"
+    #[serde(default)]
+    pub added_at: i64,
+    /// Folder the archive was unpacked into, if auto-extract ran
+    #[serde(default)]
+    pub extracted_path: Option<String>,
+    /// Unix time a "scheduled" download becomes eligible to start
+    #[serde(default)]
+    pub start_at: Option<i64>,
+    /// Validators from the last response, checked with If-Range when resuming
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// Started from a private tab: kept in memory only, never written to downloads.json
+    #[serde(default)]
+    pub private: bool,
+}
+
+pub struct DownloadManager {
+    pub downloads: Mutex<HashMap<String, DownloadItem>>,
+    pub app_dir: PathBuf,
+}
+
+impl DownloadManager {
+    pub fn new(app_dir: PathBuf) -> Self {
+        let mut manager = Self {
+            downloads: Mutex::new(HashMap::new()),
+            app_dir: app_dir.clone(),
+        };
+        manager.load();
+        manager
+    }
+
+    pub fn load(&mut self) {
+        let path = self.app_dir.join("downloads.json");
+        if path.exists() {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                if let Ok(mut data) = serde_json::from_str::<HashMap<String, DownloadItem>>(&content) {
+                    // Transfers cut off by the last exit are paused, so they don't hold queue slots;
+                    // files whose scan was cut off stay closed until scanned again
+                    for item in data.values_mut() {
+                        if item.status == "downloading" {
+                            item.status = "paused".to_string();
+                        } else if item.status == "quarantined" {
+                            item.status = "held".to_string();
+                        }
+                    }
+                    *self.downloads.lock().unwrap() = data;
+                }
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let path = self.app_dir.join("downloads.json");
+        let data = self.downloads.lock().unwrap();
+        let saved: HashMap<&String, &DownloadItem> = data.iter().filter(|(_, item)| !item.private).collect();
+        if let Ok(content) = serde_json::to_string_pretty(&saved) {
+            if let Err(e) = data::write_atomic(&path, content.as_bytes()) {
+                eprintln!("Failed to write downloads.json: {}", e);
+            }
+        }
+    }
+    
+    pub fn update_status(&self, url: &str, status: &str) {
+        let mut data = self.downloads.lock().unwrap();
+        if let Some(item) = data.get_mut(url) {
+            item.status = status.to_string();
+        }
+        drop(data); // unlock before save
+        self.save();
+    }
+    
+    pub fn update_progress(&self, url: &str, downloaded: u64, total: u64) {
+        let mut data = self.downloads.lock().unwrap();
+        if let Some(item) = data.get_mut(url) {
+            item.downloaded_size = downloaded;
+            item.total_size = total;
+        }
+        // Don't save on every progress update to avoid IO thrashing
+    }
+
+    /// Starts waiting downloads up to `max_concurrent`, oldest first. Returns (url, file_name) of
+    /// each one it moved to "downloading"; the caller spawns them.
+    pub fn take_startable(&self, max_concurrent: usize) -> Vec<(String, String)> {
+        let now = chrono::Utc::now().timestamp();
+        let mut data = self.downloads.lock().unwrap();
+        for item in data.values_mut() {
+            if item.status == "scheduled" && item.start_at.is_none_or(|t| t <= now) {
+                item.status = "queued".to_string();
+            }
+        }
+
+        let active = data.values().filter(|d| d.status == "downloading").count();
+        let mut queued: Vec<&mut DownloadItem> = data.values_mut().filter(|d| d.status == "queued").collect();
+        queued.sort_by_key(|d| d.added_at);
+        let started: Vec<(String, String)> = queued
+            .into_iter()
+            .take(max_concurrent.max(1).saturating_sub(active))
+            .map(|item| {
+                item.status = "downloading".to_string();
+                (item.url.clone(), item.file_name.clone())
+            })
+            .collect();
+        drop(data);
+
+        self.save();
+        started
+    }
+
+    pub fn set_extracted_path(&self, url: &str, path: String) {
+        let mut data = self.downloads.lock().unwrap();
+        if let Some(item) = data.get_mut(url) {
+            item.extracted_path = Some(path);
+        }
+        drop(data);
+        self.save();
+    }
+
+    pub fn get_downloads(&self) -> Vec<DownloadItem> {
+        let data = self.downloads.lock().unwrap();
+        data.values().cloned().collect()
+    }
+}
+
+#[tauri::command]
+pub(crate) fn open_file(app: AppHandle, path: String) -> Result<(), LuminaError> {
+    // Quarantined, held or blocked downloads stay closed until a scan lets them through
+    let held = app.state::<DownloadManager>().downloads.lock().unwrap()
+        .values()
+        .any(|d| d.path == path && matches!(d.status.as_str(), "quarantined" | "held" | "blocked"));
+    if held {
+        return Err(LuminaError::Other("File is quarantined".to_string()));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let _ = std::process::Command::new("explorer")
+            .arg(&path)
+            .spawn();
+    }
+    #[cfg(not(target_os = "windows"))]
+    let _ = path;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn show_in_folder(path: String) -> Result<(), LuminaError> {
+    #[cfg(target_os = "windows")]
+    {
+        std::process::Command::new("explorer")
+            .args(["/select,", &path])
+            .spawn()?;
+    }
+    #[cfg(not(target_os = "windows"))]
+    let _ = path;
+    Ok(())
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DownloadStartedPayload {
+    url: String,
+    file_name: String,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DownloadFinishedPayload {
+    url: String,
+    success: bool,
+    path: Option<String>,
+}
+
+/// One event stream for the download shelf: "added", "progress", "paused", "completed" or "failed".
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DownloadShelfEvent {
+    kind: &'static str,
+    url: String,
+    file_name: String,
+    path: Option<String>,
+    downloaded: u64,
+    total: u64,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DownloadProgressPayload {
+    url: String,
+    progress: u64,
+    total: u64,
+}
+
+const DOWNLOAD_SHELF_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+fn emit_download_shelf(app: &AppHandle, kind: &'static str, url: &str, file_name: &str, path: Option<&str>, downloaded: u64, total: u64) {
+    events::emit(app, AppEvent::DownloadShelf(DownloadShelfEvent {
+        kind,
+        url: url.to_string(),
+        file_name: file_name.to_string(),
+        path: path.map(|p| p.to_string()),
+        downloaded,
+        total,
+    }));
+    refresh_tray_menu(app);
+}
+
+enum DownloadOutcome {
+    Completed(String),
+    Failed,
+    /// The virus scan flagged the file
+    Blocked,
+    /// The virus scan gave no answer, so the file stays quarantined
+    Held,
+}
+
+/// Records how a download ended, notifies the UI and, if enabled, shows a desktop notification.
+/// Clicking it opens the downloads page; the file itself is only ever opened from there.
+fn finish_download(app: &AppHandle, url: &str, file_name: &str, outcome: DownloadOutcome, downloaded: u64) {
+    let (status, path) = match &outcome {
+        DownloadOutcome::Completed(path) => ("completed", Some(path.clone())),
+        DownloadOutcome::Failed => ("failed", None),
+        DownloadOutcome::Blocked => ("blocked", None),
+        DownloadOutcome::Held => ("held", None),
+    };
+    let manager = app.state::<DownloadManager>();
+    manager.update_status(url, status);
+    pump_download_queue(app);
+
+    events::emit(app, AppEvent::DownloadFinished(DownloadFinishedPayload {
+        url: url.to_string(),
+        success: path.is_some(),
+        path: path.clone(),
+    }));
+    emit_download_shelf(app, status, url, file_name, path.as_deref(), downloaded, downloaded);
+
+    if !app.state::<AppDataStore>().data.lock().unwrap().settings.download_notifications {
+        return;
+    }
+    let (title, body) = match outcome {
+        DownloadOutcome::Completed(_) => ("Download complete", file_name.to_string()),
+        DownloadOutcome::Failed => ("Download failed", file_name.to_string()),
+        DownloadOutcome::Blocked => ("Download blocked", format!("{}\nA threat was detected and the file was removed", file_name)),
+        DownloadOutcome::Held => ("Download held", format!("{}\nThe virus scan could not check the file, so it stays quarantined", file_name)),
+    };
+    let handle = app.clone();
+    notifications::show(app, title, &body, move |clicked| {
+        if clicked {
+            show_downloads(&handle);
+        }
+    });
+}
+
+/// Lists a file the browser wrote itself (a saved page, say) as a completed download of `url`.
+pub(crate) fn register_saved_file(app: &AppHandle, url: &str, path: &std::path::Path) {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let path_str = path.to_string_lossy().to_string();
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    mark_of_the_web(path, url);
+
+    let manager = app.state::<DownloadManager>();
+    manager.downloads.lock().unwrap().insert(url.to_string(), DownloadItem {
+        url: url.to_string(),
+        file_name: file_name.clone(),
+        total_size: size,
+        downloaded_size: size,
+        path: path_str.clone(),
+        status: "completed".to_string(),
+        added_at: chrono::Utc::now().timestamp(),
+        extracted_path: None,
+        start_at: None,
+        etag: None,
+        last_modified: None,
+        private: false,
+    });
+    manager.save();
+
+    events::emit(app, AppEvent::DownloadStarted(DownloadStartedPayload {
+        url: url.to_string(),
+        file_name: file_name.clone(),
+    }));
+    emit_download_shelf(app, "added", url, &file_name, Some(&path_str), size, size);
+    finish_download(app, url, &file_name, DownloadOutcome::Completed(path_str), size);
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+enum ScanVerdict {
+    Clean,
+    Threat,
+    /// The scanner couldn't be run or gave no answer
+    Inconclusive,
+    /// Scanning is turned off, or this platform has no scanner
+    Skipped,
+}
+
+/// Tags the file with the Internet zone (Zone.Identifier stream) so Windows SmartScreen and Office
+/// treat it as downloaded content.
+fn mark_of_the_web(path: &std::path::Path, url: &str) {
+    #[cfg(target_os = "windows")]
+    {
+        let mut stream = path.as_os_str().to_owned();
+        stream.push(":Zone.Identifier");
+        let content = format!("[ZoneTransfer]\r\nZoneId=3\r\nHostUrl={}\r\n", url);
+        if let Err(e) = std::fs::write(&stream, content) {
+            println!("Failed to write Zone.Identifier for {}: {}", path.display(), e);
+        }
+    }
+    #[cfg(not(target_os = "windows"))]
+    let _ = (path, url);
+}
+
+/// Runs a Windows Defender custom scan on the file. With scanning on, only an explicit all-clear lets the file out of quarantine.
+async fn scan_download(app: &AppHandle, path: &std::path::Path) -> ScanVerdict {
+    if !app.state::<AppDataStore>().data.lock().unwrap().settings.scan_downloads {
+        return ScanVerdict::Skipped;
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let program_files = std::env::var("ProgramFiles").unwrap_or_else(|_| "C:\\Program Files".to_string());
+        let scanner = std::path::Path::new(&program_files).join("Windows Defender").join("MpCmdRun.exe");
+        if !scanner.exists() {
+            println!("Defender not found, holding download: {}", path.display());
+            return ScanVerdict::Inconclusive;
+        }
+
+        let result = tokio::process::Command::new(scanner)
+            .args(["-Scan", "-ScanType", "3", "-File"])
+            .arg(path)
+            .output()
+            .await;
+        match result.map(|output| output.status.code()) {
+            Ok(Some(0)) => ScanVerdict::Clean,
+            Ok(Some(2)) => {
+                println!("Defender flagged download: {}", path.display());
+                ScanVerdict::Threat
+            }
+            Ok(code) => {
+                println!("Defender scan inconclusive for {} (exit {:?})", path.display(), code);
+                ScanVerdict::Inconclusive
+            }
+            Err(e) => {
+                println!("Failed to run Defender scan: {}", e);
+                ScanVerdict::Inconclusive
+            }
+        }
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = path;
+        ScanVerdict::Skipped
+    }
+}
+
+/// Holds a written download in quarantine while it is scanned, then lets it out, blocks it or keeps
+/// holding it, as the scan decides.
+async fn scan_and_finish(app: AppHandle, url: String, file_name: String, path: PathBuf, downloaded: u64) {
+    let path_str = path.to_string_lossy().to_string();
+    app.state::<DownloadManager>().update_status(&url, "quarantined");
+    emit_download_shelf(&app, "scanning", &url, &file_name, Some(&path_str), downloaded, downloaded);
+    let outcome = match scan_download(&app, &path).await {
+        ScanVerdict::Threat => {
+            // Defender's own remediation may not have run (or may be turned off), so the file goes here
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => println!("Failed to delete flagged download {}: {}", path.display(), e),
+            }
+            DownloadOutcome::Blocked
+        }
+        ScanVerdict::Inconclusive => DownloadOutcome::Held,
+        ScanVerdict::Clean | ScanVerdict::Skipped => DownloadOutcome::Completed(path_str),
+    };
+    let extract = matches!(outcome, DownloadOutcome::Completed(_))
+        && file_name.to_lowercase().ends_with(".zip")
+        && app.state::<AppDataStore>().data.lock().unwrap().settings.auto_extract_archives;
+    finish_download(&app, &url, &file_name, outcome, downloaded);
+    if extract {
+        extract_download(app, url, file_name, path).await;
+    }
+}
+
+/// Scans a held download again, for when the scanner is back.
+#[tauri::command]
+pub(crate) async fn rescan_download(app: AppHandle, url: String) -> Result<(), LuminaError> {
+    let item = app.state::<DownloadManager>().downloads.lock().unwrap().get(&url).cloned()
+        .ok_or_else(|| LuminaError::NotFound(format!("No download of {}", url)))?;
+    if item.status != "held" {
+        return Err(LuminaError::InvalidInput(format!("{} is not held", item.file_name)));
+    }
+    scan_and_finish(app, url, item.file_name, PathBuf::from(item.path), item.downloaded_size).await;
+    Ok(())
+}
+
+/// Unpacks a finished .zip next to it, into a folder named after the archive. Progress goes out on
+/// the download-shelf stream as "extracting" (entries done / total), then "extracted" or "extract-failed".
+async fn extract_download(app: AppHandle, url: String, file_name: String, archive: PathBuf) {
+    let Some(dir) = archive.parent().map(|p| p.to_path_buf()) else { return };
+    let folder_name = archive.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "archive".to_string());
+    let target = unique_download_path(&dir, &folder_name);
+
+    let progress_app = app.clone();
+    let (progress_url, progress_name) = (url.clone(), file_name.clone());
+    let extract_target = target.clone();
+    let result = tokio::task::spawn_blocking(move || -> Result<usize, String> {
+        let file = std::fs::File::open(&archive).map_err(|e| e.to_string())?;
+        let mut zip = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+        let total = zip.len() as u64;
+        std::fs::create_dir_all(&extract_target).map_err(|e| e.to_string())?;
+
+        let mut last_emit = std::time::Instant::now();
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i).map_err(|e| e.to_string())?;
+            // enclosed_name rejects absolute paths and ".." components
+            let Some(relative) = entry.enclosed_name() else {
+                println!("Skipping unsafe archive entry: {}", entry.name());
+                continue;
+            };
+            let out_path = extract_target.join(relative);
+            if entry.is_dir() {
+                std::fs::create_dir_all(&out_path).map_err(|e| e.to_string())?;
+            } else {
+                if let Some(parent) = out_path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                let mut out = std::fs::File::create(&out_path).map_err(|e| e.to_string())?;
+                std::io::copy(&mut entry, &mut out).map_err(|e| e.to_string())?;
+            }
+
+            if last_emit.elapsed() >= DOWNLOAD_SHELF_PROGRESS_INTERVAL {
+                last_emit = std::time::Instant::now();
+                emit_download_shelf(&progress_app, "extracting", &progress_url, &progress_name, None, i as u64 + 1, total);
+            }
+        }
+        Ok(zip.len())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+
+    let target_str = target.to_string_lossy().to_string();
+    match result {
+        Ok(count) => {
+            println!("Extracted {} entries from {} to {}", count, file_name, target_str);
+            app.state::<DownloadManager>().set_extracted_path(&url, target_str.clone());
+            emit_download_shelf(&app, "extracted", &url, &file_name, Some(&target_str), count as u64, count as u64);
+        }
+        Err(e) => {
+            println!("Failed to extract {}: {}", file_name, e);
+            emit_download_shelf(&app, "extract-failed", &url, &file_name, None, 0, 0);
+        }
+    }
+}
+
+#[tauri::command]
+pub(crate) fn set_auto_extract_archives(state: tauri::State<'_, AppDataStore>, enabled: bool) -> Result<(), LuminaError> {
+    state.data.lock().unwrap().settings.auto_extract_archives = enabled;
+    state.try_save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn set_scan_downloads(state: tauri::State<'_, AppDataStore>, enabled: bool) -> Result<(), LuminaError> {
+    state.data.lock().unwrap().settings.scan_downloads = enabled;
+    state.try_save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn set_download_notifications(state: tauri::State<'_, AppDataStore>, enabled: bool) -> Result<(), LuminaError> {
+    state.data.lock().unwrap().settings.download_notifications = enabled;
+    state.try_save()?;
+    Ok(())
+}
+
+/// Last path segment of a download URL, percent-decoded; query strings are not part of the name.
+pub(crate) fn filename_from_url(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back().map(|s| s.to_string()))
+        .filter(|s| !s.is_empty())
+        .map(|s| urlencoding::decode(&s).map(|d| d.into_owned()).unwrap_or(s))
+        .unwrap_or_else(|| "downloaded_file".to_string())
+}
+
+/// Extracts the filename from a Content-Disposition header, preferring RFC 5987 `filename*`.
+fn parse_content_disposition(value: &str) -> Option<String> {
+    let mut plain = None;
+    for part in value.split(';').map(str::trim) {
+        let Some((key, val)) = part.split_once('=') else { continue };
+        let key = key.trim().to_ascii_lowercase();
+        let val = val.trim();
+        if key == "filename*" {
+            // UTF-8''encoded%20name.pdf
+            let encoded = val.splitn(3, '\'').nth(2).unwrap_or(val);
+            if let Ok(decoded) = urlencoding::decode(encoded.trim_matches('"')) {
+                if !decoded.is_empty() {
+                    return Some(decoded.into_owned());
+                }
+            }
+        } else if key == "filename" {
+            plain = Some(val.trim_matches('"').replace("\\\"", "\""));
+        }
+    }
+    plain.filter(|name| !name.is_empty())
+}
+
+fn extension_for_mime(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    Some(match mime.as_str() {
+        "application/pdf" => "pdf",
+        "application/zip" | "application/x-zip-compressed" => "zip",
+        "application/gzip" | "application/x-gzip" => "gz",
+        "application/x-7z-compressed" => "7z",
+        "application/vnd.rar" | "application/x-rar-compressed" => "rar",
+        "application/x-msdownload" | "application/vnd.microsoft.portable-executable" => "exe",
+        "application/x-msi" => "msi",
+        "application/json" => "json",
+        "application/xml" | "text/xml" => "xml",
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "text/csv" => "csv",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "audio/mpeg" => "mp3",
+        "audio/ogg" => "ogg",
+        "video/mp4" => "mp4",
+        "video/webm" => "webm",
+        _ => return None,
+    })
+}
+
+/// Makes a server-provided name safe to use as a file name on every platform.
+pub(crate) fn sanitize_filename(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let mut clean: String = name
+        .chars()
+        .map(|c| if c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') { '_' } else { c })
+        .collect();
+    clean = clean.trim_matches(|c: char| c == '.' || c.is_whitespace()).to_string();
+
+    let stem = clean.split('.').next().unwrap_or("").to_ascii_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT")) && stem.len() == 4 && stem.as_bytes()[3].is_ascii_digit());
+    if reserved {
+        clean.insert(0, '_');
+    }
+
+    if clean.chars().count() > 200 {
+        let (base, ext) = match clean.rsplit_once('.') {
+            Some((base, ext)) if ext.len() <= 10 => (base.to_string(), format!(".{}", ext)),
+            _ => (clean.clone(), String::new()),
+        };
+        clean = base.chars().take(200 - ext.len()).collect::<String>() + &ext;
+    }
+
+    if clean.is_empty() {
+        "download".to_string()
+    } else {
+        clean
+    }
+}
+
+/// "name.ext", then "name (1).ext", "name (2).ext", ... until the path is free.
+pub(crate) fn unique_download_path(dir: &std::path::Path, file_name: &str) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let (stem, ext) = match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (file_name, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, ext)))
+        .find(|p| !p.exists())
+        .unwrap_or(candidate)
+}
+
+/// If-Range value for resuming `item`. Weak ETags can't be used with If-Range, so those fall back
+/// to Last-Modified.
+fn resume_validator(item: &DownloadItem) -> Option<String> {
+    item.etag
+        .clone()
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| item.last_modified.clone())
+}
+
+/// First byte offset of a "Content-Range: bytes start-end/total" header.
+fn content_range_start(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    let value = headers.get(reqwest::header::CONTENT_RANGE)?.to_str().ok()?;
+    let range = value.trim().strip_prefix("bytes")?.trim_start();
+    range.split('-').next()?.trim().parse().ok()
+}
+
+/// Full size of the file from a "Content-Range: bytes start-end/total" header, unless it is "*".
+fn content_range_total(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    let value = headers.get(reqwest::header::CONTENT_RANGE)?.to_str().ok()?;
+    value.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+/// Picks the final file name from the response headers, falling back to the URL-derived name.
+fn resolve_download_filename(headers: &reqwest::header::HeaderMap, fallback: &str) -> String {
+    let header = |name| headers.get(name).and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok());
+    let mut name = header(reqwest::header::CONTENT_DISPOSITION)
+        .and_then(parse_content_disposition)
+        .unwrap_or_else(|| fallback.to_string());
+    name = sanitize_filename(&name);
+
+    if !name.contains('.') {
+        if let Some(ext) = header(reqwest::header::CONTENT_TYPE).and_then(extension_for_mime) {
+            name = format!("{}.{}", name, ext);
+        }
+    }
+    name
+}
+
+async fn download_file(app: AppHandle, url: String, file_name: String) {
+    let download_dir = app.path().download_dir().unwrap_or(std::path::PathBuf::from("downloads"));
+    if !download_dir.exists() {
+        let _ = tokio::fs::create_dir_all(&download_dir).await;
+    }
+
+    // Use DownloadManager
+    let manager = app.state::<DownloadManager>();
+
+    // Resume only a download we already track whose partial file is still on disk
+    let existing = manager.downloads.lock().unwrap().get(&url).cloned().filter(|item| item.status != "completed");
+    let mut downloaded: u64 = match &existing {
+        Some(item) => tokio::fs::metadata(&item.path).await.map(|m| m.len()).unwrap_or(0),
+        None => 0,
+    };
+    let has_partial = downloaded > 0;
+
+    let validator = existing.as_ref().and_then(resume_validator);
+    if has_partial && validator.is_none() {
+        // Without a validator there is no way to tell whether the remote file changed
+        println!("No ETag/Last-Modified for {}, restarting download", url);
+        downloaded = 0;
+    }
+
+    let client = http::client(&app, http::Purpose::Download).unwrap_or_default();
+    let mut request = client.get(&url);
+    
+    if let (true, Some(validator)) = (downloaded > 0, &validator) {
+        // If-Range makes the server send the full file (200) when the validator no longer matches
+        request = request
+            .header(reqwest::header::RANGE, format!("bytes={}-", downloaded))
+            .header(reqwest::header::IF_RANGE, validator.as_str());
+    }
+
+    let mut response = request.send().await;
+
+    // A partial response has to pick up exactly where the file on disk ends
+    if let Ok(res) = &response {
+        if res.status() == reqwest::StatusCode::PARTIAL_CONTENT && content_range_start(res.headers()) != Some(downloaded) {
+            println!("Content-Range mismatch for {}, restarting download", url);
+            response = client.get(&url).send().await;
+        }
+    }
+
+    let (etag, last_modified) = match &response {
+        Ok(res) => {
+            let header = |name| res.headers().get(name).and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok()).map(|v| v.to_string());
+            (header(reqwest::header::ETAG), header(reqwest::header::LAST_MODIFIED))
+        }
+        Err(_) => existing.as_ref().map(|item| (item.etag.clone(), item.last_modified.clone())).unwrap_or_default(),
+    };
+
+    let (file_name, path) = match (&existing, &response) {
+        (Some(item), _) if has_partial => (item.file_name.clone(), PathBuf::from(&item.path)),
+        (_, Ok(res)) => {
+            let name = resolve_download_filename(res.headers(), &file_name);
+            let path = unique_download_path(&download_dir, &name);
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or(name);
+            (name, path)
+        }
+        (_, Err(_)) => {
+            let name = sanitize_filename(&file_name);
+            (name.clone(), download_dir.join(name))
+        }
+    };
+    let path_str = path.to_string_lossy().to_string();
+
+    // Register
+    {
+        let mut data = manager.downloads.lock().unwrap();
+        data.insert(url.clone(), DownloadItem {
+            url: url.clone(),
+            file_name: file_name.clone(),
+            total_size: 0,
+            downloaded_size: downloaded,
+            path: path_str.clone(),
+            status: "downloading".to_string(),
+            added_at: chrono::Utc::now().timestamp(),
+            extracted_path: None,
+            start_at: None,
+            etag,
+            last_modified,
+            private: existing.as_ref().is_some_and(|item| item.private),
+        });
+    }
+    manager.save();
+
+    events::emit(&app, AppEvent::DownloadStarted(DownloadStartedPayload {
+        url: url.clone(),
+        file_name: file_name.clone(),
+    }));
+    emit_download_shelf(&app, "added", &url, &file_name, Some(&path_str), downloaded, 0);
+
+    match response {
+        Ok(res) => {
+            let partial = res.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+
+            let mut file;
+            if partial {
+                 match tokio::fs::OpenOptions::new().create(true).append(true).open(&path).await {
+                    Ok(mut f) => {
+                        // Use AsyncSeekExt (restored)
+                        let _ = f.seek(std::io::SeekFrom::End(0)).await;
+                        file = f;
+                    }
+                    Err(e) => {
+                         println!("Failed to open file for append: {}", e);
+                         finish_download(&app, &url, &file_name, DownloadOutcome::Failed, downloaded);
+                        return;
+                    }
+                 }
+            } else {
+                downloaded = 0;
+                match tokio::fs::File::create(&path).await {
+                    Ok(f) => file = f,
+                    Err(e) => {
+                         println!("Failed to create file: {}", e);
+                         finish_download(&app, &url, &file_name, DownloadOutcome::Failed, downloaded);
+                        return;
+                    }
+                }
+            }
+            let total_size = if partial {
+                content_range_total(res.headers()).unwrap_or_else(|| res.content_length().unwrap_or(0) + downloaded)
+            } else {
+                res.content_length().unwrap_or(0)
+            };
+
+            let mut stream = res.bytes_stream();
+            let mut last_save = std::time::Instant::now();
+            let mut last_shelf_update = std::time::Instant::now();
+
+            while let Some(item) = stream.next().await {
+                match item {
+                    Ok(chunk) => {
+                        if (file.write_all(&chunk).await).is_err() {
+                             finish_download(&app, &url, &file_name, DownloadOutcome::Failed, downloaded);
+                             return;
+                        }
+                        downloaded += chunk.len() as u64;
+                        manager.update_progress(&url, downloaded, total_size);
+                        
+                        if last_save.elapsed().as_secs() > 5 {
+                            manager.save();
+                            last_save = std::time::Instant::now();
+                        }
+
+                        events::emit(&app, AppEvent::DownloadProgress(DownloadProgressPayload {
+                            url: url.clone(),
+                            progress: downloaded,
+                            total: total_size,
+                        }));
+                        if last_shelf_update.elapsed() >= DOWNLOAD_SHELF_PROGRESS_INTERVAL {
+                            emit_download_shelf(&app, "progress", &url, &file_name, Some(&path_str), downloaded, total_size);
+                            last_shelf_update = std::time::Instant::now();
+                        }
+                    }
+                    Err(_) => {
+                         let _ = file.flush().await;
+                         fail_or_pause(&app, &url, &file_name, downloaded).await;
+                         return;
+                    }
+                }
+            }
+            
+            // Ensure file is written and closed
+            let _ = file.sync_all().await;
+            drop(file);
+
+            mark_of_the_web(&path, &url);
+            scan_and_finish(app, url, file_name, path, downloaded).await;
+        }
+        Err(_) => {
+            fail_or_pause(&app, &url, &file_name, downloaded).await;
+        }
+    }
+}
+
+/// Downloads cut off because the network went away, by URL; they pick up where they stopped once it is back
+static OFFLINE_PAUSED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn offline_paused() -> &'static Mutex<HashSet<String>> {
+    OFFLINE_PAUSED.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// A transfer that broke off fails, unless the connection is what went away: then it waits as "paused".
+async fn fail_or_pause(app: &AppHandle, url: &str, file_name: &str, downloaded: u64) {
+    if network::check_now(app).await {
+        finish_download(app, url, file_name, DownloadOutcome::Failed, downloaded);
+        return;
+    }
+    println!("Lumina: Pausing download {} until the network is back", url);
+    let manager = app.state::<DownloadManager>();
+    manager.update_status(url, "paused");
+    offline_paused().lock().unwrap().insert(url.to_string());
+    pump_download_queue(app);
+    let path = manager.downloads.lock().unwrap().get(url).map(|d| d.path.clone());
+    emit_download_shelf(app, "paused", url, file_name, path.as_deref(), downloaded, 0);
+}
+
+/// Queues the downloads the network cut off again; download_file resumes them from their partial file.
+pub(crate) fn resume_offline_paused(app: &AppHandle) {
+    let urls: Vec<String> = offline_paused().lock().unwrap().drain().collect();
+    let manager = app.state::<DownloadManager>();
+    for url in urls {
+        let paused = manager.downloads.lock().unwrap().get(&url).is_some_and(|d| d.status == "paused");
+        if paused {
+            manager.update_status(&url, "queued");
+        }
+    }
+    pump_download_queue(app);
+}
+
+/// How often scheduled downloads are checked for their start time
+pub(crate) const DOWNLOAD_SCHEDULE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Adds a download as "queued" (or "scheduled" if `start_at` is in the future) and lets the queue
+/// start it when a slot is free. A download already on the list that hasn't finished keeps its
+/// entry, partial file included, so it picks up where it stopped.
+pub(crate) fn enqueue_download(app: &AppHandle, url: String, file_name: String, start_at: Option<i64>, private: bool) {
+    let manager = app.state::<DownloadManager>();
+    let now = chrono::Utc::now().timestamp();
+    let status = if start_at.is_some_and(|t| t > now) { "scheduled" } else { "queued" };
+    let mut file_name = sanitize_filename(&file_name);
+    let mut path = None;
+    {
+        let mut data = manager.downloads.lock().unwrap();
+        match data.get_mut(&url) {
+            Some(item) if matches!(item.status.as_str(), "downloading" | "quarantined") => return,
+            Some(item) if !matches!(item.status.as_str(), "completed" | "held" | "blocked") => {
+                item.status = status.to_string();
+                item.start_at = start_at;
+                file_name = item.file_name.clone();
+                path = Some(item.path.clone()).filter(|p| !p.is_empty());
+            }
+            // The real path is picked once the response headers are known
+            _ => {
+                data.insert(url.clone(), DownloadItem {
+                    url: url.clone(),
+                    file_name: file_name.clone(),
+                    total_size: 0,
+                    downloaded_size: 0,
+                    path: String::new(),
+                    status: status.to_string(),
+                    added_at: now,
+                    extracted_path: None,
+                    start_at,
+                    etag: None,
+                    last_modified: None,
+                    private,
+                });
+            }
+        }
+    }
+    manager.save();
+    offline_paused().lock().unwrap().remove(&url);
+
+    emit_download_shelf(app, status, &url, &file_name, path.as_deref(), 0, 0);
+    pump_download_queue(app);
+}
+
+pub(crate) fn pump_download_queue(app: &AppHandle) {
+    let max_concurrent = app.state::<AppDataStore>().data.lock().unwrap().settings.max_concurrent_downloads;
+    for (url, file_name) in app.state::<DownloadManager>().take_startable(max_concurrent) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            download_file(app, url, file_name).await;
+        });
+    }
+}
+
+/// Queues `url` to start at `start_at` (Unix seconds), e.g. overnight for large files.
+#[tauri::command]
+pub(crate) fn schedule_download(app: AppHandle, url: String, start_at: i64) -> Result<(), LuminaError> {
+    let parsed = Url::parse(&url)?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(LuminaError::InvalidInput("Only http(s) downloads can be scheduled".to_string()));
+    }
+    enqueue_download(&app, url, filename_from_url(&parsed), Some(start_at), false);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn set_max_concurrent_downloads(app: AppHandle, state: tauri::State<'_, AppDataStore>, max: usize) -> Result<(), LuminaError> {
+    state.data.lock().unwrap().settings.max_concurrent_downloads = max.clamp(1, 10);
+    state.try_save()?;
+    pump_download_queue(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn get_downloads(app: AppHandle) -> Vec<DownloadItem> {
+    let manager = app.state::<DownloadManager>();
+    let data = manager.downloads.lock().unwrap();
+    data.values().cloned().collect()
+}
+
+/// Takes a finished or stopped download off the list, leaving the file alone. Undoable for a while.
+#[tauri::command]
+pub(crate) fn remove_download(app: AppHandle, url: String) -> Result<(), LuminaError> {
+    let manager = app.state::<DownloadManager>();
+    let removed = {
+        let mut data = manager.downloads.lock().unwrap();
+        if data.get(&url).is_some_and(|item| matches!(item.status.as_str(), "downloading" | "quarantined")) {
+            return Err(LuminaError::InvalidInput("Cancel the download before removing it".to_string()));
+        }
+        data.remove(&url).ok_or_else(|| LuminaError::NotFound("Download not found".to_string()))?
+    };
+    manager.save();
+    refresh_tray_menu(&app);
+    undo::record(&app, format!("İndirme listeden kaldırıldı: {}", removed.file_name), undo::Tombstone::Download(removed));
+    Ok(())
+}
+
+/// Puts a stopped download back in the queue, so it counts against max_concurrent_downloads like any other.
+#[tauri::command]
+pub(crate) fn resume_download(app: AppHandle, url: String) -> Result<(), LuminaError> {
+    let item = app.state::<DownloadManager>().downloads.lock().unwrap().get(&url).cloned();
+    let item = item.ok_or_else(|| LuminaError::NotFound("Download not found".to_string()))?;
+    if !matches!(item.status.as_str(), "paused" | "failed") {
+        return Err(LuminaError::InvalidInput("Only paused or failed downloads can be resumed".to_string()));
+    }
+    enqueue_download(&app, item.url, item.file_name, None, item.private);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_disposition_prefers_the_encoded_name() {
+        assert_eq!(
+            parse_content_disposition(r#"attachment; filename="plain.pdf"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf"#).as_deref(),
+            Some("résumé.pdf")
+        );
+        assert_eq!(parse_content_disposition(r#"attachment; filename="a \"quoted\" name.txt""#).as_deref(), Some(r#"a "quoted" name.txt"#));
+        assert_eq!(parse_content_disposition("inline"), None);
+        assert_eq!(parse_content_disposition(r#"attachment; filename="""#), None);
+    }
+
+    #[test]
+    fn filenames_are_safe_everywhere() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename(r"C:\Windows\evil.exe"), "evil.exe");
+        assert_eq!(sanitize_filename("a<b>c:d|e?.txt"), "a_b_c_d_e_.txt");
+        assert_eq!(sanitize_filename(" .hidden. "), "hidden");
+        assert_eq!(sanitize_filename("CON.txt"), "_CON.txt");
+        assert_eq!(sanitize_filename("com1"), "_com1");
+        assert_eq!(sanitize_filename("console.log"), "console.log");
+        assert_eq!(sanitize_filename("..."), "download");
+    }
+
+    #[test]
+    fn long_filenames_keep_their_extension() {
+        let name = sanitize_filename(&format!("{}.pdf", "x".repeat(300)));
+        assert_eq!(name.chars().count(), 200);
+        assert!(name.ends_with(".pdf"));
+    }
+
+    #[test]
+    fn content_range_gives_the_full_size() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::CONTENT_RANGE, "bytes 500-999/1000".parse().unwrap());
+        assert_eq!(content_range_start(&headers), Some(500));
+        assert_eq!(content_range_total(&headers), Some(1000));
+        headers.insert(reqwest::header::CONTENT_RANGE, "bytes 500-999/*".parse().unwrap());
+        assert_eq!(content_range_total(&headers), None);
+    }
+
+    #[test]
+    fn url_filenames_are_decoded_without_the_query() {
+        assert_eq!(filename_from_url(&Url::parse("https://example.com/files/my%20file.zip?token=1").unwrap()), "my file.zip");
+        assert_eq!(filename_from_url(&Url::parse("https://example.com/").unwrap()), "downloaded_file");
+    }
+}
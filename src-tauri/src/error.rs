@@ -0,0 +1,113 @@
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::events::{self, AppEvent};
+use crate::ToastPayload;
+
+/// Error returned by commands. Reaches the frontend as `{ kind, message }`, so callers can tell a
+/// missing tab from a network failure and still show `message` as is.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum LuminaError {
+    /// A tab, window, download or other item the command refers to doesn't exist
+    NotFound(String),
+    InvalidInput(String),
+    Io(String),
+    Network(String),
+    Database(String),
+    /// The webview or window layer refused the operation
+    Webview(String),
+    /// The calling webview isn't allowed to use the command
+    PermissionDenied(String),
+    Other(String),
+}
+
+impl LuminaError {
+    pub fn message(&self) -> &str {
+        match self {
+            LuminaError::NotFound(m)
+            | LuminaError::InvalidInput(m)
+            | LuminaError::Io(m)
+            | LuminaError::Network(m)
+            | LuminaError::Database(m)
+            | LuminaError::Webview(m)
+            | LuminaError::PermissionDenied(m)
+            | LuminaError::Other(m) => m,
+        }
+    }
+}
+
+impl std::fmt::Display for LuminaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for LuminaError {}
+
+impl From<String> for LuminaError {
+    fn from(message: String) -> Self {
+        LuminaError::Other(message)
+    }
+}
+
+impl From<&str> for LuminaError {
+    fn from(message: &str) -> Self {
+        LuminaError::Other(message.to_string())
+    }
+}
+
+impl From<std::io::Error> for LuminaError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => LuminaError::NotFound(e.to_string()),
+            _ => LuminaError::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for LuminaError {
+    fn from(e: rusqlite::Error) -> Self {
+        LuminaError::Database(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for LuminaError {
+    fn from(e: reqwest::Error) -> Self {
+        LuminaError::Network(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for LuminaError {
+    fn from(e: tauri::Error) -> Self {
+        LuminaError::Webview(e.to_string())
+    }
+}
+
+impl From<url::ParseError> for LuminaError {
+    fn from(e: url::ParseError) -> Self {
+        LuminaError::InvalidInput(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for LuminaError {
+    fn from(e: serde_json::Error) -> Self {
+        LuminaError::InvalidInput(e.to_string())
+    }
+}
+
+impl From<tauri_plugin_shell::Error> for LuminaError {
+    fn from(e: tauri_plugin_shell::Error) -> Self {
+        LuminaError::Other(e.to_string())
+    }
+}
+
+/// Where failures nobody awaits end up (background tasks, fire-and-forget commands): logged and shown as
+/// an error toast in the main window.
+pub fn report(app: &AppHandle, context: &str, err: &LuminaError) {
+    eprintln!("Lumina: {}: {}", context, err);
+    events::emit(app, AppEvent::Toast(ToastPayload {
+        message: format!("{}: {}", context, err),
+        level: "error".to_string(),
+    }));
+}
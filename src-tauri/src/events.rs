@@ -0,0 +1,190 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, EventTarget, Manager};
+
+use crate::crash_recovery::TabCrashedPayload;
+use crate::data::Flags;
+use crate::layout::{tab_window, UiState};
+use crate::adblock::{AdblockStatePayload, AdblockStatsPayload};
+use crate::downloads::{DownloadFinishedPayload, DownloadProgressPayload, DownloadShelfEvent, DownloadStartedPayload};
+use crate::media_capture::MediaCapturePayload;
+use crate::network::NetworkStatus;
+use crate::notes::SiteNoteStatusPayload;
+use crate::pwa::TabPwaPayload;
+use crate::scripting::LuaScriptStatus;
+use crate::site_prompts::{SitePrompt, SitePromptClosedPayload};
+use crate::speech::ReadAloudStatePayload;
+use crate::store::InstallRequestedPayload;
+use crate::undo::UndoableActionPayload;
+use crate::tabs::{
+    NavigationStatePayload, PopupBlockedPayload, TabClosedPayload, TabCreatedPayload, TabDetachedPayload, TabNavigationPayload,
+    TabUpdatedPayload,
+};
+use crate::{FavoritesHealthSummary, ThemeChangedPayload, ToastPayload};
+
+/// Every event the backend sends to the frontend. The serde tag is the event name the frontend listens for.
+#[derive(Serialize)]
+#[serde(tag = "event", content = "payload", rename_all = "kebab-case")]
+pub(crate) enum AppEvent {
+    Toast(ToastPayload),
+    ScriptsReloaded(Vec<LuaScriptStatus>),
+    /// Blocked-request counts of the tabs that changed since the last batch, for every window
+    AdblockStatsUpdate(Vec<AdblockStatsPayload>),
+    AdblockStateChanged(AdblockStatePayload),
+    PopupBlocked(PopupBlockedPayload),
+    /// Already-serialized suggestion JSON
+    OmniboxResults(String),
+    NavigationState(NavigationStatePayload),
+    TabCreated(TabCreatedPayload),
+    TabUpdated(TabUpdatedPayload),
+    TabNavigation(TabNavigationPayload),
+    TabHibernated(String),
+    TabDetached(TabDetachedPayload),
+    TabClosed(TabClosedPayload),
+    TabCrashed(TabCrashedPayload),
+    FocusTab(String),
+    FavoritesHealthChecked(FavoritesHealthSummary),
+    ThemeChanged(ThemeChangedPayload),
+    PwaCanInstall(TabPwaPayload),
+    SiteNoteStatus(SiteNoteStatusPayload),
+    ReadAloudState(ReadAloudStatePayload),
+    FlagsChanged(Flags),
+    DownloadShelf(DownloadShelfEvent),
+    DownloadStarted(DownloadStartedPayload),
+    DownloadProgress(DownloadProgressPayload),
+    DownloadFinished(DownloadFinishedPayload),
+    ExtensionsChanged,
+    NetworkStatusChanged(NetworkStatus),
+    InstallRequested(InstallRequestedPayload),
+    RequestNewTab(TabCreatedPayload),
+    RequestPrivateTab,
+    ToggleCommandPalette,
+    /// Something was deleted that `undo_action` can bring back for a while
+    UndoableAction(UndoableActionPayload),
+    /// A tab started or stopped using the camera, microphone or screen capture
+    MediaCaptureChanged(MediaCapturePayload),
+    /// A site wants an answer from the user (notifications, an external app); shown in the infobar
+    SitePrompt(SitePrompt),
+    /// A prompt was answered or its tab went away
+    SitePromptClosed(SitePromptClosedPayload),
+}
+
+impl AppEvent {
+    /// Whether a window that starts late should see this event. Progress ticks and one-off requests
+    /// (open a tab, show the palette) are left out: replaying them would repeat an action or just add noise.
+    fn replayable(&self) -> bool {
+        !matches!(
+            self,
+            AppEvent::Toast(_)
+                | AppEvent::AdblockStatsUpdate(_)
+                | AppEvent::OmniboxResults(_)
+                | AppEvent::FocusTab(_)
+                | AppEvent::TabCrashed(_)
+                | AppEvent::DownloadProgress(_)
+                | AppEvent::InstallRequested(_)
+                | AppEvent::RequestNewTab(_)
+                | AppEvent::RequestPrivateTab
+                | AppEvent::ToggleCommandPalette
+                | AppEvent::UndoableAction(_)
+                | AppEvent::SitePrompt(_)
+                | AppEvent::SitePromptClosed(_)
+        )
+    }
+
+    /// The browser window an event is for: the tab's own window for tab events, the window in use
+    /// for requests to the UI. `None` goes to every window. `payload` is the event's serialized payload.
+    fn target_window(&self, app: &AppHandle, payload: &serde_json::Value) -> Option<String> {
+        match self {
+            AppEvent::OmniboxResults(_)
+            | AppEvent::RequestNewTab(_)
+            | AppEvent::RequestPrivateTab
+            | AppEvent::ToggleCommandPalette
+            | AppEvent::UndoableAction(_) => Some(app.state::<UiState>().focused_window()),
+            AppEvent::PopupBlocked(_)
+            | AppEvent::NavigationState(_)
+            | AppEvent::TabCreated(_)
+            | AppEvent::TabUpdated(_)
+            | AppEvent::TabNavigation(_)
+            | AppEvent::TabHibernated(_)
+            | AppEvent::TabClosed(_)
+            | AppEvent::TabCrashed(_)
+            | AppEvent::FocusTab(_)
+            | AppEvent::PwaCanInstall(_)
+            | AppEvent::SiteNoteStatus(_)
+            | AppEvent::MediaCaptureChanged(_) => {
+                let label = payload.get("label").and_then(|label| label.as_str()).or(payload.as_str())?;
+                // A tab that's already gone has no window; its event goes everywhere, where unknown labels are ignored
+                tab_window(app, label)
+            }
+            AppEvent::SitePrompt(prompt) => Some(prompt.window.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedEvent {
+    pub seq: u64,
+    pub timestamp: i64,
+    pub event: String,
+    pub payload: serde_json::Value,
+    /// Browser window the event was sent to, if not all of them
+    #[serde(skip)]
+    window: Option<String>,
+}
+
+const REPLAY_CAPACITY: usize = 200;
+
+static REPLAY: Mutex<VecDeque<RecordedEvent>> = Mutex::new(VecDeque::new());
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(1);
+
+/// Sends an event to the browser window it concerns (every window for app-wide events) and keeps it
+/// for `recent` if it is replayable. Replayable events with an object payload carry their `seq` in
+/// it, so a window replaying what it missed can tell which of them it already got live.
+pub(crate) fn emit(app: &AppHandle, event: AppEvent) {
+    let replayable = event.replayable();
+    let Ok(serde_json::Value::Object(mut tagged)) = serde_json::to_value(&event) else { return };
+    let name = match tagged.remove("event") {
+        Some(serde_json::Value::String(name)) => name,
+        _ => return,
+    };
+    let mut payload = tagged.remove("payload").unwrap_or(serde_json::Value::Null);
+    let window = event.target_window(app, &payload);
+
+    if replayable {
+        let seq = NEXT_SEQ.fetch_add(1, Ordering::Relaxed);
+        if let serde_json::Value::Object(fields) = &mut payload {
+            fields.insert("seq".to_string(), seq.into());
+        }
+        let mut replay = REPLAY.lock().unwrap();
+        if replay.len() == REPLAY_CAPACITY {
+            replay.pop_front();
+        }
+        replay.push_back(RecordedEvent {
+            seq,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            event: name.clone(),
+            payload: payload.clone(),
+            window: window.clone(),
+        });
+    }
+    let _ = match window {
+        Some(window) => app.emit_to(EventTarget::webview_window(window), &name, payload),
+        None => app.emit(&name, payload),
+    };
+}
+
+/// Buffered events after `since` (a `seq`), oldest first. With `window`, only what that window was sent.
+pub fn recent(since: Option<u64>, window: Option<&str>) -> Vec<RecordedEvent> {
+    let since = since.unwrap_or(0);
+    REPLAY
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| e.seq > since && (window.is_none() || e.window.is_none() || e.window.as_deref() == window))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,157 @@
+use tauri::{AppHandle, Manager, Url, Webview};
+
+use crate::adblock::normalize_site_domain;
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::history_manager::HistoryManager;
+use crate::isolation::site_group;
+use crate::layout::is_browser_window;
+use crate::tab_registry::{TabRecord, TabRegistry};
+
+/// Sites are kept by registrable domain, the unit WebKitGTK stores site data under
+fn site_of(url: &str) -> Option<String> {
+    site_group(&Url::parse(url).ok()?)
+}
+
+fn is_forgotten(app: &AppHandle, site: &str) -> bool {
+    app.state::<AppDataStore>().data.lock().unwrap().settings.forget_on_close_sites.iter().any(|s| s == site)
+}
+
+/// Whether anything besides the closed tab still shows the site: another tab (hibernated ones by
+/// their real URL), a detached tab's window or an installed app's window.
+fn site_still_open(app: &AppHandle, site: &str, closed: &TabRecord, open: &[TabRecord]) -> bool {
+    open.iter().any(|tab| site_of(&tab.url).as_deref() == Some(site))
+        || app.webviews().values().any(|webview| {
+            webview.label() != closed.label
+                && !is_browser_window(webview.label())
+                && webview.url().ok().and_then(|url| site_group(&url)).as_deref() == Some(site)
+        })
+}
+
+/// Hooks into tab closing, so closing the last tab of a listed site deletes its data.
+pub(crate) fn watch_tab_closes(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<TabRegistry>().on_close(move |closed, open| {
+        let Some(site) = site_of(&closed.url) else { return };
+        if !is_forgotten(&handle, &site) || site_still_open(&handle, &site, closed, open) {
+            return;
+        }
+        let handle = handle.clone();
+        let closed = closed.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = forget(&handle, &site, &closed).await {
+                eprintln!("Failed to forget {}: {}", site, e);
+            }
+        });
+    });
+}
+
+fn cookie_in_site(cookie_domain: Option<&str>, site: &str) -> bool {
+    let Some(domain) = cookie_domain else { return false };
+    let domain = domain.trim_start_matches('.');
+    domain == site || domain.ends_with(&format!(".{}", site))
+}
+
+/// Deletes the site's saved cookies, then its cookies and storage in the shared browser profile.
+/// Tabs in a site-isolated profile keep theirs in that profile's folder.
+async fn forget(app: &AppHandle, site: &str, closed: &TabRecord) -> Result<(), LuminaError> {
+    app.state::<HistoryManager>().delete_cookies_for_domain(site)?;
+
+    // A browser window's own webview shares the profile, and outlives the tab
+    let webview = app
+        .webviews()
+        .into_values()
+        .find(|webview| is_browser_window(webview.label()))
+        .ok_or_else(|| LuminaError::NotFound("No browser window open".to_string()))?;
+    let cookie_webview = webview.clone();
+    let cookie_site = site.to_string();
+    // Reading cookies can block on the webview's thread
+    tauri::async_runtime::spawn_blocking(move || -> tauri::Result<()> {
+        for cookie in cookie_webview.cookies()? {
+            if cookie_in_site(cookie.domain(), &cookie_site) {
+                cookie_webview.delete_cookie(cookie)?;
+            }
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| LuminaError::Other(e.to_string()))??;
+
+    let mut origins = vec![format!("https://{}", site), format!("http://{}", site), format!("https://www.{}", site), format!("http://www.{}", site)];
+    if let Ok(url) = Url::parse(&closed.url) {
+        let origin = url.origin().ascii_serialization();
+        if !origins.contains(&origin) {
+            origins.push(origin);
+        }
+    }
+    clear_site_storage(&webview, site, &origins).await.map_err(LuminaError::Webview)
+}
+
+#[cfg(windows)]
+async fn clear_site_storage(webview: &Webview, _site: &str, origins: &[String]) -> Result<(), String> {
+    for origin in origins {
+        let params = serde_json::json!({ "origin": origin, "storageTypes": "all" }).to_string();
+        crate::screenshot::call_devtools(webview, "Storage.clearDataForOrigin", &params).await?;
+    }
+    Ok(())
+}
+
+/// WebKitGTK lists site data per registrable domain, so everything under the site goes at once.
+#[cfg(target_os = "linux")]
+async fn clear_site_storage(webview: &Webview, site: &str, _origins: &[String]) -> Result<(), String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let site = site.to_string();
+    webview.with_webview(move |webview| {
+        use webkit2gtk::{WebViewExt, WebsiteDataManagerExt, WebsiteDataManagerExtManual, WebsiteDataTypes};
+        let Some(manager) = webview.inner().website_data_manager() else {
+            let _ = tx.send(Err("The webview has no data manager".to_string()));
+            return;
+        };
+        let fetched = manager.clone();
+        manager.fetch(WebsiteDataTypes::ALL, None::<&webkit2gtk::gio::Cancellable>, move |result| {
+            let records = match result {
+                Ok(records) => records,
+                Err(e) => {
+                    let _ = tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            let matching: Vec<_> = records.iter().filter(|record| cookie_in_site(record.name().as_deref(), &site)).collect();
+            if matching.is_empty() {
+                let _ = tx.send(Ok(()));
+                return;
+            }
+            fetched.remove(WebsiteDataTypes::ALL, &matching, None::<&webkit2gtk::gio::Cancellable>, move |result| {
+                let _ = tx.send(result.map_err(|e| e.to_string()));
+            });
+        });
+    }).map_err(|e| e.to_string())?;
+    rx.await.map_err(|_| "The webview dropped the request".to_string())?
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+async fn clear_site_storage(_webview: &Webview, _site: &str, _origins: &[String]) -> Result<(), String> {
+    Err("Clearing site storage is not supported on this platform yet".to_string())
+}
+
+/// Lists a site to be forgotten when its last tab closes, or takes it off. Any host is stored as
+/// its registrable domain, so "mail.example.com" lists example.com.
+#[tauri::command]
+pub(crate) fn set_forget_on_close(state: tauri::State<'_, AppDataStore>, domain: String, enabled: bool) -> Result<(), LuminaError> {
+    let host = normalize_site_domain(&domain);
+    let site = psl::domain_str(&host).unwrap_or(&host).to_string();
+    if site.is_empty() || !site.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.')) {
+        return Err(LuminaError::InvalidInput(format!("Invalid domain: {}", domain)));
+    }
+    {
+        let mut data = state.data.lock().unwrap();
+        let sites = &mut data.settings.forget_on_close_sites;
+        sites.retain(|s| s != &site);
+        if enabled {
+            sites.push(site);
+            sites.sort();
+        }
+    }
+    state.save();
+    Ok(())
+}
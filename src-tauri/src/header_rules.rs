@@ -0,0 +1,197 @@
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+
+use crate::adblock::normalize_site_domain;
+use crate::error::LuminaError;
+use crate::history_manager::{HeaderRule, HistoryManager};
+use crate::isolation::site_group;
+#[cfg(windows)]
+use crate::referrer;
+
+/// Headers the network stack owns; changing them breaks requests instead of customizing them
+const PROTECTED_HEADERS: &[&str] = &["host", "content-length", "transfer-encoding", "connection", "upgrade"];
+const MAX_VALUE_LEN: usize = 8192;
+
+/// Enabled rules, loaded from the database at startup and after every edit
+static HEADER_RULES: OnceLock<Mutex<Vec<HeaderRule>>> = OnceLock::new();
+
+fn header_rules() -> &'static Mutex<Vec<HeaderRule>> {
+    HEADER_RULES.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+pub(crate) fn load(app: &AppHandle) {
+    let rules = app.state::<HistoryManager>().get_header_rules().unwrap_or_default();
+    *header_rules().lock().unwrap() = rules.into_iter().filter(|rule| rule.enabled).collect();
+}
+
+/// What to do to one header of a request
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) enum HeaderEdit {
+    Set(String, String),
+    Remove(String),
+}
+
+/// Edits for a request to `url` made by a page showing `initiator`, by domain, then in the order the
+/// rules were added. Values are only set on https requests from a page of the rule's own site, so a
+/// header meant for one site (an auth token, say) never goes out in clear text or to another site's
+/// embeds of it. Removals always apply.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn edits_for(url: &tauri::Url, initiator: Option<&tauri::Url>) -> Vec<HeaderEdit> {
+    let Some(host) = url.host_str() else { return Vec::new() };
+    let host = host.trim_start_matches("www.");
+    let initiator_site = initiator.and_then(site_group);
+    header_rules()
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|rule| host == rule.domain || host.ends_with(&format!(".{}", rule.domain)))
+        .filter_map(|rule| match (rule.action.as_str(), &rule.value) {
+            ("set", Some(value)) => {
+                let rule_site = psl::domain_str(&rule.domain).unwrap_or(&rule.domain);
+                (url.scheme() == "https" && initiator_site.as_deref() == Some(rule_site))
+                    .then(|| HeaderEdit::Set(rule.name.clone(), value.clone()))
+            }
+            _ => Some(HeaderEdit::Remove(rule.name.clone())),
+        })
+        .collect()
+}
+
+fn validated_name(name: &str) -> Result<String, LuminaError> {
+    let name = name.trim();
+    // RFC 9110 token characters
+    let is_token = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    if name.is_empty() || !name.chars().all(is_token) {
+        return Err(LuminaError::InvalidInput(format!("Invalid header name: {}", name)));
+    }
+    if PROTECTED_HEADERS.contains(&name.to_lowercase().as_str()) {
+        return Err(LuminaError::InvalidInput(format!("The {} header can't be changed", name)));
+    }
+    Ok(name.to_string())
+}
+
+/// Adds a rule, or replaces the one with `id`. `action` is "set" (needs a value) or "remove".
+#[tauri::command]
+pub(crate) fn save_header_rule(
+    app: AppHandle,
+    id: Option<String>,
+    domain: String,
+    action: String,
+    name: String,
+    value: Option<String>,
+    enabled: bool,
+) -> Result<HeaderRule, LuminaError> {
+    let domain = normalize_site_domain(&domain);
+    if domain.is_empty() {
+        return Err(LuminaError::InvalidInput("A domain is required".to_string()));
+    }
+    let name = validated_name(&name)?;
+    let value = match action.as_str() {
+        "set" => {
+            let value = value.unwrap_or_default();
+            if value.len() > MAX_VALUE_LEN || value.contains(['\r', '\n']) {
+                return Err(LuminaError::InvalidInput("Header values must be a single line".to_string()));
+            }
+            Some(value)
+        }
+        "remove" => None,
+        _ => return Err(LuminaError::InvalidInput(format!("Unknown header action: {}", action))),
+    };
+
+    let history_manager = app.state::<HistoryManager>();
+    let existing = match &id {
+        Some(id) => Some(
+            history_manager
+                .get_header_rules()?
+                .into_iter()
+                .find(|rule| &rule.id == id)
+                .ok_or_else(|| LuminaError::NotFound(format!("Header rule {} not found", id)))?,
+        ),
+        None => None,
+    };
+    let rule = HeaderRule {
+        id: id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        domain,
+        action,
+        name,
+        value,
+        enabled,
+        created_at: existing.map_or_else(|| chrono::Utc::now().timestamp(), |rule| rule.created_at),
+    };
+    history_manager.save_header_rule(&rule)?;
+    load(&app);
+    Ok(rule)
+}
+
+#[tauri::command]
+pub(crate) fn delete_header_rule(app: AppHandle, id: String) -> Result<(), LuminaError> {
+    if !app.state::<HistoryManager>().delete_header_rule(&id)? {
+        return Err(LuminaError::NotFound(format!("Header rule {} not found", id)));
+    }
+    load(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn list_header_rules(history_manager: tauri::State<'_, HistoryManager>) -> Result<Vec<HeaderRule>, LuminaError> {
+    history_manager.get_header_rules().map_err(LuminaError::from)
+}
+
+/// Trims the Referer per the referrer policy, then applies the rules, on every request the tab
+/// makes (WebView2's WebResourceRequested). Rules win, so one can still set a Referer.
+#[cfg(windows)]
+pub(crate) fn watch_requests(webview: &tauri::Webview) {
+    let _ = webview.with_webview(move |webview| {
+        use webview2_com::Microsoft::Web::WebView2::Win32::*;
+        use webview2_com::{take_pwstr, WebResourceRequestedEventHandler};
+        use windows_core::{HSTRING, PWSTR};
+
+        let handler = WebResourceRequestedEventHandler::create(Box::new(move |sender, args| {
+            let Some(args) = args else { return Ok(()) };
+            let request = unsafe { args.Request()? };
+            let mut uri = PWSTR::null();
+            unsafe { request.Uri(&mut uri)? };
+            let uri = take_pwstr(uri);
+            let headers = unsafe { request.Headers()? };
+
+            let referer_name = HSTRING::from("Referer");
+            let mut referer = PWSTR::null();
+            if unsafe { headers.GetHeader(&referer_name, &mut referer) }.is_ok() {
+                let referer = take_pwstr(referer);
+                match referrer::trim_referer(&referer) {
+                    Some(trimmed) if trimmed != referer => unsafe { headers.SetHeader(&referer_name, &HSTRING::from(trimmed))? },
+                    Some(_) => {}
+                    None => unsafe { headers.RemoveHeader(&referer_name)? },
+                }
+            }
+
+            let Ok(url) = tauri::Url::parse(&uri) else { return Ok(()) };
+            // The document the tab shows is the one making the request
+            let initiator = sender.and_then(|core| {
+                let mut source = PWSTR::null();
+                unsafe { core.Source(&mut source) }.ok()?;
+                tauri::Url::parse(&take_pwstr(source)).ok()
+            });
+            for edit in edits_for(&url, initiator.as_ref()) {
+                unsafe {
+                    match edit {
+                        HeaderEdit::Set(name, value) => headers.SetHeader(&HSTRING::from(name), &HSTRING::from(value))?,
+                        HeaderEdit::Remove(name) => headers.RemoveHeader(&HSTRING::from(name))?,
+                    }
+                }
+            }
+            Ok(())
+        }));
+        unsafe {
+            if let Ok(core) = webview.controller().CoreWebView2() {
+                let _ = core.AddWebResourceRequestedFilter(&HSTRING::from("*"), COREWEBVIEW2_WEB_RESOURCE_CONTEXT_ALL);
+                let mut token = 0i64;
+                let _ = core.add_WebResourceRequested(&handler, &mut token);
+            }
+        }
+    });
+}
+
+/// WebKitGTK only lets a web process extension touch outgoing headers, and Lumina doesn't ship
+/// one, so the rules are kept but not applied on Linux. The referrer meta tag still is.
+#[cfg(not(windows))]
+pub(crate) fn watch_requests(_webview: &tauri::Webview) {}
@@ -1,15 +1,82 @@
 use rusqlite::{params, Connection, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::secrets::FieldCipher;
+
+/// Stable id for history rows and favorites, so they survive URL edits, imports and sync.
+pub fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// The form a URL is stored and compared in: no fragment, lowercase scheme and host, no default
+/// port, and no trailing slash except on the root path. Anything unparseable is kept as is.
+pub fn canonicalize_url(url: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(url.trim()) else {
+        return url.trim().to_string();
+    };
+    parsed.set_fragment(None);
+    if !parsed.cannot_be_a_base() && parsed.path().len() > 1 && parsed.path().ends_with('/') {
+        let path = parsed.path().trim_end_matches('/').to_string();
+        parsed.set_path(if path.is_empty() { "/" } else { &path });
+    }
+    parsed.to_string()
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HistoryItem {
+    pub id: String,
     pub url: String,
     pub title: String,
     pub visit_count: i64,
     pub last_visit: i64,
 }
 
+/// A page ranked by visits in a period
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TopSite {
+    pub url: String,
+    pub title: String,
+    pub visits: i64,
+    pub last_visit: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DayVisits {
+    /// Local date, YYYY-MM-DD
+    pub day: String,
+    pub visits: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainVisits {
+    pub domain: String,
+    pub visits: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowsingStats {
+    pub total_visits: i64,
+    pub distinct_domains: i64,
+    /// Oldest first; days without visits are left out
+    pub visits_per_day: Vec<DayVisits>,
+    pub top_domains: Vec<DomainVisits>,
+    /// Visits by local hour of day, 0 to 23
+    pub visits_per_hour: Vec<i64>,
+}
+
+/// Host without "www.", as the visit log groups it
+fn visit_host(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.trim_start_matches("www.").to_string()))
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CookieItem {
     pub domain: String,
@@ -40,6 +107,69 @@ pub struct WebStorageItem {
     pub storage_type: String, // "localStorage" or "sessionStorage"
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostBlockEntry {
+    pub domain: String,
+    pub source: String, // "default", "user" or "import"
+    pub added_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SitePermission {
+    pub origin: String,
+    pub permission: String, // e.g. "notifications"
+    pub state: String,      // "allow" or "deny"
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SiteNote {
+    pub domain: String,
+    pub content: String, // Markdown
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UrlAlias {
+    pub alias: String,
+    /// May contain `%s`, replaced by whatever is typed after the alias
+    pub url: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderRule {
+    pub id: String,
+    /// Applies to this host and its subdomains
+    pub domain: String,
+    /// "set" or "remove"
+    pub action: String,
+    pub name: String,
+    /// Only for "set"; encrypted at rest, since it is often a credential
+    pub value: Option<String>,
+    pub enabled: bool,
+    pub created_at: i64,
+}
+
+/// An identity for form autofill. Everything but `id` is stored encrypted as one JSON blob.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AutofillProfile {
+    pub id: String,
+    /// What the profile is called in the UI, e.g. "Home" or "Work"
+    pub label: String,
+    pub full_name: String,
+    pub email: String,
+    pub phone: String,
+    pub organization: String,
+    pub street_address: String,
+    pub city: String,
+    pub region: String,
+    pub postal_code: String,
+    pub country: String,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ZoomLevel {
@@ -49,12 +179,24 @@ pub struct ZoomLevel {
 
 pub struct HistoryManager {
     db_path: PathBuf,
+    /// Encrypts cookie and form values. `None` only if no key could be loaded or stored, in which
+    /// case those values aren't written at all (see `key_error`).
+    cipher: Option<FieldCipher>,
+    /// Why the key couldn't be loaded, for the user to hear about once the UI is up
+    key_error: Option<String>,
 }
 
 impl HistoryManager {
     pub fn new(app_data_dir: PathBuf) -> Self {
+        let (cipher, key_error) = match FieldCipher::load(&app_data_dir) {
+            Ok(cipher) => (Some(cipher), None),
+            Err(e) => {
+                eprintln!("Failed to load the field encryption key, cookies and form data won't be saved: {}", e);
+                (None, Some(e))
+            }
+        };
         let db_path = app_data_dir.join("history.db");
-        let manager = Self { db_path };
+        let manager = Self { db_path, cipher, key_error };
         if let Err(e) = manager.init() {
             eprintln!("Failed to initialize history database: {}", e);
         }
@@ -73,10 +215,26 @@ impl HistoryManager {
                 url TEXT NOT NULL UNIQUE,
                 title TEXT,
                 visit_count INTEGER DEFAULT 1,
-                last_visit INTEGER
+                last_visit INTEGER,
+                uuid TEXT
+            )",
+            [],
+        )?;
+        self.migrate_history_ids()?;
+        conn.execute("CREATE UNIQUE INDEX IF NOT EXISTS idx_history_uuid ON history(uuid)", [])?;
+
+        // One row per visit, for statistics; `history` only keeps a count per page
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS history_visits (
+                id INTEGER PRIMARY KEY,
+                url TEXT NOT NULL,
+                host TEXT NOT NULL,
+                visited_at INTEGER NOT NULL
             )",
             [],
         )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_history_visits_time ON history_visits(visited_at)", [])?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_history_visits_url ON history_visits(url)", [])?;
 
         // Cookies table
         conn.execute(
@@ -133,23 +291,285 @@ impl HistoryManager {
             [],
         )?;
 
+        // Internal bookkeeping (one-time seeds, migrations)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS app_meta (
+                key TEXT PRIMARY KEY,
+                value TEXT
+            )",
+            [],
+        )?;
+
+        // Host-level blocklist used alongside the filter engine
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS host_blocklist (
+                id INTEGER PRIMARY KEY,
+                domain TEXT NOT NULL UNIQUE,
+                source TEXT DEFAULT 'user',
+                added_at INTEGER
+            )",
+            [],
+        )?;
+
+        // Sites where the adblocker is turned off
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS adblock_exceptions (
+                id INTEGER PRIMARY KEY,
+                domain TEXT NOT NULL UNIQUE,
+                created_at INTEGER
+            )",
+            [],
+        )?;
+
+        // Per-package key-value storage for Lua extensions
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS lua_storage (
+                id INTEGER PRIMARY KEY,
+                package_id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at INTEGER,
+                UNIQUE(package_id, key)
+            )",
+            [],
+        )?;
+
+        // Capabilities granted to Lua packages (e.g. "tabs.eval")
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS lua_permissions (
+                id INTEGER PRIMARY KEY,
+                package_id TEXT NOT NULL,
+                permission TEXT NOT NULL,
+                granted_at INTEGER,
+                UNIQUE(package_id, permission)
+            )",
+            [],
+        )?;
+
+        // Lua console command history
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS lua_console_history (
+                id INTEGER PRIMARY KEY,
+                code TEXT NOT NULL,
+                created_at INTEGER
+            )",
+            [],
+        )?;
+
+        // Per-origin web permissions (notifications, ...)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS site_permissions (
+                id INTEGER PRIMARY KEY,
+                origin TEXT NOT NULL,
+                permission TEXT NOT NULL,
+                state TEXT NOT NULL,
+                updated_at INTEGER,
+                UNIQUE(origin, permission)
+            )",
+            [],
+        )?;
+
+        // Per-site content protection level ("default", "aggressive" or "off"), matched as a host substring
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS site_protections (
+                pattern TEXT PRIMARY KEY,
+                level TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // One Markdown note per domain
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS site_notes (
+                domain TEXT PRIMARY KEY,
+                content TEXT NOT NULL,
+                updated_at INTEGER
+            )",
+            [],
+        )?;
+
+        // Per-domain dark mode overrides (1 = force on, 0 = force off)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS dark_mode_sites (
+                domain TEXT PRIMARY KEY,
+                enabled INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Form-fill identities; `data` is an AutofillProfile as JSON, encrypted when a key is available
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS autofill_profiles (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                updated_at INTEGER
+            )",
+            [],
+        )?;
+
+        // Named tab sets; `tabs` is a list of SavedSessionTab as JSON
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS saved_sessions (
+                name TEXT PRIMARY KEY,
+                tabs TEXT NOT NULL,
+                saved_at INTEGER
+            )",
+            [],
+        )?;
+
+        // Omnibox shortcuts: typing `alias` opens `url`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS url_aliases (
+                alias TEXT PRIMARY KEY,
+                url TEXT NOT NULL,
+                created_at INTEGER
+            )",
+            [],
+        )?;
+
+        // Per-site overrides of the referrer policy setting
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS referrer_policies (
+                domain TEXT PRIMARY KEY,
+                policy TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Request headers added or removed for a domain's requests
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS header_rules (
+                id TEXT PRIMARY KEY,
+                domain TEXT NOT NULL,
+                action TEXT NOT NULL,
+                name TEXT NOT NULL,
+                value TEXT,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at INTEGER
+            )",
+            [],
+        )?;
+
+        self.migrate_encrypted_fields()?;
+
         Ok(())
     }
 
+    /// Databases from before stable ids: gives every row a uuid and canonicalizes its URL, folding
+    /// rows that turn out to be the same page into one.
+    fn migrate_history_ids(&self) -> Result<()> {
+        let mut conn = self.connect()?;
+        let has_uuid = conn
+            .prepare("SELECT 1 FROM pragma_table_info('history') WHERE name = 'uuid'")?
+            .exists([])?;
+        if !has_uuid {
+            conn.execute("ALTER TABLE history ADD COLUMN uuid TEXT", [])?;
+        }
+
+        let tx = conn.transaction()?;
+        let rows: Vec<(i64, String)> = {
+            let mut stmt = tx.prepare("SELECT id, url FROM history WHERE uuid IS NULL")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        for (id, url) in rows {
+            let canonical = canonicalize_url(&url);
+            let existing: Option<i64> = tx
+                .query_row("SELECT id FROM history WHERE url = ?1 AND id != ?2", params![canonical, id], |row| row.get(0))
+                .ok();
+            match existing {
+                // Same page under another spelling: fold the visits into the row already there
+                Some(survivor) => {
+                    tx.execute(
+                        "UPDATE history SET
+                            visit_count = visit_count + (SELECT visit_count FROM history WHERE id = ?2),
+                            last_visit = MAX(IFNULL(last_visit, 0), (SELECT IFNULL(last_visit, 0) FROM history WHERE id = ?2))
+                         WHERE id = ?1",
+                        params![survivor, id],
+                    )?;
+                    tx.execute("DELETE FROM history WHERE id = ?1", params![id])?;
+                }
+                None => {
+                    tx.execute("UPDATE history SET url = ?2, uuid = ?3 WHERE id = ?1", params![id, canonical, new_id()])?;
+                }
+            }
+        }
+        tx.commit()
+    }
+
+    /// Encrypts cookie and form values still stored in plaintext, e.g. from before field encryption.
+    fn migrate_encrypted_fields(&self) -> Result<()> {
+        let Some(cipher) = &self.cipher else {
+            return Ok(());
+        };
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+
+        let cookies: Vec<(i64, String)> = {
+            let mut stmt = tx.prepare("SELECT id, value FROM cookies WHERE value IS NOT NULL")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        for (id, value) in cookies.into_iter().filter(|(_, value)| !FieldCipher::is_encrypted(value)) {
+            tx.execute("UPDATE cookies SET value = ?2 WHERE id = ?1", params![id, cipher.encrypt(&value)])?;
+        }
+
+        let form_values: Vec<(i64, String, String, String)> = {
+            let mut stmt = tx.prepare("SELECT id, field_name, domain, field_value FROM form_data WHERE field_value IS NOT NULL")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        for (id, field_name, domain, value) in form_values.into_iter().filter(|(.., value)| !FieldCipher::is_encrypted(value)) {
+            // REPLACE: an encrypted copy of the same entry may already exist if the key was missing for a while
+            tx.execute(
+                "UPDATE OR REPLACE form_data SET field_value = ?2 WHERE id = ?1",
+                params![id, self.seal_form_value(cipher, &field_name, &domain, &value)],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// The cipher for writing a sensitive value; without a key the write fails instead of going out
+    /// in plaintext.
+    fn sealing_cipher(&self) -> Result<&FieldCipher> {
+        self.cipher
+            .as_ref()
+            .ok_or_else(|| rusqlite::Error::ToSqlConversionFailure("no field encryption key".into()))
+    }
+
+    /// Form values are sealed deterministically per field and domain so the UNIQUE constraint and
+    /// the DISTINCT in `get_form_suggestions` keep working.
+    fn seal_form_value(&self, cipher: &FieldCipher, field_name: &str, domain: &str, value: &str) -> String {
+        cipher.encrypt_deterministic(&format!("form_data\0{}\0{}", domain, field_name), value)
+    }
+
+    /// Plaintext rows pass through; rows sealed under a lost key come back as `None`
+    fn open_field(&self, stored: String) -> Option<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(&stored),
+            None => (!FieldCipher::is_encrypted(&stored)).then_some(stored),
+        }
+    }
+
     pub fn add_visit(&self, url: String, title: String) -> Result<()> {
         let conn = self.connect()?;
         let now = chrono::Utc::now().timestamp();
+        let url = canonicalize_url(&url);
 
         // Upsert logic
         // SQLite has ON CONFLICT DO UPDATE
         conn.execute(
-            "INSERT INTO history (url, title, visit_count, last_visit) 
-             VALUES (?1, ?2, 1, ?3)
+            "INSERT INTO history (url, title, visit_count, last_visit, uuid) 
+             VALUES (?1, ?2, 1, ?3, ?4)
              ON CONFLICT(url) DO UPDATE SET 
                 visit_count = visit_count + 1,
                 last_visit = excluded.last_visit,
                 title = excluded.title",
-            params![url, title, now],
+            params![url, title, now, new_id()],
+        )?;
+        conn.execute(
+            "INSERT INTO history_visits (url, host, visited_at) VALUES (?1, ?2, ?3)",
+            params![url, visit_host(&url), now],
         )?;
         Ok(())
     }
@@ -157,7 +577,7 @@ impl HistoryManager {
     pub fn search(&self, query: &str) -> Result<Vec<HistoryItem>> {
         let conn = self.connect()?;
         let mut stmt = conn.prepare(
-            "SELECT url, title, visit_count, last_visit FROM history 
+            "SELECT uuid, url, title, visit_count, last_visit FROM history 
              WHERE url LIKE ?1 OR title LIKE ?1 
              ORDER BY visit_count DESC, last_visit DESC 
              LIMIT 20",
@@ -166,10 +586,11 @@ impl HistoryManager {
         let pattern = format!("%{}%", query);
         let rows = stmt.query_map(params![pattern], |row| {
             Ok(HistoryItem {
-                url: row.get(0)?,
-                title: row.get(1)?,
-                visit_count: row.get(2)?,
-                last_visit: row.get(3)?,
+                id: row.get(0)?,
+                url: row.get(1)?,
+                title: row.get(2)?,
+                visit_count: row.get(3)?,
+                last_visit: row.get(4)?,
             })
         })?;
 
@@ -183,17 +604,43 @@ impl HistoryManager {
     pub fn get_recent(&self, limit: i64) -> Result<Vec<HistoryItem>> {
         let conn = self.connect()?;
         let mut stmt = conn.prepare(
-            "SELECT url, title, visit_count, last_visit FROM history 
+            "SELECT uuid, url, title, visit_count, last_visit FROM history 
              ORDER BY last_visit DESC 
              LIMIT ?1",
         )?;
 
         let rows = stmt.query_map(params![limit], |row| {
             Ok(HistoryItem {
-                url: row.get(0)?,
-                title: row.get(1)?,
-                visit_count: row.get(2)?,
-                last_visit: row.get(3)?,
+                id: row.get(0)?,
+                url: row.get(1)?,
+                title: row.get(2)?,
+                visit_count: row.get(3)?,
+                last_visit: row.get(4)?,
+            })
+        })?;
+
+        let mut items = Vec::new();
+        for row in rows {
+            items.push(row?);
+        }
+        Ok(items)
+    }
+
+    pub fn get_page(&self, offset: i64, limit: i64) -> Result<Vec<HistoryItem>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare(
+            "SELECT uuid, url, title, visit_count, last_visit FROM history 
+             ORDER BY last_visit DESC 
+             LIMIT ?1 OFFSET ?2",
+        )?;
+
+        let rows = stmt.query_map(params![limit, offset], |row| {
+            Ok(HistoryItem {
+                id: row.get(0)?,
+                url: row.get(1)?,
+                title: row.get(2)?,
+                visit_count: row.get(3)?,
+                last_visit: row.get(4)?,
             })
         })?;
 
@@ -204,24 +651,186 @@ impl HistoryManager {
         Ok(items)
     }
 
+    /// Deletes the entries matching `condition` in one transaction and returns them, so they can
+    /// be put back with `restore_entries`.
+    fn take_where(&self, condition: &str, args: &[&dyn rusqlite::ToSql]) -> Result<Vec<HistoryItem>> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        let items = {
+            let mut stmt = tx.prepare(&format!("SELECT uuid, url, title, visit_count, last_visit FROM history WHERE {}", condition))?;
+            let rows = stmt.query_map(args, |row| {
+                Ok(HistoryItem {
+                    id: row.get(0)?,
+                    url: row.get(1)?,
+                    title: row.get(2)?,
+                    visit_count: row.get(3)?,
+                    last_visit: row.get(4)?,
+                })
+            })?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        tx.execute(&format!("DELETE FROM history WHERE {}", condition), args)?;
+        for item in &items {
+            tx.execute("DELETE FROM history_visits WHERE url = ?1", params![item.url])?;
+        }
+        tx.commit()?;
+        Ok(items)
+    }
+
+    pub fn take_entry(&self, url: &str) -> Result<Vec<HistoryItem>> {
+        self.take_where("url = ?1", &[&canonicalize_url(url)])
+    }
+
+    pub fn take_entry_by_id(&self, id: &str) -> Result<Vec<HistoryItem>> {
+        self.take_where("uuid = ?1", &[&id])
+    }
+
+    /// Deletes entries last visited in `[start, end)` (unix seconds) and returns them.
+    pub fn take_range(&self, start: i64, end: i64) -> Result<Vec<HistoryItem>> {
+        self.take_where("last_visit >= ?1 AND last_visit < ?2", &[&start, &end])
+    }
+
+    /// Puts back entries removed by one of the `take_` methods. A page visited again in the
+    /// meantime keeps its new entry, with the old visits added to it. The visit log isn't put
+    /// back, so statistics leave the restored visits out.
+    pub fn restore_entries(&self, items: &[HistoryItem]) -> Result<()> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        for item in items {
+            tx.execute(
+                "INSERT INTO history (url, title, visit_count, last_visit, uuid)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(url) DO UPDATE SET
+                    visit_count = visit_count + excluded.visit_count,
+                    last_visit = MAX(last_visit, excluded.last_visit)",
+                params![item.url, item.title, item.visit_count, item.last_visit, item.id],
+            )?;
+        }
+        tx.commit()
+    }
+
     pub fn update_title(&self, url: String, title: String) -> Result<()> {
         let conn = self.connect()?;
         conn.execute(
             "UPDATE history SET title = ?2 WHERE url = ?1",
-            params![url, title],
+            params![canonicalize_url(&url), title],
         )?;
         Ok(())
     }
 
+    /// `update_title` for many (url, title) pairs in one transaction.
+    pub fn update_titles(&self, titles: &[(String, String)]) -> Result<()> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        for (url, title) in titles {
+            tx.execute(
+                "UPDATE history SET title = ?2 WHERE url = ?1",
+                params![canonicalize_url(url), title],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Most visited pages since `since` (unix seconds). Without a start it goes by the lifetime
+    /// counts, which also cover visits from before the visit log. The first `limit` that `keep` accepts.
+    pub fn get_top_sites(&self, since: Option<i64>, limit: i64, keep: impl Fn(&str) -> bool) -> Result<Vec<TopSite>> {
+        let conn = self.connect()?;
+        let (sql, args) = match since {
+            Some(since) => (
+                "SELECT h.url, h.title, COUNT(*) AS visits, MAX(v.visited_at) AS last
+                 FROM history_visits v JOIN history h ON h.url = v.url
+                 WHERE v.visited_at >= ?1 AND h.url LIKE 'http%'
+                 GROUP BY h.url
+                 ORDER BY visits DESC, last DESC",
+                vec![since],
+            ),
+            None => (
+                "SELECT url, title, visit_count, last_visit FROM history
+                 WHERE url LIKE 'http%'
+                 ORDER BY visit_count DESC, last_visit DESC",
+                vec![],
+            ),
+        };
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(args), |row| {
+            Ok(TopSite {
+                url: row.get(0)?,
+                title: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                visits: row.get(2)?,
+                last_visit: row.get::<_, Option<i64>>(3)?.unwrap_or_default(),
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            let site = row?;
+            if keep(&site.url) {
+                result.push(site);
+                if result.len() as i64 == limit {
+                    break;
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Aggregates over the visit log since `since` (unix seconds), or all of it.
+    pub fn get_browsing_stats(&self, since: Option<i64>, top_domains: i64) -> Result<BrowsingStats> {
+        let conn = self.connect()?;
+        let since = since.unwrap_or(0);
+
+        let (total_visits, distinct_domains) = conn.query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT NULLIF(host, '')) FROM history_visits WHERE visited_at >= ?1",
+            params![since],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT strftime('%Y-%m-%d', visited_at, 'unixepoch', 'localtime') AS day, COUNT(*)
+             FROM history_visits WHERE visited_at >= ?1
+             GROUP BY day ORDER BY day",
+        )?;
+        let visits_per_day = stmt
+            .query_map(params![since], |row| Ok(DayVisits { day: row.get(0)?, visits: row.get(1)? }))?
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT host, COUNT(*) AS visits FROM history_visits
+             WHERE visited_at >= ?1 AND host != ''
+             GROUP BY host ORDER BY visits DESC, host
+             LIMIT ?2",
+        )?;
+        let top_domains = stmt
+            .query_map(params![since, top_domains], |row| Ok(DomainVisits { domain: row.get(0)?, visits: row.get(1)? }))?
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut visits_per_hour = vec![0; 24];
+        let mut stmt = conn.prepare(
+            "SELECT CAST(strftime('%H', visited_at, 'unixepoch', 'localtime') AS INTEGER) AS hour, COUNT(*)
+             FROM history_visits WHERE visited_at >= ?1
+             GROUP BY hour",
+        )?;
+        let hours = stmt.query_map(params![since], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+        for hour in hours {
+            let (hour, visits) = hour?;
+            if let Some(slot) = visits_per_hour.get_mut(hour as usize) {
+                *slot = visits;
+            }
+        }
+
+        Ok(BrowsingStats { total_visits, distinct_domains, visits_per_day, top_domains, visits_per_hour })
+    }
+
     // ============= COOKIES =============
     pub fn set_cookie(&self, cookie: CookieItem) -> Result<()> {
         let conn = self.connect()?;
         let now = chrono::Utc::now().timestamp();
+        let value = self.sealing_cipher()?.encrypt(&cookie.value);
         conn.execute(
             "INSERT INTO cookies (domain, name, value, expires, path, secure, http_only, created_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
              ON CONFLICT(domain, name, path) DO UPDATE SET value = excluded.value, expires = excluded.expires",
-            params![cookie.domain, cookie.name, cookie.value, cookie.expires, cookie.path, cookie.secure, cookie.http_only, now],
+            params![cookie.domain, cookie.name, value, cookie.expires, cookie.path, cookie.secure, cookie.http_only, now],
         )?;
         Ok(())
     }
@@ -238,7 +847,7 @@ impl HistoryManager {
             Ok(CookieItem {
                 domain: row.get(0)?,
                 name: row.get(1)?,
-                value: row.get(2)?,
+                value: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
                 expires: row.get(3)?,
                 path: row.get(4)?,
                 secure: row.get(5)?,
@@ -248,7 +857,11 @@ impl HistoryManager {
 
         let mut result = Vec::new();
         for cookie in cookies {
-            result.push(cookie?);
+            let mut cookie = cookie?;
+            // Unreadable values are dropped like expired cookies
+            let Some(value) = self.open_field(cookie.value) else { continue };
+            cookie.value = value;
+            result.push(cookie);
         }
         Ok(result)
     }
@@ -262,16 +875,27 @@ impl HistoryManager {
         Ok(())
     }
 
+    /// Deletes the cookies of `domain` and its subdomains, host-only and domain cookies alike.
+    pub fn delete_cookies_for_domain(&self, domain: &str) -> Result<usize> {
+        let conn = self.connect()?;
+        let deleted = conn.execute(
+            "DELETE FROM cookies WHERE ltrim(domain, '.') = ?1 OR domain LIKE '%.' || ?1",
+            params![domain],
+        )?;
+        Ok(deleted)
+    }
+
     // ============= FORM DATA =============
     #[allow(dead_code)]
     pub fn save_form_data(&self, item: FormDataItem) -> Result<()> {
         let conn = self.connect()?;
         let now = chrono::Utc::now().timestamp();
+        let value = self.seal_form_value(self.sealing_cipher()?, &item.field_name, &item.domain, &item.field_value);
         conn.execute(
             "INSERT INTO form_data (field_name, field_value, domain, last_used, use_count)
              VALUES (?1, ?2, ?3, ?4, 1)
              ON CONFLICT(field_name, field_value, domain) DO UPDATE SET use_count = use_count + 1, last_used = ?4",
-            params![item.field_name, item.field_value, item.domain, now],
+            params![item.field_name, value, item.domain, now],
         )?;
         Ok(())
     }
@@ -290,7 +914,7 @@ impl HistoryManager {
 
         let mut result = Vec::new();
         for val in values {
-            result.push(val?);
+            result.extend(self.open_field(val?));
         }
         Ok(result)
     }
@@ -348,11 +972,633 @@ impl HistoryManager {
         Ok(())
     }
 
-    pub fn get_zoom_level(&self, domain: &str) -> Result<i32> {
+    /// The zoom saved for a domain, or None when it follows the default zoom setting.
+    pub fn get_zoom_override(&self, domain: &str) -> Result<Option<i32>> {
+        let conn = self.connect()?;
+        match conn.query_row("SELECT zoom FROM zoom_levels WHERE domain = ?1", params![domain], |row| row.get(0)) {
+            Ok(zoom) => Ok(Some(zoom)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // ============= APP META =============
+    pub fn get_meta(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT value FROM app_meta WHERE key = ?1")?;
+        match stmt.query_row(params![key], |row| row.get(0)) {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        let conn = self.connect()?;
+        conn.execute(
+            "INSERT INTO app_meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    // ============= MAINTENANCE =============
+    /// "ok", or the first problems PRAGMA integrity_check found.
+    pub fn integrity_check(&self) -> Result<String> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("PRAGMA integrity_check(20)")?;
+        let problems = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>>>()?;
+        Ok(problems.join("; "))
+    }
+
+    /// Gives free pages back to the file system and returns how many bytes that saved. The first
+    /// run switches the database to incremental auto-vacuum, which takes one full VACUUM.
+    pub fn vacuum(&self) -> Result<u64> {
+        let conn = self.connect()?;
+        let pragma = |name: &str| conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get::<_, i64>(0));
+        let page_size = pragma("page_size")?;
+        let before = pragma("page_count")?;
+        if pragma("auto_vacuum")? == 2 {
+            conn.execute_batch("PRAGMA incremental_vacuum")?;
+        } else {
+            conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM")?;
+        }
+        let after = pragma("page_count")?;
+        Ok((before - after).max(0) as u64 * page_size as u64)
+    }
+
+    /// Writes a compacted, consistent copy of the database to `path`, which must not exist yet.
+    pub fn backup_to(&self, path: &Path) -> Result<()> {
+        let conn = self.connect()?;
+        conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+        Ok(())
+    }
+
+    // ============= HOST BLOCKLIST =============
+    /// Inserts domains in a single transaction, skipping ones already present. Returns how many were new.
+    pub fn add_blocked_hosts(&self, domains: &[String], source: &str) -> Result<usize> {
+        let mut conn = self.connect()?;
+        let now = chrono::Utc::now().timestamp();
+        let tx = conn.transaction()?;
+        let mut added = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO host_blocklist (domain, source, added_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(domain) DO NOTHING",
+            )?;
+            for domain in domains {
+                added += stmt.execute(params![domain, source, now])?;
+            }
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    pub fn remove_blocked_host(&self, domain: &str) -> Result<()> {
+        let conn = self.connect()?;
+        conn.execute("DELETE FROM host_blocklist WHERE domain = ?1", params![domain])?;
+        Ok(())
+    }
+
+    pub fn get_blocked_hosts(&self) -> Result<Vec<HostBlockEntry>> {
         let conn = self.connect()?;
-        let mut stmt = conn.prepare("SELECT zoom FROM zoom_levels WHERE domain = ?1")?;
+        let mut stmt = conn.prepare("SELECT domain, source, added_at FROM host_blocklist ORDER BY domain")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(HostBlockEntry {
+                domain: row.get(0)?,
+                source: row.get(1)?,
+                added_at: row.get(2)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    // ============= ADBLOCK EXCEPTIONS =============
+    pub fn add_adblock_exception(&self, domain: &str) -> Result<()> {
+        let conn = self.connect()?;
+        let now = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO adblock_exceptions (domain, created_at) VALUES (?1, ?2)
+             ON CONFLICT(domain) DO NOTHING",
+            params![domain, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_adblock_exception(&self, domain: &str) -> Result<()> {
+        let conn = self.connect()?;
+        conn.execute("DELETE FROM adblock_exceptions WHERE domain = ?1", params![domain])?;
+        Ok(())
+    }
+
+    pub fn get_adblock_exceptions(&self) -> Result<Vec<String>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT domain FROM adblock_exceptions ORDER BY domain")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    // ============= LUA STORAGE =============
+    pub fn lua_storage_set(&self, package_id: &str, key: &str, value: &str) -> Result<()> {
+        let conn = self.connect()?;
+        let now = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO lua_storage (package_id, key, value, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(package_id, key) DO UPDATE SET value = excluded.value, updated_at = ?4",
+            params![package_id, key, value, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn lua_storage_get(&self, package_id: &str, key: &str) -> Result<Option<String>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT value FROM lua_storage WHERE package_id = ?1 AND key = ?2")?;
+        match stmt.query_row(params![package_id, key], |row| row.get(0)) {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn lua_storage_delete(&self, package_id: &str, key: &str) -> Result<()> {
+        let conn = self.connect()?;
+        conn.execute(
+            "DELETE FROM lua_storage WHERE package_id = ?1 AND key = ?2",
+            params![package_id, key],
+        )?;
+        Ok(())
+    }
+
+    /// Bytes used by a package, ignoring `except_key` so an overwrite is measured against its new size.
+    pub fn lua_storage_usage(&self, package_id: &str, except_key: &str) -> Result<i64> {
+        let conn = self.connect()?;
+        conn.query_row(
+            "SELECT COALESCE(SUM(LENGTH(key) + LENGTH(value)), 0) FROM lua_storage
+             WHERE package_id = ?1 AND key != ?2",
+            params![package_id, except_key],
+            |row| row.get(0),
+        )
+    }
+
+    // ============= LUA CONSOLE HISTORY =============
+    pub fn add_console_entry(&self, code: &str) -> Result<()> {
+        let conn = self.connect()?;
+        let now = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO lua_console_history (code, created_at) VALUES (?1, ?2)",
+            params![code, now],
+        )?;
+        // Keep the table bounded
+        conn.execute(
+            "DELETE FROM lua_console_history WHERE id NOT IN
+             (SELECT id FROM lua_console_history ORDER BY id DESC LIMIT 500)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent console entries, oldest first (ready for arrow-key navigation).
+    pub fn get_console_history(&self, limit: i64) -> Result<Vec<String>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare(
+            "SELECT code FROM (SELECT id, code FROM lua_console_history ORDER BY id DESC LIMIT ?1)
+             ORDER BY id ASC",
+        )?;
+
+        let rows = stmt.query_map(params![limit], |row| row.get(0))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    // ============= LUA PERMISSIONS =============
+    pub fn set_lua_permission(&self, package_id: &str, permission: &str, granted: bool) -> Result<()> {
+        let conn = self.connect()?;
+        if granted {
+            let now = chrono::Utc::now().timestamp();
+            conn.execute(
+                "INSERT INTO lua_permissions (package_id, permission, granted_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(package_id, permission) DO NOTHING",
+                params![package_id, permission, now],
+            )?;
+        } else {
+            conn.execute(
+                "DELETE FROM lua_permissions WHERE package_id = ?1 AND permission = ?2",
+                params![package_id, permission],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn has_lua_permission(&self, package_id: &str, permission: &str) -> Result<bool> {
+        let conn = self.connect()?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM lua_permissions WHERE package_id = ?1 AND permission = ?2",
+            params![package_id, permission],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    pub fn get_lua_permissions(&self, package_id: &str) -> Result<Vec<String>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT permission FROM lua_permissions WHERE package_id = ?1 ORDER BY permission")?;
+        let rows = stmt.query_map(params![package_id], |row| row.get(0))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    // ============= SITE PERMISSIONS =============
+    pub fn set_site_permission(&self, origin: &str, permission: &str, state: &str) -> Result<()> {
+        let conn = self.connect()?;
+        let now = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO site_permissions (origin, permission, state, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(origin, permission) DO UPDATE SET state = excluded.state, updated_at = excluded.updated_at",
+            params![origin, permission, state, now],
+        )?;
+        Ok(())
+    }
+
+    /// Returns None when the origin has never been asked.
+    pub fn get_site_permission(&self, origin: &str, permission: &str) -> Result<Option<String>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT state FROM site_permissions WHERE origin = ?1 AND permission = ?2")?;
+        let mut rows = stmt.query(params![origin, permission])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn remove_site_permission(&self, origin: &str, permission: &str) -> Result<()> {
+        let conn = self.connect()?;
+        conn.execute(
+            "DELETE FROM site_permissions WHERE origin = ?1 AND permission = ?2",
+            params![origin, permission],
+        )?;
+        Ok(())
+    }
+
+    /// All stored decisions, or only those for `origin` when given.
+    pub fn get_site_permissions(&self, origin: Option<&str>) -> Result<Vec<SitePermission>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare(
+            "SELECT origin, permission, state, updated_at FROM site_permissions
+             WHERE ?1 IS NULL OR origin = ?1
+             ORDER BY origin, permission",
+        )?;
+        let rows = stmt.query_map(params![origin], |row| {
+            Ok(SitePermission {
+                origin: row.get(0)?,
+                permission: row.get(1)?,
+                state: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    // ============= SITE NOTES =============
+    /// Blank content deletes the note.
+    pub fn set_site_note(&self, domain: &str, content: &str) -> Result<()> {
+        let conn = self.connect()?;
+        if content.trim().is_empty() {
+            conn.execute("DELETE FROM site_notes WHERE domain = ?1", params![domain])?;
+        } else {
+            conn.execute(
+                "INSERT INTO site_notes (domain, content, updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(domain) DO UPDATE SET content = excluded.content, updated_at = excluded.updated_at",
+                params![domain, content, chrono::Utc::now().timestamp()],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn get_site_note(&self, domain: &str) -> Result<Option<SiteNote>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT domain, content, updated_at FROM site_notes WHERE domain = ?1")?;
+        let note = stmt.query_row(params![domain], |row| {
+            Ok(SiteNote { domain: row.get(0)?, content: row.get(1)?, updated_at: row.get(2)? })
+        });
+        match note {
+            Ok(note) => Ok(Some(note)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Most recently edited first
+    pub fn get_site_notes(&self) -> Result<Vec<SiteNote>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT domain, content, updated_at FROM site_notes ORDER BY updated_at DESC")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(SiteNote { domain: row.get(0)?, content: row.get(1)?, updated_at: row.get(2)? })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    // ============= URL ALIASES =============
+    /// Replaces the target of an existing alias.
+    pub fn set_alias(&self, alias: &str, url: &str) -> Result<UrlAlias> {
+        let conn = self.connect()?;
+        let created_at = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO url_aliases (alias, url, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(alias) DO UPDATE SET url = excluded.url",
+            params![alias, url, created_at],
+        )?;
+        Ok(UrlAlias { alias: alias.to_string(), url: url.to_string(), created_at })
+    }
+
+    /// Returns false if there was no such alias.
+    pub fn remove_alias(&self, alias: &str) -> Result<bool> {
+        let conn = self.connect()?;
+        Ok(conn.execute("DELETE FROM url_aliases WHERE alias = ?1", params![alias])? > 0)
+    }
+
+    pub fn get_alias_url(&self, alias: &str) -> Result<Option<String>> {
+        let conn = self.connect()?;
+        match conn.query_row("SELECT url FROM url_aliases WHERE alias = ?1", params![alias], |row| row.get(0)) {
+            Ok(url) => Ok(Some(url)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Alphabetical
+    pub fn get_aliases(&self) -> Result<Vec<UrlAlias>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT alias, url, created_at FROM url_aliases ORDER BY alias")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(UrlAlias { alias: row.get(0)?, url: row.get(1)?, created_at: row.get(2)? })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    // ============= SAVED SESSIONS =============
+    /// Overwrites a session with the same name.
+    pub fn set_saved_session(&self, name: &str, tabs_json: &str) -> Result<()> {
+        let conn = self.connect()?;
+        conn.execute(
+            "INSERT INTO saved_sessions (name, tabs, saved_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET tabs = excluded.tabs, saved_at = excluded.saved_at",
+            params![name, tabs_json, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// The tabs JSON of a session
+    pub fn get_saved_session(&self, name: &str) -> Result<Option<String>> {
+        let conn = self.connect()?;
+        match conn.query_row("SELECT tabs FROM saved_sessions WHERE name = ?1", params![name], |row| row.get(0)) {
+            Ok(tabs) => Ok(Some(tabs)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// (name, tabs JSON, saved_at), most recently saved first
+    pub fn get_saved_sessions(&self) -> Result<Vec<(String, String, i64)>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT name, tabs, saved_at FROM saved_sessions ORDER BY saved_at DESC")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    /// Returns false if there was no such session.
+    pub fn delete_saved_session(&self, name: &str) -> Result<bool> {
+        let conn = self.connect()?;
+        Ok(conn.execute("DELETE FROM saved_sessions WHERE name = ?1", params![name])? > 0)
+    }
+
+    // ============= DARK MODE =============
+    pub fn set_dark_mode_site(&self, domain: &str, enabled: Option<bool>) -> Result<()> {
+        let conn = self.connect()?;
+        match enabled {
+            Some(enabled) => conn.execute(
+                "INSERT INTO dark_mode_sites (domain, enabled) VALUES (?1, ?2)
+                 ON CONFLICT(domain) DO UPDATE SET enabled = excluded.enabled",
+                params![domain, enabled],
+            )?,
+            None => conn.execute("DELETE FROM dark_mode_sites WHERE domain = ?1", params![domain])?,
+        };
+        Ok(())
+    }
+
+    pub fn get_dark_mode_sites(&self) -> Result<Vec<(String, bool)>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT domain, enabled FROM dark_mode_sites ORDER BY domain")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    // ============= AUTOFILL PROFILES =============
+    /// Whether sensitive fields can be stored encrypted (the key could be loaded)
+    pub fn can_encrypt(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    pub fn key_error(&self) -> Option<&str> {
+        self.key_error.as_deref()
+    }
+
+    /// Profiles are only ever stored encrypted; without the key this fails rather than write plaintext.
+    pub fn save_autofill_profile(&self, profile: &AutofillProfile) -> Result<()> {
+        let cipher = self.sealing_cipher()?;
+        let conn = self.connect()?;
+        let json = serde_json::to_string(profile).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let data = cipher.encrypt(&json);
+        conn.execute(
+            "INSERT INTO autofill_profiles (id, data, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data, updated_at = excluded.updated_at",
+            params![profile.id, data, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_autofill_profile(&self, id: &str) -> Result<()> {
+        let conn = self.connect()?;
+        conn.execute("DELETE FROM autofill_profiles WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Most recently edited first. Profiles that can't be decrypted (lost key) are left out.
+    pub fn get_autofill_profiles(&self) -> Result<Vec<AutofillProfile>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT id, data FROM autofill_profiles ORDER BY updated_at DESC")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            let (id, data) = row?;
+            let profile = self.open_field(data).and_then(|json| serde_json::from_str::<AutofillProfile>(&json).ok());
+            if let Some(profile) = profile {
+                result.push(AutofillProfile { id, ..profile });
+            }
+        }
+        Ok(result)
+    }
+
+    // ============= SITE PROTECTIONS =============
+    pub fn set_site_protection(&self, pattern: &str, level: Option<&str>) -> Result<()> {
+        let conn = self.connect()?;
+        match level {
+            Some(level) => conn.execute(
+                "INSERT INTO site_protections (pattern, level) VALUES (?1, ?2)
+                 ON CONFLICT(pattern) DO UPDATE SET level = excluded.level",
+                params![pattern, level],
+            )?,
+            None => conn.execute("DELETE FROM site_protections WHERE pattern = ?1", params![pattern])?,
+        };
+        Ok(())
+    }
+
+    pub fn get_site_protections(&self) -> Result<Vec<(String, String)>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT pattern, level FROM site_protections ORDER BY pattern")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    // ============= HEADER RULES =============
+    pub fn save_header_rule(&self, rule: &HeaderRule) -> Result<()> {
+        let conn = self.connect()?;
+        let value = match rule.value.as_deref() {
+            Some(value) => Some(self.sealing_cipher()?.encrypt(value)),
+            None => None,
+        };
+        conn.execute(
+            "INSERT INTO header_rules (id, domain, action, name, value, enabled, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(id) DO UPDATE SET domain = excluded.domain, action = excluded.action, name = excluded.name,
+                value = excluded.value, enabled = excluded.enabled",
+            params![rule.id, rule.domain, rule.action, rule.name, value, rule.enabled, rule.created_at],
+        )?;
+        Ok(())
+    }
+
+    /// Returns false if there was no such rule.
+    pub fn delete_header_rule(&self, id: &str) -> Result<bool> {
+        let conn = self.connect()?;
+        Ok(conn.execute("DELETE FROM header_rules WHERE id = ?1", params![id])? > 0)
+    }
+
+    /// By domain, then oldest first. Rules whose value can't be decrypted (lost key) are left out.
+    pub fn get_header_rules(&self) -> Result<Vec<HeaderRule>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT id, domain, action, name, value, enabled, created_at FROM header_rules ORDER BY domain, created_at")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(HeaderRule {
+                id: row.get(0)?,
+                domain: row.get(1)?,
+                action: row.get(2)?,
+                name: row.get(3)?,
+                value: row.get(4)?,
+                enabled: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            let rule = row?;
+            match rule.value.clone() {
+                Some(stored) => {
+                    if let Some(value) = self.open_field(stored) {
+                        result.push(HeaderRule { value: Some(value), ..rule });
+                    }
+                }
+                None => result.push(rule),
+            }
+        }
+        Ok(result)
+    }
+
+    // ============= REFERRER POLICIES =============
+    /// `policy: None` drops the override.
+    pub fn set_referrer_policy(&self, domain: &str, policy: Option<&str>) -> Result<()> {
+        let conn = self.connect()?;
+        match policy {
+            Some(policy) => conn.execute(
+                "INSERT INTO referrer_policies (domain, policy) VALUES (?1, ?2)
+                 ON CONFLICT(domain) DO UPDATE SET policy = excluded.policy",
+                params![domain, policy],
+            )?,
+            None => conn.execute("DELETE FROM referrer_policies WHERE domain = ?1", params![domain])?,
+        };
+        Ok(())
+    }
+
+    pub fn get_referrer_policies(&self) -> Result<Vec<(String, String)>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT domain, policy FROM referrer_policies ORDER BY domain")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let zoom = stmt.query_row(params![domain], |row| row.get(0));
-        Ok(zoom.unwrap_or(100))
+    #[test]
+    fn urls_are_stored_in_one_form() {
+        assert_eq!(canonicalize_url("HTTPS://Example.COM:443/path/#top"), "https://example.com/path");
+        assert_eq!(canonicalize_url("https://example.com"), "https://example.com/");
+        assert_eq!(canonicalize_url("https://example.com/a/?q=1"), "https://example.com/a?q=1");
+        assert_eq!(canonicalize_url("  not a url "), "not a url");
     }
 }
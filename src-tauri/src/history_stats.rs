@@ -0,0 +1,54 @@
+use serde::Deserialize;
+
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::history_manager::{BrowsingStats, HistoryManager, TopSite};
+
+const MAX_TOP_SITES: i64 = 100;
+/// Domains listed in `get_browsing_stats`
+const TOP_DOMAINS: i64 = 10;
+
+/// How far back the statistics look
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum StatsPeriod {
+    /// The last 24 hours
+    Day,
+    #[default]
+    Week,
+    /// The last 30 days
+    Month,
+    All,
+}
+
+impl StatsPeriod {
+    /// Unix seconds the period starts at; None for all of history
+    pub(crate) fn since(self) -> Option<i64> {
+        let days = match self {
+            StatsPeriod::Day => 1,
+            StatsPeriod::Week => 7,
+            StatsPeriod::Month => 30,
+            StatsPeriod::All => return None,
+        };
+        Some(chrono::Utc::now().timestamp() - days * 24 * 60 * 60)
+    }
+
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(value.to_string())).ok()
+    }
+}
+
+/// Most visited pages in the period, most visits first. Sites kept out of history are left out.
+#[tauri::command]
+pub(crate) fn get_top_sites(history_manager: tauri::State<'_, HistoryManager>, data_store: tauri::State<'_, AppDataStore>, limit: Option<i64>, period: Option<StatsPeriod>) -> Result<Vec<TopSite>, LuminaError> {
+    let limit = limit.unwrap_or(8).clamp(1, MAX_TOP_SITES);
+    history_manager
+        .get_top_sites(period.unwrap_or_default().since(), limit, |url| !data_store.is_history_excluded(url))
+        .map_err(LuminaError::from)
+}
+
+/// Visits per day, the most visited domains and a time-of-day histogram for the period.
+#[tauri::command]
+pub(crate) fn get_browsing_stats(history_manager: tauri::State<'_, HistoryManager>, period: Option<StatsPeriod>) -> Result<BrowsingStats, LuminaError> {
+    history_manager.get_browsing_stats(period.unwrap_or_default().since(), TOP_DOMAINS).map_err(LuminaError::from)
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::locale;
+
+/// Sent with the browser's own requests, so sites answer them the way they answer a tab
+pub(crate) const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/144.0.0.0 Safari/537.36 Edg/144.0.0.0";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const POOL_MAX_IDLE_PER_HOST: usize = 8;
+const MAX_REDIRECTS: usize = 10;
+/// Attempts `get_with_retry` makes before handing back the last failure
+const MAX_ATTEMPTS: u32 = 3;
+/// Doubled after every failed attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// What a request is for. Each purpose gets one pooled client, shared by every caller.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum Purpose {
+    /// Fetches made on a page's behalf: favorites checks, extension packages, filter lists
+    Page,
+    /// File and model downloads; no overall timeout, since a large file can take hours
+    Download,
+    /// Favicons and touch icons
+    Icon,
+    /// Web app manifests
+    Manifest,
+    /// Store documents and packages
+    Store,
+    /// Connectivity checks. A redirect means a captive portal, so none are followed.
+    Probe,
+    /// Sidecar servers on 127.0.0.1, which never go through the proxy
+    Local,
+}
+
+struct Profile {
+    /// None leaves the request to run as long as the body keeps coming
+    timeout: Option<Duration>,
+    redirects: bool,
+    proxied: bool,
+}
+
+impl Purpose {
+    fn profile(self) -> Profile {
+        let seconds = |s| Some(Duration::from_secs(s));
+        match self {
+            Purpose::Page => Profile { timeout: seconds(60), redirects: true, proxied: true },
+            Purpose::Download => Profile { timeout: None, redirects: true, proxied: true },
+            Purpose::Icon => Profile { timeout: seconds(5), redirects: true, proxied: true },
+            Purpose::Manifest => Profile { timeout: seconds(10), redirects: true, proxied: true },
+            Purpose::Store => Profile { timeout: seconds(30), redirects: true, proxied: true },
+            Purpose::Probe => Profile { timeout: seconds(5), redirects: false, proxied: true },
+            Purpose::Local => Profile { timeout: None, redirects: false, proxied: false },
+        }
+    }
+}
+
+/// The browser's own HTTP clients, built on first use and kept for their connection pools.
+pub(crate) struct HttpClients {
+    clients: Mutex<HashMap<Purpose, reqwest::Client>>,
+}
+
+impl HttpClients {
+    pub fn new() -> Self {
+        Self { clients: Mutex::new(HashMap::new()) }
+    }
+
+    /// Drops every client, so the next request picks up changed languages or proxy settings.
+    /// Requests already running finish on the old ones.
+    pub fn reset(&self) {
+        self.clients.lock().unwrap().clear();
+    }
+
+    fn get(&self, app: &AppHandle, purpose: Purpose) -> Result<reqwest::Client, LuminaError> {
+        if let Some(client) = self.clients.lock().unwrap().get(&purpose) {
+            return Ok(client.clone());
+        }
+        let client = build(app, purpose)?;
+        self.clients.lock().unwrap().insert(purpose, client.clone());
+        Ok(client)
+    }
+}
+
+fn proxy_url(app: &AppHandle) -> Option<String> {
+    app.state::<AppDataStore>().data.lock().unwrap().settings.proxy_url.clone()
+}
+
+fn build(app: &AppHandle, purpose: Purpose) -> Result<reqwest::Client, LuminaError> {
+    let profile = purpose.profile();
+    let mut builder = locale::client_builder(app)
+        .user_agent(BROWSER_USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST)
+        .redirect(match profile.redirects {
+            true => reqwest::redirect::Policy::limited(MAX_REDIRECTS),
+            false => reqwest::redirect::Policy::none(),
+        });
+    if let Some(timeout) = profile.timeout {
+        builder = builder.timeout(timeout);
+    }
+    // Without a proxy setting reqwest follows HTTP_PROXY/HTTPS_PROXY/NO_PROXY
+    if !profile.proxied {
+        builder = builder.no_proxy();
+    } else if let Some(url) = proxy_url(app) {
+        builder = builder.proxy(reqwest::Proxy::all(url)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// The shared client for `purpose`.
+pub(crate) fn client(app: &AppHandle, purpose: Purpose) -> Result<reqwest::Client, LuminaError> {
+    app.state::<HttpClients>().get(app, purpose)
+}
+
+/// Sends a GET, retrying connection failures, timeouts, 429 and 5xx answers with backoff.
+/// The last response comes back as is, error status included.
+pub(crate) async fn get_with_retry(client: &reqwest::Client, url: &str) -> reqwest::Result<reqwest::Response> {
+    let mut attempt = 1;
+    loop {
+        let result = client.get(url).send().await;
+        let retryable = match &result {
+            Ok(res) => res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS || res.status().is_server_error(),
+            Err(e) => e.is_connect() || e.is_timeout(),
+        };
+        if !retryable || attempt >= MAX_ATTEMPTS {
+            return result;
+        }
+        tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+        attempt += 1;
+    }
+}
+
+/// Routes the browser's own requests (downloads, icons, manifests, store, filter lists) through
+/// an HTTP proxy. Tabs keep the system's proxy. An empty URL goes back to the environment's.
+#[tauri::command]
+pub(crate) fn set_proxy_url(state: tauri::State<'_, AppDataStore>, clients: tauri::State<'_, HttpClients>, url: String) -> Result<(), LuminaError> {
+    let url = url.trim();
+    let proxy_url = if url.is_empty() {
+        None
+    } else {
+        let parsed = tauri::Url::parse(url).map_err(|e| LuminaError::InvalidInput(format!("Invalid proxy URL: {}", e)))?;
+        if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+            return Err(LuminaError::InvalidInput("Proxy URLs look like http://host:port".to_string()));
+        }
+        Some(url.to_string())
+    };
+    state.data.lock().unwrap().settings.proxy_url = proxy_url;
+    state.save();
+    clients.reset();
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::data::AppDataStore;
+use crate::http;
+
+const INDEX_FILE: &str = "index.json";
+/// How often icons no installed app points to are deleted
+const ICON_PRUNE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Icons this new are left alone by the pruner: an install may not have recorded its icon yet
+const ICON_PRUNE_GRACE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedIcon {
+    url: String,
+    /// File name in the icons folder
+    file: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: i64,
+}
+
+/// Downloaded icons by the hash of their URL, kept in icons/index.json
+static INDEX: OnceLock<Mutex<HashMap<String, CachedIcon>>> = OnceLock::new();
+
+fn icons_dir(app: &AppHandle) -> Option<PathBuf> {
+    let dir = app.path().app_data_dir().ok()?.join("icons");
+    if !dir.exists() {
+        let _ = std::fs::create_dir_all(&dir);
+    }
+    Some(dir)
+}
+
+fn index(app: &AppHandle) -> &'static Mutex<HashMap<String, CachedIcon>> {
+    INDEX.get_or_init(|| {
+        let entries = icons_dir(app)
+            .and_then(|dir| std::fs::read_to_string(dir.join(INDEX_FILE)).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Mutex::new(entries)
+    })
+}
+
+fn save_index(app: &AppHandle, entries: &HashMap<String, CachedIcon>) {
+    if let (Some(dir), Ok(json)) = (icons_dir(app), serde_json::to_string_pretty(entries)) {
+        let _ = std::fs::write(dir.join(INDEX_FILE), json);
+    }
+}
+
+fn hash_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().take(16).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Stores icon bytes as a 256px ICO (what Windows shortcuts want), or as they are when they can't
+/// be decoded (SVG, say). Files are named by content, so the same icon is only kept once.
+pub(crate) async fn save_icon(app: &AppHandle, bytes: &[u8]) -> Option<PathBuf> {
+    let dir = icons_dir(app)?;
+    let name = hash_hex(bytes);
+    let ico_path = dir.join(format!("{}.ico", name));
+    let raw_path = dir.join(format!("{}.png", name));
+    if let Some(existing) = [&ico_path, &raw_path].into_iter().find(|path| path.exists()) {
+        return Some(existing.clone());
+    }
+
+    // Image decoding and encoding are CPU heavy
+    let bytes_vec = bytes.to_vec();
+    let target = ico_path.clone();
+    let converted = tokio::task::spawn_blocking(move || {
+        let img = image::load_from_memory(&bytes_vec).ok()?;
+        let resized = img.resize(256, 256, image::imageops::FilterType::Lanczos3);
+        let file = std::fs::File::create(&target).ok()?;
+        let mut writer = std::io::BufWriter::new(file);
+        if resized.write_to(&mut writer, image::ImageFormat::Ico).is_ok() {
+            return Some(target);
+        }
+        let _ = std::fs::remove_file(&target);
+        None
+    }).await.ok().flatten();
+    if converted.is_some() {
+        return converted;
+    }
+
+    tokio::fs::write(&raw_path, bytes).await.ok()?;
+    Some(raw_path)
+}
+
+/// Fetches an icon through the cache. A cached icon is revalidated with its ETag or
+/// Last-Modified and reused when the server says it's unchanged or can't be reached.
+pub(crate) async fn download_icon(app: &AppHandle, url: &str) -> Option<PathBuf> {
+    let dir = icons_dir(app)?;
+    let key = hash_hex(url.as_bytes());
+    let cached = index(app).lock().unwrap().get(&key).cloned().filter(|icon| dir.join(&icon.file).exists());
+    let cached_path = cached.as_ref().map(|icon| dir.join(&icon.file));
+
+    let client = http::client(app, http::Purpose::Icon).ok()?;
+    let mut request = client.get(url);
+    if let Some(icon) = &cached {
+        if let Some(etag) = &icon.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &icon.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let Ok(response) = request.send().await else { return cached_path };
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED || !response.status().is_success() {
+        return cached_path;
+    }
+    let header = |name: reqwest::header::HeaderName| {
+        response.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_string)
+    };
+    let etag = header(reqwest::header::ETAG);
+    let last_modified = header(reqwest::header::LAST_MODIFIED);
+    let Ok(bytes) = response.bytes().await else { return cached_path };
+
+    let path = save_icon(app, &bytes).await?;
+    let file = path.file_name()?.to_string_lossy().to_string();
+    let mut entries = index(app).lock().unwrap();
+    entries.insert(key, CachedIcon {
+        url: url.to_string(),
+        file,
+        etag,
+        last_modified,
+        fetched_at: chrono::Utc::now().timestamp(),
+    });
+    save_index(app, &entries);
+    Some(path)
+}
+
+/// Deletes icons no installed app uses any more, along with their cache entries.
+fn prune_unused_icons(app: &AppHandle) {
+    let Some(dir) = icons_dir(app) else { return };
+    let in_use: HashSet<PathBuf> = app
+        .state::<AppDataStore>()
+        .installed_pwas()
+        .into_iter()
+        .filter_map(|pwa| pwa.icon_path.map(PathBuf::from))
+        .collect();
+
+    let mut entries = index(app).lock().unwrap();
+    entries.retain(|_, icon| in_use.contains(&dir.join(&icon.file)));
+    save_index(app, &entries);
+
+    let Ok(files) = std::fs::read_dir(&dir) else { return };
+    let mut removed = 0;
+    for file in files.flatten() {
+        let path = file.path();
+        let recent = file.metadata().and_then(|meta| meta.modified()).ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_none_or(|age| age < ICON_PRUNE_GRACE);
+        if path.file_name().is_some_and(|name| name == INDEX_FILE) || recent || in_use.contains(&path) {
+            continue;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        println!("Lumina: Removed {} unused icon(s)", removed);
+    }
+}
+
+/// Prunes the icon folder now and then every ICON_PRUNE_INTERVAL.
+pub(crate) fn start_pruner(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let handle = app.clone();
+            let _ = tokio::task::spawn_blocking(move || prune_unused_icons(&handle)).await;
+            tokio::time::sleep(ICON_PRUNE_INTERVAL).await;
+        }
+    });
+}
@@ -0,0 +1,153 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use tauri::ipc::{Invoke, InvokeBody, InvokeMessage};
+use tauri::{Runtime, Webview};
+
+use crate::error::LuminaError;
+use crate::layout::is_browser_window;
+
+/// Commands a web page may reach through the injected bridge: what the tab info script needs and
+/// nothing more. Everything else Lumina does in a page runs through `page_script`, and questions go to
+/// the infobar (`site_prompts`), so no answer ever comes from the page.
+const PAGE_COMMANDS: &[&str] = &["create_tab", "update_tab_info", "add_history_item", "pwa_detected"];
+
+/// Argument the injected bridge adds to every call (see `get_pwa_init_script`). Commands ignore it.
+/// The token lives inside the bridge's closure, never on `window`, and is added to a call's arguments
+/// with object spread rather than any built-in a page script could replace.
+const TOKEN_ARG: &str = "__luminaIpcToken";
+
+/// Per-webview bridge token, rotated whenever the webview is (re)created
+static IPC_TOKENS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+/// Tabs showing an internal page written over another origin by `force_internal_navigate`
+static INTERNAL_DOCUMENTS: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn ipc_tokens() -> &'static Mutex<HashMap<String, String>> {
+    IPC_TOKENS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn internal_documents() -> &'static Mutex<HashSet<String>> {
+    INTERNAL_DOCUMENTS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Mints the token a webview's init script hands to its bridge. Any earlier token for the label stops working.
+pub(crate) fn issue_token(label: &str) -> String {
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    ipc_tokens().lock().unwrap().insert(label.to_string(), token.clone());
+    token
+}
+
+pub(crate) fn revoke(label: &str) {
+    ipc_tokens().lock().unwrap().remove(label);
+    internal_documents().lock().unwrap().remove(label);
+}
+
+pub(crate) fn set_internal_document(label: &str, internal: bool) {
+    let mut documents = internal_documents().lock().unwrap();
+    if internal {
+        documents.insert(label.to_string());
+    } else {
+        documents.remove(label);
+    }
+}
+
+fn is_internal_url(url: &url::Url) -> bool {
+    // WebView2 serves custom schemes from http://<scheme>.localhost
+    url.scheme() == "lumina-app" || url.host_str() == Some("lumina-app.localhost")
+}
+
+/// The browser UI and Lumina's own pages get every command
+pub(crate) fn is_privileged<R: Runtime>(webview: &Webview<R>) -> bool {
+    is_browser_window(webview.label())
+        || webview.url().map(|url| is_internal_url(&url)).unwrap_or(false)
+        || internal_documents().lock().unwrap().contains(webview.label())
+}
+
+fn check<R: Runtime>(message: &InvokeMessage<R>) -> Result<(), LuminaError> {
+    let webview = message.webview_ref();
+    let args = match message.payload() {
+        InvokeBody::Json(args) => args,
+        _ => &serde_json::Value::Null,
+    };
+    check_call(webview.label(), is_privileged(webview), message.command(), args)
+}
+
+/// Whether `label` may run `command` with `args`; privileged senders may run anything.
+fn check_call(label: &str, privileged: bool, command: &str, args: &serde_json::Value) -> Result<(), LuminaError> {
+    if privileged {
+        return Ok(());
+    }
+    if !PAGE_COMMANDS.contains(&command) {
+        return Err(LuminaError::PermissionDenied(format!("{} is not available to web pages", command)));
+    }
+
+    let token = args.get(TOKEN_ARG).and_then(|t| t.as_str());
+    if token.is_none() || ipc_tokens().lock().unwrap().get(label).map(String::as_str) != token {
+        return Err(LuminaError::PermissionDenied(format!("{} was called without a valid token for {}", command, label)));
+    }
+
+    // A page speaks for its own tab only (create_tab names the tab it opens)
+    if command != "create_tab" {
+        if let Some(target) = args.get("label").and_then(|l| l.as_str()) {
+            if target != label {
+                return Err(LuminaError::PermissionDenied(format!("{} may not act on {}", label, target)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Wraps the generated command handler so every invoke is checked against the sending webview first.
+pub(crate) fn guarded<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        if let Err(e) = check(&invoke.message) {
+            eprintln!("Lumina IPC: blocked call from {}: {}", invoke.message.webview_ref().label(), e);
+            invoke.resolver.reject(e);
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn privileged_senders_run_anything() {
+        assert!(check_call("main", true, "clear_history", &serde_json::Value::Null).is_ok());
+    }
+
+    #[test]
+    fn pages_only_reach_page_commands() {
+        let token = issue_token("test-page-commands");
+        let args = json!({ TOKEN_ARG: token });
+        assert!(check_call("test-page-commands", false, "update_tab_info", &args).is_ok());
+        assert!(check_call("test-page-commands", false, "clear_history", &args).is_err());
+    }
+
+    #[test]
+    fn pages_need_their_own_current_token() {
+        let stale = issue_token("test-token");
+        let token = issue_token("test-token");
+        let other = issue_token("test-token-other");
+        assert!(check_call("test-token", false, "add_history_item", &json!({})).is_err());
+        assert!(check_call("test-token", false, "add_history_item", &json!({ TOKEN_ARG: stale })).is_err());
+        assert!(check_call("test-token", false, "add_history_item", &json!({ TOKEN_ARG: other })).is_err());
+        assert!(check_call("test-token", false, "add_history_item", &json!({ TOKEN_ARG: token })).is_ok());
+        revoke("test-token");
+        assert!(check_call("test-token", false, "add_history_item", &json!({ TOKEN_ARG: token })).is_err());
+    }
+
+    #[test]
+    fn pages_act_on_their_own_tab_only() {
+        let token = issue_token("test-own-tab");
+        assert!(check_call("test-own-tab", false, "update_tab_info", &json!({ TOKEN_ARG: token, "label": "test-own-tab" })).is_ok());
+        assert!(check_call("test-own-tab", false, "update_tab_info", &json!({ TOKEN_ARG: token, "label": "tab-2" })).is_err());
+        // create_tab names the tab it opens
+        assert!(check_call("test-own-tab", false, "create_tab", &json!({ TOKEN_ARG: token, "label": "tab-2" })).is_ok());
+    }
+}
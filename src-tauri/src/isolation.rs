@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+use url::Url;
+
+use crate::data::AppDataStore;
+use crate::downloads::sanitize_filename;
+
+/// Site group each tab's webview was created for while site isolation was on. Tabs missing here run
+/// in the shared browser profile.
+static TAB_SITE_GROUPS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn tab_site_groups() -> &'static Mutex<HashMap<String, String>> {
+    TAB_SITE_GROUPS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The registrable domain (eTLD+1) of a web page, e.g. "news.bbc.co.uk" -> "bbc.co.uk". Hosts without
+/// one (IP addresses, localhost) form their own group. Internal and non-web URLs have no group.
+pub(crate) fn site_group(url: &Url) -> Option<String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+    Some(psl::domain_str(&host).map(str::to_string).unwrap_or(host))
+}
+
+/// Cookies, storage and cache of one site group. Like `pwa_data_dir`, WKWebView ignores it.
+pub(crate) fn site_data_dir(app: &AppHandle, group: &str) -> Option<PathBuf> {
+    Some(app.path().app_data_dir().ok()?.join("site-profiles").join(sanitize_filename(group)))
+}
+
+pub(crate) fn is_enabled(app: &AppHandle) -> bool {
+    app.state::<AppDataStore>().flags().site_isolation
+}
+
+/// Records which profile a tab's webview was built with (`None`: the shared one).
+pub(crate) fn assign(label: &str, group: Option<String>) {
+    let mut groups = tab_site_groups().lock().unwrap();
+    match group {
+        Some(group) => groups.insert(label.to_string(), group),
+        None => groups.remove(label),
+    };
+}
+
+pub(crate) fn forget(label: &str) {
+    tab_site_groups().lock().unwrap().remove(label);
+}
+
+/// True when `url` belongs to a different site group than the profile the tab runs in, so the tab
+/// has to be reopened in that site's profile instead of navigating in place.
+pub(crate) fn needs_reopen(app: &AppHandle, label: &str, url: &Url) -> bool {
+    if !is_enabled(app) {
+        return false;
+    }
+    let Some(group) = site_group(url) else { return false };
+    tab_site_groups().lock().unwrap().get(label) != Some(&group)
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, LogicalPosition, LogicalSize, Manager, PhysicalSize, Webview};
+
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+
+/// Height of the top bar (tabs, address bar, bookmarks) in horizontal mode, before suggestions
+const TOP_BAR_HEIGHT: f64 = 104.0;
+const SIDEBAR_WIDTH: f64 = 200.0;
+const MENU_WIDTH: f64 = 320.0;
+/// Height of the toolbar above the page in vertical-tabs mode
+const TOOLBAR_HEIGHT: f64 = 60.0;
+
+/// A webview's place in the window, in logical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Bounds {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) width: f64,
+    pub(crate) height: f64,
+}
+
+impl Bounds {
+    fn apply(&self, webview: &Webview) {
+        let _ = webview.set_auto_resize(false);
+        let _ = webview.set_position(LogicalPosition::new(self.x, self.y));
+        let _ = webview.set_size(LogicalSize::new(self.width, self.height));
+    }
+}
+
+/// Where the browser UI and the tabs go for a given window size and UI state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Geometry {
+    pub(crate) scale_factor: f64,
+    pub(crate) window: Bounds,
+    /// The Blazor UI webview
+    pub(crate) main: Bounds,
+    /// Every tab webview except a fullscreen one, which gets `window`
+    pub(crate) tab: Bounds,
+}
+
+/// `infobar_height` is the site prompt bar under the address bar, which pushes the tab down like suggestions do.
+fn calculate_layout(window: LogicalSize<f64>, vertical_tabs: bool, menu_open: bool, suggestions_height: f64, infobar_height: f64) -> (Bounds, Bounds) {
+    let menu_width = if menu_open { MENU_WIDTH } else { 0.0 };
+    if vertical_tabs {
+        let main = Bounds { x: 0.0, y: 0.0, width: window.width, height: window.height };
+        let toolbar_height = TOOLBAR_HEIGHT + infobar_height;
+        let tab = Bounds {
+            x: SIDEBAR_WIDTH,
+            y: toolbar_height,
+            width: (window.width - SIDEBAR_WIDTH - menu_width).max(0.0),
+            height: (window.height - toolbar_height).max(0.0),
+        };
+        (main, tab)
+    } else {
+        let top_bar_height = TOP_BAR_HEIGHT + infobar_height + suggestions_height;
+        let main_height = if menu_open { window.height } else { top_bar_height };
+        let main = Bounds { x: 0.0, y: 0.0, width: window.width, height: main_height };
+        let tab = Bounds {
+            x: 0.0,
+            y: top_bar_height,
+            width: (window.width - menu_width).max(0.0),
+            height: (window.height - top_bar_height).max(0.0),
+        };
+        (main, tab)
+    }
+}
+
+/// Label prefix of the browser windows opened after "main"
+const BROWSER_WINDOW_PREFIX: &str = "browser-";
+
+/// Whether a window is a browser window (top bar plus tabs) rather than a PWA or a detached page.
+/// A browser window's UI webview has the window's label.
+pub(crate) fn is_browser_window(label: &str) -> bool {
+    label == "main" || label.starts_with(BROWSER_WINDOW_PREFIX)
+}
+
+/// The browser window a tab lives in.
+pub(crate) fn tab_window(app: &AppHandle, label: &str) -> Option<String> {
+    app.get_webview(label).map(|webview| webview.window().label().to_string())
+}
+
+/// Owns each browser window's geometry and is the only thing that moves or resizes their webviews.
+/// Everything that changes the layout (window resizes, DPI changes, the sidebar, suggestions,
+/// settings, fullscreen) ends up in `relayout`.
+pub(crate) struct LayoutManager {
+    geometry: Mutex<HashMap<String, Geometry>>,
+}
+
+impl LayoutManager {
+    pub(crate) fn new() -> Self {
+        Self { geometry: Mutex::new(HashMap::new()) }
+    }
+
+    /// The bounds a tab in `window` gets right now, for creating one. Computed on demand before the first layout.
+    pub(crate) fn tab_bounds(&self, app: &AppHandle, window: &str) -> Result<Bounds, LuminaError> {
+        if let Some(geometry) = self.geometry.lock().unwrap().get(window) {
+            return Ok(geometry.tab);
+        }
+        Ok(self.compute(app, window, None, None)?.tab)
+    }
+
+    /// `size` and `scale_factor` come from the window event that triggered the layout when there is
+    /// one; asking the window instead can return the old scale factor while a DPI change is underway.
+    fn compute(&self, app: &AppHandle, window: &str, size: Option<PhysicalSize<u32>>, scale_factor: Option<f64>) -> Result<Geometry, LuminaError> {
+        let browser_window = app.get_window(window).ok_or_else(|| LuminaError::NotFound(format!("Window {} not found", window)))?;
+        let size = match size {
+            Some(size) => size,
+            None => browser_window.inner_size()?,
+        };
+        let scale_factor = match scale_factor {
+            Some(scale_factor) => scale_factor,
+            None => browser_window.scale_factor()?,
+        };
+        let logical_size = size.to_logical::<f64>(scale_factor);
+
+        let (menu_open, suggestions_height, infobar_height) =
+            app.state::<UiState>().window(window, |ui| (ui.sidebar_open, ui.suggestions_height as f64, ui.infobar_height as f64));
+        let vertical_tabs = app.state::<AppDataStore>().data.lock().map_err(|e| e.to_string())?.settings.vertical_tabs;
+
+        let (main, tab) = calculate_layout(logical_size, vertical_tabs, menu_open, suggestions_height, infobar_height);
+        Ok(Geometry {
+            scale_factor,
+            window: Bounds { x: 0.0, y: 0.0, width: logical_size.width, height: logical_size.height },
+            main,
+            tab,
+        })
+    }
+
+    /// Recomputes a browser window's geometry and moves every webview in it to its place.
+    pub(crate) fn relayout(&self, app: &AppHandle, window: &str, size: Option<PhysicalSize<u32>>, scale_factor: Option<f64>) -> Result<Geometry, LuminaError> {
+        let geometry = self.compute(app, window, size, scale_factor)?;
+        self.geometry.lock().unwrap().insert(window.to_string(), geometry);
+
+        let (menu_open, fullscreen_tab) = app.state::<UiState>().window(window, |ui| (ui.sidebar_open, ui.fullscreen_tab.as_ref().map(|(label, _)| label.clone())));
+        let browser_window = app.get_window(window).ok_or_else(|| LuminaError::NotFound(format!("Window {} not found", window)))?;
+        for webview in browser_window.webviews() {
+            self.place(&geometry, window, &webview, fullscreen_tab.as_deref());
+        }
+        if menu_open {
+            let _ = browser_window.set_focus();
+        }
+        Ok(geometry)
+    }
+
+    /// Puts one webview (a tab that was just created, say) where its window's geometry says.
+    pub(crate) fn place_webview(&self, app: &AppHandle, webview: &Webview) -> Result<(), LuminaError> {
+        let window = webview.window().label().to_string();
+        let cached = self.geometry.lock().unwrap().get(&window).copied();
+        let geometry = match cached {
+            Some(geometry) => geometry,
+            None => self.compute(app, &window, None, None)?,
+        };
+        let fullscreen_tab = app.state::<UiState>().window(&window, |ui| ui.fullscreen_tab.as_ref().map(|(label, _)| label.clone()));
+        self.place(&geometry, &window, webview, fullscreen_tab.as_deref());
+        Ok(())
+    }
+
+    fn place(&self, geometry: &Geometry, window: &str, webview: &Webview, fullscreen_tab: Option<&str>) {
+        let label = webview.label();
+        if label == window {
+            geometry.main.apply(webview);
+        } else if fullscreen_tab == Some(label) {
+            // Covers the top bar and sidebar until the page leaves fullscreen
+            geometry.window.apply(webview);
+        } else {
+            geometry.tab.apply(webview);
+        }
+    }
+
+    pub(crate) fn forget(&self, window: &str) {
+        self.geometry.lock().unwrap().remove(window);
+    }
+}
+
+/// Lays a browser window out again after a change to its size or to the UI around its tabs.
+pub(crate) fn relayout(app: &AppHandle, window: &str) -> Result<(), LuminaError> {
+    app.state::<LayoutManager>().relayout(app, window, None, None).map(|_| ())
+}
+
+/// After a setting every browser window depends on, like vertical tabs.
+pub(crate) fn relayout_all(app: &AppHandle) {
+    for label in app.windows().into_keys().filter(|label| is_browser_window(label)) {
+        let _ = relayout(app, &label);
+    }
+}
+
+#[tauri::command]
+pub(crate) fn set_suggestions_height(state: tauri::State<'_, UiState>, app: AppHandle, window: tauri::Window, height: u32) -> Result<(), LuminaError> {
+    state.window(window.label(), |ui| ui.suggestions_height = height);
+    relayout(&app, window.label())
+}
+
+#[tauri::command]
+pub(crate) fn set_infobar_height(state: tauri::State<'_, UiState>, app: AppHandle, window: tauri::Window, height: u32) -> Result<(), LuminaError> {
+    state.window(window.label(), |ui| ui.infobar_height = height);
+    relayout(&app, window.label())
+}
+
+#[tauri::command]
+pub(crate) fn toggle_sidebar(state: tauri::State<'_, UiState>, app: AppHandle, window: tauri::Window, open: bool) -> Result<(), LuminaError> {
+    state.window(window.label(), |ui| ui.sidebar_open = open);
+    relayout(&app, window.label())
+}
+
+/// Opens another browser window with its own top bar and tabs, starting on the new tab page.
+#[tauri::command]
+pub(crate) async fn new_browser_window(app: AppHandle) -> Result<String, LuminaError> {
+    let label = format!("{}{}", BROWSER_WINDOW_PREFIX, chrono::Utc::now().timestamp_micros());
+    let window = tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::App("index.html".into()))
+        .title("Lumina Browser")
+        .inner_size(1280.0, 800.0)
+        .min_inner_size(640.0, 480.0)
+        // Same as "main" in tauri.conf.json; WebView2 won't share a browser process across different args
+        .additional_browser_args("--ignore-certificate-errors")
+        .build()?;
+    app.state::<UiState>().window(&label, |_| ());
+    app.state::<UiState>().set_focused_window(&label);
+    let _ = window.set_focus();
+    Ok(label)
+}
+
+/// Called when a page enters or leaves HTML5 fullscreen (a video's fullscreen button, say). The tab
+/// takes over its whole window, which goes fullscreen too unless it already was.
+pub(crate) fn set_tab_fullscreen(app: &AppHandle, label: &str, fullscreen: bool) {
+    let Some(window) = tab_window(app, label) else { return };
+    let Some(browser_window) = app.get_window(&window) else { return };
+    let restore_window = app.state::<UiState>().window(&window, |ui| {
+        if fullscreen {
+            let was_fullscreen = match ui.fullscreen_tab.take() {
+                Some((_, was_fullscreen)) => was_fullscreen,
+                None => {
+                    let was_fullscreen = browser_window.is_fullscreen().unwrap_or(false);
+                    let _ = browser_window.set_fullscreen(true);
+                    was_fullscreen
+                }
+            };
+            ui.fullscreen_tab = Some((label.to_string(), was_fullscreen));
+            None
+        } else {
+            match ui.fullscreen_tab.take() {
+                Some((tab, was_fullscreen)) if tab == label => Some(was_fullscreen),
+                // Another tab is the fullscreen one
+                other => {
+                    ui.fullscreen_tab = other;
+                    None
+                }
+            }
+        }
+    });
+    if restore_window == Some(false) {
+        let _ = browser_window.set_fullscreen(false);
+    }
+    if let Err(e) = relayout(app, &window) {
+        eprintln!("Lumina: layout not updated after fullscreen change: {}", e);
+    }
+}
+
+/// Follows the page's fullscreen state through WebView2's ContainsFullScreenElementChanged.
+#[cfg(windows)]
+pub(crate) fn watch_fullscreen(app: &AppHandle, webview: &tauri::Webview) {
+    let app = app.clone();
+    let label = webview.label().to_string();
+    let _ = webview.with_webview(move |webview| {
+        use webview2_com::ContainsFullScreenElementChangedEventHandler;
+
+        let handler = ContainsFullScreenElementChangedEventHandler::create(Box::new(move |sender, _| {
+            if let Some(core) = sender {
+                let mut contains = windows_core::BOOL::default();
+                unsafe { core.ContainsFullScreenElement(&mut contains)? };
+                let (app, label, fullscreen) = (app.clone(), label.clone(), contains.as_bool());
+                // Layout changes go through the event loop, which is busy running this handler
+                tauri::async_runtime::spawn(async move { set_tab_fullscreen(&app, &label, fullscreen) });
+            }
+            Ok(())
+        }));
+        unsafe {
+            if let Ok(core) = webview.controller().CoreWebView2() {
+                let mut token = 0i64;
+                let _ = core.add_ContainsFullScreenElementChanged(&handler, &mut token);
+            }
+        }
+    });
+}
+
+/// Follows the page's fullscreen state through WebKitGTK's enter/leave-fullscreen signals.
+#[cfg(target_os = "linux")]
+pub(crate) fn watch_fullscreen(app: &AppHandle, webview: &tauri::Webview) {
+    let app = app.clone();
+    let label = webview.label().to_string();
+    let _ = webview.with_webview(move |webview| {
+        use webkit2gtk::WebViewExt;
+
+        let inner = webview.inner();
+        for fullscreen in [true, false] {
+            let (app, label) = (app.clone(), label.clone());
+            let handler = move |_: &webkit2gtk::WebView| {
+                let (app, label) = (app.clone(), label.clone());
+                tauri::async_runtime::spawn(async move { set_tab_fullscreen(&app, &label, fullscreen) });
+                // false lets WebKit carry on with its own fullscreen handling
+                false
+            };
+            if fullscreen {
+                inner.connect_enter_fullscreen(handler);
+            } else {
+                inner.connect_leave_fullscreen(handler);
+            }
+        }
+    });
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub(crate) fn watch_fullscreen(_app: &AppHandle, _webview: &tauri::Webview) {}
+
+#[derive(Default)]
+pub(crate) struct WindowUi {
+    pub(crate) sidebar_open: bool,
+    pub(crate) suggestions_height: u32,
+    /// The site prompt bar, when the active tab has a question pending
+    pub(crate) infobar_height: u32,
+    pub(crate) current_tab: Option<String>,
+    /// The tab showing an HTML5 fullscreen element, and whether the window was fullscreen before it
+    pub(crate) fullscreen_tab: Option<(String, bool)>,
+}
+
+/// UI state of each browser window, by window label.
+pub(crate) struct UiState {
+    windows: Mutex<HashMap<String, WindowUi>>,
+    /// The browser window used last; its active tab is what "the current tab" means
+    focused_window: Mutex<String>,
+}
+
+impl UiState {
+    pub(crate) fn new() -> Self {
+        Self { windows: Mutex::new(HashMap::new()), focused_window: Mutex::new("main".to_string()) }
+    }
+
+    /// Runs `f` on a window's state, starting it if the window is new.
+    pub(crate) fn window<R>(&self, window: &str, f: impl FnOnce(&mut WindowUi) -> R) -> R {
+        f(self.windows.lock().unwrap().entry(window.to_string()).or_default())
+    }
+
+    pub(crate) fn focused_window(&self) -> String {
+        self.focused_window.lock().unwrap().clone()
+    }
+
+    pub(crate) fn set_focused_window(&self, window: &str) {
+        *self.focused_window.lock().unwrap() = window.to_string();
+    }
+
+    /// The active tab of the browser window used last.
+    pub(crate) fn current_tab(&self) -> Option<String> {
+        let window = self.focused_window();
+        self.window(&window, |ui| ui.current_tab.clone())
+    }
+
+    /// The active tab of every browser window.
+    pub(crate) fn current_tabs(&self) -> Vec<String> {
+        self.windows.lock().unwrap().values().filter_map(|ui| ui.current_tab.clone()).collect()
+    }
+
+    pub(crate) fn remove_window(&self, window: &str) {
+        self.windows.lock().unwrap().remove(window);
+        let mut focused = self.focused_window.lock().unwrap();
+        if *focused == window {
+            *focused = "main".to_string();
+        }
+    }
+}
@@ -1,65 +1,74 @@
+mod adblock;
+mod aliases;
+mod archive;
+mod autofill;
+mod capabilities;
+mod cli;
+mod crash_recovery;
+mod crx;
 mod data;
+mod data_viewer;
+mod downloads;
+mod error;
+mod events;
+mod forget_sites;
+mod header_rules;
 mod history_manager;
+mod history_stats;
+mod http;
+mod icons;
+mod ipc_guard;
+mod isolation;
+mod layout;
+mod local_ai;
+mod local_files;
+mod locale;
+mod maintenance;
+mod media_capture;
+mod network;
+mod notes;
+mod notifications;
+mod page_script;
+mod pwa;
+mod referrer;
+mod screenshot;
+mod scripting;
+mod secrets;
 mod security; // Added security module
+mod sessions;
+mod site_prompts;
+mod speech;
+mod spellcheck;
+mod startup;
+mod storage;
+mod store;
+mod tab_registry;
+mod tabs;
+mod undo;
+mod zoom;
 use history_manager::HistoryManager;
-use data::{AppDataStore, HistoryItem, FavoriteItem, AppSettings};
-use tauri::{AppHandle, Manager, WebviewUrl, Emitter, Listener, Url};
+use tab_registry::TabRegistry;
+use events::AppEvent;
+use error::LuminaError;
+use data::{AppDataStore, HistoryItem, FavoriteItem, FavoriteHealth, AppSettings, StartupBehavior, Flags, FLAG_INFO};
+use tauri::{AppHandle, Manager, Listener, Url};
 use futures_util::StreamExt;
-use tokio::io::{AsyncWriteExt, AsyncSeekExt};
 use std::collections::HashMap;
-use std::sync::{Mutex, Arc, OnceLock};
+use std::sync::Mutex;
 use std::path::PathBuf;
 use serde::{Serialize, Deserialize};
-use std::fs::OpenOptions;
-use adblock::engine::Engine;
-use adblock::lists::FilterSet;
+use ::adblock::engine::Engine;
+use ::adblock::lists::FilterSet;
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState, Modifiers, Code};
-use base64::Engine as _;
-use mlua::Lua;
-
-static ADBLOCK_ENGINE: OnceLock<Arc<Mutex<Engine>>> = OnceLock::new();
-static ADBLOCK_STATS: OnceLock<Arc<Mutex<HashMap<String, u32>>>> = OnceLock::new();
-
-struct LuaState {
-    lua: Mutex<Lua>,
-}
-
-// 1. Safe Lua Execution (Real Lua 5.4 Runtime)
-// This creates a sandboxed Lua environment that can interact with the browser safely.
-fn create_lua_runtime() -> Lua {
-    // Create a Lua state with safe standard libraries only (Sandbox)
-    // We exclude IO, OS, and Package libraries to prevent system access
-    // Note: Lua 5.4 has built-in bitwise operators, so BIT library is not needed/available.
-    let libs = mlua::StdLib::MATH | mlua::StdLib::STRING | mlua::StdLib::TABLE;
-    let lua = Lua::new_with(libs, mlua::LuaOptions::default()).expect("Failed to create Lua state");
-    
-    // Inject Custom Lumina API
-    let _ = lua.load("
-        -- Custom Lumina API
-        lumina = {
-            version = '0.3.6',
-            platform = 'windows'
-        }
-    ").exec();
-
-    lua
-}
-
-#[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct StoreItem {
-    pub id: String,
-    pub title: String,
-    pub author: String,
-    pub description: String,
-    pub icon: String,
-    pub version: String,
-    pub tags: Vec<String>,
-    pub verified: bool,
-    #[serde(default)]
-    pub installed: bool,
-    #[serde(default, rename = "comingSoon")]
-    pub coming_soon: bool,
-}
+use adblock::{ADBLOCK_ENABLED, ADBLOCK_ENGINE, AdblockListInfo, adblock_lists, handle_adblock_request, host_blocklist, install_adblock_engine, load_adblock_state, load_host_blocklist, neutered_resources, normalize_site_domain, set_adblock_enabled, summarize_filter_list};
+use downloads::{DOWNLOAD_SCHEDULE_CHECK_INTERVAL, DownloadItem, DownloadManager, pump_download_queue, sanitize_filename};
+use layout::{is_browser_window, relayout, relayout_all, LayoutManager, UiState};
+use pwa::{PWA_MANIFEST_CHECK_INTERVAL, PwaState, apply_pwa_caption_color, apply_pwa_colors, get_pwa_capability_script, get_pwa_init_script, on_pwa_page_load, pwa_data_dir, refresh_installed_pwas, resolve_pwa_launch};
+use scripting::{LuaState, NetworkSidecarRequest, NetworkState, SidekickState, load_all_scripts, set_sidecar_status, sidecar_status, start_script_watcher};
+use store::{STORE_CSS, StoreItem, cached_store_doc, get_store_items, perform_uninstall, request_install, unzip_into};
+use tabs::{TAB_HIBERNATE_CHECK_INTERVAL, TabCreatedPayload, TabUpdatedPayload, hibernate_idle_tabs, webview_origin};
+/// "light" or "dark" as last resolved from the theme setting
+static ACTIVE_THEME: Mutex<String> = Mutex::new(String::new());
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ToastPayload {
@@ -67,294 +76,98 @@ pub struct ToastPayload {
     pub level: String,
 }
 
-#[tauri::command]
-fn get_store_items(app: AppHandle) -> Vec<StoreItem> {
-    // Prioritize the writable app data store.json so user changes (installs) are reflected
-    let app_data_store = app.path().app_data_dir().unwrap_or_default().join("store.json");
-    
-    let paths = vec![
-        app_data_store, // Check writable path FIRST
-        PathBuf::from("store.json"),
-        PathBuf::from("data/store.json"),
-        PathBuf::from("src-tauri/store.json"),
-    ];
-
-    for path in paths {
-        if path.exists() {
-            if let Ok(content) = std::fs::read_to_string(&path) {
-                if let Ok(items) = serde_json::from_str::<Vec<StoreItem>>(&content) {
-                    return items;
-                }
-            }
-        }
-    }
-    Vec::new()
-}
-
-fn perform_install(app: &AppHandle, id: &str) -> bool {
-    // 1. Determine Store Path (Writable)
-    let app_data_dir = app.path().app_data_dir().unwrap_or(PathBuf::from("."));
-    if !app_data_dir.exists() {
-        let _ = std::fs::create_dir_all(&app_data_dir);
-    }
-    let store_path = app_data_dir.join("store.json");
-
-    // 2. Load Existing Items
-    let mut items: Vec<StoreItem> = Vec::new();
-    
-    // Try to read from writable path first
-    if store_path.exists() {
-        if let Ok(content) = std::fs::read_to_string(&store_path) {
-             if let Ok(parsed) = serde_json::from_str(&content) {
-                 items = parsed;
-             }
-        }
-    } else {
-        // Fallback to read from read-only locations (like src-tauri/store.json) to init
-        let paths = vec![
-            PathBuf::from("store.json"),
-            PathBuf::from("src-tauri/store.json"),
-        ];
-        for p in paths {
-            if p.exists() {
-                if let Ok(content) = std::fs::read_to_string(&p) {
-                     if let Ok(parsed) = serde_json::from_str(&content) {
-                         items = parsed;
-                         break;
-                     }
-                }
-            }
-        }
-    }
-
-    // 3. Update Item
-    let mut found = false;
-    for item in &mut items {
-        if item.id == id {
-            item.installed = true;
-            found = true;
-            break;
-        }
-    }
-
-    if found {
-        // 4. Save to Writable Path
-        if let Ok(json) = serde_json::to_string_pretty(&items) {
-            if let Ok(mut file) = OpenOptions::new().write(true).create(true).truncate(true).open(&store_path) {
-                if std::io::Write::write_all(&mut file, json.as_bytes()).is_ok() {
-                    return true;
-                }
-            }
-        }
-    }
-    false
-}
-
-#[tauri::command]
-fn install_package(app: AppHandle, id: String) {
-    println!("Lumina Command: Installing {}", id);
-    
-    if perform_install(&app, &id) {
-         let _ = app.emit("toast", ToastPayload {
-            message: format!("Modül başarıyla kuruldu: {}", id),
-            level: "success".to_string(),
-        });
-    } else {
-        let _ = app.emit("toast", ToastPayload {
-            message: format!("Kurulum başarısız: {}", id),
-            level: "error".to_string(),
-        });
-    }
-}
-
-#[derive(Clone, serde::Serialize)]
-struct AdblockStatsPayload {
-    label: String,
-    blocked_count: u32,
-}
-
-fn check_adblock_url(url: &str, referer: Option<&str>, label: &str, app: &AppHandle) -> bool {
-    // 0. Always Allow Internal Protocols
-    if url.starts_with("lumina:") || url.starts_with("lumina-app:") {
-        return false;
-    }
-
-    // 0. Force Block List (Overrides Friendly Policy) - Kills AdMatic & Google Ads on Friendly Sites
-    if url.contains("admatic.com.tr") || 
-       url.contains("doubleclick.net") || 
-       url.contains("googlesyndication.com") || 
-       url.contains("adnxs.com") || 
-       url.contains("smartadserver.com") ||
-       url.contains("criteo.com") ||
-       url.contains("rubiconproject.com") ||
-       url.contains("pubmatic.com") {
-        println!("Lumina Adblock: Forced block on ad domain: {}", url);
-        return true;
-    }
-
-    // 1. Friendly Domain Policy (Bypass Adblock for Gemini/Google Critical Services)
-    if let Some(ref_str) = referer {
-         if ref_str.contains("gemini.google.com") || 
-            ref_str.contains("accounts.google.com") ||
-            ref_str.contains("google.com") ||
-            ref_str.contains("youtube.com") ||
-            ref_str.contains("transfermarkt") {
-              // println!("Lumina Adblock: Bypassing friendly domain: {}", url);
-              return false;
-         }
-    }
-
-    // 1. Check Global Adblock Engine
-    if let Some(engine_arc) = ADBLOCK_ENGINE.get() {
-        if let Ok(engine) = engine_arc.lock() {
-            let check_result = engine.check_network_request(&adblock::request::Request::new(
-                url,
-                referer.unwrap_or(""), 
-                "", // Request type (empty for now)
-            ).unwrap());
-            
-            if check_result.matched {
-                println!("Lumina Adblock: Blocked {}", url);
-                
-                // Increment stats
-                if let Some(stats_arc) = ADBLOCK_STATS.get() {
-                    if let Ok(mut stats) = stats_arc.lock() {
-                        let count = stats.entry(label.to_string()).or_insert(0);
-                        *count += 1;
-                        
-                        // Emit event to frontend (Spawned to avoid blocking the resource request thread)
-                        let app_emit = app.clone();
-                        let label_emit = label.to_string();
-                        let count_emit = *count;
-                        tauri::async_runtime::spawn(async move {
-                            let _ = app_emit.emit("adblock-stats-update", AdblockStatsPayload {
-                                label: label_emit,
-                                blocked_count: count_emit,
-                            });
-                        });
-                    }
-                }
-                
-                return true;
-            }
-        }
-    }
-
-    // 2. Fallback to HostBlock List
-    if BLOCKED_DOMAINS.iter().any(|d| url.contains(d)) {
-        println!("Lumina HostBlock: {}", url);
-        // Increment stats (also for host block)
-        if let Some(stats_arc) = ADBLOCK_STATS.get() {
-            if let Ok(mut stats) = stats_arc.lock() {
-                let count = stats.entry(label.to_string()).or_insert(0);
-                *count += 1;
-                
-                // Emit event to frontend (Spawned)
-                let app_emit = app.clone();
-                let label_emit = label.to_string();
-                let count_emit = *count;
-                tauri::async_runtime::spawn(async move {
-                    let _ = app_emit.emit("adblock-stats-update", AdblockStatsPayload {
-                        label: label_emit,
-                        blocked_count: count_emit,
-                    });
-                });
-            }
-        }
-        return true;
-    }
-
-    false
-}
-
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DownloadItem {
-    pub url: String,
-    pub file_name: String,
-    pub total_size: u64,
-    pub downloaded_size: u64,
-    pub path: String,
-    pub status: String, // "downloading", "paused", "completed", "failed"
-    #[serde(default)]
-    pub added_at: i64,
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileFileInfo {
+    name: String,
+    size_bytes: u64,
 }
 
-pub struct DownloadManager {
-    pub downloads: Mutex<HashMap<String, DownloadItem>>,
-    pub app_dir: PathBuf,
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Diagnostics {
+    app_name: String,
+    app_version: String,
+    tauri_version: String,
+    webview_version: String,
+    os: String,
+    arch: String,
+    profile_path: String,
+    profile_files: Vec<ProfileFileInfo>,
+    adblock_enabled: bool,
+    adblock_engine_ready: bool,
+    adblock_lists: Vec<AdblockListInfo>,
+    adblock_resources: usize,
+    host_blocklist_size: usize,
+    sidecars: HashMap<String, String>,
+    lua_scripts: usize,
+    startup: startup::StartupReport,
+    /// Last history.db maintenance run
+    db_maintenance: Option<maintenance::DbMaintenanceReport>,
 }
 
-impl DownloadManager {
-    pub fn new(app_dir: PathBuf) -> Self {
-        let mut manager = Self {
-            downloads: Mutex::new(HashMap::new()),
-            app_dir: app_dir.clone(),
-        };
-        manager.load();
-        manager
-    }
-
-    pub fn load(&mut self) {
-        let path = self.app_dir.join("downloads.json");
-        if path.exists() {
-            if let Ok(content) = std::fs::read_to_string(&path) {
-                if let Ok(data) = serde_json::from_str::<HashMap<String, DownloadItem>>(&content) {
-                    *self.downloads.lock().unwrap() = data;
-                }
-            }
-        }
-    }
+fn collect_diagnostics(app: &AppHandle) -> Diagnostics {
+    let package = app.package_info();
+    let profile_dir = app.path().app_data_dir().unwrap_or_default();
+
+    let mut profile_files: Vec<ProfileFileInfo> = std::fs::read_dir(&profile_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let meta = entry.metadata().ok()?;
+                    meta.is_file().then(|| ProfileFileInfo {
+                        name: entry.file_name().to_string_lossy().to_string(),
+                        size_bytes: meta.len(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    profile_files.sort_by(|a, b| a.name.cmp(&b.name));
 
-    pub fn save(&self) {
-        let path = self.app_dir.join("downloads.json");
-        let data = self.downloads.lock().unwrap();
-        if let Ok(content) = serde_json::to_string_pretty(&*data) {
-            // Use OpenOptions (restored)
-            if let Ok(mut file) = OpenOptions::new().write(true).create(true).truncate(true).open(path) {
-                let _ = std::io::Write::write_all(&mut file, content.as_bytes());
-            }
-        }
-    }
-    
-    pub fn update_status(&self, url: &str, status: &str) {
-        let mut data = self.downloads.lock().unwrap();
-        if let Some(item) = data.get_mut(url) {
-            item.status = status.to_string();
-        }
-        drop(data); // unlock before save
-        self.save();
-    }
-    
-    pub fn update_progress(&self, url: &str, downloaded: u64, total: u64) {
-        let mut data = self.downloads.lock().unwrap();
-        if let Some(item) = data.get_mut(url) {
-            item.downloaded_size = downloaded;
-            item.total_size = total;
-        }
-        // Don't save on every progress update to avoid IO thrashing
+    let mut sidecars = HashMap::new();
+    for name in ["lumina-net", "lumina-sidekick", "llama-server"] {
+        sidecars.insert(name.to_string(), sidecar_status(name));
     }
-
-    pub fn get_downloads(&self) -> Vec<DownloadItem> {
-        let data = self.downloads.lock().unwrap();
-        data.values().cloned().collect()
+    sidecars.insert("kip-lang".to_string(), "on demand".to_string());
+
+    Diagnostics {
+        app_name: package.name.clone(),
+        app_version: package.version.to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+        webview_version: tauri::webview_version().unwrap_or_else(|e| format!("unknown ({})", e)),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        profile_path: profile_dir.to_string_lossy().to_string(),
+        profile_files,
+        adblock_enabled: ADBLOCK_ENABLED.load(std::sync::atomic::Ordering::Relaxed),
+        adblock_engine_ready: ADBLOCK_ENGINE.get().is_some(),
+        adblock_lists: adblock_lists().lock().unwrap().clone(),
+        adblock_resources: neutered_resources().len(),
+        host_blocklist_size: host_blocklist().lock().unwrap().len(),
+        sidecars,
+        lua_scripts: app.try_state::<LuaState>().map(|lua| lua.get_scripts().len()).unwrap_or(0),
+        startup: startup::report(),
+        db_maintenance: maintenance::last_report(app),
     }
 }
 
-
-#[allow(dead_code)]
-struct SidekickState {
-    tx: tokio::sync::mpsc::Sender<String>,
+/// Everything shown on lumina-app://about, as JSON for bug reports.
+#[tauri::command]
+fn get_diagnostics(app: AppHandle) -> Diagnostics {
+    collect_diagnostics(&app)
 }
 
 #[tauri::command]
 async fn request_omnibox_suggestions(
     app: tauri::AppHandle,
+    webview: tauri::Webview,
     _state: tauri::State<'_, SidekickState>, 
     app_data: tauri::State<'_, AppDataStore>,
     history_manager: tauri::State<'_, HistoryManager>,
     query: String
-) -> Result<(), String> {
+) -> Result<(), LuminaError> {
     // 1. Fetch Favorites
     let favorites = {
         let data = app_data.data.lock().unwrap();
@@ -362,15 +175,33 @@ async fn request_omnibox_suggestions(
     };
 
     // 2. Fetch History (Search or Recent)
-    let history_items = if query.is_empty() {
+    let mut history_items = if query.is_empty() {
         history_manager.get_recent(10).unwrap_or_default()
     } else {
         history_manager.search(&query).unwrap_or_default()
     };
+    // Visits from before a site was excluded stay out of suggestions
+    history_items.retain(|item| !app_data.is_history_excluded(&item.url));
 
     // 3. Construct Suggestions
     let mut suggestions = Vec::new();
 
+    // Open tabs first, so an already-open page is switched to instead of duplicated; only this window's can be switched to
+    let open_tabs = app.state::<TabRegistry>().snapshot(Some(webview.window().label()));
+    let query_lower = query.to_lowercase();
+    for tab in open_tabs.tabs.into_iter().filter(|t| Some(&t.label) != open_tabs.active_tab.as_ref()) {
+        let title = if tab.title.is_empty() { tab.url.clone() } else { tab.title };
+        if query.is_empty() || title.to_lowercase().contains(&query_lower) || tab.url.to_lowercase().contains(&query_lower) {
+            suggestions.push(serde_json::json!({
+                "title": title,
+                "url": tab.url,
+                "icon": "tab",
+                "type": "switch-tab",
+                "label": tab.label
+            }));
+        }
+    }
+
     // Add favorites that match query
     for fav in favorites {
         if query.is_empty() || fav.title.to_lowercase().contains(&query.to_lowercase()) || fav.url.to_lowercase().contains(&query.to_lowercase()) {
@@ -391,82 +222,368 @@ async fn request_omnibox_suggestions(
         }));
     }
 
+    // "?question" asks the local model, when Local Brain is set up
+    if let Some(question) = query.strip_prefix('?').map(str::trim).filter(|q| !q.is_empty()) {
+        if local_ai::is_available(&app) {
+            suggestions.insert(0, serde_json::json!({
+                "title": format!("Yerel yapay zekaya sor: {}", question),
+                "url": format!("lumina-app://ask?q={}", urlencoding::encode(question)),
+                "icon": "cpu",
+                "type": "ask-ai"
+            }));
+        }
+    }
+
     // 4. Emit Results directly to frontend
     let response = serde_json::json!({
         "suggestions": suggestions
     });
     
-    use tauri::Emitter;
-    let _ = app.emit("omnibox-results", response.to_string());
+    events::emit(&app, AppEvent::OmniboxResults(response.to_string()));
     
     Ok(())
 }
 
-#[tauri::command]
-async fn navigate(app: AppHandle, label: String, url: String) {
-    // println!("Rust: navigating tab {} to {}", label, url);
-    // Try to find the webview. If not found, it might be because it was JUST created and not yet in the map.
-    // In Tauri v2, add_child returns the webview instance.
-    // But navigate is a separate command called from JS, so it relies on AppHandle lookup.
-    
-    let mut webview = app.get_webview(&label);
-    if webview.is_none() {
-        // Retry logic for race conditions - Increased to 10x 100ms (1s total)
-        for i in 0..10 {
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-            webview = app.get_webview(&label);
-            if webview.is_some() { 
-                println!("Rust: webview {} found after retry {}", label, i+1);
-                break; 
-            }
-        }
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// Embeds a value as a JS literal inside an inline <script> without opening or closing a tag
+fn to_script_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "null".to_string()).replace('<', "\\u003c")
+}
+
+/// `data-args` for an element with a `data-on<event>` handler: its arguments as a JSON array.
+fn handler_args<T: Serialize>(args: &T) -> String {
+    escape_html(&serde_json::to_string(args).unwrap_or_else(|_| "[]".to_string()))
+}
+
+/// A link to `url` if it is a web, file or internal page; anything else (`javascript:`, `data:`) is
+/// shown as text.
+fn link_html(url: &str) -> String {
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme.to_ascii_lowercase());
+    if matches!(scheme.as_deref(), Some("http" | "https" | "file" | "lumina-app")) {
+        format!(r#"<a href="{0}">{0}</a>"#, escape_html(url))
+    } else {
+        escape_html(url)
     }
+}
 
-    if let Some(webview) = webview {
-        let _ = webview.set_focus();
-        
-        // Rewrite lumina:// to lumina-app://localhost/ (standardized) for internal navigation
-        let target_url = if url.starts_with("lumina://") {
-            url.replace("lumina://", "lumina-app://localhost/")
-        } else if url.starts_with("lumina-app://") {
-            if !url.contains("lumina-app://localhost/") {
-                url.replace("lumina-app://", "lumina-app://localhost/")
-            } else {
-                url.clone()
-            }
-        } else {
-            url.clone()
-        };
+/// Reads a single decoded parameter from a "?a=1&b=2" query string.
+fn query_param(query: &str, name: &str) -> Option<String> {
+    let query = query.trim_start_matches('?');
+    let query = query.split('#').next().unwrap_or(query);
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
 
-        println!("Rust: navigating tab {} to {}", label, target_url); // DEBUG LOG
+const HISTORY_PAGE_SIZE: i64 = 50;
 
-        // Use eval for navigation
-        let _ = webview.eval(format!("window.location.assign('{}')", target_url).as_str());
-        
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryPage {
+    items: Vec<history_manager::HistoryItem>,
+    page: i64,
+    has_more: bool,
+}
+
+fn load_history_page(history_manager: &HistoryManager, page: i64) -> HistoryPage {
+    let page = page.max(0);
+    // Fetch one extra row to know whether another page exists
+    let mut items = history_manager
+        .get_page(page * HISTORY_PAGE_SIZE, HISTORY_PAGE_SIZE + 1)
+        .unwrap_or_default();
+    let has_more = items.len() as i64 > HISTORY_PAGE_SIZE;
+    items.truncate(HISTORY_PAGE_SIZE as usize);
+    HistoryPage { items, page, has_more }
+}
+
+/// Colors internal pages draw from, resolved from the active theme and the accent setting.
+struct ThemeTokens {
+    accent: String,
+    bg: &'static str,
+    card: &'static str,
+    surface: &'static str,
+    border: &'static str,
+    text: &'static str,
+    text_dim: &'static str,
+    text_strong: &'static str,
+}
+
+/// Only plain hex colors are let through into page CSS.
+fn sanitize_accent(value: &str) -> Option<String> {
+    let hex = value.trim().strip_prefix('#')?;
+    (matches!(hex.len(), 3 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit())).then(|| format!("#{}", hex))
+}
+
+fn theme_tokens(app: &AppHandle) -> ThemeTokens {
+    let accent = sanitize_accent(&app.state::<AppDataStore>().data.lock().unwrap().settings.accent_color)
+        .unwrap_or_else(|| "#05B8CC".to_string());
+    if active_theme() == "light" {
+        ThemeTokens { accent, bg: "#f5f5f7", card: "#ffffff", surface: "#f3f4f6", border: "#d1d5db", text: "#1f2937", text_dim: "#6b7280", text_strong: "#111827" }
     } else {
-        println!("Rust: webview {} not found via AppHandle lookup (gave up after retries)", label);
+        ThemeTokens { accent, bg: "#121212", card: "#1e1e1e", surface: "#2d2d2d", border: "#333333", text: "#e0e0e0", text_dim: "#a0a0a0", text_strong: "#ffffff" }
+    }
+}
+
+impl ThemeTokens {
+    fn css(&self) -> String {
+        format!(
+            "<style>:root {{ --primary: {}; --bg: {}; --card: {}; --surface: {}; --border: {}; --text: {}; --text-dim: {}; --text-strong: {}; color-scheme: {}; }}</style>",
+            self.accent, self.bg, self.card, self.surface, self.border, self.text, self.text_dim, self.text_strong,
+            if self.bg == "#121212" { "dark" } else { "light" }
+        )
+    }
+}
+
+fn insert_into_head(html: &str, head: &str) -> String {
+    match html.find("<head>") {
+        Some(i) => format!("{}{}{}", &html[..i + 6], head, &html[i + 6..]),
+        None => format!("{}{}", head, html),
+    }
+}
+
+/// Renders an internal page with the current theme tokens injected ahead of its own styles.
+fn get_internal_page_html(app: &AppHandle, path: &str, query: &str) -> Option<String> {
+    let html = render_internal_page(app, &normalize_internal_path(path)?, query)?;
+    Some(insert_into_head(&html, &theme_tokens(app).css()))
+}
+
+/// Internal pages are self-contained: their own scripts, inline style, IPC, remote images (favicons,
+/// store screenshots) and the new tab search form. Nothing else loads.
+fn internal_page_csp(nonce: &str) -> String {
+    format!(
+        "default-src 'none'; script-src 'nonce-{}'; style-src 'unsafe-inline'; \
+        img-src lumina-app: http://lumina-app.localhost https: http: data: blob:; font-src data:; \
+        connect-src ipc: http://ipc.localhost lumina-app: http://lumina-app.localhost; form-action https: http:; base-uri 'none'",
+        nonce
+    )
+}
+
+/// Inline event handlers don't run under the CSP. Elements name a page function in `data-on<event>`
+/// instead; it's called with the `data-args` JSON array, then the element and the event.
+const INTERNAL_PAGE_HANDLERS: &str = r#"
+    for (const type of ['click', 'change', 'input', 'submit']) {
+        document.addEventListener(type, event => {
+            const el = event.target instanceof Element && event.target.closest('[data-on' + type + ']');
+            const handler = el && window[el.getAttribute('data-on' + type)];
+            if (typeof handler !== 'function') return;
+            handler(...(el.dataset.args ? JSON.parse(el.dataset.args) : []), el, event);
+        });
+    }
+"#;
+
+/// Gives the page's own `<script>` tags a fresh nonce, the only scripts its CSP lets run, and adds
+/// the event handler dispatch. Returns the page and its CSP, which is also put in a meta tag for
+/// pages written into a tab by force_internal_navigate.
+pub(crate) fn seal_internal_page(html: &str) -> (String, String) {
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let csp = internal_page_csp(&nonce);
+    let head = format!(r#"<meta http-equiv="Content-Security-Policy" content="{}"><script>{}</script>"#, csp, INTERNAL_PAGE_HANDLERS);
+    let html = insert_into_head(html, &head).replace("<script>", &format!(r#"<script nonce="{}">"#, nonce));
+    (html, csp)
+}
+
+static INTERNAL_ACTION_TOKEN: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+/// Per-run nonce for lumina-app:// URLs that change state without a prompt (uninstall), so a web page
+/// can't trigger them just by linking there.
+fn internal_action_token() -> &'static str {
+    INTERNAL_ACTION_TOKEN.get_or_init(|| uuid::Uuid::new_v4().simple().to_string())
+}
+
+/// Opens the install prompt for a package
+fn store_install_url(id: &str) -> String {
+    format!("lumina-app://install?id={}", urlencoding::encode(id))
+}
+
+/// `lumina-app://uninstall?id=...` carrying the action token
+fn store_uninstall_url(id: &str) -> String {
+    format!("lumina-app://uninstall?id={}&token={}", urlencoding::encode(id), internal_action_token())
+}
+
+/// Full-page card used for store install prompts and results. `message` and `actions` are HTML.
+fn store_card_page(title: &str, color: &str, icon: &str, message: &str, actions: &str) -> String {
+    format!(r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>{}</title>
+            <meta charset="UTF-8">
+            <style>
+                body {{ font-family: 'Segoe UI', system-ui, sans-serif; background: #0f172a; color: #e2e8f0; margin: 0; display: flex; align-items: center; justify-content: center; height: 100vh; }}
+                .card {{ background: #1e293b; padding: 40px; border-radius: 16px; text-align: center; border: 1px solid #334155; box-shadow: 0 10px 25px -5px rgba(0, 0, 0, 0.5); animation: popIn 0.3s cubic-bezier(0.175, 0.885, 0.32, 1.275); max-width: 480px; }}
+                @keyframes popIn {{ from {{ transform: scale(0.8); opacity: 0; }} to {{ transform: scale(1); opacity: 1; }} }}
+                h1 {{ color: {}; margin: 0 0 16px 0; font-size: 2rem; }}
+                p {{ color: #94a3b8; margin-bottom: 24px; }}
+                dl {{ display: grid; grid-template-columns: auto 1fr; gap: 6px 16px; text-align: left; margin: 0 0 24px 0; }}
+                dt {{ color: #94a3b8; }}
+                dd {{ margin: 0; }}
+                .btn {{ background: #3b82f6; color: white; text-decoration: none; padding: 10px 24px; border-radius: 8px; font-weight: 600; transition: background 0.2s; display: inline-block; border: none; font-size: 1rem; cursor: pointer; margin: 0 4px; }}
+                .btn:hover {{ background: #2563eb; }}
+                .btn.secondary {{ background: #334155; }}
+            </style>
+        </head>
+        <body>
+            <div class="card">
+                <div style="font-size: 4rem; margin-bottom: 10px;">{}</div>
+                <h1>{}</h1>
+                <p>{}</p>
+                {}
+                <a href="lumina-app://store" class="btn secondary">Return to Store</a>
+            </div>
+        </body>
+        </html>
+    "#, escape_html(title), color, icon, escape_html(title), message, actions)
+}
+
+fn install_confirmation_page(item: &StoreItem) -> String {
+    let details = format!(
+        r#"<dl><dt>Author</dt><dd>{}</dd><dt>Version</dt><dd>{}</dd><dt>Signature</dt><dd>{}</dd></dl>"#,
+        escape_html(&item.author),
+        escape_html(&item.version),
+        if item.verified { "Verified publisher" } else { "Not verified" }
+    );
+    let actions = format!(r#"{}
+        <button id="confirm" class="btn">Install</button>
+        <script>
+            document.getElementById('confirm').addEventListener('click', (e) => {{
+                const button = e.target;
+                const id = {};
+                button.disabled = true;
+                button.textContent = 'Installing...';
+                window.__TAURI__.core.invoke('confirm_install', {{ id }})
+                    .then(() => window.location.href = 'lumina-app://store/' + encodeURIComponent(id))
+                    .catch(err => {{ alert(err.message || err); button.disabled = false; button.textContent = 'Install'; }});
+            }});
+        </script>"#,
+        details,
+        to_script_json(&item.id)
+    );
+    store_card_page(
+        &format!("Install {}?", item.title),
+        "#3b82f6",
+        "📦",
+        &escape_html(&item.description),
+        &actions,
+    )
+}
+
+/// Documents Lumina serves itself: internal pages and the browser UI. WebView2 maps custom schemes to
+/// http(s)://<scheme>.localhost.
+fn is_internal_origin(origin: &str) -> bool {
+    Url::parse(origin)
+        .map(|url| matches!(url.scheme(), "lumina-app" | "tauri") || matches!(url.host_str(), Some("lumina-app.localhost" | "tauri.localhost")))
+        .unwrap_or(false)
+}
+
+/// Origin of the document that made a lumina-app:// request, if the webview says (Origin, else Referer)
+fn internal_request_origin(request: &tauri::http::Request<Vec<u8>>) -> Option<String> {
+    ["Origin", "Referer"]
+        .iter()
+        .find_map(|name| request.headers().get(*name).and_then(|value| value.to_str().ok()))
+        .map(|value| value.to_string())
+}
+
+/// Collapses repeated slashes and refuses dot segments, backslashes and control characters, encoded or
+/// not, instead of resolving them.
+fn normalize_internal_path(path: &str) -> Option<String> {
+    let decoded = urlencoding::decode(path).ok()?;
+    if decoded.chars().any(|c| c == '\\' || c.is_control()) {
+        return None;
+    }
+    if decoded.split('/').any(|segment| segment == "." || segment == "..") {
+        return None;
+    }
+    Some(path.split('/').filter(|segment| !segment.is_empty()).collect::<Vec<_>>().join("/"))
+}
+
+fn internal_response(status: u16, body: String, origin: Option<&str>) -> tauri::http::Response<Vec<u8>> {
+    let (body, csp) = seal_internal_page(&body);
+    build_internal_response(status, "text/html; charset=utf-8", &csp, body, origin)
+}
+
+fn build_internal_response(status: u16, content_type: &str, csp: &str, body: String, origin: Option<&str>) -> tauri::http::Response<Vec<u8>> {
+    let mut response = tauri::http::Response::builder()
+        .status(status)
+        .header("Content-Type", content_type)
+        .header("Content-Security-Policy", format!("{}; frame-ancestors 'none'", csp))
+        .header("X-Content-Type-Options", "nosniff")
+        .header("Referrer-Policy", "no-referrer");
+    // Only Lumina's own documents may read the response cross-origin
+    if let Some(origin) = origin.filter(|origin| is_internal_origin(origin)) {
+        response = response.header("Access-Control-Allow-Origin", origin).header("Vary", "Origin");
+    }
+    response.body(body.into_bytes()).unwrap()
+}
+
+fn internal_json_response(status: u16, body: &serde_json::Value, origin: Option<&str>) -> tauri::http::Response<Vec<u8>> {
+    build_internal_response(status, "application/json; charset=utf-8", "default-src 'none'", body.to_string(), origin)
+}
+
+/// JSON behind lumina-app://api/<route>, for internal pages that filter and sort on their own.
+/// `q` narrows every list; downloads also take `sort` (newest, oldest, name, size) and history `page`.
+fn internal_api(app: &AppHandle, route: &str, query: &str) -> Option<serde_json::Value> {
+    let search = query_param(query, "q").map(|q| q.trim().to_lowercase()).filter(|q| !q.is_empty());
+    let matches = |fields: &[&str]| search.as_deref().is_none_or(|q| fields.iter().any(|field| field.to_lowercase().contains(q)));
+
+    match route {
+        "downloads" => {
+            let mut downloads: Vec<DownloadItem> = app.state::<DownloadManager>().get_downloads()
+                .into_iter()
+                .filter(|item| !item.private && matches(&[&item.file_name, &item.url]))
+                .collect();
+            match query_param(query, "sort").as_deref() {
+                Some("oldest") => downloads.sort_by_key(|item| item.added_at),
+                Some("name") => downloads.sort_by_key(|item| item.file_name.to_lowercase()),
+                Some("size") => downloads.sort_by_key(|item| std::cmp::Reverse(item.total_size)),
+                _ => downloads.sort_by_key(|item| std::cmp::Reverse(item.added_at)),
+            }
+            serde_json::to_value(downloads).ok()
+        }
+        "history" => {
+            let history_manager = app.state::<HistoryManager>();
+            let page = match &search {
+                Some(q) => HistoryPage { items: history_manager.search(q).unwrap_or_default(), page: 0, has_more: false },
+                None => load_history_page(&history_manager, query_param(query, "page").and_then(|p| p.parse().ok()).unwrap_or(0)),
+            };
+            serde_json::to_value(page).ok()
+        }
+        "favorites" => {
+            let favorites: Vec<FavoriteItem> = app.state::<AppDataStore>().data.lock().unwrap().favorites
+                .iter()
+                .filter(|item| matches(&[&item.title, &item.url, item.keyword.as_deref().unwrap_or("")]))
+                .cloned()
+                .collect();
+            serde_json::to_value(favorites).ok()
+        }
+        _ => None,
     }
 }
 
-fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
+fn render_internal_page(app: &AppHandle, path: &str, query: &str) -> Option<String> {
     let lumina_style = r#"
         <style>
-            :root { --primary: #05B8CC; --bg: #121212; --card: #1e1e1e; --text: #e0e0e0; --text-dim: #a0a0a0; }
             body { font-family: 'Segoe UI', system-ui, sans-serif; padding: 40px; background: var(--bg); color: var(--text); max-width: 900px; margin: 0 auto; }
-            h1 { border-bottom: 2px solid #333; padding-bottom: 20px; margin-bottom: 30px; font-weight: 600; color: var(--primary); letter-spacing: 1px; }
+            h1 { border-bottom: 2px solid var(--border); padding-bottom: 20px; margin-bottom: 30px; font-weight: 600; color: var(--primary); letter-spacing: 1px; }
             .item { background: var(--card); padding: 15px 20px; margin-bottom: 10px; border-radius: 8px; border-left: 4px solid var(--primary); display: flex; align-items: center; gap: 20px; transition: transform 0.2s; }
             .item:hover { transform: translateX(5px); }
             .time, .meta { color: var(--text-dim); font-size: 0.85em; white-space: nowrap; }
-            .title, .filename { font-weight: 500; margin-bottom: 4px; color: #fff; font-size: 1.1em; }
+            .title, .filename { font-weight: 500; margin-bottom: 4px; color: var(--text-strong); font-size: 1.1em; }
             .url a { color: var(--text-dim); font-size: 0.9em; text-decoration: none; display: block; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
             .url a:hover { color: var(--primary); }
-            button { padding: 8px 16px; cursor: pointer; border: 1px solid #333; background: #2d2d2d; border-radius: 6px; color: #fff; transition: all 0.2s; }
-            button:hover { background: var(--primary); border-color: var(--primary); color: #000; }
-            .empty-state { text-align: center; color: var(--text-dim); padding: 60px; font-size: 1.2em; border: 2px dashed #333; border-radius: 12px; }
+            button { padding: 8px 16px; cursor: pointer; border: 1px solid var(--border); background: var(--surface); border-radius: 6px; color: var(--text-strong); transition: all 0.2s; }
+            button:hover { background: var(--primary); border-color: var(--primary); color: var(--bg); }
+            .empty-state { text-align: center; color: var(--text-dim); padding: 60px; font-size: 1.2em; border: 2px dashed var(--border); border-radius: 12px; }
             /* Scrollbar */
             ::-webkit-scrollbar { width: 10px; }
             ::-webkit-scrollbar-track { background: var(--bg); }
-            ::-webkit-scrollbar-thumb { background: #333; border-radius: 5px; }
+            ::-webkit-scrollbar-thumb { background: var(--border); border-radius: 5px; }
             ::-webkit-scrollbar-thumb:hover { background: var(--primary); }
             @keyframes slideIn { from { transform: translateY(100%); opacity: 0; } to { transform: translateY(0); opacity: 1; } }
         </style>
@@ -482,7 +599,8 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                             el.style.cssText = "position: fixed; bottom: 20px; right: 20px; background: #7C4DFF; color: white; padding: 15px; border-radius: 8px; z-index: 9999; box-shadow: 0 4px 12px rgba(0,0,0,0.3); animation: slideIn 0.3s ease-out; font-weight: 500; display: flex; align-items: center; gap: 10px;";
                             document.body.appendChild(el);
                         }
-                        el.innerHTML = "<span>🔮</span> " + event.payload;
+                        el.innerHTML = "<span>🔮</span> ";
+                        el.append(String(event.payload));
                         
                         // Auto hide after 5s
                         if (window.bridgeTimeout) clearTimeout(window.bridgeTimeout);
@@ -501,30 +619,126 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
 
     match path {
         "history" => {
-            let history_manager = app.state::<HistoryManager>();
-            let history = history_manager.get_recent(100).unwrap_or_default();
-            
-            let mut items_html = String::new();
-            for item in history {
-                let date = chrono::DateTime::from_timestamp(item.last_visit, 0)
-                    .map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
-                    .unwrap_or_else(|| "Unknown".to_string());
+            let page = query_param(query, "page").and_then(|p| p.parse::<i64>().ok()).unwrap_or(0);
+            let first_page = load_history_page(&app.state::<HistoryManager>(), page);
 
-                items_html.push_str(&format!(
-                    r#"<div class="item">
-                        <div class="time">{}</div>
-                        <div class="info">
-                            <div class="title">{}</div>
-                            <div class="url"><a href="{}">{}</a></div>
-                        </div>
-                    </div>"#,
-                    date, item.title, item.url, item.url
-                ));
-            }
-            
-            if items_html.is_empty() {
-                items_html = r#"<div class="empty-state">No history yet</div>"#.to_string();
-            }
+            let history_js = r#"
+                function invoke(cmd, args) {
+                    return window.__TAURI__.core.invoke(cmd, args);
+                }
+                function api(route, params) {
+                    const base = location.protocol === 'lumina-app:' ? 'lumina-app://localhost/api/' : location.origin + '/api/';
+                    return fetch(base + route + '?' + new URLSearchParams(params)).then(r => r.json());
+                }
+                function escapeHtml(s) {
+                    return String(s).replace(/[&<>"']/g, c => ({ '&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;', "'": '&#39;' }[c]));
+                }
+                // Only web and file addresses become links; javascript:, data: and the like stay text
+                function linkHtml(url) {
+                    return /^(https?|file):/i.test(url) ? '<a href="' + escapeHtml(url) + '">' + escapeHtml(url) + '</a>' : escapeHtml(url);
+                }
+                function dayStart(ts) {
+                    const d = new Date(ts * 1000);
+                    d.setHours(0, 0, 0, 0);
+                    return Math.floor(d.getTime() / 1000);
+                }
+                function dayLabel(start) {
+                    const today = dayStart(Date.now() / 1000);
+                    if (start === today) return 'Today';
+                    if (start === dayStart(today - 43200)) return 'Yesterday';
+                    return new Date(start * 1000).toLocaleDateString(undefined, { weekday: 'long', year: 'numeric', month: 'long', day: 'numeric' });
+                }
+                function dayGroup(start) {
+                    let group = document.querySelector('.day[data-start="' + start + '"]');
+                    if (!group) {
+                        group = document.createElement('div');
+                        group.className = 'day';
+                        group.dataset.start = start;
+                        group.innerHTML = '<div class="day-header"><h2>' + dayLabel(start) + '</h2>'
+                            + '<button data-onclick="deleteDay" data-args="[' + start + ']">Delete day</button></div>';
+                        document.getElementById('list').appendChild(group);
+                    }
+                    return group;
+                }
+                function renderItem(item, container) {
+                    const el = document.createElement('div');
+                    el.className = 'item';
+                    el.dataset.url = item.url;
+                    const time = new Date(item.last_visit * 1000).toLocaleTimeString(undefined, { hour: '2-digit', minute: '2-digit' });
+                    el.innerHTML = '<div class="time">' + time + '</div>'
+                        + '<div class="info" style="flex: 1; min-width: 0;"><div class="title">' + escapeHtml(item.title || item.url) + '</div>'
+                        + '<div class="url">' + linkHtml(item.url) + '</div></div>'
+                        + '<button class="delete">Delete</button>';
+                    el.querySelector('.delete').onclick = () => deleteEntry(item.url, el);
+                    container.appendChild(el);
+                }
+                function renderItems(items, grouped) {
+                    if (grouped) {
+                        items.forEach(item => renderItem(item, dayGroup(dayStart(item.last_visit))));
+                    } else {
+                        items.forEach(item => renderItem(item, document.getElementById('list')));
+                    }
+                    updateEmptyState();
+                }
+                function updateEmptyState() {
+                    const empty = document.querySelectorAll('#list .item').length === 0;
+                    document.getElementById('empty').style.display = empty ? '' : 'none';
+                }
+                function deleteEntry(url, el) {
+                    invoke('delete_history_entry', { url }).then(() => {
+                        const group = el.closest('.day');
+                        el.remove();
+                        if (group && !group.querySelector('.item')) group.remove();
+                        updateEmptyState();
+                    }).catch(e => alert(e.message || e));
+                }
+                function deleteDay(start) {
+                    if (!confirm('Delete all history for ' + dayLabel(start) + '?')) return;
+                    const end = dayStart(start + 36 * 3600);
+                    invoke('delete_history_range', { start, end }).then(() => {
+                        const group = document.querySelector('.day[data-start="' + start + '"]');
+                        if (group) group.remove();
+                        updateEmptyState();
+                    }).catch(e => alert(e.message || e));
+                }
+
+                let nextPage = INITIAL.page + 1;
+                let hasMore = INITIAL.hasMore;
+                let loading = false;
+                let searching = false;
+                function loadMore() {
+                    if (loading || !hasMore || searching) return;
+                    loading = true;
+                    api('history', { page: nextPage }).then(result => {
+                        renderItems(result.items, true);
+                        hasMore = result.hasMore;
+                        nextPage = result.page + 1;
+                    }).finally(() => { loading = false; });
+                }
+                window.addEventListener('scroll', () => {
+                    if (window.innerHeight + window.scrollY >= document.body.offsetHeight - 400) loadMore();
+                });
+
+                let searchTimer = null;
+                document.getElementById('search').addEventListener('input', (e) => {
+                    clearTimeout(searchTimer);
+                    const query = e.target.value.trim();
+                    searchTimer = setTimeout(() => {
+                        document.getElementById('list').innerHTML = '';
+                        if (!query) {
+                            searching = false;
+                            hasMore = true;
+                            nextPage = 0;
+                            loadMore();
+                            return;
+                        }
+                        searching = true;
+                        api('history', { q: query }).then(result => renderItems(result.items, false));
+                    }, 250);
+                });
+
+                renderItems(INITIAL.items, true);
+            "#;
 
             Some(format!(
                 r#"<!DOCTYPE html>
@@ -533,71 +747,232 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                     <title>History - Lumina</title>
                     <meta charset="UTF-8">
                     {}
+                    <style>
+                        #search {{ width: 100%; box-sizing: border-box; padding: 10px 14px; margin-bottom: 20px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 8px; font-size: 1em; }}
+                        .day-header {{ display: flex; align-items: center; justify-content: space-between; margin: 30px 0 10px; }}
+                        .day-header h2 {{ font-size: 1.05em; font-weight: 500; color: var(--text-dim); margin: 0; }}
+                        .item .delete {{ opacity: 0; }}
+                        .item:hover .delete {{ opacity: 1; }}
+                    </style>
                 </head>
                 <body>
                     <h1>History</h1>
-                    <div id="list">{}</div>
+                    <p><a href="lumina-app://stats">Browsing statistics</a> · <a href="lumina-app://settings/history">Sites never recorded</a></p>
+                    <input type="text" id="search" placeholder="Search history">
+                    <div id="list"></div>
+                    <div id="empty" class="empty-state" style="display: none">No history yet</div>
+                    <script>const INITIAL = {};</script>
+                    <script>{}</script>
                 </body>
                 </html>"#,
-                lumina_style, items_html
+                lumina_style, to_script_json(&first_page), history_js
             ))
         },
-        "downloads" => {
-            let download_manager = app.state::<DownloadManager>();
-            let downloads = download_manager.inner().get_downloads();
-            
-            let mut items_html = String::new();
-            for item in downloads.iter().rev() {
-                 let finished = item.status == "completed";
-                 let status_color = if finished { "#00E676" } else { "#FFAB40" }; // Material Green/Orange
-                 let status_text = if finished { "Completed" } else { "Downloading..." };
-                 
-                 let date = if item.added_at > 0 {
-                     chrono::DateTime::from_timestamp(item.added_at, 0)
-                         .map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
-                         .unwrap_or_default()
-                 } else {
-                     "".to_string()
-                 };
-
-                 items_html.push_str(&format!(
-                    r#"<div class="item" style="border-left-color: {};">
-                        <div class="icon" style="font-size: 24px; width: 40px; text-align: center;">⬇️</div>
-                        <div class="info">
-                            <div class="filename">{}</div>
-                            <div class="url"><a href="{}">{}</a></div>
-                            <div class="meta" style="color: var(--text-dim);">{} • {} • {}</div>
-                        </div>
-                        <div class="actions">
-                            <button onclick="window.__TAURI__.core.invoke('open_file', {{ path: '{}' }})">Open</button>
-                            <button onclick="window.__TAURI__.core.invoke('show_in_folder', {{ path: '{}' }})">Folder</button>
-                        </div>
-                    </div>"#,
-                    status_color,
-                    item.file_name, item.url, item.url, 
-                    status_text, item.path, date,
-                    item.path.replace("\\", "\\\\"), item.path.replace("\\", "\\\\")
-                ));
-            }
+        "stats" => {
+            let period = query_param(query, "period").and_then(|p| history_stats::StatsPeriod::parse(&p)).unwrap_or_default();
+            let history_manager = app.state::<HistoryManager>();
+            let stats = history_manager.get_browsing_stats(period.since(), 10).ok()?;
+            let data_store = app.state::<AppDataStore>();
+            let top_sites = history_manager.get_top_sites(period.since(), 10, |url| !data_store.is_history_excluded(url)).unwrap_or_default();
+
+            let periods_html: String = [("day", "24 hours", history_stats::StatsPeriod::Day), ("week", "7 days", history_stats::StatsPeriod::Week), ("month", "30 days", history_stats::StatsPeriod::Month), ("all", "All time", history_stats::StatsPeriod::All)]
+                .iter()
+                .map(|(key, name, p)| format!(
+                    r#"<a href="lumina-app://stats?period={}" class="{}">{}</a>"#,
+                    key, if *p == period { "period active" } else { "period" }, name
+                ))
+                .collect();
 
-            if items_html.is_empty() {
-                items_html = r#"<div class="empty-state">No downloads yet</div>"#.to_string();
-            }
+            // Bars are scaled to the busiest entry of each chart
+            let bar = |label: &str, visits: i64, max: i64| format!(
+                r#"<div class="bar-row"><span class="bar-label">{}</span><div class="bar"><div style="width: {}%"></div></div><span class="bar-value">{}</span></div>"#,
+                escape_html(label), visits * 100 / max.max(1), visits
+            );
+            let max_day = stats.visits_per_day.iter().map(|d| d.visits).max().unwrap_or(0);
+            let days_html: String = stats.visits_per_day.iter().rev().take(30).map(|d| bar(&d.day, d.visits, max_day)).collect();
+            let max_domain = stats.top_domains.first().map_or(0, |d| d.visits);
+            let domains_html: String = stats.top_domains.iter().map(|d| bar(&d.domain, d.visits, max_domain)).collect();
+            let max_hour = stats.visits_per_hour.iter().copied().max().unwrap_or(0);
+            let hours_html: String = stats.visits_per_hour.iter().enumerate()
+                .map(|(hour, visits)| format!(
+                    r#"<div class="hour" title="{hour:02}:00 - {visits} visits"><div style="height: {}%"></div><span>{hour}</span></div>"#,
+                    visits * 100 / max_hour.max(1)
+                ))
+                .collect();
+            let sites_html: String = top_sites.iter()
+                .map(|site| format!(
+                    r#"<div class="item"><div class="info" style="flex: 1; min-width: 0;"><div class="title">{}</div><div class="url">{}</div></div><div class="meta">{} visits</div></div>"#,
+                    escape_html(if site.title.is_empty() { &site.url } else { &site.title }), link_html(&site.url), site.visits
+                ))
+                .collect();
+            let empty = r#"<div class="empty-state">No visits in this period</div>"#;
 
             Some(format!(
                 r#"<!DOCTYPE html>
                 <html>
                 <head>
-                    <title>Downloads - Lumina</title>
+                    <title>Browsing Statistics - Lumina</title>
                     <meta charset="UTF-8">
                     {}
+                    <style>
+                        .periods {{ display: flex; gap: 8px; margin-bottom: 24px; }}
+                        .period {{ padding: 6px 14px; border-radius: 16px; background: var(--card); color: var(--text); text-decoration: none; }}
+                        .period.active {{ background: var(--primary); color: #fff; }}
+                        .summary {{ display: flex; gap: 16px; margin-bottom: 24px; }}
+                        .summary div {{ flex: 1; padding: 16px; background: var(--card); border-radius: 10px; }}
+                        .summary strong {{ display: block; font-size: 1.8em; }}
+                        .bar-row {{ display: flex; align-items: center; gap: 10px; margin: 6px 0; }}
+                        .bar-label {{ width: 180px; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }}
+                        .bar {{ flex: 1; height: 10px; background: var(--card); border-radius: 5px; overflow: hidden; }}
+                        .bar div {{ height: 100%; background: var(--primary); }}
+                        .bar-value {{ width: 50px; text-align: right; color: var(--text-dim); }}
+                        .hours {{ display: flex; align-items: flex-end; gap: 4px; height: 140px; }}
+                        .hour {{ flex: 1; height: 100%; display: flex; flex-direction: column; justify-content: flex-end; align-items: center; }}
+                        .hour div {{ width: 100%; background: var(--primary); border-radius: 3px 3px 0 0; min-height: 1px; }}
+                        .hour span {{ font-size: 0.7em; color: var(--text-dim); margin-top: 4px; }}
+                    </style>
                 </head>
                 <body>
-                    <h1>Downloads</h1>
-                    <div id="list">{}</div>
+                    <h1>Browsing Statistics</h1>
+                    <div class="periods">{}</div>
+                    <div class="summary">
+                        <div><strong>{}</strong>visits</div>
+                        <div><strong>{}</strong>sites</div>
+                    </div>
+                    <h2>Most visited pages</h2>
+                    <div>{}</div>
+                    <h2>Top sites</h2>
+                    <div>{}</div>
+                    <h2>Time of day</h2>
+                    <div class="hours">{}</div>
+                    <h2>Visits per day</h2>
+                    <div>{}</div>
                 </body>
                 </html>"#,
-                lumina_style, items_html
+                lumina_style,
+                periods_html,
+                stats.total_visits,
+                stats.distinct_domains,
+                if sites_html.is_empty() { empty.to_string() } else { sites_html },
+                if domains_html.is_empty() { empty.to_string() } else { domains_html },
+                hours_html,
+                if days_html.is_empty() { empty.to_string() } else { days_html }
+            ))
+        },
+        "downloads" => {
+            let initial = internal_api(app, "downloads", "").unwrap_or_default();
+
+            let downloads_js = r##"
+                function escapeHtml(s) {
+                    return String(s).replace(/[&<>"']/g, c => ({ '&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;', "'": '&#39;' }[c]));
+                }
+                function linkHtml(url) {
+                    return /^(https?|file):/i.test(url) ? '<a href="' + escapeHtml(url) + '">' + escapeHtml(url) + '</a>' : escapeHtml(url);
+                }
+                function api(route, params) {
+                    const base = location.protocol === 'lumina-app:' ? 'lumina-app://localhost/api/' : location.origin + '/api/';
+                    return fetch(base + route + '?' + new URLSearchParams(params)).then(r => r.json());
+                }
+                function formatDate(ts) {
+                    if (!ts) return '';
+                    return new Date(ts * 1000).toLocaleString(undefined, { year: 'numeric', month: '2-digit', day: '2-digit', hour: '2-digit', minute: '2-digit' });
+                }
+                function statusText(item) {
+                    switch (item.status) {
+                        case 'completed': return 'Completed';
+                        case 'queued': return 'Queued';
+                        case 'scheduled': return item.start_at ? 'Scheduled for ' + formatDate(item.start_at) : 'Scheduled';
+                        case 'quarantined': return 'Scanning...';
+                        case 'held': return 'Held: the virus scan could not check it';
+                        case 'failed': return 'Failed';
+                        case 'blocked': return 'Blocked';
+                        case 'paused': return 'Paused';
+                        default: return 'Downloading...';
+                    }
+                }
+                function invokePath(cmd, path) {
+                    window.__TAURI__.core.invoke(cmd, { path });
+                }
+                function render(items) {
+                    const list = document.getElementById('list');
+                    list.innerHTML = '';
+                    items.forEach(item => {
+                        const el = document.createElement('div');
+                        el.className = 'item';
+                        el.style.borderLeftColor = item.status === 'completed' ? '#00E676' : '#FFAB40';
+                        el.innerHTML = '<div class="icon" style="font-size: 24px; width: 40px; text-align: center;">⬇️</div>'
+                            + '<div class="info" style="flex: 1; min-width: 0;">'
+                            + '<div class="filename">' + escapeHtml(item.file_name) + '</div>'
+                            + '<div class="url">' + linkHtml(item.url) + '</div>'
+                            + '<div class="meta">' + escapeHtml(statusText(item)) + ' • ' + escapeHtml(item.path) + ' • ' + escapeHtml(formatDate(item.added_at)) + '</div>'
+                            + (item.extracted_path ? '<div class="meta">Extracted to <a href="#" class="extracted">' + escapeHtml(item.extracted_path) + '</a></div>' : '')
+                            + '</div>'
+                            + '<div class="actions"><button class="open">Open</button> <button class="folder">Folder</button>'
+                            + (item.status === 'held' ? ' <button class="rescan">Scan again</button>' : '')
+                            + (item.status === 'downloading' || item.status === 'quarantined' ? '' : ' <button class="remove">Remove</button>')
+                            + '</div>';
+                        el.querySelector('.open').onclick = () => invokePath('open_file', item.path);
+                        const rescan = el.querySelector('.rescan');
+                        if (rescan) rescan.onclick = () => window.__TAURI__.core.invoke('rescan_download', { url: item.url }).then(refresh).catch(e => alert(e.message || e));
+                        const remove = el.querySelector('.remove');
+                        if (remove) remove.onclick = () => window.__TAURI__.core.invoke('remove_download', { url: item.url }).then(refresh).catch(e => alert(e.message || e));
+                        el.querySelector('.folder').onclick = () => invokePath('show_in_folder', item.path);
+                        const extracted = el.querySelector('.extracted');
+                        if (extracted) extracted.onclick = (e) => { e.preventDefault(); invokePath('show_in_folder', item.extracted_path); };
+                        list.appendChild(el);
+                    });
+                    const filtered = document.getElementById('search').value.trim() !== '';
+                    const empty = document.getElementById('empty');
+                    empty.textContent = filtered ? 'No matching downloads' : 'No downloads yet';
+                    empty.style.display = items.length ? 'none' : '';
+                }
+
+                let refreshTimer = null;
+                function refresh() {
+                    clearTimeout(refreshTimer);
+                    refreshTimer = setTimeout(() => {
+                        api('downloads', { q: document.getElementById('search').value, sort: document.getElementById('sort').value })
+                            .then(render)
+                            .catch(e => console.error('Downloads: API request failed', e));
+                    }, 150);
+                }
+                document.getElementById('search').addEventListener('input', refresh);
+                document.getElementById('sort').addEventListener('change', refresh);
+
+                render(INITIAL);
+            "##;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Downloads - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .toolbar {{ display: flex; gap: 10px; margin-bottom: 20px; }}
+                        .toolbar input, .toolbar select {{ padding: 10px 14px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 8px; font-size: 1em; }}
+                        .toolbar input {{ flex: 1; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>Downloads</h1>
+                    <div class="toolbar">
+                        <input type="text" id="search" placeholder="Search downloads">
+                        <select id="sort">
+                            <option value="newest">Newest first</option>
+                            <option value="oldest">Oldest first</option>
+                            <option value="name">Name</option>
+                            <option value="size">Size</option>
+                        </select>
+                    </div>
+                    <div id="list"></div>
+                    <div id="empty" class="empty-state" style="display: none">No downloads yet</div>
+                    <script>const INITIAL = {};</script>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, to_script_json(&initial), downloads_js
             ))
         },
         "favorites" | "bookmarks" => {
@@ -607,18 +982,31 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
             
             let mut items_html = String::new();
             for item in favorites {
+                let health_html = match &item.health {
+                    Some(h) if h.status == 0 => r#"<span class="badge broken">Unreachable</span>"#.to_string(),
+                    Some(h) if h.status >= 400 => format!(r#"<span class="badge broken">Broken ({})</span>"#, h.status),
+                    Some(FavoriteHealth { redirect: Some(target), .. }) => format!(
+                        r#"<span class="badge redirected">Moved</span>
+                        <div class="meta">Now at {} <button data-url="{}" data-new-url="{}" data-onclick="updateUrl">Update to new URL</button></div>"#,
+                        escape_html(target), escape_html(&item.url), escape_html(target)
+                    ),
+                    _ => String::new(),
+                };
                 items_html.push_str(&format!(
                     r#"<div class="item">
                         <div class="icon" style="color: #FFD700; font-size: 24px;">★</div>
                         <div class="info">
-                            <div class="filename">{}</div>
-                            <div class="url"><a href="{}">{}</a></div>
+                            <div class="filename">{} {}</div>
+                            <div class="url">{}</div>
                         </div>
                         <div class="actions">
-                            <button style="border-color: #ef5350; color: #ef5350;" onmouseover="this.style.background='#ef5350'; this.style.color='white'" onmouseout="this.style.background='transparent'; this.style.color='#ef5350'" onclick="window.__TAURI__.core.invoke('remove_favorite', {{ url: '{}' }}).then(() => window.location.reload())">Remove</button>
+                            <input type="text" placeholder="Keyword" title="Type this keyword in the address bar to open the favorite; %s in the URL is replaced with the text after it" value="{}" data-url="{}" style="width: 90px; padding: 6px 8px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px;" data-onchange="setKeyword">
+                            <button class="remove" data-url="{}" data-onclick="removeFavorite">Remove</button>
                         </div>
                     </div>"#,
-                    item.title, item.url, item.url, item.url
+                    escape_html(&item.title), health_html, link_html(&item.url),
+                    escape_html(item.keyword.as_deref().unwrap_or("")), escape_html(&item.url),
+                    escape_html(&item.url)
                 ));
             }
             
@@ -633,12 +1021,41 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                     <title>Favorites - Lumina</title>
                     <meta charset="UTF-8">
                     {}
+                    <style>
+                        .badge {{ font-size: 0.75em; padding: 2px 8px; border-radius: 10px; margin-left: 6px; vertical-align: middle; }}
+                        .badge.broken {{ background: #ef5350; color: white; }}
+                        .badge.redirected {{ background: #FFAB40; color: black; }}
+                        button.remove {{ border-color: #ef5350; color: #ef5350; }}
+                        button.remove:hover {{ background: #ef5350; color: white; }}
+                    </style>
                 </head>
                 <body>
                     <h1>Favorites</h1>
+                    <button id="check-links" data-onclick="checkLinks">Check links</button>
                     <div id="list">
                         {}
                     </div>
+                    <script>
+                        function checkLinks() {{
+                            const button = document.getElementById('check-links');
+                            button.disabled = true;
+                            button.textContent = 'Checking...';
+                            window.__TAURI__.core.invoke('check_favorites_health')
+                                .then(() => window.location.reload())
+                                .catch(e => {{ alert(e.message || e); button.disabled = false; button.textContent = 'Check links'; }});
+                        }}
+                        function updateUrl(button) {{
+                            window.__TAURI__.core.invoke('update_favorite_url', {{ url: button.dataset.url, newUrl: button.dataset.newUrl }})
+                                .then(() => window.location.reload())
+                                .catch(e => alert(e.message || e));
+                        }}
+                        function setKeyword(input) {{
+                            window.__TAURI__.core.invoke('set_favorite_keyword', {{ url: input.dataset.url, keyword: input.value || null }}).catch(e => alert(e.message || e));
+                        }}
+                        function removeFavorite(button) {{
+                            window.__TAURI__.core.invoke('remove_favorite', {{ url: button.dataset.url }}).then(() => window.location.reload());
+                        }}
+                    </script>
                 </body>
                 </html>"#,
                 lumina_style, items_html
@@ -646,27 +1063,64 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
         },
         "store" => {
             // Lumina Web-Store (No-JS)
-            let store_css = r#"
-                body { font-family: 'Segoe UI', system-ui, sans-serif; background: #0f172a; color: #e2e8f0; margin: 0; padding: 0; }
-                .container { max-width: 1000px; margin: 0 auto; padding: 40px 20px; }
-                header { display: flex; align-items: center; justify-content: space-between; margin-bottom: 40px; border-bottom: 1px solid #334155; padding-bottom: 20px; }
-                h1 { margin: 0; font-size: 2.5rem; background: linear-gradient(to right, #3b82f6, #10b981); -webkit-background-clip: text; -webkit-text-fill-color: transparent; }
-                .tagline { color: #94a3b8; font-size: 1.1rem; }
-                .grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(300px, 1fr)); gap: 24px; }
-                .card { background: #1e293b; border: 1px solid #334155; border-radius: 12px; padding: 24px; transition: transform 0.2s, border-color 0.2s; position: relative; overflow: hidden; }
-                .card:hover { transform: translateY(-4px); border-color: #3b82f6; }
-                .card-header { display: flex; align-items: center; gap: 12px; margin-bottom: 16px; }
-                .icon { width: 48px; height: 48px; background: #334155; border-radius: 10px; display: flex; align-items: center; justify-content: center; font-size: 24px; }
-                .card h3 { margin: 0; font-size: 1.25rem; color: #f8fafc; }
-                .author { font-size: 0.875rem; color: #64748b; margin-top: 4px; }
-                .desc { color: #cbd5e1; line-height: 1.5; margin-bottom: 20px; font-size: 0.95rem; }
-                .meta { display: flex; gap: 12px; font-size: 0.8rem; color: #64748b; margin-bottom: 20px; }
-                .tag { background: #334155; padding: 2px 8px; border-radius: 4px; color: #94a3b8; }
-                .btn { display: block; text-align: center; background: #3b82f6; color: white; text-decoration: none; padding: 10px; border-radius: 8px; font-weight: 600; transition: background 0.2s; }
-                .btn:hover { background: #2563eb; }
-                .btn.installed { background: #10b981; pointer-events: none; opacity: 0.8; }
-                .badge-verified { color: #10b981; display: inline-flex; align-items: center; gap: 4px; font-size: 0.8rem; margin-left: auto; }
-            "#;
+            let store_css = STORE_CSS;
+
+            let mut cards_html = String::new();
+            for item in get_store_items(app.clone()) {
+                let verified = if item.verification_failed {
+                    r#"<div class="badge-failed">⚠ Verification failed</div>"#
+                } else if item.verified {
+                    r#"<div class="badge-verified">✓ Verified</div>"#
+                } else {
+                    ""
+                };
+                let tags: String = item.tags.iter()
+                    .chain(std::iter::once(&format!("v{}", item.version)))
+                    .map(|tag| format!(r#"<span class="tag">{}</span>"#, escape_html(tag)))
+                    .collect();
+                let button = if item.coming_soon {
+                    r#"<a class="btn disabled">Coming Soon</a>"#.to_string()
+                } else if item.update_available() && item.install_blocker().is_none() {
+                    format!(r#"<a href="{}" class="btn">Update to v{}</a>"#, escape_html(&store_install_url(&item.id)), escape_html(&item.version))
+                } else if item.installed {
+                    r#"<a class="btn installed">Installed</a>"#.to_string()
+                } else if let Some(reason) = item.install_blocker() {
+                    format!(r#"<a class="btn disabled" title="{}">Unavailable</a>"#, escape_html(reason))
+                } else if item.verification_failed {
+                    format!(r#"<a href="{}" class="btn failed" title="The package signature did not match its publisher key">Signature invalid - retry</a>"#, escape_html(&store_install_url(&item.id)))
+                } else {
+                    format!(r#"<a href="{}" class="btn">Install</a>"#, escape_html(&store_install_url(&item.id)))
+                };
+
+                cards_html.push_str(&format!(
+                    r#"<div class="card{}">
+                        <div class="card-header">
+                            <div class="icon">{}</div>
+                            <div>
+                                <h3><a href="lumina-app://store/{}">{}</a></h3>
+                                <div class="author">by {}</div>
+                            </div>
+                            {}
+                        </div>
+                        <div class="desc">{}</div>
+                        <div class="meta">{}</div>
+                        {}
+                    </div>"#,
+                    if item.coming_soon { " coming-soon" } else { "" },
+                    escape_html(&item.icon),
+                    urlencoding::encode(&item.id),
+                    escape_html(&item.title),
+                    escape_html(&item.author),
+                    verified,
+                    escape_html(&item.description),
+                    tags,
+                    button
+                ));
+            }
+
+            if cards_html.is_empty() {
+                cards_html = r#"<div class="empty">No extensions available</div>"#.to_string();
+            }
 
             Some(format!(
                 r##"<!DOCTYPE html>
@@ -689,90 +1143,11 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                             </div>
                         </header>
 
-                        <div class="grid">
-                            <!-- Item 1: Init Script -->
-                            <div class="card">
-                                <div class="card-header">
-                                    <div class="icon">🚀</div>
-                                    <div>
-                                        <h3>Dev Starter Pack</h3>
-                                        <div class="author">by @safkanyapi</div>
-                                    </div>
-                                    <div class="badge-verified">✓ Verified</div>
-                                </div>
-                                <div class="desc">
-                                    Essential initialization scripts for Lua development. Includes debug helpers and environment checks.
-                                </div>
-                                <div class="meta">
-                                    <span class="tag">System</span>
-                                    <span class="tag">Lua</span>
-                                    <span class="tag">v1.0.0</span>
-                                </div>
-                                <a href="lumina-app://install?id=init-script" class="btn">Install</a>
-                            </div>
-
-                            <!-- Item 2: Adblock Plus -->
-                            <div class="card">
-                                <div class="card-header">
-                                    <div class="icon">🛡️</div>
-                                    <div>
-                                        <h3>AdShield Pro</h3>
-                                        <div class="author">by @community</div>
-                                    </div>
-                                </div>
-                                <div class="desc">
-                                    Enhanced filter lists for Turkish media sites. Blocks aggressive trackers and mining scripts.
-                                </div>
-                                <div class="meta">
-                                    <span class="tag">Privacy</span>
-                                    <span class="tag">Filters</span>
-                                    <span class="tag">v2.1.0</span>
-                                </div>
-                                <a href="lumina-app://install?id=adshield" class="btn">Install</a>
-                            </div>
-
-                            <!-- Item 3: Offline AI (Placeholder) -->
-                            <div class="card" style="opacity: 0.7; border-style: dashed;">
-                                <div class="card-header">
-                                    <div class="icon">🧠</div>
-                                    <div>
-                                        <h3>Local Brain (Phi-2)</h3>
-                                        <div class="author">by @lumina_ai</div>
-                                    </div>
-                                </div>
-                                <div class="desc">
-                                    Run LLMs locally on your device. Zero data leaves your machine. (Coming Soon)
-                                </div>
-                                <div class="meta">
-                                    <span class="tag">AI</span>
-                                    <span class="tag">Experimental</span>
-                                </div>
-                                <a href="#" class="btn" style="background: #475569; cursor: not-allowed;">Coming Soon</a>
-                            </div>
-                            
-                            <!-- Item 4: Dark Reader -->
-                            <div class="card">
-                                <div class="card-header">
-                                    <div class="icon">🌙</div>
-                                    <div>
-                                        <h3>Night Owl</h3>
-                                        <div class="author">by @nightwalker</div>
-                                    </div>
-                                </div>
-                                <div class="desc">
-                                    Forces dark mode on all internal pages and supported websites via CSS injection.
-                                </div>
-                                <div class="meta">
-                                    <span class="tag">Theme</span>
-                                    <span class="tag">CSS</span>
-                                </div>
-                                <a href="lumina-app://install?id=night-owl" class="btn">Install</a>
-                            </div>
-                        </div>
+                        <div class="grid">{}</div>
                     </div>
                 </body>
                 </html>"##,
-                store_css
+                store_css, cards_html
             ))
         },
         "settings" => {
@@ -787,18 +1162,18 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                     <title>Settings</title>
                     <meta charset="UTF-8">
                     <style>
-                        body {{ font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, Helvetica, Arial, sans-serif; padding: 40px; background: #f9fafb; color: #111827; max-width: 600px; margin: 0 auto; }}
-                        h1 {{ border-bottom: 1px solid #e5e7eb; padding-bottom: 20px; margin-bottom: 30px; }}
-                        .group {{ background: white; padding: 25px; margin-bottom: 20px; border-radius: 12px; box-shadow: 0 1px 3px rgba(0,0,0,0.1); }}
+                        body {{ font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, Helvetica, Arial, sans-serif; padding: 40px; background: var(--bg); color: var(--text); max-width: 600px; margin: 0 auto; }}
+                        h1 {{ border-bottom: 1px solid var(--border); padding-bottom: 20px; margin-bottom: 30px; }}
+                        .group {{ background: var(--card); padding: 25px; margin-bottom: 20px; border-radius: 12px; box-shadow: 0 1px 3px rgba(0,0,0,0.1); }}
                         .form-group {{ margin-bottom: 20px; }}
                         .form-group:last-child {{ margin-bottom: 0; }}
-                        label {{ display: block; margin-bottom: 8px; font-weight: 500; font-size: 0.95em; color: #374151; }}
-                        input[type="text"], select {{ width: 100%; padding: 10px; border: 1px solid #d1d5db; border-radius: 6px; font-size: 1em; box-sizing: border-box; transition: border-color 0.2s; }}
-                        input[type="text"]:focus, select:focus {{ outline: none; border-color: #2563eb; ring: 2px solid #bfdbfe; }}
+                        label {{ display: block; margin-bottom: 8px; font-weight: 500; font-size: 0.95em; color: var(--text); }}
+                        input[type="text"], select {{ width: 100%; padding: 10px; background: var(--surface); color: var(--text); border: 1px solid var(--border); border-radius: 6px; font-size: 1em; box-sizing: border-box; transition: border-color 0.2s; }}
+                        input[type="text"]:focus, select:focus {{ outline: none; border-color: var(--primary); }}
                         .checkbox-group {{ display: flex; align-items: center; }}
                         input[type="checkbox"] {{ width: 18px; height: 18px; margin-right: 10px; }}
-                        button {{ background: #2563eb; color: white; border: none; padding: 12px 24px; border-radius: 8px; font-size: 1em; font-weight: 500; cursor: pointer; transition: background 0.2s; width: 100%; margin-top: 10px; }}
-                        button:hover {{ background: #1d4ed8; }}
+                        button {{ background: var(--primary); color: white; border: none; padding: 12px 24px; border-radius: 8px; font-size: 1em; font-weight: 500; cursor: pointer; transition: background 0.2s; width: 100%; margin-top: 10px; }}
+                        button:hover {{ filter: brightness(0.9); }}
                     </style>
                 </head>
                 <body>
@@ -808,6 +1183,18 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                             <label>Homepage URL</label>
                             <input type="text" id="homepage" value="{}">
                         </div>
+                        <div class="form-group checkbox-group">
+                            <input type="checkbox" id="show_home_button" {}>
+                            <label for="show_home_button" style="margin-bottom: 0">Show the home button in the toolbar</label>
+                        </div>
+                        <div class="form-group">
+                            <label>New Tab Page</label>
+                            <input type="text" id="new_tab_page" value="{}">
+                        </div>
+                        <div class="form-group">
+                            <label>Default Zoom</label>
+                            <select id="default_zoom">{}</select>
+                        </div>
                         <div class="form-group">
                             <label>Search Engine</label>
                             <select id="search_engine">
@@ -816,6 +1203,15 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                                 <option value="duckduckgo" {}>DuckDuckGo</option>
                             </select>
                         </div>
+                        <div class="form-group">
+                            <label>Languages (most preferred first)</label>
+                            <input type="text" id="accept_languages" placeholder="e.g. tr-TR, en-US">
+                            <p id="accept_languages_hint" style="margin: 6px 0 0; opacity: 0.7; font-size: 0.85em;"></p>
+                        </div>
+                        <div class="form-group">
+                            <label>Proxy for downloads, icons and updates</label>
+                            <input type="text" id="proxy_url" value="{}" placeholder="http://host:port (empty uses the system's)">
+                        </div>
                     </div>
                     
                     <div class="group">
@@ -825,8 +1221,14 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                                 <option value="dark" {}>Dark</option>
                                 <option value="light" {}>Light</option>
                                 <option value="system" {}>System</option>
+                                <option value="scheduled" {}>Scheduled</option>
                             </select>
                         </div>
+                        <div class="form-group" id="theme_schedule_group">
+                            <label>Light from / Dark from</label>
+                            <input type="time" id="theme_light_at" value="{}">
+                            <input type="time" id="theme_dark_at" value="{}">
+                        </div>
                         <div class="form-group">
                             <label>Accent Color</label>
                             <input type="text" id="accent_color" value="{}">
@@ -842,11 +1244,162 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                             <input type="checkbox" id="rounded_corners" {}>
                             <label for="rounded_corners" style="margin-bottom: 0">Rounded Corners</label>
                         </div>
+                        <div class="form-group checkbox-group">
+                            <input type="checkbox" id="download_notifications" {}>
+                            <label for="download_notifications" style="margin-bottom: 0">Notify when downloads finish</label>
+                        </div>
+                        <div class="form-group checkbox-group">
+                            <input type="checkbox" id="scan_downloads" {}>
+                            <label for="scan_downloads" style="margin-bottom: 0">Scan downloads with Windows Defender</label>
+                        </div>
+                        <div class="form-group checkbox-group">
+                            <input type="checkbox" id="auto_extract_archives" {}>
+                            <label for="auto_extract_archives" style="margin-bottom: 0">Extract .zip archives after download</label>
+                        </div>
+                        <div class="form-group">
+                            <label>Simultaneous Downloads</label>
+                            <input type="number" id="max_concurrent_downloads" min="1" max="10" value="{}">
+                        </div>
+                    </div>
+
+                    <div class="group">
+                        <div class="form-group">
+                            <label>On Startup</label>
+                            <select id="startup_behavior" data-onchange="toggleStartupUrls">
+                                <option value="homepage" {}>Open the homepage</option>
+                                <option value="new_tab" {}>Open a blank new tab</option>
+                                <option value="restore_session" {}>Continue where you left off</option>
+                                <option value="urls" {}>Open a specific set of pages</option>
+                            </select>
+                        </div>
+                        <div class="form-group" id="startup_urls_group">
+                            <label>Startup Pages (one per line)</label>
+                            <textarea id="startup_urls" rows="4" style="width: 100%; padding: 10px; background: var(--surface); color: var(--text); border: 1px solid var(--border); border-radius: 6px; font-size: 1em; box-sizing: border-box;">{}</textarea>
+                        </div>
+                    </div>
+
+                    <div class="group">
+                        <div class="form-group checkbox-group">
+                            <input type="checkbox" id="dark_mode_global" {}>
+                            <label for="dark_mode_global" style="margin-bottom: 0">Dark mode for websites without a dark theme</label>
+                        </div>
+                        <a href="lumina-app://settings/darkmode">Per-site dark mode</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Ad and overlay protection</label>
+                        <a href="lumina-app://settings/protections">Per-site protection levels</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Referrer</label>
+                        <a href="lumina-app://settings/referrer">How much of the page address sites are told</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Read aloud</label>
+                        <a href="lumina-app://settings/readaloud">Voice and speed</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Spell check</label>
+                        <a href="lumina-app://settings/spellcheck">Languages and sites</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Autofill</label>
+                        <a href="lumina-app://settings/autofill">Addresses and contact details</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Local files</label>
+                        <a href="lumina-app://settings/local-files">Folders that can open in tabs</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Sessions</label>
+                        <a href="lumina-app://settings/sessions">Saved sets of tabs to switch between</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Aliases</label>
+                        <a href="lumina-app://settings/aliases">Short names that open a site from the address bar</a>
+                    </div>
+
+                    <div class="group">
+                        <label>History</label>
+                        <a href="lumina-app://settings/history">Sites that are never recorded in history</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Forget on Close</label>
+                        <a href="lumina-app://settings/forget">Sites whose cookies and storage go when their last tab closes</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Request Headers</label>
+                        <a href="lumina-app://settings/headers">Headers added to or removed from a site's requests</a>
+                    </div>
+
+                    <div class="group">
+                        <label>Storage</label>
+                        <a href="lumina-app://settings/storage">Disk usage and cache</a>
+                    </div>
+
+                    <div class="group">
+                        <div class="form-group checkbox-group">
+                            <input type="checkbox" id="block_popups" {}>
+                            <label for="block_popups" style="margin-bottom: 0">Block pop-ups that weren't opened by a click</label>
+                        </div>
+                        <div class="form-group checkbox-group">
+                            <input type="checkbox" id="unwrap_redirects" {}>
+                            <label for="unwrap_redirects" style="margin-bottom: 0">Skip tracking redirects (Facebook, Google, Outlook link wrappers)</label>
+                        </div>
+                        <div class="form-group checkbox-group">
+                            <input type="checkbox" id="adblock_placeholders" {}>
+                            <label for="adblock_placeholders" style="margin-bottom: 0">Fill the space of blocked ads with empty placeholders</label>
+                        </div>
+                    </div>
+
+                    <div class="group">
+                        <a href="lumina-app://apps">Installed apps</a>
+                        <a href="lumina-app://about">Web engine capabilities</a>
+                    </div>
+
+                    <div class="group">
+                        <div class="form-group">
+                            <label>Privacy</label>
+                            <a href="lumina-app://settings/blocklist">Manage host blocklist</a>
+                            <a href="lumina-app://settings/protocols">Manage external protocols</a>
+                            <a href="lumina-app://extensions">Manage extensions</a>
+                        </div>
                     </div>
 
-                    <button onclick="save()">Save Settings</button>
+                    <button data-onclick="save">Save Settings</button>
 
                     <script>
+                        function toggleStartupUrls() {{
+                            const behavior = document.getElementById('startup_behavior').value;
+                            document.getElementById('startup_urls_group').style.display = behavior === 'urls' ? '' : 'none';
+                        }}
+                        toggleStartupUrls();
+
+                        function toggleThemeSchedule() {{
+                            document.getElementById('theme_schedule_group').style.display = document.getElementById('theme').value === 'scheduled' ? '' : 'none';
+                        }}
+                        document.getElementById('theme').addEventListener('change', toggleThemeSchedule);
+                        toggleThemeSchedule();
+
+                        window.__TAURI__.core.invoke('get_accept_languages').then(({{ selected, detected, active }}) => {{
+                            const input = document.getElementById('accept_languages');
+                            input.value = selected.join(', ');
+                            input.placeholder = 'System language (' + detected + ')';
+                            const effective = selected.length ? selected : [detected];
+                            if (effective.join(',') !== active.join(',')) {{
+                                document.getElementById('accept_languages_hint').textContent = 'Tabs switch to these languages after Lumina restarts; searches use them right away.';
+                            }}
+                        }});
+
                         function save() {{
                             const homepage = document.getElementById('homepage').value;
                             const search_engine = document.getElementById('search_engine').value;
@@ -854,6 +1407,10 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                             const accent_color = document.getElementById('accent_color').value;
                             const vertical_tabs = document.getElementById('vertical_tabs').checked;
                             const rounded_corners = document.getElementById('rounded_corners').checked;
+                            const new_tab_page = document.getElementById('new_tab_page').value;
+
+                            const behavior = document.getElementById('startup_behavior').value;
+                            const urls = document.getElementById('startup_urls').value.split('\n');
 
                             window.__TAURI__.core.invoke('save_settings', {{
                                 homepage, 
@@ -861,131 +1418,2408 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                                 theme, 
                                 accentColor: accent_color, 
                                 verticalTabs: vertical_tabs, 
-                                roundedCorners: rounded_corners
-                            }}).then(() => {{
+                                roundedCorners: rounded_corners,
+                                newTabPage: new_tab_page
+                            }}).then(() => window.__TAURI__.core.invoke('save_startup_settings', {{ behavior, urls }}))
+                              .then(() => window.__TAURI__.core.invoke('set_default_zoom', {{ percent: parseInt(document.getElementById('default_zoom').value, 10) }}))
+                              .then(() => window.__TAURI__.core.invoke('set_show_home_button', {{ enabled: document.getElementById('show_home_button').checked }}))
+                              .then(() => window.__TAURI__.core.invoke('set_download_notifications', {{ enabled: document.getElementById('download_notifications').checked }}))
+                              .then(() => window.__TAURI__.core.invoke('set_scan_downloads', {{ enabled: document.getElementById('scan_downloads').checked }}))
+                              .then(() => window.__TAURI__.core.invoke('set_auto_extract_archives', {{ enabled: document.getElementById('auto_extract_archives').checked }}))
+                              .then(() => window.__TAURI__.core.invoke('set_accept_languages', {{ languages: document.getElementById('accept_languages').value.split(',') }}))
+                              .then(() => window.__TAURI__.core.invoke('set_proxy_url', {{ url: document.getElementById('proxy_url').value }}))
+                              .then(() => window.__TAURI__.core.invoke('set_max_concurrent_downloads', {{ max: parseInt(document.getElementById('max_concurrent_downloads').value, 10) || 1 }}))
+                              .then(() => window.__TAURI__.core.invoke('set_dark_mode_global', {{ enabled: document.getElementById('dark_mode_global').checked }}))
+                              .then(() => window.__TAURI__.core.invoke('set_block_popups', {{ enabled: document.getElementById('block_popups').checked }}))
+                              .then(() => window.__TAURI__.core.invoke('set_unwrap_redirects', {{ enabled: document.getElementById('unwrap_redirects').checked }}))
+                              .then(() => window.__TAURI__.core.invoke('set_adblock_placeholders', {{ enabled: document.getElementById('adblock_placeholders').checked }}))
+                              .then(() => window.__TAURI__.core.invoke('set_theme_schedule', {{ lightAt: document.getElementById('theme_light_at').value, darkAt: document.getElementById('theme_dark_at').value }}))
+                              .then(() => {{
                                 alert('Settings saved!');
                             }}).catch(e => {{
-                                alert('Error saving settings: ' + e);
+                                alert('Error saving settings: ' + (e.message || e));
                             }});
                         }}
                     </script>
                 </body>
                 </html>"#,
-                settings.homepage,
+                escape_html(&settings.homepage),
+                if settings.show_home_button { "checked" } else { "" },
+                escape_html(&settings.new_tab_page),
+                default_zoom_options(settings.default_zoom),
                 if settings.search_engine == "google" { "selected" } else { "" },
                 if settings.search_engine == "bing" { "selected" } else { "" },
                 if settings.search_engine == "duckduckgo" { "selected" } else { "" },
+                escape_html(settings.proxy_url.as_deref().unwrap_or("")),
                 if settings.theme == "dark" { "selected" } else { "" },
                 if settings.theme == "light" { "selected" } else { "" },
                 if settings.theme == "system" { "selected" } else { "" },
+                if settings.theme == "scheduled" { "selected" } else { "" },
+                escape_html(&settings.theme_light_at),
+                escape_html(&settings.theme_dark_at),
                 settings.accent_color,
                 if settings.vertical_tabs { "checked" } else { "" },
-                if settings.rounded_corners { "checked" } else { "" }
+                if settings.rounded_corners { "checked" } else { "" },
+                if settings.download_notifications { "checked" } else { "" },
+                if settings.scan_downloads { "checked" } else { "" },
+                if settings.auto_extract_archives { "checked" } else { "" },
+                settings.max_concurrent_downloads,
+                if settings.startup_behavior == StartupBehavior::Homepage { "selected" } else { "" },
+                if settings.startup_behavior == StartupBehavior::NewTab { "selected" } else { "" },
+                if settings.startup_behavior == StartupBehavior::RestoreSession { "selected" } else { "" },
+                if settings.startup_behavior == StartupBehavior::Urls { "selected" } else { "" },
+                escape_html(&settings.startup_urls.join("\n")),
+                if dark_mode_config(app).global { "checked" } else { "" },
+                if settings.block_popups { "checked" } else { "" },
+                if settings.unwrap_redirects { "checked" } else { "" },
+                if settings.adblock_placeholders { "checked" } else { "" }
             ))
         },
-        "network" => {
-            Some(r#"<!DOCTYPE html>
+        "settings/darkmode" => {
+            let config = dark_mode_config(app);
+            let mut sites: Vec<(&String, &bool)> = config.sites.iter().collect();
+            sites.sort();
+
+            let mut items_html = String::new();
+            for (domain, enabled) in sites {
+                items_html.push_str(&format!(
+                    r#"<div class="item" data-domain="{domain}">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{domain}</div>
+                            <div class="meta">{state}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="setSite" data-args="{toggle_args}">{toggle_text}</button>
+                            <button data-onclick="setSite" data-args="{remove_args}">Remove</button>
+                        </div>
+                    </div>"#,
+                    domain = escape_html(domain),
+                    state = if *enabled { "Always dark" } else { "Never dark" },
+                    toggle_args = handler_args(&(domain, !enabled)),
+                    remove_args = handler_args(&(domain, None::<bool>)),
+                    toggle_text = if *enabled { "Never dark" } else { "Always dark" }
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No per-site overrides</div>"#.to_string();
+            }
+
+            let darkmode_js = r#"
+                function setSite(domain, enabled) {
+                    window.__TAURI__.core.invoke('set_dark_mode_site', { domain, enabled })
+                        .then(() => window.location.reload())
+                        .catch(e => alert(e.message || e));
+                }
+                function addSite(enabled) {
+                    const domain = document.getElementById('new-site').value.trim();
+                    if (domain) setSite(domain, enabled);
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
                 <html>
                 <head>
-                    <title>Network Manager</title>
+                    <title>Dark Mode - Lumina</title>
                     <meta charset="UTF-8">
+                    {}
                     <style>
-                        body { font-family: system-ui, -apple-system, sans-serif; padding: 40px; background: #f9fafb; color: #111827; max-width: 800px; margin: 0 auto; }
-                        h1 { border-bottom: 1px solid #e5e7eb; padding-bottom: 20px; margin-bottom: 30px; font-weight: 600; }
-                        .card { background: white; padding: 25px; margin-bottom: 20px; border-radius: 12px; box-shadow: 0 1px 3px rgba(0,0,0,0.1); }
-                        h2 { margin-top: 0; font-size: 1.2em; color: #374151; border-bottom: 1px solid #f3f4f6; padding-bottom: 10px; margin-bottom: 15px; }
-                        .status-item { display: flex; justify-content: space-between; padding: 10px 0; border-bottom: 1px solid #f3f4f6; }
-                        .status-item:last-child { border-bottom: none; }
-                        .label { font-weight: 500; color: #6b7280; }
-                        .value { font-family: monospace; color: #111827; }
-                        .form-row { display: flex; gap: 10px; align-items: flex-end; }
-                        .input-group { flex: 1; }
-                        label { display: block; margin-bottom: 5px; font-size: 0.9em; font-weight: 500; color: #374151; }
-                        input, select { width: 100%; padding: 8px 12px; border: 1px solid #d1d5db; border-radius: 6px; box-sizing: border-box; }
-                        button { padding: 9px 16px; background: #2563eb; color: white; border: none; border-radius: 6px; cursor: pointer; font-weight: 500; transition: background 0.2s; }
-                        button:hover { background: #1d4ed8; }
-                        button.secondary { background: white; border: 1px solid #d1d5db; color: #374151; }
-                        button.secondary:hover { background: #f3f4f6; }
-                        button.danger { background: #dc2626; color: white; border: none; }
-                        button.danger:hover { background: #b91c1c; }
-                        #server-list { margin-top: 10px; }
-                        .empty-list { color: #9ca3af; font-style: italic; padding: 10px 0; }
+                        .toolbar {{ display: flex; gap: 10px; margin-bottom: 20px; }}
+                        .toolbar input[type="text"] {{ flex: 1; padding: 8px 12px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
                     </style>
                 </head>
                 <body>
-                    <h1>Network Manager</h1>
-                    
-                    <div class="card">
-                        <h2>Sidecar Status</h2>
-                        <div id="status-display">
-                            <div class="status-item">
-                                <span class="label">Status</span>
-                                <span class="value" id="connection-status">Checking...</span>
-                            </div>
-                            <div class="status-item">
-                                <span class="label">Active Servers</span>
-                                <span class="value" id="active-count">0</span>
-                            </div>
-                        </div>
+                    <h1>Dark Mode</h1>
+                    <p>Global dark mode is <strong>{}</strong>. Sites listed here ignore the global setting.</p>
+                    <div class="toolbar">
+                        <input type="text" id="new-site" placeholder="example.com">
+                        <button data-onclick="addSite" data-args="[true]">Always dark</button>
+                        <button data-onclick="addSite" data-args="[false]">Never dark</button>
                     </div>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, if config.global { "on" } else { "off" }, items_html, darkmode_js
+            ))
+        },
+        "settings/protections" => {
+            let mut sites: Vec<(String, String)> = site_protections(app).into_iter().collect();
+            sites.sort();
 
-                    <div class="card">
-                        <h2>Active Servers</h2>
-                        <div id="server-list">
-                            <div class="empty-list">No active servers</div>
+            let mut items_html = String::new();
+            for (pattern, level) in &sites {
+                let options: String = PROTECTION_LEVELS
+                    .iter()
+                    .map(|l| format!(r#"<option value="{l}" {}>{l}</option>"#, if l == level { "selected" } else { "" }))
+                    .collect();
+                items_html.push_str(&format!(
+                    r#"<div class="item" data-pattern="{pattern}">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{pattern}</div>
                         </div>
-                    </div>
-
-                    <div class="card">
-                        <h2>Start New Server</h2>
-                        <div class="form-row">
-                            <div class="input-group">
-                                <label>Port</label>
-                                <input type="number" id="port-input" value="8080" min="1" max="65535">
-                            </div>
-                            <div class="input-group">
-                                <label>Type</label>
-                                <select id="type-input">
-                                    <option value="tcp">TCP</option>
-                                </select>
-                            </div>
-                            <button onclick="startServer()">Start Server</button>
+                        <div class="actions">
+                            <select data-onchange="changeSite">{options}</select>
+                            <button data-onclick="removeSite">Remove</button>
                         </div>
-                    </div>
+                    </div>"#,
+                    pattern = escape_html(pattern),
+                    options = options
+                ));
+            }
 
-                    <script>
-                        async function invokeNet(command, payload = {}) {
-                            try {
-                                const res = await window.__TAURI__.core.invoke('run_networking_command', { 
-                                    command: command, 
-                                    payload: JSON.stringify(payload) 
-                                });
-                                return JSON.parse(res);
-                            } catch (e) {
-                                console.error("Network Error:", e);
-                                return { status: "error", message: e };
-                            }
-                        }
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No per-site protection levels</div>"#.to_string();
+            }
 
-                        async function refreshStatus() {
-                            const res = await invokeNet('status');
-                            if (res.status === 'ok') {
-                                document.getElementById('connection-status').textContent = 'Connected';
-                                document.getElementById('connection-status').style.color = '#10b981';
-                                
-                                const servers = res.data.active_servers || [];
-                                document.getElementById('active-count').textContent = servers.length;
-                                
-                                const list = document.getElementById('server-list');
-                                if (servers.length === 0) {
-                                    list.innerHTML = '<div class="empty-list">No active servers</div>';
+            let protections_js = r#"
+                function setSite(domain, level) {
+                    window.__TAURI__.core.invoke('set_site_protection', { domain, level })
+                        .then(() => window.location.reload())
+                        .catch(e => alert(e.message || e));
+                }
+                function changeSite(select) {
+                    setSite(select.closest('.item').dataset.pattern, select.value);
+                }
+                function removeSite(button) {
+                    setSite(button.closest('.item').dataset.pattern, null);
+                }
+                function addSite() {
+                    const domain = document.getElementById('new-site').value.trim();
+                    if (domain) setSite(domain, document.getElementById('new-level').value);
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Site Protection - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .toolbar {{ display: flex; gap: 10px; margin-bottom: 20px; }}
+                        .toolbar input[type="text"] {{ flex: 1; padding: 8px 12px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                        select {{ padding: 6px 10px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>Site Protection</h1>
+                    <p><strong>default</strong> only removes ad frames and unlocks scrolling, <strong>aggressive</strong> also hides ad containers and floating overlays, <strong>off</strong> leaves the page untouched. Sites not listed here are aggressive. An entry matches any host that contains it.</p>
+                    <div class="toolbar">
+                        <input type="text" id="new-site" placeholder="example.com">
+                        <select id="new-level">
+                            <option value="default">default</option>
+                            <option value="aggressive">aggressive</option>
+                            <option value="off">off</option>
+                        </select>
+                        <button data-onclick="addSite">Add</button>
+                    </div>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, items_html, protections_js
+            ))
+        },
+        "settings/referrer" => {
+            let default_policy = app.state::<AppDataStore>().data.lock().unwrap().settings.referrer_policy.clone();
+            let sites = app.state::<HistoryManager>().get_referrer_policies().unwrap_or_default();
+            let policy_options = |current: &str| -> String {
+                referrer::REFERRER_POLICIES
+                    .iter()
+                    .map(|p| format!(r#"<option value="{p}" {}>{p}</option>"#, if *p == current { "selected" } else { "" }))
+                    .collect()
+            };
+
+            let mut items_html = String::new();
+            for (domain, policy) in &sites {
+                items_html.push_str(&format!(
+                    r#"<div class="item" data-domain="{domain}">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{domain}</div>
+                        </div>
+                        <div class="actions">
+                            <select data-onchange="changeSite">{options}</select>
+                            <button data-onclick="removeSite">Remove</button>
+                        </div>
+                    </div>"#,
+                    domain = escape_html(domain),
+                    options = policy_options(policy)
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No per-site referrer policies</div>"#.to_string();
+            }
+
+            let referrer_js = r#"
+                function setDefault(select) {
+                    window.__TAURI__.core.invoke('set_referrer_policy', { policy: select.value })
+                        .catch(e => alert(e.message || e));
+                }
+                function setSite(domain, policy) {
+                    window.__TAURI__.core.invoke('set_site_referrer_policy', { domain, policy })
+                        .then(() => window.location.reload())
+                        .catch(e => alert(e.message || e));
+                }
+                function changeSite(select) {
+                    setSite(select.closest('.item').dataset.domain, select.value);
+                }
+                function removeSite(button) {
+                    setSite(button.closest('.item').dataset.domain, null);
+                }
+                function addSite() {
+                    const domain = document.getElementById('new-site').value.trim();
+                    if (domain) setSite(domain, document.getElementById('new-policy').value);
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Referrer - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .toolbar {{ display: flex; gap: 10px; margin-bottom: 20px; }}
+                        .toolbar input[type="text"] {{ flex: 1; padding: 8px 12px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                        select {{ padding: 6px 10px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>Referrer</h1>
+                    <p>What a page tells the sites it loads from or links to about itself. <strong>full</strong> sends the whole address, <strong>origin</strong> only the site, <strong>none</strong> nothing. The page's site decides, and a site also covers its subdomains. Tabs opened afterwards follow changes; on Windows requests are trimmed right away.</p>
+                    <div class="toolbar">
+                        <label for="default-policy" style="align-self: center;">Everywhere else</label>
+                        <select id="default-policy" data-onchange="setDefault">{}</select>
+                    </div>
+                    <div class="toolbar">
+                        <input type="text" id="new-site" placeholder="example.com">
+                        <select id="new-policy">{}</select>
+                        <button data-onclick="addSite">Add</button>
+                    </div>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, policy_options(&default_policy), policy_options("origin"), items_html, referrer_js
+            ))
+        },
+        "console" => {
+            let history = app.state::<HistoryManager>().get_console_history(100).unwrap_or_default();
+            let scripts = app.state::<LuaState>().get_scripts();
+
+            let mut scripts_html = String::new();
+            for script in &scripts {
+                let loaded = chrono::DateTime::from_timestamp(script.loaded_at, 0)
+                    .map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+                let (status_color, status_text) = match &script.error {
+                    Some(e) => ("#ef5350", escape_html(e)),
+                    None => ("#00E676", "Loaded".to_string()),
+                };
+                scripts_html.push_str(&format!(
+                    r#"<div class="item" style="border-left-color: {};">
+                        <div class="info">
+                            <div class="filename">{}</div>
+                            <div class="meta">package: {} • {}</div>
+                            <pre class="script-status" style="color: {};">{}</pre>
+                        </div>
+                    </div>"#,
+                    status_color, escape_html(&script.path), escape_html(&script.package_id), loaded, status_color, status_text
+                ));
+            }
+
+            if scripts_html.is_empty() {
+                scripts_html = r#"<div class="empty-state">No scripts loaded</div>"#.to_string();
+            }
+
+            let console_css = r#"
+                .console-output { background: #0b0b0b; border: 1px solid var(--border); border-radius: 8px; padding: 12px 16px; height: 360px; overflow-y: auto; font-family: 'Cascadia Code', Consolas, monospace; font-size: 0.9em; }
+                .console-output pre { margin: 0 0 6px 0; white-space: pre-wrap; word-break: break-word; }
+                .line.input { color: var(--primary); }
+                .line.result { color: #00E676; }
+                .line.error { color: #ef5350; }
+                .line.info { color: var(--text-dim); }
+                .console-input { display: flex; gap: 10px; margin-top: 12px; align-items: flex-start; }
+                .console-input textarea { flex: 1; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; padding: 10px; font-family: 'Cascadia Code', Consolas, monospace; resize: vertical; min-height: 40px; }
+                .console-input textarea:focus { outline: none; border-color: var(--primary); }
+                h2 { margin-top: 40px; font-weight: 600; color: var(--text); }
+                .script-status { margin: 6px 0 0 0; white-space: pre-wrap; font-size: 0.85em; }
+            "#;
+
+            let console_js = r#"
+                (function() {
+                    const history = window.__LUA_HISTORY__ || [];
+                    let historyIndex = history.length;
+                    const output = document.getElementById('output');
+                    const input = document.getElementById('code');
+
+                    function appendLine(text, cls) {
+                        const el = document.createElement('pre');
+                        el.className = 'line ' + cls;
+                        el.textContent = text;
+                        output.appendChild(el);
+                        output.scrollTop = output.scrollHeight;
+                    }
+
+                    window.runCode = async function() {
+                        const code = input.value;
+                        if (!code.trim()) return;
+                        appendLine('> ' + code, 'input');
+                        input.value = '';
+
+                        if (history[history.length - 1] !== code) {
+                            history.push(code);
+                            window.__TAURI__.core.invoke('add_lua_console_history', { code }).catch(() => {});
+                        }
+                        historyIndex = history.length;
+
+                        try {
+                            const result = await window.__TAURI__.core.invoke('run_lua_code', { code });
+                            appendLine(result === '' ? 'ok' : result, 'result');
+                        } catch (e) {
+                            appendLine(String(e), 'error');
+                        }
+                    };
+
+                    window.stopCode = function() {
+                        window.__TAURI__.core.invoke('cancel_lua_execution');
+                    };
+
+                    window.clearOutput = function() {
+                        output.innerHTML = '';
+                    };
+
+                    window.reloadScripts = function() {
+                        window.__TAURI__.core.invoke('reload_scripts').then(() => window.location.reload());
+                    };
+
+                    input.addEventListener('keydown', (e) => {
+                        if (e.key === 'Enter' && !e.shiftKey) {
+                            e.preventDefault();
+                            window.runCode();
+                        } else if (e.key === 'ArrowUp' && !input.value.includes('\n') && history.length > 0) {
+                            e.preventDefault();
+                            historyIndex = Math.max(0, historyIndex - 1);
+                            input.value = history[historyIndex];
+                        } else if (e.key === 'ArrowDown' && !input.value.includes('\n')) {
+                            e.preventDefault();
+                            historyIndex = Math.min(history.length, historyIndex + 1);
+                            input.value = historyIndex < history.length ? history[historyIndex] : '';
+                        }
+                    });
+
+                    appendLine('Lumina Lua console. Enter runs, Shift+Enter adds a line, arrows browse history.', 'info');
+                    input.focus();
+                })();
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Lua Console - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>{}</style>
+                </head>
+                <body>
+                    <h1>Lua Console</h1>
+                    <div id="output" class="console-output"></div>
+                    <div class="console-input">
+                        <textarea id="code" rows="2" placeholder="lumina.version"></textarea>
+                        <button data-onclick="runCode">Run</button>
+                        <button data-onclick="stopCode">Stop</button>
+                        <button data-onclick="clearOutput">Clear</button>
+                    </div>
+                    <h2>Loaded Scripts <button style="float: right; font-size: 0.6em;" data-onclick="reloadScripts">Reload</button></h2>
+                    <div id="scripts">{}</div>
+                    <script>window.__LUA_HISTORY__ = {};</script>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, console_css, scripts_html, to_script_json(&history), console_js
+            ))
+        },
+        "flags" => {
+            let flags = app.state::<AppDataStore>().flags();
+
+            let mut items_html = String::new();
+            for info in FLAG_INFO {
+                items_html.push_str(&format!(
+                    r#"<div class="item">
+                        <div class="info" style="flex: 1;">
+                            <div class="title">{name}</div>
+                            <div class="meta" style="white-space: normal;">{description}</div>
+                            <div class="meta"><code>#{id}</code></div>
+                        </div>
+                        <select data-onchange="changeFlag" data-id="{id}">
+                            <option value="disabled" {off}>Disabled</option>
+                            <option value="enabled" {on}>Enabled</option>
+                        </select>
+                    </div>"#,
+                    id = escape_html(info.id),
+                    name = escape_html(info.name),
+                    description = escape_html(info.description),
+                    on = if flags.get(info.id) == Some(true) { "selected" } else { "" },
+                    off = if flags.get(info.id) == Some(true) { "" } else { "selected" },
+                ));
+            }
+
+            let flags_js = r#"
+                function changeFlag(select) {
+                    window.__TAURI__.core.invoke('set_flag', { id: select.dataset.id, enabled: select.value === 'enabled' }).catch(e => alert(e.message || e));
+                }
+                function resetFlags() {
+                    window.__TAURI__.core.invoke('reset_flags').then(() => window.location.reload());
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Experiments - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .warning {{ background: rgba(239, 68, 68, 0.1); color: #ef4444; padding: 12px 16px; border-radius: 8px; margin-bottom: 25px; }}
+                        select {{ padding: 8px 12px; background: var(--surface); color: var(--text-strong); border: 1px solid var(--border); border-radius: 6px; }}
+                        code {{ color: var(--primary); }}
+                    </style>
+                </head>
+                <body>
+                    <h1>Experiments</h1>
+                    <div class="warning">These features are experimental and may break pages. Script-based flags apply to newly opened tabs.</div>
+                    <div id="list">{}</div>
+                    <button data-onclick="resetFlags" style="margin-top: 20px;">Reset all to default</button>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, items_html, flags_js
+            ))
+        },
+        "hibernated" => {
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Sleeping tab</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <div class="empty-state">This tab is sleeping to save memory. It will reload when you switch back.</div>
+                </body>
+                </html>"#,
+                lumina_style
+            ))
+        },
+        "crashed" => {
+            let label = query_param(query, "label").unwrap_or_default();
+            let explanation = match query_param(query, "reason").as_deref() {
+                Some("out-of-memory") => "The page ran out of memory.",
+                Some("unresponsive") => "The page stopped responding.",
+                Some("killed") => "The process showing this page was ended.",
+                Some("browser-exited") => "The browser engine behind this tab stopped.",
+                _ => "Something went wrong while showing this page.",
+            };
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Aw, Snap!</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1>Aw, Snap!</h1>
+                    <div class="empty-state">
+                        <p>{}</p>
+                        <button data-onclick="reloadTab">Reload</button>
+                    </div>
+                    <script>
+                        function reloadTab() {{
+                            window.__TAURI__.core.invoke('reload_crashed_tab', {{ label: {} }}).catch(e => alert(e.message || e));
+                        }}
+                    </script>
+                </body>
+                </html>"#,
+                lumina_style,
+                escape_html(explanation),
+                to_script_json(&label)
+            ))
+        },
+        "blocked" => {
+            // Only web addresses; the page navigates there once the site is allowed
+            let url = query_param(query, "url")
+                .filter(|url| Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https")))
+                .unwrap_or_default();
+            let host = Url::parse(&url).ok().and_then(|u| u.host_str().map(normalize_site_domain)).unwrap_or_default();
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Blocked by Lumina Shield</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .blocked-url {{ font-family: monospace; word-break: break-all; color: var(--text-dim); margin: 10px 0 25px; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>🛡️ Blocked by Lumina Shield</h1>
+                    <div class="empty-state">
+                        <p>This page is on an ad or tracker list, so Lumina didn't open it.</p>
+                        <div class="blocked-url">{}</div>
+                        <button id="back">Go back</button>
+                        <button id="unblock">Allow {} and continue</button>
+                    </div>
+                    <script>
+                        document.getElementById('back').addEventListener('click', () => history.back());
+                        document.getElementById('unblock').addEventListener('click', () => {{
+                            window.__TAURI__.core.invoke('toggle_adblock_for_site', {{ domain: {} }})
+                                .then(() => window.location.replace({}))
+                                .catch(e => alert(e.message || e));
+                        }});
+                    </script>
+                </body>
+                </html>"#,
+                lumina_style,
+                escape_html(&url),
+                escape_html(&host),
+                to_script_json(&host),
+                to_script_json(&url)
+            ))
+        },
+        "about" | "version" => {
+            let diagnostics = collect_diagnostics(app);
+
+            let format_size = |bytes: u64| -> String {
+                if bytes >= 1024 * 1024 {
+                    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+                } else {
+                    format!("{:.1} KB", bytes as f64 / 1024.0)
+                }
+            };
+            let row = |label: &str, value: &str| {
+                format!(r#"<tr><td class="label">{}</td><td class="value">{}</td></tr>"#, escape_html(label), escape_html(value))
+            };
+
+            let mut general_rows = String::new();
+            general_rows.push_str(&row("Version", &format!("{} {}", diagnostics.app_name, diagnostics.app_version)));
+            general_rows.push_str(&row("Tauri", &diagnostics.tauri_version));
+            general_rows.push_str(&row("WebView", &diagnostics.webview_version));
+            let capabilities = capabilities::detect();
+            let supported = |on: bool| if on { "Supported" } else { "Not available" };
+            general_rows.push_str(&row("Service workers", &format!(
+                "{}{}",
+                supported(capabilities.service_workers),
+                if capabilities.probed { "" } else { " (from engine version)" }
+            )));
+            general_rows.push_str(&row("Web push", supported(capabilities.push)));
+            general_rows.push_str(&row("Notifications", supported(capabilities.notifications)));
+            general_rows.push_str(&row("OS", &format!("{} ({})", diagnostics.os, diagnostics.arch)));
+            general_rows.push_str(&row("Profile", &diagnostics.profile_path));
+            general_rows.push_str(&row("Lua scripts", &diagnostics.lua_scripts.to_string()));
+
+            let mut adblock_rows = String::new();
+            adblock_rows.push_str(&row("Shield", if diagnostics.adblock_enabled { "Enabled" } else { "Disabled" }));
+            adblock_rows.push_str(&row("Engine", if diagnostics.adblock_engine_ready { "Ready" } else { "Loading" }));
+            for list in &diagnostics.adblock_lists {
+                let status = match (&list.error, &list.version) {
+                    (Some(error), _) => format!("failed: {}", error),
+                    (None, Some(version)) => format!("{} rules, version {}", list.rule_count, version),
+                    (None, None) => format!("{} rules", list.rule_count),
+                };
+                adblock_rows.push_str(&row(&list.name, &status));
+            }
+            adblock_rows.push_str(&row("Redirect resources", &diagnostics.adblock_resources.to_string()));
+            adblock_rows.push_str(&row("Host blocklist", &format!("{} hosts", diagnostics.host_blocklist_size)));
+
+            let mut sidecar_names: Vec<&String> = diagnostics.sidecars.keys().collect();
+            sidecar_names.sort();
+            let mut sidecar_rows = String::new();
+            for name in sidecar_names {
+                sidecar_rows.push_str(&row(name, &diagnostics.sidecars[name]));
+            }
+
+            let mut startup_rows = String::new();
+            startup_rows.push_str(&row("First paint", &diagnostics.startup.first_paint_ms.map(|ms| format!("{} ms", ms)).unwrap_or_else(|| "Not yet".to_string())));
+            for phase in &diagnostics.startup.phases {
+                let when = if phase.deferred { "after paint" } else { "before paint" };
+                startup_rows.push_str(&row(&phase.name, &format!("{} ms (at {} ms, {})", phase.duration_ms, phase.started_ms, when)));
+            }
+            for name in &diagnostics.startup.pending {
+                startup_rows.push_str(&row(name, "Waiting for first paint"));
+            }
+
+            let mut file_rows = String::new();
+            for file in &diagnostics.profile_files {
+                file_rows.push_str(&row(&file.name, &format_size(file.size_bytes)));
+            }
+
+            let mut database_rows = String::new();
+            match &diagnostics.db_maintenance {
+                Some(report) => {
+                    let ran_at = chrono::DateTime::from_timestamp(report.ran_at, 0)
+                        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_default();
+                    database_rows.push_str(&row("Last maintenance", &ran_at));
+                    database_rows.push_str(&row("Integrity", &report.integrity));
+                    database_rows.push_str(&row("Space reclaimed", &format_size(report.freed_bytes)));
+                    database_rows.push_str(&row("Latest backup", report.backup.as_deref().unwrap_or("None")));
+                    database_rows.push_str(&row("Backups kept", &report.backups_kept.to_string()));
+                    if let Some(error) = &report.error {
+                        database_rows.push_str(&row("Error", error));
+                    }
+                }
+                None => database_rows.push_str(&row("Last maintenance", "Never")),
+            }
+
+            let about_js = r#"
+                function runMaintenance() {
+                    const btn = document.getElementById('maintenance-btn');
+                    btn.disabled = true;
+                    btn.textContent = 'Running...';
+                    window.__TAURI__.core.invoke('run_db_maintenance')
+                        .then(() => window.location.reload())
+                        .catch(e => {
+                            alert(e.message || e);
+                            btn.disabled = false;
+                            btn.textContent = 'Run maintenance now';
+                        });
+                }
+                function copyDiagnostics() {
+                    window.__TAURI__.core.invoke('get_diagnostics').then(d => {
+                        navigator.clipboard.writeText(JSON.stringify(d, null, 2));
+                        const btn = document.getElementById('copy-btn');
+                        btn.textContent = 'Copied!';
+                        setTimeout(() => btn.textContent = 'Copy diagnostics', 2000);
+                    });
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>About Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        h2 {{ font-size: 1.1em; font-weight: 500; color: var(--text-dim); margin: 30px 0 10px; }}
+                        table {{ width: 100%; border-collapse: collapse; background: var(--card); border-radius: 8px; overflow: hidden; }}
+                        td {{ padding: 10px 16px; border-bottom: 1px solid var(--border); }}
+                        tr:last-child td {{ border-bottom: none; }}
+                        td.label {{ width: 35%; color: var(--text-dim); }}
+                        td.value {{ font-family: monospace; word-break: break-all; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>About Lumina</h1>
+                    <button id="copy-btn" data-onclick="copyDiagnostics">Copy diagnostics</button>
+                    <h2>General</h2>
+                    <table>{}</table>
+                    <h2>Ad Blocking</h2>
+                    <table>{}</table>
+                    <h2>Sidecars</h2>
+                    <table>{}</table>
+                    <h2>Startup</h2>
+                    <table>{}</table>
+                    <h2>Database</h2>
+                    <table>{}</table>
+                    <button id="maintenance-btn" data-onclick="runMaintenance" style="margin-top: 10px">Run maintenance now</button>
+                    <h2>Profile Storage</h2>
+                    <table>{}</table>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, general_rows, adblock_rows, sidecar_rows, startup_rows, database_rows, file_rows, about_js
+            ))
+        },
+        "newtab" => {
+            let data_store = app.state::<AppDataStore>();
+            let (search_engine, favorites) = {
+                let data = data_store.data.lock().unwrap();
+                (data.settings.search_engine.clone(), data.favorites.clone())
+            };
+            let search_url = locale::search_endpoint(&search_engine);
+            let search_inputs: String = locale::search_params(app, &search_engine)
+                .into_iter()
+                .map(|(name, value)| format!(r#"<input type="hidden" name="{}" value="{}">"#, name, escape_html(&value)))
+                .collect();
+
+            let tile = |url: &str, title: &str| {
+                let host = url::Url::parse(url).ok().and_then(|u| u.host_str().map(|h| h.to_string())).unwrap_or_default();
+                format!(
+                    r#"<a class="tile" href="{url}">
+                        <img src="https://www.google.com/s2/favicons?domain={host}&sz=64" alt="">
+                        <span>{title}</span>
+                    </a>"#,
+                    url = escape_html(url),
+                    host = escape_html(&host),
+                    title = escape_html(if title.is_empty() { &host } else { title })
+                )
+            };
+            let tiles_html: String = favorites.iter().take(12).map(|fav| tile(&fav.url, &fav.title)).collect();
+
+            // This week's most visited pages that aren't favorites already
+            let data_store = app.state::<AppDataStore>();
+            let top_sites = app.state::<HistoryManager>()
+                .get_top_sites(history_stats::StatsPeriod::Week.since(), 8 + favorites.len() as i64, |url| !data_store.is_history_excluded(url))
+                .unwrap_or_default();
+            let top_html: String = top_sites
+                .iter()
+                .filter(|site| !favorites.iter().any(|fav| fav.url == site.url))
+                .take(8)
+                .map(|site| tile(&site.url, &site.title))
+                .collect();
+            let top_section = if top_html.is_empty() {
+                String::new()
+            } else {
+                format!(r#"<h2 class="section-title">Most visited</h2><div class="tiles">{}</div>"#, top_html)
+            };
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>New Tab</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        body {{ display: flex; flex-direction: column; align-items: center; padding-top: 18vh; }}
+                        .logo {{ font-size: 2.6em; font-weight: 600; color: var(--primary); letter-spacing: 2px; margin-bottom: 30px; }}
+                        form {{ width: 100%; max-width: 600px; }}
+                        form input {{ width: 100%; box-sizing: border-box; padding: 14px 20px; font-size: 1.1em; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 24px; outline: none; }}
+                        form input:focus {{ border-color: var(--primary); }}
+                        .tiles {{ display: grid; grid-template-columns: repeat(auto-fill, 110px); gap: 16px; justify-content: center; width: 100%; margin-top: 40px; }}
+                        .tile {{ display: flex; flex-direction: column; align-items: center; gap: 8px; padding: 14px 8px; background: var(--card); border-radius: 10px; color: var(--text); text-decoration: none; font-size: 0.85em; transition: background 0.2s; }}
+                        .tile:hover {{ background: var(--surface); }}
+                        .tile img {{ width: 32px; height: 32px; }}
+                        .tile span {{ max-width: 100%; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }}
+                        .section-title {{ font-size: 0.95em; font-weight: 500; color: var(--text-dim); margin: 36px 0 -24px; }}
+                    </style>
+                </head>
+                <body>
+                    <div class="logo">Lumina</div>
+                    <form action="{}" method="get">
+                        <input type="text" name="q" placeholder="Search the web" autofocus autocomplete="off">
+                        {}
+                    </form>
+                    <div class="tiles">{}</div>
+                    {}
+                </body>
+                </html>"#,
+                lumina_style, search_url, search_inputs, tiles_html, top_section
+            ))
+        },
+        "settings/blocklist" => {
+            let hosts = app.state::<HistoryManager>().get_blocked_hosts().unwrap_or_default();
+
+            let mut items_html = String::new();
+            for host in &hosts {
+                items_html.push_str(&format!(
+                    r#"<div class="item" data-domain="{domain}">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{domain}</div>
+                            <div class="meta">{source}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="removeHost" data-args="{args}">Remove</button>
+                        </div>
+                    </div>"#,
+                    domain = escape_html(&host.domain),
+                    args = handler_args(&[&host.domain]),
+                    source = escape_html(&host.source)
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">Blocklist is empty</div>"#.to_string();
+            }
+
+            let blocklist_js = r#"
+                function invoke(cmd, args) {
+                    return window.__TAURI__.core.invoke(cmd, args);
+                }
+                function addHost() {
+                    const domain = document.getElementById('new-host').value.trim();
+                    if (!domain) return;
+                    invoke('add_blocked_host', { domain }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+                function removeHost(domain) {
+                    invoke('remove_blocked_host', { domain }).then(() => {
+                        const el = document.querySelector('[data-domain="' + CSS.escape(domain) + '"]');
+                        if (el) el.remove();
+                    }).catch(e => alert(e.message || e));
+                }
+                function chooseHostsFile() {
+                    document.getElementById('hosts-file').click();
+                }
+                function importHosts(input) {
+                    const file = input.files[0];
+                    if (!file) return;
+                    const reader = new FileReader();
+                    reader.onload = () => {
+                        invoke('import_hosts_file', { content: reader.result }).then((added) => {
+                            alert('Imported ' + added + ' new hosts');
+                            window.location.reload();
+                        }).catch(e => alert(e.message || e));
+                    };
+                    reader.readAsText(file);
+                }
+                function filterHosts(input) {
+                    const query = input.value.toLowerCase();
+                    document.querySelectorAll('#list .item').forEach(el => {
+                        el.style.display = el.dataset.domain.includes(query) ? '' : 'none';
+                    });
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Host Blocklist - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .toolbar {{ display: flex; gap: 10px; margin-bottom: 20px; }}
+                        .toolbar input[type="text"] {{ flex: 1; padding: 8px 12px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>Host Blocklist</h1>
+                    <div class="toolbar">
+                        <input type="text" id="new-host" placeholder="ads.example.com">
+                        <button data-onclick="addHost">Add</button>
+                        <button data-onclick="chooseHostsFile">Import hosts file</button>
+                        <input type="file" id="hosts-file" style="display: none" data-onchange="importHosts">
+                    </div>
+                    <div class="toolbar">
+                        <input type="text" placeholder="Filter {} hosts..." data-oninput="filterHosts">
+                    </div>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, hosts.len(), items_html, blocklist_js
+            ))
+        },
+        "settings/protocols" => {
+            let handlers = app.state::<AppDataStore>().data.lock().unwrap().settings.protocol_handlers.clone();
+
+            let mut items_html = String::new();
+            for (scheme, action) in &handlers {
+                let option = |value: &str, text: &str| {
+                    format!(r#"<option value="{}" {}>{}</option>"#, value, if action == value { "selected" } else { "" }, text)
+                };
+                items_html.push_str(&format!(
+                    r#"<div class="item" data-scheme="{scheme}">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{scheme}:</div>
+                        </div>
+                        <div class="actions">
+                            <select data-onchange="setAction">{ask}{allow}{deny}</select>
+                            <button data-onclick="removeScheme">Remove</button>
+                        </div>
+                    </div>"#,
+                    scheme = escape_html(scheme),
+                    ask = option("ask", "Ask"),
+                    allow = option("allow", "Always open"),
+                    deny = option("deny", "Block")
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No external protocols configured</div>"#.to_string();
+            }
+
+            let protocols_js = r#"
+                function invoke(cmd, args) {
+                    return window.__TAURI__.core.invoke(cmd, args);
+                }
+                function addScheme() {
+                    const scheme = document.getElementById('new-scheme').value.trim();
+                    const action = document.getElementById('new-action').value;
+                    if (!scheme) return;
+                    invoke('set_protocol_handler', { scheme, action }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+                function setAction(select) {
+                    const scheme = select.closest('.item').dataset.scheme;
+                    invoke('set_protocol_handler', { scheme, action: select.value }).catch(e => alert(e.message || e));
+                }
+                function removeScheme(button) {
+                    const item = button.closest('.item');
+                    invoke('remove_protocol_handler', { scheme: item.dataset.scheme }).then(() => item.remove()).catch(e => alert(e.message || e));
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>External Protocols - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .toolbar {{ display: flex; gap: 10px; margin-bottom: 20px; }}
+                        .toolbar input[type="text"] {{ flex: 1; padding: 8px 12px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                        select {{ padding: 6px 10px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>External Protocols</h1>
+                    <p>Links with these schemes are opened by the default application on your system.</p>
+                    <div class="toolbar">
+                        <input type="text" id="new-scheme" placeholder="magnet">
+                        <select id="new-action">
+                            <option value="ask">Ask</option>
+                            <option value="allow">Always open</option>
+                            <option value="deny">Block</option>
+                        </select>
+                        <button data-onclick="addScheme">Add</button>
+                    </div>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, items_html, protocols_js
+            ))
+        },
+        "settings/readaloud" => {
+            let (rate, voice) = {
+                let data = app.state::<AppDataStore>();
+                let data = data.data.lock().unwrap();
+                (data.settings.read_aloud_rate, data.settings.read_aloud_voice.clone().unwrap_or_default())
+            };
+
+            let readaloud_js = r#"
+                function invoke(cmd, args) {
+                    return window.__TAURI__.core.invoke(cmd, args);
+                }
+                const voiceSelect = document.getElementById('voice');
+                const rateInput = document.getElementById('rate');
+                function save() {
+                    document.getElementById('rate-value').textContent = Number(rateInput.value).toFixed(1) + '×';
+                    invoke('set_read_aloud_settings', { rate: Number(rateInput.value), voice: voiceSelect.value || null })
+                        .catch(e => alert(e.message || e));
+                }
+                invoke('list_read_aloud_voices').then(voices => {
+                    for (const v of voices) {
+                        const option = document.createElement('option');
+                        option.value = v.id;
+                        option.textContent = v.language ? v.name + ' (' + v.language + ')' : v.name;
+                        option.selected = v.id === voiceSelect.dataset.current;
+                        voiceSelect.appendChild(option);
+                    }
+                }).catch(e => {
+                    document.getElementById('unavailable').textContent = e.message || e;
+                    voiceSelect.disabled = true;
+                    rateInput.disabled = true;
+                });
+                voiceSelect.addEventListener('change', save);
+                rateInput.addEventListener('input', save);
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Read Aloud - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        select {{ padding: 6px 10px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                        input[type="range"] {{ width: 260px; vertical-align: middle; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>Read Aloud</h1>
+                    <p>Used when a page is read aloud from the reader view. <span id="unavailable"></span></p>
+                    <div class="group">
+                        <div class="form-group">
+                            <label>Voice</label>
+                            <select id="voice" data-current="{}"><option value="">System default</option></select>
+                        </div>
+                        <div class="form-group">
+                            <label>Speed</label>
+                            <input type="range" id="rate" min="{}" max="{}" step="0.1" value="{}">
+                            <span id="rate-value">{:.1}×</span>
+                        </div>
+                    </div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, escape_html(&voice), speech::MIN_RATE, speech::MAX_RATE, rate, rate, readaloud_js
+            ))
+        },
+        "settings/spellcheck" => {
+            let (enabled, sites) = {
+                let data = app.state::<AppDataStore>();
+                let data = data.data.lock().unwrap();
+                (data.settings.spellcheck_enabled, data.settings.spellcheck_disabled_sites.clone())
+            };
+
+            let mut items_html = String::new();
+            for domain in &sites {
+                items_html.push_str(&format!(
+                    r#"<div class="item" data-domain="{domain}">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{domain}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="setSite" data-args="{args}">Remove</button>
+                        </div>
+                    </div>"#,
+                    domain = escape_html(domain),
+                    args = handler_args(&(domain, false))
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">Spell check is on for every site</div>"#.to_string();
+            }
+
+            let spellcheck_js = r#"
+                function invoke(cmd, args) {
+                    return window.__TAURI__.core.invoke(cmd, args);
+                }
+                function setSite(domain, disabled) {
+                    invoke('set_spellcheck_site_disabled', { domain, disabled })
+                        .then(() => window.location.reload())
+                        .catch(e => alert(e.message || e));
+                }
+                function addSite() {
+                    const domain = document.getElementById('new-site').value.trim();
+                    if (domain) setSite(domain, true);
+                }
+                document.getElementById('enabled').addEventListener('change', (e) => {
+                    invoke('set_spellcheck_enabled', { enabled: e.target.checked }).catch(e => alert(e.message || e));
+                });
+
+                function saveLanguages() {
+                    const languages = [...document.querySelectorAll('#languages input:checked')].map(i => i.value);
+                    invoke('set_spellcheck_languages', { languages })
+                        .then(() => document.getElementById('restart-note').style.display = 'block')
+                        .catch(e => alert(e.message || e));
+                }
+                invoke('get_spellcheck_languages').then(({ available, selected, active }) => {
+                    const list = document.getElementById('languages');
+                    const tags = [...new Set([...available, ...selected])];
+                    if (!tags.length) {
+                        list.textContent = 'Spell check uses your system languages.';
+                        return;
+                    }
+                    for (const tag of tags) {
+                        const row = document.createElement('div');
+                        row.className = 'form-group checkbox-group';
+                        const input = document.createElement('input');
+                        input.type = 'checkbox';
+                        input.id = 'lang-' + tag;
+                        input.value = tag;
+                        input.checked = selected.includes(tag);
+                        input.addEventListener('change', saveLanguages);
+                        const label = document.createElement('label');
+                        label.htmlFor = input.id;
+                        label.style.marginBottom = '0';
+                        label.textContent = tag;
+                        row.append(input, label);
+                        list.appendChild(row);
+                    }
+                    if (selected.join(',') !== active.join(',')) {
+                        document.getElementById('restart-note').style.display = 'block';
+                    }
+                }).catch(e => document.getElementById('languages').textContent = e.message || e);
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Spell Check - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .toolbar {{ display: flex; gap: 10px; margin-bottom: 20px; }}
+                        .toolbar input[type="text"] {{ flex: 1; padding: 8px 12px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                        #restart-note {{ display: none; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>Spell Check</h1>
+                    <div class="group">
+                        <div class="form-group checkbox-group">
+                            <input type="checkbox" id="enabled" {}>
+                            <label for="enabled" style="margin-bottom: 0">Check spelling in text fields</label>
+                        </div>
+                    </div>
+
+                    <h2>Dictionaries</h2>
+                    <p>None selected means your system languages are used.</p>
+                    <div class="group" id="languages"></div>
+                    <p id="restart-note">Dictionary changes apply after Lumina restarts.</p>
+
+                    <h2>Sites without spell check</h2>
+                    <div class="toolbar">
+                        <input type="text" id="new-site" placeholder="example.com">
+                        <button data-onclick="addSite">Turn off</button>
+                    </div>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, if enabled { "checked" } else { "" }, items_html, spellcheck_js
+            ))
+        },
+        "settings/storage" => {
+            let storage_js = r#"
+                function invoke(cmd, args) {
+                    return window.__TAURI__.core.invoke(cmd, args);
+                }
+                function formatSize(bytes) {
+                    if (bytes >= 1024 * 1024 * 1024) return (bytes / (1024 * 1024 * 1024)).toFixed(1) + ' GB';
+                    if (bytes >= 1024 * 1024) return (bytes / (1024 * 1024)).toFixed(1) + ' MB';
+                    return (bytes / 1024).toFixed(1) + ' KB';
+                }
+                function row(name, detail, size) {
+                    const item = document.createElement('div');
+                    item.className = 'item';
+                    const info = document.createElement('div');
+                    info.style.flex = '1';
+                    info.style.minWidth = '0';
+                    const title = document.createElement('div');
+                    title.className = 'title';
+                    title.textContent = name;
+                    const meta = document.createElement('div');
+                    meta.className = 'meta';
+                    meta.textContent = detail;
+                    info.append(title, meta);
+                    const value = document.createElement('div');
+                    value.className = 'meta';
+                    value.textContent = formatSize(size);
+                    item.append(info, value);
+                    return item;
+                }
+                function render(usage) {
+                    document.getElementById('total').textContent = formatSize(usage.totalBytes) + ' in total, ' + formatSize(usage.cacheBytes) + ' of it cache';
+                    const list = document.getElementById('list');
+                    list.replaceChildren(...usage.entries.map(e => row(e.name, e.path, e.sizeBytes)));
+                    const apps = document.getElementById('apps');
+                    apps.replaceChildren(...usage.pwas.map(p => row(p.title, 'Installed app with its own sign-in', p.sizeBytes)));
+                    if (!usage.pwas.length) apps.innerHTML = '<div class="empty-state">No installed app keeps its own data</div>';
+                }
+                function clearCache() {
+                    const button = document.getElementById('clear');
+                    button.disabled = true;
+                    invoke('clear_cache').then(render).catch(e => alert(e.message || e)).finally(() => button.disabled = false);
+                }
+                invoke('get_storage_usage').then(render).catch(e => document.getElementById('total').textContent = e.message || e);
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Storage - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .toolbar {{ display: flex; gap: 10px; align-items: center; justify-content: space-between; margin-bottom: 20px; }}
+                        h2 {{ font-size: 1.1em; font-weight: 500; color: var(--text-dim); margin: 30px 0 10px; }}
+                        .meta {{ overflow: hidden; text-overflow: ellipsis; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>Storage</h1>
+                    <div class="toolbar">
+                        <span id="total">Measuring...</span>
+                        <button id="clear" data-onclick="clearCache">Clear cache</button>
+                    </div>
+                    <div id="list"></div>
+                    <h2>Installed apps</h2>
+                    <div id="apps"></div>
+                    <p class="meta">Clearing the cache keeps you signed in; pages may load slower the next time.</p>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, storage_js
+            ))
+        },
+        "settings/autofill" => {
+            let sites = app.state::<AppDataStore>().data.lock().unwrap().settings.autofill_disabled_sites.clone();
+
+            let mut items_html = String::new();
+            for domain in &sites {
+                items_html.push_str(&format!(
+                    r#"<div class="item" data-domain="{domain}">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{domain}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="setSite" data-args="{args}">Remove</button>
+                        </div>
+                    </div>"#,
+                    domain = escape_html(domain),
+                    args = handler_args(&(domain, true))
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">Autofill works on every site</div>"#.to_string();
+            }
+
+            let autofill_js = r#"
+                const FIELDS = ['label', 'fullName', 'email', 'phone', 'organization', 'streetAddress', 'city', 'region', 'postalCode', 'country'];
+                const SNAKE = { fullName: 'full_name', streetAddress: 'street_address', postalCode: 'postal_code' };
+
+                function invoke(cmd, args) {
+                    return window.__TAURI__.core.invoke(cmd, args);
+                }
+                function setSite(domain, enabled) {
+                    invoke('set_autofill_site_enabled', { domain, enabled })
+                        .then(() => window.location.reload())
+                        .catch(e => alert(e.message || e));
+                }
+                function addSite() {
+                    const domain = document.getElementById('new-site').value.trim();
+                    if (domain) setSite(domain, false);
+                }
+
+                function edit(profile) {
+                    document.getElementById('profile-id').value = profile ? profile.id : '';
+                    for (const field of FIELDS) {
+                        document.getElementById('field-' + field).value = profile ? profile[SNAKE[field] || field] : '';
+                    }
+                    document.getElementById('field-label').focus();
+                }
+                function saveProfile(form, event) {
+                    event.preventDefault();
+                    const profile = { id: document.getElementById('profile-id').value };
+                    for (const field of FIELDS) {
+                        profile[SNAKE[field] || field] = document.getElementById('field-' + field).value.trim();
+                    }
+                    invoke('save_autofill_profile', { profile }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+                function deleteProfile(id) {
+                    if (!confirm('Delete this profile?')) return;
+                    invoke('delete_autofill_profile', { id }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+
+                invoke('list_autofill_profiles').then(list => {
+                    const container = document.getElementById('profiles');
+                    if (!list.length) {
+                        container.innerHTML = '<div class="empty-state">No profiles yet</div>';
+                        return;
+                    }
+                    for (const profile of list) {
+                        const item = document.createElement('div');
+                        item.className = 'item';
+                        const info = document.createElement('div');
+                        info.className = 'info';
+                        info.style.flex = '1';
+                        const name = document.createElement('div');
+                        name.className = 'filename';
+                        name.textContent = profile.label;
+                        const meta = document.createElement('div');
+                        meta.className = 'meta';
+                        meta.textContent = [profile.full_name, profile.email, profile.city].filter(Boolean).join(' · ');
+                        info.append(name, meta);
+                        const actions = document.createElement('div');
+                        actions.className = 'actions';
+                        const editButton = document.createElement('button');
+                        editButton.textContent = 'Edit';
+                        editButton.onclick = () => edit(profile);
+                        const deleteButton = document.createElement('button');
+                        deleteButton.textContent = 'Delete';
+                        deleteButton.onclick = () => deleteProfile(profile.id);
+                        actions.append(editButton, deleteButton);
+                        item.append(info, actions);
+                        container.appendChild(item);
+                    }
+                }).catch(e => document.getElementById('profiles').textContent = e.message || e);
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Autofill - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .toolbar {{ display: flex; gap: 10px; margin-bottom: 20px; }}
+                        .toolbar input[type="text"] {{ flex: 1; padding: 8px 12px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                        #profile-form {{ display: grid; grid-template-columns: 1fr 1fr; gap: 8px; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>Autofill</h1>
+                    <p class="meta">Profiles are stored encrypted on this device and fill name, address and contact fields when you choose Fill Form. Passwords are not part of them.</p>
+
+                    <h2>Profiles</h2>
+                    <div id="profiles"></div>
+                    <form class="group" id="profile-form" data-onsubmit="saveProfile">
+                        <input type="hidden" id="profile-id">
+                        <input id="field-label" placeholder="Profile name (Home, Work)">
+                        <input id="field-fullName" placeholder="Full name">
+                        <input id="field-email" type="email" placeholder="Email">
+                        <input id="field-phone" type="tel" placeholder="Phone">
+                        <input id="field-organization" placeholder="Company">
+                        <input id="field-streetAddress" placeholder="Street address">
+                        <input id="field-city" placeholder="City">
+                        <input id="field-region" placeholder="State or province">
+                        <input id="field-postalCode" placeholder="Postal code">
+                        <input id="field-country" placeholder="Country">
+                        <div><button type="submit">Save profile</button> <button type="button" data-onclick="edit" data-args="[null]">New</button></div>
+                    </form>
+
+                    <h2>Sites without autofill</h2>
+                    <div class="toolbar">
+                        <input type="text" id="new-site" placeholder="example.com">
+                        <button data-onclick="addSite">Turn off</button>
+                    </div>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, items_html, autofill_js
+            ))
+        },
+        "files" => {
+            let notice = |message: &str| format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head><title>Files - Lumina</title><meta charset="UTF-8">{}</head>
+                <body>
+                    <h1>Files</h1>
+                    <div class="empty-state">{} <a href="lumina-app://settings/local-files">Change this in settings</a>.</div>
+                </body>
+                </html>"#,
+                lumina_style, message
+            );
+            if !local_files::access_enabled(app) {
+                return Some(notice("Local file access is turned off."));
+            }
+
+            // Only the folders the user allowed, and what is inside them
+            let Some(path) = query_param(query, "path")
+                .map(std::path::PathBuf::from)
+                .or_else(|| local_files::allowed_folders(app).first().map(std::path::PathBuf::from))
+            else {
+                return Some(notice("No folders are allowed yet."));
+            };
+            let path = path.canonicalize().unwrap_or(path);
+            if !local_files::in_allowed_folder(app, &path) {
+                return Some(notice("This folder isn't one of the folders you allowed."));
+            }
+
+            let format_size = |bytes: u64| -> String {
+                if bytes >= 1024 * 1024 {
+                    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+                } else {
+                    format!("{:.1} KB", bytes as f64 / 1024.0)
+                }
+            };
+
+            // Each folder on the way down; the page itself and folders above the allowed ones unlinked
+            let mut ancestors: Vec<&std::path::Path> = path.ancestors().collect();
+            ancestors.reverse();
+            let breadcrumbs = ancestors
+                .iter()
+                .enumerate()
+                .map(|(i, ancestor)| {
+                    let name = ancestor.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| ancestor.to_string_lossy().into_owned());
+                    if i + 1 == ancestors.len() || !local_files::in_allowed_folder(app, ancestor) {
+                        format!("<span>{}</span>", escape_html(&name))
+                    } else {
+                        format!(r#"<a href="{}">{}</a>"#, escape_html(&local_files::browse_url(ancestor)), escape_html(&name))
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(r#" <span class="meta">›</span> "#);
+
+            let body = if path.is_dir() {
+                match local_files::read_directory(&path) {
+                    Ok(entries) if entries.is_empty() => r#"<div class="empty-state">This folder is empty</div>"#.to_string(),
+                    Ok(entries) => entries
+                        .iter()
+                        .map(|entry| {
+                            format!(
+                                r#"<div class="item">
+                                    <div class="icon">{icon}</div>
+                                    <div class="info" style="flex: 1;">
+                                        <div class="filename"><a href="{href}">{name}</a></div>
+                                    </div>
+                                    <div class="meta">{size}</div>
+                                </div>"#,
+                                icon = if entry.is_dir { "📁" } else { "📄" },
+                                href = escape_html(&local_files::browse_url(&entry.path)),
+                                name = escape_html(&entry.name),
+                                size = if entry.is_dir { String::new() } else { format_size(entry.size) }
+                            )
+                        })
+                        .collect(),
+                    Err(e) => format!(r#"<div class="empty-state">Can't read this folder: {}</div>"#, escape_html(&e.to_string())),
+                }
+            } else {
+                let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                let preview = match local_files::preview(&path) {
+                    Ok(local_files::Preview::Text(text, truncated)) => format!(
+                        r#"<pre class="preview">{}</pre>{}"#,
+                        escape_html(&text),
+                        if truncated { r#"<p class="meta">Only the start of the file is shown.</p>"# } else { "" }
+                    ),
+                    Ok(local_files::Preview::Image(data_url)) => format!(r#"<img class="preview" src="{}" alt="">"#, escape_html(&data_url)),
+                    Ok(local_files::Preview::None) => r#"<div class="empty-state">No preview for this kind of file</div>"#.to_string(),
+                    Err(e) => format!(r#"<div class="empty-state">Can't read this file: {}</div>"#, escape_html(&e.to_string())),
+                };
+                format!(
+                    r#"<p class="meta">{} · {}</p>
+                    <div class="toolbar"><button data-onclick="openFile">Open in a tab</button></div>
+                    {}"#,
+                    escape_html(local_files::mime_type(&path).split('/').next().filter(|kind| !kind.is_empty()).unwrap_or("file")),
+                    format_size(size),
+                    preview
+                )
+            };
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>{title} - Lumina</title>
+                    <meta charset="UTF-8">
+                    {style}
+                    <style>
+                        .breadcrumbs {{ margin-bottom: 20px; word-break: break-all; }}
+                        .breadcrumbs a, .filename a {{ color: var(--text-strong); text-decoration: none; }}
+                        .breadcrumbs a:hover, .filename a:hover {{ color: var(--primary); }}
+                        .toolbar {{ margin-bottom: 20px; }}
+                        pre.preview {{ background: var(--card); padding: 15px; border-radius: 8px; overflow: auto; white-space: pre-wrap; word-break: break-word; }}
+                        img.preview {{ max-width: 100%; border-radius: 8px; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>{title}</h1>
+                    <div class="breadcrumbs">{breadcrumbs}</div>
+                    {body}
+                    <script>
+                        const PATH = {path_js};
+                        function openFile() {{
+                            window.__TAURI__.core.invoke('open_local_file', {{ path: PATH }}).catch(e => alert(e.message || e));
+                        }}
+                    </script>
+                </body>
+                </html>"#,
+                title = escape_html(&path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| path.to_string_lossy().into_owned())),
+                style = lumina_style,
+                breadcrumbs = breadcrumbs,
+                body = body,
+                path_js = to_script_json(&path.to_string_lossy())
+            ))
+        },
+        "settings/sessions" => {
+            let sessions_js = r#"
+                function invoke(cmd, args) {
+                    return window.__TAURI__.core.invoke(cmd, args);
+                }
+                function saveSession() {
+                    const name = document.getElementById('new-session').value.trim();
+                    if (!name) return;
+                    invoke('save_session', { name }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+                function loadSession(name) {
+                    if (!confirm('Close the tabs in this window and open "' + name + '"?')) return;
+                    invoke('load_session', { name }).catch(e => alert(e.message || e));
+                }
+                function deleteSession(name) {
+                    invoke('delete_session', { name }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+                invoke('list_sessions').then(list => {
+                    const container = document.getElementById('list');
+                    if (!list.length) {
+                        container.innerHTML = '<div class="empty-state">No saved sessions yet</div>';
+                        return;
+                    }
+                    for (const session of list) {
+                        const item = document.createElement('div');
+                        item.className = 'item';
+                        const info = document.createElement('div');
+                        info.className = 'info';
+                        info.style.flex = '1';
+                        const name = document.createElement('div');
+                        name.className = 'filename';
+                        name.textContent = session.name;
+                        const meta = document.createElement('div');
+                        meta.className = 'meta';
+                        const details = [session.tabCount + (session.tabCount === 1 ? ' tab' : ' tabs')];
+                        if (session.groups.length) details.push(session.groups.join(', '));
+                        details.push('saved ' + new Date(session.savedAt * 1000).toLocaleString());
+                        meta.textContent = details.join(' · ');
+                        info.append(name, meta);
+                        const actions = document.createElement('div');
+                        actions.className = 'actions';
+                        const loadButton = document.createElement('button');
+                        loadButton.textContent = 'Open';
+                        loadButton.onclick = () => loadSession(session.name);
+                        const saveButton = document.createElement('button');
+                        saveButton.textContent = 'Update';
+                        saveButton.onclick = () => invoke('save_session', { name: session.name }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                        const deleteButton = document.createElement('button');
+                        deleteButton.textContent = 'Delete';
+                        deleteButton.onclick = () => deleteSession(session.name);
+                        actions.append(loadButton, saveButton, deleteButton);
+                        item.append(info, actions);
+                        container.appendChild(item);
+                    }
+                }).catch(e => document.getElementById('list').textContent = e.message || e);
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Sessions - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1>Sessions</h1>
+                    <p>A session keeps the tabs of this window, with their groups and pins, so you can come back to them later. Opening one replaces the tabs in this window. Private tabs are never saved.</p>
+                    <div class="toolbar">
+                        <input type="text" id="new-session" placeholder="Research">
+                        <button data-onclick="saveSession">Save current tabs</button>
+                    </div>
+                    <div id="list"></div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, sessions_js
+            ))
+        },
+        "settings/aliases" => {
+            let aliases = app.state::<HistoryManager>().get_aliases().unwrap_or_default();
+
+            let mut items_html = String::new();
+            for alias in &aliases {
+                items_html.push_str(&format!(
+                    r#"<div class="item">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{alias}</div>
+                            <div class="meta">{url}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="removeAlias" data-args="{args}">Remove</button>
+                        </div>
+                    </div>"#,
+                    alias = escape_html(&alias.alias),
+                    url = escape_html(&alias.url),
+                    args = handler_args(&[&alias.alias])
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No aliases yet</div>"#.to_string();
+            }
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Aliases - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1>Aliases</h1>
+                    <p>Type an alias in the address bar to open its site instead of searching. Put <strong>%s</strong> in the URL to pass along what you type after it, e.g. <strong>yt cats</strong>. Bookmark keywords win over aliases with the same name.</p>
+                    <div class="toolbar">
+                        <input type="text" id="new-alias" placeholder="yt">
+                        <input type="text" id="new-url" placeholder="youtube.com">
+                        <button data-onclick="addAlias">Add</button>
+                    </div>
+                    <div id="list">{}</div>
+                    <script>
+                        function addAlias() {{
+                            const alias = document.getElementById('new-alias').value.trim();
+                            const url = document.getElementById('new-url').value.trim();
+                            if (!alias || !url) return;
+                            window.__TAURI__.core.invoke('add_alias', {{ alias, url }})
+                                .then(() => window.location.reload())
+                                .catch(e => alert(e.message || e));
+                        }}
+                        function removeAlias(alias) {{
+                            window.__TAURI__.core.invoke('remove_alias', {{ alias }})
+                                .then(() => window.location.reload())
+                                .catch(e => alert(e.message || e));
+                        }}
+                    </script>
+                </body>
+                </html>"#,
+                lumina_style, items_html
+            ))
+        },
+        "settings/history" => {
+            let sites = app.state::<AppDataStore>().data.lock().unwrap().settings.history_excluded_sites.clone();
+
+            let mut items_html = String::new();
+            for site in &sites {
+                items_html.push_str(&format!(
+                    r#"<div class="item">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{site}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="setExcluded" data-args="{args}">Remove</button>
+                        </div>
+                    </div>"#,
+                    site = escape_html(site),
+                    args = handler_args(&(site, false))
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">Every site is recorded</div>"#.to_string();
+            }
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>History Exclusions - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1>History Exclusions</h1>
+                    <p>Visits to these sites are never recorded in history, and the address bar doesn't suggest them. <strong>bank.com</strong> also covers its subdomains; <strong>*</strong> matches anything, e.g. <strong>*.health.gov</strong> or <strong>mybank*</strong>. History already recorded stays until you delete it.</p>
+                    <div class="toolbar">
+                        <input type="text" id="new-site" placeholder="bank.com">
+                        <button data-onclick="addSite">Add</button>
+                    </div>
+                    <div id="list">{}</div>
+                    <script>
+                        function setExcluded(pattern, excluded) {{
+                            window.__TAURI__.core.invoke('set_history_excluded', {{ pattern, excluded }})
+                                .then(() => window.location.reload())
+                                .catch(e => alert(e.message || e));
+                        }}
+                        function addSite() {{
+                            const pattern = document.getElementById('new-site').value.trim();
+                            if (pattern) setExcluded(pattern, true);
+                        }}
+                    </script>
+                </body>
+                </html>"#,
+                lumina_style, items_html
+            ))
+        },
+        "settings/forget" => {
+            let sites = app.state::<AppDataStore>().data.lock().unwrap().settings.forget_on_close_sites.clone();
+
+            let mut items_html = String::new();
+            for site in &sites {
+                items_html.push_str(&format!(
+                    r#"<div class="item">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{site}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="setForget" data-args="{args}">Remove</button>
+                        </div>
+                    </div>"#,
+                    site = escape_html(site),
+                    args = handler_args(&(site, false))
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">Every site keeps its cookies</div>"#.to_string();
+            }
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Forget on Close - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1>Forget on Close</h1>
+                    <p>When the last tab of one of these sites closes, its cookies and site storage are deleted, so the next visit starts signed out. A site covers its subdomains; <strong>mail.example.com</strong> is listed as <strong>example.com</strong>. Tabs opened in a site-isolated profile keep their data in that profile.</p>
+                    <div class="toolbar">
+                        <input type="text" id="new-site" placeholder="example.com">
+                        <button data-onclick="addSite">Add</button>
+                    </div>
+                    <div id="list">{}</div>
+                    <script>
+                        function setForget(domain, enabled) {{
+                            window.__TAURI__.core.invoke('set_forget_on_close', {{ domain, enabled }})
+                                .then(() => window.location.reload())
+                                .catch(e => alert(e.message || e));
+                        }}
+                        function addSite() {{
+                            const domain = document.getElementById('new-site').value.trim();
+                            if (domain) setForget(domain, true);
+                        }}
+                    </script>
+                </body>
+                </html>"#,
+                lumina_style, items_html
+            ))
+        },
+        "settings/headers" => {
+            let rules = app.state::<HistoryManager>().get_header_rules().unwrap_or_default();
+
+            let mut items_html = String::new();
+            for rule in &rules {
+                let change = match (rule.action.as_str(), &rule.value) {
+                    ("set", Some(value)) => format!("Set {}: {}", rule.name, value),
+                    _ => format!("Remove {}", rule.name),
+                };
+                items_html.push_str(&format!(
+                    r#"<div class="item" style="{faded}">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{domain}</div>
+                            <div class="meta">{change}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="toggleRule" data-args="{rule_args}">{toggle}</button>
+                            <button data-onclick="deleteRule" data-args="{id_args}">Remove</button>
+                        </div>
+                    </div>"#,
+                    faded = if rule.enabled { "" } else { "opacity: 0.5;" },
+                    domain = escape_html(&rule.domain),
+                    change = escape_html(&change),
+                    rule_args = handler_args(&[rule]),
+                    toggle = if rule.enabled { "Turn off" } else { "Turn on" },
+                    id_args = handler_args(&[&rule.id])
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No header rules yet</div>"#.to_string();
+            }
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Request Headers - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1>Request Headers</h1>
+                    <p>Change the headers tabs send to a site and its subdomains, e.g. remove <strong>Referer</strong> for a site, or send <strong>Authorization</strong> to a self-hosted app. Values are stored encrypted. Rules apply on Windows only for now.</p>
+                    <div class="toolbar">
+                        <input type="text" id="new-domain" placeholder="example.com">
+                        <select id="new-action" data-onchange="actionChanged">
+                            <option value="set">Set</option>
+                            <option value="remove">Remove</option>
+                        </select>
+                        <input type="text" id="new-name" placeholder="Authorization">
+                        <input type="text" id="new-value" placeholder="Bearer ...">
+                        <button data-onclick="addRule">Add</button>
+                    </div>
+                    <div id="list">{}</div>
+                    <script>
+                        function saveRule(rule) {{
+                            window.__TAURI__.core.invoke('save_header_rule', rule)
+                                .then(() => window.location.reload())
+                                .catch(e => alert(e.message || e));
+                        }}
+                        function actionChanged(select) {{
+                            document.getElementById('new-value').disabled = select.value === 'remove';
+                        }}
+                        function addRule() {{
+                            const domain = document.getElementById('new-domain').value.trim();
+                            const action = document.getElementById('new-action').value;
+                            const name = document.getElementById('new-name').value.trim();
+                            const value = document.getElementById('new-value').value;
+                            if (!domain || !name) return;
+                            saveRule({{ id: null, domain, action, name, value: action === 'set' ? value : null, enabled: true }});
+                        }}
+                        function toggleRule(rule) {{
+                            saveRule({{ id: rule.id, domain: rule.domain, action: rule.action, name: rule.name, value: rule.value, enabled: !rule.enabled }});
+                        }}
+                        function deleteRule(id) {{
+                            window.__TAURI__.core.invoke('delete_header_rule', {{ id }})
+                                .then(() => window.location.reload())
+                                .catch(e => alert(e.message || e));
+                        }}
+                    </script>
+                </body>
+                </html>"#,
+                lumina_style, items_html
+            ))
+        },
+        "settings/local-files" => {
+            let store = app.state::<AppDataStore>();
+            let (folders, access) = {
+                let data = store.data.lock().unwrap();
+                (data.settings.local_file_folders.clone(), data.settings.local_file_access)
+            };
+
+            let mut items_html = String::new();
+            for folder in &folders {
+                items_html.push_str(&format!(
+                    r#"<div class="item">
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{folder}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="removeFolder" data-args="{args}">Remove</button>
+                        </div>
+                    </div>"#,
+                    folder = escape_html(folder),
+                    args = handler_args(&[folder])
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No folders yet. Drop a file on Lumina to allow its folder.</div>"#.to_string();
+            }
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Local Files - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1>Local files</h1>
+                    <div class="group">
+                        <div class="form-group checkbox-group">
+                            <input type="checkbox" id="local_file_access" {} data-onchange="setAccess">
+                            <label for="local_file_access" style="margin-bottom: 0">Allow opening local files and folders</label>
+                        </div>
+                        <a href="lumina-app://files">Browse files</a>
+                    </div>
+                    <p class="meta">Files from these folders open in tabs as file:// pages. Programs and scripts never do, and pages can't reach files outside them.</p>
+                    <div id="list">{}</div>
+                    <script>
+                        function setAccess(checkbox) {{
+                            window.__TAURI__.core.invoke('set_local_file_access', {{ enabled: checkbox.checked }}).catch(e => alert(e.message || e));
+                        }}
+                        function removeFolder(folder) {{
+                            window.__TAURI__.core.invoke('remove_local_file_folder', {{ folder }})
+                                .then(() => window.location.reload())
+                                .catch(e => alert(e.message || e));
+                        }}
+                    </script>
+                </body>
+                </html>"#,
+                lumina_style, if access { "checked" } else { "" }, items_html
+            ))
+        },
+        "extensions" => {
+            let extensions = list_extensions(app.clone());
+
+            let mut items_html = String::new();
+            for ext in &extensions {
+                items_html.push_str(&format!(
+                    r#"<div class="item" data-id="{id}" style="{dim}">
+                        <div class="icon" style="font-size: 24px; width: 40px; text-align: center;">🧩</div>
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{name} <span class="meta">{version}</span></div>
+                            <div class="meta">{description}</div>
+                            <div class="meta">Manifest V{mv} • {id}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="setEnabled" data-args="{toggle_args}">{toggle}</button>
+                            <button data-onclick="removeExtension" data-args="{remove_args}">Remove</button>
+                        </div>
+                    </div>"#,
+                    dim = if ext.enabled { "" } else { "opacity: 0.5;" },
+                    toggle_args = handler_args(&(&ext.id, !ext.enabled)),
+                    remove_args = handler_args(&[&ext.id]),
+                    toggle = if ext.enabled { "Disable" } else { "Enable" },
+                    id = escape_html(&ext.id),
+                    name = escape_html(&ext.name),
+                    version = escape_html(&ext.version),
+                    description = escape_html(&ext.description),
+                    mv = ext.manifest_version
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No extensions installed</div>"#.to_string();
+            }
+
+            let restart_display = if EXTENSIONS_CHANGED.load(std::sync::atomic::Ordering::Relaxed) { "flex" } else { "none" };
+
+            let extensions_js = r#"
+                function invoke(cmd, args) {
+                    return window.__TAURI__.core.invoke(cmd, args);
+                }
+                function installFromPath() {
+                    const path = document.getElementById('ext-path').value.trim();
+                    if (!path) return;
+                    invoke('install_extension_from_path', { path }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+                function installFromUrl() {
+                    const url = document.getElementById('ext-url').value.trim();
+                    if (!url) return;
+                    const button = document.getElementById('install-url');
+                    button.disabled = true;
+                    invoke('install_extension_from_crx', { url }).then(() => window.location.reload())
+                        .catch(e => { alert(e.message || e); button.disabled = false; });
+                }
+                function setEnabled(id, enabled) {
+                    invoke('set_extension_enabled', { id, enabled }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+                function removeExtension(id) {
+                    if (!confirm('Remove this extension?')) return;
+                    invoke('remove_extension', { id }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+                function restartApp() {
+                    invoke('restart_app');
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Extensions - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                    <style>
+                        .toolbar {{ display: flex; gap: 10px; margin-bottom: 20px; }}
+                        .toolbar input[type="text"] {{ flex: 1; padding: 8px 12px; background: var(--card); color: var(--text); border: 1px solid var(--border); border-radius: 6px; }}
+                        .restart {{ display: {}; align-items: center; justify-content: space-between; padding: 12px 16px; margin-bottom: 20px; border-radius: 8px; background: #FFAB40; color: black; }}
+                    </style>
+                </head>
+                <body>
+                    <h1>Extensions</h1>
+                    <div class="restart">
+                        <span>Extension changes take effect after Lumina restarts.</span>
+                        <button data-onclick="restartApp">Restart now</button>
+                    </div>
+                    <div class="toolbar">
+                        <input type="text" id="ext-path" placeholder="Unpacked extension folder, .crx or .zip path">
+                        <button data-onclick="installFromPath">Install</button>
+                    </div>
+                    <div class="toolbar">
+                        <input type="text" id="ext-url" placeholder="https://example.com/extension.crx">
+                        <button id="install-url" data-onclick="installFromUrl">Install from URL</button>
+                    </div>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, restart_display, items_html, extensions_js
+            ))
+        },
+        "fixups" => {
+            let fixups = list_site_fixups(app.clone());
+
+            let mut items_html = String::new();
+            for fixup in &fixups {
+                items_html.push_str(&format!(
+                    r#"<div class="item" data-id="{id}" style="{dim}">
+                        <div class="icon" style="font-size: 24px; width: 40px; text-align: center;">🩹</div>
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{name} <span class="meta">{source}</span></div>
+                            <div class="meta">{description}</div>
+                            <div class="meta">{domains}</div>
+                        </div>
+                        <div class="actions">
+                            <button data-onclick="setEnabled" data-args="{toggle_args}">{toggle}</button>
+                        </div>
+                    </div>"#,
+                    dim = if fixup.enabled { "" } else { "opacity: 0.5;" },
+                    toggle_args = handler_args(&(&fixup.id, !fixup.enabled)),
+                    toggle = if fixup.enabled { "Disable" } else { "Enable" },
+                    id = escape_html(&fixup.id),
+                    name = escape_html(&fixup.name),
+                    source = escape_html(&fixup.source),
+                    description = escape_html(&fixup.description),
+                    domains = escape_html(&fixup.domains.join(", "))
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No site fixups installed</div>"#.to_string();
+            }
+
+            let fixups_js = r#"
+                function setEnabled(id, enabled) {
+                    window.__TAURI__.core.invoke('set_site_fixup_enabled', { id, enabled }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Site Fixups - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1>Site Fixups</h1>
+                    <p class="meta">Small compatibility scripts injected only on the sites they target. Changes apply to newly opened tabs.</p>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, items_html, fixups_js
+            ))
+        },
+        "apps" => {
+            let pwas = app.state::<AppDataStore>().installed_pwas();
+
+            let mut items_html = String::new();
+            for pwa in &pwas {
+                let clear = if pwa.isolated {
+                    r#"<button data-onclick="clearData">Clear app data</button>"#
+                } else {
+                    ""
+                };
+                items_html.push_str(&format!(
+                    r#"<div class="item" data-id="{id}" data-title="{title}">
+                        <div class="icon" style="font-size: 24px; width: 40px; text-align: center;">📱</div>
+                        <div class="info" style="flex: 1;">
+                            <div class="filename">{title}</div>
+                            <div class="meta">{url}</div>
+                            <div class="meta">{profile}</div>
+                            <label class="meta"><input type="checkbox" {notifications} data-onchange="setCapability" data-args='["set_pwa_notifications"]'> Notifications</label>
+                            <label class="meta"><input type="checkbox" {service_workers} data-onchange="setCapability" data-args='["set_pwa_service_workers"]'> Service workers (offline mode, background sync; applies when the app next opens)</label>
+                        </div>
+                        <div class="actions">{clear}</div>
+                    </div>"#,
+                    id = escape_html(&pwa.id),
+                    title = escape_html(&pwa.title),
+                    url = escape_html(&pwa.start_url),
+                    profile = if pwa.isolated { "Own sign-in (separate cookies and storage)" } else { "Shares the browser profile" },
+                    notifications = if pwa.notifications { "checked" } else { "" },
+                    service_workers = if pwa.service_workers { "checked" } else { "" },
+                    clear = clear
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No apps installed</div>"#.to_string();
+            }
+
+            let apps_js = r#"
+                function setCapability(command, input) {
+                    const id = input.closest('.item').dataset.id;
+                    window.__TAURI__.core.invoke(command, { id, enabled: input.checked }).catch(e => {
+                        input.checked = !input.checked;
+                        alert(e.message || e);
+                    });
+                }
+                function clearData(button) {
+                    const item = button.closest('.item');
+                    if (!confirm('Sign out of ' + item.dataset.title + ' and delete its cookies, storage and cache? Its window will be closed.')) return;
+                    window.__TAURI__.core.invoke('clear_pwa_data', { id: item.dataset.id }).then(() => alert('App data cleared')).catch(e => alert(e.message || e));
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Installed Apps - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1>Installed Apps</h1>
+                    <p class="meta">Apps installed with their own sign-in keep their data apart from the browser and from each other.</p>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, items_html, apps_js
+            ))
+        },
+        "notes" => {
+            let notes = app.state::<HistoryManager>().get_site_notes().unwrap_or_default();
+
+            let mut items_html = String::new();
+            for note in &notes {
+                let updated = chrono::DateTime::from_timestamp(note.updated_at, 0)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                items_html.push_str(&format!(
+                    r#"<div class="item" id="{domain}" data-domain="{domain}" style="flex-direction: column; align-items: stretch;">
+                        <div style="display: flex; align-items: center; gap: 12px;">
+                            <div class="info" style="flex: 1;">
+                                <div class="filename">{domain}</div>
+                                <div class="meta">Edited {updated}</div>
+                            </div>
+                            <div class="actions">
+                                <button data-onclick="saveNote">Save</button>
+                                <button data-onclick="deleteNote">Delete</button>
+                            </div>
+                        </div>
+                        <textarea rows="6" style="width: 100%; margin-top: 8px; box-sizing: border-box; font-family: monospace;">{content}</textarea>
+                    </div>"#,
+                    domain = escape_html(&note.domain),
+                    updated = updated,
+                    content = escape_html(&note.content)
+                ));
+            }
+
+            if items_html.is_empty() {
+                items_html = r#"<div class="empty-state">No site notes yet</div>"#.to_string();
+            }
+
+            let notes_js = r#"
+                function save(domain, markdown) {
+                    return window.__TAURI__.core.invoke('save_site_note', { domain, markdown }).then(() => window.location.reload()).catch(e => alert(e.message || e));
+                }
+                function saveNote(button) {
+                    const item = button.closest('.item');
+                    save(item.dataset.domain, item.querySelector('textarea').value);
+                }
+                function deleteNote(button) {
+                    const item = button.closest('.item');
+                    if (!confirm('Delete the note for ' + item.dataset.domain + '?')) return;
+                    save(item.dataset.domain, '');
+                }
+                function addNote(form, event) {
+                    event.preventDefault();
+                    const domain = document.getElementById('new-domain').value;
+                    const markdown = document.getElementById('new-content').value;
+                    if (domain.trim() && markdown.trim()) save(domain, markdown);
+                }
+                // lumina://notes#example.com opens that site's note, or starts one
+                const target = decodeURIComponent(window.location.hash.slice(1));
+                if (target) {
+                    const item = document.getElementById(target);
+                    if (item) {
+                        item.scrollIntoView();
+                        item.querySelector('textarea').focus();
+                    } else {
+                        document.getElementById('new-domain').value = target;
+                        document.getElementById('new-content').focus();
+                    }
+                }
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Site Notes - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1>Site Notes</h1>
+                    <p class="meta">One Markdown note per site. The address bar shows a note button when you visit a site that has one.</p>
+                    <form class="item" data-onsubmit="addNote" style="flex-direction: column; align-items: stretch; gap: 8px;">
+                        <input id="new-domain" placeholder="example.com">
+                        <textarea id="new-content" rows="4" placeholder="Write a note in Markdown" style="font-family: monospace;"></textarea>
+                        <div><button type="submit">Add note</button></div>
+                    </form>
+                    <div id="list">{}</div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, items_html, notes_js
+            ))
+        },
+        "ask" => {
+            let ask_js = r#"
+                const params = new URLSearchParams(window.location.search);
+                const question = params.get('q');
+                const summarize = params.get('summarize');
+                const invoke = (cmd, args) => window.__TAURI__.core.invoke(cmd, args || {});
+                const status = document.getElementById('status');
+                const answer = document.getElementById('answer');
+                let asked = false;
+
+                function mb(bytes) { return (bytes / 1048576).toFixed(0) + ' MB'; }
+
+                function ask() {
+                    asked = true;
+                    status.textContent = 'Thinking...';
+                    const request = summarize
+                        ? invoke('summarize_page', { label: summarize })
+                        : invoke('ask_local_ai', { prompt: question, context: null });
+                    request
+                        .then(text => { status.textContent = ''; answer.textContent = text; })
+                        .catch(e => { status.textContent = e.message || e; });
+                }
+
+                function refresh() {
+                    invoke('get_local_ai_status').then(s => {
+                        const download = document.getElementById('download');
+                        download.style.display = s.state === 'missing-model' ? '' : 'none';
+                        document.getElementById('cancel').style.display = s.state === 'downloading' ? '' : 'none';
+                        if (s.state === 'not-installed') {
+                            status.textContent = 'Install Local Brain from the Lumina Store to ask questions on this device.';
+                        } else if (s.state === 'missing-model') {
+                            status.textContent = s.message || 'The model has not been downloaded yet.';
+                        } else if (s.state === 'downloading') {
+                            status.textContent = 'Downloading model: ' + mb(s.downloaded) + (s.total ? ' of ' + mb(s.total) : '');
+                        } else if (!asked) {
+                            ask();
+                            return;
+                        }
+                        if (!asked) setTimeout(refresh, 1000);
+                    });
+                }
+
+                document.getElementById('title').textContent = summarize ? 'Page Summary' : (question || 'Local AI');
+                document.getElementById('download').onclick = () => invoke('download_local_ai_model').then(refresh);
+                document.getElementById('cancel').onclick = () => invoke('cancel_local_ai_download');
+                if (summarize || question) refresh(); else status.textContent = 'Type ? and a question in the address bar.';
+            "#;
+
+            Some(format!(
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Local AI - Lumina</title>
+                    <meta charset="UTF-8">
+                    {}
+                </head>
+                <body>
+                    <h1 id="title">Local AI</h1>
+                    <p class="meta">Answered by Local Brain on this device. Nothing is sent over the network.</p>
+                    <div class="item" style="flex-direction: column; align-items: stretch; gap: 8px;">
+                        <div id="status" class="meta"></div>
+                        <div id="answer" style="white-space: pre-wrap; line-height: 1.5;"></div>
+                        <div>
+                            <button id="download" style="display: none;">Download model</button>
+                            <button id="cancel" style="display: none;">Cancel download</button>
+                        </div>
+                    </div>
+                    <script>{}</script>
+                </body>
+                </html>"#,
+                lumina_style, ask_js
+            ))
+        },
+        "network" => {
+            Some(r#"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>Network Manager</title>
+                    <meta charset="UTF-8">
+                    <style>
+                        body { font-family: system-ui, -apple-system, sans-serif; padding: 40px; background: var(--bg); color: var(--text); max-width: 800px; margin: 0 auto; }
+                        h1 { border-bottom: 1px solid var(--border); padding-bottom: 20px; margin-bottom: 30px; font-weight: 600; }
+                        .card { background: var(--card); padding: 25px; margin-bottom: 20px; border-radius: 12px; box-shadow: 0 1px 3px rgba(0,0,0,0.1); }
+                        h2 { margin-top: 0; font-size: 1.2em; color: var(--text-strong); border-bottom: 1px solid var(--border); padding-bottom: 10px; margin-bottom: 15px; }
+                        .status-item { display: flex; justify-content: space-between; padding: 10px 0; border-bottom: 1px solid var(--border); }
+                        .status-item:last-child { border-bottom: none; }
+                        .label { font-weight: 500; color: var(--text-dim); }
+                        .value { font-family: monospace; color: var(--text-strong); }
+                        .form-row { display: flex; gap: 10px; align-items: flex-end; }
+                        .input-group { flex: 1; }
+                        label { display: block; margin-bottom: 5px; font-size: 0.9em; font-weight: 500; color: var(--text); }
+                        input, select { width: 100%; padding: 8px 12px; background: var(--surface); color: var(--text); border: 1px solid var(--border); border-radius: 6px; box-sizing: border-box; }
+                        button { padding: 9px 16px; background: var(--primary); color: white; border: none; border-radius: 6px; cursor: pointer; font-weight: 500; transition: background 0.2s; }
+                        button:hover { filter: brightness(0.9); }
+                        button.secondary { background: var(--card); border: 1px solid var(--border); color: var(--text); }
+                        button.secondary:hover { background: var(--surface); }
+                        button.danger { background: #dc2626; color: white; border: none; }
+                        button.danger:hover { background: #b91c1c; }
+                        #server-list { margin-top: 10px; }
+                        .empty-list { color: var(--text-dim); font-style: italic; padding: 10px 0; }
+                    </style>
+                </head>
+                <body>
+                    <h1>Network Manager</h1>
+                    
+                    <div class="card">
+                        <h2>Sidecar Status</h2>
+                        <div id="status-display">
+                            <div class="status-item">
+                                <span class="label">Status</span>
+                                <span class="value" id="connection-status">Checking...</span>
+                            </div>
+                            <div class="status-item">
+                                <span class="label">Active Servers</span>
+                                <span class="value" id="active-count">0</span>
+                            </div>
+                        </div>
+                    </div>
+
+                    <div class="card">
+                        <h2>Active Servers</h2>
+                        <div id="server-list">
+                            <div class="empty-list">No active servers</div>
+                        </div>
+                    </div>
+
+                    <div class="card">
+                        <h2>Start New Server</h2>
+                        <div class="form-row">
+                            <div class="input-group">
+                                <label>Port</label>
+                                <input type="number" id="port-input" value="8080" min="1" max="65535">
+                            </div>
+                            <div class="input-group">
+                                <label>Type</label>
+                                <select id="type-input">
+                                    <option value="tcp">TCP</option>
+                                </select>
+                            </div>
+                            <button data-onclick="startServer">Start Server</button>
+                        </div>
+                    </div>
+
+                    <script>
+                        async function invokeNet(command, payload = {}) {
+                            try {
+                                const res = await window.__TAURI__.core.invoke('run_networking_command', { 
+                                    command: command, 
+                                    payload: JSON.stringify(payload) 
+                                });
+                                return JSON.parse(res);
+                            } catch (e) {
+                                console.error("Network Error:", e);
+                                return { status: "error", message: e };
+                            }
+                        }
+
+                        async function refreshStatus() {
+                            const res = await invokeNet('status');
+                            if (res.status === 'ok') {
+                                document.getElementById('connection-status').textContent = 'Connected';
+                                document.getElementById('connection-status').style.color = '#10b981';
+                                
+                                const servers = res.data.active_servers || [];
+                                document.getElementById('active-count').textContent = servers.length;
+                                
+                                const list = document.getElementById('server-list');
+                                if (servers.length === 0) {
+                                    list.innerHTML = '<div class="empty-list">No active servers</div>';
                                 } else {
-                                    list.innerHTML = servers.map(addr => `
-                                        <div class="status-item">
-                                            <span class="value">${addr}</span>
-                                            <button class="secondary danger" style="padding: 4px 8px; font-size: 0.8em;" onclick="stopServer('${addr}')">Stop</button>
-                                        </div>
-                                    `).join('');
+                                    list.replaceChildren(...servers.map(addr => {
+                                        const item = document.createElement('div');
+                                        item.className = 'status-item';
+                                        const value = document.createElement('span');
+                                        value.className = 'value';
+                                        value.textContent = addr;
+                                        const stop = document.createElement('button');
+                                        stop.className = 'secondary danger';
+                                        stop.style.cssText = 'padding: 4px 8px; font-size: 0.8em;';
+                                        stop.textContent = 'Stop';
+                                        stop.dataset.onclick = 'stopServer';
+                                        stop.dataset.args = JSON.stringify([addr]);
+                                        item.append(value, stop);
+                                        return item;
+                                    }));
                                 }
                             } else {
                                 document.getElementById('connection-status').textContent = 'Error';
@@ -1029,6 +3863,119 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                 </html>"#.to_string()
             )
         },
+        p if p.starts_with("store/") => {
+            let id = urlencoding::decode(&p["store/".len()..]).map(|d| d.into_owned()).unwrap_or_default();
+            let item = get_store_items(app.clone()).into_iter().find(|item| item.id == id)?;
+
+            let verified = if item.verification_failed {
+                r#"<div class="badge-failed">⚠ Verification failed</div>"#
+            } else if item.verified {
+                r#"<div class="badge-verified">✓ Verified</div>"#
+            } else {
+                ""
+            };
+            let tags: String = item.tags.iter()
+                .map(|tag| format!(r#"<span class="tag">{}</span>"#, escape_html(tag)))
+                .collect();
+
+            let mut actions = String::new();
+            if item.coming_soon {
+                actions.push_str(r#"<a class="btn disabled">Coming Soon</a>"#);
+            } else if item.installed {
+                if item.update_available() && item.install_blocker().is_none() {
+                    actions.push_str(&format!(r#"<a href="{}" class="btn">Update to v{}</a>"#, escape_html(&store_install_url(&item.id)), escape_html(&item.version)));
+                } else {
+                    actions.push_str(r#"<a class="btn installed">Installed</a>"#);
+                }
+                actions.push_str(&format!(r#"<a href="{}" class="btn danger">Uninstall</a>"#, escape_html(&store_uninstall_url(&item.id))));
+            } else if let Some(reason) = item.install_blocker() {
+                actions.push_str(&format!(r#"<a class="btn disabled" title="{}">Unavailable</a>"#, escape_html(reason)));
+            } else {
+                actions.push_str(&format!(r#"<a href="{}" class="btn">Install v{}</a>"#, escape_html(&store_install_url(&item.id)), escape_html(&item.version)));
+            }
+
+            let screenshots_html = if item.screenshots.is_empty() {
+                String::new()
+            } else {
+                let images: String = item.screenshots.iter()
+                    .map(|src| format!(r#"<img src="{}" alt="Screenshot" loading="lazy">"#, escape_html(src)))
+                    .collect();
+                format!(r#"<div class="section"><h2>Screenshots</h2><div class="screenshots">{}</div></div>"#, images)
+            };
+
+            // Documents not fetched yet are filled in by the page once it is shown, with the description
+            // standing in for the README meanwhile
+            let cached_readme = item.readme_url.as_deref().and_then(cached_store_doc);
+            let readme_html = format!(
+                r#"<pre class="doc"{}>{}</pre>"#,
+                if item.readme_url.is_some() && cached_readme.is_none() { r#" data-doc="readme""# } else { "" },
+                escape_html(cached_readme.as_deref().unwrap_or(&item.description))
+            );
+
+            let mut versions_html: String = item.versions.iter()
+                .map(|v| format!(
+                    r#"<div class="version"><strong>v{}</strong> <span class="author">{}</span><div class="desc" style="margin: 6px 0 0 0;">{}</div></div>"#,
+                    escape_html(&v.version), escape_html(&v.date), escape_html(&v.notes)
+                ))
+                .collect();
+            match item.changelog_url.as_deref().map(cached_store_doc) {
+                Some(Some(changelog)) => versions_html.push_str(&format!(r#"<pre class="doc">{}</pre>"#, escape_html(&changelog))),
+                Some(None) => versions_html.push_str(r#"<pre class="doc" data-doc="changelog" hidden></pre>"#),
+                None => {}
+            }
+            if versions_html.is_empty() {
+                versions_html = format!(r#"<div class="version"><strong>v{}</strong></div>"#, escape_html(&item.version));
+            }
+
+            Some(format!(
+                r##"<!DOCTYPE html>
+                <html>
+                <head>
+                    <title>{title} - Lumina Store</title>
+                    <meta charset="UTF-8">
+                    <style>{css}</style>
+                </head>
+                <body>
+                    <div class="container">
+                        <a href="lumina-app://store" class="back">← Back to Store</a>
+                        <div class="detail-header">
+                            <div class="icon">{icon}</div>
+                            <div>
+                                <h1>{title}</h1>
+                                <div class="author">by {author} • v{version}</div>
+                            </div>
+                            {verified}
+                        </div>
+                        <div class="meta">{tags}</div>
+                        <div class="detail-actions">{actions}</div>
+                        {screenshots}
+                        <div class="section"><h2>About</h2>{readme}</div>
+                        <div class="section"><h2>Version History</h2>{versions}</div>
+                    </div>
+                    <script>
+                        const id = {id_js};
+                        document.querySelectorAll('[data-doc]').forEach(el => {{
+                            window.__TAURI__.core.invoke('get_store_doc', {{ id, kind: el.dataset.doc }})
+                                .then(text => {{ if (text) {{ el.textContent = text; el.hidden = false; }} }})
+                                .catch(e => console.warn('Lumina Store: ' + el.dataset.doc + ' not loaded:', e));
+                        }});
+                    </script>
+                </body>
+                </html>"##,
+                css = STORE_CSS,
+                title = escape_html(&item.title),
+                icon = escape_html(&item.icon),
+                author = escape_html(&item.author),
+                version = escape_html(&item.version),
+                verified = verified,
+                tags = tags,
+                actions = actions,
+                screenshots = screenshots_html,
+                readme = readme_html,
+                versions = versions_html,
+                id_js = to_script_json(&item.id)
+            ))
+        },
         _ => Some(format!(
             r#"<!DOCTYPE html>
             <html>
@@ -1036,9 +3983,9 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
                 <title>404 Not Found</title>
                 <meta charset="UTF-8">
                 <style>
-                    body {{ font-family: system-ui, -apple-system, sans-serif; height: 100vh; display: flex; align-items: center; justify-content: center; background: #f9fafb; color: #374151; margin: 0; }}
+                    body {{ font-family: system-ui, -apple-system, sans-serif; height: 100vh; display: flex; align-items: center; justify-content: center; background: var(--bg); color: var(--text); margin: 0; }}
                     .container {{ text-align: center; }}
-                    h1 {{ font-size: 4em; margin: 0; color: #1f2937; }}
+                    h1 {{ font-size: 4em; margin: 0; color: var(--text-strong); }}
                     p {{ font-size: 1.2em; margin-top: 10px; }}
                 </style>
             </head>
@@ -1055,121 +4002,94 @@ fn get_internal_page_html(app: &AppHandle, path: &str) -> Option<String> {
 }
 
 #[tauri::command]
-fn force_internal_navigate(app: AppHandle, label: String, mut url: String) {
-    println!("Rust: force_internal_navigate tab {} to {}", label, url);
-
-    // Standardize URL to ensure same-origin (lumina-app://localhost/)
-    if url.starts_with("lumina://") {
-        url = url.replace("lumina://", "lumina-app://localhost/");
-    } else if url.starts_with("lumina-app://") {
-         let scheme = "lumina-app://";
-         if let Some(rest) = url.strip_prefix(scheme) {
-             if !rest.starts_with("localhost/") && rest != "localhost" {
-                 // Convert lumina-app://page to lumina-app://localhost/page
-                 url = format!("{}localhost/{}", scheme, rest);
-             }
-         }
-    }
-
-    if let Some(webview) = app.get_webview(&label) {
-         let _ = webview.set_focus();
-         
-         // Check if it's an internal page
-         let mut is_internal = false;
-         let mut internal_html = None;
-
-         if url.starts_with("lumina-app://") || url.starts_with("lumina://") {
-            let scheme_strip = if url.starts_with("lumina-app:") { "lumina-app:" } else { "lumina:" };
-            let without_scheme = url.strip_prefix(scheme_strip).unwrap_or(&url);
-            let without_slashes = without_scheme.trim_start_matches('/');
-            let path_and_query = without_slashes.strip_prefix("localhost").unwrap_or(without_slashes);
-            let full_path = path_and_query.trim_start_matches('/');
-            
-            // Split path and query/hash
-            let (path, _query) = if let Some(idx) = full_path.find('?') {
-                (&full_path[..idx], &full_path[idx..])
-            } else if let Some(idx) = full_path.find('#') {
-                 (&full_path[..idx], &full_path[idx..])
-            } else {
-                (full_path, "")
-            };
-            
-            let path = path.trim_end_matches('/');
-            
-            if let Some(html) = get_internal_page_html(&app, path) {
-                is_internal = true;
-                internal_html = Some(html);
-            }
-         }
-
-         if is_internal {
-             if let Some(html) = internal_html {
-                 // Escape HTML for JS string
-                 let escaped_html = html.replace("\\", "\\\\").replace("'", "\\'").replace("\n", "\\n").replace("\r", "");
-                 let js = format!(
-                     "window.stop(); document.open(); document.write('{}'); document.close(); try {{ history.pushState(null, '', '{}'); }} catch(e) {{ console.warn('PushState failed (likely origin mismatch), but content loaded:', e); }}", 
-                     escaped_html, url
-                 );
-                 let _ = webview.eval(&js);
-             }
-         } else {
-             // Fallback for external URLs or if parsing failed
-             let _ = webview.eval(format!("window.location.replace('{}')", url).as_str());
-         }
+fn add_history_item(app: AppHandle, webview: tauri::Webview, state: tauri::State<'_, AppDataStore>, history_manager: tauri::State<'_, HistoryManager>, url: String, title: String) -> Result<(), LuminaError> {
+    // A tab may only record the web page it is showing
+    if !is_browser_window(webview.label()) {
+        let parsed = url::Url::parse(&url).map_err(|e| LuminaError::InvalidInput(e.to_string()))?;
+        if !matches!(parsed.scheme(), "http" | "https") || webview.url().ok() != Some(parsed) {
+            return Err(LuminaError::PermissionDenied(format!("'{}' is not the page this tab is showing", url)));
+        }
     }
+    if state.is_history_excluded(&url) {
+        return Ok(());
+    }
+    // Legacy JSON store (optional, maybe keep for backup or remove later)
+    state.add_history(url.clone(), title.clone());
+    state.try_save()?;
+
+    // SQLite Store
+    history_manager.add_visit(url, title)?;
+    refresh_tray_menu(&app);
+    Ok(())
 }
 
 #[tauri::command]
-fn go_back(app: AppHandle, label: String) {
-    if let Some(webview) = app.get_webview(&label) {
-        let _ = webview.eval("window.history.back()");
-    }
+fn update_history_title(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, label: String, url: String, title: String) -> Result<(), LuminaError> {
+    history_manager.update_title(url, title.clone())?;
+    // Also emit tab-updated so UI reflects the real title
+    app.state::<TabRegistry>().set_title(&label, &title);
+    events::emit(&app, AppEvent::TabUpdated(TabUpdatedPayload { label, title: Some(title), favicon: None, pinned: None }));
+    Ok(())
 }
 
 #[tauri::command]
-fn go_forward(app: AppHandle, label: String) {
-    if let Some(webview) = app.get_webview(&label) {
-        let _ = webview.eval("window.history.forward()");
-    }
+fn get_history_page(history_manager: tauri::State<'_, HistoryManager>, page: i64) -> HistoryPage {
+    load_history_page(&history_manager, page)
 }
 
 #[tauri::command]
-fn refresh(app: AppHandle, label: String) {
-    if let Some(webview) = app.get_webview(&label) {
-        let _ = webview.reload();
-    }
+fn delete_history_entry(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, url: String) -> Result<(), LuminaError> {
+    let removed = history_manager.take_entry(&url)?;
+    record_history_undo(&app, removed);
+    Ok(())
 }
 
 #[tauri::command]
-fn add_history_item(state: tauri::State<'_, AppDataStore>, history_manager: tauri::State<'_, HistoryManager>, url: String, title: String) {
-    // Legacy JSON store (optional, maybe keep for backup or remove later)
-    state.add_history(url.clone(), title.clone());
-    state.save();
-
-    // SQLite Store
-    if let Err(e) = history_manager.add_visit(url, title) {
-        eprintln!("Failed to add history item: {}", e);
-    }
+fn delete_history_entry_by_id(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, id: String) -> Result<(), LuminaError> {
+    let removed = history_manager.take_entry_by_id(&id)?;
+    record_history_undo(&app, removed);
+    Ok(())
 }
 
 #[tauri::command]
-fn update_history_title(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, label: String, url: String, title: String) {
-    if let Err(e) = history_manager.update_title(url, title.clone()) {
-         eprintln!("Failed to update history title: {}", e);
-    }
-    // Also emit tab-updated so UI reflects the real title
-    let _ = app.emit("tab-updated", TabUpdatedPayload { label, title: Some(title), favicon: None });
+fn delete_history_range(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, start: i64, end: i64) -> Result<usize, LuminaError> {
+    let removed = history_manager.take_range(start, end)?;
+    let count = removed.len();
+    record_history_undo(&app, removed);
+    Ok(count)
+}
+
+fn record_history_undo(app: &AppHandle, removed: Vec<history_manager::HistoryItem>) {
+    let message = match removed.len() {
+        0 => return,
+        1 => "Geçmiş kaydı silindi".to_string(),
+        n => format!("{} geçmiş kaydı silindi", n),
+    };
+    undo::record(app, message, undo::Tombstone::History(removed));
 }
 
 #[tauri::command]
 fn search_history(history_manager: tauri::State<'_, HistoryManager>, data_store: tauri::State<'_, AppDataStore>, query: String) -> Vec<history_manager::HistoryItem> {
-    if query.starts_with("@b") {
+    // A bookmark keyword wins over everything else, then an alias
+    let keyword_match = data_store.expand_keyword(&query)
+        .map(|url| (format!("Keyword: {}", query.trim()), url))
+        .or_else(|| aliases::expand(&history_manager, &query).map(|url| (format!("Alias: {}", query.trim()), url)))
+        .map(|(title, url)| history_manager::HistoryItem {
+            id: String::new(),
+            title,
+            url,
+            visit_count: 1000,
+            last_visit: chrono::Utc::now().timestamp(),
+        });
+
+    let mut results = if query.starts_with("@b") {
         // Search Bookmarks (Favorites)
         let q = query.replace("@b", "").trim().to_lowercase();
         let favorites = data_store.data.lock().unwrap().favorites.clone();
         favorites.into_iter()
             .filter(|f| f.url.to_lowercase().contains(&q) || f.title.to_lowercase().contains(&q))
             .map(|f| history_manager::HistoryItem {
+                id: f.id,
                 url: f.url,
                 title: f.title,
                 visit_count: 100, // Boost favorites
@@ -1191,7 +4111,54 @@ fn search_history(history_manager: tauri::State<'_, HistoryManager>, data_store:
                 Vec::new()
             }
         }
+    };
+
+    // Visits from before a site was excluded stay out of suggestions
+    results.retain(|item| !data_store.is_history_excluded(&item.url));
+    if let Some(item) = keyword_match {
+        results.insert(0, item);
+    }
+    results
+}
+
+/// Keeps a site out of history, or lets it back in. Plain domains (scheme and "www." are
+/// dropped) cover their subdomains; patterns with `*` match the host as a whole.
+#[tauri::command]
+fn set_history_excluded(state: tauri::State<'_, AppDataStore>, pattern: String, excluded: bool) -> Result<(), LuminaError> {
+    let pattern = if pattern.contains('*') { pattern.trim().to_lowercase() } else { normalize_site_domain(&pattern) };
+    if pattern.is_empty() || pattern.chars().all(|c| c == '*' || c == '.') {
+        return Err(LuminaError::InvalidInput("Enter a domain like bank.com or *.bank.com".to_string()));
+    }
+    if !pattern.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '*')) {
+        return Err(LuminaError::InvalidInput(format!("Invalid domain pattern: {}", pattern)));
+    }
+    {
+        let mut data = state.data.lock().unwrap();
+        let sites = &mut data.settings.history_excluded_sites;
+        sites.retain(|p| p != &pattern);
+        if excluded {
+            sites.push(pattern);
+            sites.sort();
+        }
+    }
+    state.save();
+    Ok(())
+}
+
+/// Expands a bookmark keyword or alias typed in the omnibox, or returns None if the first word is
+/// neither. Keywords win when both match.
+#[tauri::command]
+fn resolve_keyword(data_store: tauri::State<'_, AppDataStore>, history_manager: tauri::State<'_, HistoryManager>, input: String) -> Option<String> {
+    data_store.expand_keyword(&input).or_else(|| aliases::expand(&history_manager, &input))
+}
+
+#[tauri::command]
+fn set_favorite_keyword(state: tauri::State<'_, AppDataStore>, url: String, keyword: Option<String>) -> Result<(), LuminaError> {
+    if !state.set_favorite_keyword(&url, keyword) {
+        return Err(LuminaError::NotFound("Favorite not found".to_string()));
     }
+    state.save();
+    Ok(())
 }
 
 #[tauri::command]
@@ -1211,15 +4178,107 @@ fn get_recent_history(history_manager: tauri::State<'_, HistoryManager>) -> Vec<
 }
 
 #[tauri::command]
-fn add_favorite(state: tauri::State<'_, AppDataStore>, url: String, title: String) {
+fn add_favorite(state: tauri::State<'_, AppDataStore>, url: String, title: String) -> Result<(), LuminaError> {
     state.add_favorite(url, title);
+    state.try_save()?;
+    Ok(())
+}
+
+#[tauri::command]
+fn is_favorite(state: tauri::State<'_, AppDataStore>, url: String) -> bool {
+    state.is_favorite(&url)
+}
+
+#[tauri::command]
+fn remove_favorite(app: AppHandle, state: tauri::State<'_, AppDataStore>, url: String) -> Result<(), LuminaError> {
+    if let Some(removed) = state.remove_favorite(url) {
+        state.try_save()?;
+        undo::record(&app, "Favori kaldırıldı".to_string(), undo::Tombstone::Favorites(vec![removed]));
+    }
+    Ok(())
+}
+
+/// How many favorites are checked at once by `check_favorites_health`
+const FAVORITE_CHECK_CONCURRENCY: usize = 6;
+/// A favorite that takes longer than this to answer counts as broken
+const FAVORITE_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FavoritesHealthSummary {
+    checked: usize,
+    broken: usize,
+    redirected: usize,
+}
+
+async fn check_favorite(client: &reqwest::Client, url: &str) -> FavoriteHealth {
+    let mut response = client.head(url).timeout(FAVORITE_CHECK_TIMEOUT).send().await;
+    // Some servers reject HEAD outright
+    if let Ok(res) = &response {
+        if matches!(res.status(), reqwest::StatusCode::METHOD_NOT_ALLOWED | reqwest::StatusCode::NOT_IMPLEMENTED) {
+            response = client.get(url).timeout(FAVORITE_CHECK_TIMEOUT).send().await;
+        }
+    }
+
+    let (status, redirect) = match response {
+        Ok(res) => {
+            let final_url = res.url().to_string();
+            let moved = Url::parse(url).map(|u| u.as_str() != final_url).unwrap_or(true);
+            (res.status().as_u16(), moved.then_some(final_url))
+        }
+        Err(_) => (0, None),
+    };
+    FavoriteHealth { status, redirect, checked_at: chrono::Utc::now().timestamp() }
+}
+
+/// HEAD-requests every favorite (a few at a time) and stores status codes and redirect targets,
+/// which the favorites page shows as badges. Emits "favorites-health-checked" when done.
+#[tauri::command]
+async fn check_favorites_health(app: AppHandle) -> Result<FavoritesHealthSummary, LuminaError> {
+    let urls: Vec<String> = app.state::<AppDataStore>().data.lock().unwrap()
+        .favorites
+        .iter()
+        .filter(|f| f.url.starts_with("http") && !f.url.contains("%s"))
+        .map(|f| f.url.clone())
+        .collect();
+
+    let client = http::client(&app, http::Purpose::Page)?;
+
+    let results: Vec<(String, FavoriteHealth)> = futures_util::stream::iter(urls)
+        .map(|url| {
+            let client = &client;
+            async move {
+                let health = check_favorite(client, &url).await;
+                (url, health)
+            }
+        })
+        .buffer_unordered(FAVORITE_CHECK_CONCURRENCY)
+        .collect()
+        .await;
+
+    let state = app.state::<AppDataStore>();
+    let mut summary = FavoritesHealthSummary { checked: results.len(), broken: 0, redirected: 0 };
+    for (url, health) in results {
+        if health.status == 0 || health.status >= 400 {
+            summary.broken += 1;
+        } else if health.redirect.is_some() {
+            summary.redirected += 1;
+        }
+        state.set_favorite_health(&url, health);
+    }
     state.save();
+
+    events::emit(&app, AppEvent::FavoritesHealthChecked(summary.clone()));
+    Ok(summary)
 }
 
 #[tauri::command]
-fn remove_favorite(state: tauri::State<'_, AppDataStore>, url: String) {
-    state.remove_favorite(url);
+fn update_favorite_url(state: tauri::State<'_, AppDataStore>, url: String, new_url: String) -> Result<(), LuminaError> {
+    if !state.update_favorite_url(&url, new_url) {
+        return Err(LuminaError::Other("Could not update favorite".to_string()));
+    }
     state.save();
+    Ok(())
 }
 
 #[tauri::command]
@@ -1234,797 +4293,707 @@ fn get_settings(state: tauri::State<'_, AppDataStore>) -> AppSettings {
 
 #[tauri::command]
 #[allow(clippy::too_many_arguments)]
-fn save_settings(state: tauri::State<'_, AppDataStore>, app: AppHandle, homepage: String, search_engine: String, theme: String, accent_color: String, vertical_tabs: bool, rounded_corners: bool) {
+fn save_settings(state: tauri::State<'_, AppDataStore>, app: AppHandle, homepage: String, search_engine: String, theme: String, accent_color: String, vertical_tabs: bool, rounded_corners: bool, new_tab_page: Option<String>) -> Result<(), LuminaError> {
+    let accent_changed = state.data.lock().unwrap().settings.accent_color != accent_color;
     state.update_settings(homepage, search_engine, theme, accent_color, vertical_tabs, rounded_corners);
-    state.save();
-    let _ = update_layout(app.state::<UiState>(), app.clone(), app.state::<AppDataStore>());
+    if let Some(new_tab_page) = new_tab_page {
+        state.set_new_tab_page(new_tab_page);
+    }
+    state.try_save()?;
+    if !refresh_theme(&app) && accent_changed {
+        reload_internal_pages(&app);
+    }
+    relayout_all(&app);
+    Ok(())
 }
 
-#[tauri::command]
-fn open_file(_path: String) {
-    #[cfg(target_os = "windows")]
-    {
-        let _ = std::process::Command::new("explorer")
-            .arg(_path)
-            .spawn();
+/// Zoom levels offered on the settings page
+const DEFAULT_ZOOM_CHOICES: &[i32] = &[50, 67, 75, 80, 90, 100, 110, 125, 150, 175, 200, 250];
+
+/// The settings page's zoom choices, plus the current one if it was set to something else.
+fn default_zoom_options(current: i32) -> String {
+    let mut choices = DEFAULT_ZOOM_CHOICES.to_vec();
+    if !choices.contains(&current) {
+        choices.push(current);
+        choices.sort();
     }
+    choices
+        .iter()
+        .map(|zoom| format!(r#"<option value="{zoom}" {}>{zoom}%</option>"#, if *zoom == current { "selected" } else { "" }))
+        .collect()
 }
 
+/// Open windows keep their toolbar until Lumina restarts.
 #[tauri::command]
-fn show_in_folder(path: String) {
-    #[cfg(target_os = "windows")]
-    {
-        let _ = std::process::Command::new("explorer")
-            .args(["/select,", &path])
-            .spawn();
-    }
-    #[cfg(not(target_os = "windows"))]
-    let _ = path;
+fn set_show_home_button(state: tauri::State<'_, AppDataStore>, enabled: bool) -> Result<(), LuminaError> {
+    state.data.lock().unwrap().settings.show_home_button = enabled;
+    state.try_save()?;
+    Ok(())
 }
 
-#[tauri::command]
-fn toggle_reader_mode(app: AppHandle, label: String) {
-    if let Some(webview) = app.get_webview(&label) {
-        let script = r#"
-            (function() {
-                if (window.__readerModeActive) {
-                    window.location.reload();
-                    return;
-                }
+/// How often the "scheduled" theme re-checks the clock
+const THEME_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
 
-                function findContent() {
-                    const selectors = ['article', 'main', '.content', '#content', '.post', '.entry', '.article', '#article'];
-                    for (let sel of selectors) {
-                        let el = document.querySelector(sel);
-                        if (el && el.innerText.length > 200) return el;
-                    }
-                    
-                    let divs = document.getElementsByTagName('div');
-                    let bestDiv = null;
-                    let maxP = 0;
-                    for (let div of divs) {
-                        let pCount = div.getElementsByTagName('p').length;
-                        if (pCount > maxP) {
-                            maxP = pCount;
-                            bestDiv = div;
-                        }
-                    }
-                    return bestDiv || document.body;
-                }
+#[derive(Clone, Serialize)]
+struct ThemeChangedPayload {
+    theme: String,
+}
 
-                try {
-                    const content = findContent().cloneNode(true);
-                    
-                    const style = `
-                        body {
-                            background-color: #f4ecd8 !important;
-                            color: #333 !important;
-                            font-family: Georgia, 'Times New Roman', serif !important;
-                            font-size: 20px !important;
-                            line-height: 1.6 !important;
-                            max-width: 800px !important;
-                            margin: 0 auto !important;
-                            padding: 40px 20px !important;
-                        }
-                        img { max-width: 100%; height: auto; display: block; margin: 20px auto; }
-                        a { color: #005a9c; text-decoration: underline; }
-                        h1, h2, h3, h4 { font-family: Helvetica, Arial, sans-serif; color: #111; margin-top: 1.5em; }
-                        p { margin-bottom: 1.5em; }
-                        pre, code { background: rgba(0,0,0,0.05); padding: 2px 4px; border-radius: 3px; }
-                        blockquote { border-left: 4px solid #ccc; padding-left: 16px; margin-left: 0; color: #555; font-style: italic; }
-                        
-                        /* Hide everything else */
-                        nav, header, footer, aside, .sidebar, .menu, .ad, .ads, .advertisement, iframe, .popup, .modal { display: none !important; }
-                    `;
-
-                    document.head.innerHTML = '';
-                    const styleEl = document.createElement('style');
-                    styleEl.textContent = style;
-                    document.head.appendChild(styleEl);
-
-                    document.body.innerHTML = '';
-                    document.body.appendChild(content);
-                    window.__readerModeActive = true;
-                    console.log("Reader mode activated");
-                } catch(e) {
-                    console.error("Reader mode failed:", e);
-                }
-            })();
-        "#;
-        let _ = webview.eval(script);
+fn parse_time_of_day(value: &str) -> Option<chrono::NaiveTime> {
+    chrono::NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+/// Turns the theme setting into the palette to use right now.
+fn resolve_theme(app: &AppHandle) -> &'static str {
+    let settings = app.state::<AppDataStore>().data.lock().unwrap().settings.clone();
+    match settings.theme.as_str() {
+        "light" => "light",
+        "system" => match app.get_webview_window("main").and_then(|w| w.theme().ok()) {
+            Some(tauri::Theme::Light) => "light",
+            _ => "dark",
+        },
+        "scheduled" => {
+            let (Some(light_at), Some(dark_at)) = (parse_time_of_day(&settings.theme_light_at), parse_time_of_day(&settings.theme_dark_at)) else {
+                return "dark";
+            };
+            let now = chrono::Local::now().time();
+            let is_light = if light_at <= dark_at {
+                now >= light_at && now < dark_at
+            } else {
+                now >= light_at || now < dark_at
+            };
+            if is_light { "light" } else { "dark" }
+        }
+        _ => "dark",
+    }
+}
+
+fn active_theme() -> String {
+    let theme = ACTIVE_THEME.lock().unwrap();
+    if theme.is_empty() { "dark".to_string() } else { theme.clone() }
+}
+
+/// Re-resolves the theme; on a change tells the UI and reloads open internal pages so they pick up the palette.
+/// Returns whether the pages were reloaded.
+fn refresh_theme(app: &AppHandle) -> bool {
+    let theme = resolve_theme(app);
+    {
+        let mut active = ACTIVE_THEME.lock().unwrap();
+        if *active == theme {
+            return false;
+        }
+        let first = active.is_empty();
+        *active = theme.to_string();
+        if first {
+            return false;
+        }
     }
-}
 
-fn calculate_layout(logical_size: tauri::LogicalSize<f64>, vertical_tabs: bool, menu_open: bool, suggestions_height: f64) -> (f64, f64, f64, f64, f64) {
-    let top_bar_height = 104.0 + suggestions_height;
-    let sidebar_width = 200.0;
-    let menu_width = 320.0;
-    let toolbar_height = 60.0;
+    events::emit(app, AppEvent::ThemeChanged(ThemeChangedPayload { theme: theme.to_string() }));
+    reload_internal_pages(app);
+    true
+}
 
-    if vertical_tabs {
-        let main_height = logical_size.height;
-        let x = sidebar_width;
-        let y = toolbar_height; 
-        let mut width = logical_size.width - sidebar_width;
-        if menu_open { width -= menu_width; }
-        if width < 0.0 { width = 0.0; }
-        (main_height, x, y, width, logical_size.height - toolbar_height)
-    } else {
-        let mut width = logical_size.width;
-        if menu_open { width -= menu_width; }
-        if width < 0.0 { width = 0.0; }
-        let main_height = if menu_open { logical_size.height } else { top_bar_height };
-        (main_height, 0.0, top_bar_height, width, logical_size.height - top_bar_height)
+/// Reloads every tab showing a lumina-app:// page so it re-renders with the current theme tokens.
+fn reload_internal_pages(app: &AppHandle) {
+    for (label, webview) in app.webviews() {
+        if !is_browser_window(&label) && webview.url().is_ok_and(|u| u.scheme() == "lumina-app") {
+            let _ = webview.eval("location.reload()");
+        }
     }
 }
 
 #[tauri::command]
-fn update_layout(state: tauri::State<'_, UiState>, app: AppHandle, data_store: tauri::State<'_, AppDataStore>) -> Result<(), String> {
-    println!("Rust: update_layout called");
-    let menu_open = state.sidebar_open.load(std::sync::atomic::Ordering::Relaxed);
-    let suggestions_height = state.suggestions_height.load(std::sync::atomic::Ordering::Relaxed) as f64;
-    
-    let settings = data_store.data.lock().map_err(|e| e.to_string())?;
-    let vertical_tabs = settings.settings.vertical_tabs;
-    drop(settings);
-
-    let main_window = app.get_webview_window("main").ok_or_else(|| {
-        eprintln!("Rust Critical: Main window not found in update_layout");
-        "Main window not found".to_string()
-    })?;
-    
-    let window_size = main_window.inner_size().map_err(|e| {
-        let err = e.to_string();
-        eprintln!("Rust Error: Failed to get window size: {}", err);
-        err
-    })?;
-    
-    let scale_factor = main_window.scale_factor().map_err(|e| {
-        let err = e.to_string();
-        eprintln!("Rust Error: Failed to get scale factor: {}", err);
-        err
-    })?;
-    
-    let logical_size = window_size.to_logical::<f64>(scale_factor);
-    println!("Rust: Layout calculation - Size: {:?}, Vertical: {}, Menu: {}", logical_size, vertical_tabs, menu_open);
-    
-    let (main_height, x, y, width, height) = calculate_layout(logical_size, vertical_tabs, menu_open, suggestions_height);
-    println!("Rust: Layout results - MainH: {}, x: {}, y: {}, w: {}, h: {}", main_height, x, y, width, height);
-
-    if let Some(main_webview) = app.get_webview("main") {
-        let _ = main_webview.set_auto_resize(false);
-        let _ = main_webview.set_position(tauri::LogicalPosition::new(0.0, 0.0));
-        main_webview.set_size(tauri::LogicalSize::new(logical_size.width, main_height)).map_err(|e| {
-            let err = e.to_string();
-            eprintln!("Rust Error: Failed to set main webview size: {}", err);
-            err
-        })?;
-        if menu_open { let _ = main_window.set_focus(); }
-    } else {
-        eprintln!("Rust Critical: Main webview not found in update_layout");
-    }
-    let webviews = app.webviews();
-    for webview in webviews {
-        let webview_instance = &webview.1;
-        if webview_instance.label() != "main" {
-            let _ = webview_instance.set_auto_resize(false);
-            let _ = webview_instance.set_position(tauri::LogicalPosition::new(x, y));
-            let _ = webview_instance.set_size(tauri::LogicalSize::new(width, height));
-        }
+fn get_active_theme(app: AppHandle) -> String {
+    refresh_theme(&app);
+    active_theme()
+}
+
+#[tauri::command]
+fn set_theme_schedule(app: AppHandle, state: tauri::State<'_, AppDataStore>, light_at: String, dark_at: String) -> Result<(), LuminaError> {
+    if parse_time_of_day(&light_at).is_none() || parse_time_of_day(&dark_at).is_none() {
+        return Err(LuminaError::InvalidInput("Times must be HH:MM".to_string()));
     }
+    {
+        let mut data = state.data.lock().unwrap();
+        data.settings.theme_light_at = light_at.trim().to_string();
+        data.settings.theme_dark_at = dark_at.trim().to_string();
+    }
+    state.save();
+    refresh_theme(&app);
     Ok(())
 }
 
 #[tauri::command]
-fn set_suggestions_height(state: tauri::State<'_, UiState>, app: AppHandle, data_store: tauri::State<'_, AppDataStore>, height: u32) -> Result<(), String> {
-    state.suggestions_height.store(height, std::sync::atomic::Ordering::Relaxed);
-    update_layout(state, app, data_store)
+fn save_startup_settings(state: tauri::State<'_, AppDataStore>, behavior: StartupBehavior, urls: Vec<String>) -> Result<(), LuminaError> {
+    let urls = urls.into_iter().map(|u| u.trim().to_string()).filter(|u| !u.is_empty()).collect();
+    state.update_startup(behavior, urls);
+    state.try_save()?;
+    Ok(())
 }
 
+/// Tabs the frontend should open on launch (homepage, blank tab, previous session or custom list).
 #[tauri::command]
-fn toggle_sidebar(state: tauri::State<'_, UiState>, app: AppHandle, data_store: tauri::State<'_, AppDataStore>, open: bool) -> Result<(), String> {
-    state.sidebar_open.store(open, std::sync::atomic::Ordering::Relaxed);
-    update_layout(state, app, data_store)
+fn get_startup_tabs(state: tauri::State<'_, AppDataStore>) -> Vec<String> {
+    state.startup_urls()
 }
 
-
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-struct TabNavigationPayload {
+#[derive(Clone, serde::Serialize)]
+struct WindowInfo {
     label: String,
-    url: String,
+    title: String,
+    url: String, // We might not be able to get the exact URL easily without tracking it, but we can try
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct DownloadStartedPayload {
-    url: String,
-    file_name: String,
+/// Lets a window that started late catch up on what it missed; pass the last `seq` it has seen.
+#[tauri::command]
+fn get_recent_events(webview: tauri::Webview, since: Option<u64>) -> Vec<events::RecordedEvent> {
+    events::recent(since, Some(webview.window().label()))
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct DownloadFinishedPayload {
-    url: String,
-    success: bool,
-    path: Option<String>,
+#[tauri::command]
+fn get_open_windows(app: AppHandle) -> Vec<WindowInfo> {
+    let mut windows = Vec::new();
+    for (label, window) in app.webview_windows() {
+        if label != "main" && !label.starts_with("flash-") {
+            let title = window.title().unwrap_or_else(|_| label.clone());
+            // For PWAs, we use the window URL which is typically the app URL.
+            // Note: This might be the initial URL if navigation handling is complex, 
+            // but for single-page apps/PWAs it's usually sufficient.
+            windows.push(WindowInfo {
+                label,
+                title,
+                url: window.url().map(|u| u.to_string()).unwrap_or_default(),
+            });
+        }
+    }
+    windows
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct DownloadProgressPayload {
-    url: String,
-    progress: u64,
-    total: u64,
+#[tauri::command]
+fn focus_window(app: AppHandle, label: String) -> Result<(), LuminaError> {
+    let window = app.get_webview_window(&label).ok_or_else(|| LuminaError::NotFound(format!("Window {} not found", label)))?;
+    window.set_focus()?;
+    if window.is_minimized()? {
+        window.unminimize()?;
+    }
+    Ok(())
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct TabCreatedPayload {
-    label: String,
-    url: String,
+#[tauri::command]
+async fn open_flash_window(app: AppHandle, url: String) -> Result<(), LuminaError> {
+    let label = format!("flash-{}", chrono::Utc::now().timestamp_micros());
+    let label_clone = label.clone();
+    
+    let mut builder = tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::External(url.parse().map_err(|e: url::ParseError| e.to_string())?))
+        .title("Flash Tab");
+
+    #[cfg(target_os = "windows")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0");
+    }
+    #[cfg(target_os = "linux")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
+    }
+    #[cfg(target_os = "macos")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
+    }
+
+    builder.inner_size(800.0, 600.0)
+        .decorations(false)
+        .always_on_top(true)
+        .center()
+        .focused(true)
+        .skip_taskbar(true)
+        .initialization_script(get_lumina_stealth_script())
+        .on_page_load(|window, payload| apply_site_protection(window.app_handle(), window.as_ref(), &payload))
+        .on_web_resource_request(move |request, response| {
+            handle_adblock_request(&request, response, &label_clone);
+        })
+        .build()?;
+    Ok(())
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct TabUpdatedPayload {
-    label: String,
-    title: Option<String>,
-    favicon: Option<String>,
+struct DarkModeConfig {
+    global: bool,
+    /// Domain -> forced on/off, overriding `global`
+    sites: HashMap<String, bool>,
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct TabClosedPayload {
-    label: String,
+fn dark_mode_config(app: &AppHandle) -> DarkModeConfig {
+    let history_manager = app.state::<HistoryManager>();
+    DarkModeConfig {
+        global: history_manager.get_meta("dark_mode_global").ok().flatten().as_deref() == Some("1"),
+        sites: history_manager.get_dark_mode_sites().unwrap_or_default().into_iter().collect(),
+    }
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct TabPwaPayload {
-    label: String,
-    icon_url: Option<String>,
+/// Pushes the current dark mode config into every open tab.
+fn broadcast_dark_mode(app: &AppHandle) {
+    let script = format!(
+        "window.__LUMINA_SET_DARK_MODE__ && window.__LUMINA_SET_DARK_MODE__({})",
+        to_script_json(&dark_mode_config(app))
+    );
+    for (label, webview) in app.webviews() {
+        if !is_browser_window(&label) {
+            let _ = webview.eval(&script);
+        }
+    }
 }
 
-struct PwaState {
-    icons: std::sync::Mutex<std::collections::HashMap<String, String>>,
+/// Inverts page colors for sites without a dark theme. Images, video and other media are inverted
+/// back so they look normal. With only the global toggle on, pages that are already dark are left
+/// alone; a per-domain override always wins.
+fn get_dark_mode_script(config: &DarkModeConfig) -> String {
+    format!(r#"
+    (function() {{
+        if (window.self !== window.top) return;
+        let config = {};
+        const css = `
+            html {{ filter: invert(1) hue-rotate(180deg) !important; background: #fff !important; }}
+            img, video, picture, canvas, iframe, embed, object, svg image,
+            [style*="background-image"] {{ filter: invert(1) hue-rotate(180deg) !important; }}
+        `;
+
+        function siteOverride() {{
+            const parts = location.hostname.split('.');
+            for (let i = 0; i < parts.length - 1; i++) {{
+                const domain = parts.slice(i).join('.');
+                if (domain in config.sites) return config.sites[domain];
+            }}
+            return undefined;
+        }}
+
+        function isAlreadyDark() {{
+            const parse = (el) => {{
+                const m = el && getComputedStyle(el).backgroundColor.match(/\d+(\.\d+)?/g);
+                if (!m || (m.length === 4 && parseFloat(m[3]) === 0)) return null;
+                return (0.299 * m[0] + 0.587 * m[1] + 0.114 * m[2]) / 255;
+            }};
+            const luminance = parse(document.body) ?? parse(document.documentElement);
+            return luminance !== null && luminance < 0.4;
+        }}
+
+        function apply() {{
+            const forced = siteOverride();
+            let enabled = forced !== undefined ? forced : config.global;
+            const existing = document.getElementById('lumina-dark-mode');
+            if (enabled && forced === undefined && !existing && document.body && isAlreadyDark()) enabled = false;
+
+            if (enabled && !existing) {{
+                const style = document.createElement('style');
+                style.id = 'lumina-dark-mode';
+                style.textContent = css;
+                (document.head || document.documentElement).appendChild(style);
+            }} else if (!enabled && existing) {{
+                existing.remove();
+            }}
+        }}
+
+        window.__LUMINA_SET_DARK_MODE__ = function(next) {{
+            config = next;
+            apply();
+        }};
+
+        // Decide once the page's own colors are known
+        if (document.readyState === 'loading') {{
+            document.addEventListener('DOMContentLoaded', apply);
+        }} else {{
+            apply();
+        }}
+    }})();
+    "#, to_script_json(config))
 }
 
 #[tauri::command]
-async fn pwa_detected(app: AppHandle, state: tauri::State<'_, PwaState>, label: String, icon_url: Option<String>) -> Result<(), String> {
-    if let Some(url) = &icon_url {
-        state.icons.lock().unwrap().insert(label.clone(), url.clone());
-    }
-    app.emit("pwa-can-install", TabPwaPayload { label, icon_url }).map_err(|e| e.to_string())
+fn get_dark_mode_settings(app: AppHandle) -> DarkModeConfig {
+    dark_mode_config(&app)
 }
 
 #[tauri::command]
-async fn install_pwa(app: AppHandle, state: tauri::State<'_, PwaState>, label: String) -> Result<(), String> {
-    // Get stored icon URL if available
-    let icon_url = state.icons.lock().unwrap().get(&label).cloned();
-    let icon_url_js = if let Some(u) = icon_url {
-        format!("'{}'", u)
-    } else {
-        "null".to_string()
-    };
-
-    if let Some(webview) = app.get_webview(&label) {
-        let script = format!(r#"
-            (async function() {{
-                var knownIconUrl = {};
-                if (window.deferredPrompt) {{
-                    console.log("Triggering PWA install prompt...");
-                    window.deferredPrompt.prompt();
-                    window.deferredPrompt.userChoice.then((choiceResult) => {{
-                        if (choiceResult.outcome === 'accepted') {{
-                            console.log('User accepted the install prompt');
-                        }} else {{
-                            console.log('User dismissed the install prompt');
-                        }}
-                        window.deferredPrompt = null;
-                    }});
-                }} else {{
-                    console.warn("No deferredPrompt found, falling back to manual PWA window...");
-                    var title = document.title || window.location.href;
-                    
-                    var faviconUrl = knownIconUrl;
-                    if (!faviconUrl) {{
-                        var links = document.querySelectorAll("link[rel*='icon']");
-                        if (links.length > 0) {{
-                            faviconUrl = links[0].href;
-                        }}
-                    }}
-
-                    try {{
-                        var args = {{ url: window.location.href, title: title, faviconUrl: faviconUrl }};
-                        if (window.__TAURI__ && window.__TAURI__.core) {{
-                            await window.__TAURI__.core.invoke('open_pwa_window', args);
-                        }} else if (window.__TAURI__ && window.__TAURI__.invoke) {{
-                            await window.__TAURI__.invoke('open_pwa_window', args);
-                        }} else {{
-                             // Fallback to our custom invoke
-                             if (typeof invoke === 'function') {{
-                                 invoke('open_pwa_window', args);
-                             }} else {{
-                                 console.error("No invoke mechanism found");
-                             }}
-                        }}
-                    }} catch(e) {{
-                        console.error("Failed to open PWA window:", e);
-                    }}
-                }}
-            }})();
-        "#, icon_url_js);
-        webview.eval(&script).map_err(|e| e.to_string())?;
-    }
+fn set_dark_mode_global(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, enabled: bool) -> Result<(), LuminaError> {
+    history_manager.set_meta("dark_mode_global", if enabled { "1" } else { "0" })?;
+    broadcast_dark_mode(&app);
     Ok(())
 }
 
-async fn save_icon(app: &AppHandle, bytes: &[u8]) -> Option<std::path::PathBuf> {
-    let app_dir = app.path().app_data_dir().ok()?;
-    let icons_dir = app_dir.join("icons");
-    if !icons_dir.exists() {
-        let _ = std::fs::create_dir_all(&icons_dir);
+/// `enabled: null` removes the override so the site follows the global toggle again.
+#[tauri::command]
+fn set_dark_mode_site(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, domain: String, enabled: Option<bool>) -> Result<(), LuminaError> {
+    let domain = normalize_site_domain(&domain);
+    if domain.is_empty() {
+        return Err(LuminaError::InvalidInput("Invalid domain".to_string()));
     }
+    history_manager.set_dark_mode_site(&domain, enabled)?;
+    broadcast_dark_mode(&app);
+    Ok(())
+}
 
-    // Try to load image to convert to ICO (Lumina v0.2.5 PNG->ICO Converter)
-    // We use a blocking task because image decoding/encoding is CPU intensive
-    let bytes_vec = bytes.to_vec();
-    let icons_dir_clone = icons_dir.clone();
-    
-    let converted_path = tokio::task::spawn_blocking(move || {
-        if let Ok(img) = image::load_from_memory(&bytes_vec) {
-            // Resize to 256x256 for Windows compatibility (Standard Large Icon)
-            // Windows icons behave best when they are 256x256
-            let resized = img.resize(256, 256, image::imageops::FilterType::Lanczos3);
-            
-            let filename = format!("icon_{}.ico", chrono::Utc::now().timestamp_micros());
-            let path = icons_dir_clone.join(&filename);
-            
-            if let Ok(file) = std::fs::File::create(&path) {
-                let mut writer = std::io::BufWriter::new(file);
-                // Convert to ICO
-                if resized.write_to(&mut writer, image::ImageFormat::Ico).is_ok() {
-                    return Some(path);
+/// Flag: fingerprint_protection. Generic hardware values plus per-session canvas noise.
+fn get_fingerprint_protection_script() -> &'static str {
+    r#"
+    (function() {
+        const define = (obj, prop, value) => {
+            try { Object.defineProperty(obj, prop, { get: () => value, configurable: true }); } catch (e) {}
+        };
+        define(Navigator.prototype, 'hardwareConcurrency', 4);
+        define(Navigator.prototype, 'deviceMemory', 8);
+        define(Navigator.prototype, 'plugins', []);
+        define(Screen.prototype, 'colorDepth', 24);
+        define(Screen.prototype, 'pixelDepth', 24);
+
+        // Canvas readback noise, stable within the session so pages don't break. It goes onto a copy:
+        // the page's own canvas (and whatever it draws next) stays untouched.
+        const seed = Math.floor(Math.random() * 255);
+        const noisyCopy = (canvas) => {
+            if (!canvas.width || !canvas.height) return canvas;
+            try {
+                const copy = document.createElement('canvas');
+                copy.width = canvas.width;
+                copy.height = canvas.height;
+                const ctx = copy.getContext('2d');
+                ctx.drawImage(canvas, 0, 0);
+                const image = ctx.getImageData(0, 0, Math.min(copy.width, 16), Math.min(copy.height, 16));
+                for (let i = 0; i < image.data.length; i += 4) {
+                    image.data[i] = image.data[i] ^ ((seed + i) & 1);
                 }
+                ctx.putImageData(image, 0, 0);
+                return copy;
+            } catch (e) {
+                return canvas;
             }
-        }
-        None
-    }).await.ok().flatten();
+        };
+        const originalToDataURL = HTMLCanvasElement.prototype.toDataURL;
+        HTMLCanvasElement.prototype.toDataURL = function() {
+            return originalToDataURL.apply(noisyCopy(this), arguments);
+        };
+        const originalToBlob = HTMLCanvasElement.prototype.toBlob;
+        HTMLCanvasElement.prototype.toBlob = function() {
+            return originalToBlob.apply(noisyCopy(this), arguments);
+        };
+
+        // Hide the real GPU behind WEBGL_debug_renderer_info
+        [window.WebGLRenderingContext, window.WebGL2RenderingContext].forEach(ctx => {
+            if (!ctx) return;
+            const originalGetParameter = ctx.prototype.getParameter;
+            ctx.prototype.getParameter = function(param) {
+                if (param === 0x9245) return 'Google Inc.';
+                if (param === 0x9246) return 'ANGLE (Generic Renderer)';
+                return originalGetParameter.apply(this, arguments);
+            };
+        });
+    })();
+    "#
+}
 
-    if let Some(path) = converted_path {
-        return Some(path);
+// ============= SITE PROTECTIONS =============
+// How hard the stealth script goes after ads on a site. "default" sticks to ad iframes and scroll
+// unlocks so logins and consent dialogs keep working, "aggressive" also hides broad ad containers
+// and removes floating overlays, "off" leaves the page alone. Unlisted sites are treated as aggressive.
+
+const PROTECTION_LEVELS: [&str; 3] = ["default", "aggressive", "off"];
+
+/// Sites whose login, consent or scrolling broke under overlay removal, seeded once at "default".
+const DEFAULT_GENTLE_SITES: [&str; 8] = [
+    "google.com", "gemini", "youtube.com", "transfermarkt", "cdn.privacy-mgmt.com", "consensu", "cmp", "quantcast",
+];
+
+fn seed_site_protections(app: &AppHandle) {
+    let history_manager = app.state::<HistoryManager>();
+    if history_manager.get_meta("site_protections_seeded").ok().flatten().is_some() {
+        return;
+    }
+    if DEFAULT_GENTLE_SITES.iter().all(|p| history_manager.set_site_protection(p, Some("default")).is_ok()) {
+        let _ = history_manager.set_meta("site_protections_seeded", "1");
     }
-    
-    // Fallback: Just save as is if conversion failed (e.g. SVG or format error)
-    // BUT for shortcuts we really want ICO. If we can't make ICO, we might skip returning a path
-    // or return it and hope for the best (but likely fail on Windows).
-    // Let's try to infer extension.
-    let extension = "png"; // Default to png if we can't guess
-    
-    let filename = format!("icon_{}.{}", chrono::Utc::now().timestamp_micros(), extension);
-    let path = icons_dir.join(&filename);
-    
-    let mut file = tokio::fs::File::create(&path).await.ok()?;
-    file.write_all(bytes).await.ok()?;
-    
-    Some(path)
 }
 
-async fn download_icon(app: &AppHandle, url: &str) -> Option<std::path::PathBuf> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/144.0.0.0 Safari/537.36 Edg/144.0.0.0")
-        .build()
-        .ok()?;
-        
-    let response = client.get(url).send().await.ok()?;
-    let bytes = response.bytes().await.ok()?;
-    
-    save_icon(app, &bytes).await
+fn site_protections(app: &AppHandle) -> HashMap<String, String> {
+    app.state::<HistoryManager>().get_site_protections().unwrap_or_default().into_iter().collect()
 }
 
-#[derive(Clone, serde::Serialize)]
-struct WindowInfo {
-    label: String,
-    title: String,
-    url: String, // We might not be able to get the exact URL easily without tracking it, but we can try
+/// The longest pattern contained in the host decides; unlisted sites get the full treatment.
+fn site_protection_level(protections: &HashMap<String, String>, host: &str) -> String {
+    protections
+        .iter()
+        .filter(|(pattern, _)| host.contains(pattern.as_str()))
+        .max_by_key(|(pattern, _)| pattern.len())
+        .map(|(_, level)| level.clone())
+        .unwrap_or_else(|| "aggressive".to_string())
 }
 
-fn sanitize_pwa_label(url: &str) -> String {
-    // Extract hostname or use a hash if not parseable
-    if let Ok(parsed) = url::Url::parse(url) {
-        if let Some(host) = parsed.host_str() {
-            let safe_host: String = host.chars()
-                .map(|c| if c.is_alphanumeric() { c } else { '-' })
-                .collect();
-            return format!("pwa-{}", safe_host);
-        }
+#[tauri::command]
+fn get_site_protection(app: AppHandle, url: String) -> Option<String> {
+    let parsed = url::Url::parse(&url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
     }
-    // Fallback to timestamp if parsing fails
-    format!("pwa-{}", chrono::Utc::now().timestamp_micros())
+    Some(site_protection_level(&site_protections(&app), parsed.host_str()?))
 }
 
-fn get_pwa_init_script(label: &str, invoke_key: &str) -> String {
-    format!(r#"
-        (function() {{
-            window.__TAB_LABEL__ = "{}";
-            window.__TAURI_INVOKE_KEY__ = "{}";
-            
-            // Custom IPC for PWA windows
-            function invoke(cmd, args) {{
-                // Reuse the same logic as create_tab
-                if (window.__TAURI__ && window.__TAURI__.core) {{
-                    window.__TAURI__.core.invoke(cmd, args).catch(err => console.error("Tauri invoke failed:", err));
-                    return;
-                }}
-                
-                if (typeof window.__IPC_COUNTER === 'undefined') window.__IPC_COUNTER = 0;
-                window.__IPC_COUNTER = (window.__IPC_COUNTER + 1) % 4000000000;
-                var callbackId = window.__IPC_COUNTER;
-                var noOp = function(res) {{}};
-                
-                try {{
-                     if (window.__TAURI_INTERNALS__) {{
-                         if (!window.__TAURI_INTERNALS__.callbacks) window.__TAURI_INTERNALS__.callbacks = {{}};
-                         window.__TAURI_INTERNALS__.callbacks[callbackId] = {{ resolve: noOp, reject: noOp }};
-                     }}
-                }} catch(e) {{}}
-                
-                setTimeout(function() {{
-                    try {{
-                        if (window.__TAURI_INTERNALS__ && window.__TAURI_INTERNALS__.callbacks) {{
-                             delete window.__TAURI_INTERNALS__.callbacks[callbackId];
-                        }}
-                    }} catch(e) {{}}
-                }}, 60000);
-
-                var msg = {{
-                    cmd: cmd,
-                    callback: callbackId, 
-                    error: callbackId,
-                    payload: args,
-                    __TAURI_INVOKE_KEY__: window.__TAURI_INVOKE_KEY__
-                }};
-                
-                if (window.chrome && window.chrome.webview) {{
-                    window.chrome.webview.postMessage(msg);
-                }} else if (window.webkit && window.webkit.messageHandlers && window.webkit.messageHandlers.ipc) {{
-                    window.webkit.messageHandlers.ipc.postMessage(msg);
-                }}
-            }}
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SiteInfo {
+    url: String,
+    host: String,
+    /// "secure" (https with a certificate the engine verified), "unverified" (https the engine can't
+    /// vouch for, which is every page under WebView2: it runs with --ignore-certificate-errors),
+    /// "insecure" (http), "file", "internal" or "other"
+    connection: &'static str,
+    cookie_count: usize,
+    /// Stored allow/deny decisions for the page's origin
+    permissions: Vec<history_manager::SitePermission>,
+    /// Requests blocked since the page last loaded
+    blocked_requests: u32,
+    /// Distinct hosts among the blocked requests
+    blocked_domains: u32,
+    /// Percent
+    zoom: i32,
+    /// None for pages that aren't on the web
+    protection_level: Option<String>,
+    adblock_enabled: bool,
+}
 
-            // Override window.open
-            window.open = function(url, target, features) {{
-                if (url) {{
-                    // Call create_tab directly on the main window via our fixed command
-                    invoke('create_tab', {{ label: 'new-tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000), url: url }});
-                }}
-                return null;
-            }};
-            
-            // Handle _blank links
-            document.addEventListener('click', (e) => {{
-                let target = e.target;
-                while(target && target.tagName !== 'A') target = target.parentElement;
-                if (target && target.tagName === 'A' && target.target === '_blank') {{
-                    e.preventDefault();
-                    invoke('create_tab', {{ label: 'new-tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000), url: target.href }});
-                }}
-            }}, true);
-
-            document.addEventListener('auxclick', (e) => {{
-                if (e.button === 1) {{
-                    let target = e.target;
-                    while(target && target.tagName !== 'A') target = target.parentElement;
-                    if (target && target.tagName === 'A') {{
-                        e.preventDefault();
-                        invoke('create_tab', {{ label: 'new-tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000), url: target.href }});
-                    }}
-                }}
-            }}, true);
-
-            // Context Menu Override
-            document.addEventListener('contextmenu', (e) => {{
-                let target = e.target;
-                let linkUrl = null;
-                while(target && target.tagName !== 'A') target = target.parentElement;
-                if (target && target.tagName === 'A' && target.href) {{
-                    linkUrl = target.href;
-                }}
-
-                if (linkUrl) {{
-                    e.preventDefault();
-                    e.stopPropagation(); 
-                    
-                    const existing = document.getElementById('lumina-context-menu');
-                    if (existing) existing.remove();
-
-                    const menu = document.createElement('div');
-                    menu.id = 'lumina-context-menu';
-                    menu.style.position = 'fixed';
-                    menu.style.top = e.clientY + 'px';
-                    menu.style.left = e.clientX + 'px';
-                    menu.style.zIndex = '2147483647'; 
-                    menu.style.background = '#292a2d';
-                    menu.style.border = '1px solid #3c4043';
-                    menu.style.borderRadius = '4px';
-                    menu.style.padding = '4px 0';
-                    menu.style.color = '#e8eaed';
-                    menu.style.fontFamily = 'system-ui, sans-serif';
-                    menu.style.fontSize = '13px';
-                    menu.style.userSelect = 'none';
-
-                    const createItem = (text, onClick) => {{
-                        const item = document.createElement('div');
-                        item.innerText = text;
-                        item.style.padding = '6px 12px';
-                        item.style.cursor = 'pointer';
-                        item.onmouseenter = () => item.style.background = '#3c4043';
-                        item.onmouseleave = () => item.style.background = 'transparent';
-                        item.onclick = (ev) => {{
-                            ev.stopPropagation(); 
-                            onClick();
-                            menu.remove();
-                        }};
-                        return item;
-                    }};
-
-                    menu.appendChild(createItem('Open Link in New Tab', () => {{
-                         invoke('create_tab', {{ label: 'tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000), url: linkUrl }});
-                    }}));
-                    
-                    // Add copy link
-                    menu.appendChild(createItem('Copy Link Address', () => {{
-                         navigator.clipboard.writeText(linkUrl);
-                    }}));
-                    
-                    document.body.appendChild(menu);
-                    
-                    const closeMenu = () => {{
-                        menu.remove();
-                        document.removeEventListener('click', closeMenu);
-                        document.removeEventListener('contextmenu', closeMenu);
-                    }};
-                    setTimeout(() => {{
-                        document.addEventListener('click', closeMenu);
-                        document.addEventListener('contextmenu', (e) => {{
-                             if (e.target.closest('#lumina-context-menu')) return;
-                             closeMenu();
-                        }});
-                    }}, 100);
-                }}
-            }}, true);
+/// Whether the engine verified the certificate of the page the webview shows; `None` when it can't say.
+#[cfg(target_os = "linux")]
+async fn certificate_verified(webview: &tauri::Webview) -> Option<bool> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    webview.with_webview(move |webview| {
+        use webkit2gtk::WebViewExt;
+        let _ = tx.send(webview.inner().tls_info().map(|(_, errors)| errors.is_empty()));
+    }).ok()?;
+    rx.await.ok().flatten()
+}
 
-        }})();
-    "#, label, invoke_key)
+/// WebView2 runs with --ignore-certificate-errors, so whatever it loaded is unverified.
+#[cfg(not(target_os = "linux"))]
+async fn certificate_verified(_webview: &tauri::Webview) -> Option<bool> {
+    None
 }
 
+/// Everything the padlock popup shows about a tab's page, in one call.
 #[tauri::command]
-async fn open_pwa_window(app: AppHandle, url: String, title: String, favicon_url: Option<String>, icon_data: Option<String>) -> Result<(), String> {
-    let label = sanitize_pwa_label(&url);
-    
-    // Check if window already exists
-    if let Some(window) = app.get_webview_window(&label) {
-        let _ = window.set_focus();
-        return Ok(());
-    }
-    
-    // Get Icon Path
-    let icon_path = if let Some(data) = icon_data {
-        // Decode base64
-        if let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(data) {
-             save_icon(&app, &bytes).await
-        } else {
-             None
-        }
-    } else if let Some(f_url) = favicon_url {
-        download_icon(&app, &f_url).await
+async fn get_site_info(app: AppHandle, label: String) -> Result<SiteInfo, LuminaError> {
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("Tab {} not found", label)))?;
+    let url = webview.url()?;
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+    let web = matches!(url.scheme(), "http" | "https");
+    let connection = match url.scheme() {
+        "https" => if certificate_verified(&webview).await == Some(true) { "secure" } else { "unverified" },
+        "http" => "insecure",
+        "file" => "file",
+        "lumina" | "lumina-app" => "internal",
+        _ => if url.host_str() == Some("lumina-app.localhost") { "internal" } else { "other" },
+    };
+
+    // Reading cookies can block on the webview's thread
+    let cookie_count = if web {
+        let cookie_url = url.clone();
+        tauri::async_runtime::spawn_blocking(move || webview.cookies_for_url(cookie_url))
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .map_or(0, |cookies| cookies.len())
     } else {
-        None
+        0
     };
 
-    let icon_path_clone = icon_path.clone();
+    let history_manager = app.state::<HistoryManager>();
+    let permissions = match webview_origin(&app, &label) {
+        Some(origin) => history_manager.get_site_permissions(Some(&origin))?,
+        None => Vec::new(),
+    };
+    let (blocked_requests, blocked_domains) = adblock::blocked_this_visit(&label);
+
+    Ok(SiteInfo {
+        zoom: zoom::zoom_for_host(&app, web.then_some(host.as_str())),
+        protection_level: web.then(|| site_protection_level(&site_protections(&app), &host)),
+        adblock_enabled: ADBLOCK_ENABLED.load(std::sync::atomic::Ordering::Relaxed) && !adblock::is_adblock_disabled_for_host(&host),
+        url: url.to_string(),
+        host,
+        connection,
+        cookie_count,
+        permissions,
+        blocked_requests,
+        blocked_domains,
+    })
+}
 
-    // Create Desktop Shortcut
-    let _ = create_desktop_shortcut(&title, &url, icon_path);
+/// `level: null` drops the entry. Open tabs on the site reload so the new level applies right away.
+#[tauri::command]
+fn set_site_protection(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, domain: String, level: Option<String>) -> Result<(), LuminaError> {
+    let domain = normalize_site_domain(&domain);
+    if domain.is_empty() {
+        return Err(LuminaError::InvalidInput("Invalid domain".to_string()));
+    }
+    if level.as_deref().is_some_and(|l| !PROTECTION_LEVELS.contains(&l)) {
+        return Err(LuminaError::InvalidInput("Unknown protection level".to_string()));
+    }
+    history_manager.set_site_protection(&domain, level.as_deref())?;
+    for (label, webview) in app.webviews() {
+        let on_site = webview.url().ok().and_then(|u| u.host_str().map(|h| h.contains(&domain))).unwrap_or(false);
+        if !is_browser_window(&label) && on_site {
+            let _ = webview.eval("location.reload()");
+        }
+    }
+    Ok(())
+}
 
-    let app_clone = app.clone();
-    let label_clone = label.clone();
+// ============= SITE FIXUPS =============
+// Per-domain compatibility scripts. Each lives in a folder with a fixup.json next to its script,
+// either under app_data/fixups or inside a store package under app_data/packages.
 
-    // Inject PWA script for handling window.open and context menu
-    let invoke_key = app.invoke_key();
-    let script = get_pwa_init_script(&label, invoke_key);
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SiteFixup {
+    id: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    /// "example.com" matches the host and its subdomains; "example.*" matches any TLD
+    domains: Vec<String>,
+    #[serde(default = "default_fixup_script")]
+    script: String,
+    #[serde(skip_deserializing)]
+    source: String,
+    #[serde(skip_deserializing)]
+    enabled: bool,
+}
 
-    let mut builder = tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::External(url.parse().map_err(|e: url::ParseError| e.to_string())?))
-        .title(&title)
-        .initialization_script(&script);
-
-    if let Some(path) = icon_path_clone {
-        if let Ok(img) = image::open(&path) {
-             let rgba = img.to_rgba8();
-             let (width, height) = rgba.dimensions();
-             let rgba_vec = rgba.into_raw();
-             let tauri_img = tauri::image::Image::new_owned(rgba_vec, width, height);
-             match builder.icon(tauri_img) {
-                 Ok(b) => builder = b,
-                 Err(e) => return Err(format!("Failed to set window icon: {}", e)),
-             }
-        }
-    }
+fn default_fixup_script() -> String {
+    "fixup.js".to_string()
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        builder = builder.user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/144.0.0.0 Safari/537.36 Edg/144.0.0.0");
-        builder = builder.additional_browser_args("--ignore-certificate-errors");
-    }
-    #[cfg(target_os = "linux")]
-    {
-        builder = builder.user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/144.0.0.0 Safari/537.36");
-    }
-    #[cfg(target_os = "macos")]
-    {
-        builder = builder.user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/144.0.0.0 Safari/537.36");
+/// Shipped with Lumina: some sports sites build AJAX URLs with stray spaces that break the request.
+const BUILTIN_FIXUP_ID: &str = "malformed-xhr-urls";
+const BUILTIN_FIXUP_MANIFEST: &str = r#"{
+    "id": "malformed-xhr-urls",
+    "name": "Malformed AJAX URLs",
+    "description": "Strips stray spaces from XMLHttpRequest URLs that break live scores and tables.",
+    "domains": ["transfermarkt.*", "mackolik.com"]
+}"#;
+const BUILTIN_FIXUP_SCRIPT: &str = r#"
+const originalOpen = XMLHttpRequest.prototype.open;
+XMLHttpRequest.prototype.open = function(method, url, ...args) {
+    if (typeof url === 'string' && (url.includes('%20') || url.includes(' '))) {
+        url = url.replace(/%20/g, '').replace(/\s/g, '');
     }
+    return originalOpen.call(this, method, url, ...args);
+};
+"#;
 
-    builder.inner_size(1024.0, 768.0)
-        .decorations(true) // Enable native window controls (Close, Minimize, Maximize)
-        .focused(true)
-        .initialization_script(get_lumina_stealth_script())
-        .on_web_resource_request(move |request, response| {
-            let referer = request.headers().get("referer").and_then(|h| h.to_str().ok());
-            if check_adblock_url(&request.uri().to_string(), referer, &label_clone, &app_clone) {
-                *response = tauri::http::Response::builder()
-                    .status(403)
-                    .body(std::borrow::Cow::Owned(Vec::new()))
-                    .unwrap();
-            }
-        })
-        .build()
-        .map_err(|e| e.to_string())?;
-    Ok(())
+fn fixups_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|d| d.join("fixups"))
 }
 
-#[tauri::command]
-fn get_open_windows(app: AppHandle) -> Vec<WindowInfo> {
-    let mut windows = Vec::new();
-    for (label, window) in app.webview_windows() {
-        if label != "main" && !label.starts_with("flash-") {
-            let title = window.title().unwrap_or_else(|_| label.clone());
-            // For PWAs, we use the window URL which is typically the app URL.
-            // Note: This might be the initial URL if navigation handling is complex, 
-            // but for single-page apps/PWAs it's usually sufficient.
-            windows.push(WindowInfo {
-                label,
-                title,
-                url: window.url().map(|u| u.to_string()).unwrap_or_default(),
-            });
+/// Writes the built-in fixup on first run. Once seeded it's a normal fixup the user can disable or delete.
+fn seed_builtin_fixups(app: &AppHandle) {
+    let history_manager = app.state::<HistoryManager>();
+    if history_manager.get_meta("fixups_seeded").ok().flatten().is_some() {
+        return;
+    }
+    let Some(dir) = fixups_dir(app).map(|d| d.join(BUILTIN_FIXUP_ID)) else { return };
+    let written = std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(dir.join("fixup.json"), BUILTIN_FIXUP_MANIFEST))
+        .and_then(|_| std::fs::write(dir.join("fixup.js"), BUILTIN_FIXUP_SCRIPT));
+    match written {
+        Ok(()) => {
+            let _ = history_manager.set_meta("fixups_seeded", "1");
         }
+        Err(e) => eprintln!("Failed to write built-in site fixup: {}", e),
     }
-    windows
 }
 
-#[tauri::command]
-fn focus_window(app: AppHandle, label: String) {
-    if let Some(window) = app.get_webview_window(&label) {
-        let _ = window.set_focus();
-        if window.is_minimized().unwrap_or(false) {
-            let _ = window.unminimize();
-        }
+fn read_fixup(dir: &std::path::Path, source: &str) -> Option<(SiteFixup, PathBuf)> {
+    let manifest = std::fs::read_to_string(dir.join("fixup.json")).ok()?;
+    let mut fixup: SiteFixup = serde_json::from_str(&manifest).ok()?;
+    if fixup.id.trim().is_empty() || fixup.domains.is_empty() {
+        return None;
+    }
+    // The script has to stay inside the fixup's folder
+    let script = std::path::Path::new(&fixup.script);
+    if script.components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+        return None;
     }
+    let script_path = dir.join(script);
+    fixup.source = source.to_string();
+    Some((fixup, script_path))
 }
 
-const BLOCKED_DOMAINS: &[&str] = &[
-    "doubleclick.net",
-    "googleadservices.com",
-    "googlesyndication.com",
-    "adnxs.com",
-    "rubiconproject.com",
-    "taboola.com",
-    "outbrain.com",
-    "amazon-adsystem.com",
-    "adservice.google.com",
-    "moatads.com",
-    "criteo.com",
-    "pubmatic.com",
-    "openx.net",
-    "smartadserver.com",
-];
-
-#[tauri::command]
-fn clean_page(app: AppHandle) {
-    let script = r#"
-        (function() {
-            // Domain Awareness for Clean Page
-            const host = window.location.hostname;
-            if (host.includes('google.com') || host.includes('gemini') || host.includes('youtube.com')) {
-                 console.log("Lumina Clean Page: Friendly domain detected (" + host + "), aborting force clean.");
-                 return;
-            }
-
-            const elements = document.querySelectorAll('div, iframe, section, aside, span, a, img, button');
-            let count = 0;
-            elements.forEach(el => {
-                const style = window.getComputedStyle(el);
-                if (style.position === 'fixed' || style.position === 'absolute') {
-                    // Check if it's likely an overlay/ad (high z-index or full width/height)
-                    if ((style.zIndex && parseInt(style.zIndex) > 10) || 
-                        (el.offsetWidth > window.innerWidth * 0.9 && el.offsetHeight > window.innerHeight * 0.9)) {
-                         el.remove();
-                         count++;
-                    }
-                }
-            });
-            console.log("Lumina Clean Page: Removed " + count + " floating elements.");
-        })();
-    "#;
-    
-    for (label, window) in app.webview_windows() {
-        if label != "main" {
-            let _ = window.eval(script);
+/// Every installed fixup with the path of its script, user folder first so it wins on id clashes.
+fn load_site_fixups(app: &AppHandle) -> Vec<(SiteFixup, PathBuf)> {
+    let Ok(app_data) = app.path().app_data_dir() else { return Vec::new() };
+    let store = app.state::<AppDataStore>();
+    let mut fixups: Vec<(SiteFixup, PathBuf)> = Vec::new();
+    for (dir, source) in [(app_data.join("fixups"), "local"), (app_data.join("packages"), "store")] {
+        let Ok(entries) = std::fs::read_dir(dir) else { continue };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let Some((mut fixup, script)) = read_fixup(&entry.path(), source) else { continue };
+            if fixups.iter().any(|(f, _)| f.id == fixup.id) {
+                continue;
+            }
+            fixup.enabled = store.is_fixup_enabled(&fixup.id);
+            fixups.push((fixup, script));
         }
     }
-}
-
-#[tauri::command]
-async fn open_flash_window(app: AppHandle, url: String) -> Result<(), String> {
-    let label = format!("flash-{}", chrono::Utc::now().timestamp_micros());
-    let app_handle = app.clone();
-    let label_clone = label.clone();
-    
-    let mut builder = tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::External(url.parse().map_err(|e: url::ParseError| e.to_string())?))
-        .title("Flash Tab");
-
-    #[cfg(target_os = "windows")]
-    {
-        builder = builder.user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0");
+    fixups.sort_by_key(|(f, _)| f.name.to_lowercase());
+    fixups
+}
+
+/// Whether a fixup domain pattern covers `host`: the domain and its subdomains, or with a trailing
+/// `.*` any suffix after it (`google.*` for google.com and google.co.uk).
+fn fixup_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_suffix(".*") {
+        Some(base) => host.starts_with(&format!("{}.", base)) || host.contains(&format!(".{}.", base)),
+        None => host == pattern || host.ends_with(&format!(".{}", pattern)),
     }
-    #[cfg(target_os = "linux")]
-    {
-        builder = builder.user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
+}
+
+/// `on_page_load` of tabs: once the new document has committed, runs each enabled fixup for its host
+/// as a script of its own, so one that fails to parse or misbehaves can't take the others with it.
+pub(crate) fn apply_site_fixups(app: &AppHandle, webview: &tauri::Webview, payload: &tauri::webview::PageLoadPayload<'_>) {
+    if payload.event() != tauri::webview::PageLoadEvent::Started || !matches!(payload.url().scheme(), "http" | "https") {
+        return;
     }
-    #[cfg(target_os = "macos")]
-    {
-        builder = builder.user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
+    let Some(host) = payload.url().host_str() else { return };
+    for (fixup, path) in load_site_fixups(app).into_iter().filter(|(f, _)| f.enabled) {
+        if !fixup.domains.iter().any(|d| fixup_matches(d, host)) {
+            continue;
+        }
+        let Ok(body) = std::fs::read_to_string(&path) else { continue };
+        let _ = webview.eval(format!(
+            "try {{\n{}\n}} catch (e) {{ console.warn('Lumina fixup ' + {} + ' failed', e); }}",
+            body,
+            to_script_json(&fixup.id)
+        ));
     }
+}
 
-    builder.inner_size(800.0, 600.0)
-        .decorations(false)
-        .always_on_top(true)
-        .center()
-        .focused(true)
-        .skip_taskbar(true)
-        .initialization_script(get_lumina_stealth_script())
-        .on_web_resource_request(move |request, response| {
-            let referer = request.headers().get("referer").and_then(|h| h.to_str().ok());
-            if check_adblock_url(&request.uri().to_string(), referer, &label_clone, &app_handle) {
-                *response = tauri::http::Response::builder()
-                    .status(403)
-                    .body(std::borrow::Cow::Owned(Vec::new()))
-                    .unwrap();
-            }
-        })
-        .build()
-        .map_err(|e| e.to_string())?;
+#[tauri::command]
+fn list_site_fixups(app: AppHandle) -> Vec<SiteFixup> {
+    load_site_fixups(&app).into_iter().map(|(f, _)| f).collect()
+}
+
+/// Takes effect from the next page load.
+#[tauri::command]
+fn set_site_fixup_enabled(app: AppHandle, state: tauri::State<'_, AppDataStore>, id: String, enabled: bool) -> Result<(), LuminaError> {
+    if !load_site_fixups(&app).iter().any(|(f, _)| f.id == id) {
+        return Err(LuminaError::NotFound("Fixup not found".to_string()));
+    }
+    state.set_fixup_enabled(&id, enabled);
+    state.save();
     Ok(())
 }
 
+/// Runs in every frame at document start, whatever the site's protection level.
 fn get_lumina_stealth_script() -> String {
-    r#"
+    String::from(r#"
     (function() {
-        let host = window.location.hostname;
-        
-        // Try to get parent host if current host is empty (e.g. about:blank iframe)
-        try {
-            if ((!host || host === '') && window.parent && window.parent.location && window.parent.location.hostname) {
-                host = window.parent.location.hostname;
-            }
-        } catch(e) {
-            // Access denied to parent (cross-origin)
-        }
-        
-        // console.log("Lumina Stealth Protocol: Activated on " + host);
-
-        // 0. Monkey-Patch IntersectionObserver to prevent ad script crashes
+        // Monkey-Patch IntersectionObserver to prevent ad script crashes
         const originalObserve = IntersectionObserver.prototype.observe;
         IntersectionObserver.prototype.observe = function(target) {
             if (!target || !(target instanceof Element)) {
@@ -2033,22 +5002,17 @@ fn get_lumina_stealth_script() -> String {
             }
             return originalObserve.apply(this, arguments);
         };
+    })();
+    "#)
+}
 
-        // 0.1 Monkey-Patch XHR to fix Transfermarkt malformed URLs
-        const originalOpen = XMLHttpRequest.prototype.open;
-        XMLHttpRequest.prototype.open = function(method, url, ...args) {
-            if (typeof url === 'string' && (window.location.hostname.includes('transfermarkt') || window.location.hostname.includes('mackolik'))) {
-                 // Remove spaces (%20) from URL path which break AJAX calls
-                 if (url.includes('%20') || url.includes(' ')) {
-                     const cleanUrl = url.replace(/%20/g, '').replace(/\s/g, '');
-                     // console.log("Lumina Fix: Corrected malformed URL", url, "->", cleanUrl);
-                     url = cleanUrl;
-                 }
-            }
-            return originalOpen.call(this, method, url, ...args);
-        };
+/// Overlay and ad-container removal at one protection level ("default" is the gentle one).
+fn get_site_protection_script(level: &str) -> String {
+    let mut script = String::from(r#"
+    (function(level) {
+        if (level === 'off') return;
 
-        const isFriendly = host.includes('google.com') || host.includes('gemini') || host.includes('youtube.com') || host.includes('transfermarkt') || host.includes('cdn.privacy-mgmt.com') || host.includes('consensu') || host.includes('cmp') || host.includes('quantcast');
+        const isFriendly = level === 'default';
 
         // Force remove aria-hidden from body to prevent accessibility lock
         if (isFriendly) {
@@ -2125,7 +5089,7 @@ fn get_lumina_stealth_script() -> String {
             if (!isFriendly) {
                 injectCSS(aggressiveAdStyles);
             } else {
-                // console.log("Lumina Stealth: Friendly domain (" + host + ") - Skipping aggressive CSS.");
+                // console.log("Lumina Stealth: Friendly level - Skipping aggressive CSS.");
             }
         }
         
@@ -2253,1071 +5217,310 @@ fn get_lumina_stealth_script() -> String {
              }, 1000);
         }
         
-    })();
-    "#.to_string()
+    })("#);
+    script.push_str(&to_script_json(&level));
+    script.push_str(");");
+    script
 }
 
-fn create_desktop_shortcut(_name: &str, _url: &str, _icon_path: Option<std::path::PathBuf>) -> std::io::Result<()> {
-    #[cfg(target_os = "windows")]
-    {
-        // Sanitize filename
-        let safe_name: String = _name.chars()
-            .map(|c| if c.is_alphanumeric() || c == ' ' { c } else { '_' })
-            .collect();
-        
-        let desktop = std::env::var("USERPROFILE").unwrap_or(".".to_string()) + "\\Desktop";
-        let path = std::path::Path::new(&desktop).join(format!("{}.lnk", safe_name));
-        let exe = std::env::current_exe()?;
-        let exe_path = exe.to_str().unwrap();
-        
-        // Escape quotes for PowerShell
-        let safe_url = _url.replace("'", "''");
-        
-        let mut script = format!(
-            "$WshShell = New-Object -comObject WScript.Shell; $Shortcut = $WshShell.CreateShortcut('{}'); $Shortcut.TargetPath = '{}'; $Shortcut.Arguments = '--pwa-url=\"{}\"';",
-            path.to_str().unwrap(),
-            exe_path,
-            safe_url
-        );
-        
-        if let Some(icon) = _icon_path {
-            if let Some(icon_str) = icon.to_str() {
-                script.push_str(&format!(" $Shortcut.IconLocation = '{}';", icon_str));
-            }
-        }
-        
-        script.push_str(" $Shortcut.Save()");
-        
-        std::process::Command::new("powershell")
-            .arg("-NoProfile")
-            .arg("-Command")
-            .arg(script)
-            .output()?;
+/// `on_page_load` for every web view: once the new document has committed, runs the protections
+/// at the level its site has right now. Looked up per load, so a changed level applies on reload,
+/// and the page only ever gets its own level, not the table.
+pub(crate) fn apply_site_protection(app: &AppHandle, webview: &tauri::Webview, payload: &tauri::webview::PageLoadPayload<'_>) {
+    if payload.event() != tauri::webview::PageLoadEvent::Started || !matches!(payload.url().scheme(), "http" | "https") {
+        return;
     }
-    Ok(())
+    let Some(host) = payload.url().host_str() else { return };
+    let level = site_protection_level(&site_protections(app), host);
+    let _ = webview.eval(get_site_protection_script(&level));
 }
 
-
 #[tauri::command]
-fn update_tab_info(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, label: String, title: Option<String>, favicon: Option<String>, url: Option<String>) {
-    // If URL and Title are present, update history title (but don't increment visit count)
-    if let (Some(u), Some(t)) = (&url, &title) {
-         if !u.starts_with("tauri://") && !u.starts_with("about:") {
-             let _ = history_manager.update_title(u.clone(), t.clone());
-         }
-    }
-    let _ = app.emit("tab-updated", TabUpdatedPayload { label, title, favicon });
-}
-
-struct NetworkSidecarRequest {
-    command: String,
-    payload: String,
-    response_tx: tokio::sync::oneshot::Sender<String>,
+fn get_site_permissions(history_manager: tauri::State<'_, HistoryManager>, origin: Option<String>) -> Result<Vec<history_manager::SitePermission>, LuminaError> {
+    history_manager.get_site_permissions(origin.as_deref()).map_err(LuminaError::from)
 }
 
-struct NetworkState {
-    tx: tokio::sync::mpsc::Sender<NetworkSidecarRequest>,
-}
-
-struct UiState {
-    sidebar_open: std::sync::atomic::AtomicBool,
-    suggestions_height: std::sync::atomic::AtomicU32,
-    current_tab: std::sync::Mutex<Option<String>>,
-}
-
-
-
+/// `state` is "allow", "deny" or "ask" (forget the decision).
 #[tauri::command]
-async fn create_tab(state: tauri::State<'_, UiState>, app: AppHandle, data_store: tauri::State<'_, AppDataStore>, label: String, url: String, _window: tauri::Window) -> Result<(), String> {
-    // println!("Rust: create_tab called for {} url: {}", label, url);
-
-    // Rewrite lumina:// to lumina-app://localhost/ for internal navigation to avoid OS deep link conflict
-    let url = if url.starts_with("lumina://") {
-        url.replace("lumina://", "lumina-app://localhost/")
-    } else if url.starts_with("lumina-app://") {
-         if !url.contains("lumina-app://localhost/") {
-             url.replace("lumina-app://", "lumina-app://localhost/")
-         } else {
-             url
-         }
-    } else {
-        url
-    };
-
-    // Ensure we are targeting the main window for the new tab
-    let target_window = app.get_window("main").ok_or_else(|| {
-        println!("Rust: Main window 'main' not found!");
-        "Main window not found".to_string()
-    })?;
-
-    if app.get_webview(&label).is_some() {
-        // If tab already exists, just switch to it (optional logic)
-        println!("Rust: Tab {} already exists", label);
-        return Ok(());
-    }
-
-    let window_size = target_window.inner_size().map_err(|e| e.to_string())?;
-    let scale_factor = target_window.scale_factor().map_err(|e| e.to_string())?;
-    let logical_size = window_size.to_logical::<f64>(scale_factor);
-    
-    let vertical_tabs = data_store.data.lock().unwrap().settings.vertical_tabs;
-    let sidebar_open = state.sidebar_open.load(std::sync::atomic::Ordering::Relaxed);
-    let suggestions_height = state.suggestions_height.load(std::sync::atomic::Ordering::Relaxed) as f64;
-    
-    let (main_height, x, y, tab_width, tab_height) = calculate_layout(logical_size, vertical_tabs, sidebar_open, suggestions_height);
-    
-    // Resize main webview (UI) to cover the top area
-    if let Some(main_webview) = app.get_webview("main") {
-        let _ = main_webview.set_size(tauri::LogicalSize::new(logical_size.width, main_height));
-    }
-    
-    let app_handle = app.clone();
-    let app_handle_dl = app.clone();
-
-
-    let label_clone = label.clone();
-    
-    let ad_block_script = get_lumina_stealth_script();
-
-    // Attempt to get invoke key
-    println!("Rust: Getting invoke key for {}", label);
-    let invoke_key = app.invoke_key();
-     
-    let info_script = format!(r#"
-         (function() {{
-             // Prevent execution in subframes (ads, tracking pixels) to stop IPC errors
-             try {{
-                if (window.self !== window.top) return;
-             }} catch(e) {{ return; }}
-
-             // Block execution on known ad domains (even if top-level)
-             try {{
-                 let host = window.location.hostname;
-                 if (host.includes('doubleclick') || host.includes('googlesyndication') || host.includes('adnxs') || host.includes('teads')) return;
-             }} catch(e) {{}}
-
-             window.__TAB_LABEL__ = "{}";
-             window.__TAURI_INVOKE_KEY__ = "{}";
-            
-            // Suppress Tauri callback errors caused by our manual IPC
-            const originalConsoleError = console.error;
-            const originalConsoleWarn = console.warn;
-            
-            function isTauriCallbackError(args) {{
-                if (args.length > 0 && typeof args[0] === 'string') {{
-                    return args[0].includes("Couldn't find callback id") || 
-                           args[0].includes("[TAURI] Couldn't find callback id");
-                }}
-                return false;
-            }}
-
-            console.error = function(...args) {{
-                if (isTauriCallbackError(args)) return;
-                originalConsoleError.apply(console, args);
-            }};
-            
-            console.warn = function(...args) {{
-                if (isTauriCallbackError(args)) return;
-                originalConsoleWarn.apply(console, args);
-            }};
-
-            // Custom IPC for our browser tabs via native postMessage
-            // This bypasses CSP 'connect-src' and 'frame-src' restrictions.
-            function invoke(cmd, args) {{
-                // Try Tauri v2 standard invoke first (if available and not blocked)
-                if (window.__TAURI__ && window.__TAURI__.core) {{
-                    window.__TAURI__.core.invoke(cmd, args).catch(err => console.error("Tauri invoke failed:", err));
-                    return;
-                }}
-                
-                // Use a static counter to ensure unique, valid u32 IDs
-                if (typeof window.__IPC_COUNTER === 'undefined') {{
-                    window.__IPC_COUNTER = 0;
-                }}
-                window.__IPC_COUNTER = (window.__IPC_COUNTER + 1) % 4000000000;
-                var callbackId = window.__IPC_COUNTER;
-                
-                // Register dummy callback to silence "Couldn't find callback id" errors
-                var noOp = function(res) {{}};
-                
-                try {{
-                     if (window.__TAURI_INTERNALS__) {{
-                         if (!window.__TAURI_INTERNALS__.callbacks) {{
-                             window.__TAURI_INTERNALS__.callbacks = {{}};
-                         }}
-                         // Tauri v2 style
-                         window.__TAURI_INTERNALS__.callbacks[callbackId] = {{
-                             resolve: noOp,
-                             reject: noOp
-                         }};
-                     }}
-                }} catch(e) {{}}
-                
-                // Cleanup
-                setTimeout(function() {{
-                    try {{
-                        if (window.__TAURI_INTERNALS__ && window.__TAURI_INTERNALS__.callbacks) {{
-                             delete window.__TAURI_INTERNALS__.callbacks[callbackId];
-                        }}
-                    }} catch(e) {{}}
-                }}, 60000); // 60s timeout
-
-                var msg = {{
-                    cmd: cmd,
-                    callback: callbackId, 
-                    error: callbackId,
-                    payload: args,
-                    __TAURI_INVOKE_KEY__: window.__TAURI_INVOKE_KEY__
-                }};
-                
-                if (window.chrome && window.chrome.webview) {{
-                    // WebView2 (Windows)
-                    window.chrome.webview.postMessage(msg); // Send object directly for WebView2
-                }} else if (window.webkit && window.webkit.messageHandlers && window.webkit.messageHandlers.ipc) {{
-                    // WebKit (macOS / Linux)
-                    window.webkit.messageHandlers.ipc.postMessage(msg);
-                }} else {{
-                    console.error("No native IPC found for " + cmd);
-                }}
-            }}
-
-            // PWA Detection
-            window.addEventListener('beforeinstallprompt', (e) => {{
-                // Prevent the mini-infobar from appearing on mobile
-                e.preventDefault();
-                // Stash the event so it can be triggered later.
-                window.deferredPrompt = e;
-                console.log("PWA beforeinstallprompt fired! Event stashed.");
-                invoke('pwa_detected', {{ label: window.__TAB_LABEL__ }});
-            }});
-            
-            window.addEventListener('appinstalled', () => {{
-                console.log('PWA was installed');
-                window.deferredPrompt = null;
-            }});
-            
-            // Manual PWA Detection (Fallback)
-            // Checks for manifest with display: standalone/minimal-ui
-            async function checkManifest() {{
-                if (window.deferredPrompt) return; // Already detected
-                
-                var link = document.querySelector("link[rel='manifest']");
-                if (link && link.href) {{
-                    // Try to fetch in browser first (shares session/cookies, bypasses Cloudflare)
-                    try {{
-                        console.log("Fetching PWA manifest via JS: " + link.href);
-                        let response = await fetch(link.href);
-                        if (response.ok) {{
-                            let manifest = await response.json();
-                            console.log("JS Manifest parsed:", manifest);
-                            if (manifest.display && ['standalone', 'minimal-ui', 'fullscreen'].includes(manifest.display)) {{
-                                console.log("PWA detected via JS fetch!");
-                                
-                                // Extract best icon
-                                let iconUrl = null;
-                                if (manifest.icons && Array.isArray(manifest.icons)) {{
-                                    let maxArea = 0;
-                                    for (let icon of manifest.icons) {{
-                                        if (icon.src && icon.sizes) {{
-                                            let sizes = icon.sizes.split(' ');
-                                            for (let size of sizes) {{
-                                                if (size === 'any') continue;
-                                                let parts = size.split('x');
-                                                if (parts.length === 2) {{
-                                                    let w = parseInt(parts[0]);
-                                                    let h = parseInt(parts[1]);
-                                                    if (!isNaN(w) && !isNaN(h) && w * h > maxArea) {{
-                                                        maxArea = w * h;
-                                                        iconUrl = icon.src;
-                                                    }}
-                                                }}
-                                            }}
-                                        }} else if (icon.src && !iconUrl) {{
-                                            // Fallback if no sizes
-                                            iconUrl = icon.src;
-                                        }}
-                                    }}
-                                    
-                                    // Resolve relative URL
-                                    if (iconUrl) {{
-                                        iconUrl = new URL(iconUrl, link.href).href;
-                                    }}
-                                }}
-
-                                invoke('pwa_detected', {{ label: window.__TAB_LABEL__, iconUrl: iconUrl }});
-                                return;
-                            }}
-                        }}
-                    }} catch(e) {{
-                        console.error("Browser fetch failed, falling back to Rust:", e);
-                    }}
-
-                    // Fallback to Rust (bypasses CORS/CSP if browser fetch failed)
-                    invoke('check_pwa_manifest', {{ label: window.__TAB_LABEL__, url: link.href }});
-                }}
-            }}
-            
-            // Run check on load and DOMContentLoaded, avoiding loops
-            window.addEventListener('load', checkManifest);
-            if (document.readyState === 'complete' || document.readyState === 'interactive') {{
-                 checkManifest();
-            }}
-
-            function getFavicon() {{
-                let link = document.querySelector("link[rel*='icon']") || document.querySelector("link[rel='shortcut icon']");
-                return link ? link.href : "";
-            }}
-
-            function logVisit() {{
-                if (window.location.protocol.startsWith('http')) {{
-                     invoke('add_history_item', {{
-                         url: window.location.href,
-                         title: document.title || window.location.href
-                     }});
-                }}
-            }}
-
-            function updateInfo() {{
-                 let title = document.title;
-                 let favicon = getFavicon();
-                 invoke('update_tab_info', {{
-                     label: window.__TAB_LABEL__,
-                     title: title,
-                     favicon: favicon,
-                     url: window.location.href
-                 }});
-            }}
-            
-            // Observer for head changes (title, favicon)
-            function initObserver() {{
-                var target = document.head || document.querySelector('head') || document.documentElement;
-                if (target) {{
-                    try {{
-                        new MutationObserver(updateInfo).observe(target, {{ subtree: true, childList: true, attributes: true }});
-                    }} catch(e) {{
-                        console.error("MutationObserver init failed:", e);
-                    }}
-                }}
-            }}
-
-            // Handle new tab requests
-            window.open = function(url, target, features) {{
-                if (url) {{
-                    window.__TAURI__.event.emit('request-new-tab', {{ label: 'new-tab', url: url }});
-                }}
-                return null;
-            }};
-            
-            document.addEventListener('click', (e) => {{
-                let target = e.target;
-                while(target && target.tagName !== 'A') target = target.parentElement;
-                if (target && target.tagName === 'A' && target.target === '_blank') {{
-                    e.preventDefault();
-                    window.__TAURI__.event.emit('request-new-tab', {{ label: 'new-tab', url: target.href }});
-                }}
-            }}, true);
-
-            document.addEventListener('auxclick', (e) => {{
-                if (e.button === 1) {{
-                    let target = e.target;
-                    while(target && target.tagName !== 'A') target = target.parentElement;
-                    if (target && target.tagName === 'A') {{
-                        e.preventDefault();
-                        window.__TAURI__.event.emit('request-new-tab', {{ label: 'new-tab', url: target.href }});
-                    }}
-                }}
-            }}, true);
-
-            // Custom Context Menu
-            document.addEventListener('contextmenu', (e) => {{
-                // Check if target is link
-                let target = e.target;
-                let linkUrl = null;
-                while(target && target.tagName !== 'A') target = target.parentElement;
-                if (target && target.tagName === 'A' && target.href) {{
-                    linkUrl = target.href;
-                }}
-
-                if (linkUrl) {{
-                    e.preventDefault();
-                    e.stopPropagation(); // Stop propagation immediately
-                    
-                    // Remove existing menu
-                    const existing = document.getElementById('lumina-context-menu');
-                    if (existing) existing.remove();
-
-                    const menu = document.createElement('div');
-                    menu.id = 'lumina-context-menu';
-                    menu.style.position = 'fixed';
-                    menu.style.top = e.clientY + 'px';
-                    menu.style.left = e.clientX + 'px';
-                    menu.style.zIndex = '2147483647'; // Max z-index
-                    menu.style.background = '#292a2d';
-                    menu.style.border = '1px solid #3c4043';
-                    menu.style.borderRadius = '4px';
-                    menu.style.padding = '4px 0';
-                    menu.style.boxShadow = '0 2px 6px rgba(0,0,0,0.3)';
-                    menu.style.minWidth = '150px';
-                    menu.style.color = '#e8eaed';
-                    menu.style.fontFamily = 'system-ui, -apple-system, sans-serif';
-                    menu.style.fontSize = '13px';
-                    menu.style.userSelect = 'none';
-
-                    const createItem = (text, onClick) => {{
-                        const item = document.createElement('div');
-                        item.innerText = text;
-                        item.style.padding = '6px 12px';
-                        item.style.cursor = 'pointer';
-                        item.onmouseenter = () => item.style.background = '#3c4043';
-                        item.onmouseleave = () => item.style.background = 'transparent';
-                        item.onclick = (ev) => {{
-                            ev.stopPropagation(); 
-                            onClick();
-                            menu.remove();
-                        }};
-                        return item;
-                    }};
-
-                    menu.appendChild(createItem('Open Link in New Tab', () => {{
-                         let uniqueLabel = 'tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000000);
-                         invoke('create_tab', {{ label: uniqueLabel, url: linkUrl }});
-                    }}));
-                    
-                    // Separator
-                    const sep = document.createElement('div');
-                    sep.style.height = '1px';
-                    sep.style.background = '#3c4043';
-                    sep.style.margin = '4px 0';
-                    menu.appendChild(sep);
-
-                    menu.appendChild(createItem('Back', () => window.history.back()));
-                    menu.appendChild(createItem('Forward', () => window.history.forward()));
-                    menu.appendChild(createItem('Reload', () => window.location.reload()));
-
-                    document.body.appendChild(menu);
-                    
-                    // Close on click outside
-                    const closeMenu = () => {{
-                        menu.remove();
-                        document.removeEventListener('click', closeMenu);
-                        document.removeEventListener('contextmenu', closeMenu);
-                    }};
-                    // Delay slightly to avoid immediate trigger
-                    setTimeout(() => {{
-                        document.addEventListener('click', closeMenu);
-                        document.addEventListener('contextmenu', (e) => {{
-                             if (e.target.closest('#lumina-context-menu')) return;
-                             closeMenu();
-                        }});
-                    }}, 100);
-                }}
-            }}, true); // Use Capture phase to preempt site scripts
-
-            if (document.body || document.head || document.documentElement) {{
-                initObserver();
-            }} else {{
-                document.addEventListener('DOMContentLoaded', initObserver);
-            }}
-            
-            // Initial call
-            if (document.readyState === 'complete' || document.readyState === 'interactive') {{
-                updateInfo();
-                logVisit();
-            }} else {{
-                window.addEventListener('DOMContentLoaded', updateInfo);
-                window.addEventListener('load', () => {{ updateInfo(); logVisit(); }});
-            }}
-        }})();
-    "#, label_clone, invoke_key);
-
-    let full_script = format!("{}\n{}", ad_block_script, info_script);
-
-    let url_parsed = match url.parse() {
-        Ok(u) => u,
-        Err(e) => return Err(format!("Invalid URL: {}", e)),
-    };
-
-    let app_clone_adblock = app.clone();
-    let label_clone_adblock = label.clone();
-
-    // println!("Rust: Creating WebviewBuilder for {}", label);
-    let mut builder = tauri::webview::WebviewBuilder::new(&label, WebviewUrl::External(url_parsed));
-    
-    #[cfg(target_os = "windows")]
-    {
-         // Chrome Extensions Support (Windows)
-         let mut args = Vec::new();
-         args.push("--ignore-certificate-errors".to_string());
-         
-         // Load unpacked extensions if available
-         if let Some(ext_path) = get_extension_path(&app_handle_dl) {
-             if let Ok(entries) = std::fs::read_dir(&ext_path) {
-                 let paths: Vec<String> = entries
-                     .filter_map(|e| e.ok())
-                     .filter(|e| e.path().is_dir())
-                     .map(|e| e.path().to_string_lossy().into_owned())
-                     .collect();
-                 
-                 if !paths.is_empty() {
-                     let ext_arg = format!("--load-extension={}", paths.join(","));
-                     println!("Lumina Extensions: Loading {}", ext_arg);
-                     args.push(ext_arg);
-                 }
-             }
-         }
-         
-         for arg in args {
-            builder = builder.additional_browser_args(&arg);
-         }
-
-         builder = builder.user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0");
-    }
-    #[cfg(target_os = "linux")]
-    {
-        builder = builder.user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
-    }
-    #[cfg(target_os = "macos")]
-    {
-        builder = builder.user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
+fn set_site_permission(history_manager: tauri::State<'_, HistoryManager>, origin: String, permission: String, state: String) -> Result<(), LuminaError> {
+    match state.as_str() {
+        "allow" | "deny" => history_manager.set_site_permission(&origin, &permission, &state),
+        "ask" => history_manager.remove_site_permission(&origin, &permission),
+        _ => return Err(LuminaError::InvalidInput(format!("Invalid permission state: {}", state))),
     }
+    .map_err(LuminaError::from)
+}
 
-    builder = builder.initialization_script(&full_script)
-        .on_web_resource_request(move |request, response| {
-             // Lumina Stealth: Rust-side Ad/Tracker Blocking
-             let referer = request.headers().get("referer").and_then(|h| h.to_str().ok());
-             if check_adblock_url(&request.uri().to_string(), referer, &label_clone_adblock, &app_clone_adblock) {
-                   *response = tauri::http::Response::builder()
-                    .status(403)
-                    .body(std::borrow::Cow::Owned(Vec::new()))
-                    .unwrap();
-            }
-        })
-        .on_download(move |_webview, event| {
-            match event {
-                tauri::webview::DownloadEvent::Requested { url, destination: _ } => {
-                    println!("Download requested: {}", url);
-                    let url_str = url.to_string();
-                    let mut file_name = url.as_str().split('/').next_back().unwrap_or("file").to_string();
-                    if file_name.is_empty() {
-                        file_name = "downloaded_file".to_string();
-                    }
-                    let app = app_handle_dl.clone();
-                    
-                    tauri::async_runtime::spawn(async move {
-                         download_file(app, url_str, file_name).await;
-                    });
-                    false // Suppress native download
-                }
-                _ => true
-            }
-        })
-
-        .on_navigation(move |url: &Url| {
-            // println!("Navigation: {} -> {}", label_clone, url);
-            
-            // Explicitly allow lumina-app scheme to bypass some restrictions
-            if url.scheme() == "lumina-app" {
-                 println!("Navigation ALLOWED (internal): {}", url);
-                 return true;
-            }
-
-            let _ = app_handle.emit("tab-navigation", TabNavigationPayload {
-                label: label_clone.clone(),
-                url: url.to_string(),
-            });
-            
-            true
-        });
-
-    // Use add_child to create the webview inside the existing window
-    // println!("Rust: Attempting to add_child for {}", label);
-    // Explicitly handle the Result immediately to catch panic or error
-     let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-         // println!("Rust: Inside catch_unwind, calling add_child");
-         target_window.add_child(
-             builder,
-             tauri::LogicalPosition::new(x, y),
-             tauri::LogicalSize::new(tab_width, tab_height),
-         )
-     }));
-
-    match res {
-        Ok(inner_res) => {
-             match inner_res {
-                Ok(webview) => {
-                    // println!("Rust: Tab {} created successfully. Actual label: {}", label, webview.label());
-                    // Force tracking of the new webview
-                    // In Tauri v2, add_child might not immediately register the webview in the app handle's map
-                    // But the webview object returned is valid.
-                    
-                    // Position and size are already set by add_child
-
-                    // New tab is created. 
-                    
-                    // Optimization: Hide previous tab immediately to prevent stacking/flicker
-                    {
-                        let mut current = state.current_tab.lock().unwrap();
-                        if let Some(ref old_label) = *current {
-                             if let Some(old_webview) = app.get_webview(old_label) {
-                                 let _ = old_webview.hide();
-                             }
-                        }
-                        *current = Some(label.clone());
-                    }
-
-                    let _ = webview.show();
-                    let _ = webview.set_focus();
-                    
-                    // Explicitly ensure navigation (fix for WebView2 Source being null)
-                    // This forces the webview to navigate even if the builder initialization missed it
-                    // Using eval since load_url is not available on Webview struct in this context
-                    let json_url = serde_json::to_string(&url).unwrap_or_else(|_| format!("'{}'", url));
-                    let nav_script = format!("window.location.replace({})", json_url);
-                    if let Err(e) = webview.eval(&nav_script) {
-                        println!("Rust: Failed to force load url via eval: {}", e);
-                    }
-
-                     // Force tracking settings
-                     let _ = webview.set_auto_resize(false);
-                     
-                     // Force resize to ensure visibility (Fix for black screen / 0x0 size)
-                     let _ = webview.set_size(tauri::LogicalSize::new(tab_width, tab_height));
-                     // Force position to ensure it doesn't overlap with the top bar (Fix for production layout issue)
-                     let _ = webview.set_position(tauri::LogicalPosition::new(x, y));
-
-                    let _ = app.emit::<TabCreatedPayload>("tab-created", TabCreatedPayload {
-                        label: label.clone(),
-                        url: url.clone(),
-                    });
-
-                },
-                Err(e) => {
-                    println!("Rust: Error creating tab {}: {:?}", label, e);
-                    return Err(format!("Failed to create tab: {:?}", e));
-                }
-            }
-        },
-        Err(payload) => {
-             println!("Rust: add_child PANICKED for {}: {:?}", label, payload);
-             return Err("add_child panicked".to_string());
-        }
-    }
-    
-    Ok(())
+#[tauri::command]
+fn get_flags(state: tauri::State<'_, AppDataStore>) -> Flags {
+    state.flags()
 }
 
 #[tauri::command]
-fn switch_tab(app: AppHandle, state: tauri::State<'_, UiState>, label: String) {
-    println!("Switching to tab: {}", label);
-    
-    let mut current = state.current_tab.lock().unwrap();
-    
-    // Optimization: Only hide the previously active tab instead of iterating all webviews
-    if let Some(ref old_label) = *current {
-        if old_label != &label {
-            if let Some(old_webview) = app.get_webview(old_label) {
-                let _ = old_webview.hide();
-            }
-        }
-    } else {
-        // Fallback: If no current tab tracked yet (first switch), hide all others
-        let webviews = app.webviews();
-        for webview in webviews {
-            let webview_instance = &webview.1; 
-            if webview_instance.label() != "main" && webview_instance.label() != label {
-                let _ = webview_instance.hide();
-            }
-        }
-    }
-    
-    // Show the new tab
-    if let Some(webview) = app.get_webview(&label) {
-        let _ = webview.show();
-        let _ = webview.set_focus();
+fn set_flag(app: AppHandle, state: tauri::State<'_, AppDataStore>, id: String, enabled: bool) -> Result<(), LuminaError> {
+    if !state.set_flag(&id, enabled) {
+        return Err(LuminaError::InvalidInput(format!("Unknown flag: {}", id)));
     }
-    
-    // Update state
-    *current = Some(label);
+    state.save();
+    events::emit(&app, AppEvent::FlagsChanged(state.flags()));
+    Ok(())
 }
 
 #[tauri::command]
-fn close_tab(app: AppHandle, label: String) {
-    if let Some(webview) = app.get_webview(&label) {
-        let _ = webview.close();
-        let _ = app.emit("tab-closed", TabClosedPayload { label });
-    }
+fn reset_flags(app: AppHandle, state: tauri::State<'_, AppDataStore>) -> Result<(), LuminaError> {
+    state.reset_flags();
+    state.try_save()?;
+    events::emit(&app, AppEvent::FlagsChanged(state.flags()));
+    Ok(())
 }
 
 #[tauri::command]
-async fn init_browser(app: AppHandle, window: tauri::Window) {
+async fn init_browser(app: AppHandle, window: tauri::Window) -> Result<(), LuminaError> {
     // This function is kept for backward compatibility or initial setup
     // But mostly we will use create_tab now.
-    // Let's just resize the main webview here to be safe.
-    
-    let window_size = window.inner_size().unwrap();
-    let scale_factor = window.scale_factor().unwrap();
-    let logical_size = window_size.to_logical::<f64>(scale_factor);
-    let top_ui_height = 104.0;
-    
-    if let Some(main_webview) = app.get_webview("main") {
-        let _ = main_webview.set_size(tauri::LogicalSize::new(logical_size.width, top_ui_height));
-    }
+    // Let's just lay the window's UI webview out here to be safe.
+    relayout(&app, window.label())
 }
 
-async fn download_file(app: AppHandle, url: String, file_name: String) {
-    let download_dir = app.path().download_dir().unwrap_or(std::path::PathBuf::from("downloads"));
-    if !download_dir.exists() {
-        let _ = tokio::fs::create_dir_all(&download_dir).await;
-    }
-    let path = download_dir.join(&file_name);
-    let path_str = path.to_string_lossy().to_string();
-
-    // Use DownloadManager
-    let manager = app.state::<DownloadManager>();
-    
-    // Check existing file size
-    let mut downloaded: u64 = 0;
-    if path.exists() {
-        if let Ok(metadata) = tokio::fs::metadata(&path).await {
-             downloaded = metadata.len();
-        }
-    }
-
-    // Register
-    {
-        let mut data = manager.downloads.lock().unwrap();
-        data.insert(url.clone(), DownloadItem {
-            url: url.clone(),
-            file_name: file_name.clone(),
-            total_size: 0,
-            downloaded_size: downloaded,
-            path: path_str.clone(),
-            status: "downloading".to_string(),
-            added_at: chrono::Utc::now().timestamp(),
-        });
-    }
-    manager.save();
-
-    let _ = app.emit("download-started", DownloadStartedPayload {
-        url: url.clone(),
-        file_name: file_name.clone(),
-    });
-
-    let client = reqwest::Client::new();
-    let mut request = client.get(&url);
-    
-    if downloaded > 0 {
-        request = request.header("Range", format!("bytes={}-", downloaded));
-    }
-
-    match request.send().await {
-        Ok(res) => {
-            let status = res.status();
-            let total_size = res.content_length().unwrap_or(0) + downloaded;
-            
-            let mut file;
-            if status == reqwest::StatusCode::PARTIAL_CONTENT {
-                 match tokio::fs::OpenOptions::new().create(true).append(true).open(&path).await {
-                    Ok(mut f) => {
-                        // Use AsyncSeekExt (restored)
-                        let _ = f.seek(std::io::SeekFrom::End(0)).await;
-                        file = f;
-                    }
-                    Err(e) => {
-                         println!("Failed to open file for append: {}", e);
-                         manager.update_status(&url, "failed");
-                         let _ = app.emit("download-finished", DownloadFinishedPayload {
-                            url: url.clone(),
-                            success: false,
-                            path: None,
-                        });
-                        return;
-                    }
-                 }
-            } else {
-                downloaded = 0;
-                match tokio::fs::File::create(&path).await {
-                    Ok(f) => file = f,
-                    Err(e) => {
-                         println!("Failed to create file: {}", e);
-                         manager.update_status(&url, "failed");
-                         let _ = app.emit("download-finished", DownloadFinishedPayload {
-                            url: url.clone(),
-                            success: false,
-                            path: None,
-                        });
-                        return;
-                    }
-                }
-            }
-
-            let mut stream = res.bytes_stream();
-            let mut last_save = std::time::Instant::now();
-
-            while let Some(item) = stream.next().await {
-                match item {
-                    Ok(chunk) => {
-                        if (file.write_all(&chunk).await).is_err() {
-                             manager.update_status(&url, "failed");
-                             return;
-                        }
-                        downloaded += chunk.len() as u64;
-                        manager.update_progress(&url, downloaded, total_size);
-                        
-                        if last_save.elapsed().as_secs() > 5 {
-                            manager.save();
-                            last_save = std::time::Instant::now();
-                        }
-
-                        let _ = app.emit("download-progress", DownloadProgressPayload {
-                            url: url.clone(),
-                            progress: downloaded,
-                            total: total_size,
-                        });
-                    }
-                    Err(_) => {
-                         manager.update_status(&url, "failed");
-                         return;
-                    }
-                }
-            }
-            
-            // Ensure file is written and closed
-            let _ = file.sync_all().await;
-            drop(file);
-
-            manager.update_status(&url, "completed");
-            manager.save();
-
-            let _ = app.emit("download-finished", DownloadFinishedPayload {
-                url: url.clone(),
-                success: true,
-                path: Some(path_str),
-            });
-        }
-        Err(_) => {
-            manager.update_status(&url, "failed");
-             let _ = app.emit("download-finished", DownloadFinishedPayload {
-                url: url.clone(),
-                success: false,
-                path: None,
-            });
+// 2. Chrome Extension Support (loaded on Windows only)
+// Allows loading unpacked extensions from a specific directory
+fn get_extension_path(app: &AppHandle) -> Option<PathBuf> {
+    if let Ok(app_data) = app.path().app_data_dir() {
+        let extensions_dir = app_data.join("extensions");
+        if !extensions_dir.exists() {
+            let _ = std::fs::create_dir_all(&extensions_dir);
         }
+        Some(extensions_dir)
+    } else {
+        None
     }
 }
 
-#[tauri::command]
-fn get_downloads(app: AppHandle) -> Vec<DownloadItem> {
-    let manager = app.state::<DownloadManager>();
-    let data = manager.downloads.lock().unwrap();
-    data.values().cloned().collect()
+/// Set once the installed extensions differ from what the running webviews were started with
+static EXTENSIONS_CHANGED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExtensionInfo {
+    /// Folder name under app_data/extensions
+    id: String,
+    name: String,
+    version: String,
+    description: String,
+    manifest_version: u64,
+    path: String,
+    enabled: bool,
 }
 
-#[tauri::command]
-async fn resume_download(app: AppHandle, url: String) -> Result<(), String> {
-    let manager = app.state::<DownloadManager>();
-    let item = {
-        let data = manager.downloads.lock().unwrap();
-        data.get(&url).cloned()
+/// Resolves "__MSG_key__" manifest strings from _locales/<default_locale>/messages.json.
+fn localize_manifest_string(dir: &std::path::Path, manifest: &serde_json::Value, value: &str) -> String {
+    let Some(key) = value.strip_prefix("__MSG_").and_then(|v| v.strip_suffix("__")) else {
+        return value.to_string();
     };
-    
-    if let Some(item) = item {
-        download_file(app, item.url, item.file_name).await;
-        Ok(())
-    } else {
-        Err("Download not found".to_string())
-    }
+    let locale = manifest["default_locale"].as_str().unwrap_or("en");
+    std::fs::read_to_string(dir.join("_locales").join(locale).join("messages.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&content).ok())
+        .and_then(|messages| {
+            messages.iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .and_then(|(_, v)| v["message"].as_str().map(|m| m.to_string()))
+        })
+        .unwrap_or_else(|| value.to_string())
 }
 
-#[tauri::command]
-async fn check_pwa_manifest(app: AppHandle, state: tauri::State<'_, PwaState>, label: String, url: String) -> Result<(), String> {
-    println!("Checking PWA manifest for {}: {}", label, url);
-    let client = reqwest::Client::new();
-    match client.get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/144.0.0.0 Safari/537.36 Edg/144.0.0.0")
-        .send()
-        .await 
-    {
-        Ok(res) => {
-            let status = res.status();
-            println!("Manifest fetch status: {}", status);
-            
-            let text = res.text().await.unwrap_or_default();
-            // println!("Manifest raw content: {}", text); // Uncomment for full debug if needed
-
-            if let Ok(manifest) = serde_json::from_str::<serde_json::Value>(&text) {
-                 println!("PWA Manifest fetched for {}: {:?}", label, manifest);
-                 if let Some(display) = manifest.get("display").and_then(|v: &serde_json::Value| v.as_str()) {
-                     println!("PWA Manifest display mode: {}", display);
-                     if display == "standalone" || display == "minimal-ui" || display == "fullscreen" {
-                         println!("PWA Manifest confirmed via Rust for {}", label);
-                         
-                         // Find best icon
-                         let mut best_icon_url = None;
-                         let mut max_area = 0;
-                         if let Some(icons) = manifest.get("icons").and_then(|v| v.as_array()) {
-                             for icon in icons {
-                                 if let Some(src) = icon.get("src").and_then(|v| v.as_str()) {
-                                     if let Some(sizes) = icon.get("sizes").and_then(|v| v.as_str()) {
-                                         for size in sizes.split_whitespace() {
-                                             if size == "any" { continue; }
-                                             if let Some((w, h)) = size.split_once('x') {
-                                                 if let (Ok(w), Ok(h)) = (w.parse::<i32>(), h.parse::<i32>()) {
-                                                     if w * h > max_area {
-                                                         max_area = w * h;
-                                                         best_icon_url = Some(src.to_string());
-                                                     }
-                                                 }
-                                             }
-                                         }
-                                     }
-                                     // Fallback if no sizes and we haven't found a better one yet
-                                     if best_icon_url.is_none() {
-                                         best_icon_url = Some(src.to_string());
-                                     }
-                                 }
-                             }
-                         }
-                         
-                         // Resolve relative URL
-                         let final_icon_url = if let Some(u) = best_icon_url {
-                             if let Ok(base) = url::Url::parse(&url) {
-                                 if let Ok(joined) = base.join(&u) {
-                                     Some(joined.to_string())
-                                 } else {
-                                     Some(u)
-                                 }
-                             } else {
-                                 Some(u)
-                             }
-                         } else {
-                             None
-                         };
+/// Reads and validates an unpacked extension's manifest.json.
+fn read_extension_manifest(dir: &std::path::Path) -> Result<ExtensionInfo, String> {
+    let content = std::fs::read_to_string(dir.join("manifest.json")).map_err(|_| "manifest.json not found".to_string())?;
+    let manifest: serde_json::Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("Invalid manifest.json: {}", e))?;
+
+    let manifest_version = manifest["manifest_version"].as_u64().ok_or("manifest_version missing")?;
+    if !(2..=3).contains(&manifest_version) {
+        return Err(format!("Unsupported manifest_version {}", manifest_version));
+    }
+    let name = manifest["name"].as_str().filter(|n| !n.trim().is_empty()).ok_or("Extension name missing")?;
+    let version = manifest["version"].as_str().ok_or("Extension version missing")?;
+    let valid_version = version.split('.').count() <= 4 && version.split('.').all(|part| part.parse::<u32>().is_ok());
+    if !valid_version {
+        return Err(format!("Invalid version \"{}\"", version));
+    }
 
-                         if let Some(u) = &final_icon_url {
-                              state.icons.lock().unwrap().insert(label.clone(), u.clone());
-                         }
+    Ok(ExtensionInfo {
+        id: dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        enabled: true,
+        name: localize_manifest_string(dir, &manifest, name),
+        version: version.to_string(),
+        description: localize_manifest_string(dir, &manifest, manifest["description"].as_str().unwrap_or("")),
+        manifest_version,
+        path: dir.to_string_lossy().to_string(),
+    })
+}
 
-                         let _ = app.emit("pwa-can-install", TabPwaPayload { label, icon_url: final_icon_url });
-                     }
-                 } else {
-                     println!("PWA Manifest missing 'display' field or invalid.");
-                 }
-            } else {
-                println!("Failed to parse PWA manifest JSON. Raw content start: {:.200}", text);
-            }
+fn copy_dir_all(src: &std::path::Path, dst: &std::path::Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
         }
-        Err(e) => println!("Failed to fetch manifest: {}", e),
     }
     Ok(())
 }
 
-#[tauri::command]
-async fn run_kip_code(app: tauri::AppHandle, code: String) -> Result<String, String> {
-    use tauri_plugin_shell::ShellExt;
-    use tauri_plugin_shell::process::CommandEvent;
-
-    let sidecar = app.shell().sidecar("kip-lang")
-        .map_err(|e| e.to_string())?;
-
-    let (mut rx, mut child) = sidecar
-        .spawn()
-        .map_err(|e| e.to_string())?;
-
-    // Send code + exit command to ensure the sidecar processes and terminates
-    let input = format!("{}\nexit\n", code);
-    child.write(input.as_bytes()).map_err(|e| e.to_string())?;
-
-    let mut output = String::new();
-    while let Some(event) = rx.recv().await {
-        match event {
-            CommandEvent::Stdout(line) => {
-                let text = String::from_utf8_lossy(&line);
-                output.push_str(&text);
-            }
-            CommandEvent::Stderr(line) => {
-                let text = String::from_utf8_lossy(&line);
-                println!("Kip Stderr: {}", text);
-            }
-            CommandEvent::Terminated(_) => {
-                break;
-            }
-            _ => {}
+/// The id of an extension that didn't come in a signed package: like Chrome, from the "key" in its
+/// manifest, or else from where it was installed from.
+fn unpacked_extension_id(staged: &std::path::Path, source: &std::path::Path) -> String {
+    use base64::Engine as _;
+
+    let key = std::fs::read_to_string(staged.join("manifest.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(content.trim_start_matches('\u{feff}')).ok())
+        .and_then(|manifest| manifest["key"].as_str().and_then(|key| base64::engine::general_purpose::STANDARD.decode(key).ok()));
+    match key {
+        Some(key) => crx::extension_id(&key),
+        None => {
+            let source = std::fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+            crx::extension_id(source.to_string_lossy().as_bytes())
         }
     }
-    
-    Ok(output)
 }
 
-#[tauri::command]
-async fn run_networking_command(state: tauri::State<'_, NetworkState>, command: String, payload: String) -> Result<String, String> {
-    let (tx, rx) = tokio::sync::oneshot::channel();
-    state.tx.send(NetworkSidecarRequest {
-        command,
-        payload,
-        response_tx: tx
-    }).await.map_err(|e| e.to_string())?;
+/// Moves a validated extension from `staged` into app_data/extensions/<id>, replacing an older copy
+/// of the same extension.
+fn commit_extension_install(app: &AppHandle, staged: &std::path::Path, id: &str) -> Result<ExtensionInfo, String> {
+    let extensions_dir = get_extension_path(app).ok_or("No extensions folder")?;
+    if let Err(e) = read_extension_manifest(staged) {
+        let _ = std::fs::remove_dir_all(staged);
+        return Err(e);
+    }
 
-    rx.await.map_err(|e| e.to_string())
-}
+    let target = extensions_dir.join(id);
+    if target.exists() {
+        std::fs::remove_dir_all(&target).map_err(|e| e.to_string())?;
+    }
+    std::fs::rename(staged, &target).map_err(|e| e.to_string())?;
 
-#[tauri::command]
-fn run_sidekick(app: tauri::AppHandle) -> Result<String, String> {
-    use tauri_plugin_shell::ShellExt;
-    
-    let sidecar = app.shell().sidecar("lumina-sidekick")
-        .map_err(|e| e.to_string())?;
+    let info = read_extension_manifest(&target)?;
+    println!("Lumina Extensions: Installed {} {}", info.name, info.version);
+    extensions_changed(app);
+    Ok(info)
+}
 
-    let (mut _rx, _child) = sidecar
-        .spawn()
-        .map_err(|e| e.to_string())?;
+fn extensions_changed(app: &AppHandle) {
+    EXTENSIONS_CHANGED.store(true, std::sync::atomic::Ordering::Relaxed);
+    events::emit(app, AppEvent::ExtensionsChanged);
+}
 
-    Ok("Sidekick started".to_string())
+fn extension_staging_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = get_extension_path(app)
+        .ok_or("No extensions folder")?
+        .join(format!(".staging-{}", chrono::Utc::now().timestamp_micros()));
+    Ok(dir)
 }
 
+/// Installs an unpacked extension folder, or a local .crx / .zip file.
 #[tauri::command]
-fn run_lua_code(app: AppHandle, code: String) -> Result<String, String> {
-    let state = app.state::<LuaState>();
-    let result = {
-        if let Ok(lua) = state.lua.lock() {
-            lua.load(&code).eval::<String>().map_err(|e| e.to_string())
+fn install_extension_from_path(app: AppHandle, path: String) -> Result<ExtensionInfo, LuminaError> {
+    let source = PathBuf::from(&path);
+    let staged = extension_staging_dir(&app)?;
+    let signed_id = if source.is_dir() {
+        read_extension_manifest(&source)?;
+        copy_dir_all(&source, &staged)?;
+        None
+    } else {
+        let bytes = std::fs::read(&source)?;
+        if bytes.starts_with(b"Cr24") {
+            let crx = crx::open(&bytes)?;
+            unzip_into(std::io::Cursor::new(crx.archive), &staged)?;
+            Some(crx.id)
         } else {
-            Err("Failed to lock Lua state".to_string())
+            unzip_into(std::io::Cursor::new(&bytes), &staged)?;
+            None
         }
     };
-    result
+    let id = signed_id.unwrap_or_else(|| unpacked_extension_id(&staged, &source));
+    commit_extension_install(&app, &staged, &id).map_err(LuminaError::from)
 }
 
-// 2. Chrome Extension Support (Windows Only)
-// Allows loading unpacked extensions from a specific directory
-#[cfg(target_os = "windows")]
-fn get_extension_path(app: &AppHandle) -> Option<PathBuf> {
-    if let Ok(app_data) = app.path().app_data_dir() {
-        let extensions_dir = app_data.join("extensions");
-        if !extensions_dir.exists() {
-            let _ = std::fs::create_dir_all(&extensions_dir);
-        }
-        Some(extensions_dir)
-    } else {
-        None
+/// Downloads a .crx and installs it. Only signed packages are taken from the web.
+#[tauri::command]
+async fn install_extension_from_crx(app: AppHandle, url: String) -> Result<ExtensionInfo, LuminaError> {
+    let client = http::client(&app, http::Purpose::Page)?;
+    let bytes = http::get_with_retry(&client, &url).await
+        .and_then(|res| res.error_for_status())?
+        .bytes().await?;
+
+    let crx = crx::open(&bytes)?;
+    let staged = extension_staging_dir(&app)?;
+    unzip_into(std::io::Cursor::new(crx.archive), &staged)?;
+    commit_extension_install(&app, &staged, &crx.id).map_err(LuminaError::from)
+}
+
+#[tauri::command]
+fn list_extensions(app: AppHandle) -> Vec<ExtensionInfo> {
+    let Some(dir) = get_extension_path(&app) else { return Vec::new() };
+    let mut extensions: Vec<ExtensionInfo> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().is_dir() && !e.file_name().to_string_lossy().starts_with('.'))
+                .filter_map(|e| read_extension_manifest(&e.path()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    let store = app.state::<AppDataStore>();
+    for ext in extensions.iter_mut() {
+        ext.enabled = store.is_extension_enabled(&ext.id);
     }
+    extensions.sort_by_key(|e| e.name.to_lowercase());
+    extensions
 }
 
-// === New Browser Feature Commands ===
+#[tauri::command]
+fn remove_extension(app: AppHandle, id: String) -> Result<(), LuminaError> {
+    let dir = get_extension_path(&app).ok_or("No extensions folder")?;
+    let target = dir.join(sanitize_filename(&id));
+    if id.is_empty() || !target.is_dir() {
+        return Err(LuminaError::NotFound("Extension not found".to_string()));
+    }
+    std::fs::remove_dir_all(&target)?;
+    let store = app.state::<AppDataStore>();
+    store.set_extension_enabled(&id, true);
+    store.save();
+    extensions_changed(&app);
+    Ok(())
+}
 
+/// Keeps the files but leaves the extension out of `--load-extension`.
 #[tauri::command]
-fn set_zoom_level(history_manager: tauri::State<'_, HistoryManager>, domain: String, zoom: i32) -> Result<(), String> {
-    history_manager.set_zoom_level(&domain, zoom).map_err(|e| e.to_string())
+fn set_extension_enabled(app: AppHandle, state: tauri::State<'_, AppDataStore>, id: String, enabled: bool) -> Result<(), LuminaError> {
+    let installed = get_extension_path(&app).is_some_and(|dir| dir.join(sanitize_filename(&id)).is_dir());
+    if id.is_empty() || !installed {
+        return Err(LuminaError::NotFound("Extension not found".to_string()));
+    }
+    if state.is_extension_enabled(&id) != enabled {
+        state.set_extension_enabled(&id, enabled);
+        state.save();
+        extensions_changed(&app);
+    }
+    Ok(())
 }
 
+/// Extensions are passed to WebView2 at startup, so changes need a relaunch.
 #[tauri::command]
-fn get_zoom_level(history_manager: tauri::State<'_, HistoryManager>, domain: String) -> Result<i32, String> {
-    history_manager.get_zoom_level(&domain).map_err(|e| e.to_string())
+fn restart_app(app: AppHandle) {
+    app.restart();
 }
 
+// === New Browser Feature Commands ===
+
 #[tauri::command]
 #[allow(clippy::too_many_arguments)]
-fn set_cookie(history_manager: tauri::State<'_, HistoryManager>, domain: String, name: String, value: String, expires: Option<i64>, path: Option<String>, secure: bool, http_only: bool) -> Result<(), String> {
+fn set_cookie(history_manager: tauri::State<'_, HistoryManager>, domain: String, name: String, value: String, expires: Option<i64>, path: Option<String>, secure: bool, http_only: bool) -> Result<(), LuminaError> {
     let p = path.unwrap_or_else(|| "/".to_string());
     let cookie = history_manager::CookieItem {
         domain,
@@ -3328,21 +5531,127 @@ fn set_cookie(history_manager: tauri::State<'_, HistoryManager>, domain: String,
         secure,
         http_only,
     };
-    history_manager.set_cookie(cookie).map_err(|e| e.to_string())
+    history_manager.set_cookie(cookie).map_err(LuminaError::from)
 }
 
 #[tauri::command]
-fn get_cookies(history_manager: tauri::State<'_, HistoryManager>, domain: String) -> Result<Vec<history_manager::CookieItem>, String> {
-    history_manager.get_cookies(&domain).map_err(|e| e.to_string())
+fn get_cookies(history_manager: tauri::State<'_, HistoryManager>, domain: String) -> Result<Vec<history_manager::CookieItem>, LuminaError> {
+    history_manager.get_cookies(&domain).map_err(LuminaError::from)
 }
 
 #[tauri::command]
-fn delete_cookie(history_manager: tauri::State<'_, HistoryManager>, domain: String, name: String) -> Result<(), String> {
-    history_manager.delete_cookie(&domain, &name).map_err(|e| e.to_string())
+fn delete_cookie(history_manager: tauri::State<'_, HistoryManager>, domain: String, name: String) -> Result<(), LuminaError> {
+    history_manager.delete_cookie(&domain, &name).map_err(LuminaError::from)
+}
+
+const TRAY_ID: &str = "main";
+const TRAY_RECENT_LIMIT: usize = 5;
+
+fn tray_label(text: &str, max: usize) -> String {
+    if text.chars().count() > max {
+        format!("{}…", text.chars().take(max).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+/// Builds the tray menu from the current history, downloads and adblock state.
+fn build_tray_menu(app: &AppHandle) -> tauri::Result<tauri::menu::Menu<tauri::Wry>> {
+    use tauri::menu::{CheckMenuItem, MenuBuilder, MenuItem};
+
+    let mut builder = MenuBuilder::new(app);
+
+    let recent = app.state::<HistoryManager>().get_recent(TRAY_RECENT_LIMIT as i64).unwrap_or_default();
+    if !recent.is_empty() {
+        builder = builder.item(&MenuItem::with_id(app, "tray-recent", "Son ziyaret edilenler", false, None::<&str>)?);
+        for item in recent.iter().take(TRAY_RECENT_LIMIT) {
+            let title = if item.title.trim().is_empty() { &item.url } else { &item.title };
+            builder = builder.item(&MenuItem::with_id(app, format!("history:{}", item.url), tray_label(title, 40), true, None::<&str>)?);
+        }
+        builder = builder.separator();
+    }
+
+    let active: Vec<DownloadItem> = app.state::<DownloadManager>().downloads.lock().unwrap()
+        .values()
+        .filter(|d| d.status == "downloading")
+        .cloned()
+        .collect();
+    if !active.is_empty() {
+        builder = builder.item(&MenuItem::with_id(app, "tray-downloads", "İndirmeler", false, None::<&str>)?);
+        for d in &active {
+            let progress = match (d.downloaded_size * 100).checked_div(d.total_size) {
+                Some(percent) => format!("{}%", percent),
+                None => format!("{:.1} MB", d.downloaded_size as f64 / 1_048_576.0),
+            };
+            builder = builder.item(&MenuItem::with_id(app, "downloads", format!("{} — {}", tray_label(&d.file_name, 32), progress), true, None::<&str>)?);
+        }
+        builder = builder.separator();
+    }
+
+    let adblock = ADBLOCK_ENABLED.load(std::sync::atomic::Ordering::Relaxed);
+    builder
+        .item(&MenuItem::with_id(app, "new_tab", "Yeni Sekme", true, None::<&str>)?)
+        .item(&MenuItem::with_id(app, "new_private", "Yeni Gizli Pencere", true, None::<&str>)?)
+        .item(&CheckMenuItem::with_id(app, "adblock", "Reklam Engelleyici", true, adblock, None::<&str>)?)
+        .separator()
+        .item(&MenuItem::with_id(app, "show", "Göster", true, None::<&str>)?)
+        .item(&MenuItem::with_id(app, "quit", "Çıkış", true, None::<&str>)?)
+        .build()
+}
+
+/// Swaps in a freshly built tray menu; called whenever one of its sections may have changed.
+fn refresh_tray_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else { return };
+    match build_tray_menu(app) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => eprintln!("Failed to rebuild tray menu: {}", e),
+    }
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Brings the main window forward on the downloads page.
+pub(crate) fn show_downloads(app: &AppHandle) {
+    show_main_window(app);
+    events::emit(app, AppEvent::RequestNewTab(TabCreatedPayload { label: "new-tab".to_string(), url: "lumina-app://downloads".to_string() }));
+}
+
+fn handle_tray_menu_event(app: &AppHandle, id: &str) {
+    let open_tab = |url: &str| {
+        show_main_window(app);
+        events::emit(app, AppEvent::RequestNewTab(TabCreatedPayload { label: "new-tab".to_string(), url: url.to_string() }));
+    };
+    match id {
+        "quit" => app.exit(0),
+        "show" => show_main_window(app),
+        "new_tab" => open_tab(""),
+        "new_private" => {
+            show_main_window(app);
+            events::emit(app, AppEvent::RequestPrivateTab);
+        }
+        "downloads" => show_downloads(app),
+        "adblock" => {
+            set_adblock_enabled(app, !ADBLOCK_ENABLED.load(std::sync::atomic::Ordering::Relaxed));
+        }
+        id => {
+            if let Some(url) = id.strip_prefix("history:") {
+                open_tab(url);
+            }
+        }
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    startup::begin();
+
     #[cfg(target_os = "linux")]
     std::env::set_var("WEBKIT_DISABLE_DMABUF_RENDERER", "1");
 
@@ -3359,6 +5668,7 @@ pub fn run() {
     builder
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_deep_link::init())
         .plugin(
             tauri_plugin_global_shortcut::Builder::new().with_handler(|app, shortcut, event| {
@@ -3366,7 +5676,7 @@ pub fn run() {
                     if let Some(window) = app.get_webview_window("main") {
                         if window.is_visible().unwrap_or(false) {
                             // If window is visible, we toggle the command palette UI instead of hiding the window
-                            let _ = window.emit("toggle-command-palette", ());
+                            events::emit(window.app_handle(), AppEvent::ToggleCommandPalette);
                             let _ = window.set_focus();
                         } else {
                             let _ = window.show();
@@ -3399,121 +5709,87 @@ pub fn run() {
                 (full_path, "")
             };
             
-            let path = path.trim_end_matches('/');
+            let origin = internal_request_origin(&request);
+            let origin = origin.as_deref();
+            // Web pages may link here, but not fetch, frame or embed internal pages
+            if origin.is_some_and(|origin| !is_internal_origin(origin)) {
+                println!("Lumina-App: refused {} requested by {:?}", uri, origin);
+                return internal_response(403, "<h1>403 Forbidden</h1>".to_string(), None);
+            }
+
+            let Some(path) = normalize_internal_path(path) else {
+                println!("Lumina-App: refused path {}", path);
+                return internal_response(400, "<h1>400 Bad Request</h1>".to_string(), origin);
+            };
+            let path = path.as_str();
 
-            // Store Installation Handler
+            // Only Lumina's own pages may read the API; a bare visit gets nothing either
+            if let Some(route) = path.strip_prefix("api/") {
+                if !origin.is_some_and(is_internal_origin) {
+                    return internal_response(403, "<h1>403 Forbidden</h1>".to_string(), origin);
+                }
+                return match internal_api(ctx.app_handle(), route, query) {
+                    Some(body) => internal_json_response(200, &body, origin),
+                    None => internal_json_response(404, &serde_json::json!({ "error": format!("Unknown API route {}", route) }), origin),
+                };
+            }
+
+            // Store install links only open a prompt; the package is installed by confirm_install
             if path == "install" {
-                 let id = if let Some(idx) = query.find("id=") {
-                     let rest = &query[idx + 3..];
-                     rest.split('&').next().unwrap_or(rest)
-                 } else {
-                     "unknown"
+                 let id = query_param(query, "id").unwrap_or_default();
+                 println!("Lumina Store: install requested for {}", id);
+                 return match request_install(ctx.app_handle(), &id) {
+                     Ok(item) => internal_response(200, install_confirmation_page(&item), origin),
+                     Err(e) => internal_response(404, store_card_page("Package Unavailable", "#ef4444", "⚠️", &escape_html(&e), ""), origin),
                  };
-                 
-                 println!("Lumina Store: Installing {}", id);
-                 
-                 let success = perform_install(ctx.app_handle(), id);
-                 
-                 let (title, message, color) = if success {
-                     ("Installation Complete", format!("Package <strong>{}</strong> has been successfully installed.", id), "#10b981")
-                 } else {
-                     ("Installation Failed", format!("Failed to install package <strong>{}</strong>.", id), "#ef4444")
+            }
+
+            if path == "uninstall" {
+                 if query_param(query, "token").as_deref() != Some(internal_action_token()) {
+                     println!("Lumina-App: uninstall without a valid action token");
+                     return internal_response(403, "<h1>403 Forbidden</h1><p>Open the Store to remove packages.</p>".to_string(), origin);
+                 }
+                 let id = query_param(query, "id").unwrap_or_else(|| "unknown".to_string());
+                 println!("Lumina Store: Uninstalling {}", id);
+                 let result = perform_uninstall(ctx.app_handle(), &id);
+
+                 let page = match &result {
+                     Ok(()) => store_card_page("Package Removed", "#10b981", "🎉", &format!("Package <strong>{}</strong> has been uninstalled.", escape_html(&id)), ""),
+                     Err(e) => store_card_page("Uninstall Failed", "#ef4444", "⚠️", &format!("Failed to uninstall package <strong>{}</strong>: {}", escape_html(&id), escape_html(e)), ""),
                  };
 
-                 let success_html = format!(r#"
-                    <!DOCTYPE html>
-                    <html>
-                    <head>
-                        <title>{}</title>
-                        <meta charset="UTF-8">
-                        <style>
-                            body {{ font-family: 'Segoe UI', system-ui, sans-serif; background: #0f172a; color: #e2e8f0; margin: 0; display: flex; align-items: center; justify-content: center; height: 100vh; }}
-                            .card {{ background: #1e293b; padding: 40px; border-radius: 16px; text-align: center; border: 1px solid #334155; box-shadow: 0 10px 25px -5px rgba(0, 0, 0, 0.5); animation: popIn 0.3s cubic-bezier(0.175, 0.885, 0.32, 1.275); }}
-                            @keyframes popIn {{ from {{ transform: scale(0.8); opacity: 0; }} to {{ transform: scale(1); opacity: 1; }} }}
-                            h1 {{ color: {}; margin: 0 0 16px 0; font-size: 2rem; }}
-                            p {{ color: #94a3b8; margin-bottom: 24px; }}
-                            .btn {{ background: #3b82f6; color: white; text-decoration: none; padding: 10px 24px; border-radius: 8px; font-weight: 600; transition: background 0.2s; display: inline-block; }}
-                            .btn:hover {{ background: #2563eb; }}
-                        </style>
-                    </head>
-                    <body>
-                        <div class="card">
-                            <div style="font-size: 4rem; margin-bottom: 10px;">{}</div>
-                            <h1>{}</h1>
-                            <p>{}</p>
-                            <a href="lumina-app://store" class="btn">Return to Store</a>
-                        </div>
-                    </body>
-                    </html>
-                 "#, title, color, if success { "🎉" } else { "⚠️" }, title, message);
-                 
                  // Emit Toast for feedback in main window too
-                 let _ = ctx.app_handle().emit("toast", ToastPayload {
-                     message: if success { format!("Sidekick modülü kuruldu: {}", id) } else { format!("Kurulum hatası: {}", id) },
-                     level: if success { "success".to_string() } else { "error".to_string() },
-                 });
-
-                 return tauri::http::Response::builder()
-                    .status(200)
-                    .header("Content-Type", "text/html; charset=utf-8")
-                    .header("Access-Control-Allow-Origin", "*")
-                    .body(success_html.into_bytes())
-                    .unwrap();
+                 events::emit(ctx.app_handle(), AppEvent::Toast(ToastPayload {
+                     message: match result {
+                         Ok(()) => format!("Modül kaldırıldı: {}", id),
+                         Err(_) => format!("Kaldırma hatası: {}", id),
+                     },
+                     level: if result.is_ok() { "success".to_string() } else { "error".to_string() },
+                 }));
+
+                 return internal_response(200, page, origin);
             }
 
             println!("Lumina-App Path: {}", path); // DEBUG LOG
 
-            if let Some(html) = get_internal_page_html(ctx.app_handle(), path) {
-                tauri::http::Response::builder()
-                    .status(200)
-                    .header("Content-Type", "text/html; charset=utf-8")
-                    .header("Access-Control-Allow-Origin", "*")
-                    .body(html.into_bytes())
-                    .unwrap()
-            } else {
-                println!("Lumina-App: Unknown path {}", path);
-                tauri::http::Response::builder()
-                    .status(404)
-                    .header("Content-Type", "text/html; charset=utf-8")
-                    .header("Access-Control-Allow-Origin", "*")
-                    .body(format!("<h1>404 Not Found</h1><p>Path: {}</p>", path).into_bytes())
-                    .unwrap()
+            match get_internal_page_html(ctx.app_handle(), path, query) {
+                Some(html) => internal_response(200, html, origin),
+                None => {
+                    println!("Lumina-App: Unknown path {}", path);
+                    internal_response(404, format!("<h1>404 Not Found</h1><p>Path: {}</p>", escape_html(path)), origin)
+                }
             }
         })
-        .manage(UiState { 
-            sidebar_open: std::sync::atomic::AtomicBool::new(false),
-            suggestions_height: std::sync::atomic::AtomicU32::new(0),
-            current_tab: std::sync::Mutex::new(None),
+        .manage(UiState::new())
+        .manage(LayoutManager::new())
+        .manage(TabRegistry::new())
+        .manage(http::HttpClients::new())
+        .manage(PwaState {
+            icons: std::sync::Mutex::new(std::collections::HashMap::new()),
+            manifests: std::sync::Mutex::new(std::collections::HashMap::new()),
         })
-        .manage(PwaState { icons: std::sync::Mutex::new(std::collections::HashMap::new()) })
         .setup(|app| {
             println!("Lumina: Setup started...");
-            // Initialize Lua (Real Runtime)
-            app.manage(LuaState { lua: Mutex::new(create_lua_runtime()) });
-
-            // Load scripts/init.lua if exists
-            let lua_state = app.state::<LuaState>();
-            if let Ok(lua) = lua_state.lua.lock() {
-                // Try to find init.lua in current dir (dev) or app data dir (prod)
-                let paths = vec![
-                    std::path::PathBuf::from("scripts/init.lua"),
-                    app.path().app_data_dir().unwrap_or_default().join("scripts/init.lua"),
-                ];
-
-                for path in paths {
-                    if path.exists() {
-                        if let Ok(script) = std::fs::read_to_string(&path) {
-                            println!("Executing Lua script: {:?}", path);
-                            if let Err(e) = lua.load(&script).exec() {
-                                eprintln!("Error executing Lua script {:?}: {}", path, e);
-                            } else {
-                                break; // Loaded successfully, stop looking
-                            }
-                        }
-                    }
-                }
-            }
-
             // Sidekick Channel
             let (sidekick_tx, _sidekick_rx) = tokio::sync::mpsc::channel::<String>(32);
             app.manage(SidekickState { tx: sidekick_tx });
@@ -3522,84 +5798,95 @@ pub fn run() {
             let (tx, mut rx) = tokio::sync::mpsc::channel::<NetworkSidecarRequest>(32);
             app.manage(NetworkState { tx });
             
-            let app_handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
-                use tauri_plugin_shell::ShellExt;
-                use tauri_plugin_shell::process::CommandEvent;
-
-                // Start sidecar loop
-                loop {
-                    println!("Starting Lumina-Net Sidecar...");
-                    let sidecar = match app_handle.shell().sidecar("lumina-net") {
-                        Ok(s) => s,
-                        Err(e) => {
-                            eprintln!("Failed to create sidecar command: {}", e);
-                            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-                            continue;
-                        }
-                    };
-
-                    let (mut sidecar_rx, mut sidecar_child) = match sidecar.spawn() {
-                        Ok(v) => v,
-                        Err(e) => {
-                            eprintln!("Failed to spawn sidecar: {}", e);
-                            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-                            continue;
-                        }
-                    };
-
-                    let mut current_response_tx: Option<tokio::sync::oneshot::Sender<String>> = None;
+            // The sidecar only serves page requests, so it can wait for the first paint
+            startup::defer(app.handle(), "lumina-net sidecar", move |app_handle| {
+                let app_handle = app_handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    use tauri_plugin_shell::ShellExt;
+                    use tauri_plugin_shell::process::CommandEvent;
 
+                    // Start sidecar loop
                     loop {
-                        tokio::select! {
-                            req_opt = rx.recv() => {
-                                match req_opt {
-                                    Some(req) => {
-                                         current_response_tx = Some(req.response_tx);
-                                         let request_json = serde_json::json!({
-                                            "command": req.command,
-                                            "payload": serde_json::from_str::<serde_json::Value>(&req.payload).unwrap_or(serde_json::Value::Null)
-                                         });
-                                         let input = format!("{}\n", request_json);
-                                         if let Err(e) = sidecar_child.write(input.as_bytes()) {
-                                             eprintln!("Failed to write to sidecar: {}", e);
-                                             break; 
-                                         }
+                        println!("Starting Lumina-Net Sidecar...");
+                        set_sidecar_status("lumina-net", "starting");
+                        let sidecar = match app_handle.shell().sidecar("lumina-net") {
+                            Ok(s) => s,
+                            Err(e) => {
+                                eprintln!("Failed to create sidecar command: {}", e);
+                                set_sidecar_status("lumina-net", format!("unavailable: {}", e));
+                                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                                continue;
+                            }
+                        };
+
+                        let (mut sidecar_rx, mut sidecar_child) = match sidecar.spawn() {
+                            Ok(v) => v,
+                            Err(e) => {
+                                eprintln!("Failed to spawn sidecar: {}", e);
+                                set_sidecar_status("lumina-net", format!("spawn failed: {}", e));
+                                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                                continue;
+                            }
+                        };
+
+                        set_sidecar_status("lumina-net", format!("running (pid {})", sidecar_child.pid()));
+                        let mut current_response_tx: Option<tokio::sync::oneshot::Sender<String>> = None;
+
+                        loop {
+                            tokio::select! {
+                                req_opt = rx.recv() => {
+                                    match req_opt {
+                                        Some(req) => {
+                                             current_response_tx = Some(req.response_tx);
+                                             let request_json = serde_json::json!({
+                                                "command": req.command,
+                                                "payload": serde_json::from_str::<serde_json::Value>(&req.payload).unwrap_or(serde_json::Value::Null)
+                                             });
+                                             let input = format!("{}\n", request_json);
+                                             if let Err(e) = sidecar_child.write(input.as_bytes()) {
+                                                 eprintln!("Failed to write to sidecar: {}", e);
+                                                 break; 
+                                             }
+                                        }
+                                        None => return, 
                                     }
-                                    None => return, 
                                 }
-                            }
-                            event_opt = sidecar_rx.recv() => {
-                                match event_opt {
-                                    Some(event) => {
-                                        match event {
-                                            CommandEvent::Stdout(line) => {
-                                                let text = String::from_utf8_lossy(&line).to_string();
-                                                if let Some(tx) = current_response_tx.take() {
-                                                    let _ = tx.send(text);
+                                event_opt = sidecar_rx.recv() => {
+                                    match event_opt {
+                                        Some(event) => {
+                                            match event {
+                                                CommandEvent::Stdout(line) => {
+                                                    let text = String::from_utf8_lossy(&line).to_string();
+                                                    if let Some(tx) = current_response_tx.take() {
+                                                        let _ = tx.send(text);
+                                                    }
                                                 }
+                                                CommandEvent::Stderr(line) => {
+                                                    eprintln!("Lumina-Net Stderr: {}", String::from_utf8_lossy(&line));
+                                                }
+                                                CommandEvent::Terminated(t) => {
+                                                    println!("Lumina-Net terminated: {:?}", t);
+                                                    set_sidecar_status("lumina-net", format!("terminated (code {:?})", t.code));
+                                                    break; 
+                                                }
+                                                _ => {}
                                             }
-                                            CommandEvent::Stderr(line) => {
-                                                eprintln!("Lumina-Net Stderr: {}", String::from_utf8_lossy(&line));
-                                            }
-                                            CommandEvent::Terminated(t) => {
-                                                println!("Lumina-Net terminated: {:?}", t);
-                                                break; 
-                                            }
-                                            _ => {}
                                         }
+                                        None => break, 
                                     }
-                                    None => break, 
                                 }
                             }
                         }
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
                     }
-                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                }
+                });
             });
 
             // Initialize Rust Native Security Layer
-            security::init();
+            {
+                let _span = startup::span("security");
+                security::init();
+            }
 
             // Deep Link Registration
             #[cfg(any(windows, target_os = "linux"))]
@@ -3617,48 +5904,141 @@ pub fn run() {
                 }
             }
 
-            // Initialize Adblock Engine
-            tauri::async_runtime::spawn(async move {
-                println!("Initializing Adblock Engine...");
+            // Initialize Adblock Engine with the built-in rules; EasyList is added after the first paint
+            let basic_rules = vec![
+                "||doubleclick.net^", "||googlesyndication.com^", "||adnxs.com^",
+                "||taboola.com^", "||outbrain.com^", "||adservice.google.com^",
+                "/ads.js", "/ad-", "-ad-"
+            ];
+            {
+                let _span = startup::span("adblock engine");
                 let mut filter_set = FilterSet::new(true);
-                
-                // Fallback/Basic Rules
-                let basic_rules = vec![
-                    "||doubleclick.net^", "||googlesyndication.com^", "||adnxs.com^",
-                    "||taboola.com^", "||outbrain.com^", "||adservice.google.com^",
-                    "/ads.js", "/ad-", "-ad-"
-                ];
-                filter_set.add_filters(&basic_rules, adblock::lists::ParseOptions::default());
-
-                // Fetch EasyList
-                match reqwest::get("https://easylist.to/easylist/easylist.txt").await {
-                    Ok(resp) => {
-                         if let Ok(text) = resp.text().await {
-                             println!("Downloaded EasyList, parsing...");
-                             filter_set.add_filters(text.lines().collect::<Vec<_>>(), adblock::lists::ParseOptions::default());
-                         }
-                    },
-                    Err(e) => println!("Failed to fetch EasyList: {}", e),
-                }
-
-                let engine = Engine::from_filter_set(filter_set, true);
-                let _ = ADBLOCK_ENGINE.set(Arc::new(Mutex::new(engine)));
-                println!("Adblock Engine Ready.");
+                filter_set.add_filters(&basic_rules, ::adblock::lists::ParseOptions::default());
+                adblock_lists().lock().unwrap().push(AdblockListInfo {
+                    name: "Lumina built-in".to_string(),
+                    url: None,
+                    version: None,
+                    rule_count: basic_rules.len(),
+                    fetched_at: Some(chrono::Utc::now().timestamp()),
+                    error: None,
+                });
+                let mut engine = Engine::from_filter_set(filter_set, true);
+                engine.use_resources(neutered_resources());
+                install_adblock_engine(engine);
+            }
+
+            startup::defer(app.handle(), "adblock lists", move |app| {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let _span = startup::span("adblock list refresh");
+                    let mut filter_set = FilterSet::new(true);
+                    filter_set.add_filters(&basic_rules, ::adblock::lists::ParseOptions::default());
+
+                    // Fetch EasyList
+                    let easylist_url = "https://easylist.to/easylist/easylist.txt";
+                    let mut easylist = AdblockListInfo {
+                        name: "EasyList".to_string(),
+                        url: Some(easylist_url.to_string()),
+                        version: None,
+                        rule_count: 0,
+                        fetched_at: None,
+                        error: None,
+                    };
+                    let response = match http::client(&app, http::Purpose::Page) {
+                        Ok(client) => http::get_with_retry(&client, easylist_url).await
+                            .and_then(|res| res.error_for_status())
+                            .map_err(LuminaError::from),
+                        Err(e) => Err(e),
+                    };
+                    match response {
+                        Ok(resp) => {
+                             if let Ok(text) = resp.text().await {
+                                 println!("Downloaded EasyList, parsing...");
+                                 let (version, rule_count) = summarize_filter_list(&text);
+                                 easylist.version = version;
+                                 easylist.rule_count = rule_count;
+                                 easylist.fetched_at = Some(chrono::Utc::now().timestamp());
+                                 filter_set.add_filters(text.lines().collect::<Vec<_>>(), ::adblock::lists::ParseOptions::default());
+                             }
+                        },
+                        Err(e) => {
+                            println!("Failed to fetch EasyList: {}", e);
+                            easylist.error = Some(e.to_string());
+                        }
+                    }
+                    let fetched = easylist.error.is_none();
+                    adblock_lists().lock().unwrap().push(easylist);
+                    if !fetched {
+                        return;
+                    }
+
+                    let mut engine = Engine::from_filter_set(filter_set, true);
+                    engine.use_resources(neutered_resources());
+                    install_adblock_engine(engine);
+                    println!("Adblock Engine Ready.");
+                });
             });
 
+            let app_dir = match app.path().app_data_dir() {
+                Ok(dir) => dir,
+                Err(e) => {
+                    eprintln!("Lumina Critical Error: Failed to get app data dir: {}", e);
+                    return Err(e.into());
+                }
+            };
+
+            if !app_dir.exists() {
+                if let Err(e) = std::fs::create_dir_all(&app_dir) {
+                    eprintln!("Lumina Critical Error: Failed to create app data dir: {}", e);
+                    return Err(e.into());
+                }
+            }
+            {
+                let _span = startup::span("profile data");
+                app.manage(AppDataStore::new(app_dir.clone()));
+                app.manage(DownloadManager::new(app_dir.clone()));
+                app.manage(HistoryManager::new(app_dir));
+                if let Some(e) = app.state::<HistoryManager>().key_error().map(str::to_string) {
+                    startup::defer(app.handle(), "field key report", move |app| {
+                        error::report(app, "Şifreleme anahtarı yüklenemedi, çerezler ve form verileri kaydedilmeyecek", &LuminaError::Other(e));
+                    });
+                }
+                load_adblock_state(app.handle());
+                seed_builtin_fixups(app.handle());
+                seed_site_protections(app.handle());
+                load_host_blocklist(app.handle());
+                header_rules::load(app.handle());
+                referrer::load(app.handle());
+                forget_sites::watch_tab_closes(app.handle());
+            }
+
             // Check for PWA args
             let args: Vec<String> = std::env::args().collect();
+            let cli_task = match cli::parse_cli_args(&args) {
+                Ok(task) => task,
+                Err(e) => {
+                    eprintln!("lumina: {}", e);
+                    std::process::exit(2);
+                }
+            };
             let mut pwa_url = None;
-            for arg in args {
+            let mut pwa_handler = None;
+            for arg in &args {
                 if arg.starts_with("--pwa-url=") {
                     pwa_url = Some(arg.replace("--pwa-url=", "").replace("\"", ""));
+                } else if let Some(value) = arg.strip_prefix("--pwa-handler=") {
+                    pwa_handler = Some(value.trim_matches('"').to_string());
                 }
             }
 
             if let Some(url) = pwa_url {
                  let label = format!("pwa-{}", chrono::Utc::now().timestamp_micros());
+                 let installed = app.state::<AppDataStore>().installed_pwa_by_start_url(&url);
+                 let theme_color = installed.as_ref().and_then(|p| p.theme_color.clone());
+                 let background_color = installed.as_ref().and_then(|p| p.background_color.clone());
+                 let data_dir = installed.as_ref().filter(|p| p.isolated).and_then(|p| pwa_data_dir(app.handle(), &p.id));
+                 let (url, launch_script) = resolve_pwa_launch(app.handle(), &url, pwa_handler.as_deref());
                  if let Ok(parsed_url) = url.parse() {
-                     let app_handle = app.handle().clone();
                      let label_clone = label.clone();
                      
                      let invoke_key = app.handle().invoke_key();
@@ -3666,6 +6046,9 @@ pub fn run() {
 
                      let mut builder = tauri::WebviewWindowBuilder::new(app, &label, tauri::WebviewUrl::External(parsed_url))
                         .title("PWA");
+                     if let Some(dir) = data_dir {
+                         builder = builder.data_directory(dir);
+                     }
 
                      #[cfg(target_os = "windows")]
                      {
@@ -3680,52 +6063,102 @@ pub fn run() {
                          builder = builder.user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/144.0.0.0 Safari/537.36");
                      }
 
-                     let _ = builder.inner_size(1024.0, 768.0)
+                     let builder = builder.inner_size(1024.0, 768.0)
                         .decorations(true)
                         .focused(true)
                         .initialization_script(get_lumina_stealth_script())
                         .initialization_script(&pwa_script)
+                        .initialization_script(get_pwa_capability_script(app.handle(), &label))
+                        .initialization_script(launch_script.unwrap_or_default())
+                        .on_page_load(on_pwa_page_load)
                         .on_web_resource_request(move |request, response| {
-                            let referer = request.headers().get("referer").and_then(|h| h.to_str().ok());
-                            if check_adblock_url(&request.uri().to_string(), referer, &label_clone, &app_handle) {
-                                *response = tauri::http::Response::builder()
-                                    .status(403)
-                                    .body(std::borrow::Cow::Owned(Vec::new()))
-                                    .unwrap();
-                            }
-                        })
-                        .build();
+                            handle_adblock_request(&request, response, &label_clone);
+                        });
+                     if let Ok(window) = apply_pwa_colors(builder, theme_color.as_deref(), background_color.as_deref()).build() {
+                         apply_pwa_caption_color(&window, theme_color.as_deref());
+                     }
                  }
                  if let Some(main) = app.get_webview_window("main") {
                      let _ = main.close();
                  }
             }
 
-            let app_dir = match app.path().app_data_dir() {
-                Ok(dir) => dir,
-                Err(e) => {
-                    eprintln!("Lumina Critical Error: Failed to get app data dir: {}", e);
-                    return Err(e.into());
+            // Initialize Lua (Real Runtime on its own worker thread)
+            {
+                let _span = startup::span("lua");
+                app.manage(LuaState::spawn(app.handle().clone()));
+            }
+
+            // Load user scripts (app_data/scripts/*.lua) and watch them for changes
+            startup::defer(app.handle(), "user scripts", |app| {
+                let lua_handle = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let _span = startup::span("user script loading");
+                    load_all_scripts(&lua_handle).await;
+                    start_script_watcher(&lua_handle);
+                });
+            });
+
+            // `--fetch`, `--screenshot` and `--run-lua` run without the browser UI and then exit,
+            // so there is no paint to wait for
+            let headless = cli_task.is_some();
+            if let Some(task) = cli_task {
+                startup::run_deferred(app.handle());
+                cli::start_headless(app.handle(), task);
+            }
+
+            // Start downloads queued in a previous run and promote scheduled ones when due
+            let queue_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    pump_download_queue(&queue_handle);
+                    tokio::time::sleep(DOWNLOAD_SCHEDULE_CHECK_INTERVAL).await;
                 }
-            };
+            });
 
-            if !app_dir.exists() {
-                if let Err(e) = std::fs::create_dir_all(&app_dir) {
-                    eprintln!("Lumina Critical Error: Failed to create app data dir: {}", e);
-                    return Err(e.into());
+            // Clear out icons no installed app uses any more
+            startup::defer(app.handle(), "icon cleanup", icons::start_pruner);
+            startup::defer(app.handle(), "database maintenance", maintenance::start_scheduler);
+
+            // Report blocked-request counts to the UI in batches
+            adblock::start_stats_reporter(app.handle());
+
+            // Watch connectivity to retry failed tabs and resume cut-off downloads when it returns
+            startup::defer(app.handle(), "network monitor", network::start_monitor);
+
+            // Follow the clock for the "scheduled" theme (OS changes arrive as window events)
+            let theme_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    refresh_theme(&theme_handle);
+                    tokio::time::sleep(THEME_CHECK_INTERVAL).await;
                 }
-            }
-            app.manage(AppDataStore::new(app_dir.clone()));
-            app.manage(DownloadManager::new(app_dir.clone()));
-            app.manage(HistoryManager::new(app_dir));
+            });
+
+            // Pick up name, icon and start URL changes of installed apps
+            let pwa_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(PWA_MANIFEST_CHECK_INTERVAL).await;
+                    refresh_installed_pwas(&pwa_handle).await;
+                }
+            });
+
+            // Tab hibernation sweep (no-op unless the flag is enabled)
+            let hibernate_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(TAB_HIBERNATE_CHECK_INTERVAL).await;
+                    hibernate_idle_tabs(&hibernate_handle);
+                }
+            });
 
             // Tray Setup
-            let quit_i = tauri::menu::MenuItem::with_id(app, "quit", "Çıkış", true, None::<&str>)?;
-            let show_i = tauri::menu::MenuItem::with_id(app, "show", "Göster", true, None::<&str>)?;
-            let menu = tauri::menu::Menu::with_items(app, &[&show_i, &quit_i])?;
+            let tray_span = startup::span("tray");
+            let menu = build_tray_menu(app.handle())?;
 
             let icon = app.default_window_icon().cloned();
-            let mut tray_builder = tauri::tray::TrayIconBuilder::new()
+            let mut tray_builder = tauri::tray::TrayIconBuilder::with_id(TRAY_ID)
                 .tooltip("Lumina Browser")
                 .menu(&menu);
             
@@ -3735,27 +6168,15 @@ pub fn run() {
 
             let _tray = tray_builder
                 .on_menu_event(|app: &AppHandle, event| {
-                    match event.id().as_ref() {
-                        "quit" => app.exit(0),
-                        "show" => {
-                             if let Some(window) = app.get_webview_window("main") {
-                                 let _ = window.show();
-                                 let _ = window.set_focus();
-                             }
-                        }
-                        _ => {}
-                    }
+                    handle_tray_menu_event(app, event.id().as_ref());
                 })
                 .on_tray_icon_event(|tray: &tauri::tray::TrayIcon, event| {
                      if let tauri::tray::TrayIconEvent::Click { .. } = event {
-                         let app = tray.app_handle();
-                         if let Some(window) = app.get_webview_window("main") {
-                             let _ = window.show();
-                             let _ = window.set_focus();
-                         }
+                         show_main_window(tray.app_handle());
                     }
                 })
                 .build(app)?;
+            drop(tray_span);
 
             // Use Listener (restored)
             app.listen("debug-event", |event| {
@@ -3763,7 +6184,7 @@ pub fn run() {
             });
 
             // Initial layout setup for production visibility
-            if let Some(main_webview) = app.get_webview("main") {
+            if let Some(main_webview) = app.get_webview("main").filter(|_| !headless) {
                 let _ = main_webview.set_auto_resize(false);
                 
                 // Force initial layout calculation to set correct UI height (104px)
@@ -3780,15 +6201,26 @@ pub fn run() {
                         let _ = window.set_focus();
                     }
 
-                    let state = handle.state::<UiState>();
-                    let store = handle.state::<AppDataStore>();
-                    let _ = update_layout(state, handle.clone(), store);
+                    let _ = relayout(&handle, "main");
                 });
             }
 
+            // Don't hold deferred work back forever if no browser window ever paints
+            let deferred_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(startup::FIRST_PAINT_TIMEOUT).await;
+                startup::run_deferred(&deferred_handle);
+            });
+
             println!("Lumina: Setup completed successfully.");
             Ok(())
         })
+        .on_page_load(|webview, payload| {
+            if payload.event() == tauri::webview::PageLoadEvent::Finished && layout::is_browser_window(webview.label()) {
+                startup::first_paint(webview.app_handle());
+            }
+        })
+        .on_webview_event(local_files::handle_webview_event)
         .on_window_event(|window, event| {
             match event {
                 tauri::WindowEvent::CloseRequested { .. } => {
@@ -3796,65 +6228,53 @@ pub fn run() {
                      // let _ = window.hide();
                      // api.prevent_close();
                 }
-                tauri::WindowEvent::Resized(size) => {
-                    if window.label() == "main" {
-                         let scale_factor = window.scale_factor().unwrap_or(1.0);
-                         let logical_size = size.to_logical::<f64>(scale_factor);
-                         
-                         let app_handle = window.app_handle();
-                         let ui_state = app_handle.state::<UiState>();
-                         let sidebar_open = ui_state.sidebar_open.load(std::sync::atomic::Ordering::Relaxed);
-                         let suggestions_height = ui_state.suggestions_height.load(std::sync::atomic::Ordering::Relaxed) as f64;
-                         
-                         let data_store = app_handle.state::<AppDataStore>();
-                         let vertical_tabs = if let Ok(data) = data_store.data.lock() {
-                             data.settings.vertical_tabs
-                         } else {
-                             false
-                         };
-
-                         let (main_height, x, y, width, height) = calculate_layout(logical_size, vertical_tabs, sidebar_open, suggestions_height);
-                         // println!("Rust: Window Resized - MainH: {}, w: {}, h: {}", main_height, width, height);
-
-                         // Resize main webview (UI)
-                         if let Some(main_webview) = app_handle.get_webview("main") {
-                             let _ = main_webview.set_auto_resize(false);
-                             let _ = main_webview.set_position(tauri::LogicalPosition::new(0.0, 0.0));
-                             let _ = main_webview.set_size(tauri::LogicalSize::new(logical_size.width, main_height));
-                         }
-    
-                         // Resize ALL other webviews (browser tabs)
-                         let webviews = app_handle.webviews();
-                         for webview in webviews {
-                             let webview_instance = &webview.1; 
-                             if webview_instance.label() != "main" {
-                                 let _ = webview_instance.set_auto_resize(false);
-                                 let _ = webview_instance.set_size(tauri::LogicalSize::new(width, height));
-                                 let _ = webview_instance.set_position(tauri::LogicalPosition::new(x, y));
-                             }
-                         }
+                tauri::WindowEvent::Destroyed => {
+                    ipc_guard::revoke(window.label());
+                    site_prompts::dismiss_tab(window.app_handle(), window.label());
+                    if is_browser_window(window.label()) {
+                        tabs::forget_window_tabs(window.app_handle(), window.label());
+                        window.app_handle().state::<UiState>().remove_window(window.label());
+                        window.app_handle().state::<LayoutManager>().forget(window.label());
                     }
                 }
+                tauri::WindowEvent::Focused(true) if is_browser_window(window.label()) => {
+                    window.app_handle().state::<UiState>().set_focused_window(window.label());
+                }
+                tauri::WindowEvent::ThemeChanged(_) if window.label() == "main" => {
+                    refresh_theme(window.app_handle());
+                }
+                tauri::WindowEvent::Resized(size) if is_browser_window(window.label()) => {
+                    let app_handle = window.app_handle();
+                    let _ = app_handle.state::<LayoutManager>().relayout(app_handle, window.label(), Some(*size), None);
+                }
+                // The new size and scale factor are only reliable from the event itself while the change is underway
+                tauri::WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size, .. } if is_browser_window(window.label()) => {
+                    let app_handle = window.app_handle();
+                    let _ = app_handle.state::<LayoutManager>().relayout(app_handle, window.label(), Some(*new_inner_size), Some(*scale_factor));
+                }
                 _ => {}
             }
         })
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler(ipc_guard::guarded(tauri::generate_handler![
             // New Feature Commands
-            set_zoom_level,
-            get_zoom_level,
+            zoom::set_zoom_level,
+            zoom::get_zoom_level,
+            zoom::set_default_zoom,
             set_cookie,
             get_cookies,
             delete_cookie,
-            navigate, 
-            force_internal_navigate,
-            go_back, 
-            go_forward, 
-            refresh, 
+            tabs::navigate, 
+            tabs::force_internal_navigate,
+            tabs::go_back, 
+            tabs::go_forward, 
+            tabs::refresh, 
+            tabs::go_home,
+            tabs::search_selection,
             init_browser, 
-            create_tab, 
-            switch_tab, 
-            close_tab, 
-            update_tab_info, 
+            tabs::create_tab, 
+            tabs::switch_tab, 
+            tabs::close_tab, 
+            tabs::update_tab_info, 
             add_history_item, 
             get_history, 
             get_recent_history,
@@ -3862,32 +6282,215 @@ pub fn run() {
             search_history,
             add_favorite, 
             remove_favorite, 
+            is_favorite,
             get_favorites, 
-            toggle_sidebar, 
-            set_suggestions_height,
+            layout::toggle_sidebar, 
+            layout::set_suggestions_height,
+            layout::set_infobar_height,
+            site_prompts::answer_site_prompt,
+            site_prompts::get_site_prompts,
+            layout::new_browser_window,
             get_settings, 
             save_settings, 
-            open_file, 
-            show_in_folder, 
-            toggle_reader_mode, 
-            get_downloads, 
-            resume_download, 
-            pwa_detected, 
-            install_pwa, 
-            check_pwa_manifest, 
-            open_pwa_window,
+            downloads::open_file, 
+            downloads::show_in_folder, 
+            tabs::toggle_reader_mode, 
+            downloads::get_downloads, 
+            downloads::resume_download, 
+            pwa::pwa_detected, 
+            pwa::install_pwa, 
+            pwa::open_pwa_window,
+            pwa::list_installed_pwas,
+            get_recent_events,
+            pwa::clear_pwa_data,
             get_open_windows,
             focus_window,
             open_flash_window,
-            clean_page,
-            run_kip_code,
-            run_networking_command,
-            run_sidekick,
+            adblock::clean_page,
+            screenshot::start_screenshot,
+            scripting::run_kip_code,
+            scripting::run_networking_command,
+            scripting::run_sidekick,
             request_omnibox_suggestions,
-            run_lua_code,
-            get_store_items,
-            install_package
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+            scripting::run_lua_code,
+            scripting::cancel_lua_execution,
+            scripting::reload_scripts,
+            scripting::get_script_status,
+            scripting::set_lua_permission,
+            scripting::get_lua_permissions,
+            scripting::add_lua_console_history,
+            scripting::get_lua_console_history,
+            store::get_store_items,
+            store::install_package,
+            store::confirm_install,
+            adblock::toggle_adblock,
+            adblock::toggle_adblock_for_site,
+            adblock::get_adblock_state,
+            adblock::get_host_blocklist,
+            adblock::add_blocked_host,
+            adblock::remove_blocked_host,
+            adblock::import_hosts_file,
+            adblock::get_blocked_requests,
+            adblock::get_page_shield_report,
+            save_startup_settings,
+            get_startup_tabs,
+            get_history_page,
+            delete_history_entry,
+            delete_history_entry_by_id,
+            delete_history_range,
+            history_stats::get_top_sites,
+            set_history_excluded,
+            history_stats::get_browsing_stats,
+            get_diagnostics,
+            get_flags,
+            pwa::get_web_capabilities,
+            pwa::set_pwa_notifications,
+            pwa::set_pwa_service_workers,
+            set_flag,
+            reset_flags,
+            get_site_permissions,
+            get_site_info,
+            storage::get_storage_usage,
+            storage::clear_cache,
+            maintenance::run_db_maintenance,
+            undo::undo_action,
+            downloads::remove_download,
+            downloads::rescan_download,
+            set_site_permission,
+            downloads::set_download_notifications,
+            downloads::set_scan_downloads,
+            tabs::set_block_popups,
+            tabs::set_unwrap_redirects,
+            adblock::set_adblock_placeholders,
+            tabs::allow_popups,
+            downloads::set_auto_extract_archives,
+            downloads::schedule_download,
+            downloads::set_max_concurrent_downloads,
+            resolve_keyword,
+            aliases::add_alias,
+            aliases::remove_alias,
+            aliases::list_aliases,
+            header_rules::save_header_rule,
+            header_rules::delete_header_rule,
+            header_rules::list_header_rules,
+            sessions::save_session,
+            sessions::load_session,
+            sessions::list_sessions,
+            sessions::delete_session,
+            media_capture::get_media_captures,
+            media_capture::stop_capture,
+            set_favorite_keyword,
+            check_favorites_health,
+            update_favorite_url,
+            install_extension_from_path,
+            install_extension_from_crx,
+            list_extensions,
+            remove_extension,
+            set_extension_enabled,
+            restart_app,
+            store::uninstall_package,
+            store::get_store_doc,
+            get_dark_mode_settings,
+            set_dark_mode_global,
+            set_dark_mode_site,
+            notes::save_site_note,
+            notes::get_site_note,
+            notes::list_site_notes,
+            speech::read_aloud,
+            speech::pause_read_aloud,
+            speech::resume_read_aloud,
+            speech::stop_read_aloud,
+            speech::list_read_aloud_voices,
+            speech::set_read_aloud_settings,
+            spellcheck::get_spellcheck_languages,
+            spellcheck::set_spellcheck_enabled,
+            spellcheck::set_spellcheck_languages,
+            spellcheck::set_spellcheck_site_disabled,
+            locale::get_accept_languages,
+            locale::set_accept_languages,
+            http::set_proxy_url,
+            locale::get_search_url,
+            set_show_home_button,
+            archive::save_page,
+            archive::print_simplified,
+            autofill::list_autofill_profiles,
+            autofill::save_autofill_profile,
+            autofill::delete_autofill_profile,
+            autofill::set_autofill_site_enabled,
+            autofill::fill_form,
+            local_ai::get_local_ai_status,
+            local_ai::download_local_ai_model,
+            local_ai::cancel_local_ai_download,
+            local_ai::ask_local_ai,
+            local_ai::summarize_page,
+            local_ai::open_page_summary,
+            local_files::open_local_file,
+            local_files::allow_local_file_folder,
+            local_files::remove_local_file_folder,
+            local_files::set_local_file_access,
+            crash_recovery::reload_crashed_tab,
+            network::get_network_status,
+            startup::get_startup_report,
+            get_active_theme,
+            set_theme_schedule,
+            tabs::get_open_tabs,
+            list_site_fixups,
+            get_site_protection,
+            set_site_protection,
+            referrer::set_referrer_policy,
+            forget_sites::set_forget_on_close,
+            referrer::set_site_referrer_policy,
+            set_site_fixup_enabled,
+            tabs::get_navigation_history,
+            tabs::go_to_history_index,
+            tabs::duplicate_tab,
+            tabs::detach_tab,
+            tabs::set_tab_pinned,
+            tabs::set_tab_private,
+            tabs::set_tab_group,
+            tabs::get_protocol_handlers,
+            tabs::set_protocol_handler,
+            tabs::remove_protocol_handler
+        ]))
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::ExitRequested { .. } = event {
+                tabs::freeze_session();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_paths_refuse_traversal() {
+        assert_eq!(normalize_internal_path("settings//blocklist/").as_deref(), Some("settings/blocklist"));
+        assert_eq!(normalize_internal_path("a/../b"), None);
+        assert_eq!(normalize_internal_path("a/%2e%2e/b"), None);
+        assert_eq!(normalize_internal_path("a/./b"), None);
+        assert_eq!(normalize_internal_path("a%5Cb"), None);
+        assert_eq!(normalize_internal_path("a%0Ab"), None);
+    }
+
+    #[test]
+    fn the_most_specific_protection_wins() {
+        let protections: HashMap<String, String> =
+            [("example.com", "default"), ("shop.example.com", "off")].into_iter().map(|(p, l)| (p.to_string(), l.to_string())).collect();
+        assert_eq!(site_protection_level(&protections, "www.example.com"), "default");
+        assert_eq!(site_protection_level(&protections, "shop.example.com"), "off");
+        assert_eq!(site_protection_level(&protections, "other.org"), "aggressive");
+    }
+
+    #[test]
+    fn fixup_patterns_cover_subdomains_and_suffixes() {
+        assert!(fixup_matches("example.com", "example.com"));
+        assert!(fixup_matches("example.com", "www.example.com"));
+        assert!(!fixup_matches("example.com", "notexample.com"));
+        assert!(fixup_matches("google.*", "google.co.uk"));
+        assert!(fixup_matches("google.*", "www.google.com"));
+        assert!(!fixup_matches("google.*", "notgoogle.com"));
+    }
 }
@@ -0,0 +1,347 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use futures_util::StreamExt;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tokio::io::AsyncWriteExt;
+
+use crate::error::LuminaError;
+use crate::events::{self, AppEvent};
+use crate::layout::UiState;
+use crate::scripting::set_sidecar_status;
+use crate::tabs::{article_text, TabCreatedPayload};
+use crate::{error, http, ToastPayload};
+
+/// Store package that turns the local model on
+pub(crate) const LOCAL_BRAIN_PACKAGE: &str = "local-brain";
+
+const MODEL_URL: &str = "https://huggingface.co/TheBloke/phi-2-GGUF/resolve/main/phi-2.Q4_K_M.gguf";
+const MODEL_FILE: &str = "phi-2.Q4_K_M.gguf";
+/// Free space kept on the disk on top of the model itself
+const DISK_HEADROOM: u64 = 512 * 1024 * 1024;
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// llama.cpp's HTTP server. Looked up next to the Lumina executable (where bundled sidecars live),
+/// then in app_data/local-ai, then on PATH.
+const RUNNER_NAME: &str = "llama-server";
+const RUNNER_CONTEXT_TOKENS: u32 = 2048;
+const RUNNER_STARTUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(90);
+const ANSWER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+const MAX_ANSWER_TOKENS: u32 = 384;
+/// Page text beyond this is cut so the prompt fits the model's context window
+const MAX_CONTEXT_CHARS: usize = 5000;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LocalAiStatusPayload {
+    /// "not-installed", "missing-model", "downloading", "ready" or "running"
+    state: &'static str,
+    downloaded: u64,
+    total: u64,
+    message: Option<String>,
+}
+
+struct RunningServer {
+    child: tokio::process::Child,
+    port: u16,
+}
+
+static SERVER: OnceLock<Mutex<Option<RunningServer>>> = OnceLock::new();
+static DOWNLOADING: AtomicBool = AtomicBool::new(false);
+static CANCEL_DOWNLOAD: AtomicBool = AtomicBool::new(false);
+/// (bytes so far, total bytes) of the current or last model download
+static DOWNLOAD_PROGRESS: Mutex<(u64, u64)> = Mutex::new((0, 0));
+/// Why the last model download failed
+static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+fn server() -> &'static Mutex<Option<RunningServer>> {
+    SERVER.get_or_init(|| Mutex::new(None))
+}
+
+fn local_ai_dir(app: &AppHandle) -> Result<PathBuf, LuminaError> {
+    Ok(app.path().app_data_dir()?.join("local-ai"))
+}
+
+fn model_path(app: &AppHandle) -> Result<PathBuf, LuminaError> {
+    Ok(local_ai_dir(app)?.join(MODEL_FILE))
+}
+
+fn is_package_installed(app: &AppHandle) -> bool {
+    crate::store::get_store_items(app.clone())
+        .iter()
+        .any(|item| item.id == LOCAL_BRAIN_PACKAGE && item.installed)
+}
+
+/// Whether the omnibox should offer to ask the model.
+pub(crate) fn is_available(app: &AppHandle) -> bool {
+    is_package_installed(app) && model_path(app).is_ok_and(|p| p.is_file())
+}
+
+fn status(app: &AppHandle) -> LocalAiStatusPayload {
+    let state = if !is_package_installed(app) {
+        "not-installed"
+    } else if DOWNLOADING.load(Ordering::SeqCst) {
+        "downloading"
+    } else if !model_path(app).is_ok_and(|p| p.is_file()) {
+        "missing-model"
+    } else if server().lock().unwrap().is_some() {
+        "running"
+    } else {
+        "ready"
+    };
+    let (downloaded, total) = *DOWNLOAD_PROGRESS.lock().unwrap();
+    LocalAiStatusPayload { state, downloaded, total, message: LAST_ERROR.lock().unwrap().clone() }
+}
+
+/// Fetches the model in the background unless it's already there or on its way.
+pub(crate) fn start_model_download(app: &AppHandle) {
+    if DOWNLOADING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    CANCEL_DOWNLOAD.store(false, Ordering::SeqCst);
+    *LAST_ERROR.lock().unwrap() = None;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = download_model(&app).await;
+        DOWNLOADING.store(false, Ordering::SeqCst);
+        match result {
+            Ok(()) => {
+                events::emit(&app, AppEvent::Toast(ToastPayload {
+                    message: "Yerel yapay zeka modeli hazır".to_string(),
+                    level: "success".to_string(),
+                }));
+            }
+            Err(e) => {
+                *LAST_ERROR.lock().unwrap() = Some(e.message().to_string());
+                if !CANCEL_DOWNLOAD.load(Ordering::SeqCst) {
+                    error::report(&app, "Model indirilemedi", &e);
+                }
+            }
+        }
+    });
+}
+
+/// Downloads into a .part file, picking up where an interrupted download stopped. Progress is
+/// polled through `get_local_ai_status`.
+async fn download_model(app: &AppHandle) -> Result<(), LuminaError> {
+    let path = model_path(app)?;
+    if path.is_file() {
+        return Ok(());
+    }
+    let dir = local_ai_dir(app)?;
+    tokio::fs::create_dir_all(&dir).await?;
+    let part = dir.join(format!("{}.part", MODEL_FILE));
+    let mut downloaded = tokio::fs::metadata(&part).await.map(|m| m.len()).unwrap_or(0);
+
+    let client = http::client(app, http::Purpose::Download)?;
+    let mut request = client.get(MODEL_URL);
+    if downloaded > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", downloaded));
+    }
+    let response = request.send().await?.error_for_status()?;
+    if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        downloaded = 0;
+    }
+    let remaining = response.content_length().unwrap_or(0);
+    let total = downloaded + remaining;
+
+    *DOWNLOAD_PROGRESS.lock().unwrap() = (downloaded, total);
+    let available = fs4::available_space(&dir)?;
+    if available < remaining + DISK_HEADROOM {
+        return Err(LuminaError::Io(format!(
+            "Not enough disk space for the model: {} MB needed, {} MB free",
+            (remaining + DISK_HEADROOM) / 1_000_000,
+            available / 1_000_000
+        )));
+    }
+
+    let mut file = if downloaded > 0 {
+        tokio::fs::OpenOptions::new().append(true).open(&part).await?
+    } else {
+        tokio::fs::File::create(&part).await?
+    };
+    let mut stream = response.bytes_stream();
+    let mut last_progress = std::time::Instant::now();
+    while let Some(chunk) = stream.next().await {
+        if CANCEL_DOWNLOAD.load(Ordering::SeqCst) {
+            return Err(LuminaError::Other("Download cancelled".to_string()));
+        }
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            *DOWNLOAD_PROGRESS.lock().unwrap() = (downloaded, total);
+            last_progress = std::time::Instant::now();
+        }
+    }
+    file.sync_all().await?;
+    drop(file);
+
+    if total > 0 && downloaded != total {
+        return Err(LuminaError::Network("The model download ended early".to_string()));
+    }
+    tokio::fs::rename(&part, &path).await?;
+    Ok(())
+}
+
+/// Stops the runner and deletes the model. Called when the store package is removed.
+pub(crate) fn remove_model(app: &AppHandle) {
+    CANCEL_DOWNLOAD.store(true, Ordering::SeqCst);
+    stop_server();
+    if let Ok(dir) = local_ai_dir(app) {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+fn stop_server() {
+    if let Some(mut running) = server().lock().unwrap().take() {
+        let _ = running.child.start_kill();
+    }
+    set_sidecar_status(RUNNER_NAME, "stopped");
+}
+
+fn find_runner(app: &AppHandle) -> Option<PathBuf> {
+    let file_name = format!("{}{}", RUNNER_NAME, std::env::consts::EXE_SUFFIX);
+    let beside_exe = std::env::current_exe().ok().and_then(|exe| exe.parent().map(|dir| dir.join(&file_name)));
+    let in_app_data = local_ai_dir(app).ok().map(|dir| dir.join(&file_name));
+    let on_path = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).map(|dir| dir.join(&file_name)).collect::<Vec<_>>())
+        .unwrap_or_default();
+    beside_exe.into_iter().chain(in_app_data).chain(on_path).find(|p| p.is_file())
+}
+
+/// Port of a running llama.cpp server, starting one if needed and waiting until it has loaded the model.
+async fn ensure_server(app: &AppHandle) -> Result<u16, LuminaError> {
+    if !is_package_installed(app) {
+        return Err(LuminaError::NotFound("Install Local Brain from the Lumina Store first".to_string()));
+    }
+    let model = model_path(app)?;
+    if !model.is_file() {
+        start_model_download(app);
+        return Err(LuminaError::NotFound("The local model is still downloading".to_string()));
+    }
+
+    let port = {
+        let mut server = server().lock().unwrap();
+        let alive = server.as_mut().is_some_and(|r| matches!(r.child.try_wait(), Ok(None)));
+        if let (true, Some(running)) = (alive, server.as_ref()) {
+            running.port
+        } else {
+            let runner = find_runner(app).ok_or_else(|| {
+                LuminaError::NotFound(format!("{} (llama.cpp) was not found next to Lumina or on PATH", RUNNER_NAME))
+            })?;
+            let port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+            let child = tokio::process::Command::new(&runner)
+                .arg("--model").arg(&model)
+                .args(["--host", "127.0.0.1", "--port", &port.to_string()])
+                .args(["--ctx-size", &RUNNER_CONTEXT_TOKENS.to_string()])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .kill_on_drop(true)
+                .spawn()?;
+            set_sidecar_status(RUNNER_NAME, format!("running (pid {})", child.id().unwrap_or(0)));
+            *server = Some(RunningServer { child, port });
+            port
+        }
+    };
+
+    // /health answers 503 while the model is loading
+    let client = http::client(app, http::Purpose::Local)?;
+    let started = std::time::Instant::now();
+    loop {
+        let healthy = client
+            .get(format!("http://127.0.0.1:{}/health", port))
+            .timeout(std::time::Duration::from_secs(2))
+            .send()
+            .await
+            .is_ok_and(|r| r.status().is_success());
+        if healthy {
+            return Ok(port);
+        }
+        if started.elapsed() > RUNNER_STARTUP_TIMEOUT {
+            stop_server();
+            return Err(LuminaError::Other("The local model did not start in time".to_string()));
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    }
+}
+
+/// Phi-2's instruction format, with optional page text ahead of the question.
+fn build_prompt(prompt: &str, context: Option<&str>) -> String {
+    match context.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(context) => {
+            let context: String = context.chars().take(MAX_CONTEXT_CHARS).collect();
+            format!("Instruct: Use this text to answer.\n\n{}\n\n{}\nOutput:", context, prompt.trim())
+        }
+        None => format!("Instruct: {}\nOutput:", prompt.trim()),
+    }
+}
+
+async fn complete(app: &AppHandle, prompt: String) -> Result<String, LuminaError> {
+    let port = ensure_server(app).await?;
+    let body = serde_json::json!({
+        "prompt": prompt,
+        "n_predict": MAX_ANSWER_TOKENS,
+        "temperature": 0.3,
+        "stop": ["Instruct:", "<|endoftext|>"],
+        "cache_prompt": true,
+    });
+    let response: serde_json::Value = http::client(app, http::Purpose::Local)?
+        .post(format!("http://127.0.0.1:{}/completion", port))
+        .timeout(ANSWER_TIMEOUT)
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(response["content"].as_str().unwrap_or_default().trim().to_string())
+}
+
+#[tauri::command]
+pub(crate) fn get_local_ai_status(app: AppHandle) -> LocalAiStatusPayload {
+    status(&app)
+}
+
+#[tauri::command]
+pub(crate) fn download_local_ai_model(app: AppHandle) -> Result<(), LuminaError> {
+    if !is_package_installed(&app) {
+        return Err(LuminaError::NotFound("Install Local Brain from the Lumina Store first".to_string()));
+    }
+    start_model_download(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn cancel_local_ai_download() -> Result<(), LuminaError> {
+    CANCEL_DOWNLOAD.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Answers `prompt` with the local model; `context` is optional text (a page, a selection) to answer from.
+#[tauri::command]
+pub(crate) async fn ask_local_ai(app: AppHandle, prompt: String, context: Option<String>) -> Result<String, LuminaError> {
+    if prompt.trim().is_empty() {
+        return Err(LuminaError::InvalidInput("Empty question".to_string()));
+    }
+    complete(&app, build_prompt(&prompt, context.as_deref())).await
+}
+
+#[tauri::command]
+pub(crate) async fn summarize_page(app: AppHandle, label: String) -> Result<String, LuminaError> {
+    let text = article_text(&app, &label).await?;
+    complete(&app, build_prompt("Summarize the text above in a few sentences.", Some(&text))).await
+}
+
+/// Opens the answer page with a summary of the active tab.
+#[tauri::command]
+pub(crate) fn open_page_summary(app: AppHandle) -> Result<(), LuminaError> {
+    let label = app.state::<UiState>().current_tab()
+        .ok_or_else(|| LuminaError::NotFound("No tab to summarize".to_string()))?;
+    events::emit(&app, AppEvent::RequestNewTab(TabCreatedPayload {
+        label: "new-tab".to_string(),
+        url: format!("lumina-app://ask?summarize={}", urlencoding::encode(&label)),
+    }));
+    Ok(())
+}
@@ -0,0 +1,366 @@
+use base64::Engine as _;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, DragDropEvent, Manager, PhysicalPosition, Webview, WebviewEvent};
+use url::Url;
+
+use crate::data::AppDataStore;
+use crate::error::{self, LuminaError};
+use crate::events::{self, AppEvent};
+use crate::layout::{is_browser_window, UiState};
+use crate::scripting::eval_in_webview;
+use crate::tabs::TabCreatedPayload;
+use crate::to_script_json;
+
+/// Files that never open in a tab, whatever folder they are in: the OS would run them rather than show them
+const BLOCKED_EXTENSIONS: &[&str] = &[
+    "exe", "msi", "bat", "cmd", "com", "scr", "ps1", "vbs", "lnk", "url", "reg", "sh", "desktop", "appimage", "jar",
+];
+/// Largest text file and image the folder view previews inline
+const MAX_TEXT_PREVIEW_BYTES: u64 = 512 * 1024;
+const MAX_IMAGE_PREVIEW_BYTES: u64 = 8 * 1024 * 1024;
+/// Extensions shown as text even though their MIME type isn't text/*
+const TEXT_EXTENSIONS: &[&str] = &[
+    "rs", "toml", "yaml", "yml", "ini", "cfg", "conf", "xml", "js", "ts", "css", "py", "c", "h", "cpp", "cs", "java",
+    "go", "sql", "sh", "ps1", "bat", "razor", "lua", "env", "gitignore",
+];
+/// Most a drop hands to a page in one go; bigger uploads go through the page's own file picker
+const MAX_FORWARD_BYTES: u64 = 25 * 1024 * 1024;
+const DROP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Replays a drop inside the page: straight into a file input under the cursor, otherwise as
+/// dragenter/dragover/drop on the element there. Tauri's own drop handler keeps the native events
+/// from reaching the page, so without this no site sees dropped files. Returns whether the page took them.
+const FORWARD_DROP_SCRIPT: &str = r#"
+    (function(files, px, py) {
+        const x = px / window.devicePixelRatio, y = py / window.devicePixelRatio;
+        const transfer = new DataTransfer();
+        for (const f of files) {
+            const bytes = Uint8Array.from(atob(f.data), c => c.charCodeAt(0));
+            transfer.items.add(new File([bytes], f.name, { type: f.type, lastModified: f.lastModified }));
+        }
+
+        const target = document.elementFromPoint(x, y) || document.body;
+        const input = target.closest('input[type=file]') || (target.closest('label') || {}).control;
+        if (input && input.type === 'file' && !input.disabled) {
+            if (!input.multiple && transfer.files.length > 1) {
+                const single = new DataTransfer();
+                single.items.add(transfer.files[0]);
+                input.files = single.files;
+            } else {
+                input.files = transfer.files;
+            }
+            input.dispatchEvent(new Event('input', { bubbles: true }));
+            input.dispatchEvent(new Event('change', { bubbles: true }));
+            return true;
+        }
+
+        const init = { bubbles: true, cancelable: true, composed: true, clientX: x, clientY: y, dataTransfer: transfer };
+        target.dispatchEvent(new DragEvent('dragenter', init));
+        const over = new DragEvent('dragover', init);
+        target.dispatchEvent(over);
+        // A drop zone cancels dragover; anything else doesn't take files
+        if (!over.defaultPrevented) return false;
+        target.dispatchEvent(new DragEvent('drop', init));
+        return true;
+    })
+"#;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DroppedFile {
+    name: String,
+    #[serde(rename = "type")]
+    mime: &'static str,
+    last_modified: i64,
+    /// Base64 file contents
+    data: String,
+}
+
+/// What the folder view can show of a file
+pub(crate) enum Preview {
+    /// The text, and whether it was cut short
+    Text(String, bool),
+    /// A data: URL
+    Image(String),
+    None,
+}
+
+pub(crate) struct DirectoryEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+pub(crate) fn mime_type(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "txt" | "log" | "md" => "text/plain",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "html" | "htm" => "text/html",
+        "zip" => "application/zip",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        _ => "",
+    }
+}
+
+fn is_blocked(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| BLOCKED_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Resolves `path` to a real file a tab may show.
+fn checked_file(path: &Path) -> Result<PathBuf, LuminaError> {
+    let path = path.canonicalize()?;
+    if !path.is_file() {
+        return Err(LuminaError::InvalidInput(format!("{} is not a file", path.display())));
+    }
+    if is_blocked(&path) {
+        return Err(LuminaError::PermissionDenied(format!("{} can't be opened in a tab", path.display())));
+    }
+    Ok(path)
+}
+
+/// The folders and files of `path`, folders first, each group by name.
+pub(crate) fn read_directory(path: &Path) -> Result<Vec<DirectoryEntry>, LuminaError> {
+    let mut entries: Vec<DirectoryEntry> = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let metadata = entry.metadata().ok();
+            DirectoryEntry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                path: entry.path(),
+                is_dir: metadata.as_ref().is_some_and(|m| m.is_dir()),
+                size: metadata.map_or(0, |m| m.len()),
+            }
+        })
+        .collect();
+    entries.sort_by_cached_key(|entry| (!entry.is_dir, entry.name.to_lowercase()));
+    Ok(entries)
+}
+
+/// Text goes by MIME type or extension, and an unknown file counts as text when its start is valid UTF-8
+/// without NUL bytes. Images are inlined as data: URLs. Anything big or binary has no preview.
+pub(crate) fn preview(path: &Path) -> Result<Preview, LuminaError> {
+    let size = std::fs::metadata(path)?.len();
+    let mime = mime_type(path);
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+
+    if mime.starts_with("image/") {
+        if size > MAX_IMAGE_PREVIEW_BYTES {
+            return Ok(Preview::None);
+        }
+        let data = base64::engine::general_purpose::STANDARD.encode(std::fs::read(path)?);
+        return Ok(Preview::Image(format!("data:{};base64,{}", mime, data)));
+    }
+
+    let known_text = mime.starts_with("text/") || mime == "application/json" || TEXT_EXTENSIONS.contains(&extension.as_str());
+    if !known_text && !mime.is_empty() {
+        return Ok(Preview::None);
+    }
+    use std::io::Read as _;
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)?.take(MAX_TEXT_PREVIEW_BYTES).read_to_end(&mut bytes)?;
+    if !known_text && (bytes.contains(&0) || std::str::from_utf8(&bytes[..bytes.len().min(8192)]).is_err_and(|e| e.error_len().is_some())) {
+        return Ok(Preview::None);
+    }
+    Ok(Preview::Text(String::from_utf8_lossy(&bytes).into_owned(), size > MAX_TEXT_PREVIEW_BYTES))
+}
+
+/// The folder view's address for a local folder or file.
+pub(crate) fn browse_url(path: &Path) -> String {
+    format!("lumina-app://files?path={}", urlencoding::encode(&path.to_string_lossy()))
+}
+
+/// Where a file:// URL for a folder should go instead: the folder view.
+pub(crate) fn directory_redirect(url: &Url) -> Option<String> {
+    if url.scheme() != "file" {
+        return None;
+    }
+    url.to_file_path().ok().filter(|path| path.is_dir()).map(|path| browse_url(&path))
+}
+
+pub(crate) fn access_enabled(app: &AppHandle) -> bool {
+    app.state::<AppDataStore>().data.lock().unwrap().settings.local_file_access
+}
+
+pub(crate) fn allowed_folders(app: &AppHandle) -> Vec<String> {
+    app.state::<AppDataStore>().data.lock().unwrap().settings.local_file_folders.clone()
+}
+
+/// Whether `path` (canonical) is one of the allowed folders or inside one.
+pub(crate) fn in_allowed_folder(app: &AppHandle, path: &Path) -> bool {
+    allowed_folders(app)
+        .iter()
+        .filter_map(|folder| Path::new(folder).canonicalize().ok())
+        .any(|folder| path.starts_with(folder))
+}
+
+/// Whether a tab may load this file:// URL: an existing folder or non-executable file inside a folder
+/// the user allowed. Checked on every tab creation and navigation before a folder is sent to the
+/// folder view, so a page can't reach past the allowlist.
+pub(crate) fn is_allowed_url(app: &AppHandle, url: &Url) -> bool {
+    access_enabled(app) && url.to_file_path()
+        .ok()
+        .and_then(|path| if path.is_dir() { path.canonicalize().ok() } else { checked_file(&path).ok() })
+        .is_some_and(|path| in_allowed_folder(app, &path))
+}
+
+fn open_in_new_tab(app: &AppHandle, path: &Path) -> Result<(), LuminaError> {
+    let url = Url::from_file_path(path).map_err(|_| LuminaError::InvalidInput(format!("{} has no file URL", path.display())))?;
+    events::emit(app, AppEvent::RequestNewTab(TabCreatedPayload {
+        label: "new-tab".to_string(),
+        url: url.to_string(),
+    }));
+    Ok(())
+}
+
+/// Asks in the focused browser window whether files from `path`'s folder may open in tabs.
+fn prompt_folder_access(app: &AppHandle, path: &Path) -> Result<(), LuminaError> {
+    let window = app.state::<UiState>().focused_window();
+    let webview = app.get_webview(&window).ok_or_else(|| LuminaError::NotFound(format!("Window {} not found", window)))?;
+    let folder = path.parent().unwrap_or(path);
+    let script = format!(
+        r#"(function() {{
+            const path = {path};
+            const folder = {folder};
+            if (window.confirm('Allow Lumina to open local files from this folder?\n\n' + folder)) {{
+                window.lumina.invoke('allow_local_file_folder', {{ path }});
+            }}
+        }})();"#,
+        path = to_script_json(&path.to_string_lossy()),
+        folder = to_script_json(&folder.to_string_lossy())
+    );
+    webview.eval(script)?;
+    Ok(())
+}
+
+/// Opens a local file in a new tab. A file outside the allowed folders asks for its folder first;
+/// returns whether the tab opened right away.
+#[tauri::command]
+pub(crate) fn open_local_file(app: AppHandle, path: String) -> Result<bool, LuminaError> {
+    if !access_enabled(&app) {
+        return Err(LuminaError::PermissionDenied("Local file access is turned off in settings".to_string()));
+    }
+    let path = checked_file(Path::new(&path))?;
+    if in_allowed_folder(&app, &path) {
+        open_in_new_tab(&app, &path)?;
+        return Ok(true);
+    }
+    prompt_folder_access(&app, &path)?;
+    Ok(false)
+}
+
+/// Adds the folder of `path` to the allowlist and opens the file.
+#[tauri::command]
+pub(crate) fn allow_local_file_folder(app: AppHandle, state: tauri::State<'_, AppDataStore>, path: String) -> Result<(), LuminaError> {
+    let path = checked_file(Path::new(&path))?;
+    let folder = path.parent().ok_or("File has no folder")?.to_string_lossy().into_owned();
+    {
+        let mut data = state.data.lock().unwrap();
+        let folders = &mut data.settings.local_file_folders;
+        if !folders.contains(&folder) {
+            folders.push(folder);
+            folders.sort();
+        }
+    }
+    state.save();
+    open_in_new_tab(&app, &path)
+}
+
+#[tauri::command]
+pub(crate) fn remove_local_file_folder(state: tauri::State<'_, AppDataStore>, folder: String) -> Result<(), LuminaError> {
+    state.data.lock().unwrap().settings.local_file_folders.retain(|f| f != &folder);
+    state.try_save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn set_local_file_access(state: tauri::State<'_, AppDataStore>, enabled: bool) -> Result<(), LuminaError> {
+    state.data.lock().unwrap().settings.local_file_access = enabled;
+    state.try_save()?;
+    Ok(())
+}
+
+fn read_dropped_files(paths: &[PathBuf]) -> Result<Vec<DroppedFile>, LuminaError> {
+    let mut total = 0;
+    let mut files = Vec::new();
+    for path in paths.iter().filter(|p| p.is_file()) {
+        let metadata = std::fs::metadata(path)?;
+        total += metadata.len();
+        if total > MAX_FORWARD_BYTES {
+            return Err(LuminaError::InvalidInput(format!(
+                "Dropped files are over {} MB; use the page's upload button",
+                MAX_FORWARD_BYTES / 1024 / 1024
+            )));
+        }
+        let last_modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_millis() as i64);
+        files.push(DroppedFile {
+            name: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+            mime: mime_type(path),
+            last_modified,
+            data: base64::engine::general_purpose::STANDARD.encode(std::fs::read(path)?),
+        });
+    }
+    Ok(files)
+}
+
+/// Hands dropped files to the page under the cursor. A page that doesn't take them gets the first one
+/// opened instead, as other browsers do, but in a new tab so whatever was typed on the page survives.
+fn forward_drop(app: &AppHandle, label: &str, paths: &[PathBuf], position: PhysicalPosition<f64>) {
+    let files = match read_dropped_files(paths) {
+        Ok(files) if !files.is_empty() => files,
+        Ok(_) => return,
+        Err(e) => {
+            error::report(app, "Dosya sayfaya bırakılamadı", &e);
+            return;
+        }
+    };
+    let js = format!("({})({}, {}, {})", FORWARD_DROP_SCRIPT, to_script_json(&files), position.x, position.y);
+    let accepted = eval_in_webview(app, label, &js, DROP_TIMEOUT).ok().flatten().as_deref() == Some("true");
+    if !accepted {
+        if let Some(path) = paths.iter().find(|p| p.is_file()) {
+            if let Err(e) = open_local_file(app.clone(), path.to_string_lossy().into_owned()) {
+                error::report(app, "Dosya açılamadı", &e);
+            }
+        }
+    }
+}
+
+/// Files dropped on the browser UI open in new tabs; files dropped on a page go to the page.
+pub(crate) fn handle_webview_event(webview: &Webview, event: &WebviewEvent) {
+    let WebviewEvent::DragDrop(DragDropEvent::Drop { paths, position }) = event else { return };
+    let app = webview.app_handle().clone();
+    let label = webview.label().to_string();
+
+    if is_browser_window(&label) {
+        for path in paths {
+            if let Err(e) = open_local_file(app.clone(), path.to_string_lossy().into_owned()) {
+                error::report(&app, "Dosya açılamadı", &e);
+            }
+        }
+        return;
+    }
+
+    let paths = paths.clone();
+    let position = *position;
+    tauri::async_runtime::spawn_blocking(move || forward_drop(&app, &label, &paths, position));
+}
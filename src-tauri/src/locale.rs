@@ -0,0 +1,215 @@
+use serde::Serialize;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::http::HttpClients;
+
+/// Used when neither the settings nor the system name a language
+const FALLBACK_LANGUAGE: &str = "en-US";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AcceptLanguages {
+    /// Chosen languages, most preferred first; empty means the system language
+    selected: Vec<String>,
+    /// What the system language was detected as
+    detected: String,
+    /// What tabs are actually sending, which lags `selected` until the next restart
+    active: Vec<String>,
+}
+
+/// Languages the browser process was started with. Like the spelling dictionaries these are
+/// browser arguments, and WebView2 only takes the first tab's.
+static ACTIVE_LANGUAGES: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Turns a POSIX locale ("tr_TR.UTF-8") or Windows locale name into a BCP 47 tag.
+fn normalize_tag(locale: &str) -> Option<String> {
+    let tag = locale.split(['.', '@']).next()?.replace('_', "-");
+    if tag.is_empty() || tag == "C" || tag == "POSIX" || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    Some(tag)
+}
+
+#[cfg(target_os = "windows")]
+fn system_language() -> Option<String> {
+    use windows::Win32::Globalization::GetUserDefaultLocaleName;
+
+    // LOCALE_NAME_MAX_LENGTH
+    let mut buffer = [0u16; 85];
+    let len = unsafe { GetUserDefaultLocaleName(&mut buffer) };
+    if len <= 1 {
+        return None;
+    }
+    normalize_tag(&String::from_utf16_lossy(&buffer[..len as usize - 1]))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn system_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find_map(|value| normalize_tag(&value))
+}
+
+fn detected_language() -> &'static str {
+    static DETECTED: OnceLock<String> = OnceLock::new();
+    DETECTED.get_or_init(|| system_language().unwrap_or_else(|| FALLBACK_LANGUAGE.to_string()))
+}
+
+/// Preferred languages, most preferred first. Never empty.
+pub(crate) fn languages(app: &AppHandle) -> Vec<String> {
+    let selected = app.state::<AppDataStore>().data.lock().unwrap().settings.accept_languages.clone();
+    if selected.is_empty() {
+        vec![detected_language().to_string()]
+    } else {
+        selected
+    }
+}
+
+/// Adds each regional tag's base language after it ("tr-TR" also accepts "tr"), the way
+/// Chromium builds its header.
+fn with_base_languages(tags: &[String]) -> Vec<String> {
+    let mut expanded: Vec<String> = Vec::new();
+    for tag in tags {
+        let base = tag.split('-').next().unwrap_or(tag).to_string();
+        for candidate in [tag.clone(), base] {
+            if !expanded.iter().any(|t| t.eq_ignore_ascii_case(&candidate)) {
+                expanded.push(candidate);
+            }
+        }
+    }
+    expanded
+}
+
+/// The Accept-Language header for the browser's own requests, q-weighted in order.
+pub(crate) fn accept_language_header(app: &AppHandle) -> String {
+    with_base_languages(&languages(app))
+        .iter()
+        .enumerate()
+        .map(|(i, tag)| match i {
+            0 => tag.clone(),
+            _ => format!("{};q={:.1}", tag, (10 - i.min(9)) as f32 / 10.0),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// A reqwest client builder that sends the preferred languages, for fetches made on a page's behalf.
+pub(crate) fn client_builder(app: &AppHandle) -> reqwest::ClientBuilder {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Ok(value) = reqwest::header::HeaderValue::from_str(&accept_language_header(app)) {
+        headers.insert(reqwest::header::ACCEPT_LANGUAGE, value);
+    }
+    reqwest::Client::builder().default_headers(headers)
+}
+
+fn active_languages(app: &AppHandle) -> &'static [String] {
+    ACTIVE_LANGUAGES.get_or_init(|| languages(app))
+}
+
+/// Extra WebView2 arguments for a new tab. Chromium has a single accept-languages list that both
+/// the Accept-Language header and the spelling dictionaries come from, so the chosen dictionaries
+/// are appended after the preferred languages.
+#[cfg(target_os = "windows")]
+pub(crate) fn browser_args(app: &AppHandle) -> Option<String> {
+    let mut tags = active_languages(app).to_vec();
+    for dictionary in crate::spellcheck::active_languages(app) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(dictionary)) {
+            tags.push(dictionary.clone());
+        }
+    }
+    Some(format!("--accept-lang={}", tags.join(",")))
+}
+
+/// Query parameters that ask the search engine for results in the preferred language and region.
+pub(crate) fn search_params(app: &AppHandle, engine: &str) -> Vec<(&'static str, String)> {
+    let primary = languages(app).into_iter().next().unwrap_or_else(|| FALLBACK_LANGUAGE.to_string());
+    let mut parts = primary.split('-');
+    let language = parts.next().unwrap_or_default().to_lowercase();
+    let region = parts.find(|part| part.len() == 2).map(|part| part.to_uppercase());
+
+    match engine {
+        "bing" => {
+            let mut params = vec![("setlang", primary.clone())];
+            if let Some(region) = region {
+                params.push(("cc", region));
+            }
+            params
+        }
+        // DuckDuckGo wants region-language, and calls the United Kingdom "uk"
+        "duckduckgo" => match region {
+            Some(region) => {
+                let region = if region == "GB" { "uk".to_string() } else { region.to_lowercase() };
+                vec![("kl", format!("{}-{}", region, language))]
+            }
+            None => Vec::new(),
+        },
+        _ => {
+            let mut params = vec![("hl", primary.clone())];
+            if let Some(region) = region {
+                params.push(("gl", region));
+            }
+            params
+        }
+    }
+}
+
+/// Where the search engine's form submits to.
+pub(crate) fn search_endpoint(engine: &str) -> &'static str {
+    match engine {
+        "bing" => "https://www.bing.com/search",
+        "duckduckgo" => "https://duckduckgo.com/",
+        _ => "https://www.google.com/search",
+    }
+}
+
+/// A results URL for `query` on the configured search engine, in the preferred language.
+pub(crate) fn search_url(app: &AppHandle, query: &str) -> String {
+    let engine = app.state::<AppDataStore>().data.lock().unwrap().settings.search_engine.clone();
+    let mut url = format!("{}?q={}", search_endpoint(&engine), urlencoding::encode(query));
+    for (name, value) in search_params(app, &engine) {
+        url.push_str(&format!("&{}={}", name, urlencoding::encode(&value)));
+    }
+    url
+}
+
+#[tauri::command]
+pub(crate) fn get_search_url(app: AppHandle, query: String) -> String {
+    search_url(&app, &query)
+}
+
+#[tauri::command]
+pub(crate) fn get_accept_languages(app: AppHandle) -> AcceptLanguages {
+    let selected = app.state::<AppDataStore>().data.lock().unwrap().settings.accept_languages.clone();
+    AcceptLanguages {
+        selected,
+        detected: detected_language().to_string(),
+        active: active_languages(&app).to_vec(),
+    }
+}
+
+/// Requests and searches pick this up right away; tabs after the next restart. An empty list
+/// goes back to the system language.
+#[tauri::command]
+pub(crate) fn set_accept_languages(state: tauri::State<'_, AppDataStore>, clients: tauri::State<'_, HttpClients>, languages: Vec<String>) -> Result<(), LuminaError> {
+    let mut tags: Vec<String> = Vec::new();
+    for language in languages {
+        let tag = language.trim();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.len() > 35 || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(LuminaError::InvalidInput(format!("Invalid language tag: {}", language)));
+        }
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    state.data.lock().unwrap().settings.accept_languages = tags;
+    state.save();
+    clients.reset();
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::error::LuminaError;
+use crate::history_manager::HistoryManager;
+
+/// How long history.db goes between maintenance runs
+const DB_MAINTENANCE_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// How often the scheduler checks whether a run is due
+const DB_MAINTENANCE_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+/// Backups beyond this many are deleted, oldest first
+const BACKUPS_KEPT: usize = 5;
+/// app_meta key holding the last report as JSON
+const REPORT_META_KEY: &str = "db_maintenance_report";
+
+/// Keeps a scheduled run and one started from the about page from overlapping
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DbMaintenanceReport {
+    /// Unix seconds
+    pub ran_at: i64,
+    /// "ok", or what the integrity check found
+    pub integrity: String,
+    /// Taken only when the integrity check passed, so a damaged copy never pushes out a good one
+    pub backup: Option<String>,
+    pub backups_kept: usize,
+    pub freed_bytes: u64,
+    /// The step that failed, if any; the steps before it still ran
+    pub error: Option<String>,
+}
+
+fn backups_dir(app: &AppHandle) -> Option<PathBuf> {
+    let dir = app.path().app_data_dir().ok()?.join("backups");
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+/// Deletes all but the newest BACKUPS_KEPT backups and returns how many are left.
+fn rotate_backups(dir: &Path) -> usize {
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("history-")))
+                .collect()
+        })
+        .unwrap_or_default();
+    // Names carry the timestamp, so they sort by age
+    backups.sort();
+    let excess = backups.len().saturating_sub(BACKUPS_KEPT);
+    for old in backups.drain(..excess) {
+        let _ = std::fs::remove_file(old);
+    }
+    backups.len()
+}
+
+/// Integrity check, then a backup, then an incremental vacuum. Blocks; the report is also stored
+/// in app_meta for the about page.
+fn run(app: &AppHandle) -> DbMaintenanceReport {
+    let history_manager = app.state::<HistoryManager>();
+    let mut report = DbMaintenanceReport {
+        ran_at: chrono::Utc::now().timestamp(),
+        integrity: String::new(),
+        backup: None,
+        backups_kept: 0,
+        freed_bytes: 0,
+        error: None,
+    };
+
+    match history_manager.integrity_check() {
+        Ok(result) => report.integrity = result,
+        Err(e) => report.error = Some(format!("Integrity check failed: {}", e)),
+    }
+
+    if report.integrity == "ok" {
+        if let Some(dir) = backups_dir(app) {
+            let path = dir.join(chrono::Local::now().format("history-%Y%m%d-%H%M%S.db").to_string());
+            match history_manager.backup_to(&path) {
+                Ok(()) => report.backup = Some(path.to_string_lossy().to_string()),
+                Err(e) => report.error = Some(format!("Backup failed: {}", e)),
+            }
+            report.backups_kept = rotate_backups(&dir);
+        }
+    }
+
+    match history_manager.vacuum() {
+        Ok(freed) => report.freed_bytes = freed,
+        Err(e) => report.error = Some(format!("Vacuum failed: {}", e)),
+    }
+
+    if let Ok(json) = serde_json::to_string(&report) {
+        let _ = history_manager.set_meta(REPORT_META_KEY, &json);
+    }
+    println!(
+        "Lumina: Database maintenance: integrity {}, freed {} bytes{}",
+        report.integrity,
+        report.freed_bytes,
+        report.error.as_deref().map(|e| format!(", {}", e)).unwrap_or_default()
+    );
+    report
+}
+
+pub(crate) fn last_report(app: &AppHandle) -> Option<DbMaintenanceReport> {
+    let json = app.state::<HistoryManager>().get_meta(REPORT_META_KEY).ok().flatten()?;
+    serde_json::from_str(&json).ok()
+}
+
+async fn run_exclusive(app: &AppHandle) -> Option<DbMaintenanceReport> {
+    if RUNNING.swap(true, Ordering::AcqRel) {
+        return None;
+    }
+    let handle = app.clone();
+    let report = tokio::task::spawn_blocking(move || run(&handle)).await.ok();
+    RUNNING.store(false, Ordering::Release);
+    report
+}
+
+/// Runs maintenance whenever DB_MAINTENANCE_INTERVAL has passed since the last run.
+pub(crate) fn start_scheduler(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let due = last_report(&app)
+                .is_none_or(|report| chrono::Utc::now().timestamp() - report.ran_at >= DB_MAINTENANCE_INTERVAL.as_secs() as i64);
+            if due {
+                run_exclusive(&app).await;
+            }
+            tokio::time::sleep(DB_MAINTENANCE_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub(crate) async fn run_db_maintenance(app: AppHandle) -> Result<DbMaintenanceReport, LuminaError> {
+    run_exclusive(&app)
+        .await
+        .ok_or_else(|| LuminaError::Other("Database maintenance is already running".to_string()))
+}
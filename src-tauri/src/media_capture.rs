@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+
+use crate::error::LuminaError;
+use crate::events::{self, AppEvent};
+
+/// Wraps getUserMedia and getDisplayMedia so Lumina knows which devices a page is capturing. Every
+/// track handed out is watched until it ends; `window.__LUMINA_STOP_CAPTURE__()` stops them all. A
+/// function of the tab's bridge, called from inside the tab info script.
+pub(crate) const CAPTURE_SCRIPT: &str = r#"
+    function(invoke) {
+        const devices = navigator.mediaDevices;
+        if (!devices) return;
+        const label = window.__TAB_LABEL__;
+        const live = new Set();
+        let reported = '';
+
+        function report() {
+            const state = { camera: false, microphone: false, screen: false };
+            for (const entry of live) {
+                if (entry.screen) state.screen = true;
+                else if (entry.track.kind === 'video') state.camera = true;
+                else if (entry.track.kind === 'audio') state.microphone = true;
+            }
+            const key = JSON.stringify(state);
+            if (key === reported) return;
+            reported = key;
+            invoke('update_tab_info', { label, capture: state });
+        }
+
+        function watch(stream, screen) {
+            for (const track of stream.getTracks()) {
+                const entry = { track, screen };
+                live.add(entry);
+                const end = () => { live.delete(entry); report(); };
+                track.addEventListener('ended', end);
+                const stop = track.stop.bind(track);
+                track.stop = function() { stop(); end(); };
+            }
+            report();
+            return stream;
+        }
+
+        function hook(name, screen) {
+            const original = devices[name];
+            if (typeof original !== 'function') return;
+            devices[name] = function() {
+                return original.apply(devices, arguments).then(stream => watch(stream, screen));
+            };
+        }
+        hook('getUserMedia', false);
+        hook('getDisplayMedia', true);
+
+        window.__LUMINA_STOP_CAPTURE__ = function() {
+            for (const entry of Array.from(live)) entry.track.stop();
+            live.clear();
+            report();
+        };
+    }
+"#;
+
+/// What a tab is capturing right now; sent as `media-capture-changed` so the tab strip can show it.
+#[derive(Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MediaCapturePayload {
+    pub label: String,
+    pub camera: bool,
+    pub microphone: bool,
+    pub screen: bool,
+}
+
+impl MediaCapturePayload {
+    fn is_active(&self) -> bool {
+        self.camera || self.microphone || self.screen
+    }
+}
+
+/// Tabs with at least one live capture track
+static TAB_CAPTURES: OnceLock<Mutex<HashMap<String, MediaCapturePayload>>> = OnceLock::new();
+
+fn tab_captures() -> &'static Mutex<HashMap<String, MediaCapturePayload>> {
+    TAB_CAPTURES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn update(app: &AppHandle, state: MediaCapturePayload) {
+    let changed = {
+        let mut captures = tab_captures().lock().unwrap();
+        let previous = captures.remove(&state.label).unwrap_or_else(|| MediaCapturePayload { label: state.label.clone(), ..Default::default() });
+        if state.is_active() {
+            captures.insert(state.label.clone(), state.clone());
+        }
+        previous != state
+    };
+    if changed {
+        events::emit(app, AppEvent::MediaCaptureChanged(state));
+    }
+}
+
+/// Called when a tab navigates away or closes; the page's streams end with it.
+pub(crate) fn reset(app: &AppHandle, label: &str) {
+    update(app, MediaCapturePayload { label: label.to_string(), ..Default::default() });
+}
+
+/// What the page's capture hooks report, through `update_tab_info`
+#[derive(Clone, Copy, Deserialize)]
+pub(crate) struct CaptureState {
+    camera: bool,
+    microphone: bool,
+    screen: bool,
+}
+
+pub(crate) fn report(app: &AppHandle, label: String, state: CaptureState) {
+    let CaptureState { camera, microphone, screen } = state;
+    update(app, MediaCapturePayload { label, camera, microphone, screen });
+}
+
+/// Tabs capturing a camera, microphone or screen
+#[tauri::command]
+pub(crate) fn get_media_captures() -> Vec<MediaCapturePayload> {
+    tab_captures().lock().unwrap().values().cloned().collect()
+}
+
+/// Ends every camera, microphone and screen track the tab's page holds. The page sees them end as
+/// if the device went away.
+#[tauri::command]
+pub(crate) fn stop_capture(app: AppHandle, label: String) -> Result<(), LuminaError> {
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("Tab {} not found", label)))?;
+    webview.eval("window.__LUMINA_STOP_CAPTURE__ && window.__LUMINA_STOP_CAPTURE__()")?;
+    reset(&app, &label);
+    Ok(())
+}
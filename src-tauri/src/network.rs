@@ -0,0 +1,240 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::downloads;
+use crate::events::{self, AppEvent};
+use crate::http;
+use crate::tab_registry::TabRegistry;
+use crate::ToastPayload;
+
+/// Endpoints that answer plain HTTP with a tiny response; the first one to answer means we're online
+const PROBE_URLS: &[&str] = &[
+    "http://connectivitycheck.gstatic.com/generate_204",
+    "http://www.msftconnecttest.com/connecttest.txt",
+];
+/// How often connectivity is probed without a change from the OS. Offline, often enough that
+/// tabs and downloads come back within seconds of the network.
+const PROBE_INTERVAL_ONLINE: Duration = Duration::from_secs(60);
+const PROBE_INTERVAL_OFFLINE: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NetworkStatus {
+    pub online: bool,
+    /// Unix ms of the last change between online and offline
+    pub since: i64,
+    /// Unix ms of the last probe, if any ran yet
+    pub checked_at: Option<i64>,
+}
+
+static STATUS: OnceLock<Mutex<NetworkStatus>> = OnceLock::new();
+/// Wakes the monitor for an early probe
+static WAKE: OnceLock<tokio::sync::Notify> = OnceLock::new();
+/// Tabs whose last navigation failed for lack of a connection, retried when it comes back
+static FAILED_TABS: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn status() -> &'static Mutex<NetworkStatus> {
+    STATUS.get_or_init(|| Mutex::new(NetworkStatus { online: true, since: chrono::Utc::now().timestamp_millis(), checked_at: None }))
+}
+
+fn wake() -> &'static tokio::sync::Notify {
+    WAKE.get_or_init(tokio::sync::Notify::new)
+}
+
+fn failed_tabs() -> &'static Mutex<HashSet<String>> {
+    FAILED_TABS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+async fn probe(app: &AppHandle) -> bool {
+    let Ok(client) = http::client(app, http::Purpose::Probe) else { return true };
+    for url in PROBE_URLS {
+        if client.get(*url).send().await.is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Probes right away and records the result. Returns whether the network is up.
+pub(crate) async fn check_now(app: &AppHandle) -> bool {
+    let online = probe(app).await;
+    set_online(app, online);
+    online
+}
+
+/// Asks the monitor for an early probe, e.g. because the OS reported a network change.
+fn network_changed() {
+    wake().notify_one();
+}
+
+fn set_online(app: &AppHandle, online: bool) {
+    let now = chrono::Utc::now().timestamp_millis();
+    let changed = {
+        let mut status = status().lock().unwrap();
+        status.checked_at = Some(now);
+        let changed = status.online != online;
+        if changed {
+            status.online = online;
+            status.since = now;
+        }
+        changed.then(|| status.clone())
+    };
+    let Some(status) = changed else { return };
+
+    println!("Lumina: Network is {}", if online { "back" } else { "gone" });
+    events::emit(app, AppEvent::NetworkStatusChanged(status));
+    events::emit(app, AppEvent::Toast(ToastPayload {
+        message: if online { "İnternet bağlantısı geri geldi" } else { "İnternet bağlantısı yok" }.to_string(),
+        level: if online { "success" } else { "warning" }.to_string(),
+    }));
+    if online {
+        retry_failed_tabs(app);
+        downloads::resume_offline_paused(app);
+    }
+}
+
+fn retry_failed_tabs(app: &AppHandle) {
+    let labels: Vec<String> = failed_tabs().lock().unwrap().drain().collect();
+    let tabs = app.state::<TabRegistry>().snapshot(None).tabs;
+    for label in labels {
+        let Some(tab) = tabs.iter().find(|tab| tab.label == label) else { continue };
+        if let (Some(webview), Ok(url)) = (app.get_webview(&label), tab.url.parse()) {
+            println!("Lumina: Retrying {} in tab {}", tab.url, label);
+            let _ = webview.navigate(url);
+        }
+    }
+}
+
+fn navigation_failed(label: &str) {
+    failed_tabs().lock().unwrap().insert(label.to_string());
+    // Often the first sign that the connection is gone
+    network_changed();
+}
+
+fn navigation_succeeded(label: &str) {
+    failed_tabs().lock().unwrap().remove(label);
+}
+
+pub(crate) fn forget_tab(label: &str) {
+    failed_tabs().lock().unwrap().remove(label);
+}
+
+/// Probes connectivity in the background, sooner when the OS reports a change or a tab fails to load.
+pub(crate) fn start_monitor(app: &AppHandle) {
+    watch_os_network_changes(app);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let interval = if check_now(&app).await { PROBE_INTERVAL_ONLINE } else { PROBE_INTERVAL_OFFLINE };
+            let _ = tokio::time::timeout(interval, wake().notified()).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub(crate) fn get_network_status() -> NetworkStatus {
+    status().lock().unwrap().clone()
+}
+
+/// NotifyAddrChange blocks until an IP address on the machine changes, which covers cables,
+/// Wi-Fi and VPNs coming and going.
+#[cfg(windows)]
+fn watch_os_network_changes(_app: &AppHandle) {
+    std::thread::spawn(|| loop {
+        use windows::Win32::NetworkManagement::IpHelper::NotifyAddrChange;
+
+        let result = unsafe { NotifyAddrChange(std::ptr::null_mut(), std::ptr::null()) };
+        if result != 0 {
+            eprintln!("Lumina: NotifyAddrChange failed ({}), falling back to probing", result);
+            return;
+        }
+        network_changed();
+    });
+}
+
+/// GIO's network monitor, which lives on the GTK main thread.
+#[cfg(target_os = "linux")]
+fn watch_os_network_changes(app: &AppHandle) {
+    let _ = app.run_on_main_thread(|| {
+        use webkit2gtk::gio::prelude::NetworkMonitorExt;
+
+        webkit2gtk::gio::NetworkMonitor::default().connect_network_changed(|_, _| network_changed());
+    });
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn watch_os_network_changes(_app: &AppHandle) {}
+
+/// Notes tabs whose navigation failed for lack of a connection (WebView2's NavigationCompleted).
+#[cfg(windows)]
+pub(crate) fn watch_navigation_failures(webview: &tauri::Webview) {
+    let label = webview.label().to_string();
+    let _ = webview.with_webview(move |webview| {
+        use webview2_com::Microsoft::Web::WebView2::Win32::*;
+        use webview2_com::NavigationCompletedEventHandler;
+
+        const OFFLINE_ERRORS: &[COREWEBVIEW2_WEB_ERROR_STATUS] = &[
+            COREWEBVIEW2_WEB_ERROR_STATUS_DISCONNECTED,
+            COREWEBVIEW2_WEB_ERROR_STATUS_CONNECTION_ABORTED,
+            COREWEBVIEW2_WEB_ERROR_STATUS_CONNECTION_RESET,
+            COREWEBVIEW2_WEB_ERROR_STATUS_CANNOT_CONNECT,
+            COREWEBVIEW2_WEB_ERROR_STATUS_HOST_NAME_NOT_RESOLVED,
+            COREWEBVIEW2_WEB_ERROR_STATUS_SERVER_UNREACHABLE,
+            COREWEBVIEW2_WEB_ERROR_STATUS_TIMEOUT,
+        ];
+
+        let handler = NavigationCompletedEventHandler::create(Box::new(move |_, args| {
+            let Some(args) = args else { return Ok(()) };
+            let mut success = windows_core::BOOL::default();
+            unsafe { args.IsSuccess(&mut success)? };
+            if success.as_bool() {
+                navigation_succeeded(&label);
+                return Ok(());
+            }
+            let mut error = COREWEBVIEW2_WEB_ERROR_STATUS::default();
+            unsafe { args.WebErrorStatus(&mut error)? };
+            if OFFLINE_ERRORS.contains(&error) {
+                navigation_failed(&label);
+            }
+            Ok(())
+        }));
+        unsafe {
+            if let Ok(core) = webview.controller().CoreWebView2() {
+                let mut token = 0i64;
+                let _ = core.add_NavigationCompleted(&handler, &mut token);
+            }
+        }
+    });
+}
+
+/// Notes tabs whose navigation failed for lack of a connection (WebKitGTK's load-failed).
+#[cfg(target_os = "linux")]
+pub(crate) fn watch_navigation_failures(webview: &tauri::Webview) {
+    let label = webview.label().to_string();
+    let _ = webview.with_webview(move |webview| {
+        use webkit2gtk::{LoadEvent, NetworkError, PolicyError, WebViewExt};
+
+        let inner = webview.inner();
+        let started = label.clone();
+        inner.connect_load_changed(move |_, event| {
+            if event == LoadEvent::Committed {
+                navigation_succeeded(&started);
+            }
+        });
+        inner.connect_load_failed(move |_, _, _, error| {
+            // Cancelled loads and policy changes (a download, say) aren't connection problems
+            let cancelled = error.kind::<NetworkError>() == Some(NetworkError::Cancelled) || error.kind::<PolicyError>().is_some();
+            if !cancelled {
+                navigation_failed(&label);
+            }
+            // false keeps WebKit's own error page
+            false
+        });
+    });
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub(crate) fn watch_navigation_failures(_webview: &tauri::Webview) {}
@@ -0,0 +1,75 @@
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Url};
+
+use crate::adblock::normalize_site_domain;
+use crate::error::LuminaError;
+use crate::events::{self, AppEvent};
+use crate::history_manager::{HistoryManager, SiteNote};
+use crate::layout::is_browser_window;
+
+/// Tells the UI whether the site a tab is on has a note, so the address bar can show the note button.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SiteNoteStatusPayload {
+    label: String,
+    domain: String,
+    has_note: bool,
+}
+
+/// The key a page's note is filed under: the host without "www.". Only web pages can have notes.
+fn note_domain(url: &Url) -> Option<String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    Some(normalize_site_domain(url.as_str())).filter(|domain| !domain.is_empty())
+}
+
+fn validated_domain(domain: &str) -> Result<String, LuminaError> {
+    let domain = normalize_site_domain(domain);
+    if domain.is_empty() {
+        return Err(LuminaError::InvalidInput("Invalid domain".to_string()));
+    }
+    Ok(domain)
+}
+
+/// Called when a tab commits a navigation.
+pub(crate) fn emit_site_note_status(app: &AppHandle, label: &str, url: &Url) {
+    let domain = note_domain(url).unwrap_or_default();
+    let has_note = !domain.is_empty()
+        && matches!(app.state::<HistoryManager>().get_site_note(&domain), Ok(Some(_)));
+    events::emit(app, AppEvent::SiteNoteStatus(SiteNoteStatusPayload { label: label.to_string(), domain, has_note }));
+}
+
+/// Refreshes the indicator of every open tab on `domain` after its note was saved or deleted.
+fn broadcast_site_note_status(app: &AppHandle, domain: &str) {
+    for (label, webview) in app.webviews() {
+        if is_browser_window(&label) {
+            continue;
+        }
+        if let Ok(url) = webview.url() {
+            if note_domain(&url).as_deref() == Some(domain) {
+                emit_site_note_status(app, &label, &url);
+            }
+        }
+    }
+}
+
+/// Saving blank Markdown deletes the note.
+#[tauri::command]
+pub(crate) fn save_site_note(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, domain: String, markdown: String) -> Result<(), LuminaError> {
+    let domain = validated_domain(&domain)?;
+    history_manager.set_site_note(&domain, &markdown)?;
+    broadcast_site_note_status(&app, &domain);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn get_site_note(history_manager: tauri::State<'_, HistoryManager>, domain: String) -> Result<Option<SiteNote>, LuminaError> {
+    let domain = validated_domain(&domain)?;
+    history_manager.get_site_note(&domain).map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn list_site_notes(history_manager: tauri::State<'_, HistoryManager>) -> Result<Vec<SiteNote>, LuminaError> {
+    history_manager.get_site_notes().map_err(LuminaError::from)
+}
@@ -0,0 +1,253 @@
+use notify_rust::NotificationResponse;
+use tauri::{AppHandle, Manager};
+
+use crate::events::{self, AppEvent};
+use crate::history_manager::HistoryManager;
+use crate::site_prompts;
+
+/// Shows a desktop notification with an "Open" action. `on_done` runs once it is gone, with whether
+/// the user clicked it or its action. Waiting blocks, so the notification gets a thread of its own.
+pub(crate) fn show(app: &AppHandle, title: &str, body: &str, on_done: impl FnOnce(bool) + Send + 'static) {
+    let mut notification = notify_rust::Notification::new();
+    notification.summary(title).body(body).action("default", "Open").auto_icon();
+    #[cfg(windows)]
+    {
+        // Same as the notification plugin: the app id only resolves for the installed app
+        let dev_build = tauri::utils::platform::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.ends_with("target/debug") || dir.ends_with("target/release")))
+            .unwrap_or(true);
+        if !dev_build {
+            notification.app_id(&app.config().identifier);
+        }
+    }
+    #[cfg(not(windows))]
+    let _ = app;
+
+    std::thread::spawn(move || {
+        let handle = match notification.show() {
+            Ok(handle) => handle,
+            Err(e) => {
+                eprintln!("Lumina: notification not shown: {}", e);
+                on_done(false);
+                return;
+            }
+        };
+        let mut clicked = false;
+        let _ = handle.wait_for_response(|response: &NotificationResponse| {
+            clicked = matches!(response, NotificationResponse::Default | NotificationResponse::Action(_));
+        });
+        on_done(clicked);
+    });
+}
+
+/// Brings a tab (or an app window) forward after its notification was clicked.
+fn focus(app: &AppHandle, label: &str) {
+    let Some(webview) = app.get_webview(label) else { return };
+    let _ = webview.window().set_focus();
+    events::emit(app, AppEvent::FocusTab(label.to_string()));
+}
+
+fn page_origin(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    match url.origin() {
+        url::Origin::Tuple(..) => Some(url.origin().ascii_serialization()),
+        url::Origin::Opaque(_) => None,
+    }
+}
+
+fn origin_host(origin: &str) -> String {
+    url::Url::parse(origin).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_else(|| origin.to_string())
+}
+
+fn is_allowed(app: &AppHandle, label: &str, origin: &str) -> bool {
+    crate::pwa::notifications_enabled(app, label)
+        && app.state::<HistoryManager>().get_site_permission(origin, "notifications").ok().flatten().as_deref() == Some("allow")
+}
+
+/// Answers a page's request to show notifications: the stored decision for the origin, otherwise
+/// the user's, asked in the infobar and stored if they chose to remember it.
+async fn request_permission(app: &AppHandle, label: &str, origin: &str) -> bool {
+    if !crate::pwa::notifications_enabled(app, label) {
+        return false;
+    }
+    if let Some(state) = app.state::<HistoryManager>().get_site_permission(origin, "notifications").ok().flatten() {
+        return state == "allow";
+    }
+    let message = format!("{} wants to show notifications", origin_host(origin));
+    let Some(answer) = site_prompts::ask(app, label, "notifications", origin, message, true).await else { return false };
+    if answer.remember {
+        let _ = app.state::<HistoryManager>().set_site_permission(origin, "notifications", if answer.allow { "allow" } else { "deny" });
+    }
+    answer.allow
+}
+
+/// Carries WebView2 objects through a task and back. They are only used again on the main thread,
+/// where WebView2 handed them out.
+#[cfg(windows)]
+struct UnsafeSend<T>(T);
+
+#[cfg(windows)]
+unsafe impl<T> Send for UnsafeSend<T> {}
+
+#[cfg(windows)]
+impl<T> UnsafeSend<T> {
+    fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Takes over a page's notifications (WebView2's PermissionRequested and NotificationReceived): the
+/// permission is asked in the infobar rather than by WebView2, and notifications become desktop
+/// notifications whose clicks reach the page.
+#[cfg(windows)]
+pub(crate) fn watch(app: &AppHandle, webview: &tauri::Webview) {
+    let app = app.clone();
+    let label = webview.label().to_string();
+    let _ = webview.with_webview(move |webview| {
+        use webview2_com::Microsoft::Web::WebView2::Win32::*;
+        use webview2_com::{take_pwstr, NotificationReceivedEventHandler, PermissionRequestedEventHandler};
+        use windows_core::{Interface, PWSTR};
+
+        let (permission_app, permission_label) = (app.clone(), label.clone());
+        let permission_handler = PermissionRequestedEventHandler::create(Box::new(move |_, args| {
+            let Some(args) = args else { return Ok(()) };
+            let mut kind = COREWEBVIEW2_PERMISSION_KIND::default();
+            unsafe { args.PermissionKind(&mut kind)? };
+            if kind != COREWEBVIEW2_PERMISSION_KIND_NOTIFICATIONS {
+                return Ok(());
+            }
+            let mut uri = PWSTR::null();
+            unsafe { args.Uri(&mut uri)? };
+            let Some(origin) = page_origin(&take_pwstr(uri)) else {
+                unsafe { args.SetState(COREWEBVIEW2_PERMISSION_STATE_DENY)? };
+                return Ok(());
+            };
+            let deferral = UnsafeSend(unsafe { args.GetDeferral()? });
+            let args = UnsafeSend(args);
+            let (app, label) = (permission_app.clone(), permission_label.clone());
+            tauri::async_runtime::spawn(async move {
+                let allow = request_permission(&app, &label, &origin).await;
+                let _ = app.run_on_main_thread(move || {
+                    let (args, deferral) = (args.into_inner(), deferral.into_inner());
+                    let state = if allow { COREWEBVIEW2_PERMISSION_STATE_ALLOW } else { COREWEBVIEW2_PERMISSION_STATE_DENY };
+                    unsafe {
+                        let _ = args.SetState(state);
+                        let _ = deferral.Complete();
+                    }
+                });
+            });
+            Ok(())
+        }));
+
+        let notification_handler = NotificationReceivedEventHandler::create(Box::new(move |_, args| {
+            let Some(args) = args else { return Ok(()) };
+            // Handled: WebView2 shows nothing of its own
+            unsafe { args.SetHandled(true)? };
+            let mut origin = PWSTR::null();
+            unsafe { args.SenderOrigin(&mut origin)? };
+            let origin = take_pwstr(origin);
+            let notification = unsafe { args.Notification()? };
+            if !is_allowed(&app, &label, &origin) {
+                unsafe { notification.ReportClosed()? };
+                return Ok(());
+            }
+            let (mut title, mut body) = (PWSTR::null(), PWSTR::null());
+            unsafe {
+                notification.Title(&mut title)?;
+                notification.Body(&mut body)?;
+            }
+            let body = format!("{}\n{}", take_pwstr(body), origin_host(&origin));
+            let (done_app, done_label) = (app.clone(), label.clone());
+            let pending = UnsafeSend(notification.clone());
+            show(&app, &take_pwstr(title), &body, move |clicked| {
+                let main_app = done_app.clone();
+                let _ = main_app.run_on_main_thread(move || {
+                    let notification = pending.into_inner();
+                    unsafe {
+                        let _ = if clicked { notification.ReportClicked() } else { notification.ReportClosed() };
+                    }
+                });
+                if clicked {
+                    focus(&done_app, &done_label);
+                }
+            });
+            unsafe { notification.ReportShown()? };
+            Ok(())
+        }));
+
+        unsafe {
+            if let Ok(core) = webview.controller().CoreWebView2() {
+                let mut token = 0i64;
+                let _ = core.add_PermissionRequested(&permission_handler, &mut token);
+                // NotificationReceived needs a WebView2 runtime from 2024 on; older ones keep their own toasts
+                if let Ok(core) = core.cast::<ICoreWebView2_24>() {
+                    let _ = core.add_NotificationReceived(&notification_handler, &mut token);
+                }
+            }
+        }
+    });
+}
+
+/// Takes over a page's notifications (WebKitGTK's permission-request and show-notification): the
+/// permission is asked in the infobar, and notifications become desktop notifications whose clicks
+/// reach the page.
+#[cfg(target_os = "linux")]
+pub(crate) fn watch(app: &AppHandle, webview: &tauri::Webview) {
+    let app = app.clone();
+    let label = webview.label().to_string();
+    let _ = webview.with_webview(move |webview| {
+        use webkit2gtk::glib::{self, prelude::*};
+        use webkit2gtk::{NotificationExt, NotificationPermissionRequest, PermissionRequestExt, WebViewExt};
+
+        let inner = webview.inner();
+        let (permission_app, permission_label) = (app.clone(), label.clone());
+        inner.connect_permission_request(move |webview, request| {
+            if !request.is::<NotificationPermissionRequest>() {
+                return false;
+            }
+            let Some(origin) = webview.uri().and_then(|uri| page_origin(&uri)) else {
+                request.deny();
+                return true;
+            };
+            let request = request.clone();
+            let (app, label) = (permission_app.clone(), permission_label.clone());
+            // The request stays on the main thread; only the answer is awaited
+            glib::MainContext::default().spawn_local(async move {
+                if request_permission(&app, &label, &origin).await {
+                    request.allow();
+                } else {
+                    request.deny();
+                }
+            });
+            true
+        });
+
+        inner.connect_show_notification(move |webview, notification| {
+            let Some(origin) = webview.uri().and_then(|uri| page_origin(&uri)) else { return true };
+            if !is_allowed(&app, &label, &origin) {
+                return true;
+            }
+            let title = notification.title().map(|t| t.to_string()).unwrap_or_default();
+            let body = format!("{}\n{}", notification.body().map(|b| b.to_string()).unwrap_or_default(), origin_host(&origin));
+            let (done, result) = tokio::sync::oneshot::channel();
+            show(&app, &title, &body, move |clicked| {
+                let _ = done.send(clicked);
+            });
+            let notification = notification.clone();
+            let (app, label) = (app.clone(), label.clone());
+            glib::MainContext::default().spawn_local(async move {
+                if result.await.unwrap_or(false) {
+                    notification.clicked();
+                    focus(&app, &label);
+                } else {
+                    notification.close();
+                }
+            });
+            true
+        });
+    });
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub(crate) fn watch(_app: &AppHandle, _webview: &tauri::Webview) {}
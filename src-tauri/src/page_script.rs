@@ -0,0 +1,85 @@
+use tauri::Webview;
+
+/// Where a script runs in the page. The page's own world shares its globals with the page; the
+/// isolated world sees the same DOM but none of the page's scripts, so they can't watch or wrap it.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum World {
+    Page,
+    Isolated,
+}
+
+/// Name of the isolated world Lumina's own scripts run in
+const ISOLATED_WORLD: &str = "lumina";
+
+/// `body` as the body of an async function whose result comes back JSON-serialized, so both
+/// engines hand back a plain string.
+fn wrap(body: &str) -> String {
+    format!(
+        "return (async () => {{ const value = await (async () => {{ {}\n}})(); return JSON.stringify(value === undefined ? null : value); }})();",
+        body
+    )
+}
+
+fn parse_result(json: Option<&str>) -> Result<serde_json::Value, String> {
+    match json {
+        Some(json) => serde_json::from_str(json).map_err(|e| e.to_string()),
+        None => Ok(serde_json::Value::Null),
+    }
+}
+
+/// Runs `body` (statements of an async function; `return` gives the result) in the webview's top
+/// frame and returns what it returned. The engine hands the result back itself, so nothing in the
+/// page can see, delay or forge it.
+#[cfg(target_os = "linux")]
+pub(crate) async fn run(webview: &Webview, body: &str, world: World) -> Result<serde_json::Value, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let script = wrap(body);
+    webview.with_webview(move |webview| {
+        use javascriptcore::ValueExt;
+        use webkit2gtk::WebViewExt;
+
+        let world = (world == World::Isolated).then_some(ISOLATED_WORLD);
+        webview.inner().call_async_javascript_function(&script, None, world, None, None::<&webkit2gtk::gio::Cancellable>, move |result| {
+            let json = result.map_err(|e| e.to_string()).map(|value| (!value.is_null()).then(|| value.to_str().to_string()));
+            let _ = tx.send(json);
+        });
+    }).map_err(|e| e.to_string())?;
+    let json = rx.await.map_err(|_| "The webview dropped the script".to_string())??;
+    parse_result(json.as_deref())
+}
+
+/// Runs `body` (statements of an async function; `return` gives the result) in the webview's top
+/// frame and returns what it returned. The engine hands the result back itself, so nothing in the
+/// page can see, delay or forge it.
+#[cfg(windows)]
+pub(crate) async fn run(webview: &Webview, body: &str, world: World) -> Result<serde_json::Value, String> {
+    use crate::screenshot::call_devtools;
+
+    let mut params = serde_json::json!({
+        "expression": format!("(function() {{ {} }})()", wrap(body)),
+        "awaitPromise": true,
+        "returnByValue": true,
+    });
+    if world == World::Isolated {
+        let tree = call_devtools(webview, "Page.getFrameTree", "{}").await?;
+        let frame_id = tree["frameTree"]["frame"]["id"].as_str().ok_or("The page has no frame")?;
+        let created = call_devtools(
+            webview,
+            "Page.createIsolatedWorld",
+            &serde_json::json!({ "frameId": frame_id, "worldName": ISOLATED_WORLD }).to_string(),
+        )
+        .await?;
+        params["contextId"] = created["executionContextId"].clone();
+    }
+    let response = call_devtools(webview, "Runtime.evaluate", &params.to_string()).await?;
+    if let Some(exception) = response.get("exceptionDetails") {
+        let message = exception["exception"]["description"].as_str().or(exception["text"].as_str()).unwrap_or("Script failed");
+        return Err(message.to_string());
+    }
+    parse_result(response["result"]["value"].as_str())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) async fn run(_webview: &Webview, _body: &str, _world: World) -> Result<serde_json::Value, String> {
+    Err("Running scripts in tabs is not supported on this platform yet".to_string())
+}
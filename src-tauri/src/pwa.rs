@@ -0,0 +1,1014 @@
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use base64::Engine as _;
+
+use crate::events::AppEvent;
+use crate::data::{AppDataStore, InstalledPwa, PwaProtocolHandler, PwaFileHandler};
+use crate::{capabilities, events, history_manager, http, ipc_guard, notifications, site_prompts};
+use crate::{apply_site_protection, get_lumina_stealth_script, to_script_json};
+use crate::adblock::handle_adblock_request;
+use crate::icons::{download_icon, save_icon};
+use crate::error::LuminaError;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TabPwaPayload {
+    label: String,
+    icon_url: Option<String>,
+}
+
+pub(crate) struct PwaState {
+    pub(crate) icons: std::sync::Mutex<std::collections::HashMap<String, String>>,
+    /// What the manifest declared, by tab label, then by PWA label once an install has started
+    pub(crate) manifests: std::sync::Mutex<std::collections::HashMap<String, PwaManifestInfo>>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct PwaManifestInfo {
+    manifest_url: Option<String>,
+    theme_color: Option<String>,
+    background_color: Option<String>,
+    protocols: Vec<PwaProtocolHandler>,
+    files: Vec<PwaFileHandler>,
+}
+
+/// Schemes a web app may claim without the `web+` prefix (per the HTML spec's safelist).
+const SAFELISTED_PWA_SCHEMES: &[&str] = &[
+    "bitcoin", "ftp", "ftps", "geo", "im", "irc", "ircs", "magnet", "mailto", "matrix", "mms", "news", "nntp",
+    "openpgp4fpr", "sftp", "sip", "sms", "smsto", "ssh", "tel", "urn", "webcal", "wtai", "xmpp",
+];
+
+/// Largest file handed to a PWA through `launchQueue`; it travels inside an init script.
+const PWA_LAUNCH_FILE_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// `protocol_handlers` and `file_handlers` from a manifest. Targets resolve against the manifest URL
+/// and must stay on its origin; anything malformed is dropped.
+fn parse_pwa_handlers(manifest: &serde_json::Value, manifest_url: &str) -> PwaManifestInfo {
+    let Ok(base) = url::Url::parse(manifest_url) else { return PwaManifestInfo::default() };
+    let resolve = |target: &str| base.join(target).ok().filter(|u| u.origin() == base.origin());
+
+    let protocols = manifest
+        .get("protocol_handlers")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|h| {
+            let protocol = h.get("protocol")?.as_str()?.to_lowercase();
+            let custom = protocol.strip_prefix("web+").is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_lowercase()));
+            if !custom && !SAFELISTED_PWA_SCHEMES.contains(&protocol.as_str()) {
+                return None;
+            }
+            // join() would percent-encode the placeholder
+            let url = resolve(h.get("url")?.as_str()?)?.to_string().replace("%25s", "%s");
+            url.contains("%s").then_some(PwaProtocolHandler { protocol, url })
+        })
+        .collect();
+
+    let files = manifest
+        .get("file_handlers")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|h| {
+            let action = resolve(h.get("action")?.as_str()?)?.to_string();
+            let accept = h.get("accept")?.as_object()?;
+            let mut extensions = Vec::new();
+            for value in accept.values() {
+                let listed: Vec<&str> = match value {
+                    serde_json::Value::String(ext) => vec![ext.as_str()],
+                    serde_json::Value::Array(exts) => exts.iter().filter_map(|e| e.as_str()).collect(),
+                    _ => Vec::new(),
+                };
+                extensions.extend(listed.into_iter().filter(|e| e.starts_with('.') && e.len() > 1).map(|e| e.to_lowercase()));
+            }
+            (!extensions.is_empty()).then(|| PwaFileHandler { action, extensions, mime_types: accept.keys().cloned().collect() })
+        })
+        .collect();
+
+    PwaManifestInfo { manifest_url: Some(manifest_url.to_string()), protocols, files, ..Default::default() }
+}
+
+/// Hooks the app's handlers into the OS so links and files launch Lumina with `--pwa-handler`.
+fn register_pwa_os_handlers(pwa: &InstalledPwa, app: &AppHandle) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let exe = exe.to_string_lossy();
+
+    #[cfg(target_os = "windows")]
+    {
+        let _ = app;
+        let quote = |s: &str| s.replace('\'', "''");
+        // New-Item -Force on an existing key would wipe values other apps put there
+        let set_value = |key: &str, name: &str, value: &str| {
+            format!(
+                "$k = 'HKCU:\\Software\\Classes\\{}'; if (!(Test-Path $k)) {{ New-Item -Path $k -Force | Out-Null }}; \
+                 Set-ItemProperty -Path $k -Name '{}' -Value '{}'; ",
+                quote(key),
+                quote(name),
+                quote(value)
+            )
+        };
+        let command = format!("\"{}\" --pwa-url=\"{}\" --pwa-handler=\"%1\"", exe, pwa.start_url);
+
+        let mut script = String::new();
+        for handler in &pwa.protocol_handlers {
+            let scheme = &handler.protocol;
+            script.push_str(&set_value(scheme, "(default)", &format!("URL:{} ({})", pwa.title, scheme)));
+            script.push_str(&set_value(scheme, "URL Protocol", ""));
+            script.push_str(&set_value(&format!("{}\\shell\\open\\command", scheme), "(default)", &command));
+        }
+        if !pwa.file_handlers.is_empty() {
+            let prog_id = format!("Lumina.{}", pwa.id);
+            script.push_str(&set_value(&format!("{}\\shell\\open\\command", prog_id), "(default)", &command));
+            for ext in pwa.file_handlers.iter().flat_map(|h| &h.extensions) {
+                script.push_str(&set_value(&format!("{}\\OpenWithProgids", ext), &prog_id, ""));
+            }
+        }
+
+        let output = std::process::Command::new("powershell")
+            .arg("-NoProfile")
+            .arg("-Command")
+            .arg(script)
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"").replace('`', "\\`").replace('$', "\\$");
+        let schemes: Vec<String> = pwa.protocol_handlers.iter().map(|h| format!("x-scheme-handler/{}", h.protocol)).collect();
+        let mime_types: Vec<String> = schemes
+            .iter()
+            .cloned()
+            .chain(pwa.file_handlers.iter().flat_map(|h| h.mime_types.iter().cloned()))
+            .collect();
+        let desktop_name = format!("lumina-{}.desktop", pwa.id);
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec=\"{}\" \"--pwa-url={}\" --pwa-handler=%u\nMimeType={};\nNoDisplay=true\n",
+            pwa.title.replace('\n', " "),
+            escape(&exe),
+            escape(&pwa.start_url),
+            mime_types.join(";")
+        );
+        let dir = app.path().data_dir().map_err(|e| e.to_string())?.join("applications");
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join(&desktop_name), entry).map_err(|e| e.to_string())?;
+
+        // Schemes the user just agreed to become the default; file types only join "Open with"
+        for scheme in &schemes {
+            let _ = std::process::Command::new("xdg-mime").args(["default", &desktop_name, scheme]).output();
+        }
+        let _ = std::process::Command::new("update-desktop-database").arg(&dir).output();
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        let _ = (pwa, app, exe);
+        Err("Registering app handlers isn't supported on this platform".to_string())
+    }
+}
+
+/// Where a PWA launched with `--pwa-handler` should open, plus the script that hands it any file.
+/// Links go to the matching protocol handler's URL; files go to the matching file handler's action
+/// and arrive through `window.launchQueue`. Anything unmatched just opens the start URL.
+pub(crate) fn resolve_pwa_launch(app: &AppHandle, start_url: &str, handler_arg: Option<&str>) -> (String, Option<String>) {
+    let fallback = (start_url.to_string(), None);
+    let (Some(arg), Some(pwa)) = (handler_arg, app.state::<AppDataStore>().installed_pwa_by_start_url(start_url)) else {
+        return fallback;
+    };
+
+    // Single-letter "schemes" are Windows drive letters
+    if let Some(link) = url::Url::parse(arg).ok().filter(|u| u.scheme().len() > 1 && u.scheme() != "file") {
+        return match pwa.protocol_handlers.iter().find(|h| h.protocol == link.scheme()) {
+            Some(handler) => (handler.url.replace("%s", &urlencoding::encode(arg)), None),
+            None => fallback,
+        };
+    }
+
+    let path = url::Url::parse(arg).ok().and_then(|u| u.to_file_path().ok()).unwrap_or_else(|| PathBuf::from(arg));
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy().to_lowercase())).unwrap_or_default();
+    let Some(handler) = pwa.file_handlers.iter().find(|h| h.extensions.contains(&ext)) else { return fallback };
+    if std::fs::metadata(&path).map(|m| m.len() > PWA_LAUNCH_FILE_LIMIT).unwrap_or(true) {
+        return (handler.action.clone(), None);
+    }
+    let Ok(bytes) = std::fs::read(&path) else { return (handler.action.clone(), None) };
+
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let script = format!(
+        r#"
+    (function() {{
+        const bytes = Uint8Array.from(atob('{}'), c => c.charCodeAt(0));
+        const file = new File([bytes], {}, {{ type: {} }});
+        const handle = {{ kind: 'file', name: file.name, getFile: async () => file }};
+        const params = {{ files: [handle], targetURL: location.href }};
+        window.launchQueue = {{ setConsumer(consumer) {{ consumer(params); }} }};
+    }})();
+    "#,
+        base64::engine::general_purpose::STANDARD.encode(bytes),
+        to_script_json(&name),
+        to_script_json(&handler.mime_types.first().cloned().unwrap_or_default())
+    );
+    (handler.action.clone(), Some(script))
+}
+
+/// Asks in the browser's infobar before touching OS handler registrations, and registers the app's
+/// handlers if the user agrees. The app window itself has no say.
+async fn ask_pwa_handlers(app: &AppHandle, pwa: &InstalledPwa) {
+    let mut claims: Vec<String> = pwa.protocol_handlers.iter().map(|h| format!("{}: links", h.protocol)).collect();
+    claims.extend(pwa.file_handlers.iter().flat_map(|h| h.extensions.iter().map(|e| format!("{} files", e))));
+    let message = format!("{} wants to open {} with its app window", pwa.title, claims.join(", "));
+    let origin = url::Url::parse(&pwa.start_url).map(|u| u.origin().ascii_serialization()).unwrap_or_default();
+    let Some(answer) = site_prompts::ask(app, &pwa.id, "app-handlers", &origin, message, false).await else { return };
+    if answer.allow {
+        if let Err(e) = register_pwa_os_handlers(pwa, app) {
+            eprintln!("Failed to register handlers for {}: {}", pwa.title, e);
+            return;
+        }
+    }
+    let state = app.state::<AppDataStore>();
+    state.set_pwa_handlers_registered(&pwa.id, answer.allow);
+    state.save();
+}
+
+/// A manifest color member, kept only if it is a color Lumina can apply.
+fn manifest_color(manifest: &serde_json::Value, key: &str) -> Option<String> {
+    manifest.get(key).and_then(|v| v.as_str()).map(str::trim).filter(|c| parse_css_color(c).is_some()).map(str::to_string)
+}
+
+/// Parses the forms manifests use in practice: `#rgb`, `#rrggbb`, `#rrggbbaa` and `rgb()`/`rgba()`.
+fn parse_css_color(value: &str) -> Option<(u8, u8, u8)> {
+    let value = value.trim().to_ascii_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+        return match hex.len() {
+            3 => {
+                let mut c = hex.chars().map(|c| c.to_digit(16).map(|d| (d * 17) as u8));
+                Some((c.next()??, c.next()??, c.next()??))
+            }
+            6 | 8 => Some((channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?)),
+            _ => None,
+        };
+    }
+    let inner = value.strip_prefix("rgba(").or_else(|| value.strip_prefix("rgb("))?.strip_suffix(')')?;
+    let mut parts = inner.split([',', ' ', '/']).filter(|p| !p.is_empty()).map(|p| p.parse::<f32>().ok().map(|n| n.clamp(0.0, 255.0) as u8));
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+/// Whether text on this color should be light, by the WCAG relative luminance.
+fn is_dark_color((r, g, b): (u8, u8, u8)) -> bool {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.03928 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b) < 0.179
+}
+
+/// Window background and light/dark frame from the app's manifest colors, so there is no white flash
+/// and the native titlebar matches before the page paints.
+pub(crate) fn apply_pwa_colors<'a, R: tauri::Runtime, M: Manager<R>>(
+    builder: tauri::WebviewWindowBuilder<'a, R, M>,
+    theme_color: Option<&str>,
+    background_color: Option<&str>,
+) -> tauri::WebviewWindowBuilder<'a, R, M> {
+    let mut builder = builder;
+    if let Some((r, g, b)) = background_color.and_then(parse_css_color) {
+        builder = builder.background_color(tauri::window::Color(r, g, b, 255));
+    }
+    if let Some(color) = theme_color.and_then(parse_css_color) {
+        builder = builder.theme(Some(if is_dark_color(color) { tauri::Theme::Dark } else { tauri::Theme::Light }));
+    }
+    builder
+}
+
+/// Paints the titlebar itself in the theme color where the OS allows it (Windows 11).
+pub(crate) fn apply_pwa_caption_color(_window: &tauri::WebviewWindow, _theme_color: Option<&str>) {
+    #[cfg(target_os = "windows")]
+    {
+        use windows::Win32::Foundation::HWND;
+        use windows::Win32::Graphics::Dwm::{DwmSetWindowAttribute, DWMWA_CAPTION_COLOR, DWMWA_TEXT_COLOR};
+
+        let Some(color) = _theme_color.and_then(parse_css_color) else { return };
+        let Ok(hwnd) = _window.hwnd() else { return };
+        let hwnd = HWND(hwnd.0 as isize);
+        // COLORREF is 0x00BBGGRR
+        let caption = color.0 as u32 | (color.1 as u32) << 8 | (color.2 as u32) << 16;
+        let text: u32 = if is_dark_color(color) { 0x00FFFFFF } else { 0x00000000 };
+        unsafe {
+            let _ = DwmSetWindowAttribute(hwnd, DWMWA_CAPTION_COLOR, &caption as *const u32 as *const _, 4);
+            let _ = DwmSetWindowAttribute(hwnd, DWMWA_TEXT_COLOR, &text as *const u32 as *const _, 4);
+        }
+    }
+}
+
+/// The largest icon a manifest lists, resolved against the manifest URL.
+fn best_manifest_icon(manifest: &serde_json::Value, manifest_url: &str) -> Option<String> {
+    let mut best_icon_url = None;
+    let mut max_area = 0;
+    if let Some(icons) = manifest.get("icons").and_then(|v| v.as_array()) {
+        for icon in icons {
+            if let Some(src) = icon.get("src").and_then(|v| v.as_str()) {
+                if let Some(sizes) = icon.get("sizes").and_then(|v| v.as_str()) {
+                    for size in sizes.split_whitespace() {
+                        if size == "any" { continue; }
+                        if let Some((w, h)) = size.split_once('x') {
+                            if let (Ok(w), Ok(h)) = (w.parse::<i32>(), h.parse::<i32>()) {
+                                if w * h > max_area {
+                                    max_area = w * h;
+                                    best_icon_url = Some(src.to_string());
+                                }
+                            }
+                        }
+                    }
+                }
+                // Fallback if no sizes and we haven't found a better one yet
+                if best_icon_url.is_none() {
+                    best_icon_url = Some(src.to_string());
+                }
+            }
+        }
+    }
+
+    let icon = best_icon_url?;
+    match url::Url::parse(manifest_url).and_then(|base| base.join(&icon)) {
+        Ok(joined) => Some(joined.to_string()),
+        Err(_) => Some(icon),
+    }
+}
+
+/// How often installed apps' manifests are looked at, and how old a check has to be to redo it.
+pub(crate) const PWA_MANIFEST_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+const PWA_MANIFEST_REFRESH_AGE: i64 = 24 * 60 * 60;
+
+/// Re-reads the manifest of every installed app that is due. Icon changes are applied quietly; a new
+/// name or start URL also rewrites the shortcut and OS registrations and tells the user.
+pub(crate) async fn refresh_installed_pwas(app: &AppHandle) {
+    let now = chrono::Utc::now().timestamp();
+    let due: Vec<InstalledPwa> = app
+        .state::<AppDataStore>()
+        .installed_pwas()
+        .into_iter()
+        .filter(|p| p.manifest_url.is_some() && now - p.manifest_checked_at >= PWA_MANIFEST_REFRESH_AGE)
+        .collect();
+    if due.is_empty() {
+        return;
+    }
+
+    let Ok(client) = http::client(app, http::Purpose::Manifest) else { return };
+    for mut pwa in due {
+        let Some(manifest_url) = pwa.manifest_url.clone() else { continue };
+        pwa.manifest_checked_at = now;
+
+        let manifest = match client.get(&manifest_url).send().await {
+            Ok(res) if res.status().is_success() => res.json::<serde_json::Value>().await.ok(),
+            _ => None,
+        };
+        let Some(manifest) = manifest else {
+            app.state::<AppDataStore>().upsert_installed_pwa(pwa);
+            continue;
+        };
+
+        let mut changes = Vec::new();
+        let name = manifest.get("name").or_else(|| manifest.get("short_name")).and_then(|v| v.as_str()).map(str::trim);
+        if let Some(name) = name.filter(|n| !n.is_empty() && *n != pwa.title) {
+            changes.push(format!("now called \"{}\"", name));
+            remove_desktop_shortcut(&pwa.title);
+            pwa.title = name.to_string();
+        }
+
+        // A start URL may only move within the app's origin
+        let start_url = manifest
+            .get("start_url")
+            .and_then(|v| v.as_str())
+            .and_then(|s| url::Url::parse(&manifest_url).and_then(|base| base.join(s)).ok())
+            .filter(|u| url::Url::parse(&pwa.start_url).is_ok_and(|old| old.origin() == u.origin()))
+            .map(|u| u.to_string());
+        if let Some(start_url) = start_url.filter(|u| history_manager::canonicalize_url(u) != history_manager::canonicalize_url(&pwa.start_url)) {
+            changes.push(format!("now starts at {}", start_url));
+            pwa.start_url = start_url;
+        }
+
+        pwa.theme_color = manifest_color(&manifest, "theme_color");
+        pwa.background_color = manifest_color(&manifest, "background_color");
+
+        let icon_changed = match best_manifest_icon(&manifest, &manifest_url) {
+            Some(icon_url) if pwa.icon_url.as_deref() != Some(icon_url.as_str()) => match download_icon(app, &icon_url).await {
+                Some(path) => {
+                    pwa.icon_url = Some(icon_url);
+                    pwa.icon_path = Some(path.to_string_lossy().to_string());
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        };
+
+        if icon_changed || !changes.is_empty() {
+            let _ = create_desktop_shortcut(&pwa.title, &pwa.start_url, pwa.icon_path.as_ref().map(PathBuf::from));
+        }
+        if !changes.is_empty() && pwa.handlers_registered {
+            if let Err(e) = register_pwa_os_handlers(&pwa, app) {
+                eprintln!("Failed to update handlers for {}: {}", pwa.id, e);
+            }
+        }
+        if let Some(window) = app.get_webview_window(&pwa.id) {
+            let _ = window.set_title(&pwa.title);
+            apply_pwa_caption_color(&window, pwa.theme_color.as_deref());
+            if icon_changed {
+                if let Some(img) = pwa.icon_path.as_ref().and_then(|p| image::open(p).ok()) {
+                    let rgba = img.to_rgba8();
+                    let (width, height) = rgba.dimensions();
+                    let _ = window.set_icon(tauri::image::Image::new_owned(rgba.into_raw(), width, height));
+                }
+            }
+        }
+        if !changes.is_empty() {
+            notifications::show(
+                app,
+                &format!("{} was updated", pwa.title),
+                &format!("The installed app is {}.", changes.join(" and ")),
+                |_| {},
+            );
+        }
+        app.state::<AppDataStore>().upsert_installed_pwa(pwa);
+    }
+    app.state::<AppDataStore>().save();
+}
+
+/// Where an app installed with its own sign-in keeps cookies, storage and cache. WebView2 and
+/// WebKitGTK honour it; WKWebView ignores it, so on macOS such apps still share the browser profile.
+pub(crate) fn pwa_data_dir(app: &AppHandle, id: &str) -> Option<PathBuf> {
+    Some(app.path().app_data_dir().ok()?.join("pwa-profiles").join(id))
+}
+
+#[tauri::command]
+pub(crate) fn list_installed_pwas(state: tauri::State<'_, AppDataStore>) -> Vec<InstalledPwa> {
+    state.installed_pwas()
+}
+
+/// Wipes an isolated app's profile. Its window is closed first since the engine keeps the directory locked.
+#[tauri::command]
+pub(crate) async fn clear_pwa_data(app: AppHandle, id: String) -> Result<(), LuminaError> {
+    let pwa = app.state::<AppDataStore>().installed_pwa(&id).ok_or("Unknown app")?;
+    if !pwa.isolated {
+        return Err(LuminaError::Other("This app shares the browser profile; clear its site data from the browser instead".to_string()));
+    }
+    let dir = pwa_data_dir(&app, &id).ok_or("No app data directory")?;
+
+    if let Some(window) = app.get_webview_window(&id) {
+        let _ = window.destroy();
+        // Give the engine a moment to let go of its files
+        tokio::time::sleep(std::time::Duration::from_millis(800)).await;
+    }
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn pwa_detected(app: AppHandle, state: tauri::State<'_, PwaState>, label: String, icon_url: Option<String>) -> Result<(), LuminaError> {
+    if let Some(url) = &icon_url {
+        state.icons.lock().unwrap().insert(label.clone(), url.clone());
+    }
+    events::emit(&app, AppEvent::PwaCanInstall(TabPwaPayload { label, icon_url }));
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn install_pwa(app: AppHandle, state: tauri::State<'_, PwaState>, label: String) -> Result<(), LuminaError> {
+    // A site that offered its own install prompt gets it; otherwise the page's title and icon
+    const INSTALL_PAGE_JS: &str = r#"
+        const deferred = window.deferredPrompt;
+        if (deferred) {
+            window.deferredPrompt = null;
+            deferred.prompt();
+            return { prompted: true };
+        }
+        const icon = document.querySelector("link[rel*='icon']");
+        return { prompted: false, title: document.title, faviconUrl: icon ? icon.href : null };
+    "#;
+
+    // Get stored icon URL if available
+    let icon_url = state.icons.lock().unwrap().get(&label).cloned();
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("Tab {} not found", label)))?;
+    let page_url = webview.url()?;
+
+    // The manifest info follows the install to the window open_pwa_window is about to create
+    {
+        let mut manifests = state.manifests.lock().unwrap();
+        if let Some(found) = manifests.get(&label).cloned() {
+            manifests.insert(sanitize_pwa_label(page_url.as_str()), found);
+        }
+    }
+
+    let page = crate::page_script::run(&webview, INSTALL_PAGE_JS, crate::page_script::World::Page)
+        .await
+        .map_err(LuminaError::Webview)?;
+    if page["prompted"].as_bool() == Some(true) {
+        return Ok(());
+    }
+    let title = page["title"].as_str().filter(|t| !t.trim().is_empty()).unwrap_or(page_url.as_str()).to_string();
+    let favicon_url = icon_url.or_else(|| page["faviconUrl"].as_str().map(str::to_string));
+
+    // Asked in the infobar: the page never sees the question or the answer
+    let origin = page_url.origin().ascii_serialization();
+    let message = format!(
+        "Give {} its own sign-in? Its cookies and site data will be kept apart from your browser.",
+        page_url.host_str().unwrap_or(&title)
+    );
+    let Some(answer) = site_prompts::ask(&app, &label, "app-isolation", &origin, message, false).await else { return Ok(()) };
+    open_pwa_window(app.clone(), page_url.to_string(), title, favicon_url, None, Some(answer.allow)).await
+}
+
+fn sanitize_pwa_label(url: &str) -> String {
+    // Extract hostname or use a hash if not parseable
+    if let Ok(parsed) = url::Url::parse(url) {
+        if let Some(host) = parsed.host_str() {
+            let safe_host: String = host.chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '-' })
+                .collect();
+            return format!("pwa-{}", safe_host);
+        }
+    }
+    // Fallback to timestamp if parsing fails
+    format!("pwa-{}", chrono::Utc::now().timestamp_micros())
+}
+
+/// IPC bridge and link handling for PWA (and headless) windows. Issues a fresh IPC token for `label`.
+pub(crate) fn get_pwa_init_script(label: &str, invoke_key: &str) -> String {
+    let ipc_token = ipc_guard::issue_token(label);
+    format!(r#"
+        (function() {{
+            window.__TAB_LABEL__ = "{}";
+            // Kept in this closure and sent through the native channel captured at document start,
+            // like the tab bridge (see create_tab)
+            const invokeKey = "{}";
+            const ipcToken = "{}";
+            const nativePost = (function() {{
+                if (window.chrome && window.chrome.webview) {{
+                    return window.chrome.webview.postMessage.bind(window.chrome.webview);
+                }}
+                if (window.webkit && window.webkit.messageHandlers && window.webkit.messageHandlers.ipc) {{
+                    const ipc = window.webkit.messageHandlers.ipc;
+                    return ipc.postMessage.bind(ipc);
+                }}
+                return null;
+            }})();
+            
+            // Custom IPC for PWA windows
+            function invoke(cmd, args) {{
+                args = Object.assign({{}}, args, {{ __luminaIpcToken: ipcToken }});
+
+                if (typeof window.__IPC_COUNTER === 'undefined') window.__IPC_COUNTER = 0;
+                window.__IPC_COUNTER = (window.__IPC_COUNTER + 1) % 4000000000;
+                var callbackId = window.__IPC_COUNTER;
+                var noOp = function(res) {{}};
+                
+                try {{
+                     if (window.__TAURI_INTERNALS__) {{
+                         if (!window.__TAURI_INTERNALS__.callbacks) window.__TAURI_INTERNALS__.callbacks = {{}};
+                         window.__TAURI_INTERNALS__.callbacks[callbackId] = {{ resolve: noOp, reject: noOp }};
+                     }}
+                }} catch(e) {{}}
+                
+                setTimeout(function() {{
+                    try {{
+                        if (window.__TAURI_INTERNALS__ && window.__TAURI_INTERNALS__.callbacks) {{
+                             delete window.__TAURI_INTERNALS__.callbacks[callbackId];
+                        }}
+                    }} catch(e) {{}}
+                }}, 60000);
+
+                var msg = {{
+                    cmd: cmd,
+                    callback: callbackId, 
+                    error: callbackId,
+                    payload: args,
+                    __TAURI_INVOKE_KEY__: invokeKey
+                }};
+                
+                if (nativePost) nativePost(msg);
+            }}
+
+            // Override window.open
+            window.open = function(url, target, features) {{
+                if (url) {{
+                    // Call create_tab directly on the main window via our fixed command
+                    invoke('create_tab', {{ label: 'new-tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000), url: url }});
+                }}
+                return null;
+            }};
+            
+            // Handle _blank links
+            document.addEventListener('click', (e) => {{
+                let target = e.target;
+                while(target && target.tagName !== 'A') target = target.parentElement;
+                if (target && target.tagName === 'A' && target.target === '_blank') {{
+                    e.preventDefault();
+                    invoke('create_tab', {{ label: 'new-tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000), url: target.href }});
+                }}
+            }}, true);
+
+            document.addEventListener('auxclick', (e) => {{
+                if (e.button === 1) {{
+                    let target = e.target;
+                    while(target && target.tagName !== 'A') target = target.parentElement;
+                    if (target && target.tagName === 'A') {{
+                        e.preventDefault();
+                        invoke('create_tab', {{ label: 'new-tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000), url: target.href }});
+                    }}
+                }}
+            }}, true);
+
+            // Context Menu Override
+            document.addEventListener('contextmenu', (e) => {{
+                let target = e.target;
+                let linkUrl = null;
+                while(target && target.tagName !== 'A') target = target.parentElement;
+                if (target && target.tagName === 'A' && target.href) {{
+                    linkUrl = target.href;
+                }}
+
+                if (linkUrl) {{
+                    e.preventDefault();
+                    e.stopPropagation(); 
+                    
+                    const existing = document.getElementById('lumina-context-menu');
+                    if (existing) existing.remove();
+
+                    const menu = document.createElement('div');
+                    menu.id = 'lumina-context-menu';
+                    menu.style.position = 'fixed';
+                    menu.style.top = e.clientY + 'px';
+                    menu.style.left = e.clientX + 'px';
+                    menu.style.zIndex = '2147483647'; 
+                    menu.style.background = '#292a2d';
+                    menu.style.border = '1px solid #3c4043';
+                    menu.style.borderRadius = '4px';
+                    menu.style.padding = '4px 0';
+                    menu.style.color = '#e8eaed';
+                    menu.style.fontFamily = 'system-ui, sans-serif';
+                    menu.style.fontSize = '13px';
+                    menu.style.userSelect = 'none';
+
+                    const createItem = (text, onClick) => {{
+                        const item = document.createElement('div');
+                        item.innerText = text;
+                        item.style.padding = '6px 12px';
+                        item.style.cursor = 'pointer';
+                        item.onmouseenter = () => item.style.background = '#3c4043';
+                        item.onmouseleave = () => item.style.background = 'transparent';
+                        item.onclick = (ev) => {{
+                            ev.stopPropagation(); 
+                            onClick();
+                            menu.remove();
+                        }};
+                        return item;
+                    }};
+
+                    menu.appendChild(createItem('Open Link in New Tab', () => {{
+                         invoke('create_tab', {{ label: 'tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000), url: linkUrl }});
+                    }}));
+                    
+                    // Add copy link
+                    menu.appendChild(createItem('Copy Link Address', () => {{
+                         navigator.clipboard.writeText(linkUrl);
+                    }}));
+                    
+                    document.body.appendChild(menu);
+                    
+                    const closeMenu = () => {{
+                        menu.remove();
+                        document.removeEventListener('click', closeMenu);
+                        document.removeEventListener('contextmenu', closeMenu);
+                    }};
+                    setTimeout(() => {{
+                        document.addEventListener('click', closeMenu);
+                        document.addEventListener('contextmenu', (e) => {{
+                             if (e.target.closest('#lumina-context-menu')) return;
+                             closeMenu();
+                        }});
+                    }}, 100);
+                }}
+            }}, true);
+
+        }})();
+    "#, label, invoke_key, ipc_token)
+}
+
+#[tauri::command]
+pub(crate) async fn open_pwa_window(app: AppHandle, url: String, title: String, favicon_url: Option<String>, icon_data: Option<String>, isolated: Option<bool>) -> Result<(), LuminaError> {
+    let label = sanitize_pwa_label(&url);
+    
+    // Check if window already exists
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.set_focus();
+        return Ok(());
+    }
+    
+    let icon_url = favicon_url.clone().filter(|_| icon_data.is_none());
+
+    // Get Icon Path
+    let icon_path = if let Some(data) = icon_data {
+        // Decode base64
+        if let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(data) {
+             save_icon(&app, &bytes).await
+        } else {
+             None
+        }
+    } else if let Some(f_url) = favicon_url {
+        download_icon(&app, &f_url).await
+    } else {
+        None
+    };
+
+    let icon_path_clone = icon_path.clone();
+
+    // Create Desktop Shortcut
+    let _ = create_desktop_shortcut(&title, &url, icon_path);
+
+    let label_clone = label.clone();
+
+    // Inject PWA script for handling window.open and context menu
+    let invoke_key = app.invoke_key();
+    let script = get_pwa_init_script(&label, invoke_key);
+
+    let manifest = app.state::<PwaState>().manifests.lock().unwrap().remove(&label).unwrap_or_default();
+    // A reinstall keeps what the user allowed the app
+    let previous = app.state::<AppDataStore>().installed_pwa(&label);
+    let installed = InstalledPwa {
+        id: label.clone(),
+        start_url: url.clone(),
+        title: title.clone(),
+        protocol_handlers: manifest.protocols,
+        file_handlers: manifest.files,
+        handlers_registered: false,
+        manifest_url: manifest.manifest_url,
+        icon_url,
+        icon_path: icon_path_clone.as_ref().map(|p| p.to_string_lossy().to_string()),
+        manifest_checked_at: chrono::Utc::now().timestamp(),
+        theme_color: manifest.theme_color,
+        background_color: manifest.background_color,
+        isolated: isolated.unwrap_or(false),
+        notifications: previous.as_ref().is_none_or(|pwa| pwa.notifications),
+        service_workers: previous.as_ref().is_none_or(|pwa| pwa.service_workers),
+    };
+    let data_dir = installed.isolated.then(|| pwa_data_dir(&app, &label)).flatten();
+    let colors = (installed.theme_color.clone(), installed.background_color.clone());
+    let wants_handlers = (!installed.protocol_handlers.is_empty() || !installed.file_handlers.is_empty()).then(|| installed.clone());
+    let data_store = app.state::<AppDataStore>();
+    data_store.upsert_installed_pwa(installed);
+    data_store.save();
+
+    let mut builder = tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::External(url.parse().map_err(|e: url::ParseError| e.to_string())?))
+        .title(&title)
+        .initialization_script(&script);
+    if let Some(dir) = data_dir {
+        builder = builder.data_directory(dir);
+    }
+
+    if let Some(path) = icon_path_clone {
+        if let Ok(img) = image::open(&path) {
+             let rgba = img.to_rgba8();
+             let (width, height) = rgba.dimensions();
+             let rgba_vec = rgba.into_raw();
+             let tauri_img = tauri::image::Image::new_owned(rgba_vec, width, height);
+             match builder.icon(tauri_img) {
+                 Ok(b) => builder = b,
+                 Err(e) => return Err(LuminaError::Webview(format!("Failed to set window icon: {}", e))),
+             }
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/144.0.0.0 Safari/537.36 Edg/144.0.0.0");
+        builder = builder.additional_browser_args("--ignore-certificate-errors");
+    }
+    #[cfg(target_os = "linux")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/144.0.0.0 Safari/537.36");
+    }
+    #[cfg(target_os = "macos")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/144.0.0.0 Safari/537.36");
+    }
+
+    let builder = builder.inner_size(1024.0, 768.0)
+        .decorations(true) // Enable native window controls (Close, Minimize, Maximize)
+        .focused(true)
+        .initialization_script(get_lumina_stealth_script())
+        .initialization_script(get_pwa_capability_script(&app, &label))
+        .on_page_load(on_pwa_page_load)
+        .on_web_resource_request(move |request, response| {
+            handle_adblock_request(&request, response, &label_clone);
+        });
+    let window = apply_pwa_colors(builder, colors.0.as_deref(), colors.1.as_deref())
+        .build()?;
+    apply_pwa_caption_color(&window, colors.0.as_deref());
+    crate::notifications::watch(&app, window.as_ref());
+    if let Some(pwa) = wants_handlers {
+        tauri::async_runtime::spawn(async move { ask_pwa_handlers(&app, &pwa).await });
+    }
+    Ok(())
+}
+
+/// Runs in PWA windows after the PWA init script. Applies a stub `navigator.serviceWorker` (with
+/// existing registrations dropped) when the app isn't allowed service workers.
+pub(crate) fn get_pwa_capability_script(app: &AppHandle, id: &str) -> String {
+    let service_workers = app.state::<AppDataStore>().installed_pwa(id).is_none_or(|pwa| pwa.service_workers);
+
+    let mut script = String::new();
+    if !service_workers {
+        script.push_str(r#"
+    (function() {
+        if (!('serviceWorker' in navigator)) return;
+        navigator.serviceWorker.getRegistrations().then(regs => regs.forEach(r => r.unregister())).catch(() => {});
+        const refuse = () => Promise.reject(new DOMException('Service workers are disabled for installed apps', 'SecurityError'));
+        try {
+            Object.defineProperty(navigator.serviceWorker, 'register', { value: refuse, configurable: true });
+        } catch (e) {}
+    })();
+    "#);
+    }
+    script
+}
+
+/// Whether a window may ask for notifications: tabs always can, installed apps only if allowed
+/// them. The site's own permission still applies either way.
+pub(crate) fn notifications_enabled(app: &AppHandle, label: &str) -> bool {
+    app.state::<AppDataStore>().installed_pwa(label).is_none_or(|pwa| pwa.notifications)
+}
+
+#[tauri::command]
+pub(crate) fn get_web_capabilities() -> capabilities::WebCapabilities {
+    capabilities::detect()
+}
+
+/// `on_page_load` of PWA windows: applies the site's protections as the page commits, and once it
+/// has loaded asks it what the engine exposes, for `get_web_capabilities`.
+pub(crate) fn on_pwa_page_load(window: tauri::WebviewWindow, payload: tauri::webview::PageLoadPayload<'_>) {
+    apply_site_protection(window.app_handle(), window.as_ref(), &payload);
+    if payload.event() != tauri::webview::PageLoadEvent::Finished {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        let probe = crate::page_script::run(window.as_ref(), "return 'serviceWorker' in navigator;", crate::page_script::World::Isolated).await;
+        if let Ok(serde_json::Value::Bool(service_worker)) = probe {
+            capabilities::record_probe(service_worker);
+        }
+    });
+}
+
+fn update_installed_pwa(state: &AppDataStore, id: &str, update: impl FnOnce(&mut InstalledPwa)) -> Result<(), LuminaError> {
+    {
+        let mut data = state.data.lock().unwrap();
+        let pwa = data.installed_pwas.iter_mut().find(|pwa| pwa.id == id).ok_or_else(|| LuminaError::NotFound(format!("App {} not found", id)))?;
+        update(pwa);
+    }
+    state.try_save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn set_pwa_notifications(state: tauri::State<'_, AppDataStore>, id: String, enabled: bool) -> Result<(), LuminaError> {
+    update_installed_pwa(&state, &id, |pwa| pwa.notifications = enabled)
+}
+
+/// Takes effect the next time the app's window opens.
+#[tauri::command]
+pub(crate) fn set_pwa_service_workers(state: tauri::State<'_, AppDataStore>, id: String, enabled: bool) -> Result<(), LuminaError> {
+    update_installed_pwa(&state, &id, |pwa| pwa.service_workers = enabled)
+}
+
+#[cfg(target_os = "windows")]
+fn desktop_shortcut_path(name: &str) -> std::path::PathBuf {
+    // Sanitize filename
+    let safe_name: String = name.chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' { c } else { '_' })
+        .collect();
+
+    let desktop = std::env::var("USERPROFILE").unwrap_or(".".to_string()) + "\\Desktop";
+    std::path::Path::new(&desktop).join(format!("{}.lnk", safe_name))
+}
+
+/// Drops the shortcut made for an app's old name before one is made under the new name.
+fn remove_desktop_shortcut(_name: &str) {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = std::fs::remove_file(desktop_shortcut_path(_name));
+    }
+}
+
+fn create_desktop_shortcut(_name: &str, _url: &str, _icon_path: Option<std::path::PathBuf>) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        let path = desktop_shortcut_path(_name);
+        let exe = std::env::current_exe()?;
+        let exe_path = exe.to_str().unwrap();
+        
+        // Escape quotes for PowerShell
+        let safe_url = _url.replace("'", "''");
+        
+        let mut script = format!(
+            "$WshShell = New-Object -comObject WScript.Shell; $Shortcut = $WshShell.CreateShortcut('{}'); $Shortcut.TargetPath = '{}'; $Shortcut.Arguments = '--pwa-url=\"{}\"';",
+            path.to_str().unwrap(),
+            exe_path,
+            safe_url
+        );
+        
+        if let Some(icon) = _icon_path {
+            if let Some(icon_str) = icon.to_str() {
+                script.push_str(&format!(" $Shortcut.IconLocation = '{}';", icon_str));
+            }
+        }
+        
+        script.push_str(" $Shortcut.Save()");
+        
+        std::process::Command::new("powershell")
+            .arg("-NoProfile")
+            .arg("-Command")
+            .arg(script)
+            .output()?;
+    }
+    Ok(())
+}
+
+/// Finds manifest-only web apps (no `beforeinstallprompt`) once a tab has loaded. The manifest is
+/// fetched by the page first, from Lumina's isolated world so it goes with the page's cookies, and
+/// by Rust when the page can't get it (CORS, CSP).
+pub(crate) async fn detect_manifest(app: AppHandle, webview: tauri::Webview) {
+    const FIND_MANIFEST: &str = r#"
+        const link = document.querySelector("link[rel='manifest']");
+        if (!link || !link.href) return null;
+        try {
+            const response = await fetch(link.href);
+            if (response.ok) return { url: link.href, manifest: await response.json() };
+        } catch (e) {}
+        return { url: link.href, manifest: null };
+    "#;
+
+    let label = webview.label().to_string();
+    let Ok(found) = crate::page_script::run(&webview, FIND_MANIFEST, crate::page_script::World::Isolated).await else { return };
+    let Some(url) = found["url"].as_str().map(str::to_string) else { return };
+    let manifest = match found.get("manifest").filter(|manifest| manifest.is_object()) {
+        Some(manifest) => manifest.clone(),
+        None => match fetch_manifest(&app, &url).await {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                println!("Failed to fetch PWA manifest for {}: {}", label, e);
+                return;
+            }
+        },
+    };
+    let display = manifest.get("display").and_then(|v| v.as_str()).unwrap_or_default();
+    if !matches!(display, "standalone" | "minimal-ui" | "fullscreen") {
+        return;
+    }
+
+    let state = app.state::<PwaState>();
+    let final_icon_url = best_manifest_icon(&manifest, &url);
+    if let Some(u) = &final_icon_url {
+        state.icons.lock().unwrap().insert(label.clone(), u.clone());
+    }
+    let mut info = parse_pwa_handlers(&manifest, &url);
+    info.theme_color = manifest_color(&manifest, "theme_color");
+    info.background_color = manifest_color(&manifest, "background_color");
+    state.manifests.lock().unwrap().insert(label.clone(), info);
+
+    events::emit(&app, AppEvent::PwaCanInstall(TabPwaPayload { label, icon_url: final_icon_url }));
+}
+
+async fn fetch_manifest(app: &AppHandle, url: &str) -> Result<serde_json::Value, LuminaError> {
+    let client = http::client(app, http::Purpose::Manifest)?;
+    let text = http::get_with_retry(&client, url).await?.text().await?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_colors_parse() {
+        assert_eq!(parse_css_color("#fff"), Some((255, 255, 255)));
+        assert_eq!(parse_css_color("#1A2b3C"), Some((0x1a, 0x2b, 0x3c)));
+        assert_eq!(parse_css_color("#1a2b3c80"), Some((0x1a, 0x2b, 0x3c)));
+        assert_eq!(parse_css_color("rgb(10, 20, 30)"), Some((10, 20, 30)));
+        assert_eq!(parse_css_color("rgba(10 20 300 / 0.5)"), Some((10, 20, 255)));
+        assert_eq!(parse_css_color("#12345"), None);
+        assert_eq!(parse_css_color("red"), None);
+    }
+
+    #[test]
+    fn dark_colors_get_light_text() {
+        assert!(is_dark_color((0, 0, 0)));
+        assert!(is_dark_color((0x1a, 0x23, 0x7e)));
+        assert!(!is_dark_color((255, 255, 255)));
+        assert!(!is_dark_color((0xff, 0xeb, 0x3b)));
+    }
+}
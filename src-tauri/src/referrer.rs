@@ -0,0 +1,121 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager, Url};
+
+use crate::adblock::normalize_site_domain;
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::history_manager::HistoryManager;
+use crate::to_script_json;
+
+/// "full" leaves the Referer alone, "origin" trims it to scheme and host, "none" drops it
+pub(crate) const REFERRER_POLICIES: [&str; 3] = ["full", "origin", "none"];
+
+#[derive(Clone, Default, Serialize)]
+struct Policies {
+    default: String,
+    /// Domain -> policy; a domain also covers its subdomains
+    sites: HashMap<String, String>,
+}
+
+impl Policies {
+    /// The longest domain the host falls under decides, then the setting.
+    fn for_host(&self, host: &str) -> &str {
+        let host = host.trim_start_matches("www.");
+        self.sites
+            .iter()
+            .filter(|(domain, _)| host == domain.as_str() || host.ends_with(&format!(".{}", domain)))
+            .max_by_key(|(domain, _)| domain.len())
+            .map_or(self.default.as_str(), |(_, policy)| policy.as_str())
+    }
+}
+
+/// Loaded at startup and after every change, since the request hook can't wait on the database
+static POLICIES: OnceLock<Mutex<Policies>> = OnceLock::new();
+
+fn policies() -> &'static Mutex<Policies> {
+    POLICIES.get_or_init(|| Mutex::new(Policies::default()))
+}
+
+pub(crate) fn load(app: &AppHandle) {
+    let default = app.state::<AppDataStore>().data.lock().unwrap().settings.referrer_policy.clone();
+    let sites = app.state::<HistoryManager>().get_referrer_policies().unwrap_or_default().into_iter().collect();
+    *policies().lock().unwrap() = Policies { default, sites };
+}
+
+/// The Referer to send in place of `referer`, going by the policy of the site it names; None
+/// means send none.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn trim_referer(referer: &str) -> Option<String> {
+    let Ok(url) = Url::parse(referer) else { return Some(referer.to_string()) };
+    let Some(host) = url.host_str() else { return Some(referer.to_string()) };
+    match policies().lock().unwrap().for_host(host) {
+        "none" => None,
+        "origin" => Some(format!("{}/", url.origin().ascii_serialization())),
+        _ => Some(referer.to_string()),
+    }
+}
+
+/// Adds a referrer meta tag at document start, so the page's own requests follow the policy
+/// too. "origin" maps to strict-origin, which also sends nothing from https to http.
+pub(crate) fn get_referrer_script() -> String {
+    let config = to_script_json(&*policies().lock().unwrap());
+    format!(r#"
+    (function() {{
+        if (window.self !== window.top) return;
+        const config = {};
+        const host = location.hostname.replace(/^www\./, '');
+        let policy = config.default;
+        let matched = -1;
+        for (const [domain, sitePolicy] of Object.entries(config.sites)) {{
+            if ((host === domain || host.endsWith('.' + domain)) && domain.length > matched) {{
+                policy = sitePolicy;
+                matched = domain.length;
+            }}
+        }}
+        const content = {{ origin: 'strict-origin', none: 'no-referrer' }}[policy];
+        if (!content) return;
+        const add = () => {{
+            const meta = document.createElement('meta');
+            meta.name = 'referrer';
+            meta.content = content;
+            (document.head || document.documentElement).prepend(meta);
+        }};
+        if (document.documentElement) add();
+        else document.addEventListener('readystatechange', add, {{ once: true }});
+    }})();
+    "#, config)
+}
+
+fn validated_policy(policy: &str) -> Result<(), LuminaError> {
+    if !REFERRER_POLICIES.contains(&policy) {
+        return Err(LuminaError::InvalidInput(format!("Unknown referrer policy: {}", policy)));
+    }
+    Ok(())
+}
+
+/// Tabs opened afterwards pick up the new meta tag; on Windows requests are trimmed right away.
+#[tauri::command]
+pub(crate) fn set_referrer_policy(app: AppHandle, state: tauri::State<'_, AppDataStore>, policy: String) -> Result<(), LuminaError> {
+    validated_policy(&policy)?;
+    state.data.lock().unwrap().settings.referrer_policy = policy;
+    state.save();
+    load(&app);
+    Ok(())
+}
+
+/// `policy: null` drops the override, so the site follows the setting again.
+#[tauri::command]
+pub(crate) fn set_site_referrer_policy(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, domain: String, policy: Option<String>) -> Result<(), LuminaError> {
+    let domain = normalize_site_domain(&domain);
+    if domain.is_empty() {
+        return Err(LuminaError::InvalidInput("Invalid domain".to_string()));
+    }
+    if let Some(policy) = &policy {
+        validated_policy(policy)?;
+    }
+    history_manager.set_referrer_policy(&domain, policy.as_deref())?;
+    load(&app);
+    Ok(())
+}
@@ -0,0 +1,366 @@
+use std::io::Cursor;
+use image::{imageops, ImageOutputFormat, Rgba, RgbaImage};
+use serde::Deserialize;
+use tauri::{AppHandle, Manager, Webview};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::downloads::unique_download_path;
+use crate::error::LuminaError;
+use crate::events::AppEvent;
+use crate::layout::UiState;
+use crate::page_script::World;
+use crate::{events, ToastPayload};
+
+const ANNOTATION_COLOR: Rgba<u8> = Rgba([239, 68, 68, 255]);
+/// Stroke width and arrowhead length in CSS pixels, scaled to the capture's device pixels
+const ANNOTATION_STROKE: f64 = 3.0;
+const ARROW_HEAD: f64 = 16.0;
+const BLUR_SIGMA: f32 = 12.0;
+
+/// A rectangle in CSS pixels of the tab's viewport
+#[derive(Deserialize, Clone, Copy)]
+pub(crate) struct Region {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AnnotationKind {
+    Arrow,
+    Box,
+    Blur,
+}
+
+/// Drawn from (x1, y1) to (x2, y2), in CSS pixels of the viewport. For boxes and blurs the two points
+/// are opposite corners; arrows point at (x2, y2).
+#[derive(Deserialize, Clone, Copy)]
+pub(crate) struct Annotation {
+    kind: AnnotationKind,
+    x1: f64,
+    y1: f64,
+    x2: f64,
+    y2: f64,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ScreenshotOutput {
+    Clipboard,
+    Save,
+    Cancel,
+}
+
+/// Overlay for picking a region and drawing annotations over the captured page. Run in Lumina's
+/// isolated world; returns the user's choice once they copy, save or cancel.
+const SCREENSHOT_OVERLAY_JS: &str = r#"
+if (document.getElementById('__lumina_screenshot')) return { output: 'cancel' };
+return await new Promise((resolve) => {
+
+    const root = document.createElement('div');
+    root.id = '__lumina_screenshot';
+    root.style.cssText = 'position:fixed;inset:0;z-index:2147483647;cursor:crosshair;font:13px system-ui,sans-serif;';
+    const canvas = document.createElement('canvas');
+    const dpr = window.devicePixelRatio || 1;
+    canvas.width = window.innerWidth * dpr;
+    canvas.height = window.innerHeight * dpr;
+    canvas.style.cssText = 'position:absolute;inset:0;width:100%;height:100%;';
+    const ctx = canvas.getContext('2d');
+    ctx.scale(dpr, dpr);
+
+    const bar = document.createElement('div');
+    bar.style.cssText = 'position:absolute;top:12px;left:50%;transform:translateX(-50%);display:flex;gap:4px;padding:6px;background:#1e1e1e;border:1px solid #333;border-radius:10px;box-shadow:0 8px 24px rgba(0,0,0,.4);cursor:default;';
+    const tools = [['region', 'Region'], ['arrow', 'Arrow'], ['box', 'Box'], ['blur', 'Blur']];
+    const actions = [['clipboard', 'Copy'], ['save', 'Save'], ['cancel', 'Cancel']];
+    let tool = 'region';
+    let region = null;
+    const annotations = [];
+    let drag = null;
+
+    const button = (text, onClick) => {
+        const b = document.createElement('button');
+        b.textContent = text;
+        b.style.cssText = 'border:0;border-radius:6px;padding:6px 10px;background:#2a2a2a;color:#eee;cursor:pointer;font:inherit;';
+        b.addEventListener('click', (e) => { e.stopPropagation(); onClick(); });
+        bar.appendChild(b);
+        return b;
+    };
+    const toolButtons = tools.map(([id, text]) => [id, button(text, () => { tool = id; paintButtons(); })]);
+    const paintButtons = () => toolButtons.forEach(([id, b]) => b.style.background = id === tool ? '#05B8CC' : '#2a2a2a');
+    actions.forEach(([id, text]) => button(text, () => finish(id)));
+    paintButtons();
+
+    const rect = (a) => ({ x: Math.min(a.x1, a.x2), y: Math.min(a.y1, a.y2), width: Math.abs(a.x2 - a.x1), height: Math.abs(a.y2 - a.y1) });
+    const drawAnnotation = (a) => {
+        ctx.strokeStyle = '#ef4444';
+        ctx.fillStyle = '#ef4444';
+        ctx.lineWidth = 3;
+        ctx.setLineDash([]);
+        const r = rect(a);
+        if (a.kind === 'box') {
+            ctx.strokeRect(r.x, r.y, r.width, r.height);
+        } else if (a.kind === 'blur') {
+            ctx.fillStyle = 'rgba(255,255,255,0.45)';
+            ctx.fillRect(r.x, r.y, r.width, r.height);
+        } else {
+            const angle = Math.atan2(a.y2 - a.y1, a.x2 - a.x1);
+            ctx.beginPath();
+            ctx.moveTo(a.x1, a.y1);
+            ctx.lineTo(a.x2, a.y2);
+            for (const side of [-0.5, 0.5]) {
+                ctx.moveTo(a.x2, a.y2);
+                ctx.lineTo(a.x2 - 16 * Math.cos(angle + side), a.y2 - 16 * Math.sin(angle + side));
+            }
+            ctx.stroke();
+        }
+    };
+    const paint = () => {
+        ctx.clearRect(0, 0, window.innerWidth, window.innerHeight);
+        ctx.fillStyle = 'rgba(0,0,0,0.35)';
+        ctx.fillRect(0, 0, window.innerWidth, window.innerHeight);
+        const selection = drag && tool === 'region' ? rect(drag) : region;
+        if (selection) {
+            ctx.clearRect(selection.x, selection.y, selection.width, selection.height);
+            ctx.strokeStyle = '#05B8CC';
+            ctx.lineWidth = 1;
+            ctx.setLineDash([6, 4]);
+            ctx.strokeRect(selection.x, selection.y, selection.width, selection.height);
+        }
+        annotations.forEach(drawAnnotation);
+        if (drag && tool !== 'region') drawAnnotation(drag);
+    };
+
+    canvas.addEventListener('mousedown', (e) => {
+        drag = { kind: tool, x1: e.clientX, y1: e.clientY, x2: e.clientX, y2: e.clientY };
+    });
+    window.addEventListener('mousemove', (e) => {
+        if (!drag) return;
+        drag.x2 = e.clientX;
+        drag.y2 = e.clientY;
+        paint();
+    });
+    window.addEventListener('mouseup', () => {
+        if (!drag) return;
+        const r = rect(drag);
+        if (r.width >= 4 || r.height >= 4) {
+            if (tool === 'region') region = r; else annotations.push(drag);
+        }
+        drag = null;
+        paint();
+    });
+    const onKey = (e) => { if (e.key === 'Escape') finish('cancel'); };
+    window.addEventListener('keydown', onKey, true);
+
+    function finish(output) {
+        window.removeEventListener('keydown', onKey, true);
+        root.remove();
+        resolve({ region, annotations, viewportWidth: window.innerWidth, output });
+    }
+
+    root.appendChild(canvas);
+    root.appendChild(bar);
+    document.documentElement.appendChild(root);
+    paint();
+});
+"#;
+
+/// PNG of what a webview shows: the visible viewport, or with `full_page` the whole document.
+#[cfg(target_os = "linux")]
+pub(crate) async fn capture_png(webview: &Webview, full_page: bool) -> Result<Vec<u8>, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    webview.with_webview(move |webview| {
+        use webkit2gtk::{SnapshotOptions, SnapshotRegion, WebViewExt};
+        let region = if full_page { SnapshotRegion::FullDocument } else { SnapshotRegion::Visible };
+        webview.inner().snapshot(region, SnapshotOptions::NONE, None::<&webkit2gtk::gio::Cancellable>, move |result| {
+            let png = result.map_err(|e| e.to_string()).and_then(|surface| {
+                let mut png = Vec::new();
+                surface.write_to_png(&mut png).map_err(|e| e.to_string())?;
+                Ok(png)
+            });
+            let _ = tx.send(png);
+        });
+    }).map_err(|e| e.to_string())?;
+    rx.await.map_err(|_| "The webview dropped the screenshot request".to_string())?
+}
+
+#[cfg(windows)]
+pub(crate) async fn capture_png(webview: &Webview, full_page: bool) -> Result<Vec<u8>, String> {
+    use base64::Engine;
+
+    // Page.captureScreenshot answers with { "data": "<base64 PNG>" }
+    let params = format!(r#"{{"format":"png","captureBeyondViewport":{}}}"#, full_page);
+    let value = call_devtools(webview, "Page.captureScreenshot", &params).await?;
+    let data = value["data"].as_str().ok_or("Screenshot response had no image data")?;
+    base64::engine::general_purpose::STANDARD.decode(data).map_err(|e| e.to_string())
+}
+
+/// Runs a Chrome DevTools Protocol method on the tab's WebView2 and returns its JSON result.
+#[cfg(windows)]
+pub(crate) async fn call_devtools(webview: &Webview, method: &str, params: &str) -> Result<serde_json::Value, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let method = method.to_string();
+    let params = params.to_string();
+    webview.with_webview(move |webview| {
+        use webview2_com::CallDevToolsProtocolMethodCompletedHandler;
+        use windows_core::HSTRING;
+
+        let handler = CallDevToolsProtocolMethodCompletedHandler::create(Box::new(move |result, json| {
+            let value = result
+                .map_err(|e| e.to_string())
+                .and_then(|()| serde_json::from_str::<serde_json::Value>(&json).map_err(|e| e.to_string()));
+            let _ = tx.send(value);
+            Ok(())
+        }));
+        unsafe {
+            if let Ok(core) = webview.controller().CoreWebView2() {
+                let _ = core.CallDevToolsProtocolMethod(&HSTRING::from(method), &HSTRING::from(params), &handler);
+            }
+        }
+    }).map_err(|e| e.to_string())?;
+    rx.await.map_err(|_| "The webview dropped the DevTools request".to_string())?
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) async fn capture_png(_webview: &Webview, _full_page: bool) -> Result<Vec<u8>, String> {
+    Err("Screenshots are not supported on this platform yet".to_string())
+}
+
+/// What the overlay reports when the user is done
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OverlayChoice {
+    #[serde(default)]
+    region: Option<Region>,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+    #[serde(default)]
+    viewport_width: f64,
+    output: ScreenshotOutput,
+}
+
+/// Captures the active tab and lets the user pick a region and annotate it in an overlay. The
+/// overlay's answer comes back from the engine, so the page can neither see nor forge it.
+#[tauri::command]
+pub(crate) async fn start_screenshot(app: AppHandle) -> Result<(), LuminaError> {
+    let label = app.state::<UiState>().current_tab()
+        .ok_or_else(|| LuminaError::NotFound("No tab to capture".to_string()))?;
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("Tab {} not found", label)))?;
+
+    // Taken before the overlay goes in, so the overlay never ends up in the picture
+    let png = capture_png(&webview, false).await.map_err(LuminaError::Webview)?;
+    let _ = webview.set_focus();
+
+    // The user may take a while; the command returns now and the result is handled when it comes
+    tauri::async_runtime::spawn(async move {
+        let result = crate::page_script::run(&webview, SCREENSHOT_OVERLAY_JS, World::Isolated)
+            .await
+            .map_err(LuminaError::Webview)
+            .and_then(|value| serde_json::from_value::<OverlayChoice>(value).map_err(|e| LuminaError::Other(e.to_string())))
+            .and_then(|choice| finish_screenshot(&app, &png, choice));
+        if let Err(e) = result {
+            crate::error::report(&app, "Ekran görüntüsü alınamadı", &e);
+        }
+    });
+    Ok(())
+}
+
+fn finish_screenshot(app: &AppHandle, png: &[u8], choice: OverlayChoice) -> Result<(), LuminaError> {
+    let OverlayChoice { region, annotations, viewport_width, output } = choice;
+    if output == ScreenshotOutput::Cancel {
+        return Ok(());
+    }
+
+    let mut image = image::load_from_memory(png)
+        .map_err(|e| LuminaError::Other(format!("Unreadable capture: {}", e)))?
+        .to_rgba8();
+    // The capture is in device pixels, the overlay reports CSS pixels
+    let scale = if viewport_width > 0.0 { image.width() as f64 / viewport_width } else { 1.0 };
+
+    for annotation in &annotations {
+        annotate(&mut image, annotation, scale);
+    }
+    if let Some((x, y, width, height)) = region.and_then(|r| pixel_rect(&image, r.x, r.y, r.x + r.width, r.y + r.height, scale)) {
+        image = imageops::crop_imm(&image, x, y, width, height).to_image();
+    }
+
+    let message = match output {
+        ScreenshotOutput::Clipboard => {
+            let clip = tauri::image::Image::new(image.as_raw(), image.width(), image.height());
+            app.clipboard().write_image(&clip).map_err(|e| LuminaError::Other(e.to_string()))?;
+            "Ekran görüntüsü panoya kopyalandı".to_string()
+        }
+        _ => {
+            let dir = app.path().download_dir().unwrap_or(std::path::PathBuf::from("downloads"));
+            std::fs::create_dir_all(&dir)?;
+            let path = unique_download_path(&dir, &format!("lumina-screenshot-{}.png", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+            let mut png = Vec::new();
+            image
+                .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+                .map_err(|e| LuminaError::Other(e.to_string()))?;
+            std::fs::write(&path, png)?;
+            format!("Ekran görüntüsü kaydedildi: {}", path.display())
+        }
+    };
+    events::emit(app, AppEvent::Toast(ToastPayload { message, level: "success".to_string() }));
+    Ok(())
+}
+
+/// Corners in CSS pixels to a clamped (x, y, width, height) in image pixels; `None` if nothing is left.
+fn pixel_rect(image: &RgbaImage, x1: f64, y1: f64, x2: f64, y2: f64, scale: f64) -> Option<(u32, u32, u32, u32)> {
+    let clamp_x = |v: f64| (v * scale).round().clamp(0.0, image.width() as f64) as u32;
+    let clamp_y = |v: f64| (v * scale).round().clamp(0.0, image.height() as f64) as u32;
+    let (left, right) = (clamp_x(x1.min(x2)), clamp_x(x1.max(x2)));
+    let (top, bottom) = (clamp_y(y1.min(y2)), clamp_y(y1.max(y2)));
+    (right > left && bottom > top).then_some((left, top, right - left, bottom - top))
+}
+
+fn annotate(image: &mut RgbaImage, annotation: &Annotation, scale: f64) {
+    let Annotation { kind, x1, y1, x2, y2 } = *annotation;
+    let stroke = (ANNOTATION_STROKE * scale).max(1.0);
+    match kind {
+        AnnotationKind::Box => {
+            let (left, right) = (x1.min(x2) * scale, x1.max(x2) * scale);
+            let (top, bottom) = (y1.min(y2) * scale, y1.max(y2) * scale);
+            draw_line(image, (left, top), (right, top), stroke);
+            draw_line(image, (right, top), (right, bottom), stroke);
+            draw_line(image, (right, bottom), (left, bottom), stroke);
+            draw_line(image, (left, bottom), (left, top), stroke);
+        }
+        AnnotationKind::Arrow => {
+            let (from, to) = ((x1 * scale, y1 * scale), (x2 * scale, y2 * scale));
+            draw_line(image, from, to, stroke);
+            let angle = (to.1 - from.1).atan2(to.0 - from.0);
+            let head = ARROW_HEAD * scale;
+            for side in [-0.5f64, 0.5] {
+                let tip = (to.0 - head * (angle + side).cos(), to.1 - head * (angle + side).sin());
+                draw_line(image, to, tip, stroke);
+            }
+        }
+        AnnotationKind::Blur => {
+            if let Some((x, y, width, height)) = pixel_rect(image, x1, y1, x2, y2, scale) {
+                let area = imageops::crop_imm(image, x, y, width, height).to_image();
+                let blurred = imageops::blur(&area, BLUR_SIGMA * scale as f32);
+                imageops::replace(image, &blurred, x as i64, y as i64);
+            }
+        }
+    }
+}
+
+/// Stamps a square brush of side `stroke` along the segment
+fn draw_line(image: &mut RgbaImage, from: (f64, f64), to: (f64, f64), stroke: f64) {
+    let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0) as u32;
+    let half = stroke / 2.0;
+    for step in 0..=steps {
+        let t = step as f64 / steps as f64;
+        let (cx, cy) = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+        let x_range = ((cx - half).floor().max(0.0) as u32)..((cx + half).ceil().min(image.width() as f64) as u32);
+        for x in x_range {
+            let y_range = ((cy - half).floor().max(0.0) as u32)..((cy + half).ceil().min(image.height() as f64) as u32);
+            for y in y_range {
+                image.put_pixel(x, y, ANNOTATION_COLOR);
+            }
+        }
+    }
+}
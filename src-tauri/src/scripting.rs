@@ -0,0 +1,673 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, Arc, OnceLock};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use serde::{Serialize, Deserialize};
+use mlua::Lua;
+
+use crate::history_manager::HistoryManager;
+use crate::events::AppEvent;
+use crate::events;
+use crate::adblock::{ADBLOCK_ENABLED, is_adblock_disabled_for_host, normalize_site_domain, set_adblock_enabled, toggle_adblock_site};
+use crate::error::LuminaError;
+use crate::tab_registry::TabRegistry;
+
+static SIDECAR_STATUS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+pub(crate) fn set_sidecar_status(name: &str, status: impl Into<String>) {
+    SIDECAR_STATUS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap()
+        .insert(name.to_string(), status.into());
+}
+
+pub(crate) fn sidecar_status(name: &str) -> String {
+    SIDECAR_STATUS
+        .get()
+        .and_then(|m| m.lock().unwrap().get(name).cloned())
+        .unwrap_or_else(|| "not started".to_string())
+}
+
+// Lua runs on a dedicated worker thread so long-running scripts never block commands.
+// Every job is bounded by a wall-clock timeout and an instruction budget enforced via a VM hook.
+const LUA_JOB_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+const LUA_MAX_INSTRUCTIONS: u64 = 50_000_000;
+const LUA_HOOK_INTERVAL: u32 = 10_000;
+
+struct LuaJob {
+    code: String,
+    chunk_name: String,
+    package_id: String,
+    exec: bool, // true: run as statements, false: evaluate and return the result
+    /// Set by `LuaState::cancel`, whether the job is still queued or already running
+    cancel: Arc<std::sync::atomic::AtomicBool>,
+    response_tx: tokio::sync::oneshot::Sender<Result<String, String>>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct LuaScriptStatus {
+    pub path: String,
+    pub package_id: String,
+    pub loaded_at: i64,
+    pub error: Option<String>,
+}
+
+pub(crate) struct LuaState {
+    tx: tokio::sync::mpsc::Sender<LuaJob>,
+    /// Cancel tokens of submitted jobs; they die once the worker is done with the job
+    jobs: Mutex<Vec<std::sync::Weak<std::sync::atomic::AtomicBool>>>,
+    scripts: Mutex<HashMap<String, LuaScriptStatus>>,
+    watcher: Mutex<Option<notify::RecommendedWatcher>>,
+}
+
+impl LuaState {
+    pub(crate) fn spawn(app: AppHandle) -> Self {
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<LuaJob>(32);
+
+        let spawned = std::thread::Builder::new()
+            .name("lumina-lua".to_string())
+            .spawn(move || {
+                let lua = create_lua_runtime(app);
+                while let Some(job) = rx.blocking_recv() {
+                    if job.cancel.load(std::sync::atomic::Ordering::Relaxed) {
+                        let _ = job.response_tx.send(Err("Lua execution cancelled".to_string()));
+                        continue;
+                    }
+                    install_lua_limits(&lua, job.cancel.clone());
+
+                    let result = with_lua_package(&lua, &job.package_id, |lua| {
+                        let chunk = lua.load(&job.code).set_name(job.chunk_name.as_str());
+                        if job.exec {
+                            chunk.exec().map(|_| String::new())
+                        } else {
+                            // Top-level strings are returned raw, everything else is pretty-printed
+                            chunk.eval::<mlua::MultiValue>().map(|values| {
+                                values.iter()
+                                    .map(|v| match v {
+                                        mlua::Value::String(s) => s.to_string_lossy().into_owned(),
+                                        other => format_lua_value(other, 0),
+                                    })
+                                    .collect::<Vec<_>>()
+                                    .join("\t")
+                            })
+                        }
+                    }).map_err(|e| e.to_string());
+
+                    lua.remove_hook();
+                    let _ = job.response_tx.send(result);
+                }
+                println!("Lumina Lua worker stopped.");
+            });
+        if let Err(e) = spawned {
+            eprintln!("Failed to spawn Lua worker thread: {}", e);
+        }
+
+        Self {
+            tx,
+            jobs: Mutex::new(Vec::new()),
+            scripts: Mutex::new(HashMap::new()),
+            watcher: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn submit(&self, code: String, chunk_name: &str, package_id: &str, exec: bool) -> Result<tokio::sync::oneshot::Receiver<Result<String, String>>, String> {
+        let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+        let cancel = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.retain(|job| job.strong_count() > 0);
+            jobs.push(Arc::downgrade(&cancel));
+        }
+        self.tx.try_send(LuaJob {
+            code,
+            chunk_name: chunk_name.to_string(),
+            package_id: package_id.to_string(),
+            exec,
+            cancel,
+            response_tx,
+        }).map_err(|e| format!("Lua worker unavailable: {}", e))?;
+        Ok(response_rx)
+    }
+
+    pub(crate) async fn run(&self, code: String, chunk_name: &str, package_id: &str, exec: bool) -> Result<String, String> {
+        let response_rx = self.submit(code, chunk_name, package_id, exec)?;
+        response_rx.await.map_err(|_| "Lua worker stopped".to_string())?
+    }
+
+    /// Stops the running job and drops the queued ones; jobs submitted afterwards run as usual.
+    pub(crate) fn cancel(&self) {
+        for job in self.jobs.lock().unwrap().drain(..).filter_map(|job| job.upgrade()) {
+            job.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_script(&self, path: &str, package_id: &str, result: &Result<String, String>) {
+        self.scripts.lock().unwrap().insert(path.to_string(), LuaScriptStatus {
+            path: path.to_string(),
+            package_id: package_id.to_string(),
+            loaded_at: chrono::Utc::now().timestamp(),
+            error: result.as_ref().err().cloned(),
+        });
+    }
+
+    pub(crate) fn forget_script(&self, path: &str) {
+        self.scripts.lock().unwrap().remove(path);
+    }
+
+    pub(crate) fn get_scripts(&self) -> Vec<LuaScriptStatus> {
+        let mut scripts: Vec<LuaScriptStatus> = self.scripts.lock().unwrap().values().cloned().collect();
+        scripts.sort_by(|a, b| a.path.cmp(&b.path));
+        scripts
+    }
+}
+
+// User scripts live in app_data/scripts; every *.lua there is loaded as its own package (file stem).
+fn get_scripts_dir(app: &AppHandle) -> PathBuf {
+    let dir = app.path().app_data_dir().unwrap_or_default().join("scripts");
+    if !dir.exists() {
+        let _ = std::fs::create_dir_all(&dir);
+    }
+    dir
+}
+
+fn is_lua_script(path: &std::path::Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("lua"))
+}
+
+fn collect_script_paths(app: &AppHandle) -> Vec<PathBuf> {
+    let scripts_dir = get_scripts_dir(app);
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&scripts_dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file() && is_lua_script(p)).collect())
+        .unwrap_or_default();
+    paths.sort();
+
+    // Dev fallback: the repository's scripts/init.lua, unless the profile ships its own
+    let dev_init = PathBuf::from("scripts/init.lua");
+    if dev_init.exists() && !scripts_dir.join("init.lua").exists() {
+        paths.insert(0, dev_init);
+    }
+    paths
+}
+
+async fn load_script(app: &AppHandle, path: &std::path::Path) -> Result<String, String> {
+    let lua_state = app.state::<LuaState>();
+    let chunk_name = path.to_string_lossy().to_string();
+    let package_id = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+
+    // A script file named after the console (user.lua) would otherwise run with its trust
+    let result = match std::fs::read_to_string(path) {
+        _ if package_id.is_empty() || package_id.eq_ignore_ascii_case(CONSOLE_PACKAGE_ID) => {
+            Err(format!("'{}' is reserved for the console; rename the script", CONSOLE_PACKAGE_ID))
+        }
+        Ok(script) => {
+            println!("Executing Lua script: {:?}", path);
+            lua_state.run(script, &chunk_name, &package_id, true).await
+        }
+        Err(e) => Err(format!("Failed to read script: {}", e)),
+    };
+
+    if let Err(e) = &result {
+        eprintln!("Error executing Lua script {:?}: {}", path, e);
+    }
+    lua_state.record_script(&chunk_name, &package_id, &result);
+    result
+}
+
+pub(crate) async fn load_all_scripts(app: &AppHandle) -> Vec<LuaScriptStatus> {
+    for path in collect_script_paths(app) {
+        let _ = load_script(app, &path).await;
+    }
+    app.state::<LuaState>().get_scripts()
+}
+
+// Watches app_data/scripts and re-runs scripts whose files change (debounced).
+pub(crate) fn start_script_watcher(app: &AppHandle) {
+    use notify::Watcher;
+
+    let scripts_dir = get_scripts_dir(app);
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<PathBuf>();
+
+    let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            if matches!(event.kind, notify::EventKind::Create(_) | notify::EventKind::Modify(_) | notify::EventKind::Remove(_)) {
+                for path in event.paths {
+                    if is_lua_script(&path) {
+                        let _ = tx.send(path);
+                    }
+                }
+            }
+        }
+    });
+
+    let mut watcher = match watcher {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("Failed to create script watcher: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(&scripts_dir, notify::RecursiveMode::NonRecursive) {
+        eprintln!("Failed to watch scripts dir {:?}: {}", scripts_dir, e);
+        return;
+    }
+    *app.state::<LuaState>().watcher.lock().unwrap() = Some(watcher);
+
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(first) = rx.recv().await {
+            // Editors emit bursts of events per save; collect them before reloading
+            let mut changed = std::collections::BTreeSet::from([first]);
+            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+            while let Ok(path) = rx.try_recv() {
+                changed.insert(path);
+            }
+
+            for path in changed {
+                if path.exists() {
+                    println!("Lumina Scripts: Reloading {:?}", path);
+                    let _ = load_script(&app_handle, &path).await;
+                } else {
+                    app_handle.state::<LuaState>().forget_script(&path.to_string_lossy());
+                }
+            }
+            events::emit(&app_handle, AppEvent::ScriptsReloaded(app_handle.state::<LuaState>().get_scripts()));
+        }
+    });
+}
+
+const LUA_PRETTY_MAX_DEPTH: usize = 4;
+
+// Renders a Lua value for display (console output), recursing into tables.
+fn format_lua_value(value: &mlua::Value, indent: usize) -> String {
+    match value {
+        mlua::Value::Nil => "nil".to_string(),
+        mlua::Value::Boolean(b) => b.to_string(),
+        mlua::Value::Integer(i) => i.to_string(),
+        mlua::Value::Number(n) => n.to_string(),
+        mlua::Value::String(s) => format!("{:?}", s.to_string_lossy()),
+        mlua::Value::Table(table) => {
+            if indent >= LUA_PRETTY_MAX_DEPTH {
+                return "{...}".to_string();
+            }
+            let pad = "  ".repeat(indent + 1);
+            let mut lines = Vec::new();
+            for (key, val) in table.clone().pairs::<mlua::Value, mlua::Value>().flatten() {
+                let key_str = match &key {
+                    mlua::Value::String(s) => s.to_string_lossy().into_owned(),
+                    other => format!("[{}]", format_lua_value(other, indent + 1)),
+                };
+                lines.push(format!("{}{} = {}", pad, key_str, format_lua_value(&val, indent + 1)));
+            }
+            if lines.is_empty() {
+                "{}".to_string()
+            } else {
+                format!("{{\n{}\n{}}}", lines.join(",\n"), "  ".repeat(indent))
+            }
+        }
+        mlua::Value::Function(_) => "<function>".to_string(),
+        other => format!("<{}>", other.type_name()),
+    }
+}
+
+fn install_lua_limits(lua: &Lua, cancel: Arc<std::sync::atomic::AtomicBool>) {
+    let deadline = std::time::Instant::now() + LUA_JOB_TIMEOUT;
+    let executed = std::sync::atomic::AtomicU64::new(0);
+
+    lua.set_hook(mlua::HookTriggers::new().every_nth_instruction(LUA_HOOK_INTERVAL), move |_lua, _debug| {
+        if cancel.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err(mlua::Error::RuntimeError("Lua execution cancelled".to_string()));
+        }
+        if std::time::Instant::now() >= deadline {
+            return Err(mlua::Error::RuntimeError(format!("Lua execution timed out after {}s", LUA_JOB_TIMEOUT.as_secs())));
+        }
+        let count = executed.fetch_add(LUA_HOOK_INTERVAL as u64, std::sync::atomic::Ordering::Relaxed) + LUA_HOOK_INTERVAL as u64;
+        if count >= LUA_MAX_INSTRUCTIONS {
+            return Err(mlua::Error::RuntimeError(format!("Lua instruction limit ({}) exceeded", LUA_MAX_INSTRUCTIONS)));
+        }
+        Ok(())
+    });
+}
+
+// Storage quotas for lumina.storage (per package)
+const LUA_STORAGE_QUOTA_BYTES: i64 = 1024 * 1024;
+const LUA_STORAGE_MAX_VALUE_BYTES: usize = 64 * 1024;
+
+/// Package id that lumina.storage calls are namespaced under while a script runs.
+struct LuaPackageScope(String);
+
+/// Package the interactive console runs as. Trusted, so no script file may take the id.
+const CONSOLE_PACKAGE_ID: &str = "user";
+
+/// Outside of any scope this is an id with no permissions, never the console's.
+fn lua_package_id(lua: &Lua) -> String {
+    lua.app_data_ref::<LuaPackageScope>()
+        .map(|scope| scope.0.clone())
+        .unwrap_or_default()
+}
+
+/// Runs `f` with lumina.storage scoped to `package_id`, restoring the previous scope afterwards.
+fn with_lua_package<R>(lua: &Lua, package_id: &str, f: impl FnOnce(&Lua) -> R) -> R {
+    let previous = lua.set_app_data(LuaPackageScope(package_id.to_string()));
+    let result = f(lua);
+    match previous {
+        Some(scope) => { lua.set_app_data(scope); }
+        None => { lua.remove_app_data::<LuaPackageScope>(); }
+    }
+    result
+}
+
+// 1. Safe Lua Execution (Real Lua 5.4 Runtime)
+// This creates a sandboxed Lua environment that can interact with the browser safely.
+fn create_lua_runtime(app: AppHandle) -> Lua {
+    // Create a Lua state with safe standard libraries only (Sandbox)
+    // We exclude IO, OS, and Package libraries to prevent system access
+    // Note: Lua 5.4 has built-in bitwise operators, so BIT library is not needed/available.
+    let libs = mlua::StdLib::MATH | mlua::StdLib::STRING | mlua::StdLib::TABLE;
+    let lua = Lua::new_with(libs, mlua::LuaOptions::default()).expect("Failed to create Lua state");
+    
+    // Inject Custom Lumina API
+    let _ = lua.load("
+        -- Custom Lumina API
+        lumina = {
+            version = '0.3.6',
+            platform = 'windows'
+        }
+    ").exec();
+
+    if let Err(e) = register_lua_storage_api(&lua, app.clone()) {
+        eprintln!("Failed to register lumina.storage: {}", e);
+    }
+    if let Err(e) = register_lua_tabs_api(&lua, app.clone()) {
+        eprintln!("Failed to register lumina.tabs: {}", e);
+    }
+    if let Err(e) = register_lua_adblock_api(&lua, app) {
+        eprintln!("Failed to register lumina.adblock: {}", e);
+    }
+
+    lua
+}
+
+fn lua_history_manager(app: &AppHandle) -> mlua::Result<tauri::State<'_, HistoryManager>> {
+    app.try_state::<HistoryManager>()
+        .ok_or_else(|| mlua::Error::RuntimeError("lumina.storage: database not initialized".to_string()))
+}
+
+// Packages must be granted a permission before using privileged APIs.
+// The interactive console runs as "user" and is always trusted.
+fn require_lua_permission(app: &AppHandle, package_id: &str, permission: &str) -> mlua::Result<()> {
+    if package_id == CONSOLE_PACKAGE_ID {
+        return Ok(());
+    }
+    let granted = lua_history_manager(app)?.has_lua_permission(package_id, permission).unwrap_or(false);
+    if granted {
+        Ok(())
+    } else {
+        Err(mlua::Error::RuntimeError(format!(
+            "permission '{}' not granted for package '{}'", permission, package_id
+        )))
+    }
+}
+
+const LUA_TAB_EVAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Runs `js` (an expression or statements; promises are awaited) in a tab's page and returns its result
+/// as JSON. Blocks until the engine hands the result back, so call it from a worker thread.
+pub(crate) fn eval_in_webview(app: &AppHandle, label: &str, js: &str, timeout: std::time::Duration) -> Result<Option<String>, String> {
+    eval_in(page_tab(app, label)?, js, timeout)
+}
+
+/// The webview of an open browser tab showing a web page. Browser windows and PWA windows aren't in
+/// the tab registry, and tabs showing Lumina's own pages are refused: their scripts reach every command.
+fn page_tab(app: &AppHandle, label: &str) -> Result<tauri::Webview, String> {
+    let open = app.state::<TabRegistry>().snapshot(None).tabs.iter().any(|tab| tab.label == label);
+    let webview = open.then(|| app.get_webview(label)).flatten().ok_or_else(|| format!("tab '{}' not found", label))?;
+    if crate::ipc_guard::is_privileged(&webview) {
+        return Err(format!("tab '{}' is showing a Lumina page", label));
+    }
+    Ok(webview)
+}
+
+/// `eval_in_webview` for a webview Lumina opened itself, such as the command line's headless window.
+pub(crate) fn eval_in(webview: tauri::Webview, js: &str, timeout: std::time::Duration) -> Result<Option<String>, String> {
+    let label = webview.label().to_string();
+    let body = format!("return await (0, eval)({});", serde_json::to_string(js).map_err(|e| e.to_string())?);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    tauri::async_runtime::spawn(async move {
+        let _ = tx.send(crate::page_script::run(&webview, &body, crate::page_script::World::Page).await);
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result.map(|value| Some(value.to_string())),
+        Err(_) => Err(format!("no response from tab '{}' within {}s", label, timeout.as_secs())),
+    }
+}
+
+// lumina.tabs.eval(label, js): runs JS in a tab and returns its (JSON-serializable) result
+fn register_lua_tabs_api(lua: &Lua, app: AppHandle) -> mlua::Result<()> {
+    use mlua::LuaSerdeExt;
+
+    let tabs = lua.create_table()?;
+
+    tabs.set("eval", lua.create_function(move |lua, (label, js): (String, String)| {
+        let package_id = lua_package_id(lua);
+        require_lua_permission(&app, &package_id, "tabs.eval")?;
+
+        match eval_in_webview(&app, &label, &js, LUA_TAB_EVAL_TIMEOUT) {
+            Ok(Some(json)) => {
+                let value: serde_json::Value = serde_json::from_str(&json).unwrap_or(serde_json::Value::Null);
+                lua.to_value(&value)
+            }
+            Ok(None) => Ok(mlua::Value::Nil),
+            Err(e) => Err(mlua::Error::RuntimeError(format!("lumina.tabs.eval: {}", e))),
+        }
+    })?)?;
+
+    let lumina: mlua::Table = lua.globals().get("lumina")?;
+    lumina.set("tabs", tabs)
+}
+
+// lumina.adblock: read and flip the shield (mutations need the "adblock" permission)
+fn register_lua_adblock_api(lua: &Lua, app: AppHandle) -> mlua::Result<()> {
+    let adblock = lua.create_table()?;
+
+    adblock.set("is_enabled", lua.create_function(|_, ()| {
+        Ok(ADBLOCK_ENABLED.load(std::sync::atomic::Ordering::Relaxed))
+    })?)?;
+
+    adblock.set("is_enabled_for", lua.create_function(|_, domain: String| {
+        Ok(ADBLOCK_ENABLED.load(std::sync::atomic::Ordering::Relaxed) && !is_adblock_disabled_for_host(&normalize_site_domain(&domain)))
+    })?)?;
+
+    let app_toggle = app.clone();
+    adblock.set("set_enabled", lua.create_function(move |lua, enabled: bool| {
+        require_lua_permission(&app_toggle, &lua_package_id(lua), "adblock")?;
+        set_adblock_enabled(&app_toggle, enabled);
+        Ok(())
+    })?)?;
+
+    adblock.set("toggle_site", lua.create_function(move |lua, domain: String| {
+        require_lua_permission(&app, &lua_package_id(lua), "adblock")?;
+        toggle_adblock_site(&app, &domain).map_err(mlua::Error::RuntimeError)
+    })?)?;
+
+    let lumina: mlua::Table = lua.globals().get("lumina")?;
+    lumina.set("adblock", adblock)
+}
+
+// lumina.storage: persistent key-value store backed by SQLite, namespaced per package
+fn register_lua_storage_api(lua: &Lua, app: AppHandle) -> mlua::Result<()> {
+    use mlua::LuaSerdeExt;
+
+    let storage = lua.create_table()?;
+
+    let app_set = app.clone();
+    storage.set("set", lua.create_function(move |lua, (key, value): (String, mlua::Value)| {
+        let package_id = lua_package_id(lua);
+        let history_manager = lua_history_manager(&app_set)?;
+
+        if value.is_nil() {
+            return history_manager.lua_storage_delete(&package_id, &key).map_err(mlua::Error::external);
+        }
+
+        let json: serde_json::Value = lua.from_value(value)?;
+        let encoded = json.to_string();
+        if encoded.len() > LUA_STORAGE_MAX_VALUE_BYTES {
+            return Err(mlua::Error::RuntimeError(format!(
+                "lumina.storage: value for '{}' exceeds {} bytes", key, LUA_STORAGE_MAX_VALUE_BYTES
+            )));
+        }
+
+        let used = history_manager.lua_storage_usage(&package_id, &key).map_err(mlua::Error::external)?;
+        if used + (key.len() + encoded.len()) as i64 > LUA_STORAGE_QUOTA_BYTES {
+            return Err(mlua::Error::RuntimeError(format!(
+                "lumina.storage: quota of {} bytes exceeded for package '{}'", LUA_STORAGE_QUOTA_BYTES, package_id
+            )));
+        }
+
+        history_manager.lua_storage_set(&package_id, &key, &encoded).map_err(mlua::Error::external)
+    })?)?;
+
+    let app_get = app.clone();
+    storage.set("get", lua.create_function(move |lua, key: String| {
+        let package_id = lua_package_id(lua);
+        let history_manager = lua_history_manager(&app_get)?;
+        match history_manager.lua_storage_get(&package_id, &key).map_err(mlua::Error::external)? {
+            Some(encoded) => {
+                let json: serde_json::Value = serde_json::from_str(&encoded).map_err(mlua::Error::external)?;
+                lua.to_value(&json)
+            }
+            None => Ok(mlua::Value::Nil),
+        }
+    })?)?;
+
+    storage.set("delete", lua.create_function(move |lua, key: String| {
+        let package_id = lua_package_id(lua);
+        lua_history_manager(&app)?.lua_storage_delete(&package_id, &key).map_err(mlua::Error::external)
+    })?)?;
+
+    let lumina: mlua::Table = lua.globals().get("lumina")?;
+    lumina.set("storage", storage)
+}
+
+
+#[allow(dead_code)]
+pub(crate) struct SidekickState {
+    pub(crate) tx: tokio::sync::mpsc::Sender<String>,
+}
+
+pub(crate) struct NetworkSidecarRequest {
+    pub(crate) command: String,
+    pub(crate) payload: String,
+    pub(crate) response_tx: tokio::sync::oneshot::Sender<String>,
+}
+
+pub(crate) struct NetworkState {
+    pub(crate) tx: tokio::sync::mpsc::Sender<NetworkSidecarRequest>,
+}
+
+#[tauri::command]
+pub(crate) async fn run_kip_code(app: tauri::AppHandle, code: String) -> Result<String, LuminaError> {
+    use tauri_plugin_shell::ShellExt;
+    use tauri_plugin_shell::process::CommandEvent;
+
+    let sidecar = app.shell().sidecar("kip-lang")?;
+
+    let (mut rx, mut child) = sidecar
+        .spawn()?;
+
+    // Send code + exit command to ensure the sidecar processes and terminates
+    let input = format!("{}\nexit\n", code);
+    child.write(input.as_bytes())?;
+
+    let mut output = String::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            CommandEvent::Stdout(line) => {
+                let text = String::from_utf8_lossy(&line);
+                output.push_str(&text);
+            }
+            CommandEvent::Stderr(line) => {
+                let text = String::from_utf8_lossy(&line);
+                println!("Kip Stderr: {}", text);
+            }
+            CommandEvent::Terminated(_) => {
+                break;
+            }
+            _ => {}
+        }
+    }
+    
+    Ok(output)
+}
+
+#[tauri::command]
+pub(crate) async fn run_networking_command(state: tauri::State<'_, NetworkState>, command: String, payload: String) -> Result<String, LuminaError> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    state.tx.send(NetworkSidecarRequest {
+        command,
+        payload,
+        response_tx: tx
+    }).await.map_err(|e| e.to_string())?;
+
+    rx.await.map_err(|e| LuminaError::Other(e.to_string()))
+}
+
+#[tauri::command]
+pub(crate) fn run_sidekick(app: tauri::AppHandle) -> Result<String, LuminaError> {
+    use tauri_plugin_shell::ShellExt;
+    
+    let sidecar = app.shell().sidecar("lumina-sidekick")?;
+
+    let (mut _rx, child) = sidecar
+        .spawn()
+        .map_err(|e| {
+            set_sidecar_status("lumina-sidekick", format!("spawn failed: {}", e));
+            e.to_string()
+        })?;
+
+    set_sidecar_status("lumina-sidekick", format!("started (pid {})", child.pid()));
+    Ok("Sidekick started".to_string())
+}
+
+#[tauri::command]
+pub(crate) async fn run_lua_code(state: tauri::State<'_, LuaState>, code: String) -> Result<String, LuminaError> {
+    state.run(code, "console", CONSOLE_PACKAGE_ID, false).await.map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn cancel_lua_execution(state: tauri::State<'_, LuaState>) -> Result<(), LuminaError> {
+    state.cancel();
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn reload_scripts(app: AppHandle) -> Result<Vec<LuaScriptStatus>, LuminaError> {
+    app.state::<LuaState>().scripts.lock().unwrap().clear();
+    Ok(load_all_scripts(&app).await)
+}
+
+#[tauri::command]
+pub(crate) fn get_script_status(state: tauri::State<'_, LuaState>) -> Vec<LuaScriptStatus> {
+    state.get_scripts()
+}
+
+#[tauri::command]
+pub(crate) fn set_lua_permission(history_manager: tauri::State<'_, HistoryManager>, package_id: String, permission: String, granted: bool) -> Result<(), LuminaError> {
+    history_manager.set_lua_permission(&package_id, &permission, granted).map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn get_lua_permissions(history_manager: tauri::State<'_, HistoryManager>, package_id: String) -> Result<Vec<String>, LuminaError> {
+    history_manager.get_lua_permissions(&package_id).map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn add_lua_console_history(history_manager: tauri::State<'_, HistoryManager>, code: String) -> Result<(), LuminaError> {
+    history_manager.add_console_entry(&code).map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn get_lua_console_history(history_manager: tauri::State<'_, HistoryManager>) -> Result<Vec<String>, LuminaError> {
+    history_manager.get_console_history(100).map_err(LuminaError::from)
+}
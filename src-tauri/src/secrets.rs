@@ -0,0 +1,189 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Marks a column value written by `FieldCipher`. Anything without it is a plaintext row from
+/// before encryption and is read back as is until the migration rewrites it.
+const ENCRYPTED_PREFIX: &str = "enc1:";
+const NONCE_LEN: usize = 12;
+
+#[cfg(not(windows))]
+const KEYRING_SERVICE: &str = "Lumina Browser";
+#[cfg(not(windows))]
+const KEYRING_USER: &str = "field-encryption-key";
+
+/// AES-256-GCM over single column values (cookie values, form entries). The key never touches the
+/// database: on Windows it sits next to it wrapped with DPAPI, elsewhere it lives in the OS keyring.
+pub(crate) struct FieldCipher {
+    cipher: Aes256Gcm,
+    key: [u8; 32],
+}
+
+impl FieldCipher {
+    pub(crate) fn load(app_data_dir: &Path) -> Result<Self, String> {
+        let key = load_or_create_key(app_data_dir)?;
+        Ok(Self { cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)), key })
+    }
+
+    pub(crate) fn is_encrypted(stored: &str) -> bool {
+        stored.starts_with(ENCRYPTED_PREFIX)
+    }
+
+    /// Fresh random nonce per write, for columns that are never compared in SQL.
+    pub(crate) fn encrypt(&self, plaintext: &str) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        self.seal(&nonce, plaintext)
+    }
+
+    /// Same input, same output: the nonce is derived from the key, `context` and the plaintext, so
+    /// UNIQUE constraints and DISTINCT still work on the encrypted column. Only reveals which rows
+    /// hold equal values within one context.
+    pub(crate) fn encrypt_deterministic(&self, context: &str, plaintext: &str) -> String {
+        let digest = Sha256::new()
+            .chain_update(self.key)
+            .chain_update(context.as_bytes())
+            .chain_update([0u8])
+            .chain_update(plaintext.as_bytes())
+            .finalize();
+        self.seal(Nonce::from_slice(&digest[..NONCE_LEN]), plaintext)
+    }
+
+    /// `None` when the value was encrypted under a key this profile no longer has.
+    pub(crate) fn decrypt(&self, stored: &str) -> Option<String> {
+        let Some(encoded) = stored.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Some(stored.to_string());
+        };
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).ok()?;
+        if bytes.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+
+    fn seal(&self, nonce: &Nonce<<Aes256Gcm as AeadCore>::NonceSize>, plaintext: &str) -> String {
+        // Encrypting into a Vec only fails on absurd lengths
+        let ciphertext = self.cipher.encrypt(nonce, plaintext.as_bytes()).expect("AES-GCM encryption failed");
+        let mut bytes = nonce.to_vec();
+        bytes.extend_from_slice(&ciphertext);
+        format!("{}{}", ENCRYPTED_PREFIX, base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+}
+
+fn new_key() -> [u8; 32] {
+    Aes256Gcm::generate_key(&mut OsRng).into()
+}
+
+/// The key is kept in `field_key.bin`, sealed to the Windows user account with DPAPI. A key file
+/// that can't be unsealed (copied from another account or machine) is an error and stays as it is:
+/// a new key would make everything sealed with the old one unreadable for good.
+#[cfg(windows)]
+fn load_or_create_key(app_data_dir: &Path) -> Result<[u8; 32], String> {
+    let path = app_data_dir.join("field_key.bin");
+    match std::fs::read(&path) {
+        Ok(sealed) => {
+            return dpapi::unprotect(&sealed)
+                .ok()
+                .and_then(|key| <[u8; 32]>::try_from(key).ok())
+                .ok_or_else(|| format!("{} could not be unsealed by this Windows account", path.display()));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+    let key = new_key();
+    let sealed = dpapi::protect(&key).map_err(|e| format!("DPAPI failed to seal the field key: {}", e))?;
+    write_new_file(&path, &sealed).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(key)
+}
+
+/// The key is kept in the OS keyring (Secret Service, macOS Keychain). Without a keyring it falls
+/// back to an owner-only key file in the profile, which is no worse than the plaintext it replaces.
+/// A new key is only made when neither holds one; a malformed key, or a keyring that can't be
+/// asked, is an error rather than a reason to replace the key.
+#[cfg(not(windows))]
+fn load_or_create_key(app_data_dir: &Path) -> Result<[u8; 32], String> {
+    let decode = |encoded: &str| {
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .ok()
+            .and_then(|key| <[u8; 32]>::try_from(key).ok())
+    };
+
+    let keyring_error = match keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).and_then(|entry| entry.get_password()) {
+        Ok(encoded) => return decode(&encoded).ok_or_else(|| "The field encryption key in the keyring is malformed".to_string()),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => Some(e),
+    };
+
+    let path = app_data_dir.join("field_key");
+    match std::fs::read_to_string(&path) {
+        Ok(encoded) => return decode(&encoded).ok_or_else(|| format!("The field encryption key in {} is malformed", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+    // The key may well be in a keyring that is locked or not running yet
+    if let Some(e) = keyring_error {
+        return Err(format!("The keyring is unavailable ({}) and there is no key file", e));
+    }
+
+    let key = new_key();
+    let encoded = base64::engine::general_purpose::STANDARD.encode(key);
+    let stored = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).and_then(|entry| entry.set_password(&encoded));
+    if stored.is_err() {
+        write_new_file(&path, encoded.as_bytes()).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(key)
+}
+
+/// Writes a key file that must not exist yet, readable by the owner only where the OS allows.
+#[cfg(unix)]
+fn write_new_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?;
+    file.write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_new_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(contents)
+}
+
+#[cfg(windows)]
+mod dpapi {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{LocalFree, HLOCAL};
+    use windows::Win32::Security::Cryptography::{
+        CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+
+    pub(super) fn protect(data: &[u8]) -> windows::core::Result<Vec<u8>> {
+        let input = CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_ptr() as *mut u8 };
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptProtectData(&input, PCWSTR::null(), None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)?;
+            Ok(take_blob(output))
+        }
+    }
+
+    pub(super) fn unprotect(data: &[u8]) -> windows::core::Result<Vec<u8>> {
+        let input = CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_ptr() as *mut u8 };
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptUnprotectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)?;
+            Ok(take_blob(output))
+        }
+    }
+
+    /// Copies a DPAPI output blob and releases the buffer Windows allocated for it
+    unsafe fn take_blob(blob: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+        let bytes = std::slice::from_raw_parts(blob.pbData, blob.cbData as usize).to_vec();
+        let _ = LocalFree(HLOCAL(blob.pbData as *mut core::ffi::c_void));
+        bytes
+    }
+}
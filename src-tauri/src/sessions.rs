@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::events::{self, AppEvent};
+use crate::history_manager::HistoryManager;
+use crate::layout::UiState;
+use crate::tab_registry::TabRegistry;
+use crate::tabs::{self, TabUpdatedPayload};
+
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SavedSessionTab {
+    url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(default)]
+    pinned: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SavedSessionSummary {
+    name: String,
+    /// Unix seconds
+    saved_at: i64,
+    tab_count: usize,
+    /// Distinct tab groups, in strip order
+    groups: Vec<String>,
+}
+
+fn validated_name(name: &str) -> Result<String, LuminaError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(LuminaError::InvalidInput(format!("Session names must be 1 to {} characters", MAX_NAME_LEN)));
+    }
+    Ok(name.to_string())
+}
+
+fn parse_tabs(json: &str) -> Result<Vec<SavedSessionTab>, LuminaError> {
+    serde_json::from_str(json).map_err(|e| LuminaError::Database(format!("Saved session is damaged: {}", e)))
+}
+
+/// Saves the tabs of the caller's window under `name`, replacing a session with that name.
+/// Private tabs are left out. Returns how many tabs were saved.
+#[tauri::command]
+pub(crate) fn save_session(history_manager: tauri::State<'_, HistoryManager>, registry: tauri::State<'_, TabRegistry>, webview: tauri::Webview, name: String) -> Result<usize, LuminaError> {
+    let name = validated_name(&name)?;
+    let tabs: Vec<SavedSessionTab> = registry
+        .snapshot(Some(webview.window().label()))
+        .tabs
+        .into_iter()
+        .filter(|tab| !tab.private)
+        .map(|tab| SavedSessionTab { url: tab.url, title: tab.title, group: tab.group, pinned: tab.pinned })
+        .collect();
+    if tabs.is_empty() {
+        return Err(LuminaError::InvalidInput("There are no tabs to save".to_string()));
+    }
+    let json = serde_json::to_string(&tabs).map_err(|e| LuminaError::Other(e.to_string()))?;
+    history_manager.set_saved_session(&name, &json)?;
+    Ok(tabs.len())
+}
+
+/// Replaces the tabs of the caller's window with a saved session, pinned state and groups included.
+/// The new tabs open before the old ones close, so the window is never empty.
+#[tauri::command]
+pub(crate) async fn load_session(app: AppHandle, webview: tauri::Webview, name: String) -> Result<usize, LuminaError> {
+    let json = app
+        .state::<HistoryManager>()
+        .get_saved_session(name.trim())?
+        .ok_or_else(|| LuminaError::NotFound(format!("Session {} not found", name.trim())))?;
+    let saved = parse_tabs(&json)?;
+    let window = webview.window();
+    let previous: Vec<String> = app
+        .state::<TabRegistry>()
+        .snapshot(Some(window.label()))
+        .tabs
+        .into_iter()
+        .map(|tab| tab.label)
+        .collect();
+
+    let batch = chrono::Utc::now().timestamp_micros();
+    let mut opened = 0;
+    for (index, tab) in saved.iter().enumerate() {
+        let label = format!("tab-{}-{}", batch, index);
+        let created = tabs::create_tab(app.state::<UiState>(), app.clone(), app.state::<AppDataStore>(), label.clone(), Some(tab.url.clone()), window.clone()).await;
+        if let Err(e) = created {
+            eprintln!("Lumina: Could not reopen {} from session: {}", tab.url, e);
+            continue;
+        }
+        let registry = app.state::<TabRegistry>();
+        registry.set_pinned(&label, tab.pinned);
+        registry.set_group(&label, tab.group.clone());
+        events::emit(&app, AppEvent::TabUpdated(TabUpdatedPayload {
+            label,
+            title: Some(tab.title.clone()).filter(|title| !title.is_empty()),
+            favicon: None,
+            pinned: Some(tab.pinned),
+        }));
+        opened += 1;
+    }
+    if opened == 0 {
+        return Err(LuminaError::Other("None of the session's tabs could be opened".to_string()));
+    }
+    for label in previous {
+        tabs::close_tab(app.clone(), label)?;
+    }
+    Ok(opened)
+}
+
+#[tauri::command]
+pub(crate) fn list_sessions(history_manager: tauri::State<'_, HistoryManager>) -> Result<Vec<SavedSessionSummary>, LuminaError> {
+    let sessions = history_manager.get_saved_sessions()?;
+    Ok(sessions
+        .into_iter()
+        .filter_map(|(name, json, saved_at)| {
+            let tabs = parse_tabs(&json).ok()?;
+            let mut groups: Vec<String> = Vec::new();
+            for group in tabs.iter().filter_map(|tab| tab.group.as_ref()) {
+                if !groups.contains(group) {
+                    groups.push(group.clone());
+                }
+            }
+            Some(SavedSessionSummary { name, saved_at, tab_count: tabs.len(), groups })
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub(crate) fn delete_session(history_manager: tauri::State<'_, HistoryManager>, name: String) -> Result<(), LuminaError> {
+    if !history_manager.delete_saved_session(name.trim())? {
+        return Err(LuminaError::NotFound(format!("Session {} not found", name.trim())));
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+use tokio::sync::oneshot;
+
+use crate::error::LuminaError;
+use crate::events::{self, AppEvent};
+use crate::layout::{is_browser_window, tab_window, UiState};
+
+/// A question a site made Lumina ask, shown in the browser window's infobar rather than in the page,
+/// so the page can neither draw nor answer it.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SitePrompt {
+    pub id: String,
+    /// The tab (or app window) that asked
+    pub label: String,
+    /// The browser window showing it
+    pub window: String,
+    /// "notifications", "external-protocol", "app-handlers" or "app-isolation"
+    pub kind: String,
+    pub origin: String,
+    pub message: String,
+    /// Whether the infobar offers to remember the answer for the origin
+    pub can_remember: bool,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct PromptAnswer {
+    pub allow: bool,
+    pub remember: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SitePromptClosedPayload {
+    pub id: String,
+    pub label: String,
+}
+
+struct PendingPrompt {
+    prompt: SitePrompt,
+    reply: oneshot::Sender<PromptAnswer>,
+}
+
+static PENDING: OnceLock<Mutex<HashMap<String, PendingPrompt>>> = OnceLock::new();
+
+fn pending() -> &'static Mutex<HashMap<String, PendingPrompt>> {
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Asks the user on behalf of a tab and waits for the answer. Tabs ask in their own window; app
+/// windows have no infobar, so they ask in the browser window used last, which is brought forward.
+/// `None` if the prompt was dropped unanswered because its tab closed.
+pub(crate) async fn ask(app: &AppHandle, label: &str, kind: &str, origin: &str, message: String, can_remember: bool) -> Option<PromptAnswer> {
+    let window = match tab_window(app, label) {
+        Some(window) if is_browser_window(&window) => window,
+        _ => {
+            let window = app.state::<UiState>().focused_window();
+            if let Some(browser_window) = app.get_window(&window) {
+                let _ = browser_window.set_focus();
+            }
+            window
+        }
+    };
+    let prompt = SitePrompt {
+        id: uuid::Uuid::new_v4().to_string(),
+        label: label.to_string(),
+        window,
+        kind: kind.to_string(),
+        origin: origin.to_string(),
+        message,
+        can_remember,
+    };
+    let (reply, answer) = oneshot::channel();
+    pending().lock().unwrap().insert(prompt.id.clone(), PendingPrompt { prompt: prompt.clone(), reply });
+    events::emit(app, AppEvent::SitePrompt(prompt));
+    answer.await.ok()
+}
+
+/// Drops a closing tab's prompts, leaving whoever asked without an answer.
+pub(crate) fn dismiss_tab(app: &AppHandle, label: &str) {
+    let dropped: Vec<String> = {
+        let mut pending = pending().lock().unwrap();
+        let ids: Vec<String> = pending.values().filter(|p| p.prompt.label == label).map(|p| p.prompt.id.clone()).collect();
+        ids.into_iter().filter(|id| pending.remove(id).is_some()).collect()
+    };
+    for id in dropped {
+        events::emit(app, AppEvent::SitePromptClosed(SitePromptClosedPayload { id, label: label.to_string() }));
+    }
+}
+
+/// Called by the infobar. Not page-callable, so only the browser UI can answer.
+#[tauri::command]
+pub(crate) fn answer_site_prompt(app: AppHandle, id: String, allow: bool, remember: bool) -> Result<(), LuminaError> {
+    let PendingPrompt { prompt, reply } = pending()
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .ok_or_else(|| LuminaError::NotFound(format!("Prompt {} not found", id)))?;
+    let _ = reply.send(PromptAnswer { allow, remember: remember && prompt.can_remember });
+    events::emit(&app, AppEvent::SitePromptClosed(SitePromptClosedPayload { id, label: prompt.label }));
+    Ok(())
+}
+
+/// Unanswered prompts shown in the calling browser window, for a UI that starts after they were asked.
+#[tauri::command]
+pub(crate) fn get_site_prompts(window: tauri::Window) -> Vec<SitePrompt> {
+    pending().lock().unwrap().values().filter(|p| p.prompt.window == window.label()).map(|p| p.prompt.clone()).collect()
+}
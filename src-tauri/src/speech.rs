@@ -0,0 +1,317 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::events::{self, AppEvent};
+use crate::tabs::article_text;
+
+/// Longest piece handed to the engine at once. The engines can't pause mid-utterance, so pausing
+/// stops the current piece and resuming starts it over.
+const MAX_CHUNK_CHARS: usize = 600;
+/// Bounds for the rate setting, a multiple of the engine's normal rate
+pub(crate) const MIN_RATE: f32 = 0.5;
+pub(crate) const MAX_RATE: f32 = 3.0;
+
+#[derive(Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ReadAloudStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReadAloudStatePayload {
+    label: Option<String>,
+    status: ReadAloudStatus,
+    /// Index of the piece being read, out of `total`
+    position: usize,
+    total: usize,
+}
+
+#[derive(Clone, Serialize)]
+pub(crate) struct VoiceInfo {
+    id: String,
+    name: String,
+    language: String,
+}
+
+enum SpeechCommand {
+    Read { label: String, chunks: Vec<String> },
+    Pause,
+    Resume,
+    Stop,
+    Configure { rate: f32, voice: Option<String> },
+    Voices(Sender<Result<Vec<VoiceInfo>, String>>),
+    #[cfg_attr(not(windows), allow(dead_code))]
+    UtteranceEnded(engine::UtteranceId),
+}
+
+/// Channel to the speech thread, which owns the engine (the tts handles must stay on one thread).
+static SPEECH: OnceLock<Mutex<Option<Sender<SpeechCommand>>>> = OnceLock::new();
+
+/// Starts the speech thread on first use and applies the saved rate and voice.
+fn speech(app: &AppHandle) -> Result<Sender<SpeechCommand>, LuminaError> {
+    let mut speech = SPEECH.get_or_init(|| Mutex::new(None)).lock().unwrap();
+    if let Some(tx) = speech.as_ref() {
+        return Ok(tx.clone());
+    }
+
+    let (tx, rx) = channel();
+    let (ready_tx, ready_rx) = channel();
+    let app_handle = app.clone();
+    let events_tx = tx.clone();
+    std::thread::spawn(move || {
+        let engine = match engine::Engine::new(events_tx) {
+            Ok(engine) => {
+                let _ = ready_tx.send(Ok(()));
+                engine
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        run_speech_thread(&app_handle, engine, rx);
+    });
+    ready_rx
+        .recv()
+        .map_err(|e| e.to_string())?
+        .map_err(|e| LuminaError::Other(format!("Read aloud is unavailable: {}", e)))?;
+
+    let (rate, voice) = {
+        let data = app.state::<AppDataStore>();
+        let data = data.data.lock().unwrap();
+        (data.settings.read_aloud_rate, data.settings.read_aloud_voice.clone())
+    };
+    let _ = tx.send(SpeechCommand::Configure { rate, voice });
+    *speech = Some(tx.clone());
+    Ok(tx)
+}
+
+fn run_speech_thread(app: &AppHandle, mut engine: engine::Engine, rx: Receiver<SpeechCommand>) {
+    let mut label: Option<String> = None;
+    let mut chunks: Vec<String> = Vec::new();
+    let mut position = 0;
+    let mut status = ReadAloudStatus::Stopped;
+    let mut current: Option<engine::UtteranceId> = None;
+
+    let emit = |label: &Option<String>, status, position, total| {
+        events::emit(app, AppEvent::ReadAloudState(ReadAloudStatePayload { label: label.clone(), status, position, total }));
+    };
+
+    while let Ok(command) = rx.recv() {
+        match command {
+            SpeechCommand::Read { label: tab, chunks: text } => {
+                let _ = engine.stop();
+                label = Some(tab);
+                chunks = text;
+                position = 0;
+                status = ReadAloudStatus::Playing;
+                current = engine.speak(&chunks[0]).unwrap_or(None);
+            }
+            SpeechCommand::Pause if status == ReadAloudStatus::Playing => {
+                status = ReadAloudStatus::Paused;
+                current = None;
+                let _ = engine.stop();
+            }
+            SpeechCommand::Resume if status == ReadAloudStatus::Paused => {
+                status = ReadAloudStatus::Playing;
+                current = engine.speak(&chunks[position]).unwrap_or(None);
+            }
+            SpeechCommand::Stop => {
+                status = ReadAloudStatus::Stopped;
+                current = None;
+                let _ = engine.stop();
+            }
+            SpeechCommand::Configure { rate, voice } => {
+                if let Err(e) = engine.configure(rate, voice.as_deref()) {
+                    eprintln!("Lumina: read aloud settings not applied: {}", e);
+                }
+                continue;
+            }
+            SpeechCommand::Voices(reply) => {
+                let _ = reply.send(engine.voices());
+                continue;
+            }
+            // Ends of utterances cut short by pause/stop/read arrive late and are ignored
+            SpeechCommand::UtteranceEnded(id) if status == ReadAloudStatus::Playing && current == Some(id) => {
+                position += 1;
+                if position < chunks.len() {
+                    current = engine.speak(&chunks[position]).unwrap_or(None);
+                } else {
+                    status = ReadAloudStatus::Stopped;
+                    current = None;
+                }
+            }
+            _ => continue,
+        }
+        emit(&label, status, position, chunks.len());
+    }
+}
+
+/// Paragraphs, with long ones split at sentence ends so each piece stays under MAX_CHUNK_CHARS.
+fn split_into_chunks(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    for paragraph in text.lines().map(str::trim).filter(|p| !p.is_empty()) {
+        let mut chunk = String::new();
+        for sentence in paragraph.split_inclusive(['.', '!', '?']) {
+            if !chunk.is_empty() && chunk.len() + sentence.len() > MAX_CHUNK_CHARS {
+                chunks.push(std::mem::take(&mut chunk).trim().to_string());
+            }
+            chunk.push_str(sentence);
+        }
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_string());
+        }
+    }
+    chunks
+}
+
+/// Reads the tab's article (the same text reader mode shows) aloud from the start.
+#[tauri::command]
+pub(crate) async fn read_aloud(app: AppHandle, label: String) -> Result<(), LuminaError> {
+    let chunks = split_into_chunks(&article_text(&app, &label).await?);
+    if chunks.is_empty() {
+        return Err(LuminaError::NotFound("The page has no text to read".to_string()));
+    }
+
+    speech(&app)?.send(SpeechCommand::Read { label, chunks }).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn pause_read_aloud(app: AppHandle) -> Result<(), LuminaError> {
+    speech(&app)?.send(SpeechCommand::Pause).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn resume_read_aloud(app: AppHandle) -> Result<(), LuminaError> {
+    speech(&app)?.send(SpeechCommand::Resume).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn stop_read_aloud(app: AppHandle) -> Result<(), LuminaError> {
+    speech(&app)?.send(SpeechCommand::Stop).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn list_read_aloud_voices(app: AppHandle) -> Result<Vec<VoiceInfo>, LuminaError> {
+    let (reply_tx, reply_rx) = channel();
+    speech(&app)?.send(SpeechCommand::Voices(reply_tx)).map_err(|e| e.to_string())?;
+    reply_rx.recv().map_err(|e| e.to_string())?.map_err(LuminaError::Other)
+}
+
+/// `rate` is a multiple of the voice's normal speed; `voice: null` uses the system default voice.
+#[tauri::command]
+pub(crate) fn set_read_aloud_settings(state: tauri::State<'_, AppDataStore>, rate: f32, voice: Option<String>) -> Result<(), LuminaError> {
+    if !rate.is_finite() {
+        return Err(LuminaError::InvalidInput("Invalid rate".to_string()));
+    }
+    let rate = rate.clamp(MIN_RATE, MAX_RATE);
+    let voice = voice.filter(|v| !v.is_empty());
+    {
+        let mut data = state.data.lock().unwrap();
+        data.settings.read_aloud_rate = rate;
+        data.settings.read_aloud_voice = voice.clone();
+    }
+    state.save();
+
+    // Only a running engine needs telling; a new one picks the settings up when it starts
+    if let Some(tx) = SPEECH.get().and_then(|speech| speech.lock().unwrap().clone()) {
+        let _ = tx.send(SpeechCommand::Configure { rate, voice });
+    }
+    Ok(())
+}
+
+/// WinRT speech synthesis (SAPI voices) through the tts crate.
+#[cfg(windows)]
+mod engine {
+    use std::sync::mpsc::Sender;
+
+    use super::{SpeechCommand, VoiceInfo};
+
+    pub(super) type UtteranceId = tts::UtteranceId;
+
+    pub(super) struct Engine {
+        tts: tts::Tts,
+    }
+
+    impl Engine {
+        pub(super) fn new(events: Sender<SpeechCommand>) -> Result<Self, String> {
+            let tts = tts::Tts::default().map_err(|e| e.to_string())?;
+            tts.on_utterance_end(Some(Box::new(move |id| {
+                let _ = events.send(SpeechCommand::UtteranceEnded(id));
+            })))
+            .map_err(|e| e.to_string())?;
+            Ok(Self { tts })
+        }
+
+        pub(super) fn speak(&mut self, text: &str) -> Result<Option<UtteranceId>, String> {
+            self.tts.speak(text, true).map_err(|e| e.to_string())
+        }
+
+        pub(super) fn stop(&mut self) -> Result<(), String> {
+            self.tts.stop().map(|_| ()).map_err(|e| e.to_string())
+        }
+
+        pub(super) fn configure(&mut self, rate: f32, voice: Option<&str>) -> Result<(), String> {
+            let rate = (self.tts.normal_rate() * rate).clamp(self.tts.min_rate(), self.tts.max_rate());
+            self.tts.set_rate(rate).map_err(|e| e.to_string())?;
+            if let Some(id) = voice {
+                let voices = self.tts.voices().map_err(|e| e.to_string())?;
+                let voice = voices.iter().find(|v| v.id() == id).ok_or_else(|| format!("Voice {} is not installed", id))?;
+                self.tts.set_voice(voice).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+
+        pub(super) fn voices(&self) -> Result<Vec<VoiceInfo>, String> {
+            let voices = self.tts.voices().map_err(|e| e.to_string())?;
+            Ok(voices
+                .into_iter()
+                .map(|v| VoiceInfo { id: v.id(), name: v.name(), language: v.language().to_string() })
+                .collect())
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod engine {
+    use std::sync::mpsc::Sender;
+
+    use super::{SpeechCommand, VoiceInfo};
+
+    pub(super) type UtteranceId = u64;
+
+    pub(super) struct Engine;
+
+    impl Engine {
+        pub(super) fn new(_events: Sender<SpeechCommand>) -> Result<Self, String> {
+            Err("not supported on this platform yet".to_string())
+        }
+
+        pub(super) fn speak(&mut self, _text: &str) -> Result<Option<UtteranceId>, String> {
+            Ok(None)
+        }
+
+        pub(super) fn stop(&mut self) -> Result<(), String> {
+            Ok(())
+        }
+
+        pub(super) fn configure(&mut self, _rate: f32, _voice: Option<&str>) -> Result<(), String> {
+            Ok(())
+        }
+
+        pub(super) fn voices(&self) -> Result<Vec<VoiceInfo>, String> {
+            Ok(Vec::new())
+        }
+    }
+}
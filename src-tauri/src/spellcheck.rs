@@ -0,0 +1,199 @@
+use std::sync::OnceLock;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::adblock::normalize_site_domain;
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::layout::is_browser_window;
+use crate::to_script_json;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpellcheckConfig {
+    enabled: bool,
+    /// Domains (and their subdomains) where spell checking is always off
+    disabled_sites: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub(crate) struct SpellcheckLanguages {
+    /// Dictionaries installed on the system, as BCP 47 tags
+    available: Vec<String>,
+    /// Chosen dictionaries; empty means the system languages
+    selected: Vec<String>,
+    /// What tabs are actually using, which lags `selected` until the next restart
+    active: Vec<String>,
+}
+
+/// Dictionaries the browser process was started with. WebView2 refuses to create a webview whose
+/// browser arguments differ from the running instance's, so the first tab's choice sticks.
+static ACTIVE_LANGUAGES: OnceLock<Vec<String>> = OnceLock::new();
+
+fn spellcheck_config(app: &AppHandle) -> SpellcheckConfig {
+    let data = app.state::<AppDataStore>();
+    let data = data.data.lock().unwrap();
+    SpellcheckConfig {
+        enabled: data.settings.spellcheck_enabled,
+        disabled_sites: data.settings.spellcheck_disabled_sites.clone(),
+    }
+}
+
+pub(crate) fn active_languages(app: &AppHandle) -> &'static [String] {
+    ACTIVE_LANGUAGES.get_or_init(|| app.state::<AppDataStore>().data.lock().unwrap().settings.spellcheck_languages.clone())
+}
+
+/// Turns spell checking off in editable fields when it's disabled globally or for the site.
+/// Pages that opt fields in with spellcheck="true" are overridden when they get focus.
+pub(crate) fn get_spellcheck_script(app: &AppHandle) -> String {
+    format!(r#"
+    (function() {{
+        if (window.self !== window.top) return;
+        let config = {};
+
+        function isDisabled() {{
+            if (!config.enabled) return true;
+            const host = location.hostname.replace(/^www\./, '');
+            return config.disabledSites.some(d => host === d || host.endsWith('.' + d));
+        }}
+
+        function apply() {{
+            const root = document.documentElement;
+            if (!root) return;
+            if (isDisabled()) {{
+                root.spellcheck = false;
+                root.dataset.luminaSpellcheck = 'off';
+            }} else if (root.dataset.luminaSpellcheck === 'off') {{
+                root.removeAttribute('spellcheck');
+                delete root.dataset.luminaSpellcheck;
+            }}
+        }}
+
+        document.addEventListener('focusin', (e) => {{
+            const el = e.target;
+            if (isDisabled() && el && (el.isContentEditable || el.tagName === 'INPUT' || el.tagName === 'TEXTAREA')) {{
+                el.spellcheck = false;
+            }}
+        }}, true);
+
+        window.__LUMINA_SET_SPELLCHECK__ = function(next) {{
+            config = next;
+            apply();
+        }};
+
+        if (document.documentElement) {{
+            apply();
+        }} else {{
+            document.addEventListener('DOMContentLoaded', apply);
+        }}
+    }})();
+    "#, to_script_json(&spellcheck_config(app)))
+}
+
+/// Pushes the current settings into every open tab.
+fn broadcast_spellcheck(app: &AppHandle) {
+    let script = format!(
+        "window.__LUMINA_SET_SPELLCHECK__ && window.__LUMINA_SET_SPELLCHECK__({})",
+        to_script_json(&spellcheck_config(app))
+    );
+    for (label, webview) in app.webviews() {
+        if !is_browser_window(&label) {
+            let _ = webview.eval(&script);
+        }
+    }
+}
+
+#[tauri::command]
+pub(crate) fn get_spellcheck_languages(app: AppHandle) -> Result<SpellcheckLanguages, LuminaError> {
+    let selected = app.state::<AppDataStore>().data.lock().unwrap().settings.spellcheck_languages.clone();
+    Ok(SpellcheckLanguages {
+        available: installed_dictionaries()?,
+        selected,
+        active: active_languages(&app).to_vec(),
+    })
+}
+
+#[tauri::command]
+pub(crate) fn set_spellcheck_enabled(app: AppHandle, state: tauri::State<'_, AppDataStore>, enabled: bool) -> Result<(), LuminaError> {
+    state.data.lock().unwrap().settings.spellcheck_enabled = enabled;
+    state.try_save()?;
+    broadcast_spellcheck(&app);
+    Ok(())
+}
+
+/// Takes effect for tabs opened after the next restart. An empty list goes back to the system languages.
+#[tauri::command]
+pub(crate) fn set_spellcheck_languages(state: tauri::State<'_, AppDataStore>, languages: Vec<String>) -> Result<(), LuminaError> {
+    let mut tags: Vec<String> = Vec::new();
+    for language in languages {
+        let tag = language.trim();
+        if tag.is_empty() || tag.len() > 35 || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(LuminaError::InvalidInput(format!("Invalid language tag: {}", language)));
+        }
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    state.data.lock().unwrap().settings.spellcheck_languages = tags;
+    state.save();
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn set_spellcheck_site_disabled(app: AppHandle, state: tauri::State<'_, AppDataStore>, domain: String, disabled: bool) -> Result<(), LuminaError> {
+    let domain = normalize_site_domain(&domain);
+    if domain.is_empty() {
+        return Err(LuminaError::InvalidInput("Invalid domain".to_string()));
+    }
+    {
+        let mut data = state.data.lock().unwrap();
+        let sites = &mut data.settings.spellcheck_disabled_sites;
+        sites.retain(|d| d != &domain);
+        if disabled {
+            sites.push(domain);
+            sites.sort();
+        }
+    }
+    state.save();
+    broadcast_spellcheck(&app);
+    Ok(())
+}
+
+/// Languages the Windows spell checker has dictionaries for.
+#[cfg(target_os = "windows")]
+fn installed_dictionaries() -> Result<Vec<String>, LuminaError> {
+    use windows::core::PWSTR;
+    use windows::Win32::Globalization::{ISpellCheckerFactory, SpellCheckerFactory};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CoTaskMemFree, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED};
+
+    unsafe {
+        // Already initialized on this thread is fine
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let factory: ISpellCheckerFactory = CoCreateInstance(&SpellCheckerFactory, None, CLSCTX_INPROC_SERVER)
+            .map_err(|e| LuminaError::Other(format!("Spell checker unavailable: {}", e)))?;
+        let languages = factory
+            .SupportedLanguages()
+            .map_err(|e| LuminaError::Other(format!("Spell checker unavailable: {}", e)))?;
+
+        let mut tags = Vec::new();
+        loop {
+            let mut tag = [PWSTR::null()];
+            let mut fetched = 0u32;
+            if languages.Next(&mut tag, Some(&mut fetched)).is_err() || fetched == 0 {
+                break;
+            }
+            if let Ok(s) = tag[0].to_string() {
+                tags.push(s);
+            }
+            CoTaskMemFree(Some(tag[0].0 as *const _));
+        }
+        tags.sort();
+        Ok(tags)
+    }
+}
+
+/// Other platforms use the system spell checker's languages; only on/off is configurable there.
+#[cfg(not(target_os = "windows"))]
+fn installed_dictionaries() -> Result<Vec<String>, LuminaError> {
+    Ok(Vec::new())
+}
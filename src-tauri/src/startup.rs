@@ -0,0 +1,127 @@
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+/// Deferred work runs anyway when no browser window has painted by then (PWA launches, a UI
+/// that failed to load)
+pub(crate) const FIRST_PAINT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StartupPhase {
+    pub name: String,
+    /// Milliseconds since launch when the phase began
+    pub started_ms: u64,
+    pub duration_ms: u64,
+    /// Whether the phase began after the first window painted
+    pub deferred: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StartupReport {
+    /// Milliseconds from launch until the first browser window finished loading its UI
+    pub first_paint_ms: Option<u64>,
+    pub phases: Vec<StartupPhase>,
+    /// Work still waiting for the first paint
+    pub pending: Vec<String>,
+}
+
+type DeferredTask = Box<dyn FnOnce(&AppHandle) + Send>;
+type DeferredQueue = Vec<(&'static str, DeferredTask)>;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static FIRST_PAINT: OnceLock<u64> = OnceLock::new();
+static PHASES: OnceLock<Mutex<Vec<StartupPhase>>> = OnceLock::new();
+/// None once the deferred work has been started
+static DEFERRED: OnceLock<Mutex<Option<DeferredQueue>>> = OnceLock::new();
+
+fn phases() -> &'static Mutex<Vec<StartupPhase>> {
+    PHASES.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn deferred() -> &'static Mutex<Option<DeferredQueue>> {
+    DEFERRED.get_or_init(|| Mutex::new(Some(Vec::new())))
+}
+
+fn elapsed_ms() -> u64 {
+    STARTED.get_or_init(Instant::now).elapsed().as_millis() as u64
+}
+
+/// Starts the launch clock; call first thing in `run`.
+pub(crate) fn begin() {
+    STARTED.get_or_init(Instant::now);
+}
+
+/// Times a startup phase until it is dropped. Works across awaits, so async phases can hold one.
+pub(crate) struct Span {
+    name: &'static str,
+    started_ms: u64,
+}
+
+pub(crate) fn span(name: &'static str) -> Span {
+    Span { name, started_ms: elapsed_ms() }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let duration_ms = elapsed_ms().saturating_sub(self.started_ms);
+        println!("Lumina Startup: {} took {} ms", self.name, duration_ms);
+        phases().lock().unwrap().push(StartupPhase {
+            name: self.name.to_string(),
+            started_ms: self.started_ms,
+            duration_ms,
+            deferred: FIRST_PAINT.get().is_some_and(|paint| self.started_ms >= *paint),
+        });
+    }
+}
+
+/// Holds `task` back until the first browser window has painted. Runs it right away when
+/// that already happened.
+pub(crate) fn defer(app: &AppHandle, name: &'static str, task: impl FnOnce(&AppHandle) + Send + 'static) {
+    let task: DeferredTask = Box::new(task);
+    let task = {
+        let mut pending = deferred().lock().unwrap();
+        match pending.as_mut() {
+            Some(pending) => {
+                pending.push((name, task));
+                return;
+            }
+            None => task,
+        }
+    };
+    let _span = span(name);
+    task(app);
+}
+
+/// Starts everything held back by `defer`. Only the first call does anything.
+pub(crate) fn run_deferred(app: &AppHandle) {
+    let Some(tasks) = deferred().lock().unwrap().take() else { return };
+    for (name, task) in tasks {
+        let _span = span(name);
+        task(app);
+    }
+}
+
+/// Called as a browser window's UI finishes loading.
+pub(crate) fn first_paint(app: &AppHandle) {
+    if FIRST_PAINT.set(elapsed_ms()).is_ok() {
+        println!("Lumina Startup: first window painted after {} ms", FIRST_PAINT.get().copied().unwrap_or_default());
+        run_deferred(app);
+    }
+}
+
+pub(crate) fn report() -> StartupReport {
+    let mut phases = phases().lock().unwrap().clone();
+    phases.sort_by_key(|phase| phase.started_ms);
+    let pending = deferred().lock().unwrap().as_ref()
+        .map(|tasks| tasks.iter().map(|(name, _)| name.to_string()).collect())
+        .unwrap_or_default();
+    StartupReport { first_paint_ms: FIRST_PAINT.get().copied(), phases, pending }
+}
+
+#[tauri::command]
+pub(crate) fn get_startup_report() -> StartupReport {
+    report()
+}
@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::pwa::pwa_data_dir;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StorageEntry {
+    name: String,
+    path: String,
+    size_bytes: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PwaStorage {
+    id: String,
+    title: String,
+    size_bytes: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StorageUsage {
+    /// Browser data files and folders, largest first
+    entries: Vec<StorageEntry>,
+    /// Profiles of apps installed with their own sign-in
+    pwas: Vec<PwaStorage>,
+    /// The part of `entries` that `clear_cache` can reclaim
+    cache_bytes: u64,
+    total_bytes: u64,
+}
+
+/// Size of a file, or of everything under a folder. Missing paths count as empty.
+fn disk_size(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else { return 0 };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| disk_size(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Where the shared browser profile keeps its HTTP cache. WebView2 puts it in the user data
+/// folder; WebKitGTK and WKWebView use the platform cache folder.
+#[cfg(windows)]
+fn webview_cache_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    let Ok(profile) = app.path().app_local_data_dir().map(|dir| dir.join("EBWebView").join("Default")) else { return Vec::new() };
+    vec![profile.join("Cache"), profile.join("Code Cache"), profile.join("GPUCache")]
+}
+
+#[cfg(not(windows))]
+fn webview_cache_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    app.path().app_cache_dir().into_iter().collect()
+}
+
+fn measure(app: &AppHandle) -> Result<StorageUsage, LuminaError> {
+    let data_dir = app.path().app_data_dir()?;
+    let mut entries = Vec::new();
+    let mut push = |name: &str, path: PathBuf, size_bytes: u64| {
+        entries.push(StorageEntry { name: name.to_string(), path: path.to_string_lossy().to_string(), size_bytes });
+    };
+
+    // SQLite keeps recent writes next to the database until a checkpoint
+    let history = data_dir.join("history.db");
+    let history_size = ["history.db", "history.db-wal", "history.db-shm"].iter().map(|name| disk_size(&data_dir.join(name))).sum();
+    push("History and site data", history, history_size);
+    push("History backups", data_dir.join("backups"), disk_size(&data_dir.join("backups")));
+    push("Downloads list", data_dir.join("downloads.json"), disk_size(&data_dir.join("downloads.json")));
+    push("App icons", data_dir.join("icons"), disk_size(&data_dir.join("icons")));
+    push("Site profiles", data_dir.join("site-profiles"), disk_size(&data_dir.join("site-profiles")));
+
+    #[cfg(windows)]
+    if let Ok(profile) = app.path().app_local_data_dir().map(|dir| dir.join("EBWebView")) {
+        push("Browser profile", profile.clone(), disk_size(&profile));
+    }
+    let mut cache_bytes = 0;
+    for dir in webview_cache_dirs(app) {
+        let size = disk_size(&dir);
+        cache_bytes += size;
+        push("Web cache", dir, size);
+    }
+    entries.retain(|entry| entry.size_bytes > 0);
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.size_bytes));
+
+    let mut pwas: Vec<PwaStorage> = app
+        .state::<AppDataStore>()
+        .installed_pwas()
+        .into_iter()
+        .filter(|pwa| pwa.isolated)
+        .filter_map(|pwa| {
+            let size_bytes = disk_size(&pwa_data_dir(app, &pwa.id)?);
+            Some(PwaStorage { id: pwa.id, title: pwa.title, size_bytes })
+        })
+        .collect();
+    pwas.sort_by_key(|pwa| std::cmp::Reverse(pwa.size_bytes));
+
+    // The browser profile already contains the cache on Windows
+    let counted_cache = if cfg!(windows) { cache_bytes } else { 0 };
+    let total_bytes = entries.iter().map(|e| e.size_bytes).sum::<u64>() - counted_cache
+        + pwas.iter().map(|p| p.size_bytes).sum::<u64>();
+    Ok(StorageUsage { entries, pwas, cache_bytes, total_bytes })
+}
+
+/// Walks the profile folders, which can take a while on a big cache.
+#[tauri::command]
+pub(crate) async fn get_storage_usage(app: AppHandle) -> Result<StorageUsage, LuminaError> {
+    tauri::async_runtime::spawn_blocking(move || measure(&app))
+        .await
+        .map_err(|e| LuminaError::Other(e.to_string()))?
+}
+
+#[cfg(windows)]
+async fn clear_webview_cache(webview: &tauri::Webview) -> Result<(), String> {
+    crate::screenshot::call_devtools(webview, "Network.clearBrowserCache", "{}").await.map(|_| ())
+}
+
+#[cfg(target_os = "linux")]
+async fn clear_webview_cache(webview: &tauri::Webview) -> Result<(), String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    webview.with_webview(move |webview| {
+        use webkit2gtk::{WebViewExt, WebsiteDataManagerExtManual, WebsiteDataTypes};
+        let Some(manager) = webview.inner().website_data_manager() else {
+            let _ = tx.send(Err("The webview has no data manager".to_string()));
+            return;
+        };
+        let types = WebsiteDataTypes::DISK_CACHE | WebsiteDataTypes::MEMORY_CACHE;
+        manager.clear(types, webkit2gtk::glib::TimeSpan(0), None::<&webkit2gtk::gio::Cancellable>, move |result| {
+            let _ = tx.send(result.map_err(|e| e.to_string()));
+        });
+    }).map_err(|e| e.to_string())?;
+    rx.await.map_err(|_| "The webview dropped the request".to_string())?
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+async fn clear_webview_cache(_webview: &tauri::Webview) -> Result<(), String> {
+    Err("Clearing the cache is not supported on this platform yet".to_string())
+}
+
+/// Empties the HTTP cache of every open profile (the shared one, site profiles, isolated apps),
+/// leaving cookies and site storage alone. Returns the usage afterwards.
+#[tauri::command]
+pub(crate) async fn clear_cache(app: AppHandle) -> Result<StorageUsage, LuminaError> {
+    let mut last_error = None;
+    let mut cleared = false;
+    for (_, webview) in app.webviews() {
+        match clear_webview_cache(&webview).await {
+            Ok(()) => cleared = true,
+            Err(e) => last_error = Some(e),
+        }
+    }
+    if let (false, Some(error)) = (cleared, last_error) {
+        return Err(LuminaError::Webview(error));
+    }
+    get_storage_usage(app).await
+}
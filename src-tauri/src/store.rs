@@ -0,0 +1,444 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use serde::{Serialize, Deserialize};
+use base64::Engine as _;
+
+use crate::history_manager::HistoryManager;
+use crate::events::AppEvent;
+use crate::{data, events, http, local_ai};
+use crate::{ToastPayload, broadcast_dark_mode};
+use crate::downloads::sanitize_filename;
+use crate::error::LuminaError;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct StoreItem {
+    pub id: String,
+    pub title: String,
+    pub author: String,
+    pub description: String,
+    pub icon: String,
+    pub version: String,
+    pub tags: Vec<String>,
+    pub verified: bool,
+    #[serde(default)]
+    pub installed: bool,
+    #[serde(default, rename = "comingSoon")]
+    pub coming_soon: bool,
+    /// Zip archive with the extension's files. Items without one only toggle `installed`.
+    #[serde(default, rename = "packageUrl")]
+    pub package_url: Option<String>,
+    /// Key id in PINNED_PUBLISHER_KEYS that signed the package
+    #[serde(default)]
+    pub publisher: Option<String>,
+    /// Base64 Ed25519 signature over the archive bytes
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// Set when the last install attempt failed signature verification
+    #[serde(default, rename = "verificationFailed")]
+    pub verification_failed: bool,
+    /// Version that was installed; differs from `version` when an update is available
+    #[serde(default, rename = "installedVersion")]
+    pub installed_version: Option<String>,
+    #[serde(default, rename = "readmeUrl")]
+    pub readme_url: Option<String>,
+    #[serde(default, rename = "changelogUrl")]
+    pub changelog_url: Option<String>,
+    #[serde(default)]
+    pub screenshots: Vec<String>,
+    /// Release history, newest first
+    #[serde(default)]
+    pub versions: Vec<StoreVersion>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct StoreVersion {
+    pub version: String,
+    #[serde(default)]
+    pub date: String,
+    #[serde(default)]
+    pub notes: String,
+}
+
+impl StoreItem {
+    pub(crate) fn update_available(&self) -> bool {
+        self.installed && self.installed_version.as_deref().is_some_and(|v| v != self.version)
+    }
+
+    /// Why this build won't install the item, if it won't. Its code has to either ship with Lumina
+    /// or come in a package signed by a pinned publisher; nothing unsigned is installed.
+    pub(crate) fn install_blocker(&self) -> Option<&'static str> {
+        if self.coming_soon {
+            return Some("Package is not available yet");
+        }
+        match &self.package_url {
+            None if !BUILTIN_PACKAGES.contains(&self.id.as_str()) => Some("Package has no signed files to install"),
+            Some(_) if !self.publisher.as_deref().is_some_and(is_pinned_publisher) => Some("Package publisher is not trusted by this build"),
+            _ => None,
+        }
+    }
+}
+
+/// Store detail pages wait on their documents, so these get less time than packages
+const STORE_DOC_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// README/changelog text fetched for store detail pages, keyed by URL
+static STORE_DOC_CACHE: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn store_doc_cache() -> &'static Mutex<HashMap<String, String>> {
+    STORE_DOC_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// A registry document fetched earlier in this run, for pages rendered without waiting on the network.
+pub(crate) fn cached_store_doc(url: &str) -> Option<String> {
+    store_doc_cache().lock().unwrap().get(url).cloned()
+}
+
+/// Fetches a registry document (README, changelog) once per run.
+async fn fetch_store_doc(app: &AppHandle, url: &str) -> Result<String, LuminaError> {
+    if let Some(text) = cached_store_doc(url) {
+        return Ok(text);
+    }
+    let client = http::client(app, http::Purpose::Store)?;
+    let text = client.get(url).timeout(STORE_DOC_TIMEOUT).send().await?.error_for_status()?.text().await?;
+    store_doc_cache().lock().unwrap().insert(url.to_string(), text.clone());
+    Ok(text)
+}
+
+/// Called by a store detail page once it is shown: `kind` is "readme" or "changelog". `None` if
+/// the package has no such document.
+#[tauri::command]
+pub(crate) async fn get_store_doc(app: AppHandle, id: String, kind: String) -> Result<Option<String>, LuminaError> {
+    let item = get_store_items(app.clone()).into_iter().find(|item| item.id == id)
+        .ok_or_else(|| LuminaError::NotFound(format!("Package {} not found", id)))?;
+    let url = match kind.as_str() {
+        "readme" => item.readme_url,
+        "changelog" => item.changelog_url,
+        _ => return Err(LuminaError::InvalidInput(format!("Unknown store document: {}", kind))),
+    };
+    match url {
+        Some(url) => fetch_store_doc(&app, &url).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Publisher id -> base64 Ed25519 public key. Packages signed by anyone else are rejected, so until
+/// a publisher's key is pinned here only the built-in packages can be installed.
+const PINNED_PUBLISHER_KEYS: &[(&str, &str)] = &[];
+
+/// Packages whose code ships with Lumina; installing one only switches it on, so there is nothing to verify.
+const BUILTIN_PACKAGES: &[&str] = &[NIGHT_OWL_PACKAGE, local_ai::LOCAL_BRAIN_PACKAGE];
+
+fn is_pinned_publisher(publisher: &str) -> bool {
+    PINNED_PUBLISHER_KEYS.iter().any(|(id, _)| *id == publisher)
+}
+
+/// Checks the archive against the item's signature and its publisher's pinned key.
+fn verify_package(item: &StoreItem, archive: &[u8]) -> Result<(), String> {
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    let publisher = item.publisher.as_deref().ok_or("Package has no publisher")?;
+    let key = PINNED_PUBLISHER_KEYS
+        .iter()
+        .find(|(id, _)| *id == publisher)
+        .map(|(_, key)| key)
+        .ok_or_else(|| format!("Unknown publisher: {}", publisher))?;
+    let key_bytes: [u8; 32] = base64::engine::general_purpose::STANDARD
+        .decode(key)
+        .ok()
+        .and_then(|k| k.try_into().ok())
+        .ok_or("Malformed publisher key")?;
+    let key = VerifyingKey::from_bytes(&key_bytes).map_err(|e| e.to_string())?;
+
+    let signature_bytes: [u8; 64] = item.signature.as_deref()
+        .and_then(|s| base64::engine::general_purpose::STANDARD.decode(s).ok())
+        .and_then(|s| s.try_into().ok())
+        .ok_or("Package is not signed")?;
+    key.verify(archive, &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| "Signature does not match package".to_string())
+}
+
+/// Unpacks a verified package into app_data/packages/<id>.
+fn unpack_package(app: &AppHandle, id: &str, archive: &[u8]) -> Result<(), String> {
+    let target = app.path().app_data_dir().map_err(|e| e.to_string())?.join("packages").join(sanitize_filename(id));
+    if target.exists() {
+        std::fs::remove_dir_all(&target).map_err(|e| e.to_string())?;
+    }
+    unzip_into(std::io::Cursor::new(archive), &target).map(|_| ())
+}
+
+/// Extracts a zip into `target`, skipping entries that would escape it. Returns the entry count.
+pub(crate) fn unzip_into<R: std::io::Read + std::io::Seek>(reader: R, target: &std::path::Path) -> Result<usize, String> {
+    let mut zip = zip::ZipArchive::new(reader).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(target).map_err(|e| e.to_string())?;
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(|e| e.to_string())?;
+        let Some(relative) = entry.enclosed_name() else { continue };
+        let out_path = target.join(relative);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&out_path).map_err(|e| e.to_string())?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let mut out = std::fs::File::create(&out_path).map_err(|e| e.to_string())?;
+            std::io::copy(&mut entry, &mut out).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(zip.len())
+}
+
+#[tauri::command]
+pub(crate) fn get_store_items(app: AppHandle) -> Vec<StoreItem> {
+    // Prioritize the writable app data store.json so user changes (installs) are reflected
+    let app_data_store = app.path().app_data_dir().unwrap_or_default().join("store.json");
+    
+    let paths = vec![
+        app_data_store, // Check writable path FIRST
+        PathBuf::from("store.json"),
+        PathBuf::from("data/store.json"),
+        PathBuf::from("src-tauri/store.json"),
+    ];
+
+    for path in paths {
+        if path.exists() {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                if let Ok(items) = serde_json::from_str::<Vec<StoreItem>>(&content) {
+                    return items;
+                }
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// Packages a lumina-app://install link asked for, waiting for the user to confirm on the prompt page
+static PENDING_INSTALLS: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn pending_installs() -> &'static Mutex<HashSet<String>> {
+    PENDING_INSTALLS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InstallRequestedPayload {
+    id: String,
+    title: String,
+    author: String,
+    version: String,
+    verified: bool,
+}
+
+/// Starts the confirmation flow for an install link: nothing is installed until `confirm_install`.
+pub(crate) fn request_install(app: &AppHandle, id: &str) -> Result<StoreItem, String> {
+    let item = get_store_items(app.clone()).into_iter().find(|item| item.id == id).ok_or("Package not found")?;
+    if let Some(reason) = item.install_blocker() {
+        return Err(reason.to_string());
+    }
+    pending_installs().lock().unwrap().insert(item.id.clone());
+    events::emit(app, AppEvent::InstallRequested(InstallRequestedPayload {
+        id: item.id.clone(),
+        title: item.title.clone(),
+        author: item.author.clone(),
+        version: item.version.clone(),
+        verified: item.verified,
+    }));
+    Ok(item)
+}
+
+#[tauri::command]
+pub(crate) async fn confirm_install(app: AppHandle, id: String) -> Result<(), LuminaError> {
+    if !pending_installs().lock().unwrap().remove(&id) {
+        return Err(LuminaError::InvalidInput(format!("No install of {} is waiting for confirmation", id)));
+    }
+    println!("Lumina Store: Installing {} (confirmed)", id);
+    install_store_item(&app, &id).await.map_err(LuminaError::from)?;
+    events::emit(&app, AppEvent::Toast(ToastPayload {
+        message: format!("Modül başarıyla kuruldu: {}", id),
+        level: "success".to_string(),
+    }));
+    Ok(())
+}
+
+async fn install_store_item(app: &AppHandle, id: &str) -> Result<(), String> {
+    // 1. Determine Store Path (Writable)
+    let app_data_dir = app.path().app_data_dir().unwrap_or(PathBuf::from("."));
+    if !app_data_dir.exists() {
+        let _ = std::fs::create_dir_all(&app_data_dir);
+    }
+    let store_path = app_data_dir.join("store.json");
+
+    // 2. Load Existing Items (writable copy first, then the bundled one)
+    let mut items = get_store_items(app.clone());
+    let item = items.iter_mut().find(|item| item.id == id).ok_or("Package not found")?;
+    if let Some(reason) = item.install_blocker() {
+        return Err(reason.to_string());
+    }
+
+    // 3. Fetch, verify and unpack the package, if it ships files
+    let result = match item.package_url.clone() {
+        Some(package_url) => {
+            let client = http::client(app, http::Purpose::Store).map_err(|e| e.to_string())?;
+            let archive = http::get_with_retry(&client, &package_url).await
+                .and_then(|res| res.error_for_status())
+                .map_err(|e| e.to_string())?
+                .bytes().await
+                .map_err(|e| e.to_string())?;
+            verify_package(item, &archive)
+                .inspect_err(|_| item.verification_failed = true)
+                .and_then(|_| unpack_package(app, id, &archive))
+        }
+        None => Ok(()),
+    };
+    if result.is_ok() {
+        item.installed = true;
+        item.installed_version = Some(item.version.clone());
+        item.verification_failed = false;
+        if id == NIGHT_OWL_PACKAGE {
+            set_night_owl(app, true);
+        }
+    }
+
+    // 4. Save to Writable Path
+    save_store_items(&store_path, &items)?;
+    // Local Brain's model is too big for a package; it's fetched once the package is marked installed
+    if result.is_ok() && id == local_ai::LOCAL_BRAIN_PACKAGE {
+        local_ai::start_model_download(app);
+    }
+    result
+}
+
+fn save_store_items(store_path: &std::path::Path, items: &[StoreItem]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(items).map_err(|e| e.to_string())?;
+    data::write_atomic(store_path, json.as_bytes()).map_err(|e| format!("Failed to write store.json: {}", e))
+}
+
+/// Removes a package's files and marks it as not installed.
+pub(crate) fn perform_uninstall(app: &AppHandle, id: &str) -> Result<(), String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let mut items = get_store_items(app.clone());
+    let item = items.iter_mut().find(|item| item.id == id).ok_or("Package not found")?;
+    if !item.installed {
+        return Err("Package is not installed".to_string());
+    }
+
+    let package_dir = app_data_dir.join("packages").join(sanitize_filename(id));
+    if package_dir.exists() {
+        std::fs::remove_dir_all(&package_dir).map_err(|e| e.to_string())?;
+    }
+    item.installed = false;
+    item.installed_version = None;
+    save_store_items(&app_data_dir.join("store.json"), &items)?;
+    if id == NIGHT_OWL_PACKAGE {
+        set_night_owl(app, false);
+    }
+    if id == local_ai::LOCAL_BRAIN_PACKAGE {
+        local_ai::remove_model(app);
+    }
+    Ok(())
+}
+
+/// The "Night Owl" store package is backed by the built-in dark mode module.
+const NIGHT_OWL_PACKAGE: &str = "night-owl";
+
+fn set_night_owl(app: &AppHandle, enabled: bool) {
+    let _ = app.state::<HistoryManager>().set_meta("dark_mode_global", if enabled { "1" } else { "0" });
+    broadcast_dark_mode(app);
+}
+
+#[tauri::command]
+pub(crate) fn uninstall_package(app: AppHandle, id: String) -> Result<(), LuminaError> {
+    perform_uninstall(&app, &id).map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) async fn install_package(app: AppHandle, id: String) -> Result<(), LuminaError> {
+    println!("Lumina Command: Installing {}", id);
+    
+    // The UI shows the error, so it isn't reported here as well
+    install_store_item(&app, &id).await.map_err(|e| LuminaError::Other(format!("Kurulum başarısız ({}): {}", id, e)))?;
+    events::emit(&app, AppEvent::Toast(ToastPayload {
+        message: format!("Modül başarıyla kuruldu: {}", id),
+        level: "success".to_string(),
+    }));
+    Ok(())
+}
+
+/// Shared by the store grid and package detail pages
+pub(crate) const STORE_CSS: &str = r#"
+                body { font-family: 'Segoe UI', system-ui, sans-serif; background: var(--bg); color: var(--text); margin: 0; padding: 0; }
+                .container { max-width: 1000px; margin: 0 auto; padding: 40px 20px; }
+                header { display: flex; align-items: center; justify-content: space-between; margin-bottom: 40px; border-bottom: 1px solid var(--border); padding-bottom: 20px; }
+                h1 { margin: 0; font-size: 2.5rem; background: linear-gradient(to right, var(--primary), #10b981); -webkit-background-clip: text; -webkit-text-fill-color: transparent; }
+                .tagline { color: var(--text-dim); font-size: 1.1rem; }
+                .grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(300px, 1fr)); gap: 24px; }
+                .card { background: var(--card); border: 1px solid var(--border); border-radius: 12px; padding: 24px; transition: transform 0.2s, border-color 0.2s; position: relative; overflow: hidden; }
+                .card:hover { transform: translateY(-4px); border-color: var(--primary); }
+                .card-header { display: flex; align-items: center; gap: 12px; margin-bottom: 16px; }
+                .icon { width: 48px; height: 48px; background: var(--surface); border-radius: 10px; display: flex; align-items: center; justify-content: center; font-size: 24px; }
+                .card h3 { margin: 0; font-size: 1.25rem; color: var(--text-strong); }
+                .author { font-size: 0.875rem; color: var(--text-dim); margin-top: 4px; }
+                .desc { color: var(--text); line-height: 1.5; margin-bottom: 20px; font-size: 0.95rem; }
+                .meta { display: flex; gap: 12px; font-size: 0.8rem; color: var(--text-dim); margin-bottom: 20px; }
+                .tag { background: var(--surface); padding: 2px 8px; border-radius: 4px; color: var(--text-dim); }
+                .btn { display: block; text-align: center; background: var(--primary); color: white; text-decoration: none; padding: 10px; border-radius: 8px; font-weight: 600; transition: background 0.2s; }
+                .btn:hover { filter: brightness(0.9); }
+                .btn.installed { background: #10b981; pointer-events: none; opacity: 0.8; }
+                .badge-verified { color: #10b981; display: inline-flex; align-items: center; gap: 4px; font-size: 0.8rem; margin-left: auto; }
+                .card.coming-soon { opacity: 0.7; border-style: dashed; }
+                .badge-failed { color: #ef4444; font-size: 0.8rem; margin-left: auto; }
+                .btn.failed { background: #ef4444; }
+                .btn.disabled { background: #475569; cursor: not-allowed; pointer-events: none; }
+                .empty { color: var(--text-dim); text-align: center; padding: 40px; grid-column: 1 / -1; }
+                .card h3 a { color: inherit; text-decoration: none; }
+                .card h3 a:hover { color: var(--primary); }
+                .back { color: var(--text-dim); text-decoration: none; display: inline-block; margin-bottom: 20px; }
+                .detail-header { display: flex; align-items: center; gap: 20px; margin-bottom: 24px; }
+                .detail-header .icon { width: 72px; height: 72px; font-size: 36px; }
+                .detail-actions { display: flex; gap: 12px; margin-bottom: 32px; }
+                .detail-actions .btn { padding: 10px 24px; }
+                .btn.secondary { background: var(--surface); }
+                .btn.danger { background: #ef4444; }
+                .section { background: var(--card); border: 1px solid var(--border); border-radius: 12px; padding: 24px; margin-bottom: 24px; }
+                .section h2 { margin: 0 0 16px 0; font-size: 1.2rem; color: var(--text-strong); }
+                .doc { white-space: pre-wrap; font-family: inherit; color: var(--text); line-height: 1.6; margin: 0; }
+                .screenshots { display: flex; gap: 12px; overflow-x: auto; }
+                .screenshots img { max-height: 240px; border-radius: 8px; border: 1px solid var(--border); }
+                .version { border-bottom: 1px solid var(--border); padding: 10px 0; }
+                .version:last-child { border-bottom: none; }
+            "#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, package_url: Option<&str>, publisher: Option<&str>) -> StoreItem {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": id,
+            "author": "@test",
+            "description": "",
+            "icon": "",
+            "version": "1.0.0",
+            "tags": [],
+            "verified": false,
+            "packageUrl": package_url,
+            "publisher": publisher,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn builtin_packages_install_without_files() {
+        assert_eq!(item(NIGHT_OWL_PACKAGE, None, None).install_blocker(), None);
+        assert_eq!(item(local_ai::LOCAL_BRAIN_PACKAGE, None, None).install_blocker(), None);
+    }
+
+    #[test]
+    fn unsigned_and_unpinned_packages_are_refused() {
+        assert!(item("adshield", None, None).install_blocker().is_some());
+        assert!(item("adshield", Some("https://example.com/a.zip"), None).install_blocker().is_some());
+        assert!(item("adshield", Some("https://example.com/a.zip"), Some("nobody")).install_blocker().is_some());
+    }
+}
@@ -0,0 +1,287 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::data::SessionTab;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TabRecord {
+    pub label: String,
+    /// Browser window the tab is in
+    pub window: String,
+    pub url: String,
+    pub title: String,
+    pub favicon: Option<String>,
+    pub created_at: i64,
+    pub group: Option<String>,
+    pub pinned: bool,
+    /// Pop-ups blocked since the tab last navigated
+    pub blocked_popups: u32,
+    pub last_blocked_popup: Option<String>,
+    /// Incognito: left out of the saved session, and its downloads out of downloads.json
+    pub private: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenTabs {
+    pub tabs: Vec<TabRecord>,
+    pub active_tab: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigationHistory {
+    pub entries: Vec<String>,
+    pub index: usize,
+    pub can_go_back: bool,
+    pub can_go_forward: bool,
+}
+
+/// Mirror of a tab's back/forward list, built from the top-level loads that finished. The engine keeps
+/// the real list (with pushState entries this can't see), so this is for showing it, not steering it.
+#[derive(Default)]
+struct NavStack {
+    entries: Vec<String>,
+    index: usize,
+    /// Entry a back/forward/go() request is heading to, so its navigation isn't pushed as new
+    pending: Option<usize>,
+}
+
+impl NavStack {
+    fn record(&mut self, url: &str) {
+        if let Some(target) = self.pending.take() {
+            if self.entries.get(target).is_some_and(|e| e == url) {
+                self.index = target;
+                return;
+            }
+        }
+        if self.entries.is_empty() {
+            self.entries.push(url.to_string());
+            self.index = 0;
+            return;
+        }
+        if self.entries[self.index] == url {
+            return;
+        }
+        // The page went back or forward on its own
+        if self.index > 0 && self.entries[self.index - 1] == url {
+            self.index -= 1;
+            return;
+        }
+        if self.entries.get(self.index + 1).is_some_and(|e| e == url) {
+            self.index += 1;
+            return;
+        }
+        self.entries.truncate(self.index + 1);
+        self.entries.push(url.to_string());
+        self.index += 1;
+    }
+
+    fn to_history(&self) -> NavigationHistory {
+        NavigationHistory {
+            entries: self.entries.clone(),
+            index: self.index,
+            can_go_back: self.index > 0,
+            can_go_forward: self.index + 1 < self.entries.len(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    /// Tabs in strip order
+    tabs: Vec<TabRecord>,
+    /// Active tab of each browser window
+    active: HashMap<String, String>,
+    navigation: HashMap<String, NavStack>,
+}
+
+/// Called with the closed tab and the tabs still open
+type CloseHook = Box<dyn Fn(&TabRecord, &[TabRecord]) + Send + Sync>;
+
+/// Backend copy of every open tab, kept current from tab lifecycle and page events.
+#[derive(Default)]
+pub struct TabRegistry {
+    inner: Mutex<Registry>,
+    close_hooks: Mutex<Vec<CloseHook>>,
+}
+
+impl TabRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, label: &str, url: &str, window: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.tabs.iter().any(|t| t.label == label) {
+            return;
+        }
+        inner.tabs.push(TabRecord {
+            label: label.to_string(),
+            window: window.to_string(),
+            url: url.to_string(),
+            title: String::new(),
+            favicon: None,
+            created_at: chrono::Utc::now().timestamp(),
+            group: None,
+            pinned: false,
+            blocked_popups: 0,
+            last_blocked_popup: None,
+            private: false,
+        });
+    }
+
+    /// Runs `hook` after every tab removed from the registry, outside its lock.
+    pub fn on_close(&self, hook: impl Fn(&TabRecord, &[TabRecord]) + Send + Sync + 'static) {
+        self.close_hooks.lock().unwrap().push(Box::new(hook));
+    }
+
+    pub fn remove(&self, label: &str) {
+        let Some((closed, open)) = self.take(label) else { return };
+        for hook in self.close_hooks.lock().unwrap().iter() {
+            hook(&closed, &open);
+        }
+    }
+
+    /// Removes a tab the user didn't close: its page lives on in a window of its own, or the browser
+    /// is going away with it. The close hooks don't run.
+    pub fn detach(&self, label: &str) {
+        self.take(label);
+    }
+
+    fn take(&self, label: &str) -> Option<(TabRecord, Vec<TabRecord>)> {
+        let mut inner = self.inner.lock().unwrap();
+        let closed = inner.tabs.iter().position(|t| t.label == label).map(|index| inner.tabs.remove(index));
+        inner.navigation.remove(label);
+        inner.active.retain(|_, active| active != label);
+        Some((closed?, inner.tabs.clone()))
+    }
+
+    fn update(&self, label: &str, f: impl FnOnce(&mut TabRecord)) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.tabs.iter_mut().find(|t| t.label == label) {
+            Some(tab) => {
+                f(tab);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_url(&self, label: &str, url: &str) {
+        self.update(label, |t| {
+            if t.url != url {
+                t.blocked_popups = 0;
+                t.last_blocked_popup = None;
+            }
+            t.url = url.to_string();
+        });
+    }
+
+    /// Counts a blocked pop-up against the tab and returns the new total.
+    pub fn record_blocked_popup(&self, label: &str, url: &str) -> u32 {
+        let mut count = 0;
+        self.update(label, |t| {
+            t.blocked_popups += 1;
+            t.last_blocked_popup = Some(url.to_string());
+            count = t.blocked_popups;
+        });
+        count
+    }
+
+    /// Clears the tab's blocked pop-ups, handing back the most recent one.
+    pub fn take_blocked_popup(&self, label: &str) -> Option<String> {
+        let mut last = None;
+        self.update(label, |t| {
+            t.blocked_popups = 0;
+            last = t.last_blocked_popup.take();
+        });
+        last
+    }
+
+    /// Returns whether the title changed.
+    pub fn set_title(&self, label: &str, title: &str) -> bool {
+        let mut changed = false;
+        self.update(label, |t| {
+            changed = t.title != title;
+            t.title = title.to_string();
+        });
+        changed
+    }
+
+    /// Returns whether the favicon changed.
+    pub fn set_favicon(&self, label: &str, favicon: &str) -> bool {
+        let mut changed = false;
+        self.update(label, |t| {
+            changed = t.favicon.as_deref() != Some(favicon);
+            t.favicon = Some(favicon.to_string());
+        });
+        changed
+    }
+
+    pub fn set_pinned(&self, label: &str, pinned: bool) -> bool {
+        self.update(label, |t| t.pinned = pinned)
+    }
+
+    pub fn set_private(&self, label: &str, private: bool) -> bool {
+        self.update(label, |t| t.private = private)
+    }
+
+    pub fn is_private(&self, label: &str) -> bool {
+        self.inner.lock().unwrap().tabs.iter().any(|t| t.label == label && t.private)
+    }
+
+    pub fn set_group(&self, label: &str, group: Option<String>) -> bool {
+        self.update(label, |t| t.group = group)
+    }
+
+    /// Records a finished top-level load and returns the tab's updated back/forward state.
+    pub fn record_navigation(&self, label: &str, url: &str) -> NavigationHistory {
+        let mut inner = self.inner.lock().unwrap();
+        let stack = inner.navigation.entry(label.to_string()).or_default();
+        stack.record(url);
+        stack.to_history()
+    }
+
+    pub fn navigation_history(&self, label: &str) -> Option<NavigationHistory> {
+        self.inner.lock().unwrap().navigation.get(label).map(NavStack::to_history)
+    }
+
+    /// Marks `index` as where the next navigation lands; returns the history.go() delta to get there.
+    pub fn begin_history_jump(&self, label: &str, index: usize) -> Option<i64> {
+        let mut inner = self.inner.lock().unwrap();
+        let stack = inner.navigation.get_mut(label)?;
+        if index >= stack.entries.len() || index == stack.index {
+            return None;
+        }
+        stack.pending = Some(index);
+        Some(index as i64 - stack.index as i64)
+    }
+
+    pub fn set_active(&self, label: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(window) = inner.tabs.iter().find(|t| t.label == label).map(|t| t.window.clone()) {
+            inner.active.insert(window, label.to_string());
+        }
+    }
+
+    /// The tabs of one browser window, or of all of them with the active tab of "main".
+    pub fn snapshot(&self, window: Option<&str>) -> OpenTabs {
+        let inner = self.inner.lock().unwrap();
+        let tabs = inner.tabs.iter().filter(|t| window.is_none_or(|w| t.window == w)).cloned().collect();
+        OpenTabs { tabs, active_tab: inner.active.get(window.unwrap_or("main")).cloned() }
+    }
+
+    /// What gets written as the session: pinned tabs first, then strip order.
+    pub fn session_tabs(&self) -> Vec<SessionTab> {
+        let inner = self.inner.lock().unwrap();
+        let (pinned, rest): (Vec<_>, Vec<_>) = inner.tabs.iter().filter(|t| !t.private).partition(|t| t.pinned);
+        pinned
+            .into_iter()
+            .chain(rest)
+            .map(|t| SessionTab { label: t.label.clone(), url: t.url.clone() })
+            .collect()
+    }
+}
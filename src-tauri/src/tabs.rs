@@ -0,0 +1,1717 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager, WebviewUrl, Url};
+use serde::Serialize;
+
+use crate::history_manager::HistoryManager;
+use crate::tab_registry::TabRegistry;
+use crate::events::AppEvent;
+use crate::data::AppDataStore;
+use crate::{adblock, crash_recovery, data_viewer, error, events, header_rules, ipc_guard, isolation, local_files, media_capture, network, notes, notifications, pwa, referrer, site_prompts, spellcheck, tab_registry, zoom};
+use crate::{apply_site_fixups, apply_site_protection, dark_mode_config, get_dark_mode_script, get_fingerprint_protection_script, get_internal_page_html, get_lumina_stealth_script, seal_internal_page, to_script_json};
+use crate::adblock::{blocked_request_log, forget_blocked_count, get_cosmetic_filter_script, handle_adblock_request};
+use crate::downloads::{enqueue_download, filename_from_url};
+use crate::layout::{is_browser_window, set_tab_fullscreen, tab_window, watch_fullscreen, LayoutManager, UiState};
+use crate::error::LuminaError;
+
+// Tab hibernation bookkeeping: when each tab was last shown, and the URL of every hibernated tab
+static TAB_LAST_ACTIVE: OnceLock<Mutex<HashMap<String, std::time::Instant>>> = OnceLock::new();
+static HIBERNATED_TABS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+const TAB_HIBERNATE_AFTER: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+pub(crate) const TAB_HIBERNATE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+fn tab_last_active() -> &'static Mutex<HashMap<String, std::time::Instant>> {
+    TAB_LAST_ACTIVE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn hibernated_tabs() -> &'static Mutex<HashMap<String, String>> {
+    HIBERNATED_TABS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// SPA pages change their head constantly; tab-updated goes out at most this often per tab
+const TAB_UPDATE_MIN_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+/// Page titles are written to history together, this long after the first one comes in
+const HISTORY_TITLE_FLUSH_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+/// How long page loads are collected before the session is written
+const SESSION_SAVE_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+/// Set while a session save is waiting out SESSION_SAVE_DELAY
+static SESSION_SAVE_QUEUED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+/// Set once the last browser window is gone or the app is quitting: the tabs going away then are the
+/// session to restore, so it isn't written again until a tab opens
+static SESSION_FROZEN: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+#[derive(Default)]
+struct TabUpdateThrottle {
+    last_sent: Option<std::time::Instant>,
+    /// A trailing tab-updated is already on its way
+    scheduled: bool,
+}
+
+static TAB_UPDATE_THROTTLE: OnceLock<Mutex<HashMap<String, TabUpdateThrottle>>> = OnceLock::new();
+/// History titles waiting for the next flush, by URL
+static PENDING_HISTORY_TITLES: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn tab_update_throttle() -> &'static Mutex<HashMap<String, TabUpdateThrottle>> {
+    TAB_UPDATE_THROTTLE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn pending_history_titles() -> &'static Mutex<HashMap<String, String>> {
+    PENDING_HISTORY_TITLES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Redirect/link-shim endpoints: host (exact, or any subdomain when it starts with '.'), path, and
+/// the query parameter carrying the real destination.
+const LINK_SHIMS: &[(&str, &str, &str)] = &[
+    ("l.facebook.com", "/l.php", "u"),
+    ("lm.facebook.com", "/l.php", "u"),
+    ("l.messenger.com", "/l.php", "u"),
+    ("l.instagram.com", "/", "u"),
+    ("www.google.com", "/url", "q"),
+    ("www.google.com", "/url", "url"),
+    ("www.youtube.com", "/redirect", "q"),
+    (".safelinks.protection.outlook.com", "/", "url"),
+];
+
+/// The destination a shim link points at, following nested shims a few levels deep.
+fn unwrap_link_shim(url: &Url) -> Option<Url> {
+    let mut current = url.clone();
+    let mut unwrapped = false;
+    for _ in 0..3 {
+        let host = current.host_str().unwrap_or("");
+        let target = LINK_SHIMS
+            .iter()
+            .filter(|(shim_host, path, _)| {
+                let host_matches = if shim_host.starts_with('.') { host.ends_with(shim_host) } else { host == *shim_host };
+                host_matches && current.path() == *path
+            })
+            .find_map(|(_, _, param)| current.query_pairs().find(|(k, _)| k == param).map(|(_, v)| v.into_owned()))
+            .and_then(|v| Url::parse(&v).ok())
+            .filter(|u| matches!(u.scheme(), "http" | "https"));
+        match target {
+            Some(target) => {
+                current = target;
+                unwrapped = true;
+            }
+            None => break,
+        }
+    }
+    unwrapped.then_some(current)
+}
+
+/// Schemes the webview loads itself. Anything else is handed to the OS.
+const INTERNAL_SCHEMES: &[&str] = &["http", "https", "lumina-app", "about", "data", "blob", "file", "javascript", "tauri", "asset", "ipc"];
+
+fn is_external_scheme(scheme: &str) -> bool {
+    !INTERNAL_SCHEMES.contains(&scheme)
+}
+
+fn normalize_scheme(scheme: &str) -> Option<String> {
+    let scheme = scheme.trim().trim_end_matches(':').to_lowercase();
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    (valid && is_external_scheme(&scheme)).then_some(scheme)
+}
+
+fn open_external_url(app: &AppHandle, url: &str) {
+    use tauri_plugin_opener::OpenerExt;
+
+    if let Err(e) = app.opener().open_url(url, None::<&str>) {
+        println!("Failed to open external link {}: {}", url, e);
+    }
+}
+
+/// Applies the stored per-scheme choice, asking in the infobar when there is none. Only the URL
+/// intercepted here is ever opened; the page has no say in the answer.
+fn handle_external_protocol(app: &AppHandle, label: &str, url: &Url) {
+    let action = app.state::<AppDataStore>().protocol_action(url.scheme());
+    match action.as_str() {
+        "allow" => open_external_url(app, url.as_str()),
+        "deny" => println!("External link blocked by settings: {}", url.scheme()),
+        _ => {
+            let (app, label, url) = (app.clone(), label.to_string(), url.clone());
+            tauri::async_runtime::spawn(async move {
+                let origin = webview_origin(&app, &label).unwrap_or_default();
+                let message = format!("Open {}: link with an external application? {}", url.scheme(), url);
+                let Some(answer) = site_prompts::ask(&app, &label, "external-protocol", &origin, message, true).await else { return };
+                if answer.remember {
+                    if let Some(scheme) = normalize_scheme(url.scheme()) {
+                        let state = app.state::<AppDataStore>();
+                        state.set_protocol_action(scheme, if answer.allow { "allow" } else { "deny" }.to_string());
+                        state.save();
+                    }
+                }
+                if answer.allow {
+                    open_external_url(&app, url.as_str());
+                }
+            });
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PopupBlockedPayload {
+    label: String,
+    url: String,
+    origin: String,
+    count: u32,
+}
+
+/// Opens a page a site asked for (window.open, target=_blank) as a regular tab.
+fn open_popup_tab(app: &AppHandle, opener: &str, url: String) {
+    let app = app.clone();
+    let window_label = tab_window(&app, opener).unwrap_or_else(|| "main".to_string());
+    tauri::async_runtime::spawn(async move {
+        let Some(window) = app.get_window(&window_label) else { return };
+        let label = format!("tab-{}", chrono::Utc::now().timestamp_micros());
+        if let Err(e) = create_tab(app.state::<UiState>(), app.clone(), app.state::<AppDataStore>(), label, Some(url), window).await {
+            error::report(&app, "Açılır pencere sekmede açılamadı", &e);
+        }
+    });
+}
+
+fn block_popup(app: &AppHandle, label: &str, url: &str, origin: &str) {
+    println!("Pop-up blocked on {}: {}", origin, url);
+    let count = app.state::<TabRegistry>().record_blocked_popup(label, url);
+    events::emit(app, AppEvent::PopupBlocked(PopupBlockedPayload {
+        label: label.to_string(),
+        url: url.to_string(),
+        origin: origin.to_string(),
+        count,
+    }));
+}
+
+/// New-window policy for tabs. Pop-ups become tabs when the engine says the user opened them (a
+/// click or key press in the page), or the origin is on the allow list; anything else is blocked and counted.
+fn handle_popup_request(app: &AppHandle, label: &str, url: &Url, user_initiated: bool) {
+    // Internal pages have an opaque origin and are always trusted
+    let Some(origin) = webview_origin(app, label) else {
+        open_popup_tab(app, label, url.to_string());
+        return;
+    };
+    let stored = app.state::<HistoryManager>().get_site_permission(&origin, "popups").ok().flatten();
+    let blocking = app.state::<AppDataStore>().data.lock().unwrap().settings.block_popups;
+    let allowed = match stored.as_deref() {
+        Some("allow") => true,
+        Some(_) => false,
+        None => !blocking || user_initiated,
+    };
+    if allowed {
+        open_popup_tab(app, label, url.to_string());
+    } else {
+        block_popup(app, label, url.as_str(), &origin);
+    }
+}
+
+/// Takes a tab's new-window requests from WebView2's NewWindowRequested, which knows whether the
+/// user started them. WebView2 opens nothing itself; allowed pop-ups become tabs.
+#[cfg(windows)]
+fn watch_new_windows(app: &AppHandle, webview: &tauri::Webview) {
+    let app = app.clone();
+    let label = webview.label().to_string();
+    let _ = webview.with_webview(move |webview| {
+        use webview2_com::{take_pwstr, NewWindowRequestedEventHandler};
+        use windows_core::PWSTR;
+
+        let handler = NewWindowRequestedEventHandler::create(Box::new(move |_, args| {
+            let Some(args) = args else { return Ok(()) };
+            unsafe { args.SetHandled(true)? };
+            let mut uri = PWSTR::null();
+            unsafe { args.Uri(&mut uri)? };
+            let mut user_initiated = windows_core::BOOL::default();
+            unsafe { args.IsUserInitiated(&mut user_initiated)? };
+            if let Ok(url) = Url::parse(&take_pwstr(uri)) {
+                handle_popup_request(&app, &label, &url, user_initiated.as_bool());
+            }
+            Ok(())
+        }));
+        unsafe {
+            if let Ok(core) = webview.controller().CoreWebView2() {
+                let mut token = 0i64;
+                let _ = core.add_NewWindowRequested(&handler, &mut token);
+            }
+        }
+    });
+}
+
+/// Takes a tab's new-window requests from WebKitGTK's create signal, which knows whether the user
+/// started them. Scripts may ask for windows on their own so those reach the policy and get counted;
+/// WebKit opens nothing itself, allowed pop-ups become tabs.
+#[cfg(target_os = "linux")]
+fn watch_new_windows(app: &AppHandle, webview: &tauri::Webview) {
+    let app = app.clone();
+    let label = webview.label().to_string();
+    let _ = webview.with_webview(move |webview| {
+        use webkit2gtk::{SettingsExt, URIRequestExt, WebViewExt};
+
+        let inner = webview.inner();
+        if let Some(settings) = WebViewExt::settings(&inner) {
+            settings.set_javascript_can_open_windows_automatically(true);
+        }
+        inner.connect_create(move |_, action| {
+            let uri = action.request().and_then(|request| request.uri());
+            if let Some(url) = uri.and_then(|uri| Url::parse(&uri).ok()) {
+                handle_popup_request(&app, &label, &url, action.is_user_gesture());
+            }
+            None
+        });
+    });
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn watch_new_windows(_app: &AppHandle, _webview: &tauri::Webview) {}
+
+/// Adds the tab's site to the pop-up allow list and opens the pop-up that was last blocked there.
+#[tauri::command]
+pub(crate) fn allow_popups(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, label: String) -> Result<(), LuminaError> {
+    let origin = webview_origin(&app, &label).ok_or("Tab has no origin")?;
+    history_manager.set_site_permission(&origin, "popups", "allow")?;
+    if let Some(url) = app.state::<TabRegistry>().take_blocked_popup(&label) {
+        open_popup_tab(&app, &label, url);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn get_protocol_handlers(state: tauri::State<'_, AppDataStore>) -> std::collections::BTreeMap<String, String> {
+    state.data.lock().unwrap().settings.protocol_handlers.clone()
+}
+
+#[tauri::command]
+pub(crate) fn set_protocol_handler(state: tauri::State<'_, AppDataStore>, scheme: String, action: String) -> Result<(), LuminaError> {
+    let scheme = normalize_scheme(&scheme).ok_or("Invalid scheme")?;
+    if !matches!(action.as_str(), "allow" | "deny" | "ask") {
+        return Err(LuminaError::InvalidInput(format!("Unknown action: {}", action)));
+    }
+    state.set_protocol_action(scheme, action);
+    state.save();
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn remove_protocol_handler(state: tauri::State<'_, AppDataStore>, scheme: String) -> Result<(), LuminaError> {
+    state.remove_protocol_action(&scheme.trim().trim_end_matches(':').to_lowercase());
+    state.try_save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn navigate(app: AppHandle, label: String, url: String) -> Result<(), LuminaError> {
+    // println!("Rust: navigating tab {} to {}", label, url);
+    // Try to find the webview. If not found, it might be because it was JUST created and not yet in the map.
+    // In Tauri v2, add_child returns the webview instance.
+    // But navigate is a separate command called from JS, so it relies on AppHandle lookup.
+    
+    let mut webview = app.get_webview(&label);
+    if webview.is_none() {
+        // Retry logic for race conditions - Increased to 10x 100ms (1s total)
+        for i in 0..10 {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            webview = app.get_webview(&label);
+            if webview.is_some() { 
+                println!("Rust: webview {} found after retry {}", label, i+1);
+                break; 
+            }
+        }
+    }
+
+    let Some(webview) = webview else {
+        return Err(LuminaError::NotFound(format!("No open tab '{}'", label)));
+    };
+    webview.set_focus()?;
+
+    // Rewrite lumina:// to lumina-app://localhost/ (standardized) for internal navigation
+    let target_url = if url.starts_with("lumina://") {
+        url.replace("lumina://", "lumina-app://localhost/")
+    } else if url.starts_with("lumina-app://") {
+        if !url.contains("lumina-app://localhost/") {
+            url.replace("lumina-app://", "lumina-app://localhost/")
+        } else {
+            url.clone()
+        }
+    } else {
+        url.clone()
+    };
+
+    println!("Rust: navigating tab {} to {}", label, target_url); // DEBUG LOG
+
+    // Use eval for navigation
+    webview.eval(format!("window.location.assign({})", to_script_json(&target_url)))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn force_internal_navigate(app: AppHandle, label: String, mut url: String) -> Result<(), LuminaError> {
+    println!("Rust: force_internal_navigate tab {} to {}", label, url);
+
+    // Standardize URL to ensure same-origin (lumina-app://localhost/)
+    if url.starts_with("lumina://") {
+        url = url.replace("lumina://", "lumina-app://localhost/");
+    } else if url.starts_with("lumina-app://") {
+         let scheme = "lumina-app://";
+         if let Some(rest) = url.strip_prefix(scheme) {
+             if !rest.starts_with("localhost/") && rest != "localhost" {
+                 // Convert lumina-app://page to lumina-app://localhost/page
+                 url = format!("{}localhost/{}", scheme, rest);
+             }
+         }
+    }
+
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("No open tab '{}'", label)))?;
+    webview.set_focus()?;
+    // Check if it's an internal page
+    let mut internal_html = None;
+
+    if url.starts_with("lumina-app://") || url.starts_with("lumina://") {
+        let scheme_strip = if url.starts_with("lumina-app:") { "lumina-app:" } else { "lumina:" };
+        let without_scheme = url.strip_prefix(scheme_strip).unwrap_or(&url);
+        let without_slashes = without_scheme.trim_start_matches('/');
+        let path_and_query = without_slashes.strip_prefix("localhost").unwrap_or(without_slashes);
+        let full_path = path_and_query.trim_start_matches('/');
+
+        // Split path and query/hash
+        let (path, query) = if let Some(idx) = full_path.find('?') {
+            (&full_path[..idx], &full_path[idx..])
+        } else if let Some(idx) = full_path.find('#') {
+            (&full_path[..idx], &full_path[idx..])
+        } else {
+            (full_path, "")
+        };
+
+        internal_html = get_internal_page_html(&app, path.trim_end_matches('/'), query);
+    }
+
+    if let Some((html, _)) = internal_html.as_deref().map(seal_internal_page) {
+        // The document keeps the previous page's origin, so its commands are trusted by label until the next navigation
+        ipc_guard::set_internal_document(&label, true);
+        let js = format!(
+            "window.stop(); document.open(); document.write({}); document.close(); try {{ history.pushState(null, '', {}); }} catch(e) {{ console.warn('PushState failed (likely origin mismatch), but content loaded:', e); }}",
+            to_script_json(&html), to_script_json(&url)
+        );
+        webview.eval(&js)?;
+    } else {
+        // Fallback for external URLs or if parsing failed
+        webview.eval(format!("window.location.replace({})", to_script_json(&url)))?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NavigationStatePayload {
+    label: String,
+    can_go_back: bool,
+    can_go_forward: bool,
+}
+
+fn emit_navigation_state(app: &AppHandle, label: &str, history: &tab_registry::NavigationHistory) {
+    events::emit(app, AppEvent::NavigationState(NavigationStatePayload {
+        label: label.to_string(),
+        can_go_back: history.can_go_back,
+        can_go_forward: history.can_go_forward,
+    }));
+}
+
+// The engine owns the back/forward list (pushState entries included), so it decides where these go
+#[tauri::command]
+pub(crate) fn go_back(app: AppHandle, label: String) -> Result<(), LuminaError> {
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("No open tab '{}'", label)))?;
+    webview.eval("window.history.back()").map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn go_forward(app: AppHandle, label: String) -> Result<(), LuminaError> {
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("No open tab '{}'", label)))?;
+    webview.eval("window.history.forward()").map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn get_navigation_history(registry: tauri::State<'_, TabRegistry>, label: String) -> Result<tab_registry::NavigationHistory, LuminaError> {
+    registry.navigation_history(&label).ok_or_else(|| LuminaError::NotFound(format!("No open tab '{}'", label)))
+}
+
+#[tauri::command]
+pub(crate) fn go_to_history_index(app: AppHandle, label: String, index: usize) -> Result<(), LuminaError> {
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("No open tab '{}'", label)))?;
+    let Some(delta) = app.state::<TabRegistry>().begin_history_jump(&label, index) else {
+        return Err(LuminaError::NotFound(format!("No history entry {} to go to", index)));
+    };
+    webview.eval(format!("window.history.go({})", delta)).map_err(LuminaError::from)
+}
+
+#[tauri::command]
+pub(crate) fn refresh(app: AppHandle, label: String) -> Result<(), LuminaError> {
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("No open tab '{}'", label)))?;
+    webview.reload().map_err(LuminaError::from)
+}
+
+/// Text selected on the page, including inside text fields, which `getSelection` leaves out.
+const SELECTION_SCRIPT: &str = r#"
+    (function() {
+        const el = document.activeElement;
+        if (el && (el.tagName === 'TEXTAREA' || (el.tagName === 'INPUT' && /^(text|search|url)$/i.test(el.type)))) {
+            const text = el.value.substring(el.selectionStart || 0, el.selectionEnd || 0);
+            if (text) return text;
+        }
+        return String(window.getSelection() || '');
+    })()
+"#;
+const SELECTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+/// Longer selections are cut here; search engines truncate the query anyway
+const SELECTION_MAX_CHARS: usize = 400;
+
+/// Searches the configured engine for the text selected in a tab (the active one when `label` is
+/// None) and opens the results in a new tab. Returns the results URL.
+#[tauri::command]
+pub(crate) async fn search_selection(app: AppHandle, label: Option<String>) -> Result<String, LuminaError> {
+    let label = match label {
+        Some(label) => label,
+        None => app.state::<UiState>().current_tab()
+            .ok_or_else(|| LuminaError::NotFound("No tab to search from".to_string()))?,
+    };
+    if app.get_webview(&label).is_none() {
+        return Err(LuminaError::NotFound(format!("Tab {} not found", label)));
+    }
+    let app_handle = app.clone();
+    let selection = tauri::async_runtime::spawn_blocking(move || crate::scripting::eval_in_webview(&app_handle, &label, SELECTION_SCRIPT, SELECTION_TIMEOUT))
+        .await
+        .map_err(|e| e.to_string())??
+        .and_then(|json| serde_json::from_str::<String>(&json).ok())
+        .unwrap_or_default();
+    let query: String = selection.split_whitespace().collect::<Vec<_>>().join(" ").chars().take(SELECTION_MAX_CHARS).collect();
+    if query.is_empty() {
+        return Err(LuminaError::InvalidInput("No text is selected".to_string()));
+    }
+    let url = crate::locale::search_url(&app, &query);
+    events::emit(&app, AppEvent::RequestNewTab(TabCreatedPayload { label: "new-tab".to_string(), url: url.clone() }));
+    Ok(url)
+}
+
+/// Where the home button leads in a tab. Installed apps keep their own profile, so their windows go
+/// back to the app's start page; everything else uses the homepage setting.
+fn homepage_for(app: &AppHandle, label: &str) -> String {
+    let store = app.state::<AppDataStore>();
+    let page_origin = app.get_webview(label).and_then(|webview| webview.url().ok()).map(|url| url.origin());
+    let installed = store.installed_pwa(label).or_else(|| {
+        // Apps started from their shortcut get a fresh window label, so match them by site
+        let origin = page_origin.filter(|_| label.starts_with("pwa-"))?;
+        store.installed_pwas().into_iter().find(|pwa| Url::parse(&pwa.start_url).is_ok_and(|url| url.origin() == origin))
+    });
+    match installed {
+        Some(pwa) => pwa.start_url,
+        None => store.data.lock().unwrap().settings.homepage.clone(),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn go_home(app: AppHandle, label: String) -> Result<(), LuminaError> {
+    if app.get_webview(&label).is_none() {
+        return Err(LuminaError::NotFound(format!("Tab {} not found", label)));
+    }
+    let url = homepage_for(&app, &label);
+    navigate(app, label, url).await
+}
+
+/// Picks the element holding a page's main text. Shared by reader mode, `--fetch --reader` and `article_text`.
+pub(crate) const READER_FIND_CONTENT_JS: &str = r#"
+    function findContent() {
+        const selectors = ['article', 'main', '.content', '#content', '.post', '.entry', '.article', '#article'];
+        for (let sel of selectors) {
+            let el = document.querySelector(sel);
+            if (el && el.innerText.length > 200) return el;
+        }
+        
+        let divs = document.getElementsByTagName('div');
+        let bestDiv = null;
+        let maxP = 0;
+        for (let div of divs) {
+            let pCount = div.getElementsByTagName('p').length;
+            if (pCount > maxP) {
+                maxP = pCount;
+                bestDiv = div;
+            }
+        }
+        return bestDiv || document.body;
+    }
+"#;
+
+const ARTICLE_TEXT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The plain text of a tab's main content, as reader mode would pick it.
+pub(crate) async fn article_text(app: &AppHandle, label: &str) -> Result<String, LuminaError> {
+    let js = format!("(() => {{ {} return findContent().innerText; }})()", READER_FIND_CONTENT_JS);
+    let app_handle = app.clone();
+    let tab = label.to_string();
+    let json = tauri::async_runtime::spawn_blocking(move || crate::scripting::eval_in_webview(&app_handle, &tab, &js, ARTICLE_TEXT_TIMEOUT))
+        .await
+        .map_err(|e| e.to_string())??
+        .ok_or_else(|| LuminaError::NotFound("The page has no text".to_string()))?;
+    Ok(serde_json::from_str::<String>(&json)?)
+}
+
+#[tauri::command]
+pub(crate) fn toggle_reader_mode(app: AppHandle, label: String) -> Result<(), LuminaError> {
+    let webview = app.get_webview(&label).ok_or_else(|| LuminaError::NotFound(format!("No open tab '{}'", label)))?;
+    let script = [r#"
+            (function() {
+                if (window.__readerModeActive) {
+                    window.location.reload();
+                    return;
+                }
+
+"#, READER_FIND_CONTENT_JS, r#"
+                try {
+                    const content = findContent().cloneNode(true);
+                    
+                    const style = `
+                        body {
+                            background-color: #f4ecd8 !important;
+                            color: #333 !important;
+                            font-family: Georgia, 'Times New Roman', serif !important;
+                            font-size: 20px !important;
+                            line-height: 1.6 !important;
+                            max-width: 800px !important;
+                            margin: 0 auto !important;
+                            padding: 40px 20px !important;
+                        }
+                        img { max-width: 100%; height: auto; display: block; margin: 20px auto; }
+                        a { color: #005a9c; text-decoration: underline; }
+                        h1, h2, h3, h4 { font-family: Helvetica, Arial, sans-serif; color: #111; margin-top: 1.5em; }
+                        p { margin-bottom: 1.5em; }
+                        pre, code { background: rgba(0,0,0,0.05); padding: 2px 4px; border-radius: 3px; }
+                        blockquote { border-left: 4px solid #ccc; padding-left: 16px; margin-left: 0; color: #555; font-style: italic; }
+                        
+                        /* Hide everything else */
+                        nav, header, footer, aside, .sidebar, .menu, .ad, .ads, .advertisement, iframe, .popup, .modal { display: none !important; }
+                    `;
+
+                    document.head.innerHTML = '';
+                    const styleEl = document.createElement('style');
+                    styleEl.textContent = style;
+                    document.head.appendChild(styleEl);
+
+                    document.body.innerHTML = '';
+                    document.body.appendChild(content);
+                    window.__readerModeActive = true;
+                    console.log("Reader mode activated");
+                } catch(e) {
+                    console.error("Reader mode failed:", e);
+                }
+            })();
+    "#].concat();
+    webview.eval(&script).map_err(LuminaError::from)
+}
+
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub(crate) struct TabNavigationPayload {
+    label: String,
+    url: String,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TabCreatedPayload {
+    pub(crate) label: String,
+    pub(crate) url: String,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TabUpdatedPayload {
+    pub(crate) label: String,
+    pub(crate) title: Option<String>,
+    pub(crate) favicon: Option<String>,
+    /// Set when the backend pinned or unpinned the tab itself
+    pub(crate) pinned: Option<bool>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TabClosedPayload {
+    label: String,
+}
+
+
+/// Sends the tab's current title and favicon. Within TAB_UPDATE_MIN_INTERVAL of the last one,
+/// a single trailing event carries whatever changed in between.
+fn emit_tab_updated(app: &AppHandle, label: String) {
+    let wait = {
+        let mut throttle = tab_update_throttle().lock().unwrap();
+        let entry = throttle.entry(label.clone()).or_default();
+        if entry.scheduled {
+            return;
+        }
+        let wait = entry.last_sent.map(|at| TAB_UPDATE_MIN_INTERVAL.saturating_sub(at.elapsed())).unwrap_or_default();
+        if wait.is_zero() {
+            entry.last_sent = Some(std::time::Instant::now());
+        } else {
+            entry.scheduled = true;
+        }
+        wait
+    };
+    if wait.is_zero() {
+        send_tab_updated(app, label);
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(wait).await;
+        {
+            let mut throttle = tab_update_throttle().lock().unwrap();
+            // Closed in the meantime
+            let Some(entry) = throttle.get_mut(&label) else { return };
+            entry.scheduled = false;
+            entry.last_sent = Some(std::time::Instant::now());
+        }
+        send_tab_updated(&app, label);
+    });
+}
+
+fn send_tab_updated(app: &AppHandle, label: String) {
+    let tabs = app.state::<TabRegistry>().snapshot(None).tabs;
+    let Some(tab) = tabs.into_iter().find(|tab| tab.label == label) else { return };
+    events::emit(app, AppEvent::TabUpdated(TabUpdatedPayload { label, title: Some(tab.title), favicon: tab.favicon, pinned: None }));
+}
+
+/// Updates a history entry's title (without counting a visit) with the next batch.
+fn queue_history_title(app: &AppHandle, url: &str, title: &str) {
+    let first = {
+        let mut pending = pending_history_titles().lock().unwrap();
+        let first = pending.is_empty();
+        pending.insert(url.to_string(), title.to_string());
+        first
+    };
+    if !first {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(HISTORY_TITLE_FLUSH_DELAY).await;
+        let titles: Vec<(String, String)> = pending_history_titles().lock().unwrap().drain().collect();
+        if let Err(e) = app.state::<HistoryManager>().update_titles(&titles) {
+            eprintln!("Failed to update history titles: {}", e);
+        }
+    });
+}
+
+/// Called by the injected head observer, which fires on every title or icon mutation, and by the
+/// media capture hooks with what the page is capturing.
+#[tauri::command]
+pub(crate) fn update_tab_info(
+    app: AppHandle,
+    label: String,
+    title: Option<String>,
+    favicon: Option<String>,
+    url: Option<String>,
+    capture: Option<media_capture::CaptureState>,
+) -> Result<(), LuminaError> {
+    if let Some(capture) = capture {
+        media_capture::report(&app, label, capture);
+        return Ok(());
+    }
+    if let (Some(u), Some(t)) = (&url, &title) {
+         if !u.starts_with("tauri://") && !u.starts_with("about:") {
+             queue_history_title(&app, u, t);
+         }
+    }
+    let registry = app.state::<TabRegistry>();
+    let title_changed = title.as_deref().is_some_and(|t| registry.set_title(&label, t));
+    let favicon_changed = favicon.as_deref().is_some_and(|f| registry.set_favicon(&label, f));
+    if title_changed || favicon_changed {
+        emit_tab_updated(&app, label);
+    }
+    Ok(())
+}
+
+
+
+#[tauri::command]
+pub(crate) async fn create_tab(state: tauri::State<'_, UiState>, app: AppHandle, data_store: tauri::State<'_, AppDataStore>, label: String, url: Option<String>, window: tauri::Window) -> Result<(), LuminaError> {
+    // println!("Rust: create_tab called for {} url: {}", label, url);
+
+    // No URL means a plain new tab
+    let url = url
+        .filter(|u| !u.trim().is_empty())
+        .unwrap_or_else(|| data_store.data.lock().unwrap().settings.new_tab_page.clone());
+
+    // Local files only from the folders the user allowed; pages can call create_tab too
+    if let Ok(parsed) = Url::parse(&url) {
+        if parsed.scheme() == "file" && !local_files::is_allowed_url(&app, &parsed) {
+            return Err(LuminaError::PermissionDenied(format!("{} is outside the allowed folders", url)));
+        }
+    }
+
+    // A local folder opens in the folder view
+    let url = Url::parse(&url).ok().and_then(|parsed| local_files::directory_redirect(&parsed)).unwrap_or(url);
+
+    // Rewrite lumina:// to lumina-app://localhost/ for internal navigation to avoid OS deep link conflict
+    let url = if url.starts_with("lumina://") {
+        url.replace("lumina://", "lumina-app://localhost/")
+    } else if url.starts_with("lumina-app://") {
+         if !url.contains("lumina-app://localhost/") {
+             url.replace("lumina-app://", "lumina-app://localhost/")
+         } else {
+             url
+         }
+    } else {
+        url
+    };
+
+    // The tab opens in the browser window that asked for it, or "main" when a tab, PWA or detached page asked
+    let target_window = if is_browser_window(window.label()) {
+        window
+    } else {
+        app.get_window("main").ok_or_else(|| {
+            println!("Rust: Main window 'main' not found!");
+            "Main window not found".to_string()
+        })?
+    };
+
+    if app.get_webview(&label).is_some() {
+        // If tab already exists, just switch to it (optional logic)
+        println!("Rust: Tab {} already exists", label);
+        return Ok(());
+    }
+
+    app.state::<TabRegistry>().insert(&label, &url, target_window.label());
+    SESSION_FROZEN.store(false, std::sync::atomic::Ordering::Relaxed);
+    save_session(&app);
+
+    let bounds = app.state::<LayoutManager>().tab_bounds(&app, target_window.label())?;
+
+    let app_handle = app.clone();
+    let app_handle_dl = app.clone();
+
+
+    let label_clone = label.clone();
+    
+    let ad_block_script = get_lumina_stealth_script();
+
+    // Attempt to get invoke key
+    println!("Rust: Getting invoke key for {}", label);
+    let invoke_key = app.invoke_key();
+    let ipc_token = ipc_guard::issue_token(&label);
+     
+    let info_script = format!(r#"
+         (function() {{
+             // Prevent execution in subframes (ads, tracking pixels) to stop IPC errors
+             try {{
+                if (window.self !== window.top) return;
+             }} catch(e) {{ return; }}
+
+             // Block execution on known ad domains (even if top-level)
+             try {{
+                 let host = window.location.hostname;
+                 if (host.includes('doubleclick') || host.includes('googlesyndication') || host.includes('adnxs') || host.includes('teads')) return;
+             }} catch(e) {{}}
+
+             window.__TAB_LABEL__ = "{}";
+             // The bridge's key and token live in this closure only, and calls go out through the native
+             // channel as it was at document start, so page scripts can neither read nor intercept them
+             const invokeKey = "{}";
+             const ipcToken = "{}";
+             const nativePost = (function() {{
+                 if (window.chrome && window.chrome.webview) {{
+                     // WebView2 (Windows)
+                     return window.chrome.webview.postMessage.bind(window.chrome.webview);
+                 }}
+                 if (window.webkit && window.webkit.messageHandlers && window.webkit.messageHandlers.ipc) {{
+                     // WebKit (macOS / Linux)
+                     const ipc = window.webkit.messageHandlers.ipc;
+                     return ipc.postMessage.bind(ipc);
+                 }}
+                 return null;
+             }})();
+            
+            // Suppress Tauri callback errors caused by our manual IPC
+            const originalConsoleError = console.error;
+            const originalConsoleWarn = console.warn;
+            
+            function isTauriCallbackError(args) {{
+                if (args.length > 0 && typeof args[0] === 'string') {{
+                    return args[0].includes("Couldn't find callback id") || 
+                           args[0].includes("[TAURI] Couldn't find callback id");
+                }}
+                return false;
+            }}
+
+            console.error = function(...args) {{
+                if (isTauriCallbackError(args)) return;
+                originalConsoleError.apply(console, args);
+            }};
+            
+            console.warn = function(...args) {{
+                if (isTauriCallbackError(args)) return;
+                originalConsoleWarn.apply(console, args);
+            }};
+
+            // Custom IPC for our browser tabs via native postMessage
+            // This bypasses CSP 'connect-src' and 'frame-src' restrictions.
+            function invoke(cmd, args) {{
+                // Commands check this token against the tab that sent the call. Spread defines the
+                // properties itself, so no Object.assign or prototype setter the page replaced sees it
+                args = {{ ...args, __luminaIpcToken: ipcToken }};
+
+                // Use a static counter to ensure unique, valid u32 IDs
+                if (typeof window.__IPC_COUNTER === 'undefined') {{
+                    window.__IPC_COUNTER = 0;
+                }}
+                window.__IPC_COUNTER = (window.__IPC_COUNTER + 1) % 4000000000;
+                var callbackId = window.__IPC_COUNTER;
+                
+                // Register dummy callback to silence "Couldn't find callback id" errors
+                var noOp = function(res) {{}};
+                
+                try {{
+                     if (window.__TAURI_INTERNALS__) {{
+                         if (!window.__TAURI_INTERNALS__.callbacks) {{
+                             window.__TAURI_INTERNALS__.callbacks = {{}};
+                         }}
+                         // Tauri v2 style
+                         window.__TAURI_INTERNALS__.callbacks[callbackId] = {{
+                             resolve: noOp,
+                             reject: noOp
+                         }};
+                     }}
+                }} catch(e) {{}}
+                
+                // Cleanup
+                setTimeout(function() {{
+                    try {{
+                        if (window.__TAURI_INTERNALS__ && window.__TAURI_INTERNALS__.callbacks) {{
+                             delete window.__TAURI_INTERNALS__.callbacks[callbackId];
+                        }}
+                    }} catch(e) {{}}
+                }}, 60000); // 60s timeout
+
+                var msg = {{
+                    cmd: cmd,
+                    callback: callbackId, 
+                    error: callbackId,
+                    payload: args,
+                    __TAURI_INVOKE_KEY__: invokeKey
+                }};
+                
+                if (nativePost) {{
+                    nativePost(msg);
+                }} else {{
+                    console.error("No native IPC found for " + cmd);
+                }}
+            }}
+
+            // Camera, microphone and screen capture reporting shares the bridge; nothing else in the page gets it
+            ({})(invoke);
+
+            // PWA Detection
+            window.addEventListener('beforeinstallprompt', (e) => {{
+                // Prevent the mini-infobar from appearing on mobile
+                e.preventDefault();
+                // Stash the event so it can be triggered later.
+                window.deferredPrompt = e;
+                console.log("PWA beforeinstallprompt fired! Event stashed.");
+                invoke('pwa_detected', {{ label: window.__TAB_LABEL__ }});
+            }});
+            
+            window.addEventListener('appinstalled', () => {{
+                console.log('PWA was installed');
+                window.deferredPrompt = null;
+            }});
+            
+            // Manifest-only apps are found by pwa::detect_manifest once the page has loaded
+
+            function getFavicon() {{
+                let link = document.querySelector("link[rel*='icon']") || document.querySelector("link[rel='shortcut icon']");
+                return link ? link.href : "";
+            }}
+
+            function logVisit() {{
+                if (window.location.protocol.startsWith('http')) {{
+                     invoke('add_history_item', {{
+                         url: window.location.href,
+                         title: document.title || window.location.href
+                     }});
+                }}
+            }}
+
+            function updateInfo() {{
+                 let title = document.title;
+                 let favicon = getFavicon();
+                 invoke('update_tab_info', {{
+                     label: window.__TAB_LABEL__,
+                     title: title,
+                     favicon: favicon,
+                     url: window.location.href
+                 }});
+            }}
+            
+            // Observer for head changes (title, favicon)
+            function initObserver() {{
+                var target = document.head || document.querySelector('head') || document.documentElement;
+                if (target) {{
+                    try {{
+                        new MutationObserver(updateInfo).observe(target, {{ subtree: true, childList: true, attributes: true }});
+                    }} catch(e) {{
+                        console.error("MutationObserver init failed:", e);
+                    }}
+                }}
+            }}
+
+            // window.open and target=_blank go through the Rust new-window policy; middle-click is an explicit user request.
+            document.addEventListener('auxclick', (e) => {{
+                if (e.button === 1) {{
+                    let target = e.target;
+                    while(target && target.tagName !== 'A') target = target.parentElement;
+                    if (target && target.tagName === 'A') {{
+                        e.preventDefault();
+                        invoke('create_tab', {{ label: 'tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000000), url: target.href }});
+                    }}
+                }}
+            }}, true);
+
+            // Custom Context Menu
+            document.addEventListener('contextmenu', (e) => {{
+                // Check if target is link
+                let target = e.target;
+                let linkUrl = null;
+                while(target && target.tagName !== 'A') target = target.parentElement;
+                if (target && target.tagName === 'A' && target.href) {{
+                    linkUrl = target.href;
+                }}
+
+                if (linkUrl) {{
+                    e.preventDefault();
+                    e.stopPropagation(); // Stop propagation immediately
+                    
+                    // Remove existing menu
+                    const existing = document.getElementById('lumina-context-menu');
+                    if (existing) existing.remove();
+
+                    const menu = document.createElement('div');
+                    menu.id = 'lumina-context-menu';
+                    menu.style.position = 'fixed';
+                    menu.style.top = e.clientY + 'px';
+                    menu.style.left = e.clientX + 'px';
+                    menu.style.zIndex = '2147483647'; // Max z-index
+                    menu.style.background = '#292a2d';
+                    menu.style.border = '1px solid #3c4043';
+                    menu.style.borderRadius = '4px';
+                    menu.style.padding = '4px 0';
+                    menu.style.boxShadow = '0 2px 6px rgba(0,0,0,0.3)';
+                    menu.style.minWidth = '150px';
+                    menu.style.color = '#e8eaed';
+                    menu.style.fontFamily = 'system-ui, -apple-system, sans-serif';
+                    menu.style.fontSize = '13px';
+                    menu.style.userSelect = 'none';
+
+                    const createItem = (text, onClick) => {{
+                        const item = document.createElement('div');
+                        item.innerText = text;
+                        item.style.padding = '6px 12px';
+                        item.style.cursor = 'pointer';
+                        item.onmouseenter = () => item.style.background = '#3c4043';
+                        item.onmouseleave = () => item.style.background = 'transparent';
+                        item.onclick = (ev) => {{
+                            ev.stopPropagation(); 
+                            onClick();
+                            menu.remove();
+                        }};
+                        return item;
+                    }};
+
+                    menu.appendChild(createItem('Open Link in New Tab', () => {{
+                         let uniqueLabel = 'tab-' + Date.now() + '-' + Math.floor(Math.random() * 1000000);
+                         invoke('create_tab', {{ label: uniqueLabel, url: linkUrl }});
+                    }}));
+                    
+                    // Separator
+                    const sep = document.createElement('div');
+                    sep.style.height = '1px';
+                    sep.style.background = '#3c4043';
+                    sep.style.margin = '4px 0';
+                    menu.appendChild(sep);
+
+                    menu.appendChild(createItem('Back', () => window.history.back()));
+                    menu.appendChild(createItem('Forward', () => window.history.forward()));
+                    menu.appendChild(createItem('Reload', () => window.location.reload()));
+
+                    document.body.appendChild(menu);
+                    
+                    // Close on click outside
+                    const closeMenu = () => {{
+                        menu.remove();
+                        document.removeEventListener('click', closeMenu);
+                        document.removeEventListener('contextmenu', closeMenu);
+                    }};
+                    // Delay slightly to avoid immediate trigger
+                    setTimeout(() => {{
+                        document.addEventListener('click', closeMenu);
+                        document.addEventListener('contextmenu', (e) => {{
+                             if (e.target.closest('#lumina-context-menu')) return;
+                             closeMenu();
+                        }});
+                    }}, 100);
+                }}
+            }}, true); // Use Capture phase to preempt site scripts
+
+            if (document.body || document.head || document.documentElement) {{
+                initObserver();
+            }} else {{
+                document.addEventListener('DOMContentLoaded', initObserver);
+            }}
+            
+            // Initial call
+            if (document.readyState === 'complete' || document.readyState === 'interactive') {{
+                updateInfo();
+                logVisit();
+            }} else {{
+                window.addEventListener('DOMContentLoaded', updateInfo);
+                window.addEventListener('load', () => {{ updateInfo(); logVisit(); }});
+            }}
+        }})();
+    "#, label_clone, invoke_key, ipc_token, media_capture::CAPTURE_SCRIPT);
+
+    let flags = data_store.flags();
+    let mut full_script = format!("{}\n{}", ad_block_script, info_script);
+    if flags.aggressive_cosmetic_filtering {
+        full_script.push('\n');
+        full_script.push_str(get_cosmetic_filter_script());
+    }
+    if flags.fingerprint_protection {
+        full_script.push('\n');
+        full_script.push_str(get_fingerprint_protection_script());
+    }
+    full_script.push('\n');
+    full_script.push_str(&get_dark_mode_script(&dark_mode_config(&app)));
+    full_script.push('\n');
+    full_script.push_str(&referrer::get_referrer_script());
+    full_script.push('\n');
+    full_script.push_str(&spellcheck::get_spellcheck_script(&app));
+    full_script.push('\n');
+    full_script.push_str(data_viewer::DATA_VIEWER_SCRIPT);
+    tab_last_active().lock().unwrap().insert(label.clone(), std::time::Instant::now());
+
+    let url_parsed = match url.parse() {
+        Ok(u) => u,
+        Err(e) => return Err(LuminaError::InvalidInput(format!("Invalid URL: {}", e))),
+    };
+
+    let label_clone_adblock = label.clone();
+    let app_page_load = app.clone();
+
+    // Site isolation: web pages get the profile of their site group, everything else the shared one
+    let site_group = if isolation::is_enabled(&app) { isolation::site_group(&url_parsed) } else { None };
+
+    // println!("Rust: Creating WebviewBuilder for {}", label);
+    let mut builder = tauri::webview::WebviewBuilder::new(&label, WebviewUrl::External(url_parsed));
+    if let Some(dir) = site_group.as_deref().and_then(|group| isolation::site_data_dir(&app, group)) {
+        builder = builder.data_directory(dir);
+    }
+    isolation::assign(&label, site_group);
+    
+    #[cfg(target_os = "windows")]
+    {
+         // Chrome Extensions Support (Windows)
+         let mut args = Vec::new();
+         args.push("--ignore-certificate-errors".to_string());
+         
+         // Load unpacked extensions if available
+         if let Some(ext_path) = get_extension_path(&app_handle_dl) {
+             if let Ok(entries) = std::fs::read_dir(&ext_path) {
+                 let paths: Vec<String> = entries
+                     .filter_map(|e| e.ok())
+                     .filter(|e| e.path().join("manifest.json").is_file() && !e.file_name().to_string_lossy().starts_with('.'))
+                     .filter(|e| app_handle_dl.state::<AppDataStore>().is_extension_enabled(&e.file_name().to_string_lossy()))
+                     .map(|e| e.path().to_string_lossy().into_owned())
+                     .collect();
+                 
+                 if !paths.is_empty() {
+                     let ext_arg = format!("--load-extension={}", paths.join(","));
+                     println!("Lumina Extensions: Loading {}", ext_arg);
+                     args.push(ext_arg);
+                 }
+             }
+         }
+         
+         args.extend(crate::locale::browser_args(&app));
+
+         for arg in args {
+            builder = builder.additional_browser_args(&arg);
+         }
+
+         builder = builder.user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0");
+    }
+    #[cfg(target_os = "linux")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
+    }
+    #[cfg(target_os = "macos")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
+    }
+
+    builder = builder.initialization_script(&full_script)
+        .on_web_resource_request(move |request, response| {
+             // Lumina Stealth: Rust-side Ad/Tracker Blocking
+             handle_adblock_request(&request, response, &label_clone_adblock);
+        })
+        .on_download(move |webview, event| {
+            match event {
+                tauri::webview::DownloadEvent::Requested { url, destination: _ } => {
+                    println!("Download requested: {}", url);
+                    let url_str = url.to_string();
+                    let file_name = filename_from_url(&url);
+                    let app = app_handle_dl.clone();
+                    
+                    let private = app.state::<TabRegistry>().is_private(webview.label());
+                    enqueue_download(&app, url_str, file_name, None, private);
+                    false // Suppress native download
+                }
+                _ => true
+            }
+        })
+        .on_page_load(move |webview, payload| {
+            apply_site_protection(&app_page_load, &webview, &payload);
+            apply_site_fixups(&app_page_load, &webview, &payload);
+            if payload.event() == tauri::webview::PageLoadEvent::Started {
+                // The document has committed, so this is where the tab really is now. A hibernated or
+                // crashed tab keeps its real URL in the session.
+                let label = webview.label();
+                if !hibernated_tabs().lock().unwrap().contains_key(label) && !crash_recovery::is_crashed(label) {
+                    app_page_load.state::<TabRegistry>().set_url(label, payload.url().as_str());
+                    queue_session_save(&app_page_load);
+                }
+            }
+            if payload.event() == tauri::webview::PageLoadEvent::Finished {
+                // Only loads that actually happened go into the back/forward mirror
+                let label = webview.label();
+                if !hibernated_tabs().lock().unwrap().contains_key(label) && !crash_recovery::is_crashed(label) {
+                    let history = app_page_load.state::<TabRegistry>().record_navigation(label, payload.url().as_str());
+                    emit_navigation_state(&app_page_load, label, &history);
+                }
+                tauri::async_runtime::spawn(pwa::detect_manifest(app_page_load.clone(), webview));
+            }
+        })
+        .on_navigation(move |url: &Url| {
+            // println!("Navigation: {} -> {}", label_clone, url);
+
+            // magnet:, mailto: and friends go to the OS, never into the tab
+            if is_external_scheme(url.scheme()) {
+                handle_external_protocol(&app_handle, &label_clone, url);
+                return false;
+            }
+
+            if url.scheme() == "file" && !local_files::is_allowed_url(&app_handle, url) {
+                println!("Navigation blocked (local file outside the allowed folders): {}", url);
+                return false;
+            }
+            if let Some(folder_view) = local_files::directory_redirect(url) {
+                if let Some(webview) = app_handle.get_webview(&label_clone) {
+                    let _ = webview.eval(format!("window.location.replace({})", to_script_json(&folder_view)));
+                }
+                return false;
+            }
+
+            if isolation::needs_reopen(&app_handle, &label_clone, url) {
+                rebuild_tab(&app_handle, label_clone.clone(), url.clone());
+                return false;
+            }
+
+            if adblock::blocks_navigation(url, &label_clone) {
+                println!("Navigation blocked by the shield: {}", url);
+                if let (Some(webview), Ok(target)) = (app_handle.get_webview(&label_clone), adblock::blocked_page_url(url).parse()) {
+                    // Navigating from inside the navigation handler would re-enter the webview
+                    tauri::async_runtime::spawn(async move { let _ = webview.navigate(target); });
+                }
+                return false;
+            }
+
+            ipc_guard::set_internal_document(&label_clone, false);
+
+            // Go straight to a shim's destination so history, the omnibox and adblock never see the hop
+            if app_handle.state::<AppDataStore>().data.lock().unwrap().settings.unwrap_redirects {
+                if let Some(target) = unwrap_link_shim(url) {
+                    if let Some(webview) = app_handle.get_webview(&label_clone) {
+                        let _ = webview.eval(format!("window.location.replace({})", to_script_json(&target.as_str())));
+                        return false;
+                    }
+                }
+            }
+
+            // Explicitly allow lumina-app scheme to bypass some restrictions
+            if url.scheme() == "lumina-app" {
+                 println!("Navigation ALLOWED (internal): {}", url);
+                 return true;
+            }
+
+            events::emit(&app_handle, AppEvent::TabNavigation(TabNavigationPayload {
+                label: label_clone.clone(),
+                url: url.to_string(),
+            }));
+            notes::emit_site_note_status(&app_handle, &label_clone, url);
+            media_capture::reset(&app_handle, &label_clone);
+            if let Some(webview) = app_handle.get_webview(&label_clone) {
+                let (app, url) = (app_handle.clone(), url.clone());
+                // Like navigating, zooming from inside the navigation handler would re-enter the webview
+                tauri::async_runtime::spawn(async move { zoom::apply(&app, &webview, &url) });
+            }
+            
+            true
+        });
+
+    // Use add_child to create the webview inside the existing window
+    // println!("Rust: Attempting to add_child for {}", label);
+    // Explicitly handle the Result immediately to catch panic or error
+     let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+         // println!("Rust: Inside catch_unwind, calling add_child");
+         target_window.add_child(
+             builder,
+             tauri::LogicalPosition::new(bounds.x, bounds.y),
+             tauri::LogicalSize::new(bounds.width, bounds.height),
+         )
+     }));
+
+    match res {
+        Ok(inner_res) => {
+             match inner_res {
+                Ok(webview) => {
+                    // println!("Rust: Tab {} created successfully. Actual label: {}", label, webview.label());
+                    // Force tracking of the new webview
+                    // In Tauri v2, add_child might not immediately register the webview in the app handle's map
+                    // But the webview object returned is valid.
+                    
+                    // Position and size are already set by add_child
+
+                    // New tab is created. 
+                    
+                    // Optimization: Hide previous tab immediately to prevent stacking/flicker
+                    {
+                        let previous = state.window(target_window.label(), |ui| ui.current_tab.replace(label.clone()));
+                        if let Some(old_webview) = previous.and_then(|old_label| app.get_webview(&old_label)) {
+                            let _ = old_webview.hide();
+                        }
+                    }
+                    app.state::<TabRegistry>().set_active(&label);
+
+                    let _ = webview.show();
+                    let _ = webview.set_focus();
+                    
+                    // Explicitly ensure navigation (fix for WebView2 Source being null)
+                    // This forces the webview to navigate even if the builder initialization missed it
+                    // Using eval since load_url is not available on Webview struct in this context
+                    let json_url = serde_json::to_string(&url).unwrap_or_else(|_| format!("'{}'", url));
+                    let nav_script = format!("window.location.replace({})", json_url);
+                    if let Err(e) = webview.eval(&nav_script) {
+                        println!("Rust: Failed to force load url via eval: {}", e);
+                    }
+
+                     // Place it again: add_child's bounds can come out 0x0 or under the top bar (black screen / production layout issue)
+                     let _ = app.state::<LayoutManager>().place_webview(&app, &webview);
+                    if let Ok(parsed) = Url::parse(&url) {
+                        zoom::apply(&app, &webview, &parsed);
+                    }
+                    watch_fullscreen(&app, &webview);
+                    crash_recovery::watch_process_failures(&app, &webview);
+                    notifications::watch(&app, &webview);
+                    watch_new_windows(&app, &webview);
+                    network::watch_navigation_failures(&webview);
+                    header_rules::watch_requests(&webview);
+
+                    events::emit(&app, AppEvent::TabCreated(TabCreatedPayload {
+                        label: label.clone(),
+                        url: url.clone(),
+                    }));
+
+                },
+                Err(e) => {
+                    println!("Rust: Error creating tab {}: {:?}", label, e);
+                    return Err(LuminaError::Webview(format!("Failed to create tab: {:?}", e)));
+                }
+            }
+        },
+        Err(payload) => {
+             println!("Rust: add_child PANICKED for {}: {:?}", label, payload);
+             return Err(LuminaError::Webview("add_child panicked".to_string()));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Closes a tab's webview and creates it again at `url` in the same window, leaving a background tab
+/// in the background. Site isolation needs this to move a tab to another site group's profile, since a
+/// webview's data folder is fixed when it is created; crash recovery when the browser process is gone.
+/// Back/forward history starts over.
+pub(crate) fn rebuild_tab(app: &AppHandle, label: String, url: Url) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Some(window_label) = tab_window(&app, &label) else { return };
+        let previous = app.state::<UiState>().window(&window_label, |ui| ui.current_tab.clone());
+        if let Some(webview) = app.get_webview(&label) {
+            let _ = webview.close();
+        }
+        ipc_guard::revoke(&label);
+        // create_tab bails out while the old webview is still registered
+        for _ in 0..50 {
+            if app.get_webview(&label).is_none() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        let Some(window) = app.get_window(&window_label) else { return };
+        if let Err(e) = create_tab(app.state(), app.clone(), app.state(), label.clone(), Some(url.to_string()), window).await {
+            error::report(&app, "Sekme yeniden oluşturulamadı", &e);
+            return;
+        }
+        // A background tab stays in the background
+        if let Some(previous) = previous.filter(|previous| previous != &label) {
+            if let Err(e) = switch_tab(app.clone(), app.state(), previous) {
+                error::report(&app, "Sekmeye geri dönülemedi", &e);
+            }
+        }
+    });
+}
+
+/// Parks background tabs idle for longer than TAB_HIBERNATE_AFTER on a blank internal page.
+pub(crate) fn hibernate_idle_tabs(app: &AppHandle) {
+    if !app.state::<AppDataStore>().flags().tab_hibernation {
+        return;
+    }
+    let current = app.state::<UiState>().current_tabs();
+
+    // A tab counts as active for as long as it is shown, not just when it was switched to
+    let now = std::time::Instant::now();
+    let mut last_active = tab_last_active().lock().unwrap();
+    for label in &current {
+        if let Some(stamp) = last_active.get_mut(label) {
+            *stamp = now;
+        }
+    }
+    let idle: Vec<String> = last_active
+        .iter()
+        .filter(|(label, last_active)| !current.contains(label) && last_active.elapsed() >= TAB_HIBERNATE_AFTER)
+        .map(|(label, _)| label.clone())
+        .collect();
+    drop(last_active);
+
+    for label in idle {
+        if hibernated_tabs().lock().unwrap().contains_key(&label) {
+            continue;
+        }
+        let Some(webview) = app.get_webview(&label) else { continue };
+        let Ok(url) = webview.url() else { continue };
+        if url.scheme() == "lumina-app" {
+            continue;
+        }
+
+        println!("Lumina: Hibernating tab {} ({})", label, url);
+        hibernated_tabs().lock().unwrap().insert(label.clone(), url.to_string());
+        if let Ok(target) = "lumina-app://localhost/hibernated".parse() {
+            let _ = webview.navigate(target);
+        }
+        events::emit(app, AppEvent::TabHibernated(label));
+    }
+}
+
+fn wake_tab(app: &AppHandle, label: &str) {
+    let Some(url) = hibernated_tabs().lock().unwrap().remove(label) else { return };
+    println!("Lumina: Waking tab {} ({})", label, url);
+    if let (Some(webview), Ok(target)) = (app.get_webview(label), url.parse()) {
+        let _ = webview.navigate(target);
+    }
+}
+
+/// "scheme://host[:port]" of a tab's current page, used as the permission key.
+pub(crate) fn webview_origin(app: &AppHandle, label: &str) -> Option<String> {
+    let url = app.get_webview(label)?.url().ok()?;
+    match url.origin() {
+        url::Origin::Tuple(..) => Some(url.origin().ascii_serialization()),
+        url::Origin::Opaque(_) => None,
+    }
+}
+
+#[tauri::command]
+pub(crate) fn switch_tab(app: AppHandle, state: tauri::State<'_, UiState>, label: String) -> Result<(), LuminaError> {
+    println!("Switching to tab: {}", label);
+    app.state::<TabRegistry>().set_active(&label);
+    
+    let window = tab_window(&app, &label).ok_or_else(|| LuminaError::NotFound(format!("No open tab '{}'", label)))?;
+    state.set_focused_window(&window);
+    let previous = state.window(&window, |ui| ui.current_tab.replace(label.clone()));
+
+    // Optimization: Only hide the previously active tab instead of iterating all webviews
+    if let Some(old_label) = &previous {
+        if *old_label != label {
+            if let Some(old_webview) = app.get_webview(old_label) {
+                old_webview.hide()?;
+            }
+        }
+    } else if let Some(browser_window) = app.get_window(&window) {
+        // Fallback: If no current tab tracked yet (first switch), hide the window's other tabs
+        for webview_instance in browser_window.webviews() {
+            if webview_instance.label() != window && webview_instance.label() != label {
+                webview_instance.hide()?;
+            }
+        }
+    }
+    
+    // Show the new tab
+    if let Some(webview) = app.get_webview(&label) {
+        webview.show()?;
+        webview.set_focus()?;
+    }
+    wake_tab(&app, &label);
+    crash_recovery::recover_on_switch(&app, &label);
+    // The tab being left was in use until now, so its idle time starts here
+    let now = std::time::Instant::now();
+    let mut last_active = tab_last_active().lock().unwrap();
+    if let Some(old_label) = previous.filter(|old_label| *old_label != label) {
+        if let Some(stamp) = last_active.get_mut(&old_label) {
+            *stamp = now;
+        }
+    }
+    last_active.insert(label, now);
+    Ok(())
+}
+
+/// Writes the registry's tabs out as the session restored on next launch.
+fn save_session(app: &AppHandle) {
+    if SESSION_FROZEN.load(std::sync::atomic::Ordering::Relaxed) {
+        return;
+    }
+    let data_store = app.state::<AppDataStore>();
+    data_store.set_session(app.state::<TabRegistry>().session_tabs());
+    data_store.save();
+}
+
+/// Saves the session once page loads have settled, so a burst of redirects is written once.
+fn queue_session_save(app: &AppHandle) {
+    if SESSION_SAVE_QUEUED.swap(true, std::sync::atomic::Ordering::Relaxed) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SESSION_SAVE_DELAY).await;
+        SESSION_SAVE_QUEUED.store(false, std::sync::atomic::Ordering::Relaxed);
+        save_session(&app);
+    });
+}
+
+#[tauri::command]
+pub(crate) fn get_open_tabs(registry: tauri::State<'_, TabRegistry>, webview: tauri::Webview) -> tab_registry::OpenTabs {
+    registry.snapshot(Some(webview.window().label()))
+}
+
+#[tauri::command]
+pub(crate) fn set_tab_pinned(app: AppHandle, label: String, pinned: bool) -> Result<(), LuminaError> {
+    if !app.state::<TabRegistry>().set_pinned(&label, pinned) {
+        return Err(LuminaError::NotFound(format!("No open tab '{}'", label)));
+    }
+    save_session(&app);
+    Ok(())
+}
+
+/// Marks a tab incognito (or not) as the UI toggles it.
+#[tauri::command]
+pub(crate) fn set_tab_private(app: AppHandle, label: String, private: bool) -> Result<(), LuminaError> {
+    if !app.state::<TabRegistry>().set_private(&label, private) {
+        return Err(LuminaError::NotFound(format!("No open tab '{}'", label)));
+    }
+    save_session(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn set_tab_group(registry: tauri::State<'_, TabRegistry>, label: String, group: Option<String>) -> Result<(), LuminaError> {
+    let group = group.map(|g| g.trim().to_string()).filter(|g| !g.is_empty());
+    if !registry.set_group(&label, group) {
+        return Err(LuminaError::NotFound(format!("No open tab '{}'", label)));
+    }
+    Ok(())
+}
+
+/// URL a tab is really showing; hibernated tabs report the page they were parked from.
+fn tab_url(app: &AppHandle, label: &str) -> Option<String> {
+    if let Some(url) = hibernated_tabs().lock().unwrap().get(label) {
+        return Some(url.clone());
+    }
+    app.get_webview(label).and_then(|w| w.url().ok()).map(|u| u.to_string())
+}
+
+/// Opens a copy of a tab next to it. Only the URL carries over; the webview's back/forward list can't be cloned.
+#[tauri::command]
+pub(crate) async fn duplicate_tab(app: AppHandle, label: String) -> Result<String, LuminaError> {
+    let url = tab_url(&app, &label).ok_or_else(|| format!("No open tab '{}'", label))?;
+    let window_label = tab_window(&app, &label).unwrap_or_else(|| "main".to_string());
+    let window = app.get_window(&window_label).ok_or("Window not found")?;
+    let new_label = format!("tab-{}", chrono::Utc::now().timestamp_micros());
+    create_tab(app.state::<UiState>(), app.clone(), app.state::<AppDataStore>(), new_label.clone(), Some(url), window).await?;
+    Ok(new_label)
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TabDetachedPayload {
+    label: String,
+    window_label: String,
+    url: String,
+}
+
+/// Closes the tab and reopens its page in a standalone window.
+#[tauri::command]
+pub(crate) async fn detach_tab(app: AppHandle, label: String) -> Result<String, LuminaError> {
+    let url = tab_url(&app, &label).ok_or_else(|| format!("No open tab '{}'", label))?;
+    let parsed: Url = url.parse().map_err(|e: url::ParseError| e.to_string())?;
+    let title = app.state::<TabRegistry>().snapshot(None).tabs.into_iter()
+        .find(|t| t.label == label)
+        .map(|t| t.title)
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "Lumina".to_string());
+
+    let window_label = format!("window-{}", chrono::Utc::now().timestamp_micros());
+    let window_label_clone = window_label.clone();
+    let mut builder = tauri::WebviewWindowBuilder::new(&app, &window_label, tauri::WebviewUrl::External(parsed))
+        .title(&title);
+
+    #[cfg(target_os = "windows")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0");
+    }
+    #[cfg(target_os = "linux")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
+    }
+    #[cfg(target_os = "macos")]
+    {
+        builder = builder.user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
+    }
+
+    builder.inner_size(1024.0, 768.0)
+        .decorations(true)
+        .center()
+        .focused(true)
+        .initialization_script(get_lumina_stealth_script())
+        .on_page_load(|window, payload| apply_site_protection(window.app_handle(), window.as_ref(), &payload))
+        .on_web_resource_request(move |request, response| {
+            handle_adblock_request(&request, response, &window_label_clone);
+        })
+        .build()?;
+
+    drop_tab(app.clone(), label.clone(), false)?;
+    events::emit(&app, AppEvent::TabDetached(TabDetachedPayload { label, window_label: window_label.clone(), url }));
+    Ok(window_label)
+}
+
+/// Keeps the session as it is from here on; called when the app is about to quit.
+pub(crate) fn freeze_session() {
+    SESSION_FROZEN.store(true, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Clears what the backend kept about the tabs of a browser window that was closed with them in it.
+/// With another browser window open, the user closed those tabs. Otherwise the browser is going away
+/// and they are the session to restore: they're dropped without a session write or close hooks.
+pub(crate) fn forget_window_tabs(app: &AppHandle, window: &str) {
+    if !app.windows().keys().any(|label| label != window && is_browser_window(label)) {
+        freeze_session();
+    }
+    let closed_by_user = !SESSION_FROZEN.load(std::sync::atomic::Ordering::Relaxed);
+    let tabs = app.state::<TabRegistry>().snapshot(Some(window)).tabs;
+    for tab in tabs {
+        if let Err(e) = drop_tab(app.clone(), tab.label, closed_by_user) {
+            eprintln!("Failed to close tab of closed window {}: {}", window, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub(crate) fn close_tab(app: AppHandle, label: String) -> Result<(), LuminaError> {
+    drop_tab(app, label, true)
+}
+
+/// Forgets everything about a tab and closes its webview. Only a tab the user closed counts as closed
+/// for the tab close hooks (forget-on-close): a detached tab's page goes on in its own window, and the
+/// tabs of a browser that is going away are the session to restore.
+fn drop_tab(app: AppHandle, label: String, closed_by_user: bool) -> Result<(), LuminaError> {
+    blocked_request_log().lock().unwrap().remove(&label);
+    forget_blocked_count(&label);
+    tab_update_throttle().lock().unwrap().remove(&label);
+    tab_last_active().lock().unwrap().remove(&label);
+    hibernated_tabs().lock().unwrap().remove(&label);
+    crash_recovery::forget(&label);
+    media_capture::reset(&app, &label);
+    network::forget_tab(&label);
+    ipc_guard::revoke(&label);
+    isolation::forget(&label);
+    site_prompts::dismiss_tab(&app, &label);
+    if closed_by_user {
+        app.state::<TabRegistry>().remove(&label);
+    } else {
+        app.state::<TabRegistry>().detach(&label);
+    }
+    save_session(&app);
+    // A page closed while in fullscreen never reports leaving it
+    set_tab_fullscreen(&app, &label, false);
+    if let Some(webview) = app.get_webview(&label) {
+        webview.close()?;
+        events::emit(&app, AppEvent::TabClosed(TabClosedPayload { label }));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn set_block_popups(state: tauri::State<'_, AppDataStore>, enabled: bool) -> Result<(), LuminaError> {
+    state.data.lock().unwrap().settings.block_popups = enabled;
+    state.try_save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn set_unwrap_redirects(state: tauri::State<'_, AppDataStore>, enabled: bool) -> Result<(), LuminaError> {
+    state.data.lock().unwrap().settings.unwrap_redirects = enabled;
+    state.try_save()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unwrap(url: &str) -> Option<String> {
+        unwrap_link_shim(&Url::parse(url).unwrap()).map(|u| u.to_string())
+    }
+
+    #[test]
+    fn link_shims_unwrap_to_their_destination() {
+        assert_eq!(unwrap("https://l.facebook.com/l.php?u=https%3A%2F%2Fexample.com%2Fa&h=x").as_deref(), Some("https://example.com/a"));
+        assert_eq!(unwrap("https://www.google.com/url?q=https://example.com/&sa=D").as_deref(), Some("https://example.com/"));
+        assert_eq!(
+            unwrap("https://eur01.safelinks.protection.outlook.com/?url=https%3A%2F%2Fexample.com%2F").as_deref(),
+            Some("https://example.com/")
+        );
+    }
+
+    #[test]
+    fn nested_link_shims_unwrap_fully() {
+        let inner = format!("https://www.google.com/url?q={}", urlencoding::encode("https://example.com/deep"));
+        let outer = format!("https://l.facebook.com/l.php?u={}", urlencoding::encode(&inner));
+        assert_eq!(unwrap(&outer).as_deref(), Some("https://example.com/deep"));
+    }
+
+    #[test]
+    fn other_links_are_left_alone() {
+        assert_eq!(unwrap("https://www.google.com/search?q=https://example.com/"), None);
+        assert_eq!(unwrap("https://l.facebook.com/l.php?u=javascript:alert(1)"), None);
+        assert_eq!(unwrap("https://evil-l.facebook.com.example/l.php?u=https://example.com/"), None);
+    }
+
+    #[test]
+    fn only_external_schemes_normalize() {
+        assert_eq!(normalize_scheme("Magnet:").as_deref(), Some("magnet"));
+        assert_eq!(normalize_scheme("web+music").as_deref(), Some("web+music"));
+        assert_eq!(normalize_scheme("https"), None);
+        assert_eq!(normalize_scheme("1bad"), None);
+        assert_eq!(normalize_scheme("bad scheme"), None);
+    }
+}
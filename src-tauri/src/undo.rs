@@ -0,0 +1,95 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::data::{AppDataStore, FavoriteItem};
+use crate::downloads::{DownloadItem, DownloadManager};
+use crate::error::LuminaError;
+use crate::events::{self, AppEvent};
+use crate::history_manager::{HistoryItem, HistoryManager};
+
+/// How long a deleted item can be brought back
+const UNDO_WINDOW: Duration = Duration::from_secs(30);
+/// Beyond this many, the oldest tombstones are dropped early
+const UNDO_CAPACITY: usize = 10;
+
+/// What a destructive command removed, kept so it can be put back
+pub(crate) enum Tombstone {
+    /// With the positions they had in the list
+    Favorites(Vec<(usize, FavoriteItem)>),
+    History(Vec<HistoryItem>),
+    Download(DownloadItem),
+}
+
+struct UndoAction {
+    id: u64,
+    message: String,
+    deleted_at: Instant,
+    tombstone: Tombstone,
+}
+
+static UNDO_STACK: Mutex<Vec<UndoAction>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UndoableActionPayload {
+    pub id: u64,
+    pub message: String,
+    /// How long `undo_action` can still bring it back
+    pub expires_in_ms: u64,
+}
+
+fn take(id: u64) -> Option<UndoAction> {
+    let mut stack = UNDO_STACK.lock().unwrap();
+    stack.retain(|action| action.deleted_at.elapsed() < UNDO_WINDOW);
+    let index = stack.iter().position(|action| action.id == id)?;
+    Some(stack.remove(index))
+}
+
+/// Keeps what a destructive command removed for UNDO_WINDOW and tells the UI it can be undone.
+pub(crate) fn record(app: &AppHandle, message: String, tombstone: Tombstone) {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    {
+        let mut stack = UNDO_STACK.lock().unwrap();
+        stack.retain(|action| action.deleted_at.elapsed() < UNDO_WINDOW);
+        if stack.len() >= UNDO_CAPACITY {
+            stack.remove(0);
+        }
+        stack.push(UndoAction { id, message: message.clone(), deleted_at: Instant::now(), tombstone });
+    }
+    events::emit(app, AppEvent::UndoableAction(UndoableActionPayload {
+        id,
+        message,
+        expires_in_ms: UNDO_WINDOW.as_millis() as u64,
+    }));
+}
+
+fn restore(app: &AppHandle, tombstone: Tombstone) -> Result<(), LuminaError> {
+    match tombstone {
+        Tombstone::Favorites(items) => {
+            let store = app.state::<AppDataStore>();
+            store.restore_favorites(items);
+            store.save();
+        }
+        Tombstone::History(items) => app.state::<HistoryManager>().restore_entries(&items)?,
+        Tombstone::Download(item) => {
+            let manager = app.state::<DownloadManager>();
+            manager.downloads.lock().unwrap().entry(item.url.clone()).or_insert(item);
+            manager.save();
+            crate::refresh_tray_menu(app);
+        }
+    }
+    Ok(())
+}
+
+/// Puts back what the action announced with `id` removed and returns its message. Other actions
+/// recorded since stay undoable.
+#[tauri::command]
+pub(crate) fn undo_action(app: AppHandle, id: u64) -> Result<String, LuminaError> {
+    let action = take(id).ok_or_else(|| LuminaError::NotFound("This can no longer be undone".to_string()))?;
+    restore(&app, action.tombstone)?;
+    Ok(action.message)
+}
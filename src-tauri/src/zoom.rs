@@ -0,0 +1,70 @@
+use tauri::{AppHandle, Manager, Url, Webview};
+
+use crate::data::AppDataStore;
+use crate::error::LuminaError;
+use crate::history_manager::HistoryManager;
+use crate::layout::is_browser_window;
+
+/// Same range as the zoom buttons in the toolbar
+pub(crate) const MIN_ZOOM: i32 = 50;
+pub(crate) const MAX_ZOOM: i32 = 250;
+
+fn validated_zoom(percent: i32) -> Result<i32, LuminaError> {
+    if !(MIN_ZOOM..=MAX_ZOOM).contains(&percent) {
+        return Err(LuminaError::InvalidInput(format!("Zoom must be between {}% and {}%", MIN_ZOOM, MAX_ZOOM)));
+    }
+    Ok(percent)
+}
+
+/// The zoom saved for `host`, or the default zoom setting. Internal pages have no host and always
+/// use the default.
+pub(crate) fn zoom_for_host(app: &AppHandle, host: Option<&str>) -> i32 {
+    let saved = host
+        .filter(|host| !host.is_empty() && *host != "lumina-app.localhost")
+        .and_then(|host| app.state::<HistoryManager>().get_zoom_override(&host.to_lowercase()).ok().flatten());
+    saved.unwrap_or_else(|| app.state::<AppDataStore>().data.lock().unwrap().settings.default_zoom)
+}
+
+/// Sets the zoom `url` should be shown at on a tab's webview.
+pub(crate) fn apply(app: &AppHandle, webview: &Webview, url: &Url) {
+    let host = if url.scheme() == "lumina-app" { None } else { url.host_str() };
+    let _ = webview.set_zoom(zoom_for_host(app, host) as f64 / 100.0);
+}
+
+/// Brings every open tab in line after the default or a site's zoom changed.
+pub(crate) fn reapply_all(app: &AppHandle) {
+    for (label, webview) in app.webviews() {
+        if is_browser_window(&label) {
+            continue;
+        }
+        if let Ok(url) = webview.url() {
+            apply(app, &webview, &url);
+        }
+    }
+}
+
+/// Zoom for pages without a zoom of their own, Lumina's internal pages included. Open tabs follow
+/// right away.
+#[tauri::command]
+pub(crate) fn set_default_zoom(app: AppHandle, state: tauri::State<'_, AppDataStore>, percent: i32) -> Result<(), LuminaError> {
+    let percent = validated_zoom(percent)?;
+    state.data.lock().unwrap().settings.default_zoom = percent;
+    state.save();
+    reapply_all(&app);
+    Ok(())
+}
+
+/// Saves a zoom for one site and applies it to its open tabs.
+#[tauri::command]
+pub(crate) fn set_zoom_level(app: AppHandle, history_manager: tauri::State<'_, HistoryManager>, domain: String, zoom: i32) -> Result<(), LuminaError> {
+    let zoom = validated_zoom(zoom)?;
+    history_manager.set_zoom_level(&domain.trim().to_lowercase(), zoom)?;
+    reapply_all(&app);
+    Ok(())
+}
+
+/// The zoom a site opens at: its own, or the default.
+#[tauri::command]
+pub(crate) fn get_zoom_level(app: AppHandle, domain: String) -> i32 {
+    zoom_for_host(&app, Some(&domain))
+}
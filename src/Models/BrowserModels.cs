@@ -12,8 +12,16 @@ namespace tauri_browser.Models
         public bool CanGoBack { get; set; }
         public bool CanGoForward { get; set; }
         public bool IsPwaAvailable { get; set; } = false;
+        public bool HasSiteNote { get; set; } = false;
+        public string? SiteNoteDomain { get; set; }
         public uint BlockedAdsCount { get; set; } = 0;
         public bool IsPinned { get; set; } = false;
+        public bool CapturingCamera { get; set; }
+        public bool CapturingMicrophone { get; set; }
+        public bool CapturingScreen { get; set; }
+        public bool IsCapturing => CapturingCamera || CapturingMicrophone || CapturingScreen;
+        public int BlockedPopups { get; set; } = 0;
+        public string? ProtectionLevel { get; set; }
         [JsonPropertyName("is_incognito")]
         public bool IsIncognito { get; set; } = false;
         [JsonPropertyName("zoom_level")]
@@ -28,6 +36,8 @@ namespace tauri_browser.Models
 
     public class HistoryItem 
     { 
+        [JsonPropertyName("id")]
+        public string Id { get; set; } = string.Empty;
         [JsonPropertyName("url")]
         public string Url { get; set; } = string.Empty; 
         [JsonPropertyName("title")]
@@ -42,10 +52,14 @@ namespace tauri_browser.Models
 
     public class FavoriteItem 
     { 
+        [JsonPropertyName("id")]
+        public string Id { get; set; } = string.Empty;
         [JsonPropertyName("url")]
         public string Url { get; set; } = string.Empty; 
         [JsonPropertyName("title")]
         public string Title { get; set; } = string.Empty; 
+        [JsonPropertyName("keyword")]
+        public string? Keyword { get; set; }
     }
 
     public class DownloadItem
@@ -97,6 +111,88 @@ namespace tauri_browser.Models
         public string? Path { get; set; } = string.Empty;
     }
 
+    public class PopupBlockedPayload
+    {
+        [JsonPropertyName("label")]
+        public string Label { get; set; } = string.Empty;
+        [JsonPropertyName("url")]
+        public string Url { get; set; } = string.Empty;
+        [JsonPropertyName("origin")]
+        public string Origin { get; set; } = string.Empty;
+        [JsonPropertyName("count")]
+        public int Count { get; set; }
+    }
+
+    public class SiteNoteStatusPayload
+    {
+        [JsonPropertyName("label")]
+        public string Label { get; set; } = string.Empty;
+        [JsonPropertyName("domain")]
+        public string Domain { get; set; } = string.Empty;
+        [JsonPropertyName("hasNote")]
+        public bool HasNote { get; set; }
+    }
+
+    public class MediaCapturePayload
+    {
+        [JsonPropertyName("label")]
+        public string Label { get; set; } = string.Empty;
+        [JsonPropertyName("camera")]
+        public bool Camera { get; set; }
+        [JsonPropertyName("microphone")]
+        public bool Microphone { get; set; }
+        [JsonPropertyName("screen")]
+        public bool Screen { get; set; }
+    }
+
+    public class SitePrompt
+    {
+        [JsonPropertyName("id")]
+        public string Id { get; set; } = string.Empty;
+        [JsonPropertyName("label")]
+        public string Label { get; set; } = string.Empty;
+        [JsonPropertyName("window")]
+        public string Window { get; set; } = string.Empty;
+        [JsonPropertyName("kind")]
+        public string Kind { get; set; } = string.Empty;
+        [JsonPropertyName("origin")]
+        public string Origin { get; set; } = string.Empty;
+        [JsonPropertyName("message")]
+        public string Message { get; set; } = string.Empty;
+        [JsonPropertyName("canRemember")]
+        public bool CanRemember { get; set; }
+    }
+
+    public class SitePromptClosedPayload
+    {
+        [JsonPropertyName("id")]
+        public string Id { get; set; } = string.Empty;
+        [JsonPropertyName("label")]
+        public string Label { get; set; } = string.Empty;
+    }
+
+    public class ReadAloudStatePayload
+    {
+        [JsonPropertyName("label")]
+        public string? Label { get; set; }
+        [JsonPropertyName("status")]
+        public string Status { get; set; } = "stopped";
+        [JsonPropertyName("position")]
+        public int Position { get; set; }
+        [JsonPropertyName("total")]
+        public int Total { get; set; }
+    }
+
+    public class NavigationStatePayload
+    {
+        [JsonPropertyName("label")]
+        public string Label { get; set; } = string.Empty;
+        [JsonPropertyName("canGoBack")]
+        public bool CanGoBack { get; set; }
+        [JsonPropertyName("canGoForward")]
+        public bool CanGoForward { get; set; }
+    }
+
     public class TabCreatedPayload
     {
         [JsonPropertyName("label")]
@@ -115,6 +211,9 @@ namespace tauri_browser.Models
         
         [JsonPropertyName("favicon")]
         public string? Favicon { get; set; }
+
+        [JsonPropertyName("pinned")]
+        public bool? Pinned { get; set; }
     }
 
     public class AdblockStatsPayload
@@ -130,6 +229,10 @@ namespace tauri_browser.Models
     {
         [JsonPropertyName("homepage")]
         public string Homepage { get; set; } = "https://www.google.com";
+        [JsonPropertyName("show_home_button")]
+        public bool ShowHomeButton { get; set; } = true;
+        [JsonPropertyName("new_tab_page")]
+        public string NewTabPage { get; set; } = "lumina-app://newtab";
         [JsonPropertyName("search_engine")]
         public string SearchEngine { get; set; } = "google";
         [JsonPropertyName("theme")]
@@ -147,5 +250,10 @@ namespace tauri_browser.Models
         public bool EnableFormData { get; set; } = true;
         [JsonPropertyName("cookie_expires_days")]
         public long CookieExpiresDays { get; set; } = 365;
+
+        [JsonPropertyName("startup_behavior")]
+        public string StartupBehavior { get; set; } = "homepage"; // "homepage", "new_tab", "restore_session", "urls"
+        [JsonPropertyName("startup_urls")]
+        public List<string> StartupUrls { get; set; } = new();
     }
 }
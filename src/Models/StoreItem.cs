@@ -43,4 +43,16 @@ namespace tauri_browser.Models
         [JsonPropertyName("level")]
         public string Level { get; set; } = "info";
     }
+
+    public class UndoableActionPayload
+    {
+        [JsonPropertyName("id")]
+        public long Id { get; set; }
+
+        [JsonPropertyName("message")]
+        public string Message { get; set; } = "";
+
+        [JsonPropertyName("expiresInMs")]
+        public long ExpiresInMs { get; set; }
+    }
 }
@@ -12,6 +12,13 @@ namespace tauri_browser.Models
 
         [JsonPropertyName("url")]
         public string Url { get; set; } = "";
+
+        // "switch-tab" suggestions point at an open tab instead of a URL to load
+        [JsonPropertyName("type")]
+        public string Type { get; set; } = "";
+
+        [JsonPropertyName("label")]
+        public string Label { get; set; } = "";
     }
 
     public class OmniboxResponse